@@ -14,10 +14,172 @@ Features:
     - Always fast (no C extension required)
     - 100% safe by default (no code execution)
     - Drop-in PyYAML replacement
+
+Load options (accepted as keyword arguments by every load function):
+    pyyaml_compat: Produce exactly the types PyYAML's SafeLoader would
+        (YAML 1.1 booleans like yes/on, dates and datetimes, bytes for
+        !!binary, merge keys, shared objects for aliases)
+    timestamps: Resolve plain timestamps: 2024-01-01 becomes datetime.date,
+        2024-01-01T10:00:00Z becomes datetime.datetime
+    dates_as_datetime: Like timestamps, but bare dates also become
+        datetime.datetime (at midnight) so every timestamp has one type
+    durations: Resolve ISO-8601 (PT30S, P1DT2H) and Go-style (5m30s, 1.5h,
+        300ms) durations to datetime.timedelta
+    uuids: Resolve UUID-shaped scalars to uuid.UUID
+    ip_addresses: Resolve IP addresses (10.0.0.1, ::1) and CIDR networks
+        (10.0.0.0/8) to ipaddress objects; an address with a prefix and host
+        bits set (10.0.0.1/8) becomes an interface
+    kubernetes_quantities: Convert resource quantities under resources.*
+        (128Mi, 500m) to integers: millicores for cpu, bytes/units otherwise
+    merge_keys: Merge the mapping (or list of mappings) under a plain <<
+        key into its parent, as docker-compose and GitLab CI files expect
+        (on by default); the parent's own keys win, then earlier mappings
+        in a list. False keeps << as an ordinary key, as strict YAML 1.2
+        does; a quoted '<<' never merges
+    preserve_aliases: Construct every *alias as the same object as its
+        &anchor, as PyYAML does, instead of an independent copy; documents
+        that reuse large anchored blocks take far less memory, but
+        mutating the data through one reference shows through all of them
+        (implied by pyyaml_compat)
+    allowed_tags: Custom tags to construct instead of rejecting, as
+        written ("!Ref", "!Sub" for CloudFormation templates); every other
+        tag is still rejected, and unsafe ones (!!python/...) can't be
+        listed
+    tag_strategy: What those tags construct to: "value" (the default)
+        keeps the value as if it weren't tagged, "mapping" wraps it as
+        {"Ref": value}, and a callable is called as f(tag, value) and
+        returns the object to use
+    document_markers: "lenient" ignores stray --- / ... markers (empty
+        documents) and tab/NUL junk between or after documents; "strict"
+        raises a YAMLError pointing at the first one
+    marked: Return rustyyaml.marked containers (dict/list/str subclasses)
+        with ruamel.yaml-style .lc.line/.lc.col positions (0-based), plus
+        .lc.key(k), .lc.value(k) and .lc.item(i) on mappings and sequences.
+        Scalar styles ("plain", "single-quoted", "double-quoted", "literal",
+        "folded") are in .lc.style of strings and .lc.key_style(k),
+        .lc.value_style(k) and .lc.item_style(i)
+    dict_class: Called with no arguments to create every mapping instead of
+        dict (collections.OrderedDict, a case-insensitive dict, ...); items
+        are added with __setitem__ in document order (or sorted, with
+        sort_keys). Can't be combined with marked
+    as_namespace: Return every mapping as a rustyyaml.Namespace, which
+        allows both cfg.database.port and cfg["database"]["port"]; keys
+        that aren't identifiers or clash with a dict method (items, keys,
+        get, ...) need the subscript. cfg.to_dict() converts back to
+        plain dicts
+    sort_keys: Insert the keys of every mapping in sorted order, for
+        canonical structures to hash or compare: None, booleans, numbers,
+        strings, then other keys (dates, ...) grouped by type. Sorting
+        happens while the dicts are built, so it costs no extra pass
+    profile: Select a Spring-style profile. Its section under the base
+        document's top-level profiles: key, and the later ---separated
+        documents whose profile: key names it (or lists it), are
+        deep-merged over the base document in order: mappings key by key,
+        anything else (lists included) replaced. Later documents without
+        a profile: key apply to every profile. The stream loads as that
+        one merged document (load_all returns a list of one), and schema=
+        and redact= apply to it
+    share_subtrees: The batch loaders (*_many, load_directory, load_git,
+        load_object_store, load_files_verified) return the same object for
+        every structurally identical subtree in the batch, so repeated
+        blocks (labels, resource limits) are in memory once. Shared data
+        is frozen: mappings are read-only types.MappingProxyType views and
+        lists are tuples. dict_class and as_namespace mappings are left
+        as they are
+    document_spans: load_all, load_all_unsafe, load_all_iter and
+        Loader.load_all return (document, (start, end, line)) pairs: the
+        byte offsets of each document in the UTF-8 stream (from its --- if
+        it has one, up to its ... or the next document) and the 1-based
+        line it starts on, to map documents back to the source for editing
+        or reporting.
+        Can't be combined with collect_errors or profile
+    collect_errors: load_all, the *_many loaders and load_directory keep
+        going after failures and return (results, errors); each error is a
+        YAMLError with line/column and the failing document index or path
+    schema: A JSON Schema subset (type, properties, additionalProperties,
+        items) as a dict, or the name it was registered under with
+        register_schema; string scalars are coerced to the declared type
+        ("8080" to 8080 for {"type": "integer"}); if any cannot be, a
+        YAMLError lists each failure by JSON pointer (/spec/port) and
+        line/column, and its .line/.column point at the first one
+    redact: A key path or list of key paths (grep syntax, "data.*") whose
+        values are replaced by "***" before they reach Python, for loading
+        configs that will be logged or shared (see also redact())
+    max_size_bytes: Raise a ResourceLimitError (a YAMLError) if the YAML
+        text is longer than this many bytes of UTF-8, before parsing it.
+        Files and file objects are read no further than the limit, and
+        compressed files are decompressed no further
+    max_nodes: Raise a ResourceLimitError (with .line/.column) if any
+        document has more than this many nodes (scalars, keys, sequences
+        and mappings), an alias counting as the nodes it stands for
+    max_scalar_bytes: Raise a ResourceLimitError (with .line/.column) if
+        any scalar, key or value, is longer than this many bytes of UTF-8
+    max_mapping_entries: Raise a ResourceLimitError if any mapping has more
+        than this many keys; these three are checked before any Python
+        objects are built, to reject uploads that put their size into a
+        single node or expand through aliases
+    sops_key: Decrypt SOPS-encrypted documents. A callable invoked as
+        sops_key(kind, entry) for each recipient in the sops section
+        (kind is "age", "kms", "gcp_kms", "azure_kv", "hc_vault" or "pgp";
+        entry is its dict, with the wrapped data key in entry["enc"]) that
+        returns the 32-byte data key as bytes, or None to try the next one.
+        Values are decrypted and checked against their key path in Rust,
+        typed as SOPS recorded them, and the sops section is removed. The
+        document is then checked against the MAC in the sops section, as
+        sops decrypt does, and a YAMLError raised if a value was edited,
+        added, removed or reordered since it was encrypted. Documents
+        without a sops section load as usual
+    sops_verify_mac: Pass False to skip that MAC check (like sops
+        --ignore-mac), for example for multi-document SOPS files, whose MAC
+        covers every document
+    file_info: load_directory, load_object_store and load_git return
+        (path, data, info) tuples, where info is a dict with the file's
+        "encoding" ("utf-8", "utf-16-le", ...), "bom" (whether it had a
+        byte order mark), "size" in bytes and "parse_time" in seconds
+        (shared by byte-identical files, which are parsed once)
+    on_error: load_directory, load_git and load_object_store call
+        on_error(path, error) for each file that fails, as soon as it
+        does (from a worker thread, so possibly in any order), and leave
+        the file out of the results instead of raising. error is the
+        exception with its path attribute set: a YAMLError with
+        line/column, or whatever transform raised. If on_error raises, the
+        load stops and raises that. Combine with collect_errors to also
+        get the errors at the end
+    encoding: How load_directory, load_git, load_object_store and
+        Loader.load_file decode files. By default UTF-8, UTF-16 or UTF-32
+        is detected (BOM or null bytes). A codec name ("latin-1",
+        "cp1252", "utf-16-le", ...) forces one; "auto" detects as usual
+        but reads files that aren't valid UTF-8 as cp1252, for legacy
+        Windows/Latin-1 files. file_info reports the encoding used
+    use_mmap: The file loaders (safe_load_file, load_all_file,
+        Loader.load_file, load_directory, load_files_verified) map files
+        instead of reading them, and parse UTF-8 in place without copying
+        it. This lowers peak memory for large files and batches. Files
+        must not be modified while they are loaded
+    cancel: A CancellationToken; the *_many loaders and load_directory
+        stop soon after token.cancel() is called from another thread and
+        raise concurrent.futures.CancelledError (Ctrl-C stops them the same
+        way, with KeyboardInterrupt)
 """
 
+import os
+from collections.abc import Mapping as MappingABC
+from collections.abc import MutableMapping, Sequence
+from concurrent.futures import CancelledError
 from pathlib import Path
-from typing import IO, Any, List, Optional, Tuple, Union
+from typing import (
+    IO,
+    Any,
+    Callable,
+    Dict,
+    Iterable,
+    Iterator,
+    List,
+    Mapping,
+    Optional,
+    Tuple,
+    Union,
+)
 
 # Import the Rust extension module
 try:
@@ -33,64 +195,193 @@ __version__ = _rustyyaml.__version__
 __all__ = [
     "safe_load",
     "unsafe_load",
+    "safe_load_with_sourcemap",
+    "safe_load_marked",
     "load",
     "load_all",
+    "load_all_iter",
     "load_all_unsafe",
+    "parse_lenient",
+    "parse",
+    "compose",
+    "compose_all",
+    "safe_dump",
+    "dump",
+    "safe_dump_all",
+    "load_rt",
+    "dump_rt",
+    "update_file",
+    "dump_all",
     "safe_load_file",
+    "unsafe_load_file",
     "load_all_file",
+    "safe_load_stdin",
+    "load_all_stdin",
+    "dump_file",
+    "dump_all_file",
+    "add_representer",
+    "Loader",
+    "Dumper",
     "safe_load_many",
     "unsafe_load_many",
     "load_directory",
+    "load_object_store",
+    "load_git",
+    "load_files_verified",
+    "load_archive",
     "load_directory_unsafe",
+    "dump_shm",
+    "load_shm",
+    "SharedMapping",
+    "SharedSequence",
+    "grep",
+    "index_keys",
+    "walk",
+    "map_scalars",
+    "explode",
+    "bundle",
+    "drift",
+    "to_csv",
+    "to_dotenv",
+    "yaml_to_ini",
+    "ini_to_yaml",
+    "yaml_to_xml",
+    "xml_to_yaml",
+    "list_variables",
+    "validate",
+    "register_schema",
+    "Report",
+    "Finding",
+    "parse_quantity",
+    "redact",
+    "load_logging_config",
+    "generate_dataclasses",
+    "extract_comments",
+    "detect_type",
+    "inspect_anchors",
+    "audit_tags",
+    "metrics",
+    "CancellationToken",
+    "Namespace",
     "YAMLError",
+    "TabIndentationError",
+    "IntegrityError",
+    "ResourceLimitError",
     "__version__",
 ]
 
 
-class YAMLError(ValueError):
-    """Base exception for YAML errors"""
+# filter= argument of the batch loaders: a dict matcher or a predicate
+DocumentFilter = Union[Dict[str, Any], Callable[[Any], Any]]
+
+
+# Exceptions are defined in Rust so errors raised there keep their class
+# (YAMLError subclasses ValueError; TabIndentationError adds .line/.column;
+# IntegrityError adds .expected/.actual digests; ResourceLimitError is
+# raised for the max_* options)
+YAMLError = _rustyyaml.YAMLError
+TabIndentationError = _rustyyaml.TabIndentationError
+IntegrityError = _rustyyaml.IntegrityError
+ResourceLimitError = _rustyyaml.ResourceLimitError
+
+# Results of validate()
+Report = _rustyyaml.Report
+Finding = _rustyyaml.Finding
+
+# cancel= option of the batch loaders
+CancellationToken = _rustyyaml.CancellationToken
+
+# Mappings returned with as_namespace=True
+Namespace = _rustyyaml.Namespace
+MutableMapping.register(Namespace)
+
+# Read-only views returned by load_shm()
+SharedMapping = _rustyyaml.SharedMapping
+SharedSequence = _rustyyaml.SharedSequence
+MappingABC.register(SharedMapping)
+Sequence.register(SharedSequence)
+
+# How much _read_stream asks a file-like object for at a time
+_READ_CHUNK_SIZE = 64 * 1024
 
-    pass
 
+def _check_size(size: int, max_size_bytes: int) -> None:
+    """Raise ResourceLimitError if size is over max_size_bytes"""
+    if size > max_size_bytes:
+        raise ResourceLimitError(
+            f"Limit exceeded: input of more than {max_size_bytes} bytes "
+            f"(max_size_bytes={max_size_bytes})"
+        )
 
-def _read_stream(stream: Union[str, bytes, IO, Path]) -> str:
+
+def _fspath(path: Union[str, bytes, os.PathLike]) -> str:
+    """Return a path argument as str, decoding a bytes path as os.fsdecode does"""
+    return os.fsdecode(path)
+
+
+def _read_stream(
+    stream: Union[str, bytes, IO, os.PathLike], max_size_bytes: Optional[int] = None
+) -> str:
     """
     Read YAML content from various input types
 
+    Bytes (from bytes, bytearray or memoryview objects, binary files and
+    paths) are decoded in Rust as the YAML spec says: UTF-8, UTF-16 or
+    UTF-32, chosen by the byte order mark or the null bytes around the
+    first character.
+
     Args:
         stream: YAML content as string, bytes, file object, or Path
+        max_size_bytes: The max_size_bytes option, if set: files and file
+            objects are read no further than one chunk past it
 
     Returns:
-        YAML content as UTF-8 string
+        YAML content as a string
 
     Raises:
         YAMLError: If content cannot be read or decoded
+        ResourceLimitError: If a file or file object has more than
+            max_size_bytes bytes (or characters, for a text file)
     """
-    # Handle Path objects
-    if isinstance(stream, Path):
+    # Handle Path and other os.PathLike objects
+    if isinstance(stream, os.PathLike):
         try:
-            return stream.read_text(encoding="utf-8")
+            path = _fspath(stream)
+            if max_size_bytes is not None:
+                _check_size(os.stat(path).st_size, max_size_bytes)
+            return _rustyyaml.decode(Path(path).read_bytes())
+        except YAMLError:
+            raise
         except FileNotFoundError:
             raise YAMLError(f"File not found: {stream}")
         except Exception as e:
             raise YAMLError(f"Failed to read file {stream}: {e}")
 
-    # Handle file-like objects
+    # Handle file-like objects, read in chunks until one comes back empty
+    # so sockets, pipes and objects whose read() needs a size work too
     if hasattr(stream, "read"):
-        content = stream.read()
-        if isinstance(content, bytes):
-            try:
-                return content.decode("utf-8")
-            except UnicodeDecodeError as e:
-                raise YAMLError(f"Failed to decode YAML as UTF-8: {e}")
-        return content
+        chunks = []
+        size = 0
+        try:
+            while True:
+                chunk = stream.read(_READ_CHUNK_SIZE)
+                if not chunk:
+                    break
+                chunks.append(chunk)
+                size += len(chunk)
+                if max_size_bytes is not None and size > max_size_bytes:
+                    break
+        except Exception as e:
+            raise YAMLError(f"Failed to read stream: {e}")
+        if max_size_bytes is not None:
+            _check_size(size, max_size_bytes)
+        if chunks and isinstance(chunks[0], str):
+            return "".join(chunks)
+        return _rustyyaml.decode(b"".join(bytes(chunk) for chunk in chunks))
 
     # Handle bytes
-    if isinstance(stream, bytes):
-        try:
-            return stream.decode("utf-8")
-        except UnicodeDecodeError as e:
-            raise YAMLError(f"Failed to decode YAML as UTF-8: {e}")
+    if isinstance(stream, (bytes, bytearray, memoryview)):
+        return _rustyyaml.decode(bytes(stream))
 
     # Handle strings
     if isinstance(stream, str):
@@ -98,11 +389,11 @@ def _read_stream(stream: Union[str, bytes, IO, Path]) -> str:
 
     raise YAMLError(
         f"Unsupported input type: {type(stream)}. "
-        "Expected str, bytes, file object, or Path"
+        "Expected str, bytes, file object, or os.PathLike"
     )
 
 
-def safe_load(stream: Union[str, bytes, IO, Path]) -> Any:
+def safe_load(stream: Union[str, bytes, IO, os.PathLike], **options: Any) -> Any:
     """
     Parse YAML safely (no code execution)
 
@@ -111,6 +402,7 @@ def safe_load(stream: Union[str, bytes, IO, Path]) -> Any:
 
     Args:
         stream: YAML content as string, bytes, file object, or Path
+        **options: Load options (see module docstring)
 
     Returns:
         Python object (dict, list, str, int, float, bool, or None)
@@ -125,17 +417,96 @@ def safe_load(stream: Union[str, bytes, IO, Path]) -> Any:
 
         >>> with open('config.yaml') as f:
         ...     config = safe_load(f)
+
+        >>> safe_load("enabled: yes", pyyaml_compat=True)
+        {'enabled': True}
     """
     try:
-        content = _read_stream(stream)
-        return _rustyyaml.safe_load(content)
+        content = _read_stream(stream, options.get("max_size_bytes"))
+        return _rustyyaml.safe_load(content, **options)
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def safe_load_with_sourcemap(
+    stream: Union[str, bytes, IO, os.PathLike], **options: Any
+) -> Tuple[Any, Dict[str, Tuple[int, int, Tuple[int, int]]]]:
+    """
+    Parse YAML safely and report where each value came from
+
+    The data is plain Python objects, exactly as safe_load returns them.
+    The source map is keyed by JSON pointer ("" for the root, "/a/0" for
+    the first item under "a"); each entry is (line, column, (start, end))
+    with a 1-based line/column and a byte span into the UTF-8 input.
+    Aliases map to the alias itself; values under non-scalar keys are
+    not mapped.
+
+    Args:
+        stream: YAML content as string, bytes, file object, or Path
+        **options: Load options (see module docstring)
+
+    Returns:
+        (data, sourcemap) tuple
+
+    Raises:
+        YAMLError: If YAML is malformed or contains unsafe tags
+
+    Example:
+        >>> data, sourcemap = safe_load_with_sourcemap("a:\n  - x")
+        >>> sourcemap["/a/0"]
+        (2, 5, (7, 8))
+    """
+    try:
+        content = _read_stream(stream, options.get("max_size_bytes"))
+        return _rustyyaml.safe_load_with_sourcemap(content, **options)
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def safe_load_marked(stream: Union[str, bytes, IO, os.PathLike], **options: Any) -> Any:
+    """
+    Parse YAML safely, attaching the source position of every value
+
+    Shorthand for safe_load(stream, marked=True). Mappings, sequences and
+    strings are rustyyaml.marked subclasses of dict, list and str whose
+    .lc.line/.lc.col (0-based, like ruamel.yaml) give where they start.
+    Numbers, booleans and None can't carry attributes; their positions are
+    on the containing collection: .lc.key(k) and .lc.value(k) for mapping
+    entries, .lc.item(i) for sequence items. How each scalar was written
+    is in .lc.style, .lc.key_style(k), .lc.value_style(k) and
+    .lc.item_style(i).
+
+    Args:
+        stream: YAML content as string, bytes, file object, or Path
+        **options: Load options (see module docstring); dict_class and
+            as_namespace can't be combined with it
+
+    Returns:
+        Parsed Python object with positions
+
+    Raises:
+        YAMLError: If YAML is malformed or contains unsafe tags
+
+    Example:
+        >>> config = safe_load_marked(open("config.yaml"))
+        >>> line, col = config["server"].lc.value("port")
+        >>> print(f"config.yaml:{line + 1}: port must be below 65536")
+    """
+    options["marked"] = True
+    try:
+        content = _read_stream(stream, options.get("max_size_bytes"))
+        return _rustyyaml.safe_load(content, **options)
     except YAMLError:
         raise
     except Exception as e:
         raise YAMLError(str(e))
 
 
-def unsafe_load(stream: Union[str, bytes, IO, Path]) -> Any:
+def unsafe_load(stream: Union[str, bytes, IO, os.PathLike], **options: Any) -> Any:
     """
     Parse YAML without safety checks (DANGEROUS!)
 
@@ -144,6 +515,7 @@ def unsafe_load(stream: Union[str, bytes, IO, Path]) -> Any:
 
     Args:
         stream: YAML content as string, bytes, file object, or Path
+        **options: Load options (see module docstring)
 
     Returns:
         Python object
@@ -156,15 +528,15 @@ def unsafe_load(stream: Union[str, bytes, IO, Path]) -> Any:
         >>> data = unsafe_load(trusted_yaml)
     """
     try:
-        content = _read_stream(stream)
-        return _rustyyaml.unsafe_load(content)
+        content = _read_stream(stream, options.get("max_size_bytes"))
+        return _rustyyaml.unsafe_load(content, **options)
     except YAMLError:
         raise
     except Exception as e:
         raise YAMLError(str(e))
 
 
-def load(stream: Union[str, bytes, IO, Path]) -> Any:
+def load(stream: Union[str, bytes, IO, os.PathLike], **options: Any) -> Any:
     """
     Parse YAML (defaults to safe mode)
 
@@ -173,6 +545,7 @@ def load(stream: Union[str, bytes, IO, Path]) -> Any:
 
     Args:
         stream: YAML content
+        **options: Load options (see module docstring)
 
     Returns:
         Python object
@@ -180,10 +553,10 @@ def load(stream: Union[str, bytes, IO, Path]) -> Any:
     Example:
         >>> data = load("key: value")
     """
-    return safe_load(stream)
+    return safe_load(stream, **options)
 
 
-def load_all(stream: Union[str, bytes, IO, Path]) -> List[Any]:
+def load_all(stream: Union[str, bytes, IO, os.PathLike], **options: Any) -> List[Any]:
     """
     Parse multiple YAML documents from a single stream
 
@@ -191,12 +564,15 @@ def load_all(stream: Union[str, bytes, IO, Path]) -> List[Any]:
 
     Args:
         stream: YAML content with multiple documents
+        **options: Load options (see module docstring)
 
     Returns:
-        List of Python objects (one per document)
+        List of Python objects (one per document), (documents, errors)
+        with collect_errors=True, or (document, (start, end, line)) pairs
+        with document_spans=True
 
     Raises:
-        YAMLError: If any document is malformed
+        YAMLError: If any document is malformed (unless collect_errors=True)
 
     Example:
         >>> yaml_str = '''
@@ -210,140 +586,2241 @@ def load_all(stream: Union[str, bytes, IO, Path]) -> List[Any]:
         >>> print(len(docs))  # 3
     """
     try:
-        content = _read_stream(stream)
-        return _rustyyaml.load_all(content)
+        content = _read_stream(stream, options.get("max_size_bytes"))
+        return _rustyyaml.load_all(content, **options)
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def load_all_iter(
+    stream: Union[str, bytes, IO, os.PathLike], **options: Any
+) -> Iterator[Any]:
+    """
+    Parse multiple YAML documents lazily, one per iteration
+
+    Each document is parsed and converted only when the iterator reaches
+    it, so a loop can stop early, and a stream of millions of small
+    documents is processed in constant memory (beyond the text itself).
+
+    Args:
+        stream: YAML content with multiple documents
+        **options: Load options (see module docstring); collect_errors and
+            profile need every document at once and are rejected
+
+    Returns:
+        Iterator of Python objects, one per document, or of (document,
+        (start, end, line)) pairs with document_spans=True
+
+    Raises:
+        YAMLError: If the stream has an unsafe tag (before iterating), or
+            when a malformed document is reached (with its index in
+            the document attribute)
+
+    Example:
+        >>> for event in load_all_iter(open("events.yaml")):
+        ...     if event["type"] == "done":
+        ...         break
+    """
+    try:
+        content = _read_stream(stream, options.get("max_size_bytes"))
+        return _rustyyaml.load_all_iter(content, True, **options)
     except YAMLError:
         raise
     except Exception as e:
         raise YAMLError(str(e))
 
 
-def load_all_unsafe(stream: Union[str, bytes, IO, Path]) -> List[Any]:
+def load_all_unsafe(
+    stream: Union[str, bytes, IO, os.PathLike], **options: Any
+) -> List[Any]:
     """
     Parse multiple YAML documents without safety checks
 
     Args:
         stream: YAML content with multiple documents
+        **options: Load options (see module docstring)
 
     Returns:
         List of Python objects
     """
     try:
-        content = _read_stream(stream)
-        return _rustyyaml.load_all_unsafe(content)
+        content = _read_stream(stream, options.get("max_size_bytes"))
+        return _rustyyaml.load_all_unsafe(content, **options)
     except YAMLError:
         raise
     except Exception as e:
         raise YAMLError(str(e))
 
 
-def safe_load_file(path: Union[str, Path]) -> Any:
+def parse_lenient(
+    stream: Union[str, bytes, IO, os.PathLike], **options: Any
+) -> Tuple[List[Any], List[YAMLError]]:
     """
-    Load YAML from a file safely
+    Parse YAML, recovering from localized syntax errors
+
+    Meant for editors and language servers: instead of stopping at the
+    first problem, each offending line is left out and parsing carries on,
+    so the structure around a typo is still available. A tag safe_load
+    would reject (unsafe, or not in allowed_tags) is reported the same way,
+    with the node it's on left out. Documents recover independently of
+    each other.
 
     Args:
-        path: Path to YAML file
+        stream: YAML content (any number of documents)
+        **options: Load options (see module docstring)
 
     Returns:
-        Python object
+        (documents, errors): every document, without the lines that broke
+        it (None for one that could not be recovered at all), and a
+        YAMLError per problem with line, column and document set
 
     Example:
-        >>> config = safe_load_file('config.yaml')
+        >>> docs, errors = parse_lenient("a: 1\nb: [1, 2\nc: 3\n")
+        >>> docs
+        [{'a': 1, 'c': 3}]
+        >>> errors[0].line
+        3
     """
-    return safe_load(Path(path))
+    try:
+        content = _read_stream(stream, options.get("max_size_bytes"))
+        return _rustyyaml.parse_lenient(content, **options)
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
 
 
-def load_all_file(path: Union[str, Path]) -> List[Any]:
+def parse(stream: Union[str, bytes, IO, os.PathLike]) -> Iterator[Any]:
     """
-    Load multiple YAML documents from a file
+    Parse YAML into a stream of events, like PyYAML's yaml.parse()
+
+    The events are produced by libyaml in Rust as the iterator advances,
+    as instances of the rustyyaml.events classes, which mirror PyYAML's
+    yaml.events (StreamStartEvent, DocumentStartEvent, MappingStartEvent,
+    ScalarEvent, AliasEvent, ...) with the same attributes and marks.
 
     Args:
-        path: Path to YAML file
+        stream: YAML content (any number of documents)
 
     Returns:
-        List of Python objects
+        Iterator of events
+
+    Raises:
+        YAMLError: When the iterator reaches a syntax error
+            (TabIndentationError for a tab used as indentation)
+
+    Example:
+        >>> for event in parse("name: web"):
+        ...     if isinstance(event, rustyyaml.events.ScalarEvent):
+        ...         print(event.value, event.start_mark.line)
+        name 0
+        web 0
     """
-    return load_all(Path(path))
+    try:
+        return _rustyyaml.parse(_read_stream(stream))
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
 
 
-def safe_load_many(yaml_strings: List[str]) -> List[Any]:
+def compose(stream: Union[str, bytes, IO, os.PathLike], **options: Any) -> Any:
     """
-    Parse multiple YAML strings in parallel
+    Compose a YAML document into a node graph, like PyYAML's yaml.compose()
 
-    This is 5-10x faster than a loop for large batches.
+    The nodes are instances of the rustyyaml.nodes classes, which mirror
+    PyYAML's yaml.nodes (ScalarNode, SequenceNode, MappingNode) with the
+    resolved tag, marks and style of each node, plus the anchor it was
+    defined with. An alias is the same node object as its anchor. Nothing
+    is constructed, so any tag is allowed.
 
     Args:
-        yaml_strings: List of YAML content strings
+        stream: YAML content (at most one document)
+        **options: pyyaml_compat resolves implicit tags with YAML 1.1 rules,
+            as PyYAML does; limits (max_depth, ...) apply
 
     Returns:
-        List of parsed Python objects (same order as input)
+        The root node, or None for an empty stream
+
+    Raises:
+        YAMLError: If the YAML is invalid, an alias is undefined, a limit
+            is exceeded or the stream has several documents
 
     Example:
-        >>> yamls = ["doc: 1", "doc: 2", "doc: 3"]
-        >>> results = safe_load_many(yamls)
-        >>> print(results)
-        [{'doc': 1}, {'doc': 2}, {'doc': 3}]
+        >>> node = compose("ports: &p [80, 443]")
+        >>> key, value = node.value[0]
+        >>> value.tag, value.anchor, value.flow_style
+        ('tag:yaml.org,2002:seq', 'p', True)
     """
     try:
-        return _rustyyaml.safe_load_many(yaml_strings)
+        content = _read_stream(stream, options.get("max_size_bytes"))
+        return _rustyyaml.compose(content, **options)
+    except YAMLError:
+        raise
     except Exception as e:
         raise YAMLError(str(e))
 
 
-def unsafe_load_many(yaml_strings: List[str]) -> List[Any]:
+def compose_all(
+    stream: Union[str, bytes, IO, os.PathLike], **options: Any
+) -> List[Any]:
     """
-    Parse multiple YAML strings in parallel without safety checks
+    Compose every YAML document into a node graph, like yaml.compose_all()
 
     Args:
-        yaml_strings: List of YAML content strings
+        stream: YAML content (any number of documents)
+        **options: As for compose()
 
     Returns:
-        List of parsed Python objects
+        List with the root node of each document
+
+    Raises:
+        YAMLError: If the YAML is invalid, an alias is undefined or a limit
+            is exceeded
+
+    Example:
+        >>> [node.tag for node in compose_all("a: 1\n---\n- b\n")]
+        ['tag:yaml.org,2002:map', 'tag:yaml.org,2002:seq']
     """
     try:
-        return _rustyyaml.unsafe_load_many(yaml_strings)
+        content = _read_stream(stream, options.get("max_size_bytes"))
+        return _rustyyaml.compose_all(content, **options)
+    except YAMLError:
+        raise
     except Exception as e:
         raise YAMLError(str(e))
 
 
-def load_directory(
-    directory: Union[str, Path], recursive: bool = False
-) -> List[Tuple[str, Any]]:
+def safe_dump(data: Any, stream: Optional[IO] = None, **options: Any) -> Optional[str]:
     """
-    Load all YAML files from a directory in parallel
+    Serialize Python data to YAML
+
+    The output is laid out the way PyYAML's safe_dump writes it (block
+    style, two-space indentation, long strings folded at 80 columns), and
+    safe_load reads it back as the same data: strings that would otherwise
+    load as another type ("yes", "0755", "2024-01-01") are quoted, and
+    multi-line strings are written as literal blocks. A dict or list that
+    appears more than once is written once with an anchor (&id001) and as
+    an alias (*id001) after that; data that contains itself can't be
+    loaded back, so it raises YAMLError.
 
     Args:
-        directory: Path to directory
-        recursive: If True, search subdirectories
+        data: dicts, lists, tuples, str, int, float, bool and None, plus
+            datetime and date (written as timestamps, which load back as
+            such with timestamps=True or pyyaml_compat=True), timedelta
+            (as a Go-style duration such as 1h30m0s, which loads back as
+            one with durations=True), Decimal (as a number), UUID, paths
+            and ipaddress addresses and networks (as strings; the latter
+            load back as such with ip_addresses=True), Enum members (by
+            value), dataclass instances (as mappings of their fields) and
+            types registered with add_representer
+        stream: File object to write to; if None, the YAML is returned
+        **options: Dump options, as PyYAML names them:
+
+            sort_keys: Write mapping keys in sorted order (default True);
+                False keeps insertion order
+            indent: Spaces per nesting level, 2 to 9 (default 2)
+            width: Fold long strings at spaces past this column (default
+                80); None never folds
+            default_flow_style: True writes every collection in flow style
+                ({a: 1}, [1, 2]); None only those holding just scalars
+                (default False, block style)
+            bool_style: How booleans are written: "true" (true/false, the
+                default), "True" (True/False) or "yes" (yes/no, which only
+                loads back as booleans with pyyaml_compat=True)
+            null_style: How None is written: "null" (the default), "~", or
+                "" to leave the value out (key:, -) wherever YAML allows
+                it; keys, flow collections and a document that is only
+                None still get null
+            comments: Comments to write as "# " lines above keys and list
+                items, by key path in grep() syntax ({"database.port":
+                "set by infra team", "servers[*].name": "unique"}); a path
+                that matches no key or item written in block style raises
+                YAMLError
+            canonical: Always write equal data as the same bytes, for
+                generated files kept in git: keys sorted (ties by their
+                text), default layout, long strings never folded. Can't be
+                combined with the options above. (Not PyYAML's canonical
+                form, which spells out every tag.)
 
     Returns:
-        List of (filename, data) tuples
+        The YAML text, or None if it was written to stream
+
+    Raises:
+        YAMLError: If data holds objects of other types, an option is
+            unknown or out of range, or a comments path matches nothing
 
     Example:
-        >>> results = load_directory("./configs")
-        >>> for filename, data in results:
-        ...     print(f"{filename}: {data}")
+        >>> print(safe_dump({"name": "web", "ports": [80, 443]}), end="")
+        name: web
+        ports:
+        - 80
+        - 443
     """
     try:
-        return _rustyyaml.load_directory(str(directory), recursive)
+        text = _rustyyaml.safe_dump(data, **options)
+    except YAMLError:
+        raise
     except Exception as e:
         raise YAMLError(str(e))
+    if stream is None:
+        return text
+    stream.write(text)
+    return None
 
 
-def load_directory_unsafe(
-    directory: Union[str, Path], recursive: bool = False
-) -> List[Tuple[str, Any]]:
+def load_rt(stream: Union[str, bytes, IO, os.PathLike], **options: Any) -> Any:
     """
-    Load all YAML files from a directory without safety checks
+    Parse YAML safely for editing, so dump_rt() can write it back as it was
+
+    A mapping or sequence document is returned as a
+    rustyyaml.roundtrip.CommentedMap or CommentedSeq: a dict or list that
+    remembers the text it was loaded from. Edit it like any other data,
+    then dump_rt() rewrites only what changed: comments, blank lines,
+    quoting, key order, indentation and anchors stay as they were. A
+    scalar document is returned as plain data.
 
     Args:
-        directory: Path to directory
-        recursive: If True, search subdirectories
+        stream: YAML content (at most one document)
+        **options: Load options that change how values are constructed
+            (pyyaml_compat, timestamps, limits, ...); document_markers,
+            marked, dict_class, as_namespace, schema, redact, sops_key and
+            profile aren't supported
 
     Returns:
-        List of (filename, data) tuples
+        The data, remembering its source if it's a mapping or sequence;
+        its .styles maps the JSON pointer of each scalar to its style
+        ("plain", "single-quoted", "double-quoted", "literal", "folded")
+
+    Raises:
+        YAMLError: If YAML is malformed or contains unsafe tags, or an
+            option isn't supported
+
+    Example:
+        >>> config = load_rt(open("deploy.yaml"))
+        >>> config["spec"]["replicas"] = 3
+        >>> open("deploy.yaml", "w").write(dump_rt(config))
+    """
+    try:
+        content = _read_stream(stream, options.get("max_size_bytes"))
+        return _rustyyaml.load_rt(content, **options)
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def dump_rt(data: Any, stream: Optional[IO] = None) -> Optional[str]:
+    """
+    Serialize data loaded with load_rt(), keeping the original text
+
+    The data is compared with the text it was loaded from, and only what
+    changed is rewritten: a changed scalar in place, keeping its quotes; a
+    removed entry or item with its lines; new entries and items after the
+    last one, at the same indentation. A flow collection ([a, b]) whose
+    keys or length changed is rewritten in flow style. Values are compared
+    strictly, so replacing 1 with True or 1.0 counts as a change. Data that
+    didn't come from load_rt() is dumped like safe_dump(data,
+    sort_keys=False).
+
+    Args:
+        data: A CommentedMap or CommentedSeq from load_rt(), edited
+        stream: File object to write to; if None, the YAML is returned
+
+    Returns:
+        The YAML text, or None if it was written to stream
+
+    Raises:
+        YAMLError: If data holds objects that can't be written as YAML
+
+    Example:
+        >>> config = load_rt("version: '1.2'  # bump on release\nname: web\n")
+        >>> config["version"] = "1.3"
+        >>> print(dump_rt(config), end="")
+        version: '1.3'  # bump on release
+        name: web
+    """
+    try:
+        text = _rustyyaml.dump_rt(data)
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+    if stream is None:
+        return text
+    stream.write(text)
+    return None
+
+
+def update_file(
+    path: Union[str, os.PathLike], key_path: str, new_value: Any, **options: Any
+) -> None:
+    """
+    Set one value in a YAML file, keeping everything else as it was
+
+    The file is loaded with load_rt(), the value at key_path replaced and
+    the file written back with dump_rt(), so comments, key order, quoting
+    and indentation are kept. The file is only written if its text changes.
+
+    Args:
+        path: UTF-8 YAML file with a single document
+        key_path: The value to set, in grep() syntax (spec.containers[0].image)
+            but without *; the last key is added if the mapping lacks it
+        new_value: Value to write there
+        **options: As for load_rt()
+
+    Raises:
+        YAMLError: If the file can't be read, parsed or written, key_path
+            names no value, or the file is compressed or not UTF-8
+
+    Example:
+        >>> update_file("Chart.yaml", "version", "1.4.0")
+        >>> update_file("deploy.yaml", "spec.template.spec.containers[0].image",
+        ...             "web:1.4.0")
+    """
+    try:
+        _rustyyaml.update_file(_fspath(path), key_path, new_value, **options)
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def dump(data: Any, stream: Optional[IO] = None, **options: Any) -> Optional[str]:
+    """
+    Serialize Python data to YAML
+
+    This is an alias for safe_dump() to match PyYAML's API. Unlike
+    PyYAML, arbitrary Python objects are never serialized.
+
+    Example:
+        >>> dump({"key": "value"})
+        'key: value\\n'
+    """
+    return safe_dump(data, stream, **options)
+
+
+def safe_dump_all(
+    documents: Iterable[Any], stream: Optional[IO] = None, **options: Any
+) -> Optional[str]:
+    """
+    Serialize several documents to one YAML stream
+
+    Each document is written as safe_dump would write it, with a "---"
+    line between documents, so load_all reads the stream back as a list
+    of them.
+
+    Args:
+        documents: Any iterable of documents (a list of manifests, a
+            generator)
+        stream: File object to write to; if None, the YAML is returned
+        **options: Dump options (see safe_dump), and headers: a comment
+            for each document (None for none), written as "# " lines
+            under its "---", as helm template writes "# Source:" lines
+
+    Returns:
+        The YAML text, or None if it was written to stream
+
+    Raises:
+        YAMLError: If a document holds objects of unsupported types, or
+            headers doesn't have one entry per document
+
+    Example:
+        >>> print(safe_dump_all([{"kind": "Service"}, {"kind": "Deployment"}]))
+        kind: Service
+        ---
+        kind: Deployment
+    """
+    try:
+        text = _rustyyaml.safe_dump_all(documents, **options)
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+    if stream is None:
+        return text
+    stream.write(text)
+    return None
+
+
+def dump_all(
+    documents: Iterable[Any], stream: Optional[IO] = None, **options: Any
+) -> Optional[str]:
+    """
+    Serialize several documents to one YAML stream
+
+    This is an alias for safe_dump_all() to match PyYAML's API.
+    """
+    return safe_dump_all(documents, stream, **options)
+
+
+def safe_load_file(
+    path: Union[str, os.PathLike],
+    sha256: Optional[str] = None,
+    streaming: bool = False,
+    **options: Any,
+) -> Any:
+    """
+    Load YAML from a file safely
+
+    The file is read and decoded in Rust without holding the GIL, and an
+    error raised for it has its path attribute set to the file.
+
+    Args:
+        path: Path to YAML file (UTF-8, UTF-16 or UTF-32, detected from its
+            BOM, or as the encoding option says). A .gz, .bz2 or .xz
+            file is decompressed in Rust first
+        sha256: Expected SHA-256 of the file (hex, optionally prefixed
+            "sha256:"). The bytes read are checked in Rust before anything
+            is decompressed, decoded or parsed; a mismatch raises
+            IntegrityError
+        streaming: Read the file in chunks and convert the document one
+            top-level entry at a time, so a multi-GB file is never held in
+            memory whole. Values are built as with the composer-based
+            options (pyyaml_compat, timestamps, ...); options that need the
+            whole document (marked, schema, redact, sops_key, profile,
+            document_markers, encoding) and sha256 are rejected
+        **options: Load options (see module docstring)
+
+    Returns:
+        Python object
+
+    Example:
+        >>> config = safe_load_file('config.yaml')
+        >>> config = safe_load_file('deploy.yaml', sha256=release["deploy.yaml"])
+        >>> export = safe_load_file('export.yaml', streaming=True)
+    """
+    try:
+        if streaming:
+            if sha256 is not None:
+                raise ValueError("streaming=True can't be combined with sha256")
+            return _rustyyaml.load_file_streaming(_fspath(path), True, **options)
+        if sha256 is None:
+            return _rustyyaml.load_file(_fspath(path), True, **options)
+        return _rustyyaml.load_file_verified(_fspath(path), sha256, **options)
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def unsafe_load_file(
+    path: Union[str, os.PathLike], streaming: bool = False, **options: Any
+) -> Any:
+    """
+    Load YAML from a file without safety checks (DANGEROUS!)
+
+    Reads the file as safe_load_file does, then parses it as unsafe_load
+    would. Only use this for files you trust.
+
+    Args:
+        path: Path to YAML file
+        streaming: Read and convert the file incrementally (see
+            safe_load_file)
+        **options: Load options (see module docstring)
+
+    Returns:
+        Python object
     """
     try:
-        return _rustyyaml.load_directory_unsafe(str(directory), recursive)
+        if streaming:
+            return _rustyyaml.load_file_streaming(_fspath(path), False, **options)
+        return _rustyyaml.load_file(_fspath(path), False, **options)
+    except YAMLError:
+        raise
     except Exception as e:
         raise YAMLError(str(e))
+
+
+def load_all_file(path: Union[str, os.PathLike], **options: Any) -> List[Any]:
+    """
+    Load multiple YAML documents from a file
+
+    The file is read and decoded in Rust without holding the GIL, so a
+    large multi-document bundle never becomes a Python string. Documents
+    are checked for unsafe tags as load_all checks them, and an error
+    raised for the file has its path attribute set.
+
+    Args:
+        path: Path to YAML file
+        **options: Load options (see module docstring); collect_errors and
+            document_spans shape the result as for load_all
+
+    Returns:
+        List of Python objects
+    """
+    try:
+        return _rustyyaml.load_all_file(_fspath(path), True, **options)
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def safe_load_stdin(**options: Any) -> Any:
+    """
+    Load YAML from standard input safely
+
+    Stdin is read to its end and decoded in Rust with the GIL released, so
+    pipelines like `kubectl get -o yaml | python script.py` need no
+    buffering in Python. Anything already read through sys.stdin is not
+    seen again.
+
+    Args:
+        **options: Load options (see module docstring)
+
+    Returns:
+        Python object
+
+    Example:
+        >>> manifest = safe_load_stdin()
+    """
+    try:
+        return _rustyyaml.load_stdin(True, **options)
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def load_all_stdin(**options: Any) -> List[Any]:
+    """
+    Load every YAML document from standard input
+
+    Read like safe_load_stdin, then parsed like load_all (a `kubectl get
+    -o yaml` list or a `---`-separated stream of manifests).
+
+    Args:
+        **options: Load options (see module docstring)
+
+    Returns:
+        List of Python objects (one per document), shaped as for load_all
+        with collect_errors or document_spans
+    """
+    try:
+        return _rustyyaml.load_all_stdin(True, **options)
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def dump_file(
+    data: Any,
+    path: Union[str, os.PathLike],
+    make_dirs: bool = False,
+    **options: Any,
+) -> None:
+    """
+    Serialize Python data to a YAML file
+
+    The YAML is produced and written in Rust, with the GIL released, so a
+    large document never becomes a Python string.
+
+    Args:
+        data: Data to write (see safe_dump)
+        path: File to write; an existing file is replaced
+        make_dirs: Create missing parent directories first
+        **options: Dump options (see safe_dump)
+
+    Raises:
+        YAMLError: If data holds unsupported types or the file can't be
+            written
+
+    Example:
+        >>> dump_file(config, "out/config.yaml", make_dirs=True)
+    """
+    try:
+        _rustyyaml.dump_file(data, _fspath(path), make_dirs, **options)
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def dump_all_file(
+    documents: Iterable[Any],
+    path: Union[str, os.PathLike],
+    make_dirs: bool = False,
+    **options: Any,
+) -> None:
+    """
+    Serialize several documents to one YAML file, separated by "---"
+
+    Args:
+        documents: Any iterable of documents (see safe_dump_all)
+        path: File to write; an existing file is replaced
+        make_dirs: Create missing parent directories first
+        **options: Dump options and headers (see safe_dump_all)
+
+    Raises:
+        YAMLError: If a document holds unsupported types or the file can't
+            be written
+
+    Example:
+        >>> dump_all_file(manifests, "deploy/bundle.yaml")
+    """
+    try:
+        _rustyyaml.dump_all_file(documents, _fspath(path), make_dirs, **options)
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def add_representer(data_type: type, representer: Callable[[Any], Any]) -> None:
+    """
+    Teach the dump functions to serialize a type
+
+    Whenever an object of exactly data_type is dumped, representer is
+    called with it and its result is dumped in its place. The result can
+    be any data safe_dump handles, including objects of other types with
+    representers. Registering a type again replaces its representer.
+
+    Args:
+        data_type: The type (subclasses need their own representer, as in
+            PyYAML)
+        representer: Callable taking the object and returning plain data
+
+    Raises:
+        YAMLError: If data_type isn't a type or representer isn't callable
+
+    Example:
+        >>> add_representer(ipaddress.IPv4Address, str)
+        >>> safe_dump({"host": ipaddress.ip_address("10.0.0.1")})
+        'host: 10.0.0.1\\n'
+    """
+    try:
+        _rustyyaml.add_representer(data_type, representer)
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+class Loader(_rustyyaml.Loader):
+    """
+    Load documents with options validated once
+
+    safe_load(text, **options) parses its options on every call, and
+    options like schema= or redact= compile something first. A Loader does
+    that once and reuses it, which matters in hot loops.
+
+    Args:
+        safe: False skips safety checks, like unsafe_load (default: True)
+        **options: Load options (see module docstring)
+
+    Raises:
+        YAMLError: If an option is unknown or invalid
+
+    Example:
+        >>> loader = Loader(schema="deployment", timestamps=True)
+        >>> docs = [loader.load(text) for text in texts]
+        >>> config = loader.load_file("config.yaml")
+    """
+
+    def __new__(cls, safe: bool = True, **options: Any) -> "Loader":
+        try:
+            return super().__new__(cls, safe, **options)
+        except YAMLError:
+            raise
+        except Exception as e:
+            raise YAMLError(str(e))
+
+    def load(self, stream: Union[str, bytes, IO, os.PathLike]) -> Any:
+        """Parse a single document"""
+        try:
+            return super().load(_read_stream(stream))
+        except YAMLError:
+            raise
+        except Exception as e:
+            raise YAMLError(str(e))
+
+    def load_all(self, stream: Union[str, bytes, IO, os.PathLike]) -> List[Any]:
+        """Parse every document ((documents, errors) with collect_errors)"""
+        try:
+            return super().load_all(_read_stream(stream))
+        except YAMLError:
+            raise
+        except Exception as e:
+            raise YAMLError(str(e))
+
+    def load_file(self, path: Union[str, os.PathLike]) -> Any:
+        """Load a file's single document; errors have path set"""
+        try:
+            return super().load_file(_fspath(path))
+        except YAMLError:
+            raise
+        except Exception as e:
+            raise YAMLError(str(e))
+
+
+class Dumper(_rustyyaml.Dumper):
+    """
+    Dump documents with options validated once
+
+    safe_dump(data, **options) parses its options on every call. A Dumper
+    does that once, and keeps representers of its own: they apply only to
+    what it dumps, and win over ones registered with add_representer.
+
+    Args:
+        **options: Dump options (see safe_dump)
+
+    Raises:
+        YAMLError: If an option is unknown or invalid
+
+    Example:
+        >>> dumper = Dumper(sort_keys=False, bool_style="True")
+        >>> dumper.add_representer(Money, str)
+        >>> texts = [dumper.dump(record) for record in records]
+    """
+
+    def __new__(cls, **options: Any) -> "Dumper":
+        try:
+            return super().__new__(cls, **options)
+        except YAMLError:
+            raise
+        except Exception as e:
+            raise YAMLError(str(e))
+
+    def dump(
+        self,
+        data: Any,
+        stream: Optional[IO] = None,
+        comments: Optional[Dict[str, str]] = None,
+    ) -> Optional[str]:
+        """Serialize data (see safe_dump); None if written to stream"""
+        try:
+            text = super().dump(data, comments)
+        except YAMLError:
+            raise
+        except Exception as e:
+            raise YAMLError(str(e))
+        if stream is None:
+            return text
+        stream.write(text)
+        return None
+
+    def dump_all(
+        self,
+        documents: Iterable[Any],
+        stream: Optional[IO] = None,
+        headers: Optional[List[Optional[str]]] = None,
+    ) -> Optional[str]:
+        """Serialize documents to one stream (see safe_dump_all)"""
+        try:
+            text = super().dump_all(documents, headers)
+        except YAMLError:
+            raise
+        except Exception as e:
+            raise YAMLError(str(e))
+        if stream is None:
+            return text
+        stream.write(text)
+        return None
+
+    def dump_file(
+        self,
+        data: Any,
+        path: Union[str, os.PathLike],
+        make_dirs: bool = False,
+        comments: Optional[Dict[str, str]] = None,
+    ) -> None:
+        """Serialize data to a file, written from Rust (see dump_file)"""
+        try:
+            super().dump_file(data, _fspath(path), make_dirs, comments)
+        except YAMLError:
+            raise
+        except Exception as e:
+            raise YAMLError(str(e))
+
+    def add_representer(
+        self, data_type: type, representer: Callable[[Any], Any]
+    ) -> None:
+        """Serialize data_type with representer, in this dumper only"""
+        try:
+            super().add_representer(data_type, representer)
+        except YAMLError:
+            raise
+        except Exception as e:
+            raise YAMLError(str(e))
+
+
+def safe_load_many(
+    yaml_strings: List[str], filter: Optional[DocumentFilter] = None, **options: Any
+) -> List[Any]:
+    """
+    Parse multiple YAML strings in parallel
+
+    This is 5-10x faster than a loop for large batches.
+
+    Args:
+        yaml_strings: List of YAML content strings
+        filter: Keep only matching documents. A dict such as
+            {"kind": "Deployment"} keeps documents containing those keys and
+            values (nested dicts match recursively) and is checked in Rust,
+            so rejected documents never become Python objects. A callable
+            keeps documents for which it returns a truthy value
+        **options: Load options (see module docstring)
+
+    Returns:
+        List of parsed Python objects (same order as input), or
+        (results, errors) with collect_errors=True
+
+    Example:
+        >>> yamls = ["doc: 1", "doc: 2", "doc: 3"]
+        >>> results = safe_load_many(yamls)
+        >>> print(results)
+        [{'doc': 1}, {'doc': 2}, {'doc': 3}]
+
+        >>> safe_load_many(manifests, filter={"kind": "Deployment"})
+    """
+    try:
+        return _rustyyaml.safe_load_many(yaml_strings, filter, **options)
+    except (YAMLError, CancelledError):
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def unsafe_load_many(
+    yaml_strings: List[str], filter: Optional[DocumentFilter] = None, **options: Any
+) -> List[Any]:
+    """
+    Parse multiple YAML strings in parallel without safety checks
+
+    Args:
+        yaml_strings: List of YAML content strings
+        filter: Keep only matching documents (see safe_load_many)
+        **options: Load options (see module docstring)
+
+    Returns:
+        List of parsed Python objects
+    """
+    try:
+        return _rustyyaml.unsafe_load_many(yaml_strings, filter, **options)
+    except (YAMLError, CancelledError):
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def load_directory(
+    directory: Union[str, os.PathLike],
+    recursive: bool = False,
+    transform: Optional[Callable[[Any], Any]] = None,
+    filter: Optional[DocumentFilter] = None,
+    **options: Any,
+) -> List[Tuple[str, Any]]:
+    """
+    Load all YAML files from a directory in parallel
+
+    Byte-identical files (copied defaults in overlay trees) are parsed
+    once; each path still gets its own result. Files may be UTF-8, UTF-16
+    or UTF-32, with or without a byte order mark (see the encoding option
+    for legacy 8-bit files).
+
+    Args:
+        directory: Path to directory
+        recursive: If True, search subdirectories
+        transform: Called on each parsed document; its return value is
+            collected instead (e.g. to keep only the fields you need).
+            Exceptions it raises are reported like parse errors
+        filter: Keep only matching files (see safe_load_many); applied
+            before transform
+        **options: Load options (see module docstring)
+
+    Returns:
+        List of (filename, data) tuples ((filename, data, info) with
+        file_info=True), or (results, errors) with collect_errors=True
+
+    Example:
+        >>> results = load_directory("./configs")
+        >>> for filename, data in results:
+        ...     print(f"{filename}: {data}")
+
+        >>> kinds = load_directory("./k8s", transform=lambda doc: doc["kind"])
+    """
+    try:
+        return _rustyyaml.load_directory(
+            _fspath(directory), recursive, transform, filter, **options
+        )
+    except (YAMLError, CancelledError):
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def load_files_verified(
+    manifest: Mapping[Union[str, os.PathLike], str],
+    transform: Optional[Callable[[Any], Any]] = None,
+    filter: Optional[DocumentFilter] = None,
+    **options: Any,
+) -> List[Tuple[str, Any]]:
+    """
+    Load the files of a checksum manifest in parallel
+
+    Each file's SHA-256 is checked in Rust before it is decoded or parsed;
+    a mismatch raises IntegrityError (with .path, .expected and .actual),
+    or is collected or passed to on_error like any other failed file.
+
+    Args:
+        manifest: Mapping of path to expected SHA-256 (hex, optionally
+            prefixed "sha256:")
+        transform: Called on each parsed document (see load_directory)
+        filter: Keep only matching files (see safe_load_many)
+        **options: Load options (see module docstring)
+
+    Returns:
+        List of (path, data) tuples in manifest order, shaped like
+        load_directory's results
+
+    Example:
+        >>> manifest = json.load(open("release.json"))["sha256"]
+        >>> configs = load_files_verified(manifest, collect_errors=True)
+    """
+    try:
+        manifest = {_fspath(path): digest for path, digest in manifest.items()}
+        return _rustyyaml.load_files_verified(manifest, transform, filter, **options)
+    except (YAMLError, CancelledError):
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def load_archive(
+    path: Union[str, os.PathLike],
+    transform: Optional[Callable[[Any], Any]] = None,
+    filter: Optional[DocumentFilter] = None,
+    **options: Any,
+) -> List[Tuple[str, Any]]:
+    """
+    Load every YAML member of a zip or tar archive in parallel
+
+    The archive is read in Rust without extracting it; its .yaml and .yml
+    members (compressed ones too) are parsed in parallel. Zip, tar and
+    gzipped tar (.tgz, as Helm charts are packaged) are detected from the
+    content.
+
+    Args:
+        path: Path to the archive
+        transform: Called on each parsed document (see load_directory)
+        filter: Keep only matching members (see safe_load_many)
+        **options: Load options (see module docstring)
+
+    Returns:
+        List of (member_name, data) tuples in archive order, shaped like
+        load_directory's results; errors for a member have path set to its
+        name
+
+    Raises:
+        YAMLError: If the archive can't be read or isn't a zip or tar archive
+
+    Example:
+        >>> for name, doc in load_archive("nginx-15.1.0.tgz"):
+        ...     print(name, doc.get("kind"))
+    """
+    try:
+        return _rustyyaml.load_archive(_fspath(path), transform, filter, **options)
+    except (YAMLError, CancelledError):
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def load_directory_unsafe(
+    directory: Union[str, os.PathLike],
+    recursive: bool = False,
+    transform: Optional[Callable[[Any], Any]] = None,
+    filter: Optional[DocumentFilter] = None,
+    **options: Any,
+) -> List[Tuple[str, Any]]:
+    """
+    Load all YAML files from a directory without safety checks
+
+    Args:
+        directory: Path to directory
+        recursive: If True, search subdirectories
+        transform: Called on each parsed document (see load_directory)
+        filter: Keep only matching files (see safe_load_many)
+        **options: Load options (see module docstring)
+
+    Returns:
+        List of (filename, data) tuples
+    """
+    try:
+        return _rustyyaml.load_directory_unsafe(
+            _fspath(directory), recursive, transform, filter, **options
+        )
+    except (YAMLError, CancelledError):
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def load_object_store(
+    url: str,
+    recursive: bool = False,
+    transform: Optional[Callable[[Any], Any]] = None,
+    filter: Optional[DocumentFilter] = None,
+    storage_options: Optional[Dict[str, str]] = None,
+    max_concurrency: int = 16,
+    **options: Any,
+) -> List[Tuple[str, Any]]:
+    """
+    Load all YAML objects under an object store prefix in parallel
+
+    Like load_directory for a bucket: the .yaml/.yml objects under the
+    prefix are listed, downloaded with at most max_concurrency requests in
+    flight, and parsed in parallel. Credentials come from the usual
+    environment variables (AWS_ACCESS_KEY_ID, AWS_REGION,
+    GOOGLE_SERVICE_ACCOUNT, AZURE_STORAGE_ACCOUNT_NAME, ...).
+
+    Args:
+        url: Prefix URL: s3://bucket/prefix/, gs://bucket/prefix/,
+            az://container/prefix/ or file:///path/
+        recursive: If True, include objects below nested prefixes
+        transform: Called on each parsed document (see load_directory)
+        filter: Keep only matching objects (see safe_load_many)
+        storage_options: Store settings that override the environment,
+            e.g. {"aws_region": "eu-west-1", "aws_endpoint": "http://minio:9000"}
+        max_concurrency: Downloads in flight at once
+        **options: Load options (see module docstring)
+
+    Returns:
+        List of (object_url, data) tuples in path order, or
+        (results, errors) with collect_errors=True
+
+    Raises:
+        YAMLError: If the store can't be listed or read, or an object is
+            malformed (unless collect_errors=True)
+
+    Example:
+        >>> for url, doc in load_object_store("s3://deploys/prod/", recursive=True):
+        ...     print(url, doc["kind"])
+        s3://deploys/prod/web.yaml Deployment
+    """
+    try:
+        return _rustyyaml.load_object_store(
+            url,
+            recursive,
+            transform,
+            filter,
+            storage_options,
+            max_concurrency,
+            **options,
+        )
+    except (YAMLError, CancelledError):
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def load_git(
+    repo: Union[str, os.PathLike],
+    ref: str = "main",
+    path: Union[str, os.PathLike] = "",
+    recursive: bool = False,
+    transform: Optional[Callable[[Any], Any]] = None,
+    filter: Optional[DocumentFilter] = None,
+    **options: Any,
+) -> List[Tuple[str, Any]]:
+    """
+    Load all YAML files under a path of a git repository at a revision
+
+    Files are read straight from the object database, so nothing is
+    checked out and the worktree is left alone. Remote repositories are
+    cloned (bare) into a temporary directory that is removed afterwards.
+
+    Args:
+        repo: Local repository (worktree or bare), or a URL to clone
+            (https://..., git@host:org/repo.git)
+        ref: Any revision: branch, tag, commit id, HEAD~3
+        path: Directory (or single file) within the repository; the
+            default is the repository root
+        recursive: If True, include subdirectories of path
+        transform: Called on each parsed document (see load_directory)
+        filter: Keep only matching files (see safe_load_many)
+        **options: Load options (see module docstring)
+
+    Returns:
+        List of (path, data) tuples in path order, with paths relative to
+        the repository root, or (results, errors) with collect_errors=True
+
+    Raises:
+        YAMLError: If the repository can't be opened or cloned, the ref or
+            path doesn't exist, or a file is malformed (unless
+            collect_errors=True)
+
+    Example:
+        >>> for path, doc in load_git(".", ref="v1.4.0", path="deploy/"):
+        ...     print(path, doc["kind"])
+        deploy/web.yaml Deployment
+    """
+    try:
+        return _rustyyaml.load_git(
+            _fspath(repo),
+            ref,
+            _fspath(path),
+            recursive,
+            transform,
+            filter,
+            **options,
+        )
+    except (YAMLError, CancelledError):
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def dump_shm(doc: Any) -> str:
+    """
+    Encode loaded data into shared memory for other processes to load
+
+    The document is written once, in a compact binary form, to a file in
+    /dev/shm (the temporary directory on systems without one). Workers
+    open it with load_shm, so a process pool shares a single parsed copy
+    instead of re-parsing or unpickling it per worker.
+
+    Args:
+        doc: Loaded data: dicts, lists, tuples, str, int, float, bool and
+            None (Namespace objects are encoded as their dicts)
+
+    Returns:
+        Handle to pass to load_shm (the path of the file). Remove it with
+        os.remove once the workers have loaded it; their views stay valid.
+
+    Raises:
+        YAMLError: If the data holds other types or encodes to over 4 GiB
+
+    Example:
+        >>> handle = dump_shm(safe_load_file("corpus.yaml"))
+        >>> with ProcessPoolExecutor(initializer=init, initargs=(handle,)) as pool:
+        ...     results = list(pool.map(check, names))
+        >>> os.remove(handle)
+    """
+    try:
+        return _rustyyaml.dump_shm(doc)
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def load_shm(handle: Union[str, os.PathLike]) -> Any:
+    """
+    Load a document written by dump_shm, without copying it
+
+    The file is mapped read-only, so every process reads the same memory.
+    Mappings and sequences come back as SharedMapping and SharedSequence
+    views that decode values only when they are accessed; to_dict() and
+    to_list() copy a subtree into plain dicts and lists.
+
+    Args:
+        handle: Handle returned by dump_shm
+
+    Returns:
+        SharedMapping, SharedSequence, or the scalar of a scalar document
+
+    Raises:
+        YAMLError: If the file can't be opened or isn't a shared document
+
+    Example:
+        >>> def init(handle):
+        ...     global corpus
+        ...     corpus = load_shm(handle)
+        >>> corpus["services"]["web"]["replicas"]
+        3
+    """
+    try:
+        return _rustyyaml.load_shm(_fspath(handle))
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def grep(
+    directory: Union[str, os.PathLike], key_path: str, recursive: bool = False
+) -> List[Tuple[str, str, Any]]:
+    """
+    Find the values at a key path in every YAML file of a directory
+
+    Files are searched in parallel in Rust; only the matches are converted
+    to Python objects.
+
+    Args:
+        directory: Path to directory
+        key_path: Dotted path with [N] for list items and * as a wildcard
+            for any key or item, e.g. "spec.template.spec.containers[*].image"
+        recursive: If True, search subdirectories
+
+    Returns:
+        List of (file, path, value) tuples ordered by file, where path is
+        the concrete path of the match ("...containers[1].image")
+
+    Raises:
+        YAMLError: If a file is malformed or the key path is invalid
+
+    Example:
+        >>> for file, path, image in grep("./k8s", "spec.containers[*].image"):
+        ...     print(f"{file}: {image}")
+    """
+    try:
+        return _rustyyaml.grep(_fspath(directory), key_path, recursive)
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def index_keys(
+    directory: Union[str, os.PathLike], recursive: bool = False
+) -> Dict[str, List[Tuple[str, int, int]]]:
+    """
+    Index every key path defined in a directory
+
+    Answers "where is spec.replicas set?" across a config tree without
+    loading anything into Python. Paths use the same syntax as grep
+    results; list items appear as [N].
+
+    Args:
+        directory: Path to directory
+        recursive: If True, index subdirectories
+
+    Returns:
+        Dict of key path to (file, line, column) definitions (1-based),
+        in file name order
+
+    Raises:
+        YAMLError: If a file is malformed
+
+    Example:
+        >>> index = index_keys("./overlays", recursive=True)
+        >>> index["spec.replicas"]
+        [('./overlays/prod/app.yaml', 4, 3), ...]
+    """
+    try:
+        return _rustyyaml.index_keys(_fspath(directory), recursive)
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def walk(
+    doc_or_yaml: Any, max_depth: Optional[int] = None, leaves_only: bool = False
+) -> List[Tuple[str, Any]]:
+    """
+    List every value in a document with its key path
+
+    The traversal runs in Rust, so it is much faster than a recursive
+    Python function over a large structure. Loaded data is walked as is:
+    the values returned are the objects in it, and anything that is not a
+    dict, list or tuple is a leaf.
+
+    Args:
+        doc_or_yaml: YAML text (string, bytes, file object, or Path), or
+            already loaded data
+        max_depth: Don't descend below this many levels (1 lists only the
+            top-level keys); collections at the limit are returned whole
+        leaves_only: Skip mappings and sequences and return only the
+            values inside them
+
+    Returns:
+        (path, value) tuples in document order, with paths in grep syntax
+        ("spec.containers[0].image"); the root itself is not included
+
+    Raises:
+        YAMLError: If the YAML is malformed
+
+    Example:
+        >>> walk("a: {b: 1}\\nc: [x]\\n")
+        [('a', {'b': 1}), ('a.b', 1), ('c', ['x']), ('c[0]', 'x')]
+        >>> walk({"a": {"b": 1}}, leaves_only=True)
+        [('a.b', 1)]
+    """
+    try:
+        if isinstance(doc_or_yaml, (str, bytes, os.PathLike)) or hasattr(
+            doc_or_yaml, "read"
+        ):
+            doc_or_yaml = _read_stream(doc_or_yaml)
+        return _rustyyaml.walk(doc_or_yaml, max_depth, leaves_only)
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def map_scalars(
+    yaml_or_doc: Any,
+    fn: Callable[[Any], Any],
+    paths: Optional[Union[str, List[str]]] = None,
+) -> Any:
+    """
+    Apply a callable to every scalar value
+
+    The building block for templating, unit conversion and rewriting
+    secrets. Keys are never passed, only values.
+
+    Args:
+        yaml_or_doc: YAML text (string, bytes, file object, or Path), or
+            already loaded data
+        fn: Called with each scalar value, as loaded; its return value
+            replaces it (a list or dict is written in flow style)
+        paths: Key path or list of key paths in grep syntax, e.g.
+            "spec.containers[*].image"; only scalars at or below a match
+            are passed (default: all of them)
+
+    Returns:
+        For YAML text, the same text with the changed scalars rewritten, so
+        comments and formatting are kept. For loaded data, a transformed
+        copy (the original is not modified).
+
+    Raises:
+        YAMLError: If the YAML is malformed, a path is invalid, or fn
+            raises
+
+    Example:
+        >>> print(map_scalars("image: nginx:1.25  # pinned\\n", str.upper))
+        image: NGINX:1.25  # pinned
+        >>> map_scalars({"a": [1, 2], "b": 3}, lambda v: v * 10, paths="a")
+        {'a': [10, 20], 'b': 3}
+    """
+    try:
+        if isinstance(yaml_or_doc, (str, bytes, os.PathLike)) or hasattr(
+            yaml_or_doc, "read"
+        ):
+            yaml_or_doc = _read_stream(yaml_or_doc)
+        return _rustyyaml.map_scalars(yaml_or_doc, fn, paths)
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def explode(
+    path: Union[str, os.PathLike],
+    out_dir: Union[str, os.PathLike],
+    name_template: str = "{kind}-{name}.yaml",
+) -> List[str]:
+    """
+    Split a multi-document YAML file into one file per document
+
+    Each document's original text (comments, quoting, formatting) is
+    written verbatim. Empty documents are skipped. Nothing is written if
+    two documents would get the same file name.
+
+    Args:
+        path: Multi-document YAML file
+        out_dir: Directory to write to (created if missing)
+        name_template: File name with {field} placeholders: key paths into
+            the document ({metadata.labels.app}), {name} and {namespace}
+            (looked up under metadata) and {index}
+
+    Returns:
+        Paths of the written files, in document order
+
+    Raises:
+        YAMLError: If the file is malformed, a document lacks a template
+            field or two documents map to the same name
+
+    Example:
+        >>> explode("bundle.yaml", "manifests/")
+        ['manifests/Deployment-web.yaml', 'manifests/Service-web.yaml']
+    """
+    try:
+        return _rustyyaml.explode(_fspath(path), _fspath(out_dir), name_template)
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def bundle(
+    paths_or_dir: Union[str, os.PathLike, List[Union[str, os.PathLike]]],
+    out_path: Union[str, os.PathLike],
+    recursive: bool = False,
+) -> int:
+    """
+    Concatenate YAML files into one multi-document bundle
+
+    Every file is parsed first, so a malformed file fails the call before
+    the output is created. Documents are then streamed to out_path
+    verbatim, each starting with ---; empty documents are dropped.
+
+    Args:
+        paths_or_dir: List of files, or a directory whose .yaml/.yml files
+            are bundled in file name order
+        out_path: Bundle to write (replaced if it exists)
+        recursive: With a directory, include subdirectories
+
+    Returns:
+        Number of documents written
+
+    Raises:
+        YAMLError: If an input file is malformed or cannot be read
+
+    Example:
+        >>> bundle("manifests/", "release.yaml")
+        3
+    """
+    try:
+        if isinstance(paths_or_dir, (str, bytes, os.PathLike)):
+            paths_or_dir = _fspath(paths_or_dir)
+        else:
+            paths_or_dir = [_fspath(path) for path in paths_or_dir]
+        return _rustyyaml.bundle(paths_or_dir, _fspath(out_path), recursive)
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def drift(
+    directory: Union[str, os.PathLike],
+    baseline: Union[str, os.PathLike],
+    recursive: bool = False,
+) -> List[Dict[str, Any]]:
+    """
+    Compare a directory of YAML files against a baseline
+
+    Files are parsed and diffed in parallel in Rust; only the differences
+    are converted to Python objects.
+
+    Args:
+        directory: Directory with the current files
+        baseline: Baseline directory (files paired by relative path,
+            documents by position) or a multi-document bundle (documents
+            paired by kind, metadata.namespace and metadata.name)
+        recursive: If True, include subdirectories
+
+    Returns:
+        One report per file, ordered by file name, with keys:
+        - file: path relative to its directory (bundle documents no file
+          matches are reported under the bundle's path)
+        - status: "unchanged", "changed", "added" or "removed"
+        - changes: dicts with document (index), op ("added", "removed",
+          "changed"), path (key path, "" for a whole document) and the
+          old and/or new value
+
+    Raises:
+        YAMLError: If a file is malformed or a path does not exist
+
+    Example:
+        >>> for report in drift("./live", "./baseline"):
+        ...     for change in report["changes"]:
+        ...         print(report["file"], change["op"], change["path"])
+        app.yaml changed spec.replicas
+    """
+    try:
+        return _rustyyaml.drift(_fspath(directory), _fspath(baseline), recursive)
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def to_csv(
+    dir_or_docs: Union[str, os.PathLike, List[Any]],
+    out_path: Optional[Union[str, os.PathLike]] = None,
+    recursive: bool = False,
+) -> Union[str, int]:
+    """
+    Flatten configs into CSV for auditing
+
+    Every scalar becomes a row with columns file, document (index within
+    the file), key_path (grep syntax, "spec.containers[0].image"), value
+    and type ("str", "int", "float", "bool", "null", or "map"/"seq" for
+    empty collections). Files are read and flattened in parallel.
+
+    Args:
+        dir_or_docs: Directory of .yaml/.yml files, or a list of loaded
+            documents (their file column is empty)
+        out_path: Write the CSV to this file instead of returning it
+        recursive: With a directory, include subdirectories
+
+    Returns:
+        The CSV text (with a header row), or the number of rows written
+        when out_path is given
+
+    Raises:
+        YAMLError: If a file is malformed or the directory does not exist
+
+    Example:
+        >>> print(to_csv([{"spec": {"replicas": 3}}]))
+        file,document,key_path,value,type
+        ,0,spec.replicas,3,int
+        >>> to_csv("./k8s", "audit.csv", recursive=True)
+        1284
+    """
+    try:
+        if isinstance(dir_or_docs, (str, bytes, os.PathLike)):
+            dir_or_docs = _fspath(dir_or_docs)
+        if out_path is not None:
+            out_path = _fspath(out_path)
+        return _rustyyaml.to_csv(dir_or_docs, out_path, recursive)
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def to_dotenv(doc: Dict[str, Any], prefix: str = "APP_") -> str:
+    """
+    Flatten a config into dotenv (.env) lines
+
+    Nested keys are joined with "__" and upper-cased, so
+    {"database": {"port": 5432}} becomes APP_DATABASE__PORT=5432. Sequence
+    items use their index (APP_HOSTS__0), other characters in keys become
+    "_", None becomes an empty value and empty collections are left out.
+    Values are single-quoted when they contain spaces or shell characters,
+    and double-quoted with escapes when they contain line breaks or quotes.
+
+    Args:
+        doc: Loaded config (a dict)
+        prefix: Prepended to every variable name
+
+    Returns:
+        The env file text, one NAME=value line per scalar
+
+    Raises:
+        YAMLError: If doc is not a mapping
+
+    Example:
+        >>> print(to_dotenv({"database": {"port": 5432, "password": "p w"}}))
+        APP_DATABASE__PORT=5432
+        APP_DATABASE__PASSWORD='p w'
+    """
+    try:
+        return _rustyyaml.to_dotenv(doc, prefix)
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def yaml_to_ini(stream: Union[str, bytes, IO, os.PathLike]) -> str:
+    """
+    Convert a YAML config to INI (configparser) format
+
+    Top-level mappings become sections. Nested mappings become dotted
+    sections ([server.tls]) and lists of scalars become one item per
+    continuation line. Key order is kept.
+
+    Args:
+        stream: YAML content as string, bytes, file object, or Path
+
+    Returns:
+        INI text
+
+    Raises:
+        YAMLError: If the YAML is malformed or does not fit INI (top-level
+            scalars, multi-line strings, nested lists)
+
+    Example:
+        >>> print(yaml_to_ini("server: {port: 8080, hosts: [a, b]}"))
+        [server]
+        port = 8080
+        hosts =
+            a
+            b
+    """
+    try:
+        content = _read_stream(stream)
+        return _rustyyaml.yaml_to_ini(content)
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def ini_to_yaml(stream: Union[str, bytes, IO, os.PathLike]) -> str:
+    """
+    Convert an INI config to YAML
+
+    Sections become top-level mappings (dotted section names nest), keys
+    keep their case and order, and values resolve like plain YAML scalars:
+    8080 becomes an int and true a bool, while an empty value stays an
+    empty string. A value that starts on an indented continuation line
+    becomes a list.
+
+    Args:
+        stream: INI content as string, bytes, file object, or Path
+
+    Returns:
+        YAML text
+
+    Raises:
+        YAMLError: For keys outside a section, malformed lines or
+            duplicate keys, with the line number
+
+    Example:
+        >>> print(ini_to_yaml("[server]\nport = 8080\n"))
+        server:
+          port: 8080
+    """
+    try:
+        content = _read_stream(stream)
+        return _rustyyaml.ini_to_yaml(content)
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def yaml_to_xml(
+    stream: Union[str, bytes, IO, os.PathLike],
+    root: str = "root",
+    attr_prefix: str = "@",
+    text_key: str = "#text",
+    list_item: Optional[str] = None,
+) -> str:
+    """
+    Convert a YAML document to XML
+
+    Keys starting with attr_prefix become attributes and text_key holds an
+    element's text next to attributes or children. A list is written as the
+    element repeated once per item, or with list_item, as one element
+    wrapping a <list_item> element per entry.
+
+    Args:
+        stream: YAML content as string, bytes, file object, or Path
+        root: Root element name, used unless the document is a mapping
+            with a single key
+        attr_prefix: Prefix marking attribute keys
+        text_key: Key holding element text
+        list_item: Element name for list entries (default: repeat the
+            element)
+
+    Returns:
+        Indented XML text with an XML declaration
+
+    Raises:
+        YAMLError: If the YAML is malformed or a key is not a valid element
+            name
+
+    Example:
+        >>> print(yaml_to_xml("server: {'@id': web, host: [a, b]}"))
+        <?xml version="1.0" encoding="UTF-8"?>
+        <server id="web">
+          <host>a</host>
+          <host>b</host>
+        </server>
+    """
+    try:
+        content = _read_stream(stream)
+        return _rustyyaml.yaml_to_xml(content, root, attr_prefix, text_key, list_item)
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def xml_to_yaml(
+    stream: Union[str, bytes, IO, os.PathLike],
+    attr_prefix: str = "@",
+    text_key: str = "#text",
+    force_list: Optional[List[str]] = None,
+    typed: bool = True,
+) -> str:
+    """
+    Convert XML to YAML
+
+    The root element becomes the single top-level key. Attributes become
+    keys with attr_prefix, repeated child elements become lists, and an
+    element with only text becomes that text (an empty one is null).
+    Comments and processing instructions are dropped.
+
+    Args:
+        stream: XML content as string, bytes, file object, or Path
+        attr_prefix: Prefix for attribute keys
+        text_key: Key for text next to attributes or child elements
+        force_list: Element names that are always lists, even when they
+            appear once
+        typed: Resolve text like plain YAML scalars (8080 becomes an
+            int); with False every value is a string
+
+    Returns:
+        YAML text
+
+    Raises:
+        YAMLError: For malformed XML, with the line number
+
+    Example:
+        >>> print(xml_to_yaml('<server id="web"><port>8080</port></server>'))
+        server:
+          '@id': web
+          port: 8080
+    """
+    try:
+        content = _read_stream(stream)
+        return _rustyyaml.xml_to_yaml(
+            content, attr_prefix, text_key, force_list, typed
+        )
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def list_variables(
+    stream: Union[str, bytes, IO, os.PathLike], syntax: str = "dollar"
+) -> List[Tuple[str, int, int]]:
+    """
+    List the template placeholders used in scalar values
+
+    Useful for checking that every variable a config needs is defined
+    before rendering it. Placeholders in mapping keys are not reported.
+
+    Args:
+        stream: YAML content as string, bytes, file object, or Path
+        syntax: "dollar" for ${VAR} / ${VAR:-default} ($${VAR} is an
+            escaped literal), or "gotpl" for Go/Helm templates, which
+            reports field references such as .Values.image.tag. Go
+            templates that are not valid YAML are scanned as plain text.
+
+    Returns:
+        List of (name, line, column) tuples (1-based) in source order
+
+    Raises:
+        YAMLError: If the YAML is malformed or syntax is unknown
+
+    Example:
+        >>> list_variables("image: ${REGISTRY}/app:${TAG:-latest}")
+        [('REGISTRY', 1, 8), ('TAG', 1, 24)]
+        >>> list_variables("tag: {{ .Values.image.tag }}", syntax="gotpl")
+        [('.Values.image.tag', 1, 9)]
+    """
+    try:
+        content = _read_stream(stream)
+        return _rustyyaml.list_variables(content, syntax)
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def validate(
+    stream: Union[str, bytes, IO, os.PathLike],
+    schema: Union[Dict[str, Any], str],
+    additional_properties: bool = True,
+    validators: Optional[Dict[str, Callable[[Any], Any]]] = None,
+) -> "Report":
+    """
+    Validate YAML against a schema
+
+    The schema is a JSON Schema subset: type, properties, required,
+    additionalProperties and items. Every document of the stream is
+    checked, and each finding points at the offending line.
+
+    Args:
+        stream: YAML content as string, bytes, file object, or Path
+        schema: The schema, as a dict, or the name of one registered with
+            register_schema
+        additional_properties: False rejects keys a schema does not
+            declare in every object that lists properties (as if each said
+            additionalProperties: false); unknown keys that look like a
+            typo of a declared one get a did-you-mean suggestion
+        validators: Business rules as a dict of key path pattern (as in
+            grep, e.g. "spec.containers[*].image") to a callable. Each is
+            called with every value the pattern matches and vetoes it by
+            returning False or an error message; None or True accepts it.
+            Schema checks run in Rust first, so only matching values are
+            converted to Python
+
+    Returns:
+        A Report with .passed (no errors), .errors, .warnings, .findings
+        and to_json(). Each Finding has path (JSON pointer of the offending
+        node, "/spec/ports/0/port"), 1-based line and column, severity
+        ("error" or "warning"), rule ("type", "required",
+        "additional-properties", "possible-typo", or the validator's
+        pattern) and message. Undeclared keys that look like a typo are
+        warnings even where allowed.
+
+    Raises:
+        YAMLError: If the YAML is malformed or the schema is invalid
+
+    Example:
+        >>> schema = {"properties": {"replicas": {"type": "integer"}}}
+        >>> report = validate("replica: 3", schema, additional_properties=False)
+        >>> report.passed
+        False
+        >>> for f in report.errors:
+        ...     print(f"::error line={f.line},col={f.column}::{f.message}")
+        ::error line=1,col=1::unexpected key 'replica' (did you mean ...
+
+        >>> def registry(image):
+        ...     if not image.startswith("registry.example.com/"):
+        ...         return f"{image} is not from the internal registry"
+        >>> validate(manifest, schema,
+        ...          validators={"spec.containers[*].image": registry})
+    """
+    try:
+        content = _read_stream(stream)
+        return _rustyyaml.validate(
+            content, schema, additional_properties, validators
+        )
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def register_schema(name: str, schema: Dict[str, Any]) -> None:
+    """
+    Compile a schema once and make it available by name
+
+    Validating many documents against the same schema compiles it on every
+    call; a registered schema is compiled a single time and shared.
+    validate() and the schema= load option accept the name in its place.
+
+    Args:
+        name: Name to refer to the schema by; registering a name again
+            replaces its schema
+        schema: The schema, as for validate()
+
+    Raises:
+        YAMLError: If the schema is invalid
+
+    Example:
+        >>> register_schema("deployment", schema)
+        >>> reports = [validate(text, "deployment") for text in manifests]
+    """
+    try:
+        _rustyyaml.register_schema(name, schema)
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def parse_quantity(value: str, milli: bool = False) -> int:
+    """
+    Parse a Kubernetes resource quantity
+
+    Args:
+        value: Quantity such as "128Mi", "500m", "1.5G" or "2e3"
+        milli: Return thousandths of a unit (CPU millicores)
+
+    Returns:
+        Integer number of units, rounded away from zero like Kubernetes
+
+    Raises:
+        ValueError: If value is not a valid quantity
+
+    Example:
+        >>> parse_quantity("128Mi")
+        134217728
+        >>> parse_quantity("0.5", milli=True)
+        500
+    """
+    return _rustyyaml.parse_quantity(value, milli)
+
+
+def redact(doc_or_yaml: Any, paths: Union[str, List[str]]) -> Any:
+    """
+    Mask the values at key paths with "***"
+
+    Useful for logging or sharing configs without their secrets. A matched
+    mapping or sequence is masked as a whole.
+
+    Args:
+        doc_or_yaml: YAML text (string, bytes, file object, or Path), or
+            already loaded data
+        paths: Key path or list of key paths in grep syntax, e.g.
+            "data.*" or "spec.containers[*].env[*].value"
+
+    Returns:
+        For YAML text, the same text with only the matched values replaced,
+        so comments and formatting are kept. For loaded data, a masked copy
+        (the original is not modified).
+
+    Raises:
+        YAMLError: If the YAML is malformed or a path is invalid
+
+    Example:
+        >>> print(redact("user: admin\npassword: hunter2  # rotate\n", "password"))
+        user: admin
+        password: "***"  # rotate
+        >>> redact({"env": [{"name": "TOKEN", "value": "abc"}]}, "env[*].value")
+        {'env': [{'name': 'TOKEN', 'value': '***'}]}
+    """
+    try:
+        if isinstance(doc_or_yaml, (str, bytes, os.PathLike)) or hasattr(
+            doc_or_yaml, "read"
+        ):
+            doc_or_yaml = _read_stream(doc_or_yaml)
+        return _rustyyaml.redact(doc_or_yaml, paths)
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def load_logging_config(
+    path: Union[str, os.PathLike], apply: bool = False
+) -> Dict[str, Any]:
+    """
+    Load a logging.config.dictConfig config from a YAML file
+
+    The config is checked before it is returned, so mistakes that
+    dictConfig only reports while building handlers (or never, for loggers
+    that are not used yet) fail early, each with its line: a missing
+    version, handlers without a class, unknown levels, and formatters,
+    filters or handlers that are referenced but not defined. References are
+    not checked in incremental configs.
+
+    Args:
+        path: YAML file
+        apply: Also pass the config to logging.config.dictConfig
+
+    Returns:
+        The config as a dict
+
+    Raises:
+        YAMLError: If the file can't be read or parsed, the config is
+            invalid (listing every problem), or dictConfig rejects it
+
+    Example:
+        >>> load_logging_config("logging.yaml", apply=True)
+        >>> load_logging_config("broken.yaml")
+        YAMLError: Invalid logging config broken.yaml:
+          /handlers/console/formatter (line 6, column 16): handler
+          'console' uses undefined formatter 'detailed'
+    """
+    try:
+        return _rustyyaml.load_logging_config(_fspath(path), apply)
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def generate_dataclasses(
+    yaml_or_dir: Union[str, os.PathLike],
+    root_name: str = "Config",
+    recursive: bool = False,
+    style: str = "dataclass",
+) -> str:
+    """
+    Generate typed Python classes from sample YAML
+
+    The structure of every sample document is merged: keys missing from
+    some samples or null in any become Optional[...] = None, values seen
+    with several types become a Union, and each mapping gets its own class
+    (emitted before the classes that use it). Keys that aren't identifiers
+    get a sanitized field name ("app-id" becomes app_id), with a
+    Field(alias=...) for pydantic. Review the result before relying on it:
+    it only knows what the samples show.
+
+    Args:
+        yaml_or_dir: YAML text (each document is a sample), or a directory
+            whose .yaml/.yml files are the samples
+        root_name: Class name for the top-level mapping
+        recursive: With a directory, include subdirectories
+        style: "dataclass" for @dataclass classes, "pydantic" for
+            pydantic BaseModel subclasses
+
+    Returns:
+        Python source text
+
+    Raises:
+        YAMLError: If a sample is malformed or not a mapping, or there are
+            no samples
+
+    Example:
+        >>> print(generate_dataclasses("name: web\\nport: 80\\n"))
+        # Generated by rustyyaml.generate_dataclasses from 1 document
+        from dataclasses import dataclass
+        <BLANKLINE>
+        <BLANKLINE>
+        @dataclass
+        class Config:
+            name: str
+            port: int
+    """
+    try:
+        if isinstance(yaml_or_dir, os.PathLike):
+            yaml_or_dir = Path(_fspath(yaml_or_dir))
+        return _rustyyaml.generate_dataclasses(
+            yaml_or_dir, root_name, recursive, style
+        )
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def extract_comments(
+    stream: Union[str, bytes, IO, os.PathLike]
+) -> List[Dict[str, Any]]:
+    """
+    Extract comments with their position and the path of the nearest node
+
+    Comments are found without a round-trip load. Each comment is
+    attributed to a node by JSON pointer. A trailing comment
+    ("port: 80  # deprecated") belongs to the last node starting on its
+    line. A comment on its own line belongs to the node right after it
+    (the deepest one, so a comment above spec.replicas is "/spec/replicas").
+
+    Args:
+        stream: YAML content as string, bytes, file object, or Path
+
+    Returns:
+        One dict per comment, in source order: text (after the #,
+        trimmed), line and column of the # (1-based), inline (code
+        precedes it on its line), document index, and path (JSON pointer,
+        or None when no node follows)
+
+    Raises:
+        YAMLError: If the YAML is malformed
+
+    Example:
+        >>> extract_comments("spec:\\n  # deprecated: use ports\\n  port: 80\\n")
+        [{'text': 'deprecated: use ports', 'line': 2, 'column': 3,
+          'inline': False, 'document': 0, 'path': '/spec/port'}]
+    """
+    try:
+        return _rustyyaml.extract_comments(_read_stream(stream))
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def detect_type(
+    stream: Union[str, bytes, IO, os.PathLike],
+) -> Tuple[Optional[str], Dict[str, Any]]:
+    """
+    Classify a document by structural fingerprints, without loading it
+
+    Only the outline of the first non-empty document is read (top-level
+    keys and a few levels below), so this is much cheaper than a load and
+    suits routing files to the right validator before parsing them.
+
+    Args:
+        stream: YAML content as string, bytes, file object, or Path
+
+    Returns:
+        (label, fields). label is "kubernetes" (apiVersion and kind),
+        "github-workflow" (on and jobs), "docker-compose" (services),
+        "ansible-playbook" (a list of plays with hosts), "ansible-tasks"
+        (a list of tasks using when, register, block, ...) or None. fields
+        holds what identifies the document: apiVersion, kind, name and
+        namespace for Kubernetes; name, on (triggers) and jobs for
+        workflows; version and services for Compose; hosts and names for
+        Ansible
+
+    Raises:
+        YAMLError: If the part of the YAML that was read is malformed
+
+    Example:
+        >>> detect_type("apiVersion: v1\\nkind: Service\\nmetadata: {name: web}\\n")
+        ('kubernetes', {'apiVersion': 'v1', 'kind': 'Service', 'name': 'web'})
+    """
+    try:
+        return _rustyyaml.detect_type(_read_stream(stream))
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def inspect_anchors(stream: Union[str, bytes, IO, os.PathLike]) -> Dict[str, Any]:
+    """
+    Report the anchors and aliases of YAML without loading it
+
+    The stream is only composed, never expanded, so this is safe and cheap
+    even for a "billion laughs" document: use it to audit third-party YAML
+    before accepting it.
+
+    Args:
+        stream: YAML content as string, bytes, file object, or Path; every
+            document is inspected
+
+    Returns:
+        A dict: anchors, a list with a dict per anchor definition in
+        document order (name, document index, 1-based line and column,
+        aliases referring to it, and size: nodes in its value with its own
+        aliases expanded); aliases, the total number of aliases; nodes, the
+        nodes as written (an alias counting as one); expanded_nodes, the
+        nodes once every alias is expanded; and expansion_factor,
+        expanded_nodes / nodes
+
+    Raises:
+        YAMLError: If the YAML is malformed or uses an undefined alias
+
+    Example:
+        >>> report = inspect_anchors("base: &b {cpu: 1}\nweb: *b\napi: *b\n")
+        >>> report["anchors"][0]["aliases"], round(report["expansion_factor"], 2)
+        (2, 1.44)
+    """
+    try:
+        return _rustyyaml.inspect_anchors(_read_stream(stream))
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def audit_tags(stream: Union[str, bytes, IO, os.PathLike]) -> List[Dict[str, Any]]:
+    """
+    List every tag in YAML without constructing anything
+
+    The stream is only composed, so any tag (unsafe ones included) is
+    reported instead of rejected: use it to inventory the custom tags in a
+    repository before deciding which to pass as allowed_tags.
+
+    Args:
+        stream: YAML content as string, bytes, file object, or Path; every
+            document is audited
+
+    Returns:
+        A dict per tagged node, in document order: tag (resolved, so
+        !!binary is "tag:yaml.org,2002:binary"), document index, 1-based
+        line and column, kind ("scalar", "sequence" or "mapping") and
+        unsafe (True for tags like !!python/object that safe_load always
+        rejects)
+
+    Raises:
+        YAMLError: If the YAML is malformed
+
+    Example:
+        >>> [t["tag"] for t in audit_tags("a: !Ref x\nb: !Sub [y]\n")]
+        ['!Ref', '!Sub']
+    """
+    try:
+        return _rustyyaml.audit_tags(_read_stream(stream))
+    except YAMLError:
+        raise
+    except Exception as e:
+        raise YAMLError(str(e))
+
+
+def metrics() -> Dict[str, Union[int, float]]:
+    """
+    Cumulative parser counters for this process
+
+    The counters are kept in Rust with atomics and only ever grow, so they
+    map directly onto Prometheus counters; sample them periodically and
+    export the values (or the differences between samples).
+
+    Returns:
+        Dict with keys:
+        - documents: documents parsed
+        - bytes: bytes of YAML parsed
+        - parse_time: seconds spent parsing, summed over threads
+        - cache_hits: batch-loaded files that reused the parse of an
+          identical file
+        - errors: parses that failed
+
+    Example:
+        >>> before = metrics()
+        >>> data = safe_load("a: 1")
+        >>> metrics()["documents"] - before["documents"]
+        1
+    """
+    return _rustyyaml.metrics()
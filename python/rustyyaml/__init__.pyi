@@ -1,60 +1,525 @@
 """Type stubs for RustyYAML"""
 
-from pathlib import Path
-from typing import IO, Any, List, Tuple, Union
+import os
+from typing import (
+    IO,
+    Any,
+    Callable,
+    Dict,
+    ItemsView,
+    Iterable,
+    Iterator,
+    KeysView,
+    List,
+    Mapping,
+    Optional,
+    Tuple,
+    Union,
+    ValuesView,
+)
 
-StreamType = Union[str, bytes, IO[str], IO[bytes], Path]
+from .events import Event
+from .nodes import Node
+
+PathType = Union[str, os.PathLike[str]]
+StreamType = Union[
+    str, bytes, bytearray, memoryview, IO[str], IO[bytes], os.PathLike[str]
+]
 
 class YAMLError(ValueError):
     """Base exception for YAML errors"""
-    ...
 
-def safe_load(stream: StreamType) -> Any:
+    line: Optional[int]
+    column: Optional[int]
+    # Set on errors returned by collect_errors=True
+    document: Optional[int]
+    path: Optional[str]
+
+class TabIndentationError(YAMLError):
+    """A tab character was used for indentation"""
+
+class IntegrityError(YAMLError):
+    """A file's checksum does not match the expected one"""
+
+    expected: str
+    actual: str
+
+class ResourceLimitError(YAMLError):
+    """The input is larger than a resource limit allows"""
+
+class Finding:
+    """A single validation finding"""
+
+    path: str
+    line: int
+    column: int
+    severity: str
+    rule: str
+    message: str
+
+class Report:
+    """Result of validate()"""
+
+    findings: List[Finding]
+    errors: List[Finding]
+    warnings: List[Finding]
+    passed: bool
+    def to_json(self) -> str: ...
+    def __len__(self) -> int: ...
+
+class CancellationToken:
+    """Cancels the batch loads it is passed to (cancel= option)"""
+
+    cancelled: bool
+    def cancel(self) -> None: ...
+
+class Namespace:
+    """A dict whose keys are also attributes (as_namespace= option)"""
+
+    def __init__(self, data: Optional[Dict[Any, Any]] = None) -> None: ...
+    def __getattr__(self, name: str) -> Any: ...
+    def __setattr__(self, name: str, value: Any) -> None: ...
+    def __delattr__(self, name: str) -> None: ...
+    def __getitem__(self, key: Any) -> Any: ...
+    def __setitem__(self, key: Any, value: Any) -> None: ...
+    def __delitem__(self, key: Any) -> None: ...
+    def __contains__(self, key: Any) -> bool: ...
+    def __len__(self) -> int: ...
+    def __iter__(self) -> Iterator[Any]: ...
+    def keys(self) -> KeysView[Any]: ...
+    def values(self) -> ValuesView[Any]: ...
+    def items(self) -> ItemsView[Any, Any]: ...
+    def get(self, key: Any, default: Any = None) -> Any: ...
+    def to_dict(self) -> Dict[Any, Any]: ...
+
+class SharedMapping:
+    """A read-only mapping in a document loaded with load_shm()"""
+
+    def __getitem__(self, key: Any) -> Any: ...
+    def __contains__(self, key: Any) -> bool: ...
+    def __len__(self) -> int: ...
+    def __iter__(self) -> Iterator[Any]: ...
+    def keys(self) -> List[Any]: ...
+    def values(self) -> List[Any]: ...
+    def items(self) -> List[Tuple[Any, Any]]: ...
+    def get(self, key: Any, default: Any = None) -> Any: ...
+    def to_dict(self) -> Dict[Any, Any]: ...
+
+class SharedSequence:
+    """A read-only sequence in a document loaded with load_shm()"""
+
+    def __getitem__(self, index: Union[int, slice]) -> Any: ...
+    def __len__(self) -> int: ...
+    def __iter__(self) -> Iterator[Any]: ...
+    def to_list(self) -> List[Any]: ...
+
+class Loader:
+    """Loads documents with options validated once"""
+
+    def __init__(self, safe: bool = True, **options: Any) -> None: ...
+    def load(self, stream: StreamType) -> Any: ...
+    def load_all(self, stream: StreamType) -> List[Any]: ...
+    def load_file(self, path: PathType) -> Any: ...
+
+class Dumper:
+    """Dumps documents with options validated once"""
+
+    def __init__(self, **options: Any) -> None: ...
+    def dump(
+        self,
+        data: Any,
+        stream: Optional[IO[str]] = None,
+        comments: Optional[Dict[str, str]] = None,
+    ) -> Optional[str]: ...
+    def dump_all(
+        self,
+        documents: Iterable[Any],
+        stream: Optional[IO[str]] = None,
+        headers: Optional[List[Optional[str]]] = None,
+    ) -> Optional[str]: ...
+    def dump_file(
+        self,
+        data: Any,
+        path: PathType,
+        make_dirs: bool = False,
+        comments: Optional[Dict[str, str]] = None,
+    ) -> None: ...
+    def add_representer(
+        self, data_type: type, representer: Callable[[Any], Any]
+    ) -> None: ...
+
+def safe_load(stream: StreamType, **options: Any) -> Any:
     """Parse YAML safely (no code execution)"""
     ...
 
-def unsafe_load(stream: StreamType) -> Any:
+def unsafe_load(stream: StreamType, **options: Any) -> Any:
     """Parse YAML without safety checks (DANGEROUS!)"""
     ...
 
-def load(stream: StreamType) -> Any:
+def load(stream: StreamType, **options: Any) -> Any:
     """Parse YAML (defaults to safe mode)"""
     ...
 
-def load_all(stream: StreamType) -> List[Any]:
+def load_all(stream: StreamType, **options: Any) -> List[Any]:
     """Parse multiple YAML documents from a single stream"""
     ...
 
-def load_all_unsafe(stream: StreamType) -> List[Any]:
+def load_all_iter(stream: StreamType, **options: Any) -> Iterator[Any]:
+    """Parse multiple YAML documents lazily, one per iteration"""
+    ...
+
+def safe_dump(
+    data: Any, stream: Optional[IO[str]] = None, **options: Any
+) -> Optional[str]:
+    """Serialize Python data to YAML"""
+    ...
+
+def dump(
+    data: Any, stream: Optional[IO[str]] = None, **options: Any
+) -> Optional[str]:
+    """Serialize Python data to YAML (alias for safe_dump)"""
+    ...
+
+def load_rt(stream: StreamType, **options: Any) -> Any:
+    """Parse YAML for editing; dump_rt() writes it back as it was"""
+    ...
+
+def dump_rt(data: Any, stream: Optional[IO[str]] = None) -> Optional[str]:
+    """Serialize data from load_rt(), rewriting only what changed"""
+    ...
+
+def update_file(
+    path: PathType, key_path: str, new_value: Any, **options: Any
+) -> None:
+    """Set the value at key_path in a YAML file, keeping everything else"""
+    ...
+
+def safe_dump_all(
+    documents: Iterable[Any],
+    stream: Optional[IO[str]] = None,
+    **options: Any,
+) -> Optional[str]:
+    """Serialize several documents to one ---separated YAML stream"""
+    ...
+
+def dump_all(
+    documents: Iterable[Any],
+    stream: Optional[IO[str]] = None,
+    **options: Any,
+) -> Optional[str]:
+    """Serialize several documents to one YAML stream (alias for safe_dump_all)"""
+    ...
+
+def load_all_unsafe(stream: StreamType, **options: Any) -> List[Any]:
     """Parse multiple YAML documents without safety checks"""
     ...
 
-def safe_load_file(path: Union[str, Path]) -> Any:
+def parse_lenient(
+    stream: StreamType, **options: Any
+) -> Tuple[List[Any], List[YAMLError]]:
+    """Parse YAML, recovering from localized syntax errors"""
+    ...
+
+def parse(stream: StreamType) -> Iterator[Event]:
+    """Parse YAML into a stream of PyYAML-compatible events"""
+    ...
+
+def compose(stream: StreamType, **options: Any) -> Optional[Node]:
+    """Compose a YAML document into PyYAML-compatible nodes"""
+    ...
+
+def compose_all(stream: StreamType, **options: Any) -> List[Node]:
+    """Compose every YAML document into PyYAML-compatible nodes"""
+    ...
+
+def safe_load_marked(stream: StreamType, **options: Any) -> Any:
+    """Parse YAML safely into rustyyaml.marked containers with .lc positions"""
+    ...
+
+def safe_load_with_sourcemap(
+    stream: StreamType, **options: Any
+) -> Tuple[Any, Dict[str, Tuple[int, int, Tuple[int, int]]]]:
+    """Parse YAML safely, returning (data, sourcemap)"""
+    ...
+
+def safe_load_file(
+    path: PathType,
+    sha256: Optional[str] = None,
+    streaming: bool = False,
+    **options: Any,
+) -> Any:
     """Load YAML from a file safely"""
     ...
 
-def load_all_file(path: Union[str, Path]) -> List[Any]:
+def unsafe_load_file(
+    path: PathType, streaming: bool = False, **options: Any
+) -> Any:
+    """Load YAML from a file without safety checks (DANGEROUS!)"""
+    ...
+
+def load_all_file(path: PathType, **options: Any) -> List[Any]:
     """Load multiple YAML documents from a file"""
     ...
 
-def safe_load_many(yaml_strings: List[str]) -> List[Any]:
+def safe_load_stdin(**options: Any) -> Any:
+    """Load YAML from standard input safely"""
+    ...
+
+def load_all_stdin(**options: Any) -> List[Any]:
+    """Load every YAML document from standard input"""
+    ...
+
+def dump_file(
+    data: Any, path: PathType, make_dirs: bool = False, **options: Any
+) -> None:
+    """Serialize Python data to a YAML file, written from Rust"""
+    ...
+
+def dump_all_file(
+    documents: Iterable[Any],
+    path: PathType,
+    make_dirs: bool = False,
+    **options: Any,
+) -> None:
+    """Serialize several documents to one ---separated YAML file"""
+    ...
+
+def add_representer(data_type: type, representer: Callable[[Any], Any]) -> None:
+    """Teach the dump functions to serialize a type"""
+    ...
+
+DocumentFilter = Union[Dict[str, Any], Callable[[Any], Any]]
+
+def safe_load_many(
+    yaml_strings: List[str], filter: Optional[DocumentFilter] = None, **options: Any
+) -> List[Any]:
     """Parse multiple YAML strings in parallel"""
     ...
 
-def unsafe_load_many(yaml_strings: List[str]) -> List[Any]:
+def unsafe_load_many(
+    yaml_strings: List[str], filter: Optional[DocumentFilter] = None, **options: Any
+) -> List[Any]:
     """Parse multiple YAML strings in parallel without safety checks"""
     ...
 
 def load_directory(
-    directory: Union[str, Path], recursive: bool = False
+    directory: PathType,
+    recursive: bool = False,
+    transform: Optional[Callable[[Any], Any]] = None,
+    filter: Optional[DocumentFilter] = None,
+    **options: Any,
 ) -> List[Tuple[str, Any]]:
     """Load all YAML files from a directory in parallel"""
     ...
 
 def load_directory_unsafe(
-    directory: Union[str, Path], recursive: bool = False
+    directory: PathType,
+    recursive: bool = False,
+    transform: Optional[Callable[[Any], Any]] = None,
+    filter: Optional[DocumentFilter] = None,
+    **options: Any,
 ) -> List[Tuple[str, Any]]:
     """Load all YAML files from a directory without safety checks"""
     ...
 
+def load_object_store(
+    url: str,
+    recursive: bool = False,
+    transform: Optional[Callable[[Any], Any]] = None,
+    filter: Optional[DocumentFilter] = None,
+    storage_options: Optional[Dict[str, str]] = None,
+    max_concurrency: int = 16,
+    **options: Any,
+) -> List[Tuple[str, Any]]:
+    """Load all YAML objects under an object store prefix in parallel"""
+    ...
+
+def load_git(
+    repo: PathType,
+    ref: str = "main",
+    path: PathType = "",
+    recursive: bool = False,
+    transform: Optional[Callable[[Any], Any]] = None,
+    filter: Optional[DocumentFilter] = None,
+    **options: Any,
+) -> List[Tuple[str, Any]]:
+    """Load all YAML files under a path of a git repository at a revision"""
+    ...
+
+def load_files_verified(
+    manifest: Mapping[PathType, str],
+    transform: Optional[Callable[[Any], Any]] = None,
+    filter: Optional[DocumentFilter] = None,
+    **options: Any,
+) -> List[Tuple[str, Any]]:
+    """Load the files of a checksum manifest, verifying each one's SHA-256"""
+    ...
+
+def load_archive(
+    path: PathType,
+    transform: Optional[Callable[[Any], Any]] = None,
+    filter: Optional[DocumentFilter] = None,
+    **options: Any,
+) -> List[Tuple[str, Any]]:
+    """Load every YAML member of a zip or tar archive in parallel"""
+    ...
+
+def dump_shm(doc: Any) -> str:
+    """Encode loaded data into shared memory; returns a handle for load_shm"""
+    ...
+
+def load_shm(handle: PathType) -> Any:
+    """Map a document written by dump_shm as lazily decoded read-only views"""
+    ...
+
+def grep(
+    directory: PathType, key_path: str, recursive: bool = False
+) -> List[Tuple[str, str, Any]]:
+    """Find the values at a key path in every YAML file of a directory"""
+    ...
+
+def index_keys(
+    directory: PathType, recursive: bool = False
+) -> Dict[str, List[Tuple[str, int, int]]]:
+    """Index every key path defined in a directory"""
+    ...
+
+def walk(
+    doc_or_yaml: Any, max_depth: Optional[int] = None, leaves_only: bool = False
+) -> List[Tuple[str, Any]]:
+    """List every value in a document with its key path"""
+    ...
+
+def map_scalars(
+    yaml_or_doc: Any,
+    fn: Callable[[Any], Any],
+    paths: Optional[Union[str, List[str]]] = None,
+) -> Any:
+    """Apply a callable to every scalar value (YAML text keeps its formatting)"""
+    ...
+
+def explode(
+    path: PathType,
+    out_dir: PathType,
+    name_template: str = "{kind}-{name}.yaml",
+) -> List[str]:
+    """Split a multi-document YAML file into one file per document"""
+    ...
+
+def bundle(
+    paths_or_dir: Union[PathType, List[PathType]],
+    out_path: PathType,
+    recursive: bool = False,
+) -> int:
+    """Concatenate YAML files into one multi-document bundle"""
+    ...
+
+def drift(
+    directory: PathType,
+    baseline: PathType,
+    recursive: bool = False,
+) -> List[Dict[str, Any]]:
+    """Compare a directory of YAML files against a baseline"""
+    ...
+
+def to_csv(
+    dir_or_docs: Union[PathType, List[Any]],
+    out_path: Optional[PathType] = None,
+    recursive: bool = False,
+) -> Union[str, int]:
+    """Flatten configs into CSV rows (file, document, key_path, value, type)"""
+    ...
+
+def to_dotenv(doc: Dict[str, Any], prefix: str = "APP_") -> str:
+    """Flatten a config into dotenv (.env) lines"""
+    ...
+
+def yaml_to_ini(stream: StreamType) -> str:
+    """Convert a YAML config to INI (configparser) format"""
+    ...
+
+def ini_to_yaml(stream: StreamType) -> str:
+    """Convert an INI config to YAML"""
+    ...
+
+def yaml_to_xml(
+    stream: StreamType,
+    root: str = "root",
+    attr_prefix: str = "@",
+    text_key: str = "#text",
+    list_item: Optional[str] = None,
+) -> str:
+    """Convert a YAML document to XML"""
+    ...
+
+def xml_to_yaml(
+    stream: StreamType,
+    attr_prefix: str = "@",
+    text_key: str = "#text",
+    force_list: Optional[List[str]] = None,
+    typed: bool = True,
+) -> str:
+    """Convert XML to YAML"""
+    ...
+
+def list_variables(
+    stream: StreamType, syntax: str = "dollar"
+) -> List[Tuple[str, int, int]]:
+    """List the template placeholders used in scalar values"""
+    ...
+
+def validate(
+    stream: StreamType,
+    schema: Union[Dict[str, Any], str],
+    additional_properties: bool = True,
+    validators: Optional[Dict[str, Callable[[Any], Any]]] = None,
+) -> Report:
+    """Validate YAML against a schema and per-path validator callbacks"""
+    ...
+
+def register_schema(name: str, schema: Dict[str, Any]) -> None:
+    """Compile a schema once and make it available by name"""
+    ...
+
+def parse_quantity(value: str, milli: bool = False) -> int:
+    """Parse a Kubernetes resource quantity"""
+    ...
+
+def redact(doc_or_yaml: Any, paths: Union[str, List[str]]) -> Any:
+    """Mask the values at key paths"""
+    ...
+
+def load_logging_config(path: PathType, apply: bool = False) -> Dict[str, Any]:
+    """Load a logging.config.dictConfig config from a YAML file"""
+    ...
+
+def generate_dataclasses(
+    yaml_or_dir: PathType,
+    root_name: str = "Config",
+    recursive: bool = False,
+    style: str = "dataclass",
+) -> str:
+    """Generate typed Python classes (dataclass or pydantic) from sample YAML"""
+    ...
+
+def extract_comments(stream: StreamType) -> List[Dict[str, Any]]:
+    """Extract comments with their position and the path of the nearest node"""
+    ...
+
+def detect_type(stream: StreamType) -> Tuple[Optional[str], Dict[str, Any]]:
+    """Classify a document (Kubernetes, workflow, Compose, Ansible) cheaply"""
+    ...
+
+def inspect_anchors(stream: StreamType) -> Dict[str, Any]:
+    """Count anchors, their aliases and the expansion factor, without loading"""
+    ...
+
+def audit_tags(stream: StreamType) -> List[Dict[str, Any]]:
+    """List every tag with its location and kind, without constructing"""
+    ...
+
+def metrics() -> Dict[str, Union[int, float]]:
+    """Cumulative parser counters (documents, bytes, parse_time, cache_hits, errors)"""
+    ...
+
 __version__: str
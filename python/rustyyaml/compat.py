@@ -9,19 +9,50 @@ Import this module to replace PyYAML with RustyYAML globally:
 This allows zero-code migration from PyYAML to RustyYAML.
 """
 
+import functools
 import sys
 import warnings
-from typing import Any
+from typing import Any, Callable
 
 # Import RustyAML
-from . import (
-    YAMLError,
-    __version__,
-    load,
-    load_all,
-    safe_load,
-    unsafe_load,
-)
+from . import YAMLError, __version__
+from . import events as _events
+from . import nodes as _nodes
+from . import add_representer as _add_representer
+from . import compose as _compose
+from . import compose_all as _compose_all
+from . import load as _load
+from . import load_all as _load_all
+from . import parse as _parse
+from . import safe_dump as _safe_dump
+from . import safe_dump_all as _safe_dump_all
+from . import safe_load as _safe_load
+from . import unsafe_load as _unsafe_load
+
+
+def _pyyaml_types(func: Callable) -> Callable:
+    """
+    Wrap a loader so it returns exactly the types PyYAML would
+
+    Code importing 'yaml' expects YAML 1.1 semantics (yes/no booleans,
+    datetime objects, bytes for !!binary), so the compat layer turns on
+    pyyaml_compat. The Loader argument is accepted and ignored.
+    """
+
+    @functools.wraps(func)
+    def wrapper(stream, Loader=None, **options: Any):
+        options.setdefault("pyyaml_compat", True)
+        return func(stream, **options)
+
+    return wrapper
+
+
+safe_load = _pyyaml_types(_safe_load)
+unsafe_load = _pyyaml_types(_unsafe_load)
+load = _pyyaml_types(_load)
+load_all = _pyyaml_types(_load_all)
+compose = _pyyaml_types(_compose)
+compose_all = _pyyaml_types(_compose_all)
 
 
 # PyYAML compatibility aliases
@@ -87,30 +118,27 @@ class BaseDumper:
     pass
 
 
-# Functions not yet implemented
-def dump(*args, **kwargs) -> str:
-    """Dump Python object to YAML (not implemented in v1)"""
-    raise NotImplementedError(
-        "dump() is not yet implemented in RustyAML v1. "
-        "Use PyYAML for YAML generation, or wait for RustyAML v2."
-    )
+def safe_dump(data, stream=None, Dumper=None, **kwds: Any):
+    """Dump plain Python data to YAML (the Dumper argument is ignored)"""
+    return _safe_dump(data, stream, **kwds)
 
 
-def dump_all(*args, **kwargs) -> str:
-    """Dump multiple documents to YAML (not implemented in v1)"""
-    raise NotImplementedError("dump_all() not yet implemented")
+def dump(data, stream=None, Dumper=None, **kwds: Any):
+    """Dump plain Python data to YAML, as safe_dump does"""
+    return _safe_dump(data, stream, **kwds)
 
 
-def safe_dump(*args, **kwargs) -> str:
-    """Safe dump to YAML (not implemented in v1)"""
-    raise NotImplementedError("safe_dump() not yet implemented")
+def safe_dump_all(documents, stream=None, Dumper=None, **kwds: Any):
+    """Dump documents to one YAML stream (the Dumper argument is ignored)"""
+    return _safe_dump_all(documents, stream, **kwds)
 
 
-def safe_dump_all(*args, **kwargs) -> str:
-    """Safe dump multiple documents (not implemented in v1)"""
-    raise NotImplementedError("safe_dump_all() not yet implemented")
+def dump_all(documents, stream=None, Dumper=None, **kwds: Any):
+    """Dump documents to one YAML stream, as safe_dump_all does"""
+    return _safe_dump_all(documents, stream, **kwds)
 
 
+# Functions not yet implemented
 def add_constructor(tag, constructor, Loader=None):
     """Add a constructor (not implemented)"""
     raise NotImplementedError(
@@ -119,9 +147,34 @@ def add_constructor(tag, constructor, Loader=None):
     )
 
 
+class _RepresenterDumper:
+    """
+    Stands in for the dumper passed to PyYAML representers
+
+    The represent_* methods return the plain data to dump rather than a
+    node, so the tag a representer asks for is not written.
+    """
+
+    @staticmethod
+    def represent_scalar(tag, value, style=None):
+        return value
+
+    @staticmethod
+    def represent_sequence(tag, sequence, flow_style=None):
+        return list(sequence)
+
+    @staticmethod
+    def represent_mapping(tag, mapping, flow_style=None):
+        return dict(mapping)
+
+    @staticmethod
+    def represent_data(data):
+        return data
+
+
 def add_representer(data_type, representer, Dumper=None):
-    """Add a representer (not implemented)"""
-    raise NotImplementedError("add_representer() not yet implemented")
+    """Add a PyYAML-style representer(dumper, data) (Dumper is ignored)"""
+    _add_representer(data_type, lambda data: representer(_RepresenterDumper, data))
 
 
 def add_implicit_resolver(tag, regexp, first, Loader=None, Dumper=None):
@@ -144,16 +197,6 @@ def add_multi_representer(data_type, multi_representer, Dumper=None):
     raise NotImplementedError("add_multi_representer() not yet implemented")
 
 
-def compose(stream, Loader=None):
-    """Compose a YAML document (not implemented)"""
-    raise NotImplementedError("compose() not yet implemented")
-
-
-def compose_all(stream, Loader=None):
-    """Compose all YAML documents (not implemented)"""
-    raise NotImplementedError("compose_all() not yet implemented")
-
-
 def emit(events, stream=None, Dumper=None, **kwargs):
     """Emit YAML events (not implemented)"""
     raise NotImplementedError("emit() not yet implemented")
@@ -175,8 +218,8 @@ def scan(stream, Loader=None):
 
 
 def parse(stream, Loader=None):
-    """Parse YAML events (not implemented)"""
-    raise NotImplementedError("parse() not yet implemented")
+    """Parse YAML into events (rustyyaml.events, mirroring yaml.events)"""
+    return _parse(stream)
 
 
 # Create a fake 'yaml' module
@@ -197,13 +240,13 @@ class YAMLModule:
     load = staticmethod(load)
     load_all = staticmethod(load_all)
 
-    # Dump functions (not implemented)
+    # Dump functions
     dump = staticmethod(dump)
     dump_all = staticmethod(dump_all)
     safe_dump = staticmethod(safe_dump)
     safe_dump_all = staticmethod(safe_dump_all)
 
-    # Constructor/representer functions (not implemented)
+    # Constructor/representer functions (only add_representer is implemented)
     add_constructor = staticmethod(add_constructor)
     add_representer = staticmethod(add_representer)
     add_implicit_resolver = staticmethod(add_implicit_resolver)
@@ -211,7 +254,7 @@ class YAMLModule:
     add_multi_constructor = staticmethod(add_multi_constructor)
     add_multi_representer = staticmethod(add_multi_representer)
 
-    # Low-level functions (not implemented)
+    # Low-level functions (only parse and compose are implemented)
     compose = staticmethod(compose)
     compose_all = staticmethod(compose_all)
     emit = staticmethod(emit)
@@ -220,6 +263,30 @@ class YAMLModule:
     scan = staticmethod(scan)
     parse = staticmethod(parse)
 
+    # Events, as yaml.events exports them at the top level
+    Mark = _events.Mark
+    Event = _events.Event
+    NodeEvent = _events.NodeEvent
+    CollectionStartEvent = _events.CollectionStartEvent
+    CollectionEndEvent = _events.CollectionEndEvent
+    StreamStartEvent = _events.StreamStartEvent
+    StreamEndEvent = _events.StreamEndEvent
+    DocumentStartEvent = _events.DocumentStartEvent
+    DocumentEndEvent = _events.DocumentEndEvent
+    AliasEvent = _events.AliasEvent
+    ScalarEvent = _events.ScalarEvent
+    SequenceStartEvent = _events.SequenceStartEvent
+    SequenceEndEvent = _events.SequenceEndEvent
+    MappingStartEvent = _events.MappingStartEvent
+    MappingEndEvent = _events.MappingEndEvent
+
+    # Nodes, as yaml.nodes exports them at the top level
+    Node = _nodes.Node
+    ScalarNode = _nodes.ScalarNode
+    CollectionNode = _nodes.CollectionNode
+    SequenceNode = _nodes.SequenceNode
+    MappingNode = _nodes.MappingNode
+
     # Classes
     YAMLObject = YAMLObject
     YAMLError = YAMLError
@@ -250,7 +317,7 @@ sys.modules["yaml"] = _yaml_module  # type: ignore
 # Warn the user
 warnings.warn(
     "PyYAML has been replaced with RustyYAML via rustyyaml.compat. "
-    "Some PyYAML features (dump, YAMLObject) are not yet supported.",
+    "Some PyYAML features (YAMLObject, constructors) are not yet supported.",
     UserWarning,
     stacklevel=2,
 )
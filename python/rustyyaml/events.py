@@ -0,0 +1,189 @@
+"""
+Event classes for rustyyaml.parse()
+
+Mirrors PyYAML's ``yaml.events`` (same class names, attributes and
+hierarchy), so linters and highlighters written against ``yaml.parse()``
+work unchanged. The events are produced by libyaml in Rust. Marks count
+characters and match those of PyYAML's libyaml-based CLoader;
+DocumentStartEvent.version and .tags (the %YAML and %TAG directives) are
+always None.
+
+    >>> [type(e).__name__ for e in rustyyaml.parse("[a]")][2:5]
+    ['SequenceStartEvent', 'ScalarEvent', 'SequenceEndEvent']
+"""
+
+from typing import Dict, Optional, Tuple
+
+
+class Mark:
+    """A position in the source (0-based, like PyYAML's yaml.Mark)"""
+
+    __slots__ = ("name", "index", "line", "column", "buffer", "pointer")
+
+    def __init__(
+        self,
+        name: str,
+        index: int,
+        line: int,
+        column: int,
+        buffer: Optional[str] = None,
+        pointer: Optional[int] = None,
+    ) -> None:
+        self.name = name
+        self.index = index
+        self.line = line
+        self.column = column
+        # Kept for PyYAML compatibility; the source isn't attached
+        self.buffer = buffer
+        self.pointer = pointer
+
+    def get_snippet(self, indent: int = 4, max_length: int = 75) -> Optional[str]:
+        return None
+
+    def __str__(self) -> str:
+        return '  in "%s", line %d, column %d' % (
+            self.name,
+            self.line + 1,
+            self.column + 1,
+        )
+
+
+class Event:
+    def __init__(
+        self, start_mark: Optional[Mark] = None, end_mark: Optional[Mark] = None
+    ) -> None:
+        self.start_mark = start_mark
+        self.end_mark = end_mark
+
+    def __repr__(self) -> str:
+        attributes = [
+            key
+            for key in ["anchor", "tag", "implicit", "value"]
+            if hasattr(self, key)
+        ]
+        arguments = ", ".join(
+            "%s=%r" % (key, getattr(self, key)) for key in attributes
+        )
+        return "%s(%s)" % (self.__class__.__name__, arguments)
+
+
+class NodeEvent(Event):
+    def __init__(
+        self,
+        anchor: Optional[str],
+        start_mark: Optional[Mark] = None,
+        end_mark: Optional[Mark] = None,
+    ) -> None:
+        self.anchor = anchor
+        self.start_mark = start_mark
+        self.end_mark = end_mark
+
+
+class CollectionStartEvent(NodeEvent):
+    def __init__(
+        self,
+        anchor: Optional[str],
+        tag: Optional[str],
+        implicit: bool,
+        start_mark: Optional[Mark] = None,
+        end_mark: Optional[Mark] = None,
+        flow_style: Optional[bool] = None,
+    ) -> None:
+        self.anchor = anchor
+        self.tag = tag
+        self.implicit = implicit
+        self.start_mark = start_mark
+        self.end_mark = end_mark
+        self.flow_style = flow_style
+
+
+class CollectionEndEvent(Event):
+    pass
+
+
+class StreamStartEvent(Event):
+    def __init__(
+        self,
+        start_mark: Optional[Mark] = None,
+        end_mark: Optional[Mark] = None,
+        encoding: Optional[str] = None,
+    ) -> None:
+        self.start_mark = start_mark
+        self.end_mark = end_mark
+        self.encoding = encoding
+
+
+class StreamEndEvent(Event):
+    pass
+
+
+class DocumentStartEvent(Event):
+    def __init__(
+        self,
+        start_mark: Optional[Mark] = None,
+        end_mark: Optional[Mark] = None,
+        explicit: Optional[bool] = None,
+        version: Optional[Tuple[int, int]] = None,
+        tags: Optional[Dict[str, str]] = None,
+    ) -> None:
+        self.start_mark = start_mark
+        self.end_mark = end_mark
+        self.explicit = explicit
+        self.version = version
+        self.tags = tags
+
+
+class DocumentEndEvent(Event):
+    def __init__(
+        self,
+        start_mark: Optional[Mark] = None,
+        end_mark: Optional[Mark] = None,
+        explicit: Optional[bool] = None,
+    ) -> None:
+        self.start_mark = start_mark
+        self.end_mark = end_mark
+        self.explicit = explicit
+
+
+class AliasEvent(NodeEvent):
+    pass
+
+
+class ScalarEvent(NodeEvent):
+    def __init__(
+        self,
+        anchor: Optional[str],
+        tag: Optional[str],
+        implicit: Tuple[bool, bool],
+        value: str,
+        start_mark: Optional[Mark] = None,
+        end_mark: Optional[Mark] = None,
+        style: Optional[str] = None,
+    ) -> None:
+        self.anchor = anchor
+        self.tag = tag
+        # (plain, quoted): whether the tag may be resolved from a plain or a
+        # non-plain scalar
+        self.implicit = implicit
+        self.value = value
+        self.start_mark = start_mark
+        self.end_mark = end_mark
+        # None for plain, or one of ' " | >
+        self.style = style
+
+
+class SequenceStartEvent(CollectionStartEvent):
+    pass
+
+
+class SequenceEndEvent(CollectionEndEvent):
+    pass
+
+
+class MappingStartEvent(CollectionStartEvent):
+    pass
+
+
+class MappingEndEvent(CollectionEndEvent):
+    pass
+
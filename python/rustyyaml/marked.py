@@ -0,0 +1,93 @@
+"""
+Position-carrying containers for marked=True
+
+Mirrors ruamel.yaml's conventions so lint plugins written against
+CommentedMap/CommentedSeq work unchanged: every mapping, sequence and string
+value has an ``lc`` attribute with 0-based ``line`` and ``col`` of where it
+starts, and containers record the positions of their keys and items.
+
+Scalar styles are recorded too, for linters that enforce a quoting policy:
+``lc.style`` of a string, and ``lc.key_style(k)``, ``lc.value_style(k)``
+and ``lc.item_style(i)`` for any scalar in a container. A style is one of
+"plain", "single-quoted", "double-quoted", "literal" or "folded" (None for
+collections and aliases).
+
+    >>> data = rustyyaml.safe_load("a:\\n  - x", marked=True)
+    >>> data["a"].lc.line, data["a"].lc.col
+    (1, 2)
+    >>> data.lc.key("a"), data.lc.value("a")
+    ((0, 0), (1, 2))
+    >>> data["a"].lc.item(0)
+    (1, 4)
+    >>> data["a"][0].lc.style, data.lc.value_style("a")
+    ('plain', None)
+"""
+
+from typing import Any, Dict, List, Optional, Tuple
+
+
+class LineCol:
+    """Source position of a node (0-based, like ruamel.yaml)"""
+
+    __slots__ = ("line", "col", "data", "style")
+
+    def __init__(
+        self,
+        line: int,
+        col: int,
+        data: Optional[Dict[Any, List[Any]]] = None,
+        style: Optional[str] = None,
+    ) -> None:
+        self.line = line
+        self.col = col
+        # Mappings: key -> [key_line, key_col, value_line, value_col,
+        #                   key_style, value_style]
+        # Sequences: index -> [line, col, style]
+        self.data = data if data is not None else {}
+        # How a string was written ("plain", "double-quoted", ...)
+        self.style = style
+
+    def key(self, k: Any) -> Tuple[int, int]:
+        """Position of mapping key ``k``"""
+        return self.data[k][0], self.data[k][1]
+
+    def value(self, k: Any) -> Tuple[int, int]:
+        """Position of the value stored under mapping key ``k``"""
+        return self.data[k][2], self.data[k][3]
+
+    def item(self, idx: int) -> Tuple[int, int]:
+        """Position of sequence item ``idx``"""
+        return self.data[idx][0], self.data[idx][1]
+
+    def key_style(self, k: Any) -> Optional[str]:
+        """Style of mapping key ``k``"""
+        return self.data[k][4]
+
+    def value_style(self, k: Any) -> Optional[str]:
+        """Style of the value stored under mapping key ``k``"""
+        return self.data[k][5]
+
+    def item_style(self, idx: int) -> Optional[str]:
+        """Style of sequence item ``idx``"""
+        return self.data[idx][2]
+
+    def __repr__(self) -> str:
+        return f"LineCol(line={self.line}, col={self.col})"
+
+
+class MarkedMap(dict):
+    """A dict with a source position (ruamel's CommentedMap.lc)"""
+
+    __slots__ = ("lc",)
+
+
+class MarkedSeq(list):
+    """A list with a source position (ruamel's CommentedSeq.lc)"""
+
+    __slots__ = ("lc",)
+
+
+class MarkedStr(str):
+    """A str with a source position"""
+
+    # str subclasses cannot have non-empty __slots__, so lc lives in __dict__
@@ -0,0 +1,100 @@
+"""
+Node classes for rustyyaml.compose()
+
+Mirrors PyYAML's ``yaml.nodes`` (same class names, attributes and
+hierarchy), so schema tooling that inspects node structure can switch over.
+Every node has its resolved ``tag``, ``start_mark`` and ``end_mark``
+(rustyyaml.events.Mark); scalars have ``style`` and collections
+``flow_style``. As in PyYAML, an alias is the very node object its anchor
+names. Unlike PyYAML, nodes also record the ``anchor`` they were defined
+with.
+
+    >>> node = rustyyaml.compose("ports: &p [80, 443]")
+    >>> key, value = node.value[0]
+    >>> value.tag, value.anchor, [item.value for item in value.value]
+    ('tag:yaml.org,2002:seq', 'p', ['80', '443'])
+"""
+
+from typing import Any, Optional
+
+from .events import Mark
+
+
+class Node:
+    def __init__(
+        self,
+        tag: str,
+        value: Any,
+        start_mark: Optional[Mark],
+        end_mark: Optional[Mark],
+        anchor: Optional[str] = None,
+    ) -> None:
+        self.tag = tag
+        self.value = value
+        self.start_mark = start_mark
+        self.end_mark = end_mark
+        self.anchor = anchor
+
+    def __repr__(self) -> str:
+        value = self.value
+        if isinstance(value, list):
+            if len(value) == 0:
+                value = "<empty>"
+            elif len(value) == 1:
+                value = "<1 item>"
+            else:
+                value = "<%d items>" % len(value)
+        else:
+            if len(value) > 75:
+                value = repr(value[:70] + " ... ")
+            else:
+                value = repr(value)
+        return "%s(tag=%r, value=%s)" % (self.__class__.__name__, self.tag, value)
+
+
+class ScalarNode(Node):
+    id = "scalar"
+
+    def __init__(
+        self,
+        tag: str,
+        value: str,
+        start_mark: Optional[Mark] = None,
+        end_mark: Optional[Mark] = None,
+        style: Optional[str] = None,
+        anchor: Optional[str] = None,
+    ) -> None:
+        self.tag = tag
+        self.value = value
+        self.start_mark = start_mark
+        self.end_mark = end_mark
+        # None for plain, or one of ' " | >
+        self.style = style
+        self.anchor = anchor
+
+
+class CollectionNode(Node):
+    def __init__(
+        self,
+        tag: str,
+        value: list,
+        start_mark: Optional[Mark] = None,
+        end_mark: Optional[Mark] = None,
+        flow_style: Optional[bool] = None,
+        anchor: Optional[str] = None,
+    ) -> None:
+        self.tag = tag
+        self.value = value
+        self.start_mark = start_mark
+        self.end_mark = end_mark
+        self.flow_style = flow_style
+        self.anchor = anchor
+
+
+class SequenceNode(CollectionNode):
+    id = "sequence"
+
+
+class MappingNode(CollectionNode):
+    # value is a list of (key_node, value_node) tuples
+    id = "mapping"
@@ -0,0 +1,45 @@
+"""
+Round-trip containers for rustyyaml.load_rt()
+
+load_rt() returns a mapping or sequence document as a CommentedMap or
+CommentedSeq (named after ruamel.yaml's): a dict or list that also
+remembers the text it was loaded from. dump_rt() compares the data with
+that text and rewrites only what changed, so comments, blank lines,
+quoting, key order and indentation survive an edit:
+
+    >>> config = rustyyaml.load_rt("# web tier\\nreplicas: 2  # min 2\\n")
+    >>> config["replicas"] = 3
+    >>> rustyyaml.dump_rt(config)
+    '# web tier\\nreplicas: 3  # min 2\\n'
+
+Only the document itself is one of these; the mappings and sequences in it
+are plain dicts and lists. ``styles`` maps the JSON pointer of each scalar
+as loaded to its style, for linters enforcing a quoting policy:
+
+    >>> rustyyaml.load_rt("a: 'x'\nb: [1, \"y\"]\n").styles
+    {'/a': 'single-quoted', '/b/0': 'plain', '/b/1': 'double-quoted'}
+"""
+
+from typing import Any, Dict
+
+
+class CommentedMap(dict):
+    """A dict that remembers the YAML it was loaded from"""
+
+    __slots__ = ("source", "options", "styles")
+
+    source: str
+    # The load options, used again to compare the data with the source
+    options: Dict[str, Any]
+    # JSON pointer -> "plain", "single-quoted", ... of each scalar as loaded
+    styles: Dict[str, str]
+
+
+class CommentedSeq(list):
+    """A list that remembers the YAML it was loaded from"""
+
+    __slots__ = ("source", "options", "styles")
+
+    source: str
+    options: Dict[str, Any]
+    styles: Dict[str, str]
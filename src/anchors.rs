@@ -0,0 +1,233 @@
+//! Audit the anchors and aliases of a YAML stream (`inspect_anchors`)
+//!
+//! Aliases are what make a "billion laughs" document small on disk and
+//! huge once loaded, so the report says how many nodes the stream has as
+//! written and how many it expands to. The stream is only composed: no
+//! alias is expanded, and sizes are added up (saturating) from each
+//! anchor's size, so inspecting a hostile document is cheap.
+
+use std::collections::HashMap;
+
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+
+use crate::compose::{self, Node, NodeKind};
+use crate::error::YAMLError;
+
+/// One definition of an anchor
+#[derive(Debug, PartialEq, Eq)]
+pub struct Anchor {
+    pub name: String,
+    /// Index of the document defining it
+    pub document: usize,
+    /// 0-based position of the anchored node
+    pub line: usize,
+    pub column: usize,
+    /// Aliases referring to this definition
+    pub aliases: usize,
+    /// Nodes in the anchored value with its own aliases expanded
+    pub size: u64,
+}
+
+/// What `inspect` found in a stream
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Report {
+    /// Every definition, in document order
+    pub anchors: Vec<Anchor>,
+    /// Nodes as written (an alias counts as one)
+    pub nodes: u64,
+    /// Nodes once every alias is expanded
+    pub expanded_nodes: u64,
+}
+
+impl Report {
+    /// How many times larger the stream gets when loaded
+    pub fn expansion_factor(&self) -> f64 {
+        if self.nodes == 0 {
+            1.0
+        } else {
+            self.expanded_nodes as f64 / self.nodes as f64
+        }
+    }
+}
+
+struct Inspector {
+    report: Report,
+    document: usize,
+    /// The definition in `report.anchors` each anchor name refers to now
+    current: HashMap<String, usize>,
+}
+
+impl Inspector {
+    /// Expanded size of `node`, recording its anchors and aliases
+    fn size(&mut self, node: &Node) -> Result<u64, YAMLError> {
+        self.report.nodes += 1;
+        let size = match &node.kind {
+            NodeKind::Alias { anchor } => {
+                let Some(&index) = self.current.get(anchor) else {
+                    return Err(YAMLError::parse(
+                        node.start.line + 1,
+                        node.start.column + 1,
+                        format!("found undefined alias '{}'", anchor),
+                    ));
+                };
+                let definition = &mut self.report.anchors[index];
+                definition.aliases += 1;
+                return Ok(definition.size);
+            }
+            NodeKind::Scalar { .. } => 1,
+            NodeKind::Sequence { items, .. } => {
+                let mut size: u64 = 1;
+                for item in items {
+                    size = size.saturating_add(self.size(item)?);
+                }
+                size
+            }
+            NodeKind::Mapping { pairs, .. } => {
+                let mut size: u64 = 1;
+                for (key, value) in pairs {
+                    size = size.saturating_add(self.size(key)?);
+                    size = size.saturating_add(self.size(value)?);
+                }
+                size
+            }
+        };
+
+        // Registered once complete: an alias inside its own anchor is
+        // undefined, as it is when loading
+        if let Some(name) = &node.anchor {
+            self.current.insert(name.clone(), self.report.anchors.len());
+            self.report.anchors.push(Anchor {
+                name: name.clone(),
+                document: self.document,
+                line: node.start.line,
+                column: node.start.column,
+                aliases: 0,
+                size,
+            });
+        }
+        Ok(size)
+    }
+}
+
+/// Count the anchors, aliases and expanded size of every document in
+/// `yaml_str`
+///
+/// # Errors
+/// * Parse errors, and undefined aliases
+pub fn inspect(yaml_str: &str) -> Result<Report, YAMLError> {
+    let mut inspector = Inspector {
+        report: Report::default(),
+        document: 0,
+        current: HashMap::new(),
+    };
+    for (index, document) in compose::compose_all(yaml_str)?.iter().enumerate() {
+        // Anchors don't carry over from one document to the next
+        inspector.current.clear();
+        inspector.document = index;
+        let size = inspector.size(&document.root)?;
+        inspector.report.expanded_nodes = inspector.report.expanded_nodes.saturating_add(size);
+    }
+    Ok(inspector.report)
+}
+
+/// Report the anchors and aliases of a YAML stream without loading it
+///
+/// # Arguments
+/// * `yaml_str` - YAML content; every document is inspected
+///
+/// # Returns
+/// A dict with:
+/// * `anchors` - one dict per anchor definition, in document order:
+///   `name`, `document` (index), `line` and `column` (1-based), `aliases`
+///   (how many aliases refer to it) and `size` (nodes in its value, its own
+///   aliases expanded)
+/// * `aliases` - total number of aliases
+/// * `nodes` - nodes as written, an alias counting as one
+/// * `expanded_nodes` - nodes once every alias is expanded
+/// * `expansion_factor` - `expanded_nodes / nodes` (1.0 for an empty stream)
+///
+/// # Errors
+/// * `YAMLError` for syntax errors and undefined aliases
+///
+/// # Example
+/// ```python
+/// report = rustyyaml.inspect_anchors(untrusted)
+/// if report["expansion_factor"] > 10:
+///     raise ValueError("too many aliases")
+/// ```
+#[pyfunction]
+pub fn inspect_anchors(py: Python, yaml_str: &str) -> PyResult<PyObject> {
+    let report = py.allow_threads(|| inspect(yaml_str))?;
+    let anchors = PyList::empty_bound(py);
+    let mut aliases = 0;
+    for anchor in &report.anchors {
+        aliases += anchor.aliases;
+        let entry = PyDict::new_bound(py);
+        entry.set_item("name", &anchor.name)?;
+        entry.set_item("document", anchor.document)?;
+        entry.set_item("line", anchor.line + 1)?;
+        entry.set_item("column", anchor.column + 1)?;
+        entry.set_item("aliases", anchor.aliases)?;
+        entry.set_item("size", anchor.size)?;
+        anchors.append(entry)?;
+    }
+    let result = PyDict::new_bound(py);
+    result.set_item("anchors", anchors)?;
+    result.set_item("aliases", aliases)?;
+    result.set_item("nodes", report.nodes)?;
+    result.set_item("expanded_nodes", report.expanded_nodes)?;
+    result.set_item("expansion_factor", report.expansion_factor())?;
+    Ok(result.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts() {
+        let report = inspect("base: &b {x: 1, y: [2, 3]}\nweb: *b\napi: *b\n").unwrap();
+        assert_eq!(
+            report.anchors,
+            [Anchor {
+                name: "b".to_string(),
+                document: 0,
+                line: 0,
+                column: 6,
+                aliases: 2,
+                size: 7,
+            }]
+        );
+        // The root, three keys, the anchored value and two aliases
+        assert_eq!(report.nodes, 1 + 3 + 7 + 2);
+        assert_eq!(report.expanded_nodes, 1 + 3 + 7 * 3);
+    }
+
+    #[test]
+    fn test_redefined_and_per_document() {
+        let report = inspect("- &a 1\n- *a\n- &a [x]\n- *a\n- *a\n---\n&a z\n").unwrap();
+        let counts: Vec<_> = report
+            .anchors
+            .iter()
+            .map(|anchor| (anchor.document, anchor.aliases, anchor.size))
+            .collect();
+        assert_eq!(counts, [(0, 1, 1), (0, 2, 2), (1, 0, 1)]);
+        assert!(inspect("a: &a 1\n---\nb: *a\n").is_err());
+        assert!(inspect("a: &a [*a]\n").is_err());
+    }
+
+    #[test]
+    fn test_billion_laughs() {
+        let mut source =
+            String::from("a0: &a0 [lol, lol, lol, lol, lol, lol, lol, lol, lol, lol]\n");
+        for level in 1..30 {
+            let aliases = vec![format!("*a{}", level - 1); 10].join(", ");
+            source.push_str(&format!("a{level}: &a{level} [{aliases}]\n"));
+        }
+        let report = inspect(&source).unwrap();
+        assert_eq!(report.expanded_nodes, u64::MAX);
+        assert!(report.expansion_factor() > 1e15);
+        assert_eq!(inspect("").unwrap().expansion_factor(), 1.0);
+    }
+}
@@ -0,0 +1,466 @@
+//! Load the YAML members of a zip or tar archive without extracting it
+//!
+//! `load_archive` reads the archive in Rust, picks out its `.yaml`/`.yml`
+//! members (Helm chart packages, CI artifact bundles) and parses them in
+//! parallel, like `load_directory` does for files. The format is detected
+//! from the content: zip, tar, or gzipped tar (`.tgz`, `.tar.gz`).
+//!
+//! Zip members may be stored or deflated; zip64 archives and encrypted
+//! members are rejected. Tar archives may use ustar, pax or GNU long names.
+//! `max_size_bytes` applies to each member: a gzipped tar is read a member
+//! at a time as it's inflated and a deflated zip member is inflated only up
+//! to the limit, so a small archive can't expand into a huge one in memory.
+
+use flate2::read::{DeflateDecoder, MultiGzDecoder};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use rayon::prelude::*;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use crate::batch;
+use crate::compression;
+use crate::error::YAMLError;
+use crate::filter::DocumentFilter;
+use crate::interrupt;
+use crate::limits::Limits;
+use crate::options::LoadOptions;
+
+const BLOCK: usize = 512;
+
+fn archive_error(path: &Path, message: impl std::fmt::Display) -> YAMLError {
+    YAMLError::Archive {
+        path: path.display().to_string(),
+        message: message.to_string(),
+    }
+}
+
+/// A member's bytes as stored in the archive
+#[derive(Debug)]
+enum Data {
+    Stored(Vec<u8>),
+    Deflated(Vec<u8>),
+    /// A tar member over `max_size_bytes`, skipped rather than read
+    Refused(YAMLError),
+}
+
+impl Data {
+    /// The member's content, inflated up to one byte past `max_size_bytes`
+    ///
+    /// # Errors
+    /// * `Limit` if it is (or inflates to) more than `max_size_bytes`
+    /// * `error(err)` if it doesn't inflate
+    fn extract(
+        self,
+        limits: &Limits,
+        error: impl FnOnce(io::Error) -> YAMLError,
+    ) -> Result<Vec<u8>, YAMLError> {
+        match self {
+            Data::Stored(bytes) => limits.check_size(bytes.len()).map(|()| bytes),
+            Data::Deflated(bytes) => limits.read_to_end(DeflateDecoder::new(&bytes[..]), error),
+            Data::Refused(err) => Err(err),
+        }
+    }
+}
+
+/// The YAML members of the archive at `path`, in archive order
+fn read_members(path: &Path, limits: &Limits) -> Result<Vec<(String, Data)>, YAMLError> {
+    let bytes = batch::read_bytes(path)?;
+    let members = if bytes.starts_with(b"PK\x03\x04") || bytes.starts_with(b"PK\x05\x06") {
+        zip_members(&bytes)
+    } else if bytes.starts_with(&[0x1f, 0x8b]) {
+        tar_members(MultiGzDecoder::new(&bytes[..]), limits)
+    } else {
+        tar_members(&bytes[..], limits)
+    };
+    let members = members.map_err(|message| archive_error(path, message))?;
+    Ok(members
+        .into_iter()
+        .filter(|(name, _)| compression::is_yaml_file(Path::new(name)))
+        .collect())
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> Result<usize, String> {
+    bytes
+        .get(at..at + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]) as usize)
+        .ok_or_else(|| "truncated".to_string())
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> Result<usize, String> {
+    bytes
+        .get(at..at + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+        .ok_or_else(|| "truncated".to_string())
+}
+
+fn slice(bytes: &[u8], at: usize, len: usize) -> Result<&[u8], String> {
+    at.checked_add(len)
+        .and_then(|end| bytes.get(at..end))
+        .ok_or_else(|| "truncated".to_string())
+}
+
+/// Files of a zip archive, found through its central directory
+fn zip_members(bytes: &[u8]) -> Result<Vec<(String, Data)>, String> {
+    // The end of central directory record is last, before a comment of up
+    // to 64 KiB
+    let earliest = bytes.len().saturating_sub(22 + 0xffff);
+    let end = (earliest..=bytes.len().saturating_sub(22))
+        .rev()
+        .find(|&at| bytes[at..].starts_with(b"PK\x05\x06"))
+        .ok_or("no zip end of central directory")?;
+    let count = u16_at(bytes, end + 10)?;
+    let mut at = u32_at(bytes, end + 16)?;
+    if count == 0xffff || at == 0xffff_ffff {
+        return Err("zip64 archives aren't supported".to_string());
+    }
+
+    let mut members = Vec::with_capacity(count);
+    for _ in 0..count {
+        if !slice(bytes, at, 4)?.starts_with(b"PK\x01\x02") {
+            return Err("bad zip central directory entry".to_string());
+        }
+        let flags = u16_at(bytes, at + 8)?;
+        let method = u16_at(bytes, at + 10)?;
+        let size = u32_at(bytes, at + 20)?;
+        let name_len = u16_at(bytes, at + 28)?;
+        let extra_len = u16_at(bytes, at + 30)?;
+        let comment_len = u16_at(bytes, at + 32)?;
+        let local = u32_at(bytes, at + 42)?;
+        let name = String::from_utf8_lossy(slice(bytes, at + 46, name_len)?).into_owned();
+        at += 46 + name_len + extra_len + comment_len;
+
+        if name.ends_with('/') || !compression::is_yaml_file(Path::new(&name)) {
+            continue;
+        }
+        if flags & 1 != 0 {
+            return Err(format!("{} is encrypted", name));
+        }
+        if !slice(bytes, local, 4)?.starts_with(b"PK\x03\x04") {
+            return Err(format!("bad zip local header for {}", name));
+        }
+        let start = local + 30 + u16_at(bytes, local + 26)? + u16_at(bytes, local + 28)?;
+        let data = slice(bytes, start, size)?.to_vec();
+        let data = match method {
+            0 => Data::Stored(data),
+            8 => Data::Deflated(data),
+            method => {
+                return Err(format!(
+                    "{} uses unsupported compression method {}",
+                    name, method
+                ))
+            }
+        };
+        members.push((name, data));
+    }
+    Ok(members)
+}
+
+/// An octal (or, for large values, base-256) tar header number
+fn tar_number(field: &[u8]) -> Result<usize, String> {
+    if field.first().is_some_and(|byte| byte & 0x80 != 0) {
+        return Ok(field[1..]
+            .iter()
+            .fold(0usize, |n, &byte| (n << 8) | byte as usize));
+    }
+    let digits = String::from_utf8_lossy(field);
+    let digits = digits.trim_matches(|c: char| c == '\0' || c == ' ');
+    if digits.is_empty() {
+        return Ok(0);
+    }
+    usize::from_str_radix(digits, 8).map_err(|_| "bad tar header number".to_string())
+}
+
+fn tar_string(field: &[u8]) -> String {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+/// The `path` record of a pax extended header, if it has one
+fn pax_path(records: &[u8]) -> Option<String> {
+    let records = String::from_utf8_lossy(records);
+    let mut rest = &*records;
+    while let Some((len, _)) = rest.split_once(' ') {
+        let record = rest.get(..len.parse().ok()?)?;
+        rest = &rest[record.len()..];
+        let (_, field) = record.split_once(' ')?;
+        if let Some(path) = field.trim_end_matches('\n').strip_prefix("path=") {
+            return Some(path.to_string());
+        }
+    }
+    None
+}
+
+/// Read `size` bytes of `reader`, or skip them if not `keep`
+fn tar_data(reader: &mut impl Read, size: usize, keep: bool) -> Result<Vec<u8>, String> {
+    let mut data = Vec::new();
+    let read = if keep {
+        reader.take(size as u64).read_to_end(&mut data)
+    } else {
+        io::copy(&mut reader.take(size as u64), &mut io::sink()).map(|read| read as usize)
+    };
+    match read.map_err(|e| e.to_string())? {
+        read if read < size => Err("truncated".to_string()),
+        _ => Ok(data),
+    }
+}
+
+/// Regular YAML files of a tar archive, read a header at a time: the data
+/// of other members, and of YAML members over `max_size_bytes`, is skipped
+fn tar_members(mut reader: impl Read, limits: &Limits) -> Result<Vec<(String, Data)>, String> {
+    let mut members = Vec::new();
+    // Name from a preceding pax or GNU long name header
+    let mut long_name = None;
+    // A short or zero block ends the archive, but only after a header: as
+    // the first block it means this isn't one
+    let mut started = false;
+    loop {
+        let mut header = Vec::with_capacity(BLOCK);
+        (&mut reader)
+            .take(BLOCK as u64)
+            .read_to_end(&mut header)
+            .map_err(|e| e.to_string())?;
+        if header.len() < BLOCK || header.iter().all(|&b| b == 0) {
+            if !started {
+                return Err("not a zip or tar archive".to_string());
+            }
+            break;
+        }
+        let checksum: usize = header
+            .iter()
+            .enumerate()
+            .map(|(i, &b)| if (148..156).contains(&i) { b' ' } else { b } as usize)
+            .sum();
+        if tar_number(&header[148..156]) != Ok(checksum) {
+            return Err("not a zip or tar archive".to_string());
+        }
+        started = true;
+        let size = tar_number(&header[124..136])?;
+        let refused = limits.check_size(size).err();
+        let padding = size.div_ceil(BLOCK) * BLOCK - size;
+
+        match header[156] {
+            b'x' | b'L' if refused.is_some() => return Err("header too large".to_string()),
+            b'x' => long_name = pax_path(&tar_data(&mut reader, size, true)?).or(long_name),
+            b'L' => long_name = Some(tar_string(&tar_data(&mut reader, size, true)?)),
+            b'0' | b'7' | 0 => {
+                let name = long_name.take().unwrap_or_else(|| {
+                    let name = tar_string(&header[..100]);
+                    let prefix = tar_string(&header[345..500]);
+                    if header[257..262] == *b"ustar" && !prefix.is_empty() {
+                        format!("{}/{}", prefix, name)
+                    } else {
+                        name
+                    }
+                });
+                let name = name.strip_prefix("./").unwrap_or(&name).to_string();
+                let wanted = compression::is_yaml_file(Path::new(&name));
+                let data = tar_data(&mut reader, size, wanted && refused.is_none())?;
+                if wanted {
+                    members.push((name, refused.map_or(Data::Stored(data), Data::Refused)));
+                }
+            }
+            _ => {
+                tar_data(&mut reader, size, false)?;
+                long_name = None;
+            }
+        }
+        // The last member's padding may be cut short
+        io::copy(&mut (&mut reader).take(padding as u64), &mut io::sink())
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(members)
+}
+
+/// Load every YAML member of a zip or tar archive in parallel
+///
+/// # Arguments
+/// * `path` - Path to a .zip, .tar, .tar.gz or .tgz archive (the format is
+///   detected from the content)
+/// * `transform`, `filter` - As for `load_directory`
+/// * `options` - Load options, applied to every member
+///
+/// # Returns
+/// List of `(member_name, data)` tuples in archive order; `(results,
+/// errors)` with `collect_errors=True`, where each error has `path` set to
+/// the member's name
+///
+/// # Errors
+/// * `YAMLError` if the archive can't be read or isn't a zip or tar archive
+///
+/// # Example
+/// ```python
+/// for name, manifest in rustyyaml.load_archive("nginx-15.1.0.tgz"):
+///     print(name, manifest.get("kind"))
+/// ```
+#[pyfunction]
+#[pyo3(signature = (path, transform=None, filter=None, **options))]
+pub fn load_archive(
+    py: Python,
+    path: PathBuf,
+    transform: Option<&Bound<'_, PyAny>>,
+    filter: Option<&Bound<'_, PyAny>>,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<PyObject> {
+    let options = LoadOptions::from_kwargs(options)?;
+    let filter = filter.map(DocumentFilter::from_py).transpose()?;
+    let filter = filter.as_ref();
+
+    let loaded = interrupt::run(py, options.cancel.as_deref(), |cancel| {
+        let members = read_members(&path, &options.limits)?;
+        let contents = members
+            .into_par_iter()
+            .map(|(name, data)| {
+                let member = PathBuf::from(name);
+                let content = data
+                    .extract(&options.limits, |e| {
+                        archive_error(&path, format!("{}: {}", member.display(), e))
+                    })
+                    .and_then(|bytes| compression::decompress(&member, bytes, &options.limits))
+                    .and_then(|bytes| batch::decode_file(&member, &bytes, options.encoding));
+                (member, content)
+            })
+            .collect();
+        Ok::<_, YAMLError>(batch::parse_deduplicated(
+            contents, cancel, filter, &options, true,
+        ))
+    });
+    let loaded = loaded.and_then(|loaded| loaded.map_err(PyErr::from));
+    let loaded = loaded.inspect_err(|err| {
+        // Best effort: the error is raised either way
+        let _ = err.value_bound(py).setattr("path", &path);
+    })?;
+
+    batch::convert_results(py, loaded, filter, transform, &options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::process::Command;
+
+    fn run(directory: &Path, program: &str, args: &[&str]) {
+        let status = Command::new(program)
+            .args(args)
+            .current_dir(directory)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    fn names(path: &Path) -> Vec<(String, Vec<u8>)> {
+        let limits = Limits::default();
+        read_members(path, &limits)
+            .unwrap()
+            .into_iter()
+            .map(|(name, data)| (name, data.extract(&limits, YAMLError::from).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_read_members() {
+        let directory =
+            std::env::temp_dir().join(format!("rustyyaml-archive-{}", std::process::id()));
+        let chart = directory.join("chart/templates");
+        fs::create_dir_all(&chart).unwrap();
+        fs::write(directory.join("chart/Chart.yaml"), "name: web\n").unwrap();
+        fs::write(chart.join("deploy.yml"), "kind: Deployment\n".repeat(50)).unwrap();
+        fs::write(chart.join("NOTES.txt"), "not yaml").unwrap();
+
+        let expected = vec![
+            ("chart/Chart.yaml".to_string(), b"name: web\n".to_vec()),
+            (
+                "chart/templates/deploy.yml".to_string(),
+                "kind: Deployment\n".repeat(50).into_bytes(),
+            ),
+        ];
+        run(
+            &directory,
+            "tar",
+            &["czf", "chart.tgz", "chart/Chart.yaml", "chart/templates"],
+        );
+        assert_eq!(names(&directory.join("chart.tgz")), expected);
+        run(
+            &directory,
+            "tar",
+            &["cf", "chart.tar", "chart/Chart.yaml", "chart/templates"],
+        );
+        assert_eq!(names(&directory.join("chart.tar")), expected);
+        run(
+            &directory,
+            "zip",
+            &["-qr", "chart.zip", "chart/Chart.yaml", "chart/templates"],
+        );
+        assert_eq!(names(&directory.join("chart.zip")), expected);
+
+        // Long names come from a pax (or GNU) header
+        let deep = format!("{}/x.yaml", "d".repeat(120));
+        fs::create_dir_all(directory.join("d".repeat(120))).unwrap();
+        fs::write(directory.join(&deep), "a: 1").unwrap();
+        run(
+            &directory,
+            "tar",
+            &["--format=pax", "-cf", "deep.tar", &deep],
+        );
+        assert_eq!(names(&directory.join("deep.tar"))[0].0, deep);
+        run(
+            &directory,
+            "tar",
+            &["--format=gnu", "-cf", "deep.tar", &deep],
+        );
+        assert_eq!(names(&directory.join("deep.tar"))[0].0, deep);
+
+        // Whatever its size, a file that isn't an archive is an error
+        for (name, content) in [
+            ("plain.yaml", "a: 1\n".repeat(200)),
+            ("small.yaml", "a: 1\n".to_string()),
+            ("empty.yaml", String::new()),
+            ("zeros.tar", "\0".repeat(1024)),
+        ] {
+            fs::write(directory.join(name), content).unwrap();
+            let err = read_members(&directory.join(name), &Limits::default()).unwrap_err();
+            assert!(matches!(err, YAMLError::Archive { .. }), "{}", name);
+        }
+        run(&directory, "gzip", &["-k", "small.yaml"]);
+        assert!(read_members(&directory.join("small.yaml.gz"), &Limits::default()).is_err());
+
+        // Each member is held to max_size_bytes, as inflated
+        let limits = Limits {
+            max_size_bytes: Some(100),
+            ..Default::default()
+        };
+        for archive in ["chart.tgz", "chart.zip"] {
+            let members = read_members(&directory.join(archive), &limits).unwrap();
+            let sizes: Vec<_> = members
+                .into_iter()
+                .map(|(_, data)| data.extract(&limits, YAMLError::from))
+                .collect();
+            assert_eq!(sizes[0].as_ref().unwrap(), b"name: web\n", "{}", archive);
+            assert!(
+                matches!(sizes[1], Err(YAMLError::Limit { .. })),
+                "{}",
+                archive
+            );
+        }
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_tar_number() {
+        assert_eq!(tar_number(b"0000644\0"), Ok(0o644));
+        assert_eq!(tar_number(b"     12 "), Ok(0o12));
+        assert_eq!(tar_number(b"\0\0\0\0"), Ok(0));
+        assert_eq!(tar_number(&[0x80, 0, 1, 0]), Ok(256));
+        assert!(tar_number(b"9").is_err());
+    }
+
+    #[test]
+    fn test_pax_path() {
+        assert_eq!(
+            pax_path(b"20 ctime=1700000000\n16 path=a/b.yml\n"),
+            Some("a/b.yml".to_string())
+        );
+        assert_eq!(pax_path(b"11 uid=100\n"), None);
+    }
+}
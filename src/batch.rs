@@ -6,14 +6,27 @@
 //! - Kubernetes: Load all manifests in a directory
 //! - Config validation: Check 100 config files at once
 
+use memmap2::Mmap;
 use pyo3::prelude::*;
+use pyo3::types::PyDict;
 use rayon::prelude::*;
-use std::fs;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
+use crate::compression::{self, Compression};
+use crate::encoding::{self, Decoded, Decoding};
 use crate::error::YAMLError;
-use crate::safe;
-use crate::types::yaml_to_python;
+use crate::filter::DocumentFilter;
+use crate::interrupt;
+use crate::limits::Limits;
+use crate::metrics;
+use crate::options::LoadOptions;
+use crate::parser::{self, Parsed};
+use crate::share::Sharing;
 
 /// Parse multiple YAML strings in parallel
 ///
@@ -22,9 +35,15 @@ use crate::types::yaml_to_python;
 ///
 /// # Arguments
 /// * `yaml_strings` - Vec of YAML content strings
+/// * `filter` - Keep only matching documents: a dict matcher such as
+///   `{"kind": "Deployment"}` (checked in Rust, see `filter::matches`) or a
+///   callable returning a truthy value for documents to keep
+/// * `options` - Load options, applied to every document
 ///
 /// # Returns
 /// * `Vec<PyObject>` - Parsed Python objects (same order as input)
+/// * With `collect_errors=True`: `(results, errors)`, where each error has
+///   `document` set to the index of the failing string
 ///
 /// # Errors
 /// * Returns error for the FIRST failed parse
@@ -32,58 +51,161 @@ use crate::types::yaml_to_python;
 ///
 /// # Example
 /// ```python
-/// import rustyaml
+/// import rustyyaml
 /// yamls = ["doc: 1", "doc: 2", "doc: 3"]
-/// results = rustyaml.safe_load_many(yamls)
+/// results = rustyyaml.safe_load_many(yamls)
 /// # Parses all 3 in parallel
 /// ```
 #[pyfunction]
-pub fn safe_load_many(py: Python, yaml_strings: Vec<String>) -> PyResult<Vec<PyObject>> {
+#[pyo3(signature = (yaml_strings, filter=None, **options))]
+pub fn safe_load_many(
+    py: Python,
+    yaml_strings: Vec<String>,
+    filter: Option<&Bound<'_, PyAny>>,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<PyObject> {
+    let options = LoadOptions::from_kwargs(options)?;
+    let filter = filter.map(DocumentFilter::from_py).transpose()?;
+    load_many(py, yaml_strings, filter.as_ref(), &options, true)
+}
+
+/// Parse multiple YAML strings in parallel without safety checks
+#[pyfunction]
+#[pyo3(signature = (yaml_strings, filter=None, **options))]
+pub fn unsafe_load_many(
+    py: Python,
+    yaml_strings: Vec<String>,
+    filter: Option<&Bound<'_, PyAny>>,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<PyObject> {
+    let options = LoadOptions::from_kwargs(options)?;
+    let filter = filter.map(DocumentFilter::from_py).transpose()?;
+    load_many(py, yaml_strings, filter.as_ref(), &options, false)
+}
+
+fn load_many(
+    py: Python,
+    yaml_strings: Vec<String>,
+    filter: Option<&DocumentFilter>,
+    options: &LoadOptions,
+    safe: bool,
+) -> PyResult<PyObject> {
+    options.check_no_files()?;
+    if options.collect_errors {
+        return load_many_collect(py, yaml_strings, filter, options, safe);
+    }
+
     // Parse all YAML strings in parallel using rayon
     // We collect into Results first, then convert to PyObjects
-    let parsed_values: Result<Vec<_>, YAMLError> = py.allow_threads(|| {
-        yaml_strings
-            .par_iter()
-            .map(|yaml_str| {
-                // Parse YAML to serde_yaml::Value (pure Rust, no GIL needed)
-                let value: serde_yaml::Value =
-                    serde_yaml::from_str(yaml_str).map_err(YAMLError::from)?;
+    let parsed: Result<Vec<Option<Parsed>>, YAMLError> =
+        interrupt::run(py, options.cancel.as_deref(), |cancel| {
+            yaml_strings
+                .par_iter()
+                // Parse YAML (pure Rust, no GIL needed)
+                .map(|yaml_str| parse_unless(cancel, yaml_str, filter, options, safe))
+                .collect()
+        })?;
 
-                // Check safety
-                safe::check_safety(&value)?;
+    // Now convert to Python objects (requires GIL)
+    let mut sharing = Sharing::for_options(py, options)?;
+    let mut results = Vec::new();
+    for parsed in parsed?.iter().flatten() {
+        results.extend(convert(
+            py,
+            parsed,
+            filter,
+            None,
+            sharing.as_mut(),
+            options,
+        )?);
+    }
+    Ok(results.into_py(py))
+}
 
-                Ok(value)
-            })
-            .collect()
-    });
+/// `load_many` in collect-errors mode: `(results, errors)`
+fn load_many_collect(
+    py: Python,
+    yaml_strings: Vec<String>,
+    filter: Option<&DocumentFilter>,
+    options: &LoadOptions,
+    safe: bool,
+) -> PyResult<PyObject> {
+    let parsed: Vec<Result<Option<Parsed>, YAMLError>> =
+        interrupt::run(py, options.cancel.as_deref(), |cancel| {
+            yaml_strings
+                .par_iter()
+                .map(|yaml_str| parse_unless(cancel, yaml_str, filter, options, safe))
+                .collect()
+        })?;
 
-    // Now convert to Python objects (requires GIL)
-    let values = parsed_values?;
-    values
-        .iter()
-        .map(|value| yaml_to_python(py, value))
-        .collect()
+    let mut sharing = Sharing::for_options(py, options)?;
+    let mut results = Vec::new();
+    let mut errors = Vec::new();
+    for (index, parsed) in parsed.into_iter().enumerate() {
+        let converted = parsed.map_err(PyErr::from).and_then(|parsed| match parsed {
+            Some(parsed) => convert(py, &parsed, filter, None, sharing.as_mut(), options),
+            None => Ok(None),
+        });
+        match converted {
+            Ok(obj) => results.extend(obj),
+            Err(err) => errors.push(parser::annotate_error(py, err, Some(index), None)?),
+        }
+    }
+    Ok((results, errors).into_py(py))
 }
 
-/// Parse multiple YAML strings in parallel without safety checks
-#[pyfunction]
-pub fn unsafe_load_many(py: Python, yaml_strings: Vec<String>) -> PyResult<Vec<PyObject>> {
-    let parsed_values: Result<Vec<_>, YAMLError> = py.allow_threads(|| {
-        yaml_strings
-            .par_iter()
-            .map(|yaml_str| {
-                let value: serde_yaml::Value =
-                    serde_yaml::from_str(yaml_str).map_err(YAMLError::from)?;
-                Ok(value)
-            })
-            .collect()
-    });
+/// Parse one document and apply the declarative part of `filter` (no GIL)
+///
+/// `Ok(None)` means the filter rejected the document.
+pub fn parse_filtered(
+    yaml_str: &str,
+    filter: Option<&DocumentFilter>,
+    options: &LoadOptions,
+    safe: bool,
+) -> Result<Option<Parsed>, YAMLError> {
+    let parsed = parser::parse_document(yaml_str, options, safe)?;
+    let rejected = filter.and_then(|filter| filter.check_parsed(&parsed)) == Some(false);
+    Ok((!rejected).then_some(parsed))
+}
 
-    let values = parsed_values?;
-    values
-        .iter()
-        .map(|value| yaml_to_python(py, value))
-        .collect()
+/// `parse_filtered`, or `Ok(None)` once the job was interrupted
+fn parse_unless(
+    cancel: &interrupt::Cancel,
+    yaml_str: &str,
+    filter: Option<&DocumentFilter>,
+    options: &LoadOptions,
+    safe: bool,
+) -> Result<Option<Parsed>, YAMLError> {
+    if cancel.is_set() {
+        return Ok(None);
+    }
+    parse_filtered(yaml_str, filter, options, safe)
+}
+
+/// Convert a document, then apply the Python side of `filter` and `transform`
+///
+/// `Ok(None)` means the filter rejected the document.
+fn convert<'py>(
+    py: Python<'py>,
+    parsed: &Parsed,
+    filter: Option<&DocumentFilter>,
+    transform: Option<&Bound<'py, PyAny>>,
+    sharing: Option<&mut Sharing<'py>>,
+    options: &LoadOptions,
+) -> PyResult<Option<PyObject>> {
+    let mut obj = parser::to_python(py, parsed, options)?;
+    if let Some(filter) = filter {
+        if !filter.is_declarative(parsed) && !filter.check_object(py, obj.bind(py))? {
+            return Ok(None);
+        }
+    }
+    if let Some(sharing) = sharing {
+        obj = sharing.share(obj.bind(py))?;
+    }
+    match transform {
+        Some(transform) => Ok(Some(transform.call1((obj,))?.unbind())),
+        None => Ok(Some(obj)),
+    }
 }
 
 /// Load all YAML files from a directory in parallel
@@ -91,112 +213,494 @@ pub fn unsafe_load_many(py: Python, yaml_strings: Vec<String>) -> PyResult<Vec<P
 /// # Arguments
 /// * `directory` - Path to directory containing .yaml/.yml files
 /// * `recursive` - If true, search subdirectories
+/// * `transform` - Optional callable applied to each parsed document; its
+///   return value replaces the document in the results
+/// * `filter` - Keep only matching files (see `safe_load_many`); applied
+///   before `transform`
+/// * `options` - Load options, applied to every file
 ///
 /// # Returns
 /// * List of (filename, parsed_data) tuples
+/// * With `file_info=True`: `(filename, parsed_data, info)` tuples, where
+///   `info` holds the file's `encoding`, whether it had a `bom`, its
+///   `size` in bytes and `parse_time` in seconds
+/// * With `collect_errors=True`: `(results, errors)`, where each error has
+///   `path` set to the failing file
+///
+/// Files may be UTF-8, UTF-16 or UTF-32, with or without a byte order mark.
 ///
 /// # Example
 /// ```python
-/// results = rustyaml.load_directory("./configs", recursive=True)
+/// results = rustyyaml.load_directory("./configs", recursive=True)
 /// for filename, data in results:
 ///     print(f"{filename}: {data}")
+///
+/// # Keep only what is needed
+/// images = rustyyaml.load_directory("./k8s", transform=lambda d: d["image"])
 /// ```
 #[pyfunction]
-#[pyo3(signature = (directory, recursive=false))]
+#[pyo3(signature = (directory, recursive=false, transform=None, filter=None, **options))]
 pub fn load_directory(
     py: Python,
-    directory: String,
+    directory: PathBuf,
     recursive: bool,
-) -> PyResult<Vec<(String, PyObject)>> {
-    let dir_path = Path::new(&directory);
-    if !dir_path.is_dir() {
-        return Err(YAMLError::FileNotFound { path: directory }.into());
-    }
+    transform: Option<&Bound<'_, PyAny>>,
+    filter: Option<&Bound<'_, PyAny>>,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<PyObject> {
+    let options = LoadOptions::from_kwargs(options)?;
+    let filter = filter.map(DocumentFilter::from_py).transpose()?;
+    let directory = DirectoryLoad {
+        directory,
+        recursive,
+        transform,
+        filter: filter.as_ref(),
+    };
+    load_directory_impl(py, directory, &options, true)
+}
 
-    // Collect all YAML files
-    let mut yaml_files = Vec::new();
-    collect_yaml_files(dir_path, recursive, &mut yaml_files)?;
+/// Load all YAML files from a directory without safety checks
+#[pyfunction]
+#[pyo3(signature = (directory, recursive=false, transform=None, filter=None, **options))]
+pub fn load_directory_unsafe(
+    py: Python,
+    directory: PathBuf,
+    recursive: bool,
+    transform: Option<&Bound<'_, PyAny>>,
+    filter: Option<&Bound<'_, PyAny>>,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<PyObject> {
+    let options = LoadOptions::from_kwargs(options)?;
+    let filter = filter.map(DocumentFilter::from_py).transpose()?;
+    let directory = DirectoryLoad {
+        directory,
+        recursive,
+        transform,
+        filter: filter.as_ref(),
+    };
+    load_directory_impl(py, directory, &options, false)
+}
+
+/// Arguments of `load_directory` other than the load options
+struct DirectoryLoad<'a, 'py> {
+    directory: PathBuf,
+    recursive: bool,
+    transform: Option<&'a Bound<'py, PyAny>>,
+    filter: Option<&'a DocumentFilter>,
+}
 
-    // Read and parse in parallel
-    let parsed_results: Result<Vec<_>, YAMLError> = py.allow_threads(|| {
-        yaml_files
+fn load_directory_impl(
+    py: Python,
+    request: DirectoryLoad,
+    options: &LoadOptions,
+    safe: bool,
+) -> PyResult<PyObject> {
+    let DirectoryLoad {
+        directory,
+        recursive,
+        transform,
+        filter,
+    } = request;
+    let yaml_files = find_yaml_files(&directory, recursive)?;
+
+    // Read and parse in parallel; Ctrl-C stops the remaining files
+    let loaded = interrupt::run(py, options.cancel.as_deref(), |cancel| {
+        let contents = yaml_files
             .par_iter()
             .map(|path| {
-                // Read file
-                let content = fs::read_to_string(path).map_err(|e| YAMLError::ParseError {
-                    line: 0,
-                    col: 0,
-                    message: format!("Failed to read {}: {}", path.display(), e),
-                })?;
+                let content = if cancel.is_set() {
+                    Ok(Decoded::default())
+                } else {
+                    read_decoded(path, options)
+                };
+                (path.clone(), content)
+            })
+            .collect();
+        parse_deduplicated(contents, cancel, filter, options, safe)
+    })?;
 
-                // Parse YAML
-                let value: serde_yaml::Value =
-                    serde_yaml::from_str(&content).map_err(YAMLError::from)?;
+    convert_results(py, loaded, filter, transform, options)
+}
 
-                // Check safety
-                safe::check_safety(&value)?;
+/// Passes failures to the `on_error=` callback as workers find them
+struct ErrorHook<'a> {
+    callback: &'a PyObject,
+    cancel: &'a interrupt::Cancel,
+    /// What the callback raised; the job stops at the first exception
+    raised: Mutex<Option<PyErr>>,
+}
 
-                Ok((path.to_string_lossy().to_string(), value))
-            })
-            .collect()
+impl ErrorHook<'_> {
+    /// Call back with `(path, error)`, the error annotated with its path
+    fn report(&self, path: &Path, err: &YAMLError) {
+        if self.cancel.is_set() {
+            return;
+        }
+        Python::with_gil(|py| {
+            let called = parser::annotate_error(py, err.clone().into(), None, Some(path))
+                .and_then(|err| self.callback.call1(py, (path, err)));
+            if let Err(err) = called {
+                let mut raised = self.raised.lock().unwrap_or_else(|err| err.into_inner());
+                raised.get_or_insert(err);
+                self.cancel.set();
+            }
+        });
+    }
+}
+
+/// Files parsed with each distinct content parsed once
+///
+/// Overlay trees often hold byte-identical copies of the same defaults;
+/// their paths share one parsed document (each still becomes its own
+/// Python object).
+pub struct Deduplicated {
+    /// `(path, index into documents)`, or the error for that path
+    pub files: Vec<(PathBuf, Result<usize, YAMLError>)>,
+    /// `None` where the filter rejected the document
+    pub documents: Vec<Option<Parsed>>,
+    /// Encoding, BOM and size of each file, `None` where it wasn't read
+    pub sources: Vec<Option<Decoded>>,
+    /// How long parsing each document took
+    pub parse_times: Vec<Duration>,
+    /// What the `on_error=` callback raised, if it did
+    pub callback_error: Option<PyErr>,
+}
+
+/// Parse `(path, content)` pairs in parallel, each distinct content once
+///
+/// Contents are skipped (as if rejected by the filter) once `cancel` is set.
+/// Read and parse errors go to the `on_error=` callback, if there is one,
+/// as soon as they are known.
+pub fn parse_deduplicated(
+    contents: Vec<(PathBuf, Result<Decoded, YAMLError>)>,
+    cancel: &interrupt::Cancel,
+    filter: Option<&DocumentFilter>,
+    options: &LoadOptions,
+    safe: bool,
+) -> Deduplicated {
+    let hook = options.on_error.as_deref().map(|callback| ErrorHook {
+        callback,
+        cancel,
+        raised: Mutex::new(None),
     });
+    let mut distinct: HashMap<&str, usize> = HashMap::new();
+    let mut unique: Vec<&str> = Vec::new();
+    // The paths that share each distinct content
+    let mut holders: Vec<Vec<&Path>> = Vec::new();
+    let indices: Vec<Option<usize>> = contents
+        .iter()
+        .map(|(path, content)| {
+            let content = match content {
+                Ok(content) => content.text.as_str(),
+                Err(err) => {
+                    if let Some(hook) = &hook {
+                        hook.report(path, err);
+                    }
+                    return None;
+                }
+            };
+            let index = *distinct.entry(content).or_insert_with(|| {
+                unique.push(content);
+                holders.push(Vec::new());
+                unique.len() - 1
+            });
+            holders[index].push(path);
+            Some(index)
+        })
+        .collect();
+
+    metrics::record_cache_hits(indices.iter().flatten().count() - unique.len());
+
+    let parsed: Vec<(Result<Option<Parsed>, YAMLError>, Duration)> = unique
+        .par_iter()
+        .zip(&holders)
+        .map(|(content, paths)| {
+            let start = Instant::now();
+            let result = parse_unless(cancel, content, filter, options, safe);
+            let elapsed = start.elapsed();
+            if let (Some(hook), Err(err)) = (&hook, &result) {
+                for path in paths {
+                    hook.report(path, err);
+                }
+            }
+            (result, elapsed)
+        })
+        .collect();
+    let mut documents = Vec::with_capacity(parsed.len());
+    let mut failures = Vec::with_capacity(parsed.len());
+    let mut parse_times = Vec::with_capacity(parsed.len());
+    for (result, elapsed) in parsed {
+        parse_times.push(elapsed);
+        match result {
+            Ok(document) => {
+                documents.push(document);
+                failures.push(None);
+            }
+            Err(err) => {
+                documents.push(None);
+                failures.push(Some(err));
+            }
+        }
+    }
 
-    // Convert to Python objects
-    let results = parsed_results?;
-    results
+    let mut sources = Vec::with_capacity(contents.len());
+    let files = contents
         .into_iter()
-        .map(|(path, value)| {
-            let py_obj = yaml_to_python(py, &value)?;
-            Ok((path, py_obj))
+        .zip(indices)
+        .map(|((path, content), index)| {
+            let result = match (content, index) {
+                (Err(err), _) => {
+                    sources.push(None);
+                    Err(err)
+                }
+                (Ok(mut source), Some(index)) => {
+                    // Only the metadata is kept; the text was parsed
+                    source.text = Default::default();
+                    sources.push(Some(source));
+                    match &failures[index] {
+                        Some(err) => Err(err.clone()),
+                        None => Ok(index),
+                    }
+                }
+                (Ok(_), None) => unreachable!("every readable file has an index"),
+            };
+            (path, result)
         })
-        .collect()
+        .collect();
+    Deduplicated {
+        files,
+        documents,
+        sources,
+        parse_times,
+        callback_error: hook.and_then(|hook| {
+            hook.raised
+                .into_inner()
+                .unwrap_or_else(|err| err.into_inner())
+        }),
+    }
 }
 
-/// Load all YAML files from a directory without safety checks
-#[pyfunction]
-#[pyo3(signature = (directory, recursive=false))]
-pub fn load_directory_unsafe(
-    py: Python,
-    directory: String,
-    recursive: bool,
-) -> PyResult<Vec<(String, PyObject)>> {
-    let dir_path = Path::new(&directory);
-    if !dir_path.is_dir() {
-        return Err(YAMLError::FileNotFound { path: directory }.into());
+/// Convert parsed files to `load_directory`'s result
+///
+/// Returns the `(path, document)` list (`(path, document, info)` with
+/// `file_info`), or `(results, errors)` in collect-errors mode. Errors
+/// found here (the filter or `transform` raising) go to the `on_error=`
+/// callback too; with one, failed files are left out instead of raised.
+pub fn convert_results<'py>(
+    py: Python<'py>,
+    loaded: Deduplicated,
+    filter: Option<&DocumentFilter>,
+    transform: Option<&Bound<'py, PyAny>>,
+    options: &LoadOptions,
+) -> PyResult<PyObject> {
+    if let Some(err) = loaded.callback_error {
+        return Err(err);
+    }
+    let mut sharing = Sharing::for_options(py, options)?;
+    // Convert to Python objects, then transform them in one pass under the GIL
+    let mut results: Vec<PyObject> = Vec::new();
+    let mut errors = Vec::new();
+    for ((path, index), source) in loaded.files.into_iter().zip(&loaded.sources) {
+        // Read and parse errors were passed to on_error already
+        let reported = index.is_err();
+        let converted = index.map_err(PyErr::from).and_then(|index| {
+            let obj = match &loaded.documents[index] {
+                Some(parsed) => convert(py, parsed, filter, transform, sharing.as_mut(), options)?,
+                None => None,
+            };
+            match (obj, source) {
+                (Some(obj), Some(source)) if options.file_info => {
+                    let info = file_info(py, source, loaded.parse_times[index])?;
+                    Ok(Some((&path, obj, info).into_py(py)))
+                }
+                (Some(obj), _) => Ok(Some((&path, obj).into_py(py))),
+                (None, _) => Ok(None),
+            }
+        });
+        match converted {
+            Ok(Some(result)) => results.push(result),
+            Ok(None) => {}
+            Err(err) if options.collect_errors || options.on_error.is_some() => {
+                let err = parser::annotate_error(py, err, None, Some(&path))?;
+                match &options.on_error {
+                    Some(on_error) if !reported => {
+                        on_error.call1(py, (&path, &err))?;
+                    }
+                    _ => {}
+                }
+                if options.collect_errors {
+                    errors.push(err);
+                }
+            }
+            Err(err) => return Err(err),
+        }
     }
 
-    let mut yaml_files = Vec::new();
-    collect_yaml_files(dir_path, recursive, &mut yaml_files)?;
+    if options.collect_errors {
+        Ok((results, errors).into_py(py))
+    } else {
+        Ok(results.into_py(py))
+    }
+}
 
-    let parsed_results: Result<Vec<_>, YAMLError> = py.allow_threads(|| {
-        yaml_files
-            .par_iter()
-            .map(|path| {
-                let content = fs::read_to_string(path).map_err(|e| YAMLError::ParseError {
-                    line: 0,
-                    col: 0,
-                    message: format!("Failed to read {}: {}", path.display(), e),
-                })?;
+/// The `info` dict of `file_info=True` results
+fn file_info(py: Python, source: &Decoded, parse_time: Duration) -> PyResult<PyObject> {
+    let info = PyDict::new_bound(py);
+    info.set_item("encoding", source.encoding.name())?;
+    info.set_item("bom", source.bom)?;
+    info.set_item("size", source.size)?;
+    info.set_item("parse_time", parse_time.as_secs_f64())?;
+    Ok(info.into_py(py))
+}
 
-                let value: serde_yaml::Value =
-                    serde_yaml::from_str(&content).map_err(YAMLError::from)?;
+/// Read a YAML file to a string, whichever encoding it uses
+pub fn read_file(path: &Path) -> Result<String, YAMLError> {
+    decode_file(path, &read_contents(path)?, Decoding::Detect)
+        .map(|decoded| decoded.text.into_string())
+}
 
-                Ok((path.to_string_lossy().to_string(), value))
-            })
-            .collect()
-    });
+/// Read and decode a YAML file as the `encoding=` and `use_mmap=` options
+/// say (compressed files are decompressed instead of mapped)
+///
+/// # Errors
+/// * `ParseError` if the file can't be read or decompressed
+/// * `Limit` if it is (or decompresses to) more than `max_size_bytes`,
+///   found before reading it whole
+/// * `DecodingError` if it isn't valid in the encoding used
+pub fn read_decoded(path: &Path, options: &LoadOptions) -> Result<Decoded, YAMLError> {
+    if options.use_mmap && Compression::of(path).is_none() {
+        return read_mapped(path, options);
+    }
+    decode_file(
+        path,
+        &compression::read(path, &options.limits)?,
+        options.encoding,
+    )
+}
 
-    let results = parsed_results?;
-    results
-        .into_iter()
-        .map(|(path, value)| {
-            let py_obj = yaml_to_python(py, &value)?;
-            Ok((path, py_obj))
-        })
-        .collect()
+/// Read standard input to its end and decode it as the `encoding=` option
+/// says
+///
+/// # Errors
+/// * `ParseError` if stdin can't be read
+/// * `DecodingError` if it isn't valid in the encoding used
+pub fn read_stdin(decoding: Decoding) -> Result<Decoded, YAMLError> {
+    let mut bytes = Vec::new();
+    io::stdin()
+        .lock()
+        .read_to_end(&mut bytes)
+        .map_err(|e| read_error(Path::new("<stdin>"), e))?;
+    decode_file(Path::new("<stdin>"), &bytes, decoding)
+}
+
+/// Read a file's bytes
+///
+/// # Errors
+/// * `ParseError` if the file can't be read
+pub fn read_bytes(path: &Path) -> Result<Vec<u8>, YAMLError> {
+    fs::read(path).map_err(|e| read_error(path, e))
+}
+
+/// Read a file's bytes, decompressed if its extension says so
+/// (`config.yaml.gz`, `.bz2`, `.xz`)
+///
+/// # Errors
+/// * `ParseError` if the file can't be read or decompressed
+pub fn read_contents(path: &Path) -> Result<Vec<u8>, YAMLError> {
+    compression::read(path, &Limits::default())
+}
+
+fn read_mapped(path: &Path, options: &LoadOptions) -> Result<Decoded, YAMLError> {
+    let map = map_file(path)?;
+    options
+        .limits
+        .check_size(map.as_ref().map_or(0, |map| map.len()))?;
+    decode_mapped_file(path, map, options.encoding)
+}
+
+/// Map a file read-only (`None` if it is empty: not every platform maps
+/// an empty file)
+///
+/// # Errors
+/// * `ParseError` if the file can't be opened or mapped
+pub fn map_file(path: &Path) -> Result<Option<Arc<Mmap>>, YAMLError> {
+    let file = File::open(path).map_err(|e| read_error(path, e))?;
+    if file.metadata().map_err(|e| read_error(path, e))?.len() == 0 {
+        return Ok(None);
+    }
+    // SAFETY: the mapping is read-only, and `use_mmap=True` is documented
+    // as requiring that the file isn't modified while it is loaded
+    let map = unsafe { Mmap::map(&file) }.map_err(|e| read_error(path, e))?;
+    Ok(Some(Arc::new(map)))
+}
+
+/// Decode a file mapped by `map_file`, UTF-8 in place; errors name the file
+pub fn decode_mapped_file(
+    path: &Path,
+    map: Option<Arc<Mmap>>,
+    decoding: Decoding,
+) -> Result<Decoded, YAMLError> {
+    match map {
+        Some(map) => encoding::decode_mapped(map, decoding).map_err(|err| name_file(path, err)),
+        None => decode_file(path, &[], decoding),
+    }
+}
+
+/// The error for failing to read `path`: `FileNotFound` if it doesn't
+/// exist, `Io` otherwise
+pub fn read_error(path: &Path, err: io::Error) -> YAMLError {
+    if err.kind() == io::ErrorKind::NotFound {
+        return YAMLError::FileNotFound {
+            path: path.display().to_string(),
+        };
+    }
+    YAMLError::Io {
+        action: "read",
+        path: path.display().to_string(),
+        message: err.to_string(),
+    }
+}
+
+/// The error for failing to write `path` (or create its directory)
+pub fn write_error(path: &Path, err: io::Error) -> YAMLError {
+    YAMLError::Io {
+        action: "write",
+        path: path.display().to_string(),
+        message: err.to_string(),
+    }
 }
 
-/// Helper: Recursively collect all .yaml and .yml files
+/// Decode the bytes read from `path`; errors name the file
+pub fn decode_file(path: &Path, bytes: &[u8], decoding: Decoding) -> Result<Decoded, YAMLError> {
+    encoding::decode_with(bytes, decoding).map_err(|err| name_file(path, err))
+}
+
+fn name_file(path: &Path, err: YAMLError) -> YAMLError {
+    match err {
+        YAMLError::DecodingError { message } => YAMLError::DecodingError {
+            message: format!("{}: {}", path.display(), message),
+        },
+        err => err,
+    }
+}
+
+/// All .yaml and .yml files (compressed or not) in `directory`
+///
+/// # Errors
+/// * `FileNotFound` if `directory` is not a directory
+pub fn find_yaml_files(directory: &Path, recursive: bool) -> Result<Vec<PathBuf>, YAMLError> {
+    if !directory.is_dir() {
+        return Err(YAMLError::FileNotFound {
+            path: directory.display().to_string(),
+        });
+    }
+
+    let mut yaml_files = Vec::new();
+    collect_yaml_files(directory, recursive, &mut yaml_files)?;
+    Ok(yaml_files)
+}
+
+/// Helper: Recursively collect all .yaml and .yml files (compressed or not)
 fn collect_yaml_files(
     dir: &Path,
     recursive: bool,
@@ -218,12 +722,8 @@ fn collect_yaml_files(
         let path = entry.path();
 
         if path.is_file() {
-            // Check if it's a YAML file
-            if let Some(ext) = path.extension() {
-                let ext_str = ext.to_string_lossy().to_lowercase();
-                if ext_str == "yaml" || ext_str == "yml" {
-                    files.push(path);
-                }
+            if compression::is_yaml_file(&path) {
+                files.push(path);
             }
         } else if path.is_dir() && recursive {
             collect_yaml_files(&path, recursive, files)?;
@@ -236,6 +736,8 @@ fn collect_yaml_files(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use pyo3::types::PyList;
+    use std::sync::Arc;
 
     #[test]
     fn test_parallel_loading() {
@@ -246,7 +748,10 @@ mod tests {
                 "doc: 3".to_string(),
             ];
 
-            let results = safe_load_many(py, yamls).unwrap();
+            let results: Vec<PyObject> = safe_load_many(py, yamls, None, None)
+                .unwrap()
+                .extract(py)
+                .unwrap();
             assert_eq!(results.len(), 3);
         });
     }
@@ -260,7 +765,10 @@ mod tests {
                 "value: third".to_string(),
             ];
 
-            let results = safe_load_many(py, yamls).unwrap();
+            let results: Vec<PyObject> = safe_load_many(py, yamls, None, None)
+                .unwrap()
+                .extract(py)
+                .unwrap();
             assert_eq!(results.len(), 3);
 
             // Results should be in the same order as input
@@ -280,17 +788,65 @@ mod tests {
                 "also_valid: yaml".to_string(),
             ];
 
-            let result = safe_load_many(py, yamls);
+            let result = safe_load_many(py, yamls, None, None);
             assert!(result.is_err());
         });
     }
 
+    #[test]
+    fn test_parse_deduplicated() {
+        let contents = vec![
+            (
+                PathBuf::from("a.yaml"),
+                encoding::decode_with(b"shared: 1", Decoding::Detect),
+            ),
+            (
+                PathBuf::from("b.yaml"),
+                encoding::decode_with(b"other: 2", Decoding::Detect),
+            ),
+            (
+                PathBuf::from("c.yaml"),
+                encoding::decode_with(b"shared: 1", Decoding::Detect),
+            ),
+            (
+                PathBuf::from("d.yaml"),
+                encoding::decode_with(b"bad: [", Decoding::Detect),
+            ),
+            (
+                PathBuf::from("e.yaml"),
+                encoding::decode_with(b"bad: [", Decoding::Detect),
+            ),
+            (
+                PathBuf::from("f.yaml"),
+                Err(YAMLError::invalid_number("x".to_string())),
+            ),
+        ];
+        let cancel = interrupt::Cancel::default();
+        let loaded = parse_deduplicated(contents, &cancel, None, &LoadOptions::default(), true);
+        // Each distinct content is parsed once
+        assert_eq!(loaded.documents.len(), 3);
+        let indices: Vec<Option<usize>> = loaded
+            .files
+            .iter()
+            .map(|(_, index)| index.as_ref().ok().copied())
+            .collect();
+        assert_eq!(indices, [Some(0), Some(1), Some(0), None, None, None]);
+        assert_eq!(loaded.parse_times.len(), 3);
+        assert_eq!(loaded.sources[1].as_ref().unwrap().size, 8);
+        assert!(loaded.sources[5].is_none());
+        // Every copy of a broken file reports the error
+        assert!(loaded.files[4].1.as_ref().unwrap_err().location().is_some());
+    }
+
     #[test]
     fn test_parallel_loading_empty_list() {
         Python::with_gil(|py| {
             let yamls: Vec<String> = vec![];
 
-            let results = safe_load_many(py, yamls).unwrap();
+            let results: Vec<PyObject> = safe_load_many(py, yamls, None, None)
+                .unwrap()
+                .extract(py)
+                .unwrap();
             assert_eq!(results.len(), 0);
         });
     }
@@ -310,7 +866,10 @@ mod tests {
                 .map(|i| format!("key_{}: value_{}", i, i))
                 .collect();
 
-            let results = safe_load_many(py, yamls).unwrap();
+            let results: Vec<PyObject> = safe_load_many(py, yamls, None, None)
+                .unwrap()
+                .extract(py)
+                .unwrap();
             assert_eq!(results.len(), 100);
         });
     }
@@ -324,8 +883,81 @@ mod tests {
                 "doc: 3".to_string(),
             ];
 
-            let results = unsafe_load_many(py, yamls).unwrap();
+            let results: Vec<PyObject> = unsafe_load_many(py, yamls, None, None)
+                .unwrap()
+                .extract(py)
+                .unwrap();
             assert_eq!(results.len(), 3);
         });
     }
+
+    #[test]
+    fn test_load_many_collect_errors() {
+        Python::with_gil(|py| {
+            let yamls = vec![
+                "valid: yaml".to_string(),
+                "invalid: [".to_string(),
+                "also_valid: yaml".to_string(),
+            ];
+            let options = PyDict::new_bound(py);
+            options.set_item("collect_errors", true).unwrap();
+
+            let result = safe_load_many(py, yamls, None, Some(&options)).unwrap();
+            let (results, errors): (Vec<PyObject>, Vec<PyObject>) = result.extract(py).unwrap();
+            assert_eq!(results.len(), 2);
+            assert_eq!(errors.len(), 1);
+            let document: usize = errors[0]
+                .bind(py)
+                .getattr("document")
+                .unwrap()
+                .extract()
+                .unwrap();
+            assert_eq!(document, 1);
+        });
+    }
+
+    #[test]
+    fn test_on_error_reports_each_path() {
+        Python::with_gil(|py| {
+            let failed = PyList::empty_bound(py);
+            let globals = PyDict::new_bound(py);
+            globals.set_item("failed", &failed).unwrap();
+            let callback = py
+                .eval_bound(
+                    "lambda path, error: failed.append(path)",
+                    Some(&globals),
+                    None,
+                )
+                .unwrap();
+            let options = LoadOptions {
+                on_error: Some(Arc::new(callback.unbind())),
+                ..Default::default()
+            };
+            let contents = vec![
+                (
+                    PathBuf::from("a.yaml"),
+                    encoding::decode_with(b"bad: [", Decoding::Detect),
+                ),
+                (
+                    PathBuf::from("b.yaml"),
+                    encoding::decode_with(b"ok: 1", Decoding::Detect),
+                ),
+                (
+                    PathBuf::from("c.yaml"),
+                    encoding::decode_with(b"bad: [", Decoding::Detect),
+                ),
+            ];
+            let cancel = interrupt::Cancel::default();
+            let loaded =
+                py.allow_threads(|| parse_deduplicated(contents, &cancel, None, &options, true));
+            assert!(loaded.callback_error.is_none());
+            let mut paths: Vec<String> = failed.extract().unwrap();
+            paths.sort();
+            assert_eq!(paths, ["a.yaml", "c.yaml"]);
+
+            // Failed files are left out of the results
+            let results = convert_results(py, loaded, None, None, &options).unwrap();
+            assert_eq!(results.bind(py).len().unwrap(), 1);
+        });
+    }
 }
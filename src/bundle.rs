@@ -0,0 +1,367 @@
+//! Split bundles into files and join files into bundles
+//!
+//! Documents are cut out of the source text at their `---` boundaries, so
+//! comments, quoting and formatting survive unchanged in both directions.
+//! `explode` names files from a template filled in with fields of each
+//! document; `bundle` adds whatever markers are needed to keep documents
+//! from different files apart.
+
+use pyo3::exceptions::PyTypeError;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::batch;
+use crate::compose::{self, Document, Node, NodeKind};
+use crate::error::YAMLError;
+use crate::search::{self, Segment};
+
+/// Source text of each document (comments before a `---` stay with the
+/// document above it)
+pub fn document_texts<'a>(yaml_str: &'a str, documents: &[Document]) -> Vec<&'a str> {
+    let mut starts: Vec<usize> = documents
+        .iter()
+        .enumerate()
+        .map(|(i, document)| match i {
+            0 => 0,
+            _ if document.explicit_start => document.start.index,
+            _ => documents[i - 1].end.index,
+        })
+        .collect();
+    starts.push(yaml_str.len());
+    starts.windows(2).map(|w| &yaml_str[w[0]..w[1]]).collect()
+}
+
+/// Fill in `{field}` placeholders from a document
+///
+/// Placeholders are key paths (`{metadata.labels.app}`); `{name}` and
+/// `{namespace}` fall back to `metadata.name`/`metadata.namespace`, and
+/// `{index}` is the document's position in the file.
+pub fn render_name(template: &str, root: &Node, index: usize) -> Result<String, String> {
+    let mut name = String::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        name.push_str(&rest[..open]);
+        let close = rest[open..]
+            .find('}')
+            .ok_or_else(|| format!("unclosed '{{' in name template '{}'", template))?;
+        let field = &rest[open + 1..open + close];
+        let value = if field == "index" {
+            index.to_string()
+        } else {
+            field_value(root, field)
+                .ok_or_else(|| format!("document {} has no '{}' field", index, field))?
+                .to_string()
+        };
+        name.push_str(&sanitize(&value));
+        rest = &rest[open + close + 1..];
+    }
+    name.push_str(rest);
+    Ok(name)
+}
+
+fn field_value<'n>(root: &'n Node, field: &str) -> Option<&'n str> {
+    let segments = search::parse_key_path(field)?;
+    lookup(root, &segments).or_else(|| match field {
+        "name" | "namespace" => {
+            let metadata = [Segment::Key("metadata".to_string()), segments[0].clone()];
+            lookup(root, &metadata)
+        }
+        _ => None,
+    })
+}
+
+/// Scalar at an exact key path (wildcards never match)
+fn lookup<'n>(node: &'n Node, segments: &[Segment]) -> Option<&'n str> {
+    let Some((segment, rest)) = segments.split_first() else {
+        return match &node.kind {
+            NodeKind::Scalar { value, .. } => Some(value),
+            _ => None,
+        };
+    };
+    match (segment, &node.kind) {
+        (Segment::Key(name), NodeKind::Mapping { pairs, .. }) => {
+            pairs.iter().rev().find_map(|(key, value)| match &key.kind {
+                NodeKind::Scalar { value: key, .. } if key == name => lookup(value, rest),
+                _ => None,
+            })
+        }
+        (Segment::Index(index), NodeKind::Sequence { items, .. }) => {
+            lookup(items.get(*index)?, rest)
+        }
+        _ => None,
+    }
+}
+
+/// Keep substituted values from escaping the output directory
+fn sanitize(value: &str) -> String {
+    if value == "." || value == ".." {
+        return "_".repeat(value.len());
+    }
+    value
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | '\0' => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// Is this document nothing but an empty scalar (e.g. a stray `---`)?
+fn is_empty(document: &Document) -> bool {
+    document.root.is_plain_untagged()
+        && matches!(&document.root.kind, NodeKind::Scalar { value, .. } if value.is_empty())
+}
+
+fn explode_file(path: &Path, out_dir: &Path, template: &str) -> Result<Vec<PathBuf>, YAMLError> {
+    let content = batch::read_file(path)?;
+    let documents = compose::compose_all(&content)?;
+    let texts = document_texts(&content, &documents);
+
+    // Work out every name before writing anything
+    let mut targets = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for (index, (document, text)) in documents.iter().zip(texts).enumerate() {
+        if is_empty(document) {
+            continue;
+        }
+        let name = render_name(template, &document.root, index)
+            .map_err(|message| YAMLError::parse(document.start.line + 1, 1, message))?;
+        if let Some(previous) = seen.insert(name.clone(), index) {
+            return Err(YAMLError::parse(
+                document.start.line + 1,
+                1,
+                format!(
+                    "documents {} and {} would both be written to '{}'",
+                    previous, index, name
+                ),
+            ));
+        }
+        targets.push((out_dir.join(name), text));
+    }
+
+    fs::create_dir_all(out_dir).map_err(|e| batch::write_error(out_dir, e))?;
+    let mut written = Vec::new();
+    for (target, text) in targets {
+        let mut text = text.to_string();
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+        fs::write(&target, text).map_err(|e| batch::write_error(&target, e))?;
+        written.push(target);
+    }
+    Ok(written)
+}
+
+/// Split a multi-document YAML file into one file per document
+///
+/// Each file gets the document's original text verbatim. Empty documents
+/// are skipped, and nothing is written if two documents would get the
+/// same name.
+///
+/// # Arguments
+/// * `path` - Multi-document YAML file
+/// * `out_dir` - Directory to write to (created if missing)
+/// * `name_template` - File name with `{field}` placeholders: key paths
+///   into the document, `{name}`/`{namespace}` (from `metadata`) and
+///   `{index}`
+///
+/// # Returns
+/// Paths of the written files, in document order
+///
+/// # Example
+/// ```python
+/// rustyyaml.explode("bundle.yaml", "manifests/")
+/// # ['manifests/Deployment-web.yaml', 'manifests/Service-web.yaml']
+/// ```
+#[pyfunction]
+#[pyo3(signature = (path, out_dir, name_template="{kind}-{name}.yaml"))]
+pub fn explode(
+    py: Python,
+    path: PathBuf,
+    out_dir: PathBuf,
+    name_template: &str,
+) -> PyResult<Vec<PathBuf>> {
+    Ok(py.allow_threads(|| explode_file(&path, &out_dir, name_template))?)
+}
+
+/// Does this document text carry `%` directives before its `---`?
+fn has_directives(text: &str) -> bool {
+    text.lines()
+        .take_while(|line| !line.starts_with("---"))
+        .any(|line| line.starts_with('%'))
+}
+
+/// Write the documents of `content` to `out`, each introduced by `---`
+///
+/// Returns the number of documents written.
+fn write_documents(
+    out: &mut impl Write,
+    content: &str,
+    documents: &[Document],
+    first: bool,
+) -> std::io::Result<usize> {
+    let mut count = 0;
+    for (document, text) in documents.iter().zip(document_texts(content, documents)) {
+        if is_empty(document) {
+            continue;
+        }
+        // Directives are only allowed after an explicit end of document
+        if has_directives(text) && !(first && count == 0) {
+            out.write_all(b"...\n")?;
+        }
+        if !document.explicit_start {
+            out.write_all(b"---\n")?;
+        }
+        out.write_all(text.as_bytes())?;
+        if !text.ends_with('\n') {
+            out.write_all(b"\n")?;
+        }
+        count += 1;
+    }
+    Ok(count)
+}
+
+fn bundle_files(files: &[PathBuf], out_path: &Path) -> Result<usize, YAMLError> {
+    // Validate everything before creating the output
+    files
+        .par_iter()
+        .map(|path| {
+            let content = batch::read_file(path)?;
+            compose::compose_all(&content)
+                .map(|_| ())
+                .map_err(|err| err.in_file(path))
+        })
+        .collect::<Result<(), YAMLError>>()?;
+
+    // Then stream the files one at a time
+    let file = File::create(out_path).map_err(|e| batch::write_error(out_path, e))?;
+    let mut out = BufWriter::new(file);
+    let mut count = 0;
+    for path in files {
+        let content = batch::read_file(path)?;
+        let documents = compose::compose_all(&content)?;
+        count += write_documents(&mut out, &content, &documents, count == 0)
+            .map_err(|e| batch::write_error(out_path, e))?;
+    }
+    out.flush().map_err(|e| batch::write_error(out_path, e))?;
+    Ok(count)
+}
+
+/// Concatenate YAML files into one multi-document bundle
+///
+/// Every input is parsed first, so a malformed file fails the call before
+/// the output is created. Documents are then copied verbatim, one file at a
+/// time, each starting with `---`; empty documents are dropped.
+///
+/// # Arguments
+/// * `paths_or_dir` - List of files, or a directory of .yaml/.yml files
+///   (taken in file name order)
+/// * `out_path` - Bundle to write (replaced if it exists)
+/// * `recursive` - With a directory, include subdirectories
+///
+/// # Returns
+/// Number of documents written
+///
+/// # Example
+/// ```python
+/// rustyyaml.bundle("manifests/", "release.yaml")
+/// rustyyaml.bundle(["crds.yaml", "app.yaml"], "release.yaml")
+/// ```
+#[pyfunction]
+#[pyo3(signature = (paths_or_dir, out_path, recursive=false))]
+pub fn bundle(
+    py: Python,
+    paths_or_dir: &Bound<'_, PyAny>,
+    out_path: PathBuf,
+    recursive: bool,
+) -> PyResult<usize> {
+    let files: Vec<PathBuf> = if let Ok(directory) = paths_or_dir.extract::<PathBuf>() {
+        let mut files = batch::find_yaml_files(&directory, recursive)?;
+        files.sort();
+        // Don't bundle a previous bundle written into the same directory
+        let out = fs::canonicalize(&out_path).ok();
+        files.retain(|file| out.is_none() || fs::canonicalize(file).ok() != out);
+        files
+    } else if let Ok(paths) = paths_or_dir.extract::<Vec<PathBuf>>() {
+        paths
+    } else {
+        return Err(PyTypeError::new_err(
+            "paths_or_dir must be a directory or a list of file paths",
+        ));
+    };
+
+    Ok(py.allow_threads(|| bundle_files(&files, &out_path))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BUNDLE: &str = "# web app\nkind: Deployment\nmetadata: {name: web}\n\
+                          ---\n# service\nkind: Service\nmetadata:\n  name: 'web'\n...\n\
+                          ---\nkind: ConfigMap\nmetadata: {name: cfg}\n";
+
+    #[test]
+    fn test_document_texts_verbatim() {
+        let documents = compose::compose_all(BUNDLE).unwrap();
+        let texts = document_texts(BUNDLE, &documents);
+        assert_eq!(texts.len(), 3);
+        assert_eq!(texts.concat(), BUNDLE);
+        assert!(texts[0].starts_with("# web app\n"));
+        assert!(texts[1].starts_with("---\n# service\n"));
+        assert!(texts[1].ends_with("...\n"));
+        assert!(texts[2].starts_with("---\nkind: ConfigMap"));
+    }
+
+    #[test]
+    fn test_render_name() {
+        let documents = compose::compose_all(BUNDLE).unwrap();
+        let name = |template, i: usize| render_name(template, &documents[i].root, i);
+        assert_eq!(
+            name("{kind}-{name}.yaml", 0).unwrap(),
+            "Deployment-web.yaml"
+        );
+        assert_eq!(name("{index}-{metadata.name}.yml", 1).unwrap(), "1-web.yml");
+        assert!(name("{namespace}.yaml", 0).is_err());
+        assert!(name("{kind.yaml", 0).is_err());
+    }
+
+    #[test]
+    fn test_write_documents_adds_markers() {
+        let mut out = Vec::new();
+        for (i, content) in ["a: 1\n", "# c\nb: 2", "--- [3]\n...\n---\n"]
+            .iter()
+            .enumerate()
+        {
+            let documents = compose::compose_all(content).unwrap();
+            write_documents(&mut out, content, &documents, i == 0).unwrap();
+        }
+        let bundle = String::from_utf8(out).unwrap();
+        assert_eq!(bundle, "---\na: 1\n---\n# c\nb: 2\n--- [3]\n...\n");
+        assert_eq!(compose::compose_all(&bundle).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_directives_get_an_end_marker() {
+        let mut out = Vec::new();
+        let first = "a: 1\n";
+        let second = "%YAML 1.1\n---\nb: 2\n";
+        for (i, content) in [first, second].iter().enumerate() {
+            let documents = compose::compose_all(content).unwrap();
+            write_documents(&mut out, content, &documents, i == 0).unwrap();
+        }
+        let bundle = String::from_utf8(out).unwrap();
+        assert_eq!(bundle, "---\na: 1\n...\n%YAML 1.1\n---\nb: 2\n");
+        assert_eq!(compose::compose_all(&bundle).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_sanitize() {
+        assert_eq!(sanitize("../etc/passwd"), ".._etc_passwd");
+        assert_eq!(sanitize(".."), "__");
+    }
+}
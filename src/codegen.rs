@@ -0,0 +1,522 @@
+//! Generate typed Python classes from sample YAML
+//!
+//! `generate_dataclasses` merges the structure of every sample document
+//! into one `Type` per position, then renders a class per mapping:
+//!
+//! * A key missing from some samples, or null in any, is `Optional[...] = None`
+//! * Values seen with several types become a `Union` (`int` and `float`
+//!   merge to `float`)
+//! * List items are merged across all lists at that position
+//! * Mappings with non-string keys become `Dict[Any, Any]`
+//!
+//! Classes are emitted children first, so no forward references are
+//! needed. Keys that are not Python identifiers get a sanitized field name
+//! (with a `Field(alias=...)` for pydantic, a comment for dataclasses).
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use serde_yaml::Value;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::PathBuf;
+
+use crate::batch;
+use crate::diff;
+use crate::error::YAMLError;
+use crate::options::LoadOptions;
+use crate::parser::{self, Parsed};
+
+/// What to generate
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Style {
+    Dataclass,
+    Pydantic,
+}
+
+impl Style {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "dataclass" => Some(Style::Dataclass),
+            "pydantic" => Some(Style::Pydantic),
+            _ => None,
+        }
+    }
+}
+
+/// Everything seen at one position of the samples
+#[derive(Debug, Default)]
+struct Type {
+    null: bool,
+    bool: bool,
+    int: bool,
+    float: bool,
+    str: bool,
+    /// Merged item type, if lists were seen
+    list: Option<Box<Type>>,
+    /// Merged fields, if mappings with string keys were seen
+    object: Option<Object>,
+    /// Mappings with non-string keys were seen
+    dict: bool,
+}
+
+impl Type {
+    fn observe(&mut self, value: &Value) {
+        match value {
+            Value::Null => self.null = true,
+            Value::Bool(_) => self.bool = true,
+            Value::Number(n) if n.is_f64() => self.float = true,
+            Value::Number(_) => self.int = true,
+            Value::String(_) => self.str = true,
+            Value::Sequence(items) => {
+                let item = self.list.get_or_insert_with(Default::default);
+                for value in items {
+                    item.observe(value);
+                }
+            }
+            Value::Mapping(map) if map.keys().all(Value::is_string) => self
+                .object
+                .get_or_insert_with(Default::default)
+                .observe(map),
+            Value::Mapping(_) => self.dict = true,
+            Value::Tagged(tagged) => self.observe(&tagged.value),
+        }
+    }
+
+    /// Was anything but null seen?
+    fn is_empty(&self) -> bool {
+        !(self.bool || self.int || self.float || self.str || self.dict)
+            && self.list.is_none()
+            && self.object.is_none()
+    }
+}
+
+/// Merged fields of the mappings seen at one position
+#[derive(Debug, Default)]
+struct Object {
+    samples: usize,
+    /// `(key, type, samples containing the key)` in first-seen order
+    fields: Vec<(String, Type, usize)>,
+    index: HashMap<String, usize>,
+}
+
+impl Object {
+    fn observe(&mut self, map: &serde_yaml::Mapping) {
+        self.samples += 1;
+        for (key, value) in map {
+            let Some(key) = key.as_str() else { continue };
+            let index = *self.index.entry(key.to_string()).or_insert_with(|| {
+                self.fields.push((key.to_string(), Type::default(), 0));
+                self.fields.len() - 1
+            });
+            let (_, ty, count) = &mut self.fields[index];
+            ty.observe(value);
+            *count += 1;
+        }
+    }
+}
+
+const KEYWORDS: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
+    "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+    "with", "yield",
+];
+
+/// A Python identifier for `key`: `app-name` becomes `app_name`
+fn identifier(key: &str) -> String {
+    let mut name: String = key
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    if KEYWORDS.contains(&name.as_str()) {
+        name.push('_');
+    }
+    name
+}
+
+/// A class name for `key`: `server-config` becomes `ServerConfig`
+fn class_name(key: &str) -> String {
+    key.split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            let first = chars.next().map(|c| c.to_uppercase().to_string());
+            first.unwrap_or_default() + chars.as_str()
+        })
+        .collect()
+}
+
+/// A double-quoted Python string literal
+fn string_literal(text: &str) -> String {
+    let mut out = String::from('"');
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\x{:02x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Renders classes for merged types
+struct Generator {
+    style: Style,
+    /// Rendered classes, children before parents
+    classes: Vec<String>,
+    names: HashSet<String>,
+    /// Names used from `typing`
+    typing: BTreeSet<&'static str>,
+    uses_field: bool,
+}
+
+impl Generator {
+    /// An unused class name for a mapping at `key` inside class `parent`
+    fn reserve(&mut self, key: &str, parent: &str) -> String {
+        let mut name = class_name(key);
+        if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+            name = format!("{}{}", parent, name);
+        }
+        if self.names.contains(&name) {
+            name = format!("{}{}", parent, name);
+        }
+        let base = name.clone();
+        let mut suffix = 2;
+        while self.names.contains(&name) {
+            name = format!("{}{}", base, suffix);
+            suffix += 1;
+        }
+        self.names.insert(name.clone());
+        name
+    }
+
+    /// Annotation for `ty` (without `Optional`), emitting classes it needs
+    fn annotation(&mut self, ty: &Type, key: &str, parent: &str) -> String {
+        let mut parts = Vec::new();
+        if let Some(object) = &ty.object {
+            let name = self.reserve(key, parent);
+            self.class(&name, object);
+            parts.push(name);
+        }
+        if let Some(item) = &ty.list {
+            let item = if item.is_empty() {
+                self.typing.insert("Any");
+                "Any".to_string()
+            } else {
+                let item_key = format!("{}Item", class_name(key));
+                self.optional(item, !item.null, &item_key, parent)
+            };
+            self.typing.insert("List");
+            parts.push(format!("List[{}]", item));
+        }
+        if ty.dict {
+            self.typing.insert("Dict");
+            self.typing.insert("Any");
+            parts.push("Dict[Any, Any]".to_string());
+        }
+        if ty.str {
+            parts.push("str".to_string());
+        }
+        if ty.float {
+            parts.push("float".to_string());
+        } else if ty.int {
+            parts.push("int".to_string());
+        }
+        if ty.bool {
+            parts.push("bool".to_string());
+        }
+        match parts.len() {
+            0 => {
+                self.typing.insert("Any");
+                "Any".to_string()
+            }
+            1 => parts.remove(0),
+            _ => {
+                self.typing.insert("Union");
+                format!("Union[{}]", parts.join(", "))
+            }
+        }
+    }
+
+    /// Annotation for `ty`, wrapped in `Optional` unless it is `required`
+    fn optional(&mut self, ty: &Type, required: bool, key: &str, parent: &str) -> String {
+        let annotation = self.annotation(ty, key, parent);
+        if required || annotation == "Any" {
+            annotation
+        } else {
+            self.typing.insert("Optional");
+            format!("Optional[{}]", annotation)
+        }
+    }
+
+    /// Render the class `name` for `object` (after the classes it uses)
+    fn class(&mut self, name: &str, object: &Object) {
+        let mut taken = HashSet::new();
+        let mut fields = Vec::new();
+        for (key, ty, count) in &object.fields {
+            let required = *count == object.samples && !ty.null;
+            let annotation = self.optional(ty, required, key, name);
+            let mut field = identifier(key);
+            let base = field.clone();
+            let mut suffix = 2;
+            while !taken.insert(field.clone()) {
+                field = format!("{}_{}", base, suffix);
+                suffix += 1;
+            }
+            let line = match (self.style, required, field == *key) {
+                (Style::Dataclass, true, true) => format!("{}: {}", field, annotation),
+                (Style::Dataclass, false, true) => format!("{}: {} = None", field, annotation),
+                (Style::Dataclass, true, false) => format!(
+                    "{}: {}  # YAML key: {}",
+                    field,
+                    annotation,
+                    string_literal(key)
+                ),
+                (Style::Dataclass, false, false) => format!(
+                    "{}: {} = None  # YAML key: {}",
+                    field,
+                    annotation,
+                    string_literal(key)
+                ),
+                (Style::Pydantic, true, true) => format!("{}: {}", field, annotation),
+                (Style::Pydantic, false, true) => format!("{}: {} = None", field, annotation),
+                (Style::Pydantic, true, false) => {
+                    self.uses_field = true;
+                    format!(
+                        "{}: {} = Field(alias={})",
+                        field,
+                        annotation,
+                        string_literal(key)
+                    )
+                }
+                (Style::Pydantic, false, false) => {
+                    self.uses_field = true;
+                    format!(
+                        "{}: {} = Field(None, alias={})",
+                        field,
+                        annotation,
+                        string_literal(key)
+                    )
+                }
+            };
+            fields.push((required, line));
+        }
+        // Dataclass fields without defaults must come first
+        if self.style == Style::Dataclass {
+            fields.sort_by_key(|(required, _)| !required);
+        }
+
+        let mut class = match self.style {
+            Style::Dataclass => format!("@dataclass\nclass {}:\n", name),
+            Style::Pydantic => format!("class {}(BaseModel):\n", name),
+        };
+        if fields.is_empty() {
+            class.push_str("    pass\n");
+        }
+        for (_, line) in fields {
+            class.push_str(&format!("    {}\n", line));
+        }
+        self.classes.push(class);
+    }
+}
+
+/// Python source for classes describing `samples`, the root named `root_name`
+///
+/// # Errors
+/// * If there are no samples, or a sample is not a mapping
+pub fn generate(samples: &[Value], root_name: &str, style: Style) -> Result<String, String> {
+    let mut root = Object::default();
+    for sample in samples {
+        match sample {
+            Value::Mapping(map) => root.observe(map),
+            Value::Null => {}
+            _ => return Err("every document must be a mapping".to_string()),
+        }
+    }
+    if root.samples == 0 {
+        return Err("no documents to infer classes from".to_string());
+    }
+
+    let mut generator = Generator {
+        style,
+        classes: Vec::new(),
+        names: HashSet::from([root_name.to_string()]),
+        typing: BTreeSet::new(),
+        uses_field: false,
+    };
+    generator.class(root_name, &root);
+
+    let mut out = format!(
+        "# Generated by rustyyaml.generate_dataclasses from {} document{}\n",
+        root.samples,
+        if root.samples == 1 { "" } else { "s" }
+    );
+    match style {
+        Style::Dataclass => out.push_str("from dataclasses import dataclass\n"),
+        Style::Pydantic if generator.uses_field => {
+            out.push_str("from pydantic import BaseModel, Field\n")
+        }
+        Style::Pydantic => out.push_str("from pydantic import BaseModel\n"),
+    }
+    if !generator.typing.is_empty() {
+        let names: Vec<&str> = generator.typing.into_iter().collect();
+        out.push_str(&format!("from typing import {}\n", names.join(", ")));
+    }
+    for class in generator.classes {
+        out.push_str("\n\n");
+        out.push_str(&class);
+    }
+    Ok(out)
+}
+
+/// Generate typed Python classes from sample YAML
+///
+/// # Arguments
+/// * `yaml_or_dir` - YAML text (every document is a sample) or a directory
+///   whose .yaml/.yml files are the samples
+/// * `root_name` - Name of the class for the top-level mapping
+/// * `recursive` - With a directory, include subdirectories
+/// * `style` - `"dataclass"` or `"pydantic"` (`BaseModel` subclasses)
+///
+/// # Returns
+/// Python source text: imports, then one class per mapping
+///
+/// # Example
+/// ```python
+/// source = rustyyaml.generate_dataclasses("configs/", root_name="AppConfig")
+/// Path("app_config.py").write_text(source)
+/// ```
+#[pyfunction]
+#[pyo3(signature = (yaml_or_dir, root_name="Config", recursive=false, style="dataclass"))]
+pub fn generate_dataclasses(
+    py: Python,
+    yaml_or_dir: &Bound<'_, PyAny>,
+    root_name: &str,
+    recursive: bool,
+    style: &str,
+) -> PyResult<String> {
+    let style = Style::from_name(style).ok_or_else(|| {
+        PyValueError::new_err(format!(
+            "style must be 'dataclass' or 'pydantic', not '{}'",
+            style
+        ))
+    })?;
+    if identifier(root_name) != root_name {
+        return Err(PyValueError::new_err(format!(
+            "root_name '{}' is not a Python identifier",
+            root_name
+        )));
+    }
+
+    // A string is a directory if one exists; any other path-like always is
+    let text: Option<String> = yaml_or_dir.extract().ok();
+    let directory = match &text {
+        Some(text) => Some(PathBuf::from(text)).filter(|path| path.is_dir()),
+        None => Some(yaml_or_dir.extract::<PathBuf>()?),
+    };
+
+    let samples: Vec<Value> = py.allow_threads(|| {
+        if let Some(directory) = &directory {
+            let mut files = batch::find_yaml_files(directory, recursive)?;
+            files.sort();
+            let documents: Result<Vec<Vec<Value>>, YAMLError> = files
+                .par_iter()
+                .map(|path| diff::read_documents(path))
+                .collect();
+            Ok::<_, YAMLError>(documents?.into_iter().flatten().collect())
+        } else {
+            let text = text.as_deref().unwrap_or_default();
+            let documents = parser::parse_documents(text, &LoadOptions::default(), true)?;
+            Ok(documents
+                .into_iter()
+                .filter_map(|document| match document {
+                    Parsed::Value(value) => Some(value),
+                    Parsed::Composed(_) => None,
+                })
+                .collect())
+        }
+    })?;
+
+    generate(&samples, root_name, style).map_err(PyValueError::new_err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samples(yaml: &str) -> Vec<Value> {
+        serde_yaml::Deserializer::from_str(yaml)
+            .map(|document| serde::Deserialize::deserialize(document).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_generate_dataclasses() {
+        let docs = samples(
+            "name: web\nport: 80\ndatabase: {host: db, ratio: 1}\ntags: [a]\n\
+             ---\nname: api\nport: 8080\ndatabase: {host: db2, ratio: 0.5}\ntags: []\n\
+             debug: true\napp-id: null\n",
+        );
+        let source = generate(&docs, "Config", Style::Dataclass).unwrap();
+        assert_eq!(
+            source,
+            "# Generated by rustyyaml.generate_dataclasses from 2 documents\n\
+             from dataclasses import dataclass\n\
+             from typing import Any, List, Optional\n\
+             \n\n\
+             @dataclass\n\
+             class Database:\n    host: str\n    ratio: float\n\
+             \n\n\
+             @dataclass\n\
+             class Config:\n\
+             \x20   name: str\n\
+             \x20   port: int\n\
+             \x20   database: Database\n\
+             \x20   tags: List[str]\n\
+             \x20   debug: Optional[bool] = None\n\
+             \x20   app_id: Any = None  # YAML key: \"app-id\"\n"
+        );
+    }
+
+    #[test]
+    fn test_generate_pydantic() {
+        let docs = samples("items:\n  - {id: 1, spec: {x: a}}\n  - {id: x}\nfor: 1\n");
+        let source = generate(&docs, "Root", Style::Pydantic).unwrap();
+        assert!(source.contains("from pydantic import BaseModel, Field\n"));
+        assert!(source.contains("from typing import List, Optional, Union\n"));
+        assert!(source.contains("class Spec(BaseModel):\n    x: str\n"));
+        assert!(source.contains("class ItemsItem(BaseModel):\n    id: Union[str, int]\n"));
+        assert!(source.contains("    spec: Optional[Spec] = None\n"));
+        assert!(
+            source.contains("    items: List[ItemsItem]\n    for_: int = Field(alias=\"for\")\n")
+        );
+    }
+
+    #[test]
+    fn test_names() {
+        assert_eq!(identifier("app-name"), "app_name");
+        assert_eq!(identifier("2fa"), "_2fa");
+        assert_eq!(identifier("class"), "class_");
+        assert_eq!(class_name("server-config"), "ServerConfig");
+        assert_eq!(class_name("apiVersion"), "ApiVersion");
+    }
+
+    #[test]
+    fn test_generate_errors() {
+        assert!(generate(&samples("[1, 2]"), "Config", Style::Dataclass).is_err());
+        assert!(generate(&[], "Config", Style::Dataclass).is_err());
+    }
+}
@@ -0,0 +1,307 @@
+//! Extract comments with their position and the node they describe
+//!
+//! libyaml drops comments, so they are found in the source text: a `#` at
+//! the start of a line or after whitespace starts a comment unless it lies
+//! inside a scalar (the composed node spans say where scalars are).
+//!
+//! Each comment is attributed to the nearest node, by JSON pointer:
+//!
+//! * A trailing comment (`port: 5432  # deprecated`) describes the last
+//!   node that starts on its line, so `/port`
+//! * A comment on its own line describes the node that follows it, the
+//!   deepest one starting there (`# deprecated` above `spec.replicas` is
+//!   about `/spec/replicas`, not `/spec`)
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::compose::{self, Node, NodeKind};
+use crate::error::YAMLError;
+use crate::events::{Mark, ScalarStyle};
+use crate::sourcemap;
+
+/// A comment and the node it belongs to
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Comment {
+    /// Text after the `#`, trimmed
+    pub text: String,
+    /// Position of the `#`
+    pub start: Mark,
+    /// Is there content before it on its line?
+    pub inline: bool,
+    /// Index of the document it belongs to
+    pub document: usize,
+    /// JSON pointer of the nearest node (`None` if there are no nodes)
+    pub pointer: Option<String>,
+}
+
+/// A node, positioned where its key (or sequence item) starts
+struct Entry {
+    anchor: Mark,
+    pointer: String,
+    document: usize,
+}
+
+/// Byte ranges covered by scalars, where `#` is content
+///
+/// Block scalars start after their header line, which may hold a comment.
+fn scalar_ranges(node: &Node, source: &str, ranges: &mut Vec<(usize, usize)>) {
+    match &node.kind {
+        NodeKind::Scalar { style, .. } => {
+            let start = match style {
+                ScalarStyle::Literal | ScalarStyle::Folded => source[node.start.index..]
+                    .find('\n')
+                    .map_or(node.end.index, |offset| node.start.index + offset),
+                _ => node.start.index,
+            };
+            ranges.push((start, node.end.index));
+        }
+        NodeKind::Sequence { items, .. } => {
+            for item in items {
+                scalar_ranges(item, source, ranges);
+            }
+        }
+        NodeKind::Mapping { pairs, .. } => {
+            for (key, value) in pairs {
+                scalar_ranges(key, source, ranges);
+                scalar_ranges(value, source, ranges);
+            }
+        }
+        NodeKind::Alias { .. } => {}
+    }
+}
+
+/// Entries for `node` and everything below it reachable through scalar keys
+fn entries(node: &Node, anchor: Mark, pointer: &mut String, document: usize, out: &mut Vec<Entry>) {
+    out.push(Entry {
+        anchor,
+        pointer: pointer.clone(),
+        document,
+    });
+    let len = pointer.len();
+    match &node.kind {
+        NodeKind::Sequence { items, .. } => {
+            for (i, item) in items.iter().enumerate() {
+                pointer.push('/');
+                pointer.push_str(&i.to_string());
+                entries(item, item.start, pointer, document, out);
+                pointer.truncate(len);
+            }
+        }
+        NodeKind::Mapping { pairs, .. } => {
+            for (key, value) in pairs {
+                let NodeKind::Scalar { value: name, .. } = &key.kind else {
+                    continue;
+                };
+                pointer.push('/');
+                pointer.push_str(&sourcemap::escape(name));
+                entries(value, key.start, pointer, document, out);
+                pointer.truncate(len);
+            }
+        }
+        NodeKind::Scalar { .. } | NodeKind::Alias { .. } => {}
+    }
+}
+
+/// Every comment in `source`, in order
+///
+/// # Errors
+/// * Parse errors (comments are only found in well-formed YAML)
+pub fn extract(source: &str) -> Result<Vec<Comment>, YAMLError> {
+    let documents = compose::compose_all(source)?;
+    let mut ranges = Vec::new();
+    let mut nodes = Vec::new();
+    for (index, document) in documents.iter().enumerate() {
+        scalar_ranges(&document.root, source, &mut ranges);
+        entries(
+            &document.root,
+            document.root.start,
+            &mut String::new(),
+            index,
+            &mut nodes,
+        );
+    }
+    ranges.sort_unstable();
+    nodes.sort_by_key(|entry| entry.anchor.index);
+
+    let mut comments = Vec::new();
+    let mut range = 0;
+    let (mut line, mut column, mut line_start) = (0, 0, 0);
+    let mut previous = '\n';
+    let mut chars = source.char_indices();
+    while let Some((index, c)) = chars.next() {
+        while range < ranges.len() && ranges[range].1 <= index {
+            range += 1;
+        }
+        let in_scalar = range < ranges.len() && ranges[range].0 <= index;
+        if c == '#' && !in_scalar && previous.is_whitespace() {
+            let end = source[index..]
+                .find('\n')
+                .map_or(source.len(), |n| index + n);
+            let start = Mark {
+                index,
+                line,
+                column,
+            };
+            let inline = !source[line_start..index].trim().is_empty();
+            let text = source[index + 1..end].trim().to_string();
+            comments.push(comment(text, start, inline, &nodes, &documents));
+            // Continue at the line break
+            while chars.as_str().len() > source.len() - end {
+                chars.next();
+            }
+            previous = '#';
+            continue;
+        }
+        if c == '\n' {
+            line += 1;
+            column = 0;
+            line_start = index + 1;
+        } else {
+            column += 1;
+        }
+        previous = c;
+    }
+    Ok(comments)
+}
+
+/// Attribute a comment to the nearest node
+fn comment(
+    text: String,
+    start: Mark,
+    inline: bool,
+    nodes: &[Entry],
+    documents: &[compose::Document],
+) -> Comment {
+    let next = nodes.partition_point(|entry| entry.anchor.index <= start.index);
+    // Trailing: the last node starting earlier on the same line
+    let trailing = nodes[..next]
+        .last()
+        .filter(|entry| inline && entry.anchor.line == start.line);
+    // Otherwise the deepest node starting after it
+    let following = || {
+        let anchor = nodes.get(next)?.anchor.index;
+        nodes[next..]
+            .iter()
+            .take_while(|entry| entry.anchor.index == anchor)
+            .last()
+    };
+    match trailing.or_else(following) {
+        Some(entry) => Comment {
+            text,
+            start,
+            inline,
+            document: entry.document,
+            pointer: Some(entry.pointer.clone()),
+        },
+        None => Comment {
+            text,
+            start,
+            inline,
+            document: documents
+                .iter()
+                .rposition(|document| document.start.index <= start.index)
+                .unwrap_or(0),
+            pointer: None,
+        },
+    }
+}
+
+/// Extract comments with their position and the path of the nearest node
+///
+/// # Arguments
+/// * `yaml_str` - YAML content (any number of documents)
+///
+/// # Returns
+/// List of dicts with `text` (after the `#`, trimmed), 1-based `line` and
+/// `column` of the `#`, `inline` (code precedes it on the line),
+/// `document` index and `path` (JSON pointer of the nearest node, or None)
+///
+/// # Example
+/// ```python
+/// for c in rustyyaml.extract_comments(text):
+///     if c["text"].startswith("deprecated:"):
+///         print(c["path"], c["line"], c["text"])
+/// ```
+#[pyfunction]
+pub fn extract_comments(py: Python, yaml_str: &str) -> PyResult<PyObject> {
+    let comments = py.allow_threads(|| extract(yaml_str))?;
+    let result = comments
+        .iter()
+        .map(|comment| {
+            let dict = PyDict::new_bound(py);
+            dict.set_item("text", &comment.text)?;
+            dict.set_item("line", comment.start.line + 1)?;
+            dict.set_item("column", comment.start.column + 1)?;
+            dict.set_item("inline", comment.inline)?;
+            dict.set_item("document", comment.document)?;
+            dict.set_item("path", &comment.pointer)?;
+            Ok(dict.into_any().unbind())
+        })
+        .collect::<PyResult<Vec<PyObject>>>()?;
+    Ok(result.into_py(py))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn found(source: &str) -> Vec<(String, usize, bool, Option<String>)> {
+        extract(source)
+            .unwrap()
+            .into_iter()
+            .map(|c| (c.text, c.start.line, c.inline, c.pointer))
+            .collect()
+    }
+
+    fn row(
+        text: &str,
+        line: usize,
+        inline: bool,
+        pointer: &str,
+    ) -> (String, usize, bool, Option<String>) {
+        (text.to_string(), line, inline, Some(pointer.to_string()))
+    }
+
+    #[test]
+    fn test_attribution() {
+        let source = "# header\n\
+                      name: web  # trailing\n\
+                      spec:\n  \
+                        # deprecated: use ports\n  \
+                        port: 80\n  \
+                        items:\n    \
+                          - a  # first\n";
+        assert_eq!(
+            found(source),
+            [
+                row("header", 0, false, "/name"),
+                row("trailing", 1, true, "/name"),
+                row("deprecated: use ports", 3, false, "/spec/port"),
+                row("first", 6, true, "/spec/items/0"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hash_in_scalars() {
+        let source =
+            "a: 'x # no'\nb: c#d\nc: \"# no\"\nd: | # header\n  # text\n  more\ne: 1 # yes\n";
+        let comments = found(source);
+        let texts: Vec<&str> = comments.iter().map(|c| c.0.as_str()).collect();
+        assert_eq!(texts, ["header", "yes"]);
+        assert_eq!(comments[0].3.as_deref(), Some("/d"));
+    }
+
+    #[test]
+    fn test_documents_and_positions() {
+        let source = "a: 1\n---\n# about b\nb: 2\n# end\n";
+        let comments = extract(source).unwrap();
+        assert_eq!(comments[0].document, 1);
+        assert_eq!(comments[0].pointer.as_deref(), Some("/b"));
+        assert_eq!((comments[0].start.line, comments[0].start.column), (2, 0));
+        assert_eq!(comments[1].document, 1);
+        assert_eq!(comments[1].pointer, None);
+        assert!(extract("a: [").is_err());
+    }
+}
@@ -0,0 +1,429 @@
+//! Compose the event stream into a node tree
+//!
+//! Unlike `serde_yaml::Value`, a `Node` remembers its tag, anchor, scalar
+//! style and source span. Aliases are kept as `Alias` nodes and resolved
+//! during construction so anchors are never expanded eagerly here.
+
+use crate::error::YAMLError;
+use crate::events::{Event, EventKind, Mark, Parser, ScalarStyle};
+use crate::resolve::{TAG_MAP, TAG_SEQ};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum NodeKind {
+    Scalar {
+        value: String,
+        style: ScalarStyle,
+    },
+    Sequence {
+        items: Vec<Node>,
+        flow: bool,
+    },
+    Mapping {
+        pairs: Vec<(Node, Node)>,
+        flow: bool,
+    },
+    Alias {
+        anchor: String,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Node {
+    pub kind: NodeKind,
+    /// Fully resolved tag (`tag:yaml.org,2002:str`, `!Ref`, ...) if one was written
+    pub tag: Option<String>,
+    pub anchor: Option<String>,
+    pub start: Mark,
+    pub end: Mark,
+}
+
+impl Node {
+    /// Is this a plain scalar with no explicit tag (subject to implicit resolution)?
+    pub fn is_plain_untagged(&self) -> bool {
+        self.tag.is_none()
+            && matches!(
+                self.kind,
+                NodeKind::Scalar {
+                    style: ScalarStyle::Plain,
+                    ..
+                }
+            )
+    }
+}
+
+/// One document of a stream
+#[derive(Clone, Debug, PartialEq)]
+pub struct Document {
+    pub root: Node,
+    pub start: Mark,
+    pub end: Mark,
+    /// Was the document opened with an explicit `---`?
+    pub explicit_start: bool,
+    /// Was the document closed with an explicit `...`?
+    pub explicit_end: bool,
+}
+
+/// Compose every document in `yaml_str`
+pub fn compose_all(yaml_str: &str) -> Result<Vec<Document>, YAMLError> {
+    let mut composer = Composer {
+        parser: Parser::new(yaml_str),
+    };
+    composer.documents()
+}
+
+/// Compose a single document (PyYAML semantics: `None` for an empty stream)
+///
+/// # Errors
+/// Fails if the stream contains more than one document
+pub fn compose_one(yaml_str: &str) -> Result<Option<Document>, YAMLError> {
+    let mut documents = compose_all(yaml_str)?;
+    if documents.len() > 1 {
+        return Err(another_document(documents[1].start));
+    }
+    Ok(documents.pop())
+}
+
+fn another_document(start: Mark) -> YAMLError {
+    YAMLError::parse(
+        start.line + 1,
+        start.column + 1,
+        "expected a single document in the stream, but found another document".to_string(),
+    )
+}
+
+/// Composes the documents of a stream one at a time
+pub struct Documents<'input> {
+    composer: Composer<'input>,
+    done: bool,
+}
+
+impl<'input> Documents<'input> {
+    pub fn new(parser: Parser<'input>) -> Self {
+        Documents {
+            composer: Composer { parser },
+            done: false,
+        }
+    }
+}
+
+impl Iterator for Documents<'_> {
+    type Item = Result<Document, YAMLError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let document = self.composer.next_document().transpose();
+        // Stop after the end of the stream or the first error
+        self.done = !matches!(document, Some(Ok(_)));
+        document
+    }
+}
+
+/// The root of a document composed by `Entries`
+#[derive(Debug)]
+pub enum Root {
+    /// A mapping (untagged or `!!map`) starting here; read its pairs with
+    /// `Entries::next_pair`
+    Mapping(Mark),
+    /// A sequence (untagged or `!!seq`); read its items with
+    /// `Entries::next_item`
+    Sequence,
+    /// Any other root, composed whole
+    Node(Node),
+}
+
+/// Composes a single document one root entry at a time
+///
+/// Only the entry being read is held as nodes, so a caller that converts
+/// and drops each one never has the whole tree in memory.
+pub struct Entries<'input> {
+    composer: Composer<'input>,
+}
+
+impl<'input> Entries<'input> {
+    pub fn new(parser: Parser<'input>) -> Self {
+        Entries {
+            composer: Composer { parser },
+        }
+    }
+
+    /// Start the document (`None` for an empty stream)
+    pub fn root(&mut self) -> Result<Option<Root>, YAMLError> {
+        loop {
+            let event = self.composer.next()?;
+            match event.kind {
+                EventKind::StreamStart => {}
+                EventKind::StreamEnd => return Ok(None),
+                EventKind::DocumentStart { .. } => break,
+                other => {
+                    return Err(YAMLError::parse(
+                        event.start.line + 1,
+                        event.start.column + 1,
+                        format!("unexpected event {:?} outside of a document", other),
+                    ))
+                }
+            }
+        }
+
+        let first = self.composer.next()?;
+        Ok(Some(match &first.kind {
+            EventKind::MappingStart { tag, .. }
+                if tag.as_deref().is_none_or(|tag| tag == TAG_MAP) =>
+            {
+                Root::Mapping(first.start)
+            }
+            EventKind::SequenceStart { tag, .. }
+                if tag.as_deref().is_none_or(|tag| tag == TAG_SEQ) =>
+            {
+                Root::Sequence
+            }
+            _ => Root::Node(self.composer.node(first)?),
+        }))
+    }
+
+    /// The next item of a `Root::Sequence` (`None` after the last one)
+    pub fn next_item(&mut self) -> Result<Option<Node>, YAMLError> {
+        let event = self.composer.next()?;
+        if event.kind == EventKind::SequenceEnd {
+            return Ok(None);
+        }
+        self.composer.node(event).map(Some)
+    }
+
+    /// The next key and value of a `Root::Mapping` (`None` after the last)
+    pub fn next_pair(&mut self) -> Result<Option<(Node, Node)>, YAMLError> {
+        let event = self.composer.next()?;
+        if event.kind == EventKind::MappingEnd {
+            return Ok(None);
+        }
+        let key = self.composer.node(event)?;
+        let value_event = self.composer.next()?;
+        Ok(Some((key, self.composer.node(value_event)?)))
+    }
+
+    /// Read the end of the document, once the root has been read
+    ///
+    /// # Errors
+    /// Fails if another document follows, as `compose_one` does
+    pub fn finish(&mut self) -> Result<(), YAMLError> {
+        self.composer.next()?;
+        let event = self.composer.next()?;
+        match event.kind {
+            EventKind::StreamEnd => Ok(()),
+            _ => Err(another_document(event.start)),
+        }
+    }
+}
+
+struct Composer<'input> {
+    parser: Parser<'input>,
+}
+
+impl Composer<'_> {
+    fn next(&mut self) -> Result<Event, YAMLError> {
+        self.parser.next_event()
+    }
+
+    fn documents(&mut self) -> Result<Vec<Document>, YAMLError> {
+        let mut documents = Vec::new();
+        while let Some(document) = self.next_document()? {
+            documents.push(document);
+        }
+        Ok(documents)
+    }
+
+    /// The next document (`None` at the end of the stream)
+    fn next_document(&mut self) -> Result<Option<Document>, YAMLError> {
+        loop {
+            let event = self.next()?;
+            match event.kind {
+                EventKind::StreamStart => {}
+                EventKind::StreamEnd => return Ok(None),
+                EventKind::DocumentStart { implicit } => {
+                    let first = self.next()?;
+                    let root = self.node(first)?;
+                    let end = self.next()?;
+                    let explicit_end =
+                        matches!(end.kind, EventKind::DocumentEnd { implicit: false });
+                    return Ok(Some(Document {
+                        root,
+                        start: event.start,
+                        end: end.end,
+                        explicit_start: !implicit,
+                        explicit_end,
+                    }));
+                }
+                other => {
+                    return Err(YAMLError::parse(
+                        event.start.line + 1,
+                        event.start.column + 1,
+                        format!("unexpected event {:?} outside of a document", other),
+                    ))
+                }
+            }
+        }
+    }
+
+    fn node(&mut self, event: Event) -> Result<Node, YAMLError> {
+        let start = event.start;
+        match event.kind {
+            EventKind::Alias { anchor } => Ok(Node {
+                kind: NodeKind::Alias { anchor },
+                tag: None,
+                anchor: None,
+                start,
+                end: event.end,
+            }),
+            EventKind::Scalar {
+                anchor,
+                tag,
+                value,
+                style,
+            } => Ok(Node {
+                kind: NodeKind::Scalar { value, style },
+                tag,
+                anchor,
+                start,
+                end: event.end,
+            }),
+            EventKind::SequenceStart { anchor, tag, flow } => {
+                let mut items = Vec::new();
+                loop {
+                    let next = self.next()?;
+                    if next.kind == EventKind::SequenceEnd {
+                        return Ok(Node {
+                            kind: NodeKind::Sequence { items, flow },
+                            tag,
+                            anchor,
+                            start,
+                            end: next.end,
+                        });
+                    }
+                    items.push(self.node(next)?);
+                }
+            }
+            EventKind::MappingStart { anchor, tag, flow } => {
+                let mut pairs = Vec::new();
+                loop {
+                    let next = self.next()?;
+                    if next.kind == EventKind::MappingEnd {
+                        return Ok(Node {
+                            kind: NodeKind::Mapping { pairs, flow },
+                            tag,
+                            anchor,
+                            start,
+                            end: next.end,
+                        });
+                    }
+                    let key = self.node(next)?;
+                    let value_event = self.next()?;
+                    let value = self.node(value_event)?;
+                    pairs.push((key, value));
+                }
+            }
+            other => Err(YAMLError::parse(
+                start.line + 1,
+                start.column + 1,
+                format!("unexpected event {:?} while composing a node", other),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compose_mapping() {
+        let doc = compose_one("a: 1\nb: [x, y]").unwrap().unwrap();
+        match &doc.root.kind {
+            NodeKind::Mapping { pairs, flow } => {
+                assert!(!flow);
+                assert_eq!(pairs.len(), 2);
+                assert!(matches!(
+                    pairs[1].1.kind,
+                    NodeKind::Sequence { flow: true, .. }
+                ));
+            }
+            other => panic!("expected mapping, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_compose_empty_stream() {
+        assert!(compose_one("").unwrap().is_none());
+        assert_eq!(compose_all("").unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_compose_multiple_documents() {
+        let docs = compose_all("a: 1\n---\nb: 2\n...\n").unwrap();
+        assert_eq!(docs.len(), 2);
+        assert!(!docs[0].explicit_start);
+        assert!(docs[1].explicit_start);
+        assert!(docs[1].explicit_end);
+        assert!(compose_one("a: 1\n---\nb: 2").is_err());
+    }
+
+    #[test]
+    fn test_compose_keeps_aliases() {
+        let doc = compose_one("a: &x [1]\nb: *x").unwrap().unwrap();
+        let NodeKind::Mapping { pairs, .. } = &doc.root.kind else {
+            panic!("expected mapping");
+        };
+        assert_eq!(pairs[0].1.anchor.as_deref(), Some("x"));
+        assert!(matches!(&pairs[1].1.kind, NodeKind::Alias { anchor } if anchor == "x"));
+    }
+
+    #[test]
+    fn test_documents() {
+        let mut documents = Documents::new(Parser::new("a: 1\n---\nb: 2\n"));
+        assert!(!documents.next().unwrap().unwrap().explicit_start);
+        assert!(documents.next().unwrap().unwrap().explicit_start);
+        assert!(documents.next().is_none());
+
+        let mut documents = Documents::new(Parser::new("a: 1\n---\nb: [\n"));
+        assert!(documents.next().unwrap().is_ok());
+        assert!(documents.next().unwrap().is_err());
+        assert!(documents.next().is_none());
+    }
+
+    #[test]
+    fn test_entries() {
+        let mut entries = Entries::new(Parser::new("a: 1\nb: [x, y]\n"));
+        let Some(Root::Mapping(start)) = entries.root().unwrap() else {
+            panic!("expected a mapping root");
+        };
+        assert_eq!(start.index, 0);
+        let (key, _) = entries.next_pair().unwrap().unwrap();
+        assert!(matches!(&key.kind, NodeKind::Scalar { value, .. } if value == "a"));
+        let (_, value) = entries.next_pair().unwrap().unwrap();
+        assert!(matches!(value.kind, NodeKind::Sequence { flow: true, .. }));
+        assert!(entries.next_pair().unwrap().is_none());
+        entries.finish().unwrap();
+
+        let mut entries = Entries::new(Parser::new("- 1\n---\n- 2\n"));
+        assert!(matches!(entries.root().unwrap(), Some(Root::Sequence)));
+        assert!(entries.next_item().unwrap().is_some());
+        assert!(entries.next_item().unwrap().is_none());
+        assert!(entries.finish().is_err());
+
+        let mut entries = Entries::new(Parser::new("!!set {a, b}"));
+        assert!(matches!(entries.root().unwrap(), Some(Root::Node(_))));
+        entries.finish().unwrap();
+        assert!(Entries::new(Parser::new("")).root().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_plain_untagged() {
+        let doc = compose_one("[a, 'b', !!str c]").unwrap().unwrap();
+        let NodeKind::Sequence { items, .. } = &doc.root.kind else {
+            panic!("expected sequence");
+        };
+        assert!(items[0].is_plain_untagged());
+        assert!(!items[1].is_plain_untagged());
+        assert!(!items[2].is_plain_untagged());
+    }
+}
@@ -0,0 +1,236 @@
+//! Compressed YAML files (`config.yaml.gz`)
+//!
+//! File loads look at the last extension: `.gz`, `.bz2` and `.xz` files are
+//! decompressed in Rust before decoding, so archived logs and configs load
+//! like any other file (and in parallel on the batch path).
+
+use bzip2::read::MultiBzDecoder;
+use flate2::read::MultiGzDecoder;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use crate::batch::read_error;
+use crate::error::YAMLError;
+use crate::limits::Limits;
+use xz2::read::XzDecoder;
+
+/// How a file is compressed, from its extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Bzip2,
+    Xz,
+}
+
+impl Compression {
+    /// The compression `path`'s extension names, if any
+    pub fn of(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        match extension.as_str() {
+            "gz" => Some(Compression::Gzip),
+            "bz2" => Some(Compression::Bzip2),
+            "xz" => Some(Compression::Xz),
+            _ => None,
+        }
+    }
+
+    /// Reader for the decompressed stream of `reader`
+    ///
+    /// All three read concatenated streams as one, as `cat a.gz b.gz` (or
+    /// appending with `gzip -c`, `bzip2 -c`, `xz -c`) writes them.
+    fn decoder(self, reader: impl Read + 'static) -> Box<dyn Read> {
+        match self {
+            Compression::Gzip => Box::new(MultiGzDecoder::new(reader)),
+            Compression::Bzip2 => Box::new(MultiBzDecoder::new(reader)),
+            Compression::Xz => Box::new(XzDecoder::new_multi_decoder(reader)),
+        }
+    }
+}
+
+/// Whether `path` names a YAML file: `.yaml` or `.yml`, optionally
+/// compressed (`.yaml.gz`, `.yaml.bz2`, `.yaml.xz`)
+pub fn is_yaml_file(path: &Path) -> bool {
+    let path = match Compression::of(path) {
+        Some(_) => Path::new(path.file_stem().unwrap_or_default()),
+        None => path,
+    };
+    path.extension().is_some_and(|ext| {
+        let ext = ext.to_string_lossy().to_lowercase();
+        ext == "yaml" || ext == "yml"
+    })
+}
+
+/// Decompress `bytes` read from `path`, if its extension says it is
+/// compressed; other files are returned as they are. Decompression stops
+/// one byte past `max_size_bytes`.
+///
+/// # Errors
+/// * `ParseError` if the data isn't valid for its compression
+/// * `Limit` if the data is (or decompresses to) more than `max_size_bytes`
+pub fn decompress(path: &Path, bytes: Vec<u8>, limits: &Limits) -> Result<Vec<u8>, YAMLError> {
+    let Some(compression) = Compression::of(path) else {
+        limits.check_size(bytes.len())?;
+        return Ok(bytes);
+    };
+    let decoder = compression.decoder(io::Cursor::new(bytes));
+    limits.read_to_end(decoder, |e| decompress_error(path, e))
+}
+
+/// Read the file at `path`, decompressed if its extension says so
+///
+/// A plain file larger than `max_size_bytes` is refused from its size;
+/// a compressed one is decompressed as it's read, up to one byte past the
+/// limit, so neither is ever read whole.
+///
+/// # Errors
+/// * `ParseError` if the file can't be read or decompressed
+/// * `Limit` if it is (or decompresses to) more than `max_size_bytes`
+pub fn read(path: &Path, limits: &Limits) -> Result<Vec<u8>, YAMLError> {
+    let file = File::open(path).map_err(|e| read_error(path, e))?;
+    match Compression::of(path) {
+        Some(_) => limits.read_to_end(open_file(path, file), |e| decompress_error(path, e)),
+        None => {
+            let len = file.metadata().map_err(|e| read_error(path, e))?.len();
+            limits.check_size(usize::try_from(len).unwrap_or(usize::MAX))?;
+            limits.read_to_end(file, |e| read_error(path, e))
+        }
+    }
+}
+
+/// Open `path` for reading, decompressing on the fly if it is compressed
+///
+/// # Errors
+/// * `ParseError` if the file can't be opened (errors in the compressed
+///   data surface as the reader is read)
+pub fn open(path: &Path) -> Result<Box<dyn Read>, YAMLError> {
+    let file = File::open(path).map_err(|e| read_error(path, e))?;
+    Ok(open_file(path, file))
+}
+
+fn open_file(path: &Path, file: File) -> Box<dyn Read> {
+    match Compression::of(path) {
+        Some(compression) => compression.decoder(file),
+        None => Box::new(file),
+    }
+}
+
+fn decompress_error(path: &Path, err: io::Error) -> YAMLError {
+    YAMLError::parse(
+        0,
+        0,
+        format!("Failed to decompress {}: {}", path.display(), err),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bzip2::write::BzEncoder;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+    use xz2::write::XzEncoder;
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn bzip2(data: &[u8]) -> Vec<u8> {
+        let mut encoder = BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn xz(data: &[u8]) -> Vec<u8> {
+        let mut encoder = XzEncoder::new(Vec::new(), 6);
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_is_yaml_file() {
+        for name in [
+            "a.yaml",
+            "a.YML",
+            "a.yaml.gz",
+            "a.yml.GZ",
+            "a.yaml.bz2",
+            "a.yml.xz",
+        ] {
+            assert!(is_yaml_file(Path::new(name)), "{}", name);
+        }
+        for name in ["a.json", "a.gz", "a.json.xz", "yaml"] {
+            assert!(!is_yaml_file(Path::new(name)), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_decompress() {
+        let path = Path::new("config.yaml.gz");
+        let limits = Limits::default();
+        assert_eq!(
+            decompress(path, gzip(b"a: 1\n"), &limits).unwrap(),
+            b"a: 1\n"
+        );
+
+        // Concatenated members decompress as one stream
+        let mut members = gzip(b"a: 1\n");
+        members.extend(gzip(b"b: 2\n"));
+        assert_eq!(decompress(path, members, &limits).unwrap(), b"a: 1\nb: 2\n");
+
+        assert_eq!(
+            decompress(Path::new("config.yaml"), b"a: 1".to_vec(), &limits).unwrap(),
+            b"a: 1"
+        );
+        assert!(decompress(path, b"a: 1".to_vec(), &limits).is_err());
+    }
+
+    #[test]
+    fn test_bzip2_and_xz() {
+        let limits = Limits::default();
+        for (name, compress) in [
+            ("config.yaml.bz2", bzip2 as fn(&[u8]) -> Vec<u8>),
+            ("config.yaml.xz", xz),
+        ] {
+            let path = Path::new(name);
+            assert_eq!(
+                decompress(path, compress(b"a: 1\n"), &limits).unwrap(),
+                b"a: 1\n"
+            );
+            let mut streams = compress(b"a: 1\n");
+            streams.extend(compress(b"b: 2\n"));
+            assert_eq!(
+                decompress(path, streams, &limits).unwrap(),
+                b"a: 1\nb: 2\n",
+                "{}",
+                name
+            );
+            let err = decompress(path, b"a: 1".to_vec(), &limits).unwrap_err();
+            assert!(err.to_string().contains("Failed to decompress"), "{}", name);
+            let small = Limits {
+                max_size_bytes: Some(100),
+                ..Default::default()
+            };
+            let err = decompress(path, compress(&vec![b'0'; 1 << 20]), &small).unwrap_err();
+            assert!(matches!(err, YAMLError::Limit { .. }), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_max_size_bytes() {
+        let limits = Limits {
+            max_size_bytes: Some(100),
+            ..Default::default()
+        };
+        // A megabyte of zeros compresses to about a kilobyte; it's refused
+        // without being inflated whole
+        let bomb = gzip(&vec![b'0'; 1 << 20]);
+        let err = decompress(Path::new("bomb.yaml.gz"), bomb, &limits).unwrap_err();
+        assert!(matches!(err, YAMLError::Limit { .. }));
+        assert!(decompress(Path::new("a.yaml.gz"), gzip(&[b'0'; 100]), &limits).is_ok());
+        let err = decompress(Path::new("a.yaml"), vec![b'0'; 101], &limits).unwrap_err();
+        assert!(matches!(err, YAMLError::Limit { .. }));
+    }
+}
@@ -0,0 +1,987 @@
+//! Construct Python objects from composed nodes
+//!
+//! This is the composer-based counterpart of `types::yaml_to_python`.
+//! It is used whenever `LoadOptions` asks for something serde_yaml's
+//! `Value` cannot express (YAML 1.1 resolution, timestamps, binary, ...).
+
+use std::collections::HashMap;
+
+use base64::Engine;
+use pyo3::prelude::*;
+use pyo3::sync::GILOnceCell;
+use pyo3::types::{IntoPyDict, PyBytes, PyDict, PyFloat, PyList, PySet, PyTuple};
+
+use crate::compose::{Document, Node, NodeKind};
+use crate::error::YAMLError;
+use crate::events::ScalarStyle;
+use crate::options::LoadOptions;
+use crate::quantity;
+use crate::resolve::{self, Int, IpKind, Scalar, Schema, Timestamp};
+use crate::safe::TagStrategy;
+use crate::types::{sorted_items, str_to_python};
+
+/// Convert a composed document (or an empty stream) to Python
+pub fn construct_document(
+    py: Python,
+    document: Option<&Document>,
+    options: &LoadOptions,
+) -> PyResult<PyObject> {
+    let Some(document) = document else {
+        return Ok(py.None());
+    };
+    let mut constructor = Constructor::new(py, options);
+    constructor.construct(&document.root)
+}
+
+/// Convert one node of a composed document to Python
+///
+/// Anchors defined before `node` in the document rooted at `root` are
+/// known, so aliases inside `node` resolve as they would in a full load.
+pub fn construct_node(
+    py: Python,
+    root: &Node,
+    node: &Node,
+    options: &LoadOptions,
+) -> PyResult<PyObject> {
+    let mut constructor = Constructor::new(py, options);
+    constructor.register_anchors(root, node.start.index);
+    constructor.construct(node)
+}
+
+/// Position relative to a Kubernetes `resources:` block
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum QuantityScope {
+    /// Quantity conversion is disabled
+    Off,
+    /// Not inside a `resources:` block
+    Outside,
+    /// Directly inside `resources:` (the `limits`/`requests` mappings)
+    Resources,
+    /// Inside `resources.<bucket>`; scalar values are quantities
+    Bucket,
+}
+
+struct Constructor<'a, 'py> {
+    py: Python<'py>,
+    schema: Schema,
+    /// Aliases resolve to the same Python object (PyYAML semantics)
+    share_aliases: bool,
+    /// Plain `<<` keys merge
+    merge_keys: bool,
+    /// Resolve plain timestamps even when the schema does not
+    timestamps: bool,
+    /// Construct bare dates as midnight `datetime`s
+    dates_as_datetime: bool,
+    /// Resolve plain durations to `timedelta`
+    durations: bool,
+    /// Resolve UUID-shaped plain scalars to `uuid.UUID`
+    uuids: bool,
+    /// Resolve IP addresses/networks to `ipaddress` objects
+    ip_addresses: bool,
+    /// Where the node being constructed sits relative to `resources:`
+    quantity_scope: QuantityScope,
+    /// Build `rustyyaml.marked` containers carrying `.lc` positions
+    marked: bool,
+    /// Called to create each mapping instead of `dict`
+    dict_class: Option<Bound<'py, PyAny>>,
+    /// Insert mapping keys in `types::key_order`
+    sort_keys: bool,
+    /// Custom tags constructed instead of rejected, and how
+    allowed_tags: Vec<String>,
+    tag_strategy: TagStrategy,
+    /// Most recent node defined for each anchor
+    anchors: HashMap<&'a str, &'a Node>,
+    /// Constructed object for each anchor when aliases are shared
+    shared: HashMap<&'a str, PyObject>,
+}
+
+impl<'a, 'py> Constructor<'a, 'py> {
+    fn new(py: Python<'py>, options: &LoadOptions) -> Self {
+        Constructor {
+            py,
+            schema: options.schema(),
+            share_aliases: options.share_aliases(),
+            merge_keys: options.merge_keys(),
+            timestamps: options.resolve_timestamps(),
+            dates_as_datetime: options.dates_as_datetime,
+            durations: options.durations,
+            uuids: options.uuids,
+            ip_addresses: options.ip_addresses,
+            quantity_scope: if options.kubernetes_quantities {
+                QuantityScope::Outside
+            } else {
+                QuantityScope::Off
+            },
+            marked: options.marked,
+            dict_class: options
+                .dict_class
+                .as_ref()
+                .map(|class| class.bind(py).clone()),
+            sort_keys: options.sort_keys,
+            allowed_tags: options.allowed_tags.clone(),
+            tag_strategy: options.tag_strategy.clone(),
+            anchors: HashMap::new(),
+            shared: HashMap::new(),
+        }
+    }
+
+    fn construct(&mut self, node: &'a Node) -> PyResult<PyObject> {
+        // A tag in `allowed_tags` is applied to the value built as if the
+        // node weren't tagged
+        let (tag, allowed) = match node.tag.as_deref() {
+            Some(tag) if self.allowed_tags.iter().any(|allowed| allowed == tag) => {
+                (None, Some(tag))
+            }
+            tag => (tag, None),
+        };
+        let obj = match &node.kind {
+            NodeKind::Alias { anchor } => return self.alias(node, anchor),
+            NodeKind::Scalar { value, style } => self.scalar(node, tag, value, *style)?,
+            NodeKind::Sequence { items, .. } => self.sequence(node, tag, items)?,
+            NodeKind::Mapping { pairs, .. } => self.mapping(node, tag, pairs)?,
+        };
+        let obj = match allowed {
+            Some(tag) => self.apply_tag(tag, obj)?,
+            None => obj,
+        };
+
+        if let Some(anchor) = &node.anchor {
+            self.anchors.insert(anchor, node);
+            if self.share_aliases {
+                self.shared.insert(anchor, obj.clone_ref(self.py));
+            }
+        }
+        Ok(obj)
+    }
+
+    /// Record anchored nodes that end before byte offset `before`
+    fn register_anchors(&mut self, node: &'a Node, before: usize) {
+        if node.start.index >= before {
+            return;
+        }
+        if let (Some(anchor), true) = (&node.anchor, node.end.index <= before) {
+            self.anchors.insert(anchor, node);
+        }
+        match &node.kind {
+            NodeKind::Sequence { items, .. } => {
+                for item in items {
+                    self.register_anchors(item, before);
+                }
+            }
+            NodeKind::Mapping { pairs, .. } => {
+                for (key, value) in pairs {
+                    self.register_anchors(key, before);
+                    self.register_anchors(value, before);
+                }
+            }
+            NodeKind::Scalar { .. } | NodeKind::Alias { .. } => {}
+        }
+    }
+
+    fn alias(&mut self, node: &Node, anchor: &str) -> PyResult<PyObject> {
+        if let Some(obj) = self.shared.get(anchor) {
+            return Ok(obj.clone_ref(self.py));
+        }
+        let target = self.resolve_alias(node, anchor)?;
+        self.construct(target)
+    }
+
+    fn resolve_alias(&self, node: &Node, anchor: &str) -> Result<&'a Node, YAMLError> {
+        self.anchors.get(anchor).copied().ok_or_else(|| {
+            YAMLError::parse(
+                node.start.line + 1,
+                node.start.column + 1,
+                format!("found undefined alias '{}'", anchor),
+            )
+        })
+    }
+
+    /// Follow an alias to the node it refers to
+    fn deref(&self, node: &'a Node) -> Result<&'a Node, YAMLError> {
+        match &node.kind {
+            NodeKind::Alias { anchor } => self.resolve_alias(node, anchor),
+            _ => Ok(node),
+        }
+    }
+
+    /// Construct an `allowed_tags` tag as `tag_strategy` says
+    fn apply_tag(&self, tag: &str, obj: PyObject) -> PyResult<PyObject> {
+        match &self.tag_strategy {
+            TagStrategy::Value => Ok(obj),
+            TagStrategy::Mapping => {
+                let mapping = match &self.dict_class {
+                    Some(class) => class.call0()?,
+                    None => PyDict::new_bound(self.py).into_any(),
+                };
+                mapping.set_item(tag.strip_prefix('!').unwrap_or(tag), obj)?;
+                Ok(mapping.unbind())
+            }
+            TagStrategy::Call(callable) => Ok(callable.call1(self.py, (tag, obj))?),
+        }
+    }
+
+    fn scalar(
+        &mut self,
+        node: &Node,
+        tag: Option<&str>,
+        value: &str,
+        style: ScalarStyle,
+    ) -> PyResult<PyObject> {
+        let resolved = match tag {
+            None if style == ScalarStyle::Plain => self.resolve_plain(value),
+            None | Some("!") => Scalar::Str,
+            Some(resolve::TAG_BINARY) => return self.binary(node, value),
+            Some(tag) if is_scalar_tag(tag) => {
+                resolve::resolve_tagged(value, tag).ok_or_else(|| {
+                    YAMLError::parse(
+                        node.start.line + 1,
+                        node.start.column + 1,
+                        format!("invalid value '{}' for tag {}", value, tag),
+                    )
+                })?
+            }
+            Some(tag) => return Err(YAMLError::unsafe_tag(tag.to_string()).into()),
+        };
+        if self.marked && resolved == Scalar::Str {
+            let string = marked_module(self.py)?
+                .getattr("MarkedStr")?
+                .call1((value,))?;
+            self.set_line_col(&string, node, None, style_name(node))?;
+            return Ok(string.unbind());
+        }
+        self.scalar_to_python(resolved, value)
+    }
+
+    /// Attach `.lc` (ruamel.yaml's `LineCol`, 0-based) to a marked object
+    fn set_line_col(
+        &self,
+        obj: &Bound<'py, PyAny>,
+        node: &Node,
+        data: Option<Bound<'py, PyDict>>,
+        style: Option<&str>,
+    ) -> PyResult<()> {
+        let line_col = marked_module(self.py)?.getattr("LineCol")?.call1((
+            node.start.line,
+            node.start.column,
+            data,
+            style,
+        ))?;
+        obj.setattr("lc", line_col)
+    }
+
+    /// A `LineCol.data` entry: the line and column of each node, then
+    /// their scalar styles
+    fn line_col_entry(&self, nodes: &[&Node]) -> PyResult<Bound<'py, PyList>> {
+        let entry = PyList::empty_bound(self.py);
+        for node in nodes {
+            entry.append(node.start.line)?;
+            entry.append(node.start.column)?;
+        }
+        for node in nodes {
+            entry.append(style_name(node))?;
+        }
+        Ok(entry)
+    }
+
+    /// Construct a mapping key; keys stay plain even when `marked`
+    fn construct_key(&mut self, key: &'a Node) -> PyResult<PyObject> {
+        let marked = std::mem::replace(&mut self.marked, false);
+        let result = self.construct(key);
+        self.marked = marked;
+        result
+    }
+
+    /// An empty `MarkedSeq`/`MarkedMap` or a plain list/dict
+    fn container(&self, class: &str) -> PyResult<Option<Bound<'py, PyAny>>> {
+        if !self.marked {
+            return Ok(None);
+        }
+        Ok(Some(marked_module(self.py)?.getattr(class)?.call0()?))
+    }
+
+    /// Schema resolution, then the opt-in resolvers for what is left as `str`
+    fn resolve_plain(&self, value: &str) -> Scalar {
+        let scalar = resolve::resolve_plain(value, self.schema);
+        if scalar != Scalar::Str {
+            return scalar;
+        }
+        if self.timestamps {
+            if let Some(ts) = resolve::resolve_timestamp(value) {
+                return Scalar::Timestamp(ts);
+            }
+        }
+        if self.durations {
+            if let Some(micros) = resolve::parse_duration(value) {
+                return Scalar::Duration(micros);
+            }
+        }
+        if self.uuids && resolve::is_uuid(value) {
+            return Scalar::Uuid;
+        }
+        if self.ip_addresses {
+            if let Some(kind) = resolve::resolve_ip(value) {
+                return Scalar::Ip(kind);
+            }
+        }
+        Scalar::Str
+    }
+
+    fn scalar_to_python(&self, scalar: Scalar, value: &str) -> PyResult<PyObject> {
+        let py = self.py;
+        Ok(match scalar {
+            Scalar::Null => py.None(),
+            Scalar::Bool(b) => b.to_object(py),
+            Scalar::Int(Int::Small(i)) => i.to_object(py),
+            Scalar::Int(Int::Big { text, radix }) => py
+                .import_bound("builtins")?
+                .getattr("int")?
+                .call1((text, radix))?
+                .unbind(),
+            Scalar::Float(f) => PyFloat::new_bound(py, f).into(),
+            Scalar::Timestamp(ts) => timestamp_to_python(py, &ts, self.dates_as_datetime)?,
+            Scalar::Duration(micros) => datetime_module(py)?
+                .getattr("timedelta")?
+                .call1((0, 0, micros))?
+                .unbind(),
+            Scalar::Uuid => uuid_module(py)?.getattr("UUID")?.call1((value,))?.unbind(),
+            Scalar::Ip(kind) => {
+                let constructor = match kind {
+                    IpKind::Address => "ip_address",
+                    IpKind::Network => "ip_network",
+                    IpKind::Interface => "ip_interface",
+                };
+                ipaddress_module(py)?
+                    .getattr(constructor)?
+                    .call1((value,))?
+                    .unbind()
+            }
+            Scalar::Str | Scalar::Merge => str_to_python(py, value),
+        })
+    }
+
+    fn binary(&self, node: &Node, value: &str) -> PyResult<PyObject> {
+        // Like base64.decodebytes(), ignore embedded whitespace/newlines
+        let compact: String = value.chars().filter(|c| !c.is_whitespace()).collect();
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(compact)
+            .map_err(|e| {
+                YAMLError::parse(
+                    node.start.line + 1,
+                    node.start.column + 1,
+                    format!("failed to decode base64 data: {}", e),
+                )
+            })?;
+        Ok(PyBytes::new_bound(self.py, &bytes).into())
+    }
+
+    fn sequence(
+        &mut self,
+        node: &Node,
+        tag: Option<&str>,
+        items: &'a [Node],
+    ) -> PyResult<PyObject> {
+        match tag {
+            None | Some(resolve::TAG_SEQ) => {
+                let scope = self.quantity_scope;
+                if scope != QuantityScope::Off {
+                    self.quantity_scope = QuantityScope::Outside;
+                }
+                let marked = self.container("MarkedSeq")?;
+                let list = match &marked {
+                    Some(obj) => obj.downcast::<PyList>()?.clone(),
+                    None => PyList::empty_bound(self.py),
+                };
+                let positions = PyDict::new_bound(self.py);
+                for (i, item) in items.iter().enumerate() {
+                    list.append(self.construct(item)?)?;
+                    if marked.is_some() {
+                        positions.set_item(i, self.line_col_entry(&[item])?)?;
+                    }
+                }
+                self.quantity_scope = scope;
+                if let Some(obj) = &marked {
+                    self.set_line_col(obj, node, Some(positions), None)?;
+                }
+                Ok(list.into())
+            }
+            // !!omap and !!pairs become a list of (key, value) tuples, like PyYAML
+            Some(resolve::TAG_OMAP) | Some(resolve::TAG_PAIRS) => {
+                let list = PyList::empty_bound(self.py);
+                for item in items {
+                    let item = self.deref(item)?;
+                    let NodeKind::Mapping { pairs, .. } = &item.kind else {
+                        return Err(YAMLError::parse(
+                            item.start.line + 1,
+                            item.start.column + 1,
+                            "expected a mapping of length 1".to_string(),
+                        )
+                        .into());
+                    };
+                    if pairs.len() != 1 {
+                        return Err(YAMLError::parse(
+                            item.start.line + 1,
+                            item.start.column + 1,
+                            format!(
+                                "expected a single mapping item, but found {} items",
+                                pairs.len()
+                            ),
+                        )
+                        .into());
+                    }
+                    let key = self.construct(&pairs[0].0)?;
+                    let value = self.construct(&pairs[0].1)?;
+                    list.append(PyTuple::new_bound(self.py, [key, value]))?;
+                }
+                Ok(list.into())
+            }
+            Some(tag) => Err(YAMLError::unsafe_tag(tag.to_string()).into()),
+        }
+    }
+
+    fn mapping(
+        &mut self,
+        node: &Node,
+        tag: Option<&str>,
+        pairs: &'a [(Node, Node)],
+    ) -> PyResult<PyObject> {
+        let pairs = self.flatten(pairs)?;
+        match tag {
+            None | Some(resolve::TAG_MAP) => {
+                let scope = self.quantity_scope;
+                let marked = self.container("MarkedMap")?;
+                let dict = match (&marked, &self.dict_class) {
+                    (Some(obj), _) => obj.clone(),
+                    (None, Some(class)) => class.call0()?,
+                    (None, None) => PyDict::new_bound(self.py).into_any(),
+                };
+                let positions = PyDict::new_bound(self.py);
+                let mut items = Vec::with_capacity(pairs.len());
+                for (k, v) in pairs {
+                    // Constructed in document order, so anchors come before
+                    // their aliases even when the keys are sorted
+                    let key = self.construct_key(k)?.into_bound(self.py);
+                    let value = self.mapping_value(scope, k, v)?;
+                    if marked.is_some() {
+                        positions.set_item(&key, self.line_col_entry(&[k, v])?)?;
+                    }
+                    items.push((key, value.into_bound(self.py)));
+                }
+                if self.sort_keys {
+                    let resolved = PyDict::new_bound(self.py);
+                    for (key, value) in items {
+                        resolved.set_item(key, value)?;
+                    }
+                    items = sorted_items(&resolved);
+                }
+                for (key, value) in items {
+                    dict.set_item(key, value)?;
+                }
+                if let Some(obj) = &marked {
+                    self.set_line_col(obj, node, Some(positions), None)?;
+                }
+                Ok(dict.into())
+            }
+            Some(resolve::TAG_SET) => {
+                let set = PySet::empty_bound(self.py)?;
+                for (k, _) in pairs {
+                    set.add(self.construct(k)?)?;
+                }
+                Ok(set.into())
+            }
+            Some(tag) => Err(YAMLError::unsafe_tag(tag.to_string()).into()),
+        }
+    }
+
+    /// Construct a mapping value, converting `resources.*` quantities
+    fn mapping_value(
+        &mut self,
+        scope: QuantityScope,
+        key: &Node,
+        value: &'a Node,
+    ) -> PyResult<PyObject> {
+        if scope == QuantityScope::Off {
+            return self.construct(value);
+        }
+        let name = match &key.kind {
+            NodeKind::Scalar { value, .. } => Some(value.as_str()),
+            _ => None,
+        };
+
+        if let (QuantityScope::Bucket, Some(name)) = (scope, name) {
+            let target = self.deref(value)?;
+            if let NodeKind::Scalar { value: text, .. } = &target.kind {
+                // An anchored scalar must go through construct() to be registered
+                if target.tag.is_none() && value.anchor.is_none() {
+                    let milli = quantity::is_cpu_resource(name);
+                    if let Some(amount) = quantity::parse_quantity(text, milli) {
+                        return Ok(amount.to_object(self.py));
+                    }
+                }
+            }
+        }
+
+        self.quantity_scope = match (scope, name) {
+            (QuantityScope::Outside, Some("resources")) => QuantityScope::Resources,
+            (QuantityScope::Resources, _) => QuantityScope::Bucket,
+            _ => QuantityScope::Outside,
+        };
+        let result = self.construct(value);
+        self.quantity_scope = scope;
+        result
+    }
+
+    /// Apply `<<` merge keys (PyYAML's `flatten_mapping`)
+    ///
+    /// Merged pairs come first so the mapping's own keys override them.
+    fn flatten(&self, pairs: &'a [(Node, Node)]) -> PyResult<Vec<(&'a Node, &'a Node)>> {
+        let mut merged = Vec::new();
+        let mut own = Vec::with_capacity(pairs.len());
+
+        for (key, value) in pairs {
+            if !self.is_merge_key(key) {
+                own.push((key, value));
+                continue;
+            }
+            let value = self.deref(value)?;
+            match &value.kind {
+                NodeKind::Mapping { pairs, .. } => merged.extend(self.flatten(pairs)?),
+                NodeKind::Sequence { items, .. } => {
+                    let mut submerge = Vec::new();
+                    for item in items {
+                        let item = self.deref(item)?;
+                        let NodeKind::Mapping { pairs, .. } = &item.kind else {
+                            return Err(YAMLError::parse(
+                                item.start.line + 1,
+                                item.start.column + 1,
+                                "expected a mapping for merging".to_string(),
+                            )
+                            .into());
+                        };
+                        submerge.push(self.flatten(pairs)?);
+                    }
+                    // Earlier mappings in the list take precedence
+                    for pairs in submerge.into_iter().rev() {
+                        merged.extend(pairs);
+                    }
+                }
+                _ => {
+                    return Err(YAMLError::parse(
+                        value.start.line + 1,
+                        value.start.column + 1,
+                        "expected a mapping or list of mappings for merging".to_string(),
+                    )
+                    .into())
+                }
+            }
+        }
+
+        merged.extend(own);
+        Ok(merged)
+    }
+
+    fn is_merge_key(&self, key: &Node) -> bool {
+        is_merge_key(key, self.merge_keys)
+    }
+}
+
+/// How a scalar node was written (`None` for collections and aliases)
+pub fn style_name(node: &Node) -> Option<&'static str> {
+    match &node.kind {
+        NodeKind::Scalar { style, .. } => Some(style.name()),
+        _ => None,
+    }
+}
+
+/// Is `key` a `<<` merge key?
+///
+/// A key tagged `!!merge` always is; a plain `<<` is if `merge_keys`
+/// (the `merge_keys=` option) is on.
+pub fn is_merge_key(key: &Node, merge_keys: bool) -> bool {
+    let NodeKind::Scalar { value, .. } = &key.kind else {
+        return false;
+    };
+    match key.tag.as_deref() {
+        Some(tag) => tag == resolve::TAG_MERGE,
+        None => merge_keys && key.is_plain_untagged() && value == "<<",
+    }
+}
+
+/// Tags handled by `resolve::resolve_tagged`
+fn is_scalar_tag(tag: &str) -> bool {
+    matches!(
+        tag,
+        resolve::TAG_STR
+            | resolve::TAG_NULL
+            | resolve::TAG_BOOL
+            | resolve::TAG_INT
+            | resolve::TAG_FLOAT
+            | resolve::TAG_TIMESTAMP
+    )
+}
+
+/// Import a module once per interpreter
+fn cached_module<'py>(
+    py: Python<'py>,
+    cell: &'static GILOnceCell<Py<PyModule>>,
+    name: &str,
+) -> PyResult<&'py Bound<'py, PyModule>> {
+    cell.get_or_try_init(py, || py.import_bound(name).map(Bound::unbind))
+        .map(|module| module.bind(py))
+}
+
+fn datetime_module(py: Python<'_>) -> PyResult<&Bound<'_, PyModule>> {
+    static DATETIME: GILOnceCell<Py<PyModule>> = GILOnceCell::new();
+    cached_module(py, &DATETIME, "datetime")
+}
+
+fn uuid_module(py: Python<'_>) -> PyResult<&Bound<'_, PyModule>> {
+    static UUID: GILOnceCell<Py<PyModule>> = GILOnceCell::new();
+    cached_module(py, &UUID, "uuid")
+}
+
+fn ipaddress_module(py: Python<'_>) -> PyResult<&Bound<'_, PyModule>> {
+    static IPADDRESS: GILOnceCell<Py<PyModule>> = GILOnceCell::new();
+    cached_module(py, &IPADDRESS, "ipaddress")
+}
+
+fn marked_module(py: Python<'_>) -> PyResult<&Bound<'_, PyModule>> {
+    static MARKED: GILOnceCell<Py<PyModule>> = GILOnceCell::new();
+    cached_module(py, &MARKED, "rustyyaml.marked")
+}
+
+/// Build a `datetime.date` or `datetime.datetime`
+///
+/// With `force_datetime`, a bare date becomes a naive `datetime` at midnight.
+fn timestamp_to_python(py: Python, ts: &Timestamp, force_datetime: bool) -> PyResult<PyObject> {
+    let datetime = datetime_module(py)?;
+    let Some(time) = &ts.time else {
+        let class = if force_datetime { "datetime" } else { "date" };
+        return Ok(datetime
+            .getattr(class)?
+            .call1((ts.year, ts.month, ts.day))?
+            .unbind());
+    };
+
+    let tzinfo = match time.offset_minutes {
+        None => py.None(),
+        Some(0) => datetime.getattr("timezone")?.getattr("utc")?.unbind(),
+        Some(minutes) => {
+            let delta = datetime
+                .getattr("timedelta")?
+                .call((), Some(&[("minutes", minutes)].into_py_dict_bound(py)))?;
+            datetime.getattr("timezone")?.call1((delta,))?.unbind()
+        }
+    };
+
+    let kwargs = [("tzinfo", tzinfo)].into_py_dict_bound(py);
+    Ok(datetime
+        .getattr("datetime")?
+        .call(
+            (
+                ts.year,
+                ts.month,
+                ts.day,
+                time.hour,
+                time.minute,
+                time.second,
+                time.microsecond,
+            ),
+            Some(&kwargs),
+        )?
+        .unbind())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compose::compose_one;
+
+    fn load(py: Python, yaml: &str) -> PyResult<PyObject> {
+        let options = LoadOptions {
+            pyyaml_compat: true,
+            ..Default::default()
+        };
+        let doc = compose_one(yaml)?;
+        construct_document(py, doc.as_ref(), &options)
+    }
+
+    #[test]
+    fn test_yaml11_scalars() {
+        Python::with_gil(|py| {
+            let result = load(py, "a: yes\nb: 0755\nc: '0755'\nd: ~").unwrap();
+            let dict = result.bind(py).downcast::<PyDict>().unwrap();
+            let a: bool = dict.get_item("a").unwrap().unwrap().extract().unwrap();
+            let b: i64 = dict.get_item("b").unwrap().unwrap().extract().unwrap();
+            let c: String = dict.get_item("c").unwrap().unwrap().extract().unwrap();
+            assert!(a);
+            assert_eq!(b, 493);
+            assert_eq!(c, "0755");
+            assert!(dict.get_item("d").unwrap().unwrap().is_none());
+        });
+    }
+
+    #[test]
+    fn test_binary() {
+        Python::with_gil(|py| {
+            let result = load(py, "!!binary aGVsbG8=").unwrap();
+            let bytes: Vec<u8> = result.extract(py).unwrap();
+            assert_eq!(bytes, b"hello");
+        });
+    }
+
+    #[test]
+    fn test_timestamp_types() {
+        Python::with_gil(|py| {
+            let result = load(py, "- 2024-01-01\n- 2024-01-01T10:00:00Z").unwrap();
+            let list = result.bind(py).downcast::<PyList>().unwrap();
+            let date_type = list
+                .get_item(0)
+                .unwrap()
+                .get_type()
+                .name()
+                .unwrap()
+                .to_string();
+            let dt_type = list
+                .get_item(1)
+                .unwrap()
+                .get_type()
+                .name()
+                .unwrap()
+                .to_string();
+            assert_eq!(date_type, "date");
+            assert_eq!(dt_type, "datetime");
+        });
+    }
+
+    #[test]
+    fn test_timestamps_in_core_schema() {
+        Python::with_gil(|py| {
+            let type_names = |options: &LoadOptions| {
+                let doc = compose_one("- 2024-01-01\n- 2024-01-01 10:00:00\n- yes").unwrap();
+                let result = construct_document(py, doc.as_ref(), options).unwrap();
+                let list = result.bind(py).downcast::<PyList>().unwrap().clone();
+                list.iter()
+                    .map(|item| item.get_type().name().unwrap().to_string())
+                    .collect::<Vec<_>>()
+            };
+
+            let options = LoadOptions {
+                timestamps: true,
+                ..Default::default()
+            };
+            assert_eq!(type_names(&options), ["date", "datetime", "str"]);
+
+            let options = LoadOptions {
+                dates_as_datetime: true,
+                ..Default::default()
+            };
+            assert_eq!(type_names(&options), ["datetime", "datetime", "str"]);
+        });
+    }
+
+    #[test]
+    fn test_durations() {
+        Python::with_gil(|py| {
+            let options = LoadOptions {
+                durations: true,
+                ..Default::default()
+            };
+            let doc = compose_one("- 5m30s\n- PT1S\n- '5m30s'\n- 5").unwrap();
+            let result = construct_document(py, doc.as_ref(), &options).unwrap();
+            let list = result.bind(py).downcast::<PyList>().unwrap();
+            let seconds: f64 = list
+                .get_item(0)
+                .unwrap()
+                .call_method0("total_seconds")
+                .unwrap()
+                .extract()
+                .unwrap();
+            assert_eq!(seconds, 330.0);
+            assert_eq!(
+                list.get_item(1).unwrap().get_type().name().unwrap(),
+                "timedelta"
+            );
+            assert_eq!(list.get_item(2).unwrap().get_type().name().unwrap(), "str");
+            assert_eq!(list.get_item(3).unwrap().get_type().name().unwrap(), "int");
+        });
+    }
+
+    #[test]
+    fn test_uuids() {
+        Python::with_gil(|py| {
+            let options = LoadOptions {
+                uuids: true,
+                ..Default::default()
+            };
+            let yaml =
+                "- 123e4567-e89b-12d3-a456-426614174000\n- '123e4567-e89b-12d3-a456-426614174000'";
+            let doc = compose_one(yaml).unwrap();
+            let result = construct_document(py, doc.as_ref(), &options).unwrap();
+            let list = result.bind(py).downcast::<PyList>().unwrap();
+            assert_eq!(list.get_item(0).unwrap().get_type().name().unwrap(), "UUID");
+            assert_eq!(list.get_item(1).unwrap().get_type().name().unwrap(), "str");
+        });
+    }
+
+    #[test]
+    fn test_ip_addresses() {
+        Python::with_gil(|py| {
+            let options = LoadOptions {
+                ip_addresses: true,
+                ..Default::default()
+            };
+            let doc = compose_one("[10.0.0.1, 10.0.0.0/8, 10.0.0.1/8, '::1', 1.5]").unwrap();
+            let result = construct_document(py, doc.as_ref(), &options).unwrap();
+            let list = result.bind(py).downcast::<PyList>().unwrap();
+            let names: Vec<String> = list
+                .iter()
+                .map(|item| item.get_type().name().unwrap().to_string())
+                .collect();
+            assert_eq!(
+                names,
+                [
+                    "IPv4Address",
+                    "IPv4Network",
+                    "IPv4Interface",
+                    "str",
+                    "float"
+                ]
+            );
+        });
+    }
+
+    #[test]
+    fn test_kubernetes_quantities() {
+        Python::with_gil(|py| {
+            let options = LoadOptions {
+                kubernetes_quantities: true,
+                ..Default::default()
+            };
+            let yaml = "containers:\n- name: app\n  memory: 1Gi\n  resources:\n    limits: {cpu: 500m, memory: 128Mi}\n    requests: {cpu: '1', memory: huge}";
+            let doc = compose_one(yaml).unwrap();
+            let result = construct_document(py, doc.as_ref(), &options).unwrap();
+            let container = result
+                .bind(py)
+                .get_item("containers")
+                .unwrap()
+                .get_item(0)
+                .unwrap();
+            let resources = container.get_item("resources").unwrap();
+            let limits = resources.get_item("limits").unwrap();
+            let requests = resources.get_item("requests").unwrap();
+
+            let cpu: i64 = limits.get_item("cpu").unwrap().extract().unwrap();
+            let memory: i64 = limits.get_item("memory").unwrap().extract().unwrap();
+            let requested: i64 = requests.get_item("cpu").unwrap().extract().unwrap();
+            let huge: String = requests.get_item("memory").unwrap().extract().unwrap();
+            let outside: String = container.get_item("memory").unwrap().extract().unwrap();
+            assert_eq!((cpu, memory, requested), (500, 134217728, 1000));
+            assert_eq!(huge, "huge");
+            assert_eq!(outside, "1Gi");
+        });
+    }
+
+    #[test]
+    fn test_merge_keys() {
+        Python::with_gil(|py| {
+            let yaml = "base: &b {x: 1, y: 2}\nchild:\n  <<: *b\n  y: 3";
+            let result = load(py, yaml).unwrap();
+            let dict = result.bind(py).downcast::<PyDict>().unwrap();
+            let child = dict.get_item("child").unwrap().unwrap();
+            let child = child.downcast::<PyDict>().unwrap();
+            let x: i64 = child.get_item("x").unwrap().unwrap().extract().unwrap();
+            let y: i64 = child.get_item("y").unwrap().unwrap().extract().unwrap();
+            assert_eq!((x, y), (1, 3));
+            assert!(child.get_item("<<").unwrap().is_none());
+        });
+    }
+
+    #[test]
+    fn test_aliases_share_identity() {
+        Python::with_gil(|py| {
+            let result = load(py, "a: &x [1]\nb: *x").unwrap();
+            let dict = result.bind(py).downcast::<PyDict>().unwrap();
+            let a = dict.get_item("a").unwrap().unwrap();
+            let b = dict.get_item("b").unwrap().unwrap();
+            assert!(a.is(&b));
+        });
+    }
+
+    #[test]
+    fn test_preserve_aliases() {
+        Python::with_gil(|py| {
+            let shared = |options: LoadOptions| {
+                let doc = compose_one("a: &x {n: 1}\nb: *x").unwrap();
+                let result = construct_document(py, doc.as_ref(), &options).unwrap();
+                let dict = result.bind(py).downcast::<PyDict>().unwrap().clone();
+                let a = dict.get_item("a").unwrap().unwrap();
+                let b = dict.get_item("b").unwrap().unwrap();
+                a.is(&b)
+            };
+            assert!(!shared(LoadOptions::default()));
+            assert!(shared(LoadOptions {
+                preserve_aliases: true,
+                ..Default::default()
+            }));
+        });
+    }
+
+    #[test]
+    fn test_unknown_tag_rejected() {
+        Python::with_gil(|py| {
+            assert!(load(py, "!Ref foo").is_err());
+            assert!(load(py, "!!int abc").is_err());
+        });
+    }
+
+    #[test]
+    fn test_allowed_tags() {
+        Python::with_gil(|py| {
+            let repr = |yaml: &str, tag_strategy: TagStrategy| -> PyResult<String> {
+                let options = LoadOptions {
+                    allowed_tags: vec!["!Ref".to_string(), "!Sub".to_string()],
+                    tag_strategy,
+                    ..Default::default()
+                };
+                let doc = compose_one(yaml)?;
+                let result = construct_document(py, doc.as_ref(), &options)?;
+                Ok(result.bind(py).repr()?.to_string())
+            };
+            let yaml = "a: !Ref 1\nb: !Ref '1'\nc: !Sub [x, 2]\n";
+            assert_eq!(
+                repr(yaml, TagStrategy::Value).unwrap(),
+                "{'a': 1, 'b': '1', 'c': ['x', 2]}"
+            );
+            assert_eq!(
+                repr(yaml, TagStrategy::Mapping).unwrap(),
+                "{'a': {'Ref': 1}, 'b': {'Ref': '1'}, 'c': {'Sub': ['x', 2]}}"
+            );
+            // Everything else is still rejected
+            assert!(repr("!GetAtt x", TagStrategy::Value).is_err());
+        });
+    }
+
+    #[test]
+    fn test_empty_document() {
+        Python::with_gil(|py| {
+            let options = LoadOptions::default();
+            assert!(construct_document(py, None, &options).unwrap().is_none(py));
+        });
+    }
+
+    #[test]
+    fn test_construct_node_resolves_earlier_anchors() {
+        Python::with_gil(|py| {
+            let document = compose_one("base: &b {a: 1}\nitems:\n  - *b\n")
+                .unwrap()
+                .unwrap();
+            let NodeKind::Mapping { pairs, .. } = &document.root.kind else {
+                panic!("expected a mapping");
+            };
+            let options = LoadOptions::default();
+            let items = construct_node(py, &document.root, &pairs[1].1, &options).unwrap();
+            let expected = py.eval_bound("[{'a': 1}]", None, None).unwrap();
+            assert!(items.bind(py).eq(expected).unwrap());
+        });
+    }
+}
@@ -0,0 +1,407 @@
+//! Sniff what kind of file a YAML document is
+//!
+//! Routing files to the right validator should not cost a full load. This
+//! reads the libyaml event stream of the first non-empty document and keeps
+//! only its outline (keys and scalars down to `MAX_DEPTH`; anything deeper
+//! is skipped without being built), then matches structural fingerprints:
+//!
+//! * `kubernetes`: a mapping with scalar `apiVersion` and `kind`
+//! * `github-workflow`: a mapping with `on` and a `jobs` mapping
+//! * `docker-compose`: a mapping with a `services` mapping
+//! * `ansible-playbook`: a list of mappings, one with `hosts` or
+//!   `import_playbook`
+//! * `ansible-tasks`: a list of mappings, one using a task keyword
+//!   (`when`, `register`, `block`, ...) or an `ansible.builtin.` module
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::error::YAMLError;
+use crate::events::{Event, EventKind, Parser};
+
+/// Deepest level kept in the outline (the root is level 0)
+const MAX_DEPTH: usize = 3;
+
+/// Keys only Ansible tasks use
+const TASK_KEYWORDS: &[&str] = &[
+    "block",
+    "include_tasks",
+    "import_tasks",
+    "include_role",
+    "import_role",
+    "register",
+    "when",
+    "notify",
+    "loop",
+    "with_items",
+    "become",
+];
+
+/// Outline of a document
+#[derive(Clone, Debug, PartialEq)]
+enum Shape {
+    Scalar(String),
+    /// Pairs with scalar keys, in order (empty below `MAX_DEPTH`)
+    Mapping(Vec<(String, Shape)>),
+    /// Items (empty below `MAX_DEPTH`)
+    Sequence(Vec<Shape>),
+    Alias,
+}
+
+impl Shape {
+    fn get(&self, key: &str) -> Option<&Shape> {
+        match self {
+            Shape::Mapping(pairs) => pairs.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn text(&self) -> Option<&str> {
+        match self {
+            Shape::Scalar(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn keys(&self) -> Vec<String> {
+        match self {
+            Shape::Mapping(pairs) => pairs.iter().map(|(key, _)| key.clone()).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Scalar, or the scalar items of a sequence
+    fn texts(&self) -> Vec<String> {
+        match self {
+            Shape::Scalar(value) => vec![value.clone()],
+            Shape::Sequence(items) => items
+                .iter()
+                .filter_map(|item| item.text().map(str::to_string))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// A key field of a detected document
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Field {
+    Text(String),
+    List(Vec<String>),
+}
+
+/// What a document is, and the fields that identify it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Detected {
+    pub label: &'static str,
+    pub fields: Vec<(&'static str, Field)>,
+}
+
+struct Outliner<'input> {
+    parser: Parser<'input>,
+}
+
+impl Outliner<'_> {
+    /// Outline of the first non-empty document (`None` if there is none)
+    fn first_document(&mut self) -> Result<Option<Shape>, YAMLError> {
+        loop {
+            match self.parser.next_event()?.kind {
+                EventKind::StreamEnd => return Ok(None),
+                EventKind::DocumentStart { .. } => {
+                    let event = self.parser.next_event()?;
+                    let root = self.shape(event, 0)?;
+                    if root != Shape::Scalar(String::new()) {
+                        return Ok(Some(root));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Outline of the node starting with `event`, at `depth`
+    fn shape(&mut self, event: Event, depth: usize) -> Result<Shape, YAMLError> {
+        match event.kind {
+            EventKind::Scalar { value, .. } => Ok(Shape::Scalar(value)),
+            EventKind::Alias { .. } => Ok(Shape::Alias),
+            EventKind::MappingStart { .. } if depth >= MAX_DEPTH => {
+                self.skip()?;
+                Ok(Shape::Mapping(Vec::new()))
+            }
+            EventKind::SequenceStart { .. } if depth >= MAX_DEPTH => {
+                self.skip()?;
+                Ok(Shape::Sequence(Vec::new()))
+            }
+            EventKind::MappingStart { .. } => {
+                let mut pairs = Vec::new();
+                while let Some(key) = self.child(depth)? {
+                    let event = self.parser.next_event()?;
+                    let value = self.shape(event, depth + 1)?;
+                    if let Shape::Scalar(key) = key {
+                        pairs.push((key, value));
+                    }
+                }
+                Ok(Shape::Mapping(pairs))
+            }
+            EventKind::SequenceStart { .. } => {
+                let mut items = Vec::new();
+                while let Some(item) = self.child(depth)? {
+                    items.push(item);
+                }
+                Ok(Shape::Sequence(items))
+            }
+            other => Err(YAMLError::parse(
+                event.start.line + 1,
+                event.start.column + 1,
+                format!("unexpected event {:?} in a document", other),
+            )),
+        }
+    }
+
+    /// Next child of a collection at `depth`, or `None` at its end
+    fn child(&mut self, depth: usize) -> Result<Option<Shape>, YAMLError> {
+        let event = self.parser.next_event()?;
+        match event.kind {
+            EventKind::MappingEnd | EventKind::SequenceEnd => Ok(None),
+            _ => self.shape(event, depth + 1).map(Some),
+        }
+    }
+
+    /// Skip the rest of a collection, however deep
+    fn skip(&mut self) -> Result<(), YAMLError> {
+        let mut open = 1;
+        while open > 0 {
+            match self.parser.next_event()?.kind {
+                EventKind::MappingStart { .. } | EventKind::SequenceStart { .. } => open += 1,
+                EventKind::MappingEnd | EventKind::SequenceEnd => open -= 1,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+fn text_field(name: &'static str, shape: Option<&Shape>) -> Option<(&'static str, Field)> {
+    Some((name, Field::Text(shape?.text()?.to_string())))
+}
+
+/// Match the fingerprints against a document outline
+fn classify(root: &Shape) -> Option<Detected> {
+    if let (Some(api_version), Some(kind)) = (
+        root.get("apiVersion").and_then(Shape::text),
+        root.get("kind").and_then(Shape::text),
+    ) {
+        let metadata = root.get("metadata");
+        let mut fields = vec![
+            ("apiVersion", Field::Text(api_version.to_string())),
+            ("kind", Field::Text(kind.to_string())),
+        ];
+        fields.extend(text_field("name", metadata.and_then(|m| m.get("name"))));
+        fields.extend(text_field(
+            "namespace",
+            metadata.and_then(|m| m.get("namespace")),
+        ));
+        return Some(Detected {
+            label: "kubernetes",
+            fields,
+        });
+    }
+
+    if let (Some(on), Some(jobs @ Shape::Mapping(_))) = (root.get("on"), root.get("jobs")) {
+        let triggers = match on {
+            Shape::Mapping(_) => on.keys(),
+            _ => on.texts(),
+        };
+        let mut fields: Vec<_> = text_field("name", root.get("name")).into_iter().collect();
+        fields.push(("on", Field::List(triggers)));
+        fields.push(("jobs", Field::List(jobs.keys())));
+        return Some(Detected {
+            label: "github-workflow",
+            fields,
+        });
+    }
+
+    if let Some(services @ Shape::Mapping(_)) = root.get("services") {
+        let mut fields: Vec<_> = text_field("version", root.get("version"))
+            .into_iter()
+            .collect();
+        fields.push(("services", Field::List(services.keys())));
+        return Some(Detected {
+            label: "docker-compose",
+            fields,
+        });
+    }
+
+    let Shape::Sequence(items) = root else {
+        return None;
+    };
+    if items.is_empty() || !items.iter().all(|item| matches!(item, Shape::Mapping(_))) {
+        return None;
+    }
+    let names = || {
+        items
+            .iter()
+            .filter_map(|item| item.get("name").and_then(Shape::text))
+            .map(str::to_string)
+            .collect()
+    };
+    let has_key = |test: &dyn Fn(&str) -> bool| {
+        items
+            .iter()
+            .any(|item| item.keys().iter().any(|key| test(key)))
+    };
+    if has_key(&|key| matches!(key, "hosts" | "import_playbook")) {
+        let hosts = items
+            .iter()
+            .filter_map(|item| item.get("hosts"))
+            .flat_map(Shape::texts)
+            .collect();
+        return Some(Detected {
+            label: "ansible-playbook",
+            fields: vec![
+                ("hosts", Field::List(hosts)),
+                ("names", Field::List(names())),
+            ],
+        });
+    }
+    if has_key(&|key| TASK_KEYWORDS.contains(&key) || key.starts_with("ansible.builtin.")) {
+        return Some(Detected {
+            label: "ansible-tasks",
+            fields: vec![("names", Field::List(names()))],
+        });
+    }
+    None
+}
+
+/// Detect the type of the first non-empty document in `yaml_str`
+///
+/// # Errors
+/// * Parse errors in the part of the stream that was read
+pub fn detect(yaml_str: &str) -> Result<Option<Detected>, YAMLError> {
+    let mut outliner = Outliner {
+        parser: Parser::new(yaml_str),
+    };
+    Ok(outliner.first_document()?.as_ref().and_then(classify))
+}
+
+/// Classify a document by structural fingerprints, without loading it
+///
+/// # Arguments
+/// * `yaml_str` - YAML content; only the first non-empty document is read
+///
+/// # Returns
+/// `(label, fields)`: one of `"kubernetes"`, `"github-workflow"`,
+/// `"docker-compose"`, `"ansible-playbook"` or `"ansible-tasks"` (`None`
+/// when nothing matches), and a dict of the fields that identify it, e.g.
+/// `{"apiVersion": "apps/v1", "kind": "Deployment", "name": "web"}`
+///
+/// # Example
+/// ```python
+/// label, fields = rustyyaml.detect_type(text)
+/// validators[label].check(text)
+/// ```
+#[pyfunction]
+pub fn detect_type(py: Python, yaml_str: &str) -> PyResult<(Option<&'static str>, PyObject)> {
+    let detected = py.allow_threads(|| detect(yaml_str))?;
+    let fields = PyDict::new_bound(py);
+    let Some(detected) = detected else {
+        return Ok((None, fields.into()));
+    };
+    for (name, field) in detected.fields {
+        match field {
+            Field::Text(text) => fields.set_item(name, text)?,
+            Field::List(items) => fields.set_item(name, items)?,
+        }
+    }
+    Ok((Some(detected.label), fields.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn label(yaml_str: &str) -> Option<&'static str> {
+        detect(yaml_str).unwrap().map(|detected| detected.label)
+    }
+
+    fn list(items: &[&str]) -> Field {
+        Field::List(items.iter().map(|item| item.to_string()).collect())
+    }
+
+    fn text(value: &str) -> Field {
+        Field::Text(value.to_string())
+    }
+
+    #[test]
+    fn test_kubernetes() {
+        let source = "# leading comment\n---\n---\napiVersion: apps/v1\nkind: Deployment\n\
+                      metadata:\n  name: web\n  labels: {app: web}\n\
+                      spec: {template: {spec: {containers: [{image: nginx}]}}}\n\
+                      ---\nnot: parsed: [\n";
+        assert_eq!(
+            detect(source).unwrap(),
+            Some(Detected {
+                label: "kubernetes",
+                fields: vec![
+                    ("apiVersion", text("apps/v1")),
+                    ("kind", text("Deployment")),
+                    ("name", text("web")),
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn test_workflow_and_compose() {
+        let workflow = "name: CI\non: [push, pull_request]\njobs:\n  test: {runs-on: x}\n";
+        let detected = detect(workflow).unwrap().unwrap();
+        assert_eq!(detected.label, "github-workflow");
+        assert_eq!(
+            detected.fields,
+            [
+                ("name", text("CI")),
+                ("on", list(&["push", "pull_request"])),
+                ("jobs", list(&["test"])),
+            ]
+        );
+        let workflow = "on:\n  push: {branches: [main]}\njobs: {build: {}}\n";
+        assert_eq!(
+            detect(workflow).unwrap().unwrap().fields[0],
+            ("on", list(&["push"]))
+        );
+
+        let compose = "version: '3.8'\nservices:\n  web: {image: nginx}\n  db: {}\n";
+        let detected = detect(compose).unwrap().unwrap();
+        assert_eq!(detected.label, "docker-compose");
+        assert_eq!(
+            detected.fields,
+            [("version", text("3.8")), ("services", list(&["web", "db"]))]
+        );
+    }
+
+    #[test]
+    fn test_ansible() {
+        let playbook = "- name: Web\n  hosts: [web, api]\n  tasks: []\n- hosts: db\n";
+        let detected = detect(playbook).unwrap().unwrap();
+        assert_eq!(detected.label, "ansible-playbook");
+        assert_eq!(
+            detected.fields,
+            [
+                ("hosts", list(&["web", "api", "db"])),
+                ("names", list(&["Web"]))
+            ]
+        );
+        let tasks = "- name: Install\n  ansible.builtin.apt: {name: nginx}\n\
+                     - name: Start\n  service: {name: nginx}\n  when: start\n";
+        assert_eq!(label(tasks), Some("ansible-tasks"));
+    }
+
+    #[test]
+    fn test_unknown() {
+        assert_eq!(label(""), None);
+        assert_eq!(label("a: 1\n"), None);
+        assert_eq!(label("- name: just a list\n"), None);
+        assert_eq!(label("plain"), None);
+        assert!(detect("a: [").is_err());
+    }
+}
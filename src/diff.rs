@@ -0,0 +1,512 @@
+//! Structural diffs and config drift
+//!
+//! `diff` compares two parsed documents and lists what was added, removed
+//! or changed, using the key path syntax of `grep` (`spec.ports[0].port`).
+//! Mappings are compared key by key and sequences item by item; scalars
+//! compare like filters do, so `1` and `1.0` are equal.
+//!
+//! `drift` runs `diff` over a whole directory against a baseline, which is
+//! either another directory (files paired by relative path, documents by
+//! position) or a bundle (documents paired by Kubernetes identity).
+
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+use rayon::prelude::*;
+use serde_yaml::Value;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::batch;
+use crate::error::YAMLError;
+use crate::filter;
+use crate::options::LoadOptions;
+use crate::parser::{self, Parsed};
+use crate::search::key_text;
+use crate::types::yaml_to_python;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+    Added,
+    Removed,
+    Changed,
+}
+
+impl Op {
+    pub fn name(self) -> &'static str {
+        match self {
+            Op::Added => "added",
+            Op::Removed => "removed",
+            Op::Changed => "changed",
+        }
+    }
+}
+
+/// One difference between two documents
+#[derive(Clone, Debug, PartialEq)]
+pub struct Change {
+    pub op: Op,
+    /// Key path of the difference (`""` for the whole document)
+    pub path: String,
+    pub old: Option<Value>,
+    pub new: Option<Value>,
+}
+
+impl Change {
+    fn added(path: String, new: &Value) -> Self {
+        Change {
+            op: Op::Added,
+            path,
+            old: None,
+            new: Some(new.clone()),
+        }
+    }
+
+    fn removed(path: String, old: &Value) -> Self {
+        Change {
+            op: Op::Removed,
+            path,
+            old: Some(old.clone()),
+            new: None,
+        }
+    }
+}
+
+/// Every difference between `old` and `new`, in document order
+pub fn diff(old: &Value, new: &Value) -> Vec<Change> {
+    let mut changes = Vec::new();
+    diff_into(old, new, String::new(), &mut changes);
+    changes
+}
+
+fn diff_into(old: &Value, new: &Value, path: String, changes: &mut Vec<Change>) {
+    match (old, new) {
+        (Value::Mapping(old_map), Value::Mapping(new_map)) => {
+            for (key, old_value) in old_map {
+                let child = child_path(&path, key);
+                match new_map.get(key) {
+                    Some(new_value) => diff_into(old_value, new_value, child, changes),
+                    None => changes.push(Change::removed(child, old_value)),
+                }
+            }
+            for (key, new_value) in new_map {
+                if !old_map.contains_key(key) {
+                    changes.push(Change::added(child_path(&path, key), new_value));
+                }
+            }
+        }
+        (Value::Sequence(old_items), Value::Sequence(new_items)) => {
+            for (index, old_value) in old_items.iter().enumerate() {
+                let child = format!("{}[{}]", path, index);
+                match new_items.get(index) {
+                    Some(new_value) => diff_into(old_value, new_value, child, changes),
+                    None => changes.push(Change::removed(child, old_value)),
+                }
+            }
+            for (index, new_value) in new_items.iter().enumerate().skip(old_items.len()) {
+                changes.push(Change::added(format!("{}[{}]", path, index), new_value));
+            }
+        }
+        (Value::Tagged(old_tagged), Value::Tagged(new_tagged))
+            if old_tagged.tag == new_tagged.tag =>
+        {
+            diff_into(&old_tagged.value, &new_tagged.value, path, changes)
+        }
+        _ => {
+            if !filter::matches(old, new) {
+                changes.push(Change {
+                    op: Op::Changed,
+                    path,
+                    old: Some(old.clone()),
+                    new: Some(new.clone()),
+                });
+            }
+        }
+    }
+}
+
+fn child_path(path: &str, key: &Value) -> String {
+    let key = key_text(key).unwrap_or_else(|| {
+        serde_yaml::to_string(key)
+            .map(|text| text.trim_end().to_string())
+            .unwrap_or_default()
+    });
+    if path.is_empty() {
+        key
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    Unchanged,
+    Changed,
+    /// Only in the directory
+    Added,
+    /// Only in the baseline
+    Removed,
+}
+
+impl Status {
+    pub fn name(self) -> &'static str {
+        match self {
+            Status::Unchanged => "unchanged",
+            Status::Changed => "changed",
+            Status::Added => "added",
+            Status::Removed => "removed",
+        }
+    }
+}
+
+/// Drift of one file: its changes, each with the index of its document
+#[derive(Debug)]
+pub struct FileDrift {
+    pub file: String,
+    pub status: Status,
+    pub changes: Vec<(usize, Change)>,
+}
+
+impl FileDrift {
+    fn whole(file: String, status: Status, documents: &[Value]) -> Self {
+        let changes = documents
+            .iter()
+            .enumerate()
+            .filter(|(_, document)| !document.is_null())
+            .map(|(index, document)| {
+                let change = match status {
+                    Status::Removed => Change::removed(String::new(), document),
+                    _ => Change::added(String::new(), document),
+                };
+                (index, change)
+            })
+            .collect();
+        FileDrift {
+            file,
+            status,
+            changes,
+        }
+    }
+
+    fn compared(file: String, changes: Vec<(usize, Change)>) -> Self {
+        let status = if changes.is_empty() {
+            Status::Unchanged
+        } else {
+            Status::Changed
+        };
+        FileDrift {
+            file,
+            status,
+            changes,
+        }
+    }
+}
+
+/// Every document of a file (no GIL needed)
+pub fn read_documents(path: &Path) -> Result<Vec<Value>, YAMLError> {
+    let content = batch::read_file(path)?;
+    let documents = parser::parse_documents(&content, &LoadOptions::default(), true)
+        .map_err(|err| err.in_file(path))?;
+    Ok(documents
+        .into_iter()
+        .filter_map(|document| match document {
+            Parsed::Value(value) => Some(value),
+            Parsed::Composed(_) => None,
+        })
+        .collect())
+}
+
+/// Compare documents by position
+fn diff_documents(old: &[Value], new: &[Value]) -> Vec<(usize, Change)> {
+    let mut changes = Vec::new();
+    for index in 0..old.len().max(new.len()) {
+        match (old.get(index), new.get(index)) {
+            (Some(old), Some(new)) => {
+                changes.extend(diff(old, new).into_iter().map(|change| (index, change)))
+            }
+            (Some(old), None) => changes.push((index, Change::removed(String::new(), old))),
+            (None, Some(new)) => changes.push((index, Change::added(String::new(), new))),
+            (None, None) => {}
+        }
+    }
+    changes
+}
+
+/// `kind/namespace/name` of a Kubernetes-style document
+pub fn identity(document: &Value) -> Option<String> {
+    let kind = document.get("kind")?.as_str()?;
+    let metadata = document.get("metadata")?;
+    let name = metadata.get("name")?.as_str()?;
+    let namespace = metadata
+        .get("namespace")
+        .and_then(Value::as_str)
+        .unwrap_or("");
+    Some(format!("{}/{}/{}", kind, namespace, name))
+}
+
+fn relative(path: &Path, root: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .to_string()
+}
+
+fn drift_against_directory(
+    directory: &Path,
+    baseline: &Path,
+    recursive: bool,
+) -> Result<Vec<FileDrift>, YAMLError> {
+    let current: HashMap<String, PathBuf> = batch::find_yaml_files(directory, recursive)?
+        .into_iter()
+        .map(|path| (relative(&path, directory), path))
+        .collect();
+    let previous: HashMap<String, PathBuf> = batch::find_yaml_files(baseline, recursive)?
+        .into_iter()
+        .map(|path| (relative(&path, baseline), path))
+        .collect();
+
+    let mut files: Vec<&String> = current.keys().chain(previous.keys()).collect();
+    files.sort();
+    files.dedup();
+
+    files
+        .par_iter()
+        .map(|&file| {
+            let drift = match (previous.get(file), current.get(file)) {
+                (Some(old), Some(new)) => FileDrift::compared(
+                    file.clone(),
+                    diff_documents(&read_documents(old)?, &read_documents(new)?),
+                ),
+                (None, Some(new)) => {
+                    FileDrift::whole(file.clone(), Status::Added, &read_documents(new)?)
+                }
+                (Some(old), None) => {
+                    FileDrift::whole(file.clone(), Status::Removed, &read_documents(old)?)
+                }
+                (None, None) => unreachable!("file comes from one of the maps"),
+            };
+            Ok(drift)
+        })
+        .collect()
+}
+
+fn drift_against_bundle(
+    directory: &Path,
+    baseline: &Path,
+    recursive: bool,
+) -> Result<Vec<FileDrift>, YAMLError> {
+    let bundled = read_documents(baseline)?;
+    let mut by_identity: HashMap<String, &Value> = HashMap::new();
+    for document in &bundled {
+        if let Some(id) = identity(document) {
+            by_identity.entry(id).or_insert(document);
+        }
+    }
+
+    let mut paths = batch::find_yaml_files(directory, recursive)?;
+    paths.sort();
+
+    let compared: Vec<(FileDrift, Vec<String>)> = paths
+        .par_iter()
+        .map(|path| {
+            let file = relative(path, directory);
+            let documents = read_documents(path)?;
+            let mut matched = Vec::new();
+            let mut changes = Vec::new();
+            for (index, document) in documents.iter().enumerate() {
+                if document.is_null() {
+                    continue;
+                }
+                let old = identity(document).and_then(|id| Some((by_identity.get(&id)?, id)));
+                match old {
+                    Some((old, id)) => {
+                        changes.extend(diff(old, document).into_iter().map(|c| (index, c)));
+                        matched.push(id);
+                    }
+                    None => changes.push((index, Change::added(String::new(), document))),
+                }
+            }
+            let drift = if matched.is_empty() && !changes.is_empty() {
+                FileDrift {
+                    file,
+                    status: Status::Added,
+                    changes,
+                }
+            } else {
+                FileDrift::compared(file, changes)
+            };
+            Ok((drift, matched))
+        })
+        .collect::<Result<_, YAMLError>>()?;
+
+    let matched: HashSet<String> = compared
+        .iter()
+        .flat_map(|(_, ids)| ids.iter().cloned())
+        .collect();
+    let mut report: Vec<FileDrift> = compared.into_iter().map(|(drift, _)| drift).collect();
+
+    // Baseline documents no file accounts for
+    let removed: Vec<(usize, Change)> = bundled
+        .iter()
+        .enumerate()
+        .filter(|(_, document)| {
+            !document.is_null() && identity(document).is_none_or(|id| !matched.contains(&id))
+        })
+        .map(|(index, document)| (index, Change::removed(String::new(), document)))
+        .collect();
+    if !removed.is_empty() {
+        report.push(FileDrift {
+            file: baseline.to_string_lossy().to_string(),
+            status: Status::Removed,
+            changes: removed,
+        });
+    }
+    Ok(report)
+}
+
+fn change_to_python(py: Python, document: usize, change: &Change) -> PyResult<PyObject> {
+    let dict = PyDict::new_bound(py);
+    dict.set_item("document", document)?;
+    dict.set_item("op", change.op.name())?;
+    dict.set_item("path", &change.path)?;
+    if let Some(old) = &change.old {
+        dict.set_item("old", yaml_to_python(py, old)?)?;
+    }
+    if let Some(new) = &change.new {
+        dict.set_item("new", yaml_to_python(py, new)?)?;
+    }
+    Ok(dict.into())
+}
+
+/// Compare a directory of YAML files against a baseline
+///
+/// Files are read and diffed in parallel; only the differences are
+/// converted to Python.
+///
+/// With a baseline directory, files are paired by relative path and their
+/// documents by position. With a baseline bundle, documents are paired by
+/// `kind`, `metadata.namespace` and `metadata.name`; baseline documents no
+/// file matches are reported under the bundle's own path as `"removed"`.
+///
+/// # Arguments
+/// * `directory` - Directory containing the current .yaml/.yml files
+/// * `baseline` - Baseline directory, or a multi-document bundle file
+/// * `recursive` - If true, include subdirectories
+///
+/// # Returns
+/// One dict per file, ordered by file name: `file` (relative to its
+/// directory), `status` (`"unchanged"`, `"changed"`, `"added"` or
+/// `"removed"`) and `changes`, a list of dicts with `document`, `op`
+/// (`"added"`, `"removed"`, `"changed"`), `path` (`""` for a whole
+/// document) and `old`/`new` values where they exist
+///
+/// # Example
+/// ```python
+/// for report in rustyyaml.drift("./live", "./baseline", recursive=True):
+///     for change in report["changes"]:
+///         print(report["file"], change["op"], change["path"])
+/// ```
+#[pyfunction]
+#[pyo3(signature = (directory, baseline, recursive=false))]
+pub fn drift(
+    py: Python,
+    directory: PathBuf,
+    baseline: PathBuf,
+    recursive: bool,
+) -> PyResult<PyObject> {
+    let report = py.allow_threads(|| {
+        if baseline.is_dir() {
+            drift_against_directory(&directory, &baseline, recursive)
+        } else if baseline.is_file() {
+            drift_against_bundle(&directory, &baseline, recursive)
+        } else {
+            Err(YAMLError::FileNotFound {
+                path: baseline.to_string_lossy().to_string(),
+            })
+        }
+    })?;
+
+    let reports = PyList::empty_bound(py);
+    for file in &report {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("file", &file.file)?;
+        dict.set_item("status", file.status.name())?;
+        let changes = PyList::empty_bound(py);
+        for (document, change) in &file.changes {
+            changes.append(change_to_python(py, *document, change)?)?;
+        }
+        dict.set_item("changes", changes)?;
+        reports.append(dict)?;
+    }
+    Ok(reports.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn yaml(text: &str) -> Value {
+        serde_yaml::from_str(text).unwrap()
+    }
+
+    fn summary(changes: &[Change]) -> Vec<(&'static str, &str)> {
+        changes
+            .iter()
+            .map(|change| (change.op.name(), change.path.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_mappings_and_sequences() {
+        let old = yaml("spec: {replicas: 2, ports: [80, 443], paused: true}");
+        let new = yaml("spec: {replicas: 3, ports: [80], selector: {app: web}}");
+        let changes = diff(&old, &new);
+        assert_eq!(
+            summary(&changes),
+            [
+                ("changed", "spec.replicas"),
+                ("removed", "spec.ports[1]"),
+                ("removed", "spec.paused"),
+                ("added", "spec.selector"),
+            ]
+        );
+        assert_eq!(changes[0].old, Some(yaml("2")));
+        assert_eq!(changes[0].new, Some(yaml("3")));
+        assert_eq!(changes[3].old, None);
+    }
+
+    #[test]
+    fn test_diff_scalars() {
+        assert!(diff(&yaml("{a: 1}"), &yaml("{a: 1.0}")).is_empty());
+        assert_eq!(
+            summary(&diff(&yaml("{a: 1}"), &yaml("{a: '1'}"))),
+            [("changed", "a")]
+        );
+        assert_eq!(
+            summary(&diff(&yaml("[1]"), &yaml("{a: 1}"))),
+            [("changed", "")]
+        );
+        assert_eq!(
+            summary(&diff(&yaml("[1, 2]"), &yaml("[1, 2, 3]"))),
+            [("added", "[2]")]
+        );
+    }
+
+    #[test]
+    fn test_identity() {
+        let doc = yaml("kind: Service\nmetadata: {name: web, namespace: prod}");
+        assert_eq!(identity(&doc).as_deref(), Some("Service/prod/web"));
+        let doc = yaml("kind: Namespace\nmetadata: {name: prod}");
+        assert_eq!(identity(&doc).as_deref(), Some("Namespace//prod"));
+        assert!(identity(&yaml("a: 1")).is_none());
+    }
+
+    #[test]
+    fn test_diff_documents_by_position() {
+        let old = [yaml("a: 1"), yaml("b: 2")];
+        let new = [yaml("a: 2")];
+        let changes = diff_documents(&old, &new);
+        assert_eq!(changes.len(), 2);
+        assert_eq!((changes[0].0, changes[0].1.op), (0, Op::Changed));
+        assert_eq!((changes[1].0, changes[1].1.op), (1, Op::Removed));
+    }
+}
@@ -0,0 +1,2038 @@
+//! YAML output (`safe_dump`, `safe_dump_all`, `dump_file`, `dump_all_file`
+//! and the reusable `Dumper`)
+//!
+//! Python data is converted to a `serde_yaml::Value` with
+//! `types::python_to_yaml` and written by a small emitter here rather than
+//! by serde_yaml, so the output follows PyYAML's `safe_dump` layout (block
+//! style, sequences in mappings not indented, `...` after a plain root
+//! scalar) and a later load gives back the same data:
+//!
+//! - strings that would resolve to something else under either the YAML
+//!   1.2 core schema or PyYAML's 1.1 rules (`"yes"`, `"0755"`,
+//!   `"2024-01-01"`) are quoted
+//! - multi-line strings are written as literal blocks (`|`)
+//! - floats always have a `.` or an exponent, so they stay floats
+//! - a dict or list that appears more than once is written once with an
+//!   anchor (`&id001`) and as an alias (`*id001`) after that; data that
+//!   contains itself is an error, since it couldn't be loaded back
+//!
+//! Common stdlib objects are written as PyYAML users would expect: dates
+//! and datetimes as (unquoted) timestamps, `Decimal`s as numbers, `UUID`s
+//! and paths as strings, enum members by value and dataclasses as mappings
+//! of their fields. Timedeltas are written as Go-style durations (`1h30m0s`)
+//! and `ipaddress` objects as their text (`10.0.0.0/8`), which the
+//! `durations=True` and `ip_addresses=True` load options read back. Objects of other types are converted by the function
+//! registered for their type with `add_representer`, if there is one.
+//!
+//! `comments=` writes `#` lines above the keys and items at the key paths
+//! it names (`grep` syntax, so `database.port` or `servers[*].host`);
+//! keys written in flow style can't carry one, so a path that matches no
+//! block-style key or item is an error rather than silently dropped.
+//!
+//! With `canonical=True` the layout is fixed (sorted keys, two-space block
+//! style, no folding) and keys that sort as equal are ordered by their
+//! text, so the same data always dumps to the same bytes however it was
+//! built.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::sync::GILOnceCell;
+use pyo3::types::{PyDict, PyString, PyTuple, PyType};
+use serde_yaml::value::TaggedValue;
+use serde_yaml::{Mapping, Number, Value};
+
+use crate::batch;
+use crate::error::YAMLError;
+use crate::resolve::{self, Scalar, Schema};
+use crate::search::{self, Segment};
+use crate::types::{self, PLAIN_TAG, SHARED_TAG};
+
+/// Longest key written as an implicit key (the YAML limit)
+const MAX_SIMPLE_KEY: usize = 1024;
+
+/// How a string is written
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Style {
+    Plain,
+    SingleQuoted,
+    DoubleQuoted,
+    Literal,
+}
+
+/// Order of mapping keys with `sort_keys`: null, bools, numbers, strings,
+/// then anything else in document order
+fn key_rank(key: &Value) -> u8 {
+    match key {
+        Value::Null => 0,
+        Value::Bool(_) => 1,
+        Value::Number(_) => 2,
+        Value::String(_) => 3,
+        _ => 4,
+    }
+}
+
+fn compare_keys(a: &Value, b: &Value) -> Ordering {
+    key_rank(a).cmp(&key_rank(b)).then_with(|| match (a, b) {
+        (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+        (Value::Number(a), Value::Number(b)) => {
+            let (a, b) = (a.as_f64().unwrap_or(0.0), b.as_f64().unwrap_or(0.0));
+            a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+        }
+        (Value::String(a), Value::String(b)) => a.cmp(b),
+        _ => Ordering::Equal,
+    })
+}
+
+/// A float as YAML that reads back as a float (`1.0`, `1.0e+20`, `.inf`)
+fn format_float(f: f64) -> String {
+    if f.is_nan() {
+        return ".nan".to_string();
+    }
+    if f.is_infinite() {
+        return if f > 0.0 { ".inf" } else { "-.inf" }.to_string();
+    }
+    // Shortest round-trip digits, in exponent form for very large or
+    // small values, like Python's repr
+    let text = format!("{:?}", f);
+    match text.split_once('e') {
+        Some((mantissa, exponent)) => {
+            let mantissa = if mantissa.contains('.') {
+                mantissa.to_string()
+            } else {
+                format!("{}.0", mantissa)
+            };
+            // PyYAML's float pattern needs the exponent's sign
+            match exponent.strip_prefix('-') {
+                Some(digits) => format!("{}e-{}", mantissa, digits),
+                None => format!("{}e+{}", mantissa, exponent),
+            }
+        }
+        None => text,
+    }
+}
+
+fn format_number(n: &Number) -> String {
+    if let Some(i) = n.as_i64() {
+        i.to_string()
+    } else if let Some(u) = n.as_u64() {
+        u.to_string()
+    } else {
+        format_float(n.as_f64().unwrap_or(f64::NAN))
+    }
+}
+
+/// Characters that can be written as they are (YAML's `c-printable`,
+/// without the byte order mark and the characters libyaml reads as line
+/// breaks)
+fn is_printable(c: char) -> bool {
+    matches!(c, '\x20'..='\x7e' | '\u{a0}'..='\u{d7ff}' | '\u{e000}'..='\u{fffd}' | '\u{10000}'..)
+        && !matches!(c, '\u{2028}' | '\u{2029}' | '\u{feff}')
+}
+
+/// Whether `text` can be a plain scalar that reads back as this string
+fn allows_plain(text: &str, flow: bool) -> bool {
+    let Some(first) = text.chars().next() else {
+        return false;
+    };
+    if text.starts_with(' ') || text.ends_with(' ') || !text.chars().all(is_printable) {
+        return false;
+    }
+    if text.starts_with("---") || text.starts_with("...") {
+        return false;
+    }
+    let second = text[first.len_utf8()..].chars().next();
+    match first {
+        // Indicators that may start a plain scalar if a non-space follows
+        '-' | '?' | ':'
+            if second.is_none_or(|c| c == ' ')
+                || (flow && second.is_some_and(|c| ",[]{}".contains(c))) =>
+        {
+            return false;
+        }
+        ',' | '[' | ']' | '{' | '}' | '#' | '&' | '*' | '!' | '|' | '>' | '\'' | '"' | '%'
+        | '@' | '`' => return false,
+        _ => {}
+    }
+    if text.contains(": ") || text.contains(" #") || text.ends_with(':') {
+        return false;
+    }
+    if flow && text.contains([',', '[', ']', '{', '}']) {
+        return false;
+    }
+    resolve::resolve_plain(text, Schema::Core) == Scalar::Str
+        && resolve::resolve_plain(text, Schema::PyYaml) == Scalar::Str
+}
+
+/// The style to write a string in
+fn choose_style(text: &str, flow: bool, key: bool) -> Style {
+    if allows_plain(text, flow) {
+        return Style::Plain;
+    }
+    let printable = text.chars().all(|c| c == '\n' || is_printable(c));
+    if text.contains('\n') {
+        // A literal block keeps every line as it is, but can't be a key
+        // and needs a hint to start with a space or a line break
+        if !key && !flow && printable && !text.starts_with([' ', '\n']) {
+            return Style::Literal;
+        }
+        return Style::DoubleQuoted;
+    }
+    if printable {
+        Style::SingleQuoted
+    } else {
+        Style::DoubleQuoted
+    }
+}
+
+/// `text` as a quoted scalar: single-quoted if `single` and it can be,
+/// otherwise double-quoted
+pub fn quoted(text: &str, single: bool) -> String {
+    let printable = text.chars().all(is_printable);
+    if single && printable {
+        format!("'{}'", text.replace('\'', "''"))
+    } else {
+        double_quoted(text)
+    }
+}
+
+fn double_quoted(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\0' => out.push_str("\\0"),
+            '\x07' => out.push_str("\\a"),
+            '\x08' => out.push_str("\\b"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\x0b' => out.push_str("\\v"),
+            '\x0c' => out.push_str("\\f"),
+            '\r' => out.push_str("\\r"),
+            '\x1b' => out.push_str("\\e"),
+            '\u{85}' => out.push_str("\\N"),
+            '\u{a0}' => out.push_str("\\_"),
+            '\u{2028}' => out.push_str("\\L"),
+            '\u{2029}' => out.push_str("\\P"),
+            c if is_printable(c) => out.push(c),
+            c if (c as u32) <= 0xff => out.push_str(&format!("\\x{:02X}", c as u32)),
+            c if (c as u32) <= 0xffff => out.push_str(&format!("\\u{:04X}", c as u32)),
+            c => out.push_str(&format!("\\U{:08X}", c as u32)),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Which collections are written in flow style (`default_flow_style=`)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FlowStyle {
+    /// Block style throughout (`False`, the default)
+    #[default]
+    Block,
+    /// Flow style throughout (`True`)
+    Flow,
+    /// Flow style for collections that hold only scalars (`None`)
+    Leaves,
+}
+
+/// How booleans are written (`bool_style=`)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BoolStyle {
+    /// `true`/`false` (`"true"`, the default)
+    #[default]
+    Lower,
+    /// `True`/`False` (`"True"`)
+    Title,
+    /// `yes`/`no` (`"yes"`), booleans only to YAML 1.1 readers
+    YesNo,
+}
+
+impl BoolStyle {
+    fn from_python(value: &Bound<'_, PyAny>) -> PyResult<Self> {
+        match value.extract::<String>()?.as_str() {
+            "true" => Ok(BoolStyle::Lower),
+            "True" => Ok(BoolStyle::Title),
+            "yes" => Ok(BoolStyle::YesNo),
+            other => Err(PyValueError::new_err(format!(
+                "bool_style must be 'true', 'True' or 'yes', not '{}'",
+                other
+            ))),
+        }
+    }
+
+    fn text(self, b: bool) -> &'static str {
+        match (self, b) {
+            (BoolStyle::Lower, true) => "true",
+            (BoolStyle::Lower, false) => "false",
+            (BoolStyle::Title, true) => "True",
+            (BoolStyle::Title, false) => "False",
+            (BoolStyle::YesNo, true) => "yes",
+            (BoolStyle::YesNo, false) => "no",
+        }
+    }
+}
+
+/// How `None` is written (`null_style=`)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NullStyle {
+    /// `null` (the default)
+    #[default]
+    Null,
+    /// `~`
+    Tilde,
+    /// Nothing (`key:`, `-`); `null` where a value can't be left out (keys,
+    /// flow collections, a document that is only `None`)
+    Empty,
+}
+
+impl NullStyle {
+    fn from_python(value: &Bound<'_, PyAny>) -> PyResult<Self> {
+        match value.extract::<String>()?.as_str() {
+            "null" => Ok(NullStyle::Null),
+            "~" => Ok(NullStyle::Tilde),
+            "" => Ok(NullStyle::Empty),
+            other => Err(PyValueError::new_err(format!(
+                "null_style must be 'null', '~' or '', not '{}'",
+                other
+            ))),
+        }
+    }
+}
+
+/// Keyword options shared by the dump functions
+#[derive(Clone, Copy, Debug)]
+pub struct DumpOptions {
+    /// Write mapping keys in sorted order (`sort_keys=`)
+    pub sort_keys: bool,
+    /// Spaces per nesting level, 2 to 9 (`indent=`)
+    pub indent: usize,
+    /// Fold long scalars and flow collections at spaces past this column
+    /// (`width=`; `None` never folds)
+    pub width: Option<usize>,
+    pub flow_style: FlowStyle,
+    /// Byte-identical output for equal data (`canonical=`)
+    pub canonical: bool,
+    pub bool_style: BoolStyle,
+    pub null_style: NullStyle,
+}
+
+impl Default for DumpOptions {
+    /// PyYAML's `safe_dump` defaults
+    fn default() -> Self {
+        DumpOptions {
+            sort_keys: true,
+            indent: 2,
+            width: Some(80),
+            flow_style: FlowStyle::Block,
+            canonical: false,
+            bool_style: BoolStyle::Lower,
+            null_style: NullStyle::Null,
+        }
+    }
+}
+
+impl DumpOptions {
+    /// Build options from the `**options` passed to a pyfunction
+    ///
+    /// # Errors
+    /// * `TypeError` for unknown option names or badly typed values
+    /// * `ValueError` for an `indent` outside 2..=9, a zero `width`, an
+    ///   unknown `bool_style` or `null_style`, or layout options combined
+    ///   with `canonical`
+    pub fn from_kwargs(kwargs: Option<&Bound<'_, PyDict>>) -> PyResult<Self> {
+        let mut options = DumpOptions::default();
+        let Some(kwargs) = kwargs else {
+            return Ok(options);
+        };
+        for (key, value) in kwargs.iter() {
+            let key: String = key.extract()?;
+            match key.as_str() {
+                "sort_keys" => options.sort_keys = value.extract()?,
+                "indent" => options.indent = value.extract()?,
+                "width" => options.width = value.extract()?,
+                "default_flow_style" => {
+                    options.flow_style = match value.extract::<Option<bool>>()? {
+                        Some(false) => FlowStyle::Block,
+                        Some(true) => FlowStyle::Flow,
+                        None => FlowStyle::Leaves,
+                    };
+                }
+                "canonical" => options.canonical = value.extract()?,
+                "bool_style" => options.bool_style = BoolStyle::from_python(&value)?,
+                "null_style" => options.null_style = NullStyle::from_python(&value)?,
+                other => {
+                    return Err(PyTypeError::new_err(format!(
+                        "unexpected keyword argument '{}'",
+                        other
+                    )))
+                }
+            }
+        }
+        if !(2..=9).contains(&options.indent) {
+            return Err(PyValueError::new_err(format!(
+                "indent must be between 2 and 9, not {}",
+                options.indent
+            )));
+        }
+        if options.width == Some(0) {
+            return Err(PyValueError::new_err("width must be positive"));
+        }
+        if options.canonical {
+            for name in ["sort_keys", "indent", "width", "default_flow_style"] {
+                if kwargs.contains(name)? {
+                    return Err(PyValueError::new_err(format!(
+                        "canonical=True fixes the layout; it can't be combined with {}",
+                        name
+                    )));
+                }
+            }
+            // Long lines aren't folded, so editing a word never reflows
+            // the lines around it
+            options.width = None;
+        }
+        Ok(options)
+    }
+}
+
+/// A comment to write above the keys and items its path matches
+/// (`comments=`)
+pub struct Comment {
+    path: String,
+    segments: Vec<Segment>,
+    text: String,
+    used: bool,
+}
+
+/// Parse `comments=`: key path to comment text
+///
+/// # Errors
+/// * `TypeError` if it isn't a dict of strings
+/// * `ValueError` naming the first malformed path
+pub fn extract_comments(comments: &Bound<'_, PyAny>) -> PyResult<Vec<Comment>> {
+    let comments = comments.downcast::<PyDict>()?;
+    comments
+        .iter()
+        .map(|(path, text)| {
+            let path: String = path.extract()?;
+            let segments = search::parse_key_path(&path)
+                .ok_or_else(|| PyValueError::new_err(format!("invalid key path '{}'", path)))?;
+            Ok(Comment {
+                path,
+                segments,
+                text: text.extract()?,
+                used: false,
+            })
+        })
+        .collect()
+}
+
+/// Does the key path pattern `segments` name the node at `path`?
+fn path_matches(segments: &[Segment], path: &[Option<Segment>]) -> bool {
+    segments.len() == path.len()
+        && segments
+            .iter()
+            .zip(path)
+            .all(|(segment, step)| match (segment, step) {
+                (Segment::AnyKey, Some(Segment::Key(_))) => true,
+                (Segment::AnyIndex, Some(Segment::Index(_))) => true,
+                (segment, Some(step)) => segment == step,
+                (_, None) => false,
+            })
+}
+
+/// Writes one document
+struct Emitter {
+    out: String,
+    options: DumpOptions,
+    /// Anchor number of each shared container written so far in the
+    /// document, by its `SHARED_TAG` tag
+    anchors: HashMap<String, usize>,
+    comments: Vec<Comment>,
+    /// Keys and indexes from the root to the node being written (`None`
+    /// for keys a path can't name); only kept when there are comments
+    path: Vec<Option<Segment>>,
+}
+
+impl Emitter {
+    fn new(options: DumpOptions) -> Self {
+        Emitter {
+            out: String::new(),
+            options,
+            anchors: HashMap::new(),
+            comments: Vec::new(),
+            path: Vec::new(),
+        }
+    }
+
+    /// `text` as `#` lines at `indent`
+    fn comment_lines(&mut self, text: &str, indent: usize) {
+        for line in text.lines() {
+            self.indent(indent);
+            self.out.push('#');
+            if !line.is_empty() {
+                self.out.push(' ');
+                self.out.push_str(line);
+            }
+            self.out.push('\n');
+        }
+    }
+
+    /// Step into a mapping entry or sequence item
+    fn enter(&mut self, step: impl FnOnce() -> Option<Segment>) {
+        if !self.comments.is_empty() {
+            self.path.push(step());
+        }
+    }
+
+    fn leave(&mut self) {
+        self.path.pop();
+    }
+
+    /// The comments whose paths match the current node, at `indent`
+    fn write_comments(&mut self, indent: usize) {
+        let mut texts = Vec::new();
+        for comment in &mut self.comments {
+            if path_matches(&comment.segments, &self.path) {
+                comment.used = true;
+                texts.push(comment.text.clone());
+            }
+        }
+        for text in texts {
+            self.comment_lines(&text, indent);
+        }
+    }
+
+    /// The comments of a sequence item, and those of the first entry of a
+    /// block collection that starts on the item's `-` line
+    fn item_comments(&mut self, item: &Value, indent: usize) {
+        if self.comments.is_empty() {
+            return;
+        }
+        self.write_comments(indent);
+        match item {
+            Value::Mapping(mapping) if self.is_block(item) => {
+                let first = self.entries(mapping)[0].0;
+                self.path.push(search::key_text(first).map(Segment::Key));
+                self.write_comments(indent);
+                self.leave();
+            }
+            Value::Sequence(items) if self.is_block(item) => {
+                self.path.push(Some(Segment::Index(0)));
+                self.item_comments(&items[0], indent);
+                self.leave();
+            }
+            _ => {}
+        }
+    }
+
+    fn indent(&mut self, indent: usize) {
+        self.out.extend(std::iter::repeat_n(' ', indent));
+    }
+
+    /// Characters on the current line so far
+    fn column(&self) -> usize {
+        let line = self.out.rfind('\n').map_or(0, |newline| newline + 1);
+        self.out[line..].chars().count()
+    }
+
+    /// Is the current line past the width?
+    fn past_width(&self) -> bool {
+        self.options
+            .width
+            .is_some_and(|width| self.column() > width)
+    }
+
+    /// The entries of a mapping, in output order
+    fn entries<'a>(&self, mapping: &'a Mapping) -> Vec<(&'a Value, &'a Value)> {
+        let mut entries: Vec<_> = mapping.iter().collect();
+        if self.options.canonical {
+            // Keys that compare as equal (complex keys, `1` and `1.0`) are
+            // ordered by how they're written rather than left in insertion
+            // order
+            let mut keyed: Vec<_> = entries
+                .into_iter()
+                .map(|entry| (self.key_text(entry.0), entry))
+                .collect();
+            keyed.sort_by(|(a_text, a), (b_text, b)| {
+                compare_keys(a.0, b.0).then_with(|| a_text.cmp(b_text))
+            });
+            return keyed.into_iter().map(|(_, entry)| entry).collect();
+        }
+        if self.options.sort_keys {
+            entries.sort_by(|a, b| compare_keys(a.0, b.0));
+        }
+        entries
+    }
+
+    /// A key as it's written
+    fn key_text(&self, key: &Value) -> String {
+        let mut emitter = Emitter::new(self.options);
+        emitter.flow(key, true, None);
+        emitter.out
+    }
+
+    /// Is this container written in flow style?
+    fn is_flow(&self, value: &Value) -> bool {
+        match self.options.flow_style {
+            FlowStyle::Block => false,
+            FlowStyle::Flow => true,
+            FlowStyle::Leaves => match value {
+                Value::Mapping(mapping) => mapping.values().all(|value| !is_container(value)),
+                Value::Sequence(items) => items.iter().all(|item| !is_container(item)),
+                _ => false,
+            },
+        }
+    }
+
+    /// A non-empty container written in block style
+    fn is_block(&self, value: &Value) -> bool {
+        let non_empty = match value {
+            Value::Mapping(mapping) => !mapping.is_empty(),
+            Value::Sequence(items) => !items.is_empty(),
+            _ => false,
+        };
+        non_empty && !self.is_flow(value)
+    }
+
+    /// A string that isn't a literal block; with `fold`, continuing on
+    /// lines at that indent past the width
+    fn inline_string(&mut self, text: &str, flow: bool, key: bool, fold: Option<usize>) {
+        let rendered = match choose_style(text, flow, key) {
+            Style::Plain => text.to_string(),
+            Style::SingleQuoted => format!("'{}'", text.replace('\'', "''")),
+            Style::DoubleQuoted | Style::Literal => double_quoted(text),
+        };
+        match fold {
+            Some(indent) if self.options.width.is_some() => self.folded(&rendered, indent),
+            _ => self.out.push_str(&rendered),
+        }
+    }
+
+    /// Write a plain or quoted scalar, turning single spaces into line
+    /// breaks once the line is past the width (as PyYAML does; a folded
+    /// line break reads back as a space)
+    fn folded(&mut self, rendered: &str, indent: usize) {
+        let bytes = rendered.as_bytes();
+        let mut start = 0;
+        for (space, _) in rendered.match_indices(' ') {
+            // Keep runs of spaces and the spaces next to quotes
+            if space < 2
+                || space + 2 >= bytes.len()
+                || bytes[space - 1] == b' '
+                || bytes[space + 1] == b' '
+            {
+                continue;
+            }
+            self.out.push_str(&rendered[start..space]);
+            start = space + 1;
+            if self.past_width() {
+                self.out.push('\n');
+                self.indent(indent);
+            } else {
+                self.out.push(' ');
+            }
+        }
+        self.out.push_str(&rendered[start..]);
+    }
+
+    /// A literal block: header, then each line at `indent`
+    fn literal(&mut self, text: &str, indent: usize) {
+        let body = text.trim_end_matches('\n');
+        let chomping = match text.len() - body.len() {
+            0 => "-",
+            1 => "",
+            _ => "+",
+        };
+        self.out.push('|');
+        self.out.push_str(chomping);
+        self.out.push('\n');
+        // Every line gets a break; with `-` the last one is stripped again
+        let lines = if chomping == "-" {
+            text
+        } else {
+            &text[..text.len() - 1]
+        };
+        for line in lines.split('\n') {
+            if !line.is_empty() {
+                self.indent(indent);
+                self.out.push_str(line);
+            }
+            self.out.push('\n');
+        }
+    }
+
+    /// A value in flow style. `indent` is where lines continue past the
+    /// width; keys (`None`) stay on one line.
+    fn flow(&mut self, value: &Value, key: bool, indent: Option<usize>) {
+        match value {
+            Value::Null if self.options.null_style == NullStyle::Tilde => self.out.push('~'),
+            Value::Null => self.out.push_str("null"),
+            Value::Bool(b) => self.out.push_str(self.options.bool_style.text(*b)),
+            Value::Number(n) => self.out.push_str(&format_number(n)),
+            Value::String(s) => self.inline_string(s, true, key, indent),
+            Value::Sequence(items) => {
+                self.out.push('[');
+                for (index, item) in items.iter().enumerate() {
+                    if index > 0 {
+                        self.flow_separator(indent);
+                    }
+                    self.flow(item, false, indent);
+                }
+                self.out.push(']');
+            }
+            Value::Mapping(mapping) => {
+                self.out.push('{');
+                for (index, (k, v)) in self.entries(mapping).into_iter().enumerate() {
+                    if index > 0 {
+                        self.flow_separator(indent);
+                    }
+                    self.flow_key(k);
+                    self.out.push_str(": ");
+                    self.flow(v, false, indent);
+                }
+                self.out.push('}');
+            }
+            Value::Tagged(tagged) => match self.alias(tagged) {
+                Some(alias) => self.out.push_str(&alias),
+                None if tagged.tag == PLAIN_TAG => {
+                    if let Value::String(text) = &tagged.value {
+                        self.out.push_str(text);
+                    }
+                }
+                None => {
+                    let property = self.property(tagged);
+                    self.out.push_str(&property);
+                    self.out.push(' ');
+                    self.flow(&tagged.value, key, indent);
+                }
+            },
+        }
+    }
+
+    /// The `*alias` of a shared container already written
+    fn alias(&self, tagged: &TaggedValue) -> Option<String> {
+        self.anchors
+            .get(&tagged.tag.to_string())
+            .map(|number| format!("*id{:03}", number))
+    }
+
+    /// What goes before a tagged value: its `!tag`, or a new `&anchor` for
+    /// a shared container (numbered like PyYAML's)
+    fn property(&mut self, tagged: &TaggedValue) -> String {
+        let tag = tagged.tag.to_string();
+        if !tag.trim_start_matches('!').starts_with(SHARED_TAG) {
+            return tag;
+        }
+        let number = self.anchors.len() + 1;
+        self.anchors.insert(tag, number);
+        format!("&id{:03}", number)
+    }
+
+    /// A tagged block container, not yet written (so not an alias)
+    fn is_block_node(&self, tagged: &TaggedValue) -> bool {
+        self.is_block(&tagged.value) && self.alias(tagged).is_none()
+    }
+
+    /// A key, with a space after an alias (`*id001 :`) since `:` could be
+    /// part of the alias name
+    fn flow_key(&mut self, key: &Value) {
+        let start = self.out.len();
+        self.flow(key, true, None);
+        if self.out[start..].starts_with('*') {
+            self.out.push(' ');
+        }
+    }
+
+    /// `, ` between flow items, or `,` and a new line past the width
+    fn flow_separator(&mut self, indent: Option<usize>) {
+        self.out.push(',');
+        match indent {
+            Some(indent) if self.past_width() => {
+                self.out.push('\n');
+                self.indent(indent);
+            }
+            _ => self.out.push(' '),
+        }
+    }
+
+    /// A scalar or flow collection after `key: ` or `- `, through the end
+    /// of its last line; continuation lines go at `indent` plus a level
+    fn scalar(&mut self, value: &Value, indent: usize) {
+        let continued = indent + self.options.indent;
+        match value {
+            Value::String(s) if choose_style(s, false, false) == Style::Literal => {
+                self.literal(s, continued);
+                return;
+            }
+            Value::String(s) => self.inline_string(s, false, false, Some(continued)),
+            _ => self.flow(value, false, Some(continued)),
+        }
+        self.out.push('\n');
+    }
+
+    /// A mapping key, through the `:`
+    fn key(&mut self, key: &Value, indent: usize) {
+        let start = self.out.len();
+        self.flow_key(key);
+        if self.out.len() - start > MAX_SIMPLE_KEY {
+            // Too long for an implicit key: `? key` on a line of its own
+            self.out.insert_str(start, "? ");
+            self.out.push('\n');
+            self.indent(indent);
+        }
+        self.out.push(':');
+    }
+
+    /// Is `value` left out (`null_style=""`)?
+    fn is_empty_null(&self, value: &Value) -> bool {
+        value.is_null() && self.options.null_style == NullStyle::Empty
+    }
+
+    /// The value of a block mapping entry, after its `:`
+    fn mapping_value(&mut self, value: &Value, indent: usize) {
+        match value {
+            value if self.is_empty_null(value) => self.out.push('\n'),
+            Value::Mapping(mapping) if self.is_block(value) => {
+                self.out.push('\n');
+                self.block_mapping(mapping, indent + self.options.indent, false);
+            }
+            // Sequences in mappings aren't indented (as PyYAML writes them)
+            Value::Sequence(items) if self.is_block(value) => {
+                self.out.push('\n');
+                self.block_sequence(items, indent, false);
+            }
+            Value::Tagged(tagged) if self.is_block_node(tagged) => {
+                self.out.push(' ');
+                let property = self.property(tagged);
+                self.out.push_str(&property);
+                self.mapping_value(&tagged.value, indent);
+            }
+            _ => {
+                self.out.push(' ');
+                self.scalar(value, indent);
+            }
+        }
+    }
+
+    /// `inline`: the first entry goes on the current line (after `- `),
+    /// its comments already written above that line
+    fn block_mapping(&mut self, mapping: &Mapping, indent: usize, inline: bool) {
+        for (index, (key, value)) in self.entries(mapping).into_iter().enumerate() {
+            self.enter(|| search::key_text(key).map(Segment::Key));
+            if index > 0 || !inline {
+                self.write_comments(indent);
+                self.indent(indent);
+            }
+            self.key(key, indent);
+            self.mapping_value(value, indent);
+            self.leave();
+        }
+    }
+
+    /// `inline`: the first item goes on the current line (after `- `),
+    /// its comments already written above that line
+    fn block_sequence(&mut self, items: &[Value], indent: usize, inline: bool) {
+        for (index, item) in items.iter().enumerate() {
+            self.enter(|| Some(Segment::Index(index)));
+            if index > 0 || !inline {
+                self.item_comments(item, indent);
+                self.indent(indent);
+            }
+            self.out.push('-');
+            self.sequence_item(item, indent);
+            self.leave();
+        }
+    }
+
+    /// An item of a block sequence, after its `-`
+    fn sequence_item(&mut self, item: &Value, indent: usize) {
+        let nested = indent + self.options.indent;
+        match item {
+            item if self.is_empty_null(item) => self.out.push('\n'),
+            // Block collections start on the `-` line, at the next level
+            Value::Mapping(mapping) if self.is_block(item) => {
+                self.indent(self.options.indent - 1);
+                self.block_mapping(mapping, nested, true);
+            }
+            Value::Sequence(items) if self.is_block(item) => {
+                self.indent(self.options.indent - 1);
+                self.block_sequence(items, nested, true);
+            }
+            Value::Tagged(tagged) if self.is_block_node(tagged) => {
+                self.out.push(' ');
+                let property = self.property(tagged);
+                self.out.push_str(&property);
+                self.out.push('\n');
+                self.block(&tagged.value, nested);
+            }
+            _ => {
+                self.out.push(' ');
+                self.scalar(item, indent);
+            }
+        }
+    }
+
+    /// A non-empty container on lines of its own at `indent`
+    fn block(&mut self, value: &Value, indent: usize) {
+        match value {
+            Value::Mapping(mapping) => self.block_mapping(mapping, indent, false),
+            Value::Sequence(items) => self.block_sequence(items, indent, false),
+            _ => unreachable!("only called for block containers"),
+        }
+    }
+
+    /// A whole document
+    fn document(&mut self, value: &Value) {
+        self.anchors.clear();
+        match value {
+            value if self.is_block(value) => self.block(value, 0),
+            Value::Tagged(tagged) if self.is_block_node(tagged) => {
+                let property = self.property(tagged);
+                self.out.push_str(&property);
+                self.out.push('\n');
+                self.block(&tagged.value, 0);
+            }
+            _ => {
+                let start = self.out.len();
+                self.scalar(value, 0);
+                // A plain scalar could run on into a following document
+                let plain = !self.out[start..].starts_with(['\'', '"', '[', '{', '!', '|']);
+                if plain {
+                    self.out.push_str("...\n");
+                }
+            }
+        }
+    }
+}
+
+/// A mapping or sequence (possibly tagged)
+fn is_container(value: &Value) -> bool {
+    match value {
+        Value::Mapping(_) | Value::Sequence(_) => true,
+        Value::Tagged(tagged) => is_container(&tagged.value),
+        _ => false,
+    }
+}
+
+/// Write `value` as a YAML document
+pub fn emit(value: &Value, options: DumpOptions) -> String {
+    let mut emitter = Emitter::new(options);
+    emitter.document(value);
+    emitter.out
+}
+
+/// Write `value` as a YAML document with `comments` above the keys and
+/// items they name
+///
+/// # Errors
+/// * `ValueError` naming a comment path that matches no key or item
+///   written in block style
+pub fn emit_commented(
+    value: &Value,
+    options: DumpOptions,
+    comments: Vec<Comment>,
+) -> PyResult<String> {
+    let mut emitter = Emitter::new(options);
+    emitter.comments = comments;
+    emitter.document(value);
+    match emitter.comments.iter().find(|comment| !comment.used) {
+        Some(comment) => Err(PyValueError::new_err(format!(
+            "comment path '{}' matches no key or item written in block style",
+            comment.path
+        ))),
+        None => Ok(emitter.out),
+    }
+}
+
+/// Write `value` on one line, collections in flow style (`[1, {a: b}]`)
+pub fn emit_flow(value: &Value) -> String {
+    let mut emitter = Emitter::new(DumpOptions {
+        sort_keys: false,
+        width: None,
+        ..DumpOptions::default()
+    });
+    emitter.flow(value, false, None);
+    emitter.out
+}
+
+/// Write a stream of documents, separated by `---` lines
+///
+/// A document with a header gets an explicit `---` (even the first) with
+/// the header's lines as `#` comments under it, as `helm template` writes
+/// `# Source:` lines.
+pub fn emit_all(values: &[Value], headers: &[Option<String>], options: DumpOptions) -> String {
+    let mut emitter = Emitter::new(options);
+    for (index, value) in values.iter().enumerate() {
+        let header = headers.get(index).and_then(Option::as_deref);
+        if index > 0 || header.is_some() {
+            emitter.out.push_str("---\n");
+        }
+        if let Some(header) = header {
+            emitter.comment_lines(header, 0);
+        }
+        emitter.document(value);
+    }
+    emitter.out
+}
+
+/// `data` as a YAML document, with `comments=` (see `safe_dump`) and a
+/// `Dumper`'s representers
+fn dump_text(
+    py: Python,
+    data: &Bound<'_, PyAny>,
+    comments: Option<&Bound<'_, PyAny>>,
+    options: DumpOptions,
+    representers: Option<&Py<PyDict>>,
+) -> PyResult<String> {
+    let comments = comments.map(extract_comments).transpose()?;
+    let value = types::python_to_yaml_for_dump_with(data, representers)?;
+    py.allow_threads(|| match comments {
+        Some(comments) => emit_commented(&value, options, comments),
+        None => Ok(emit(&value, options)),
+    })
+}
+
+/// `documents` as a YAML stream, with `headers=` (see `safe_dump_all`)
+/// and a `Dumper`'s representers
+fn dump_all_text(
+    py: Python,
+    documents: &Bound<'_, PyAny>,
+    headers: Option<Vec<Option<String>>>,
+    options: DumpOptions,
+    representers: Option<&Py<PyDict>>,
+) -> PyResult<String> {
+    let values = documents
+        .iter()?
+        .map(|document| types::python_to_yaml_for_dump_with(&document?, representers))
+        .collect::<PyResult<Vec<_>>>()?;
+    let headers = headers.unwrap_or_default();
+    if !headers.is_empty() && headers.len() != values.len() {
+        return Err(PyValueError::new_err(format!(
+            "headers has {} entries for {} documents",
+            headers.len(),
+            values.len()
+        )));
+    }
+    Ok(py.allow_threads(|| emit_all(&values, &headers, options)))
+}
+
+/// Serialize Python data to YAML
+///
+/// # Arguments
+/// * `data` - dicts, lists, tuples, str, int, float, bool and None
+/// * `options` - Dump options:
+///   * `sort_keys` - Write mapping keys in sorted order (as PyYAML does by
+///     default); otherwise in insertion order
+///   * `canonical` - Always write equal data as the same bytes (sorted
+///     keys, default layout, no folding), for diffs of generated files
+///   * `indent` - Spaces per nesting level, 2 to 9 (default 2)
+///   * `width` - Fold long scalars at spaces past this column (default 80;
+///     `None` never folds)
+///   * `default_flow_style` - `True` writes collections as `{...}` and
+///     `[...]`, `None` only those holding scalars (default `False`)
+///   * `bool_style` - `"true"` (default), `"True"` or `"yes"` for
+///     `true`/`false`, `True`/`False` or `yes`/`no`
+///   * `null_style` - `"null"` (default), `"~"`, or `""` to leave `None`
+///     values out (`key:`) where YAML allows it
+/// * `comments` - Comments to write above keys and items, by key path
+///   (`{"database.port": "set by the infra team"}`; `grep` syntax)
+///
+/// # Returns
+/// The YAML document, which `safe_load` reads back as `data`
+///
+/// # Errors
+/// * `TypeError` for objects of other types or unknown options
+/// * `ValueError` for an `indent` outside 2 to 9, an unknown `bool_style`
+///   or `null_style`, layout options with `canonical`, or a comment path
+///   that is malformed or matches no key or item in block style
+///
+/// # Example
+/// ```python
+/// rustyyaml.safe_dump({"name": "web", "ports": [80, 443]})
+/// # 'name: web\nports:\n- 80\n- 443\n'
+/// ```
+#[pyfunction]
+#[pyo3(signature = (data, comments=None, **options))]
+pub fn safe_dump(
+    py: Python,
+    data: &Bound<'_, PyAny>,
+    comments: Option<&Bound<'_, PyAny>>,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<String> {
+    let options = DumpOptions::from_kwargs(options)?;
+    dump_text(py, data, comments, options, None)
+}
+
+/// Serialize a sequence of documents to one YAML stream
+///
+/// # Arguments
+/// * `documents` - Any iterable of documents, each as for `safe_dump`
+/// * `headers` - A comment per document (`None` for none), written under
+///   its `---` line
+/// * `options` - As for `safe_dump`
+///
+/// # Returns
+/// The documents separated by `---` lines (empty for no documents), which
+/// `load_all` reads back as a list of them
+///
+/// # Errors
+/// * As for `safe_dump`, and `ValueError` if `headers` doesn't have one
+///   entry per document
+///
+/// # Example
+/// ```python
+/// rustyyaml.safe_dump_all([{"kind": "Service"}, {"kind": "Deployment"}])
+/// # 'kind: Service\n---\nkind: Deployment\n'
+/// ```
+#[pyfunction]
+#[pyo3(signature = (documents, headers=None, **options))]
+pub fn safe_dump_all(
+    py: Python,
+    documents: &Bound<'_, PyAny>,
+    headers: Option<Vec<Option<String>>>,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<String> {
+    let options = DumpOptions::from_kwargs(options)?;
+    dump_all_text(py, documents, headers, options, None)
+}
+
+/// Functions registered with `add_representer`, by type
+fn representers(py: Python<'_>) -> &Bound<'_, PyDict> {
+    static REPRESENTERS: GILOnceCell<Py<PyDict>> = GILOnceCell::new();
+    REPRESENTERS
+        .get_or_init(py, || PyDict::new_bound(py).unbind())
+        .bind(py)
+}
+
+/// The representer for `obj`'s exact type, if any: `own`'s (a `Dumper`'s),
+/// otherwise the one registered with `add_representer`
+pub fn representer_for<'py>(
+    obj: &Bound<'py, PyAny>,
+    own: Option<&Py<PyDict>>,
+) -> PyResult<Option<Bound<'py, PyAny>>> {
+    if let Some(own) = own {
+        if let Some(representer) = own.bind(obj.py()).get_item(obj.get_type())? {
+            return Ok(Some(representer));
+        }
+    }
+    let representers = representers(obj.py());
+    if representers.is_empty() {
+        return Ok(None);
+    }
+    representers.get_item(obj.get_type())
+}
+
+/// Stdlib types written natively, imported once per interpreter
+struct Stdlib {
+    datetime: Py<PyType>,
+    date: Py<PyType>,
+    timedelta: Py<PyType>,
+    decimal: Py<PyType>,
+    uuid: Py<PyType>,
+    /// The `ipaddress` address and network classes (interfaces are
+    /// addresses)
+    ip: PyObject,
+    pure_path: Py<PyType>,
+    enumeration: Py<PyType>,
+    /// `dataclasses.fields`
+    fields: PyObject,
+}
+
+fn stdlib(py: Python<'_>) -> PyResult<&Stdlib> {
+    static STDLIB: GILOnceCell<Stdlib> = GILOnceCell::new();
+    STDLIB.get_or_try_init(py, || {
+        let class = |module: &str, name: &str| -> PyResult<Py<PyType>> {
+            Ok(py
+                .import_bound(module)?
+                .getattr(name)?
+                .downcast_into::<PyType>()?
+                .unbind())
+        };
+        Ok(Stdlib {
+            datetime: class("datetime", "datetime")?,
+            date: class("datetime", "date")?,
+            timedelta: class("datetime", "timedelta")?,
+            decimal: class("decimal", "Decimal")?,
+            uuid: class("uuid", "UUID")?,
+            ip: PyTuple::new_bound(
+                py,
+                [
+                    class("ipaddress", "IPv4Address")?,
+                    class("ipaddress", "IPv6Address")?,
+                    class("ipaddress", "IPv4Network")?,
+                    class("ipaddress", "IPv6Network")?,
+                ],
+            )
+            .into_any()
+            .unbind(),
+            pure_path: class("pathlib", "PurePath")?,
+            enumeration: class("enum", "Enum")?,
+            fields: py.import_bound("dataclasses")?.getattr("fields")?.unbind(),
+        })
+    })
+}
+
+/// How a stdlib object is written (see `builtin`)
+pub enum Builtin<'py> {
+    /// A date or datetime, as PyYAML writes it (`2024-01-02 03:04:05`)
+    Timestamp(String),
+    /// A `Decimal`: its exact text, if that reads back as a number or is
+    /// past the float range, and the nearest float
+    Decimal(Option<String>, f64),
+    /// Data to convert in the object's place
+    Data(Bound<'py, PyAny>),
+}
+
+/// What `safe_dump` makes of `obj` if it's a stdlib object it knows
+pub fn builtin<'py>(obj: &Bound<'py, PyAny>) -> PyResult<Option<Builtin<'py>>> {
+    let py = obj.py();
+    let stdlib = stdlib(py)?;
+    // datetime before date: datetime is a date subclass
+    if obj.is_instance(stdlib.datetime.bind(py))? {
+        let text = obj.call_method1("isoformat", (" ",))?.extract()?;
+        return Ok(Some(Builtin::Timestamp(text)));
+    }
+    if obj.is_instance(stdlib.date.bind(py))? {
+        let text = obj.call_method0("isoformat")?.extract()?;
+        return Ok(Some(Builtin::Timestamp(text)));
+    }
+    if obj.is_instance(stdlib.timedelta.bind(py))? {
+        let part =
+            |name: &str| -> PyResult<i128> { obj.getattr(name)?.extract::<i64>().map(i128::from) };
+        let micros =
+            (part("days")? * 86_400 + part("seconds")?) * 1_000_000 + part("microseconds")?;
+        let text = PyString::new_bound(py, &resolve::format_duration(micros));
+        return Ok(Some(Builtin::Data(text.into_any())));
+    }
+    if obj.is_instance(stdlib.decimal.bind(py))? {
+        let text: String = obj.str()?.extract()?;
+        let is_number = |schema| {
+            matches!(
+                resolve::resolve_plain(&text, schema),
+                Scalar::Int(_) | Scalar::Float(_)
+            )
+        };
+        let float: f64 = obj.extract()?;
+        // `1E+3` isn't a float to PyYAML, nor `NaN` to anyone
+        let exact = if is_number(Schema::Core) && is_number(Schema::PyYaml) {
+            Some(text)
+        } else if float.is_infinite() && obj.call_method0("is_finite")?.is_truthy()? {
+            // Past the float range, as `1.0E+1000` rather than `.inf`
+            match text.split_once('E') {
+                Some((mantissa, exponent)) if !mantissa.contains('.') => {
+                    Some(format!("{}.0E{}", mantissa, exponent))
+                }
+                _ => Some(text),
+            }
+        } else {
+            None
+        };
+        return Ok(Some(Builtin::Decimal(exact, float)));
+    }
+    if obj.is_instance(stdlib.uuid.bind(py))?
+        || obj.is_instance(stdlib.ip.bind(py))?
+        || obj.is_instance(stdlib.pure_path.bind(py))?
+    {
+        return Ok(Some(Builtin::Data(obj.str()?.into_any())));
+    }
+    if obj.is_instance(stdlib.enumeration.bind(py))? {
+        return Ok(Some(Builtin::Data(obj.getattr("value")?)));
+    }
+    let is_dataclass = obj.get_type().hasattr("__dataclass_fields__")?;
+    if is_dataclass {
+        let fields = PyDict::new_bound(py);
+        for field in stdlib.fields.bind(py).call1((obj,))?.iter()? {
+            let name = field?.getattr("name")?;
+            fields.set_item(&name, obj.getattr(name.downcast::<PyString>()?)?)?;
+        }
+        return Ok(Some(Builtin::Data(fields.into_any())));
+    }
+    Ok(None)
+}
+
+/// Teach the dumper to serialize a type
+///
+/// # Arguments
+/// * `data_type` - The type; only exact instances use the representer (as
+///   in PyYAML), and registering a type again replaces its representer
+/// * `representer` - Called with each instance; returns data that can be
+///   dumped (str, dict, list, ..., or other types with representers)
+///
+/// # Errors
+/// * `TypeError` if `data_type` isn't a type or `representer` can't be
+///   called
+///
+/// # Example
+/// ```python
+/// rustyyaml.add_representer(ipaddress.IPv4Address, str)
+/// rustyyaml.safe_dump({"host": ipaddress.ip_address("10.0.0.1")})
+/// # 'host: 10.0.0.1\n'
+/// ```
+#[pyfunction]
+pub fn add_representer(
+    py: Python,
+    data_type: &Bound<'_, PyType>,
+    representer: &Bound<'_, PyAny>,
+) -> PyResult<()> {
+    register(representers(py), data_type, representer)
+}
+
+fn register(
+    registry: &Bound<'_, PyDict>,
+    data_type: &Bound<'_, PyType>,
+    representer: &Bound<'_, PyAny>,
+) -> PyResult<()> {
+    if !representer.is_callable() {
+        return Err(PyTypeError::new_err("representer must be callable"));
+    }
+    registry.set_item(data_type, representer)
+}
+
+/// Write `text` to `path`, creating missing parent directories if
+/// `make_dirs`
+fn write_file(path: &Path, text: &str, make_dirs: bool) -> Result<(), YAMLError> {
+    let failed = |e| batch::write_error(path, e);
+    if make_dirs {
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent).map_err(failed)?;
+        }
+    }
+    fs::write(path, text).map_err(failed)
+}
+
+/// Serialize Python data to a YAML file
+///
+/// The YAML is emitted and written in Rust without holding the GIL, and
+/// never becomes a Python string.
+///
+/// # Arguments
+/// * `data` - As for `safe_dump`
+/// * `path` - File to write (replaced if it exists)
+/// * `make_dirs` - Create missing parent directories
+/// * `options` - As for `safe_dump`
+///
+/// # Errors
+/// * `TypeError` and `ValueError` as for `safe_dump`
+/// * `YAMLError` if the file can't be written
+#[pyfunction]
+#[pyo3(signature = (data, path, make_dirs=false, comments=None, **options))]
+pub fn dump_file(
+    py: Python,
+    data: &Bound<'_, PyAny>,
+    path: PathBuf,
+    make_dirs: bool,
+    comments: Option<&Bound<'_, PyAny>>,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<()> {
+    let options = DumpOptions::from_kwargs(options)?;
+    let text = dump_text(py, data, comments, options, None)?;
+    py.allow_threads(|| write_file(&path, &text, make_dirs))?;
+    Ok(())
+}
+
+/// Serialize a sequence of documents to a YAML file (see `dump_file` and
+/// `safe_dump_all`)
+#[pyfunction]
+#[pyo3(signature = (documents, path, make_dirs=false, headers=None, **options))]
+pub fn dump_all_file(
+    py: Python,
+    documents: &Bound<'_, PyAny>,
+    path: PathBuf,
+    make_dirs: bool,
+    headers: Option<Vec<Option<String>>>,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<()> {
+    let options = DumpOptions::from_kwargs(options)?;
+    let text = dump_all_text(py, documents, headers, options, None)?;
+    py.allow_threads(|| write_file(&path, &text, make_dirs))?;
+    Ok(())
+}
+
+/// Dumps documents with options validated once, and representers of its
+/// own
+///
+/// ```python
+/// dumper = rustyyaml.Dumper(sort_keys=False, indent=4)
+/// dumper.add_representer(Money, str)
+/// for record in records:
+///     text = dumper.dump(record)
+/// ```
+#[pyclass(module = "rustyyaml", frozen, subclass)]
+pub struct Dumper {
+    options: DumpOptions,
+    /// Representers for this dumper only, by type
+    representers: Py<PyDict>,
+}
+
+#[pymethods]
+impl Dumper {
+    /// Validate `options` (as for `safe_dump`)
+    #[new]
+    #[pyo3(signature = (**options))]
+    fn new(py: Python, options: Option<&Bound<'_, PyDict>>) -> PyResult<Self> {
+        Ok(Dumper {
+            options: DumpOptions::from_kwargs(options)?,
+            representers: PyDict::new_bound(py).unbind(),
+        })
+    }
+
+    /// Serialize `data` (`comments=` as for `safe_dump`)
+    #[pyo3(signature = (data, comments=None))]
+    fn dump(
+        &self,
+        py: Python,
+        data: &Bound<'_, PyAny>,
+        comments: Option<&Bound<'_, PyAny>>,
+    ) -> PyResult<String> {
+        dump_text(py, data, comments, self.options, Some(&self.representers))
+    }
+
+    /// Serialize documents to one stream (`headers=` as for
+    /// `safe_dump_all`)
+    #[pyo3(signature = (documents, headers=None))]
+    fn dump_all(
+        &self,
+        py: Python,
+        documents: &Bound<'_, PyAny>,
+        headers: Option<Vec<Option<String>>>,
+    ) -> PyResult<String> {
+        dump_all_text(
+            py,
+            documents,
+            headers,
+            self.options,
+            Some(&self.representers),
+        )
+    }
+
+    /// Serialize `data` to a file (see `dump_file`)
+    #[pyo3(signature = (data, path, make_dirs=false, comments=None))]
+    fn dump_file(
+        &self,
+        py: Python,
+        data: &Bound<'_, PyAny>,
+        path: PathBuf,
+        make_dirs: bool,
+        comments: Option<&Bound<'_, PyAny>>,
+    ) -> PyResult<()> {
+        let text = dump_text(py, data, comments, self.options, Some(&self.representers))?;
+        py.allow_threads(|| write_file(&path, &text, make_dirs))?;
+        Ok(())
+    }
+
+    /// Register a representer for this dumper only (see `add_representer`);
+    /// it takes precedence over one registered globally for the same type
+    fn add_representer(
+        &self,
+        py: Python,
+        data_type: &Bound<'_, PyType>,
+        representer: &Bound<'_, PyAny>,
+    ) -> PyResult<()> {
+        register(self.representers.bind(py), data_type, representer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unsorted() -> DumpOptions {
+        DumpOptions {
+            sort_keys: false,
+            ..DumpOptions::default()
+        }
+    }
+
+    fn dump_with(yaml: &str, options: DumpOptions) -> String {
+        emit(&serde_yaml::from_str(yaml).unwrap(), options)
+    }
+
+    fn dump(yaml: &str) -> String {
+        dump_with(yaml, unsorted())
+    }
+
+    fn round_trip_with(value: &Value, options: DumpOptions) {
+        let text = emit(value, options);
+        let loaded: Value = serde_yaml::from_str(&text).unwrap();
+        assert_eq!(&loaded, value, "{}", text);
+    }
+
+    fn round_trip(value: &Value) {
+        round_trip_with(value, unsorted());
+    }
+
+    #[test]
+    fn test_block_layout() {
+        assert_eq!(
+            dump("name: web\nports: [80, 443]\nenv: {DEBUG: '1'}\nempty: {}\nnone: []"),
+            "name: web\nports:\n- 80\n- 443\nenv:\n  DEBUG: '1'\nempty: {}\nnone: []\n"
+        );
+        assert_eq!(
+            dump("[{a: 1, b: [x, y]}, [1, [2]], null]"),
+            "- a: 1\n  b:\n  - x\n  - y\n- - 1\n  - - 2\n- null\n"
+        );
+        assert_eq!(dump("hello"), "hello\n...\n");
+        assert_eq!(dump("{}"), "{}\n");
+        assert_eq!(dump("'yes'"), "'yes'\n");
+    }
+
+    #[test]
+    fn test_document_stream() {
+        let values: Vec<Value> = ["a: 1", "[x]", "text", "{}"]
+            .iter()
+            .map(|yaml| serde_yaml::from_str(yaml).unwrap())
+            .collect();
+        let text = emit_all(&values, &[], unsorted());
+        assert_eq!(text, "a: 1\n---\n- x\n---\ntext\n...\n---\n{}\n");
+        let loaded: Vec<Value> = serde_yaml::Deserializer::from_str(&text)
+            .map(|document| serde::Deserialize::deserialize(document).unwrap())
+            .collect();
+        assert_eq!(loaded, values);
+        assert_eq!(emit_all(&[], &[], unsorted()), "");
+
+        let headers = [
+            Some("Source: chart/templates/service.yaml".to_string()),
+            None,
+            Some("two\n\nlines".to_string()),
+        ];
+        let text = emit_all(&values[..3], &headers, unsorted());
+        assert_eq!(
+            text,
+            "---\n# Source: chart/templates/service.yaml\na: 1\n---\n- x\n---\n# two\n#\n# lines\ntext\n...\n"
+        );
+        let loaded: Vec<Value> = serde_yaml::Deserializer::from_str(&text)
+            .map(|document| serde::Deserialize::deserialize(document).unwrap())
+            .collect();
+        assert_eq!(loaded, values[..3]);
+    }
+
+    #[test]
+    fn test_write_file() {
+        let dir = std::env::temp_dir().join(format!("rustyyaml-dump-{}", std::process::id()));
+        let path = dir.join("nested/out.yaml");
+        let err = write_file(&path, "a: 1\n", false).unwrap_err();
+        assert!(matches!(
+            err,
+            YAMLError::Io {
+                action: "write",
+                ..
+            }
+        ));
+        assert!(err.to_string().starts_with("Failed to write "));
+        write_file(&path, "a: 1\n", true).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "a: 1\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_sort_keys() {
+        let value = serde_yaml::from_str("b: 1\na: {d: 1, c: 2}\n1: x\n").unwrap();
+        assert_eq!(
+            emit(&value, DumpOptions::default()),
+            "1: x\na:\n  c: 2\n  d: 1\nb: 1\n"
+        );
+    }
+
+    #[test]
+    fn test_indent() {
+        let options = DumpOptions {
+            indent: 4,
+            ..unsorted()
+        };
+        assert_eq!(
+            dump_with("a: {b: [1, {c: 2, d: 3}]}\ns: \"x\\ny\\n\"", options),
+            "a:\n    b:\n    - 1\n    -   c: 2\n        d: 3\ns: |\n    x\n    y\n"
+        );
+        assert_eq!(dump_with("[[1, 2]]", options), "-   - 1\n    - 2\n");
+        round_trip_with(
+            &serde_yaml::from_str("[{a: [{b: 1}], c: [[2, 3]]}, [[4]]]").unwrap(),
+            options,
+        );
+    }
+
+    #[test]
+    fn test_width() {
+        let text = "lorem ipsum dolor sit amet ".repeat(5);
+        let value = Value::from(vec![Value::from(text.trim_end()), Value::from("x  y")]);
+        let options = DumpOptions {
+            width: Some(40),
+            ..unsorted()
+        };
+        let folded = emit(&value, options);
+        assert!(folded.lines().count() > 3, "{}", folded);
+        assert!(folded.lines().all(|line| line.len() <= 50), "{}", folded);
+        round_trip_with(&value, options);
+        let unfolded = DumpOptions {
+            width: None,
+            ..unsorted()
+        };
+        assert_eq!(emit(&value, unfolded).lines().count(), 2);
+
+        // Quoted scalars, flow collections and continuations that look like
+        // indicators fold too
+        for source in [
+            "k: 'a - b ? c # d yes no '",
+            "k: \"tab\\there and more words \\u00e9 to fold ok\"",
+            "k: \"'quoted' words wrap at the width\"",
+            "[alpha, beta, gamma, delta, epsilon, zeta, eta, theta, iota]",
+        ] {
+            let value: Value = serde_yaml::from_str(source).unwrap();
+            let options = DumpOptions {
+                width: Some(10),
+                flow_style: FlowStyle::Leaves,
+                ..unsorted()
+            };
+            round_trip_with(&value, options);
+            assert!(emit(&value, options).lines().count() > 2, "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_flow_style() {
+        let source = "a: {b: [1, 2], c: x}\nd: []\ne: [[1], {f: g}]";
+        let flow = DumpOptions {
+            flow_style: FlowStyle::Flow,
+            ..unsorted()
+        };
+        assert_eq!(
+            dump_with(source, flow),
+            "{a: {b: [1, 2], c: x}, d: [], e: [[1], {f: g}]}\n"
+        );
+        let leaves = DumpOptions {
+            flow_style: FlowStyle::Leaves,
+            ..unsorted()
+        };
+        assert_eq!(
+            dump_with(source, leaves),
+            "a:\n  b: [1, 2]\n  c: x\nd: []\ne:\n- [1]\n- {f: g}\n"
+        );
+        assert_eq!(dump_with("[1, 2]", leaves), "[1, 2]\n");
+        for options in [flow, leaves] {
+            round_trip_with(&serde_yaml::from_str(source).unwrap(), options);
+        }
+    }
+
+    #[test]
+    fn test_dump_options() {
+        Python::with_gil(|py| {
+            let kwargs = PyDict::new_bound(py);
+            kwargs.set_item("indent", 4).unwrap();
+            kwargs.set_item("width", py.None()).unwrap();
+            kwargs.set_item("default_flow_style", py.None()).unwrap();
+            let options = DumpOptions::from_kwargs(Some(&kwargs)).unwrap();
+            assert_eq!(options.indent, 4);
+            assert_eq!(options.width, None);
+            assert_eq!(options.flow_style, FlowStyle::Leaves);
+            assert!(options.sort_keys);
+
+            kwargs.set_item("indent", 1).unwrap();
+            assert!(DumpOptions::from_kwargs(Some(&kwargs)).is_err());
+            kwargs.set_item("indent", 2).unwrap();
+            kwargs.set_item("explicit_start", true).unwrap();
+            assert!(DumpOptions::from_kwargs(Some(&kwargs)).is_err());
+        });
+    }
+
+    #[test]
+    fn test_bool_style() {
+        let source = "enabled: true\ndebug: false\nflags: {true: x}\nlist: [true]";
+        for (style, yes, no) in [
+            (BoolStyle::Lower, "true", "false"),
+            (BoolStyle::Title, "True", "False"),
+            (BoolStyle::YesNo, "yes", "no"),
+        ] {
+            let options = DumpOptions {
+                bool_style: style,
+                ..unsorted()
+            };
+            assert_eq!(
+                dump_with(source, options),
+                format!("enabled: {yes}\ndebug: {no}\nflags:\n  {yes}: x\nlist:\n- {yes}\n")
+            );
+        }
+        // Strings spelled like booleans stay quoted whatever the style
+        let options = DumpOptions {
+            bool_style: BoolStyle::YesNo,
+            ..unsorted()
+        };
+        assert_eq!(
+            dump_with("[yes, 'yes', True]", options),
+            "- 'yes'\n- 'yes'\n- yes\n"
+        );
+
+        Python::with_gil(|py| {
+            let kwargs = PyDict::new_bound(py);
+            kwargs.set_item("bool_style", "True").unwrap();
+            let options = DumpOptions::from_kwargs(Some(&kwargs)).unwrap();
+            assert_eq!(options.bool_style, BoolStyle::Title);
+            kwargs.set_item("bool_style", "on").unwrap();
+            assert!(DumpOptions::from_kwargs(Some(&kwargs)).is_err());
+        });
+    }
+
+    #[test]
+    fn test_null_style() {
+        let source = "a: null\nb: [null, 1]\nc: {null: x}\nd:\n- null\n- - null";
+        let style = |null_style| DumpOptions {
+            null_style,
+            ..unsorted()
+        };
+        assert_eq!(
+            dump_with(source, style(NullStyle::Tilde)),
+            "a: ~\nb:\n- ~\n- 1\nc:\n  ~: x\nd:\n- ~\n- - ~\n"
+        );
+        let empty = style(NullStyle::Empty);
+        assert_eq!(
+            dump_with(source, empty),
+            "a:\nb:\n-\n- 1\nc:\n  null: x\nd:\n-\n- -\n"
+        );
+        // Flow collections and a bare document can't leave a value out
+        assert_eq!(
+            dump_with(
+                "{a: [null]}",
+                DumpOptions {
+                    flow_style: FlowStyle::Flow,
+                    ..empty
+                }
+            ),
+            "{a: [null]}\n"
+        );
+        assert_eq!(dump_with("null", empty), "null\n...\n");
+        for options in [style(NullStyle::Tilde), empty] {
+            round_trip_with(&serde_yaml::from_str(source).unwrap(), options);
+        }
+    }
+
+    #[test]
+    fn test_comments() {
+        let value: Value = serde_yaml::from_str(
+            "database: {host: db, port: 5432}\nservers: [{name: a, port: 1}, {name: b}]\nflags: [x]",
+        )
+        .unwrap();
+        let comments = |paths: &[(&str, &str)]| -> Vec<Comment> {
+            paths
+                .iter()
+                .map(|&(path, text)| Comment {
+                    path: path.to_string(),
+                    segments: search::parse_key_path(path).unwrap(),
+                    text: text.to_string(),
+                    used: false,
+                })
+                .collect()
+        };
+        let text = emit_commented(
+            &value,
+            unsorted(),
+            comments(&[
+                ("database", "Primary database"),
+                ("database.port", "set by infra team"),
+                ("servers[*].name", "must be unique\n\nlowercase"),
+                ("servers[1]", "spare"),
+                ("servers[0].port", "only on a"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(
+            text,
+            "# Primary database\n\
+             database:\n  host: db\n  # set by infra team\n  port: 5432\n\
+             servers:\n\
+             # must be unique\n#\n# lowercase\n- name: a\n  # only on a\n  port: 1\n\
+             # spare\n# must be unique\n#\n# lowercase\n- name: b\n\
+             flags:\n- x\n"
+        );
+        let loaded: Value = serde_yaml::from_str(&text).unwrap();
+        assert_eq!(loaded, value);
+
+        // Keys in flow style, and keys that aren't there, can't be commented
+        let flow = DumpOptions {
+            flow_style: FlowStyle::Leaves,
+            ..unsorted()
+        };
+        assert!(emit_commented(&value, flow, comments(&[("database.port", "x")])).is_err());
+        let err =
+            emit_commented(&value, unsorted(), comments(&[("database.user", "x")])).unwrap_err();
+        Python::with_gil(|py| assert!(err.value_bound(py).to_string().contains("database.user")));
+        assert_eq!(
+            emit_commented(&value, unsorted(), Vec::new()).unwrap(),
+            emit(&value, unsorted())
+        );
+    }
+
+    #[test]
+    fn test_canonical() {
+        let canonical = DumpOptions {
+            canonical: true,
+            width: None,
+            ..DumpOptions::default()
+        };
+        // The same data built in different orders
+        let first: Value =
+            serde_yaml::from_str("{[b]: 1, [a]: 2, 1.0: x, 1: y, c: long text}").unwrap();
+        let second: Value =
+            serde_yaml::from_str("{c: long text, 1: y, 1.0: x, [a]: 2, [b]: 1}").unwrap();
+        let text = emit(&first, canonical);
+        assert_eq!(text, emit(&second, canonical));
+        assert_eq!(text, "1: y\n1.0: x\nc: long text\n[a]: 2\n[b]: 1\n");
+        // Without canonical, equal-ranking keys keep insertion order
+        assert_ne!(
+            emit(&first, DumpOptions::default()),
+            emit(&second, DumpOptions::default())
+        );
+
+        Python::with_gil(|py| {
+            let kwargs = PyDict::new_bound(py);
+            kwargs.set_item("canonical", true).unwrap();
+            let options = DumpOptions::from_kwargs(Some(&kwargs)).unwrap();
+            assert!(options.canonical && options.sort_keys);
+            assert_eq!(options.width, None);
+            kwargs.set_item("sort_keys", false).unwrap();
+            assert!(DumpOptions::from_kwargs(Some(&kwargs)).is_err());
+        });
+    }
+
+    #[test]
+    fn test_anchors() {
+        Python::with_gil(|py| {
+            let data = py
+                .eval_bound(
+                    "(lambda x, t: {'a': x, 'b': [x, {'c': x}], 'k': {t: t}})({'cpu': 1}, (1, 2))",
+                    None,
+                    None,
+                )
+                .unwrap();
+            let value = types::python_to_yaml_for_dump(&data).unwrap();
+            let text = emit(&value, DumpOptions::default());
+            assert_eq!(
+                text,
+                "a: &id001\n  cpu: 1\nb:\n- *id001\n- c: *id001\nk:\n  &id002 [1, 2]: *id002\n"
+            );
+            let loaded: Value = serde_yaml::from_str(&text).unwrap();
+            assert_eq!(loaded["b"][1]["c"]["cpu"], Value::from(1));
+
+            // Anchors are numbered per document
+            let text = emit_all(&[value.clone(), value], &[], DumpOptions::default());
+            assert_eq!(text.matches("&id001").count(), 2);
+            assert!(!text.contains("&id003"));
+
+            let flow = DumpOptions {
+                flow_style: FlowStyle::Flow,
+                ..DumpOptions::default()
+            };
+            let data = py
+                .eval_bound("(lambda x: {x: x})((1,))", None, None)
+                .unwrap();
+            let value = types::python_to_yaml_for_dump(&data).unwrap();
+            assert_eq!(emit(&value, flow), "{&id001 [1]: *id001}\n");
+        });
+    }
+
+    #[test]
+    fn test_representers() {
+        Python::with_gil(|py| {
+            let globals = PyDict::new_bound(py);
+            py.run_bound(
+                "class Host:\n    def __init__(self, name): self.name = name\n\
+                 class Cluster:\n    def __init__(self, *hosts): self.hosts = list(hosts)\n\
+                 class Loop: pass",
+                Some(&globals),
+                None,
+            )
+            .unwrap();
+            let class = |name: &str| {
+                globals
+                    .get_item(name)
+                    .unwrap()
+                    .unwrap()
+                    .downcast_into::<PyType>()
+                    .unwrap()
+            };
+            let data = py
+                .eval_bound("Cluster(Host('a'), Host('b'))", Some(&globals), None)
+                .unwrap();
+            assert!(types::python_to_yaml(&data).is_err());
+
+            let host_name = py.eval_bound("lambda host: host.name", None, None).unwrap();
+            let cluster_hosts = py
+                .eval_bound("lambda cluster: {'hosts': cluster.hosts}", None, None)
+                .unwrap();
+            add_representer(py, &class("Host"), &host_name).unwrap();
+            add_representer(py, &class("Cluster"), &cluster_hosts).unwrap();
+            let value = types::python_to_yaml_for_dump(&data).unwrap();
+            assert_eq!(emit(&value, DumpOptions::default()), "hosts:\n- a\n- b\n");
+
+            // A representer that returns its argument would never finish
+            let identity = py.eval_bound("lambda x: x", None, None).unwrap();
+            add_representer(py, &class("Loop"), &identity).unwrap();
+            let looped = py.eval_bound("[Loop()]", Some(&globals), None).unwrap();
+            assert!(types::python_to_yaml(&looped).is_err());
+            assert!(add_representer(py, &class("Loop"), &looped).is_err());
+        });
+    }
+
+    #[test]
+    fn test_dumper() {
+        Python::with_gil(|py| {
+            let options = PyDict::new_bound(py);
+            options.set_item("sort_keys", false).unwrap();
+            options.set_item("indent", 4).unwrap();
+            let dumper = Dumper::new(py, Some(&options)).unwrap();
+            let data = py
+                .eval_bound("{'b': {'c': 1}, 'a': 2}", None, None)
+                .unwrap();
+            for _ in 0..2 {
+                assert_eq!(
+                    dumper.dump(py, &data, None).unwrap(),
+                    "b:\n    c: 1\na: 2\n"
+                );
+            }
+
+            // Its representers are its own, and win over global ones
+            let globals = PyDict::new_bound(py);
+            py.run_bound("class Money:\n    cents = 150", Some(&globals), None)
+                .unwrap();
+            let money = globals
+                .get_item("Money")
+                .unwrap()
+                .unwrap()
+                .downcast_into::<PyType>()
+                .unwrap();
+            let data = py.eval_bound("[Money()]", Some(&globals), None).unwrap();
+            let cents = py.eval_bound("lambda m: m.cents", None, None).unwrap();
+            let dollars = py
+                .eval_bound("lambda m: m.cents / 100", None, None)
+                .unwrap();
+            add_representer(py, &money, &cents).unwrap();
+            dumper.add_representer(py, &money, &dollars).unwrap();
+            assert_eq!(dumper.dump(py, &data, None).unwrap(), "- 1.5\n");
+            let text = safe_dump(py, &data, None, None).unwrap();
+            assert_eq!(text, "- 150\n");
+            let other = Dumper::new(py, None).unwrap();
+            assert_eq!(other.dump(py, &data, None).unwrap(), "- 150\n");
+            assert!(dumper.add_representer(py, &money, &data).is_err());
+
+            options.set_item("indent", 1).unwrap();
+            assert!(Dumper::new(py, Some(&options)).is_err());
+        });
+    }
+
+    #[test]
+    fn test_stdlib_types() {
+        Python::with_gil(|py| {
+            let globals = PyDict::new_bound(py);
+            py.run_bound(
+                "import dataclasses, datetime, decimal, enum, ipaddress, pathlib, uuid\n\
+                 class Color(enum.Enum):\n    RED = 'red'\n\
+                 @dataclasses.dataclass\n\
+                 class Port:\n    number: int\n    color: Color\n\
+                 data = {\n\
+                 'when': datetime.datetime(2024, 1, 2, 3, 4, 5),\n\
+                 'day': datetime.date(2024, 1, 2),\n\
+                 'utc': datetime.datetime(2024, 1, 2, tzinfo=datetime.timezone.utc),\n\
+                 'wait': datetime.timedelta(minutes=90, microseconds=500000),\n\
+                 'price': decimal.Decimal('1.10'),\n\
+                 'big': decimal.Decimal('1E+3'),\n\
+                 'huge': decimal.Decimal('-1E+1000'),\n\
+                 'id': uuid.UUID(int=1),\n\
+                 'hosts': [ipaddress.ip_address('::1'), ipaddress.ip_network('10.0.0.0/8'),\n\
+                 ipaddress.ip_interface('192.168.1.5/24')],\n\
+                 'path': pathlib.PurePosixPath('/etc/app.yaml'),\n\
+                 'port': Port(80, Color.RED),\n\
+                 }",
+                Some(&globals),
+                None,
+            )
+            .unwrap();
+            let data = globals.get_item("data").unwrap().unwrap();
+            let value = types::python_to_yaml_for_dump(&data).unwrap();
+            assert_eq!(
+                emit(&value, unsorted()),
+                "when: 2024-01-02 03:04:05\n\
+                 day: 2024-01-02\n\
+                 utc: 2024-01-02 00:00:00+00:00\n\
+                 wait: 1h30m0.5s\n\
+                 price: 1.10\n\
+                 big: 1000.0\n\
+                 huge: -1.0E+1000\n\
+                 id: 00000000-0000-0000-0000-000000000001\n\
+                 hosts:\n- ::1\n- 10.0.0.0/8\n- 192.168.1.5/24\n\
+                 path: /etc/app.yaml\n\
+                 port:\n  number: 80\n  color: red\n"
+            );
+            // Other conversions see plain strings and numbers
+            let value = types::python_to_yaml(&data).unwrap();
+            assert_eq!(value["day"], Value::from("2024-01-02"));
+            assert_eq!(value["price"], Value::from(1.1));
+        });
+    }
+
+    #[test]
+    fn test_string_styles() {
+        assert_eq!(choose_style("hello world", false, false), Style::Plain);
+        assert_eq!(choose_style("-x", false, false), Style::Plain);
+        for ambiguous in [
+            "yes",
+            "null",
+            "0755",
+            "1e3",
+            "2024-01-01",
+            "",
+            " x",
+            "a: b",
+            "- x",
+        ] {
+            assert_ne!(
+                choose_style(ambiguous, false, false),
+                Style::Plain,
+                "{}",
+                ambiguous
+            );
+        }
+        assert_eq!(choose_style("it's", false, false), Style::Plain);
+        assert_eq!(choose_style("#it's", false, false), Style::SingleQuoted);
+        assert_eq!(choose_style("a,b", true, false), Style::SingleQuoted);
+        assert_eq!(choose_style("tab\there", false, false), Style::DoubleQuoted);
+        assert_eq!(choose_style("a\nb\n", false, false), Style::Literal);
+        assert_eq!(choose_style("a\nb\n", false, true), Style::DoubleQuoted);
+        assert_eq!(dump("s: \"a\\nb\\n\""), "s: |\n  a\n  b\n");
+        assert_eq!(dump("s: \"a\\n\\nb\""), "s: |-\n  a\n\n  b\n");
+    }
+
+    #[test]
+    fn test_floats() {
+        assert_eq!(format_float(1.0), "1.0");
+        assert_eq!(format_float(0.1), "0.1");
+        assert_eq!(format_float(1e20), "1.0e+20");
+        assert_eq!(format_float(-2.5e-7), "-2.5e-7");
+        assert_eq!(format_float(f64::NEG_INFINITY), "-.inf");
+    }
+
+    #[test]
+    fn test_round_trips() {
+        let sources = [
+            "a: {b: [1, 2.5, -3, true, null, '', ' padded ']}",
+            "- \"multi\\nline\\n\\n\"\n- \"trailing\\n\\n\\n\"\n- \"no break\\nat end\"",
+            "- \"\\ttab\"\n- \"\\u0007bell\"\n- \"\\u2028sep\"\n- \"caf\u{e9}\"",
+            "- 'x: y'\n- '#comment'\n- '[a]'\n- '---'\n- '...'\n- '~'\n- 'true'\n- '12:30'",
+            "[1, 2]: list key\n{a: 1}: map key\n? null\n: null value",
+            "\"one\\ntwo\": multi-line key",
+        ];
+        for source in sources {
+            round_trip(&serde_yaml::from_str(source).unwrap());
+        }
+        round_trip(&Value::String("x".repeat(2000)));
+        let mut long_key = Mapping::new();
+        long_key.insert(Value::String("k".repeat(2000)), Value::from(1));
+        round_trip(&Value::Mapping(long_key));
+    }
+}
@@ -0,0 +1,469 @@
+//! Detect and decode the encodings YAML allows: UTF-8, UTF-16 and UTF-32
+//!
+//! Detection follows the YAML spec (5.2, "Character Encodings"): a byte
+//! order mark decides, and without one the pattern of null bytes around
+//! the first character does (a YAML stream starts with an ASCII
+//! character). Everything else is UTF-8.
+//!
+//! Legacy files in Latin-1 or Windows-1252 are not YAML strictly speaking,
+//! but the `encoding=` option can name one of them, or ask for `"auto"`:
+//! detection as above, with Windows-1252 for 8-bit text that isn't UTF-8.
+//!
+//! UTF-8 read from a memory-mapped file (`use_mmap=True`) is not copied:
+//! the decoded `Text` points into the mapping.
+
+use std::fmt;
+use std::ops::Deref;
+use std::sync::Arc;
+
+use memmap2::Mmap;
+use pyo3::prelude::*;
+
+use crate::error::YAMLError;
+
+/// Encoding of a YAML file
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Utf32Le,
+    Utf32Be,
+    Latin1,
+    Windows1252,
+}
+
+impl Encoding {
+    /// Python codec name
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Utf16Le => "utf-16-le",
+            Encoding::Utf16Be => "utf-16-be",
+            Encoding::Utf32Le => "utf-32-le",
+            Encoding::Utf32Be => "utf-32-be",
+            Encoding::Latin1 => "latin-1",
+            Encoding::Windows1252 => "cp1252",
+        }
+    }
+
+    /// The encoding a Python codec name (or a common alias) refers to
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase().replace('_', "-");
+        Some(match name.as_str() {
+            "utf-8" | "utf8" => Encoding::Utf8,
+            "utf-16-le" | "utf-16le" => Encoding::Utf16Le,
+            "utf-16-be" | "utf-16be" => Encoding::Utf16Be,
+            "utf-32-le" | "utf-32le" => Encoding::Utf32Le,
+            "utf-32-be" | "utf-32be" => Encoding::Utf32Be,
+            "latin-1" | "latin1" | "iso-8859-1" | "iso8859-1" => Encoding::Latin1,
+            "cp1252" | "windows-1252" => Encoding::Windows1252,
+            _ => return None,
+        })
+    }
+
+    /// Its byte order mark (empty for the 8-bit encodings)
+    fn bom(self) -> &'static [u8] {
+        match self {
+            Encoding::Utf8 => b"\xEF\xBB\xBF",
+            Encoding::Utf16Le => b"\xFF\xFE",
+            Encoding::Utf16Be => b"\xFE\xFF",
+            Encoding::Utf32Le => b"\xFF\xFE\x00\x00",
+            Encoding::Utf32Be => b"\x00\x00\xFE\xFF",
+            Encoding::Latin1 | Encoding::Windows1252 => b"",
+        }
+    }
+}
+
+/// How files are decoded (`encoding=` option)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Decoding {
+    /// UTF-8, UTF-16 or UTF-32, as the bytes say
+    #[default]
+    Detect,
+    /// Like `Detect`, but text that isn't valid UTF-8 is Windows-1252
+    Auto,
+    /// Always this encoding (a matching byte order mark is skipped)
+    Fixed(Encoding),
+}
+
+impl Decoding {
+    /// `"auto"` or an encoding name
+    pub fn from_name(name: &str) -> Option<Self> {
+        if name.eq_ignore_ascii_case("auto") {
+            return Some(Decoding::Auto);
+        }
+        Encoding::from_name(name).map(Decoding::Fixed)
+    }
+}
+
+/// Characters of Windows-1252 bytes 0x80 to 0x9F; the five bytes it
+/// leaves undefined map to the C1 control with the same number, as
+/// browsers do
+const WINDOWS_1252: [char; 32] = [
+    '\u{20AC}', '\u{81}', '\u{201A}', '\u{192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2C6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8D}', '\u{17D}', '\u{8F}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2DC}', '\u{2122}', '\u{161}', '\u{203A}', '\u{153}', '\u{9D}', '\u{17E}', '\u{178}',
+];
+
+/// Decoded text, with how it was encoded
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Decoded {
+    /// The text, without the byte order mark
+    pub text: Text,
+    pub encoding: Encoding,
+    /// Did the bytes start with a byte order mark?
+    pub bom: bool,
+    /// Size of the encoded bytes
+    pub size: usize,
+}
+
+/// Decoded text, owned or read in place from a mapped file
+#[derive(Clone)]
+pub enum Text {
+    Owned(String),
+    /// UTF-8 (checked when mapped) from byte `start` of the mapping on
+    Mapped {
+        map: Arc<Mmap>,
+        start: usize,
+    },
+}
+
+impl Text {
+    pub fn as_str(&self) -> &str {
+        self
+    }
+
+    pub fn into_string(self) -> String {
+        match self {
+            Text::Owned(text) => text,
+            Text::Mapped { .. } => self.to_string(),
+        }
+    }
+}
+
+impl Deref for Text {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        match self {
+            Text::Owned(text) => text,
+            // SAFETY: checked to be UTF-8 in `decode_mapped`; the mapping
+            // is read-only, and callers are told not to modify the file
+            // while it is loaded
+            Text::Mapped { map, start } => unsafe { std::str::from_utf8_unchecked(&map[*start..]) },
+        }
+    }
+}
+
+impl Default for Text {
+    fn default() -> Self {
+        Text::Owned(String::new())
+    }
+}
+
+impl From<String> for Text {
+    fn from(text: String) -> Self {
+        Text::Owned(text)
+    }
+}
+
+impl PartialEq for Text {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for Text {}
+
+impl PartialEq<&str> for Text {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl fmt::Debug for Text {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for Text {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self)
+    }
+}
+
+/// The encoding of `bytes`, and the length of its byte order mark
+pub fn detect(bytes: &[u8]) -> (Encoding, usize) {
+    match bytes {
+        [0x00, 0x00, 0xFE, 0xFF, ..] => (Encoding::Utf32Be, 4),
+        [0x00, 0x00, 0x00, _, ..] => (Encoding::Utf32Be, 0),
+        [0xFF, 0xFE, 0x00, 0x00, ..] => (Encoding::Utf32Le, 4),
+        [_, 0x00, 0x00, 0x00, ..] => (Encoding::Utf32Le, 0),
+        [0xFE, 0xFF, ..] => (Encoding::Utf16Be, 2),
+        [0x00, _, ..] => (Encoding::Utf16Be, 0),
+        [0xFF, 0xFE, ..] => (Encoding::Utf16Le, 2),
+        [_, 0x00, ..] => (Encoding::Utf16Le, 0),
+        [0xEF, 0xBB, 0xBF, ..] => (Encoding::Utf8, 3),
+        _ => (Encoding::Utf8, 0),
+    }
+}
+
+/// Decode bytes as the `encoding=` option says
+///
+/// # Errors
+/// * `DecodingError` for bytes that are invalid in the chosen encoding
+pub fn decode_with(bytes: &[u8], decoding: Decoding) -> Result<Decoded, YAMLError> {
+    let (encoding, bom) = encoding_of(bytes, decoding);
+    let body = &bytes[bom..];
+    let text = match decode_as(body, encoding) {
+        Err(_) if decoding == Decoding::Auto && encoding == Encoding::Utf8 && bom == 0 => {
+            return Ok(Decoded {
+                text: decode_as(body, Encoding::Windows1252)?.into(),
+                encoding: Encoding::Windows1252,
+                bom: false,
+                size: bytes.len(),
+            });
+        }
+        result => result?,
+    };
+    Ok(Decoded {
+        text: text.into(),
+        encoding,
+        bom: bom > 0,
+        size: bytes.len(),
+    })
+}
+
+/// Decode a mapped file as the `encoding=` option says, in place if it
+/// is UTF-8
+///
+/// # Errors
+/// * `DecodingError` for bytes that are invalid in the chosen encoding
+pub fn decode_mapped(map: Arc<Mmap>, decoding: Decoding) -> Result<Decoded, YAMLError> {
+    let (encoding, bom) = encoding_of(&map, decoding);
+    if encoding == Encoding::Utf8 && std::str::from_utf8(&map[bom..]).is_ok() {
+        return Ok(Decoded {
+            size: map.len(),
+            text: Text::Mapped { map, start: bom },
+            encoding,
+            bom: bom > 0,
+        });
+    }
+    decode_with(&map, decoding)
+}
+
+/// The encoding `decoding` picks for `bytes`, and the length of its
+/// byte order mark
+fn encoding_of(bytes: &[u8], decoding: Decoding) -> (Encoding, usize) {
+    match decoding {
+        Decoding::Detect | Decoding::Auto => detect(bytes),
+        Decoding::Fixed(encoding) => {
+            let bom = encoding.bom();
+            let len = if bom.is_empty() || !bytes.starts_with(bom) {
+                0
+            } else {
+                bom.len()
+            };
+            (encoding, len)
+        }
+    }
+}
+
+/// Decode a YAML stream given as bytes (`safe_load(b"...")`, binary files)
+///
+/// # Returns
+/// The text, in UTF-8, UTF-16 or UTF-32 as detected, without its byte
+/// order mark
+///
+/// # Errors
+/// * `YAMLError` for bytes that are invalid in the detected encoding
+#[pyfunction]
+pub fn decode(py: Python, data: &[u8]) -> PyResult<String> {
+    let decoded = py.allow_threads(|| decode_with(data, Decoding::Detect))?;
+    Ok(decoded.text.into_string())
+}
+
+/// Decode `body` (without a byte order mark) as `encoding`
+fn decode_as(body: &[u8], encoding: Encoding) -> Result<String, YAMLError> {
+    let invalid = |message: &str| YAMLError::DecodingError {
+        message: format!("invalid {}: {}", encoding.name(), message),
+    };
+    let text = match encoding {
+        Encoding::Utf8 => {
+            String::from_utf8(body.to_vec()).map_err(|err| invalid(&err.to_string()))?
+        }
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            if !body.len().is_multiple_of(2) {
+                return Err(invalid("odd number of bytes"));
+            }
+            let units = body.chunks_exact(2).map(|pair| {
+                let pair = [pair[0], pair[1]];
+                if encoding == Encoding::Utf16Le {
+                    u16::from_le_bytes(pair)
+                } else {
+                    u16::from_be_bytes(pair)
+                }
+            });
+            char::decode_utf16(units)
+                .collect::<Result<String, _>>()
+                .map_err(|err| invalid(&err.to_string()))?
+        }
+        Encoding::Utf32Le | Encoding::Utf32Be => {
+            if !body.len().is_multiple_of(4) {
+                return Err(invalid("length is not a multiple of 4 bytes"));
+            }
+            body.chunks_exact(4)
+                .map(|quad| {
+                    let quad = [quad[0], quad[1], quad[2], quad[3]];
+                    let code = if encoding == Encoding::Utf32Le {
+                        u32::from_le_bytes(quad)
+                    } else {
+                        u32::from_be_bytes(quad)
+                    };
+                    char::from_u32(code)
+                        .ok_or_else(|| invalid(&format!("invalid code point {:#x}", code)))
+                })
+                .collect::<Result<String, _>>()?
+        }
+        Encoding::Latin1 => body.iter().map(|&byte| char::from(byte)).collect(),
+        Encoding::Windows1252 => body
+            .iter()
+            .map(|&byte| match byte {
+                0x80..=0x9F => WINDOWS_1252[usize::from(byte - 0x80)],
+                _ => char::from(byte),
+            })
+            .collect(),
+    };
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(bytes: &[u8]) -> Result<Decoded, YAMLError> {
+        decode_with(bytes, Decoding::Detect)
+    }
+
+    fn utf16(text: &str, little_endian: bool, bom: bool) -> Vec<u8> {
+        let bom = bom.then_some('\u{feff}');
+        bom.into_iter()
+            .chain(text.chars())
+            .collect::<String>()
+            .encode_utf16()
+            .flat_map(|unit| {
+                if little_endian {
+                    unit.to_le_bytes()
+                } else {
+                    unit.to_be_bytes()
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_decode_mapped() {
+        let path = std::env::temp_dir().join(format!("rustyyaml-mapped-{}", std::process::id()));
+        let map = |bytes: &[u8]| {
+            std::fs::write(&path, bytes).unwrap();
+            let file = std::fs::File::open(&path).unwrap();
+            Arc::new(unsafe { Mmap::map(&file) }.unwrap())
+        };
+
+        let decoded = decode_mapped(map(b"\xEF\xBB\xBFa: 1"), Decoding::Detect).unwrap();
+        assert!(matches!(decoded.text, Text::Mapped { start: 3, .. }));
+        assert_eq!((decoded.text.as_str(), decoded.bom), ("a: 1", true));
+
+        let decoded = decode_mapped(map(&utf16("a: 1", true, true)), Decoding::Detect).unwrap();
+        assert!(matches!(decoded.text, Text::Owned(_)));
+        assert_eq!(decoded.text, "a: 1");
+
+        assert!(decode_mapped(map(b"a: \xFF"), Decoding::Detect).is_err());
+        let decoded = decode_mapped(map(b"a: \xE9"), Decoding::Auto).unwrap();
+        assert_eq!(decoded.text, "a: é");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_decode() {
+        let text = "name: café\n";
+        let decoded = decode(text.as_bytes()).unwrap();
+        assert_eq!((decoded.encoding, decoded.bom), (Encoding::Utf8, false));
+
+        let decoded = decode(&[b"\xEF\xBB\xBF", text.as_bytes()].concat()).unwrap();
+        assert_eq!((decoded.encoding, decoded.bom), (Encoding::Utf8, true));
+        assert_eq!(decoded.text, text);
+
+        for (little_endian, bom, encoding) in [
+            (true, true, Encoding::Utf16Le),
+            (true, false, Encoding::Utf16Le),
+            (false, true, Encoding::Utf16Be),
+            (false, false, Encoding::Utf16Be),
+        ] {
+            let bytes = utf16(text, little_endian, bom);
+            let decoded = decode(&bytes).unwrap();
+            assert_eq!(
+                decoded,
+                Decoded {
+                    text: text.to_string().into(),
+                    encoding,
+                    bom,
+                    size: bytes.len(),
+                }
+            );
+        }
+
+        let utf32: Vec<u8> = "\u{feff}a: 1"
+            .chars()
+            .flat_map(|c| (c as u32).to_le_bytes())
+            .collect();
+        let decoded = decode(&utf32).unwrap();
+        assert_eq!(
+            (decoded.encoding, decoded.text.as_str()),
+            (Encoding::Utf32Le, "a: 1")
+        );
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        assert!(matches!(
+            decode(b"a: \xFF"),
+            Err(YAMLError::DecodingError { .. })
+        ));
+        assert!(decode(&[0xFF, 0xFE, b'a']).is_err());
+    }
+
+    #[test]
+    fn test_decode_legacy() {
+        let latin1 = b"name: caf\xE9 \x80\n";
+        let decoded = decode_with(latin1, Decoding::Fixed(Encoding::Latin1)).unwrap();
+        assert_eq!(decoded.text, "name: café \u{80}\n");
+
+        // "auto" keeps UTF-8 and falls back to Windows-1252 for the rest
+        let decoded = decode_with(latin1, Decoding::Auto).unwrap();
+        assert_eq!(
+            (decoded.encoding, decoded.text.as_str()),
+            (Encoding::Windows1252, "name: café €\n")
+        );
+        let decoded = decode_with("a: é".as_bytes(), Decoding::Auto).unwrap();
+        assert_eq!(
+            (decoded.encoding, decoded.text.as_str()),
+            (Encoding::Utf8, "a: é")
+        );
+        assert!(decode_with(b"\xEF\xBB\xBFa: \xFF", Decoding::Auto).is_err());
+
+        let decoded = decode_with(b"\xFF\xFEa\x00", Decoding::Fixed(Encoding::Utf16Le)).unwrap();
+        assert_eq!((decoded.text.as_str(), decoded.bom), ("a", true));
+
+        assert_eq!(Decoding::from_name("AUTO"), Some(Decoding::Auto));
+        assert_eq!(
+            Decoding::from_name("ISO_8859_1"),
+            Some(Decoding::Fixed(Encoding::Latin1))
+        );
+        assert_eq!(Decoding::from_name("ebcdic"), None);
+    }
+}
@@ -5,10 +5,77 @@
 //! 2. Convert cleanly to Python exceptions
 //! 3. Include suggestions for common mistakes
 
-use pyo3::{exceptions::PyValueError, PyErr};
+use pyo3::prelude::*;
 use thiserror::Error;
 
-#[derive(Error, Debug)]
+use crate::events::Mark;
+
+/// Python exception classes, re-exported by the `rustyyaml` package
+// create_exception! checks pyo3's `gil-refs` feature from inside this crate
+#[allow(unexpected_cfgs)]
+pub mod exceptions {
+    use pyo3::create_exception;
+    use pyo3::exceptions::PyValueError;
+
+    create_exception!(
+        rustyyaml,
+        YAMLError,
+        PyValueError,
+        "Base exception for YAML errors"
+    );
+    create_exception!(
+        rustyyaml,
+        TabIndentationError,
+        YAMLError,
+        "A tab character was used for indentation (YAML only allows spaces)"
+    );
+    create_exception!(
+        rustyyaml,
+        IntegrityError,
+        YAMLError,
+        "A file's checksum does not match the expected one"
+    );
+    create_exception!(
+        rustyyaml,
+        ResourceLimitError,
+        YAMLError,
+        "The input is larger than a resource limit allows"
+    );
+}
+
+/// A value that does not fit the schema, by JSON pointer
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SchemaViolation {
+    pub pointer: String,
+    /// Where the value is in the source, if known
+    pub mark: Option<Mark>,
+    pub message: String,
+}
+
+fn render_violations(violations: &[SchemaViolation]) -> String {
+    violations
+        .iter()
+        .map(|v| match v.mark {
+            Some(mark) => format!(
+                "\n  {} (line {}, column {}): {}",
+                v.pointer,
+                mark.line + 1,
+                mark.column + 1,
+                v.message
+            ),
+            None => format!("\n  {}: {}", v.pointer, v.message),
+        })
+        .collect()
+}
+
+fn render_mark(mark: &Option<Mark>) -> String {
+    match mark {
+        Some(mark) => format!(" at line {}, column {}", mark.line + 1, mark.column + 1),
+        None => String::new(),
+    }
+}
+
+#[derive(Clone, Error, Debug)]
 pub enum YAMLError {
     #[error("YAML parse error at line {line}, column {col}: {message}")]
     ParseError {
@@ -26,8 +93,54 @@ pub enum YAMLError {
     #[error("File not found: {path}")]
     FileNotFound { path: String },
 
-    #[error("UTF-8 decoding error: {message}")]
+    #[error("Failed to {action} {path}: {message}")]
+    Io {
+        /// `"read"` or `"write"`
+        action: &'static str,
+        path: String,
+        message: String,
+    },
+
+    #[error("Decoding error: {message}")]
     DecodingError { message: String },
+
+    #[error("{message}")]
+    Git { message: String },
+
+    #[error("Invalid archive {path}: {message}")]
+    Archive { path: String, message: String },
+
+    #[error("Schema coercion failed:{}", render_violations(.violations))]
+    Schema { violations: Vec<SchemaViolation> },
+
+    #[error("Invalid logging config {path}:{}", render_violations(.violations))]
+    LoggingConfig {
+        path: String,
+        violations: Vec<SchemaViolation>,
+    },
+
+    #[error("Limit exceeded{}: {message}", render_mark(.mark))]
+    Limit { message: String, mark: Option<Mark> },
+
+    #[error("Invalid profiles{}: {message}", render_mark(.mark))]
+    Profile { message: String, mark: Option<Mark> },
+
+    #[error("Checksum mismatch for {path}: expected sha256 {expected}, got {actual}")]
+    Integrity {
+        path: String,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("SOPS decryption failed: {message}")]
+    Sops { message: String },
+
+    #[error("Tab character used for indentation at line {line}, column {col}\n\n{context}\nHint: YAML indentation must use spaces, not tabs")]
+    TabIndentation {
+        line: usize,
+        col: usize,
+        context: String,
+    },
 }
 
 impl YAMLError {
@@ -46,6 +159,53 @@ impl YAMLError {
         YAMLError::InvalidNumber { value }
     }
 
+    /// 1-based (line, column) of the error, if known
+    pub fn location(&self) -> Option<(usize, usize)> {
+        match self {
+            YAMLError::ParseError { line, col, .. }
+            | YAMLError::TabIndentation { line, col, .. }
+                if *line > 0 =>
+            {
+                Some((*line, *col))
+            }
+            // The first violation with a known position
+            YAMLError::Schema { violations } | YAMLError::LoggingConfig { violations, .. } => {
+                violations
+                    .iter()
+                    .find_map(|v| v.mark)
+                    .map(|mark| (mark.line + 1, mark.column + 1))
+            }
+            YAMLError::Limit {
+                mark: Some(mark), ..
+            }
+            | YAMLError::Profile {
+                mark: Some(mark), ..
+            } => Some((mark.line + 1, mark.column + 1)),
+            _ => None,
+        }
+    }
+
+    /// Prefix the message of a parse error with the file it came from
+    pub fn in_file(self, path: &std::path::Path) -> Self {
+        match self {
+            YAMLError::ParseError { line, col, message } => YAMLError::ParseError {
+                line,
+                col,
+                message: format!("{}: {}", path.display(), message),
+            },
+            err => err,
+        }
+    }
+
+    /// Create a tab indentation error, rendering a caret under the tab
+    pub fn tab_indentation(line: usize, col: usize, yaml_content: &str) -> Self {
+        YAMLError::TabIndentation {
+            line,
+            col,
+            context: extract_context(yaml_content, line, col),
+        }
+    }
+
     /// Create a parse error with rich context
     ///
     /// Shows the offending line and points to the error location
@@ -66,9 +226,38 @@ impl YAMLError {
 }
 
 /// Convert our errors to Python exceptions
+///
+/// Every exception gets `line`/`column` (`None` when unknown) and
+/// `document`/`path` attributes (`None` unless set by collect-errors mode).
 impl From<YAMLError> for PyErr {
     fn from(err: YAMLError) -> PyErr {
-        PyValueError::new_err(err.to_string())
+        Python::with_gil(|py| {
+            let py_err = match err {
+                YAMLError::TabIndentation { .. } => {
+                    exceptions::TabIndentationError::new_err(err.to_string())
+                }
+                YAMLError::Integrity { .. } => exceptions::IntegrityError::new_err(err.to_string()),
+                YAMLError::Limit { .. } => exceptions::ResourceLimitError::new_err(err.to_string()),
+                _ => exceptions::YAMLError::new_err(err.to_string()),
+            };
+            let (line, column) = err.location().unzip();
+            let value = py_err.value_bound(py);
+            let _ = value.setattr("line", line);
+            let _ = value.setattr("column", column);
+            let _ = value.setattr("document", py.None());
+            let _ = value.setattr("path", py.None());
+            if let YAMLError::Integrity {
+                path,
+                expected,
+                actual,
+            } = &err
+            {
+                let _ = value.setattr("path", path);
+                let _ = value.setattr("expected", expected);
+                let _ = value.setattr("actual", actual);
+            }
+            py_err
+        })
     }
 }
 
@@ -132,11 +321,17 @@ fn extract_context(content: &str, error_line: usize, error_col: usize) -> String
         let line_content = lines[error_line - 1];
         context.push_str(&format!("  {} | {}\n", error_line, line_content));
 
-        // Add pointer
+        // Add pointer, keeping tabs so it lines up when the terminal expands them
+        let padding: String = line_content
+            .chars()
+            .chain(std::iter::repeat(' '))
+            .take(error_col.saturating_sub(1))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
         context.push_str(&format!(
             "  {} | {}^\n",
             " ".repeat(error_line.to_string().len()),
-            " ".repeat(error_col.saturating_sub(1))
+            padding
         ));
     }
 
@@ -171,6 +366,31 @@ mod tests {
         assert!(msg.contains("unexpected token"));
     }
 
+    #[test]
+    fn test_context_pointer_keeps_tabs() {
+        let yaml = "a:\n\t\tb: 1";
+        let context = extract_context(yaml, 2, 2);
+        assert!(context.contains("  | \t^"));
+    }
+
+    #[test]
+    fn test_tab_indentation_error() {
+        Python::with_gil(|py| {
+            let err: PyErr = YAMLError::tab_indentation(2, 1, "a:\n\tb: 1").into();
+            assert!(err.is_instance_of::<exceptions::TabIndentationError>(py));
+            assert!(err.is_instance_of::<exceptions::YAMLError>(py));
+            assert!(err.is_instance_of::<pyo3::exceptions::PyValueError>(py));
+            let line: usize = err
+                .value_bound(py)
+                .getattr("line")
+                .unwrap()
+                .extract()
+                .unwrap();
+            assert_eq!(line, 2);
+            assert!(err.to_string().contains("^"));
+        });
+    }
+
     #[test]
     fn test_unsafe_tag_error() {
         let err = YAMLError::unsafe_tag("!!python/object".to_string());
@@ -0,0 +1,243 @@
+//! PyYAML-compatible event stream (`parse`)
+//!
+//! `parse` hands out the libyaml events `events::Parser` produces as
+//! instances of the classes in `rustyyaml.events`, which mirror PyYAML's
+//! `yaml.events`, so tools built on `yaml.parse()` (linters, syntax
+//! highlighters) can switch over. Events are produced as the iterator is
+//! advanced; a caller that stops early never parses the rest.
+
+use std::io::Cursor;
+use std::sync::Arc;
+
+use pyo3::prelude::*;
+use pyo3::sync::GILOnceCell;
+use pyo3::types::PyModule;
+
+use crate::events::{Event, EventKind, Mark, Parser, ScalarStyle};
+use crate::iter::Text;
+use crate::tabs;
+
+/// The `name` PyYAML gives marks in a string
+const SOURCE_NAME: &str = "<unicode string>";
+
+fn events_module(py: Python<'_>) -> PyResult<&Bound<'_, PyModule>> {
+    static EVENTS: GILOnceCell<Py<PyModule>> = GILOnceCell::new();
+    EVENTS
+        .get_or_try_init(py, || {
+            py.import_bound("rustyyaml.events").map(Bound::unbind)
+        })
+        .map(|module| module.bind(py))
+}
+
+/// PyYAML's `implicit` for a scalar: whether its tag may be resolved from
+/// the plain form, and from a quoted form
+fn scalar_implicit(tag: Option<&str>, style: ScalarStyle) -> (bool, bool) {
+    match tag {
+        Some("!") => (true, false),
+        None if style == ScalarStyle::Plain => (true, false),
+        None => (false, true),
+        Some(_) => (false, false),
+    }
+}
+
+/// PyYAML's `style` for a scalar: `None` for plain, else its indicator
+pub fn scalar_style(style: ScalarStyle) -> Option<&'static str> {
+    match style {
+        ScalarStyle::Plain => None,
+        ScalarStyle::SingleQuoted => Some("'"),
+        ScalarStyle::DoubleQuoted => Some("\""),
+        ScalarStyle::Literal => Some("|"),
+        ScalarStyle::Folded => Some(">"),
+    }
+}
+
+/// Whether a collection's tag may be resolved (none, or the `!` tag)
+fn collection_implicit(tag: Option<&str>) -> bool {
+    tag.is_none_or(|tag| tag == "!")
+}
+
+/// Converts the byte offsets of marks to the character offsets PyYAML
+/// reports, counting on from the last one (marks mostly move forward)
+pub struct CharIndex {
+    text: Arc<str>,
+    byte: usize,
+    char: usize,
+}
+
+impl CharIndex {
+    pub fn new(text: Arc<str>) -> Self {
+        CharIndex {
+            text,
+            byte: 0,
+            char: 0,
+        }
+    }
+
+    /// The `rustyyaml.events.Mark` for `mark`
+    pub fn mark<'py>(&mut self, py: Python<'py>, mark: Mark) -> PyResult<Bound<'py, PyAny>> {
+        events_module(py)?.getattr("Mark")?.call1((
+            SOURCE_NAME,
+            self.of(mark.index),
+            mark.line,
+            mark.column,
+        ))
+    }
+
+    fn of(&mut self, byte: usize) -> usize {
+        if byte < self.byte {
+            (self.byte, self.char) = (0, 0);
+        }
+        let byte = byte.min(self.text.len());
+        self.char += self.text[self.byte..byte].chars().count();
+        self.byte = byte;
+        self.char
+    }
+}
+
+/// Build the `rustyyaml.events` instance for `event`
+fn to_python(py: Python, event: &Event, index: &mut CharIndex) -> PyResult<PyObject> {
+    let module = events_module(py)?;
+    let (start, end) = (index.mark(py, event.start)?, index.mark(py, event.end)?);
+    let class = |name: &str| module.getattr(name);
+
+    let event = match &event.kind {
+        EventKind::StreamStart => class("StreamStartEvent")?.call1((start, end))?,
+        EventKind::StreamEnd => class("StreamEndEvent")?.call1((start, end))?,
+        EventKind::DocumentStart { implicit } => {
+            class("DocumentStartEvent")?.call1((start, end, !implicit))?
+        }
+        EventKind::DocumentEnd { implicit } => {
+            class("DocumentEndEvent")?.call1((start, end, !implicit))?
+        }
+        EventKind::Alias { anchor } => class("AliasEvent")?.call1((anchor, start, end))?,
+        EventKind::Scalar {
+            anchor,
+            tag,
+            value,
+            style,
+        } => class("ScalarEvent")?.call1((
+            anchor.as_deref(),
+            tag.as_deref(),
+            scalar_implicit(tag.as_deref(), *style),
+            value,
+            start,
+            end,
+            scalar_style(*style),
+        ))?,
+        EventKind::SequenceStart { anchor, tag, flow } => class("SequenceStartEvent")?.call1((
+            anchor.as_deref(),
+            tag.as_deref(),
+            collection_implicit(tag.as_deref()),
+            start,
+            end,
+            *flow,
+        ))?,
+        EventKind::SequenceEnd => class("SequenceEndEvent")?.call1((start, end))?,
+        EventKind::MappingStart { anchor, tag, flow } => class("MappingStartEvent")?.call1((
+            anchor.as_deref(),
+            tag.as_deref(),
+            collection_implicit(tag.as_deref()),
+            start,
+            end,
+            *flow,
+        ))?,
+        EventKind::MappingEnd => class("MappingEndEvent")?.call1((start, end))?,
+    };
+    Ok(event.unbind())
+}
+
+/// Iterator over the events of a stream, parsed as they are reached
+#[pyclass(module = "rustyyaml", unsendable)]
+pub struct EventIterator {
+    parser: Parser<'static>,
+    text: Arc<str>,
+    index: CharIndex,
+}
+
+#[pymethods]
+impl EventIterator {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self, py: Python) -> PyResult<Option<PyObject>> {
+        match self.parser.next() {
+            None => Ok(None),
+            Some(event) => {
+                let event = event.map_err(|err| tabs::explain(err, &self.text))?;
+                to_python(py, &event, &mut self.index).map(Some)
+            }
+        }
+    }
+}
+
+/// Parse a stream into events, like PyYAML's `yaml.parse()`
+///
+/// # Arguments
+/// * `yaml_str` - YAML content
+///
+/// # Returns
+/// An iterator of `rustyyaml.events` instances (`StreamStartEvent`,
+/// `DocumentStartEvent`, `MappingStartEvent`, `ScalarEvent`, ...)
+///
+/// # Errors
+/// Syntax errors are raised as `YAMLError` when the iterator reaches them
+/// (`TabIndentationError` for a tab used as indentation)
+#[pyfunction]
+pub fn parse(yaml_str: &str) -> EventIterator {
+    let text: Arc<str> = yaml_str.into();
+    EventIterator {
+        parser: Parser::from_reader(Cursor::new(Text(text.clone()))),
+        text: text.clone(),
+        index: CharIndex::new(text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scalar_implicit() {
+        assert_eq!(scalar_implicit(None, ScalarStyle::Plain), (true, false));
+        assert_eq!(
+            scalar_implicit(None, ScalarStyle::DoubleQuoted),
+            (false, true)
+        );
+        assert_eq!(
+            scalar_implicit(Some("!"), ScalarStyle::Literal),
+            (true, false)
+        );
+        assert_eq!(
+            scalar_implicit(Some("tag:yaml.org,2002:str"), ScalarStyle::Plain),
+            (false, false)
+        );
+        assert!(collection_implicit(None));
+        assert!(!collection_implicit(Some("tag:yaml.org,2002:set")));
+    }
+
+    #[test]
+    fn test_char_index() {
+        let mut index = CharIndex::new("é: ü\nb: 1\n".into());
+        assert_eq!(index.of(4), 3);
+        assert_eq!(index.of(7), 5);
+        assert_eq!(index.of(2), 1);
+        assert_eq!(index.of(100), 10);
+    }
+
+    #[test]
+    fn test_scalar_style() {
+        let styles = [
+            ScalarStyle::Plain,
+            ScalarStyle::SingleQuoted,
+            ScalarStyle::DoubleQuoted,
+            ScalarStyle::Literal,
+            ScalarStyle::Folded,
+        ];
+        let indicators: Vec<_> = styles.into_iter().map(scalar_style).collect();
+        assert_eq!(
+            indicators,
+            [None, Some("'"), Some("\""), Some("|"), Some(">")]
+        );
+    }
+}
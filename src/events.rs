@@ -0,0 +1,470 @@
+//! Low-level YAML event stream
+//!
+//! serde_yaml hides everything below its `Value` type: tags on core types,
+//! scalar styles (quoted vs plain), anchors and source positions are all
+//! lost. Several features need that information, so this module exposes
+//! the libyaml event stream directly (the same parser serde_yaml uses).
+//!
+//! All `unsafe` code for talking to libyaml lives in this file.
+//!
+//! A syntax error that a tab in a line's indentation explains is reported
+//! as that tab (`TabIndentation`) rather than libyaml's scanner error,
+//! whenever the parser has the source text to look at.
+
+use std::ffi::{c_void, CStr};
+use std::io::{self, Read};
+use std::mem::MaybeUninit;
+use std::ptr::addr_of_mut;
+use std::slice;
+
+#[allow(clippy::unsafe_removed_from_name)]
+use unsafe_libyaml as sys;
+
+use crate::error::YAMLError;
+use crate::limits;
+use crate::tabs;
+
+/// A position in the source text (all fields 0-based)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Mark {
+    /// Byte offset into the input
+    pub index: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Mark {
+    fn from_sys(mark: sys::yaml_mark_t) -> Self {
+        Mark {
+            index: mark.index as usize,
+            line: mark.line as usize,
+            column: mark.column as usize,
+        }
+    }
+}
+
+/// How a scalar was written in the source
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScalarStyle {
+    Plain,
+    SingleQuoted,
+    DoubleQuoted,
+    Literal,
+    Folded,
+}
+
+impl ScalarStyle {
+    /// Name reported by `marked=` and `load_rt` (`"double-quoted"`, ...)
+    pub fn name(self) -> &'static str {
+        match self {
+            ScalarStyle::Plain => "plain",
+            ScalarStyle::SingleQuoted => "single-quoted",
+            ScalarStyle::DoubleQuoted => "double-quoted",
+            ScalarStyle::Literal => "literal",
+            ScalarStyle::Folded => "folded",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EventKind {
+    StreamStart,
+    StreamEnd,
+    DocumentStart {
+        implicit: bool,
+    },
+    DocumentEnd {
+        implicit: bool,
+    },
+    Alias {
+        anchor: String,
+    },
+    Scalar {
+        anchor: Option<String>,
+        tag: Option<String>,
+        value: String,
+        style: ScalarStyle,
+    },
+    SequenceStart {
+        anchor: Option<String>,
+        tag: Option<String>,
+        flow: bool,
+    },
+    SequenceEnd,
+    MappingStart {
+        anchor: Option<String>,
+        tag: Option<String>,
+        flow: bool,
+    },
+    MappingEnd,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Event {
+    pub kind: EventKind,
+    pub start: Mark,
+    pub end: Mark,
+}
+
+/// Pull parser producing `Event`s from a UTF-8 string or a reader
+pub struct Parser<'input> {
+    pin: Box<ParserPinned<'input>>,
+    done: bool,
+}
+
+/// libyaml keeps pointers into this struct, so it must never move
+struct ParserPinned<'input> {
+    sys: sys::yaml_parser_t,
+    input: Input<'input>,
+}
+
+/// Where a `Parser` gets its input from
+enum Input<'input> {
+    /// A whole string, which libyaml reads in place
+    Str(&'input str),
+    /// Pulled in chunks as libyaml needs more
+    Reader(ReaderInput),
+}
+
+struct ReaderInput {
+    reader: Box<dyn Read>,
+    /// Why the last read failed; libyaml only knows that it did
+    error: Option<io::Error>,
+}
+
+impl<'input> Parser<'input> {
+    pub fn new(input: &'input str) -> Self {
+        let mut pin = Box::<ParserPinned<'input>>::new_uninit();
+        let pin = unsafe {
+            let ptr = pin.as_mut_ptr();
+            let parser = addr_of_mut!((*ptr).sys);
+            if sys::yaml_parser_initialize(parser).fail {
+                panic!("libyaml failed to allocate a parser");
+            }
+            sys::yaml_parser_set_encoding(parser, sys::YAML_UTF8_ENCODING);
+            sys::yaml_parser_set_input_string(parser, input.as_ptr(), input.len() as u64);
+            addr_of_mut!((*ptr).input).write(Input::Str(input));
+            pin.assume_init()
+        };
+        Parser { pin, done: false }
+    }
+
+    /// Fetch the next event
+    pub fn next_event(&mut self) -> Result<Event, YAMLError> {
+        let mut event = MaybeUninit::<sys::yaml_event_t>::uninit();
+        unsafe {
+            let parser = addr_of_mut!(self.pin.sys);
+            if (&(*parser)).error != sys::YAML_NO_ERROR {
+                return Err(self.error());
+            }
+            let event = event.as_mut_ptr();
+            if sys::yaml_parser_parse(parser, event).fail {
+                return Err(self.error());
+            }
+            let converted = convert_event(&*event);
+            sys::yaml_event_delete(event);
+            if converted.kind == EventKind::StreamEnd {
+                self.done = true;
+            }
+            Ok(converted)
+        }
+    }
+
+    /// The error the parser stopped on, or the read error behind it
+    fn error(&mut self) -> YAMLError {
+        let source = match &self.pin.input {
+            Input::Reader(ReaderInput {
+                error: Some(err), ..
+            }) => {
+                return limits::size_error(err).unwrap_or_else(|| {
+                    YAMLError::parse(0, 0, format!("Failed to read input: {}", err))
+                });
+            }
+            Input::Str(source) => Some(*source),
+            Input::Reader(_) => None,
+        };
+        let err = unsafe { parse_error(addr_of_mut!(self.pin.sys)) };
+        match source {
+            Some(source) => tabs::explain(err, source),
+            None => err,
+        }
+    }
+}
+
+impl Parser<'static> {
+    /// Pull parser reading from `reader`, a chunk at a time
+    ///
+    /// Only libyaml's buffer is held, never the whole input. The encoding
+    /// (UTF-8 or UTF-16) is detected from a byte order mark.
+    pub fn from_reader(reader: impl Read + 'static) -> Self {
+        let mut pin = Box::<ParserPinned<'static>>::new_uninit();
+        let pin = unsafe {
+            let ptr = pin.as_mut_ptr();
+            let parser = addr_of_mut!((*ptr).sys);
+            if sys::yaml_parser_initialize(parser).fail {
+                panic!("libyaml failed to allocate a parser");
+            }
+            let input = addr_of_mut!((*ptr).input);
+            input.write(Input::Reader(ReaderInput {
+                reader: Box::new(reader),
+                error: None,
+            }));
+            let Input::Reader(reader) = &mut *input else {
+                unreachable!()
+            };
+            let data: *mut ReaderInput = reader;
+            sys::yaml_parser_set_input(parser, read_handler, data.cast());
+            pin.assume_init()
+        };
+        Parser { pin, done: false }
+    }
+}
+
+/// libyaml's read callback for `Input::Reader` (returns 1 on success)
+unsafe fn read_handler(data: *mut c_void, buffer: *mut u8, size: u64, size_read: *mut u64) -> i32 {
+    let input = &mut *data.cast::<ReaderInput>();
+    let buffer = slice::from_raw_parts_mut(buffer, size as usize);
+    loop {
+        match input.reader.read(buffer) {
+            Ok(read) => {
+                *size_read = read as u64;
+                return 1;
+            }
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => {
+                input.error = Some(err);
+                return 0;
+            }
+        }
+    }
+}
+
+impl Iterator for Parser<'_> {
+    type Item = Result<Event, YAMLError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let event = self.next_event();
+        if event.is_err() {
+            self.done = true;
+        }
+        Some(event)
+    }
+}
+
+impl Drop for ParserPinned<'_> {
+    fn drop(&mut self) {
+        unsafe { sys::yaml_parser_delete(&mut self.sys) }
+    }
+}
+
+/// Build a `YAMLError` from libyaml's error state
+unsafe fn parse_error(parser: *const sys::yaml_parser_t) -> YAMLError {
+    let parser = &*parser;
+    let mark = Mark::from_sys(parser.problem_mark);
+    let mut message = if parser.problem.is_null() {
+        "libyaml parser failed but there is no error".to_string()
+    } else {
+        CStr::from_ptr(parser.problem)
+            .to_string_lossy()
+            .into_owned()
+    };
+    if !parser.context.is_null() {
+        message.push_str(", ");
+        message.push_str(&CStr::from_ptr(parser.context).to_string_lossy());
+    }
+    YAMLError::parse(mark.line + 1, mark.column + 1, message)
+}
+
+unsafe fn convert_event(event: &sys::yaml_event_t) -> Event {
+    let kind = match event.type_ {
+        sys::YAML_STREAM_START_EVENT => EventKind::StreamStart,
+        sys::YAML_STREAM_END_EVENT => EventKind::StreamEnd,
+        sys::YAML_DOCUMENT_START_EVENT => EventKind::DocumentStart {
+            implicit: event.data.document_start.implicit,
+        },
+        sys::YAML_DOCUMENT_END_EVENT => EventKind::DocumentEnd {
+            implicit: event.data.document_end.implicit,
+        },
+        sys::YAML_ALIAS_EVENT => EventKind::Alias {
+            anchor: optional_string(event.data.alias.anchor).unwrap_or_default(),
+        },
+        sys::YAML_SCALAR_EVENT => {
+            let scalar = &event.data.scalar;
+            let bytes = slice::from_raw_parts(scalar.value, scalar.length as usize);
+            EventKind::Scalar {
+                anchor: optional_string(scalar.anchor),
+                tag: optional_string(scalar.tag),
+                value: String::from_utf8_lossy(bytes).into_owned(),
+                style: match scalar.style {
+                    sys::YAML_SINGLE_QUOTED_SCALAR_STYLE => ScalarStyle::SingleQuoted,
+                    sys::YAML_DOUBLE_QUOTED_SCALAR_STYLE => ScalarStyle::DoubleQuoted,
+                    sys::YAML_LITERAL_SCALAR_STYLE => ScalarStyle::Literal,
+                    sys::YAML_FOLDED_SCALAR_STYLE => ScalarStyle::Folded,
+                    _ => ScalarStyle::Plain,
+                },
+            }
+        }
+        sys::YAML_SEQUENCE_START_EVENT => {
+            let seq = &event.data.sequence_start;
+            EventKind::SequenceStart {
+                anchor: optional_string(seq.anchor),
+                tag: optional_string(seq.tag),
+                flow: seq.style == sys::YAML_FLOW_SEQUENCE_STYLE,
+            }
+        }
+        sys::YAML_SEQUENCE_END_EVENT => EventKind::SequenceEnd,
+        sys::YAML_MAPPING_START_EVENT => {
+            let map = &event.data.mapping_start;
+            EventKind::MappingStart {
+                anchor: optional_string(map.anchor),
+                tag: optional_string(map.tag),
+                flow: map.style == sys::YAML_FLOW_MAPPING_STYLE,
+            }
+        }
+        sys::YAML_MAPPING_END_EVENT => EventKind::MappingEnd,
+        // YAML_NO_EVENT is never produced by a successful parse
+        _ => EventKind::StreamEnd,
+    };
+
+    Event {
+        kind,
+        start: Mark::from_sys(event.start_mark),
+        end: Mark::from_sys(event.end_mark),
+    }
+}
+
+unsafe fn optional_string(ptr: *const u8) -> Option<String> {
+    if ptr.is_null() {
+        return None;
+    }
+    Some(
+        CStr::from_ptr(ptr as *const std::ffi::c_char)
+            .to_string_lossy()
+            .into_owned(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(yaml: &str) -> Vec<EventKind> {
+        Parser::new(yaml).map(|e| e.unwrap().kind).collect()
+    }
+
+    #[test]
+    fn test_simple_mapping_events() {
+        let events = kinds("key: value");
+        assert_eq!(events.len(), 8);
+        assert_eq!(events[0], EventKind::StreamStart);
+        assert!(matches!(
+            events[2],
+            EventKind::MappingStart { flow: false, .. }
+        ));
+        assert!(matches!(
+            &events[3],
+            EventKind::Scalar { value, style: ScalarStyle::Plain, .. } if value == "key"
+        ));
+        assert_eq!(events[7], EventKind::StreamEnd);
+    }
+
+    #[test]
+    fn test_scalar_styles_and_tags() {
+        let events = kinds("- 'a'\n- \"b\"\n- !!str c\n- !Ref d");
+        let scalars: Vec<_> = events
+            .iter()
+            .filter_map(|e| match e {
+                EventKind::Scalar { tag, style, .. } => Some((tag.clone(), *style)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(scalars[0], (None, ScalarStyle::SingleQuoted));
+        assert_eq!(scalars[1], (None, ScalarStyle::DoubleQuoted));
+        assert_eq!(
+            scalars[2],
+            (
+                Some("tag:yaml.org,2002:str".to_string()),
+                ScalarStyle::Plain
+            )
+        );
+        assert_eq!(scalars[3].0.as_deref(), Some("!Ref"));
+    }
+
+    #[test]
+    fn test_anchor_and_alias_events() {
+        let events = kinds("a: &x 1\nb: *x");
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, EventKind::Alias { anchor } if anchor == "x")));
+    }
+
+    #[test]
+    fn test_marks() {
+        let events: Vec<_> = Parser::new("a: 1\nbb: 2").map(|e| e.unwrap()).collect();
+        let bb = events
+            .iter()
+            .find(|e| matches!(&e.kind, EventKind::Scalar { value, .. } if value == "bb"))
+            .unwrap();
+        assert_eq!(bb.start.line, 1);
+        assert_eq!(bb.start.column, 0);
+        assert_eq!(bb.start.index, 5);
+        assert_eq!(bb.end.index, 7);
+    }
+
+    #[test]
+    fn test_reader_input() {
+        let parse = |bytes: Vec<u8>| -> Vec<EventKind> {
+            Parser::from_reader(io::Cursor::new(bytes))
+                .map(|e| e.unwrap().kind)
+                .collect()
+        };
+        assert_eq!(parse(b"key: value".to_vec()), kinds("key: value"));
+
+        let utf16: Vec<u8> = "\u{feff}key: café"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        assert!(parse(utf16)
+            .iter()
+            .any(|e| matches!(e, EventKind::Scalar { value, .. } if value == "café")));
+    }
+
+    #[test]
+    fn test_reader_error() {
+        struct Failing;
+        impl Read for Failing {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("disk on fire"))
+            }
+        }
+        let result: Result<Vec<_>, _> = Parser::from_reader(Failing).collect();
+        assert!(result.unwrap_err().to_string().contains("disk on fire"));
+    }
+
+    #[test]
+    fn test_tab_indentation() {
+        let result: Result<Vec<_>, _> = Parser::new("a:\n\tb: 1\n").collect();
+        assert!(matches!(
+            result.unwrap_err(),
+            YAMLError::TabIndentation {
+                line: 2,
+                col: 1,
+                ..
+            }
+        ));
+        // A reader's text isn't kept, so its error stays libyaml's
+        let result: Result<Vec<_>, _> =
+            Parser::from_reader(io::Cursor::new(b"a:\n\tb: 1\n".to_vec())).collect();
+        assert!(matches!(result.unwrap_err(), YAMLError::ParseError { .. }));
+    }
+
+    #[test]
+    fn test_parse_error_has_location() {
+        let result: Result<Vec<_>, _> = Parser::new("key: [unclosed").collect();
+        let msg = result.unwrap_err().to_string();
+        assert!(msg.contains("line"));
+    }
+}
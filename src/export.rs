@@ -0,0 +1,363 @@
+//! Flatten configs into other formats
+//!
+//! `to_csv` turns every scalar into one row:
+//! `file,document,key_path,value,type`. Key paths use the `grep` syntax
+//! (`spec.containers[0].image`); `document` is the index of the document
+//! within its file, so multi-document files don't produce ambiguous rows.
+//! Empty mappings and sequences get a row of their own (type `map`/`seq`)
+//! so no key is lost.
+//!
+//! `to_dotenv` turns every scalar into a `PREFIX_KEY__NESTED=value` line
+//! for containers that take env files.
+
+use pyo3::exceptions::PyTypeError;
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use serde_yaml::Value;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::batch;
+use crate::diff;
+use crate::error::YAMLError;
+use crate::search::key_text;
+use crate::types::python_to_yaml;
+
+const HEADER: &str = "file,document,key_path,value,type\n";
+
+/// One flattened scalar
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Row {
+    pub file: String,
+    pub document: usize,
+    pub path: String,
+    pub value: String,
+    pub kind: &'static str,
+}
+
+/// Scalars below `value` as `(key_path, value, type)`, in document order
+pub fn flatten(value: &Value) -> Vec<(String, String, &'static str)> {
+    let mut rows = Vec::new();
+    flatten_into(value, String::new(), &mut rows);
+    rows
+}
+
+fn flatten_into(value: &Value, path: String, rows: &mut Vec<(String, String, &'static str)>) {
+    let (text, kind) = match value {
+        Value::Null => (String::new(), "null"),
+        Value::Bool(b) => (b.to_string(), "bool"),
+        Value::Number(n) if n.is_f64() => (n.to_string(), "float"),
+        Value::Number(n) => (n.to_string(), "int"),
+        Value::String(s) => (s.clone(), "str"),
+        Value::Tagged(tagged) => return flatten_into(&tagged.value, path, rows),
+        Value::Mapping(map) if !map.is_empty() => {
+            for (key, child) in map {
+                let key = key_text(key).unwrap_or_else(|| {
+                    serde_yaml::to_string(key)
+                        .unwrap_or_default()
+                        .trim_end()
+                        .to_string()
+                });
+                let child_path = if path.is_empty() {
+                    key
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                flatten_into(child, child_path, rows);
+            }
+            return;
+        }
+        Value::Sequence(items) if !items.is_empty() => {
+            for (index, child) in items.iter().enumerate() {
+                flatten_into(child, format!("{}[{}]", path, index), rows);
+            }
+            return;
+        }
+        Value::Mapping(_) => (String::new(), "map"),
+        Value::Sequence(_) => (String::new(), "seq"),
+    };
+    rows.push((path, text, kind));
+}
+
+/// Rows of every document, tagged with `file`
+fn rows_of(file: &str, documents: &[Value]) -> Vec<Row> {
+    documents
+        .iter()
+        .enumerate()
+        .flat_map(|(document, value)| {
+            flatten(value)
+                .into_iter()
+                .map(move |(path, value, kind)| Row {
+                    file: file.to_string(),
+                    document,
+                    path,
+                    value,
+                    kind,
+                })
+        })
+        .collect()
+}
+
+/// Quote a field if it contains a separator, quote or line break (RFC 4180)
+fn field(text: &str) -> std::borrow::Cow<'_, str> {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\"")).into()
+    } else {
+        text.into()
+    }
+}
+
+/// Render rows as CSV, header first
+pub fn write_csv(rows: &[Row]) -> String {
+    let mut out = String::from(HEADER);
+    for row in rows {
+        out.push_str(&format!(
+            "{},{},{},{},{}\n",
+            field(&row.file),
+            row.document,
+            field(&row.path),
+            field(&row.value),
+            row.kind
+        ));
+    }
+    out
+}
+
+/// Flatten YAML files or loaded documents into CSV
+///
+/// Files are read and flattened in parallel, in file name order.
+///
+/// # Arguments
+/// * `dir_or_docs` - Directory of .yaml/.yml files, or a list of loaded
+///   documents (their `file` column is empty and `document` is the index)
+/// * `out_path` - Write the CSV here instead of returning it
+/// * `recursive` - With a directory, include subdirectories
+///
+/// # Returns
+/// The CSV text, or the number of rows written when `out_path` is given
+///
+/// # Example
+/// ```python
+/// rustyyaml.to_csv("k8s/", "audit.csv", recursive=True)
+/// # file,document,key_path,value,type
+/// # k8s/web.yaml,0,spec.replicas,3,int
+/// ```
+#[pyfunction]
+#[pyo3(signature = (dir_or_docs, out_path=None, recursive=false))]
+pub fn to_csv(
+    py: Python,
+    dir_or_docs: &Bound<'_, PyAny>,
+    out_path: Option<PathBuf>,
+    recursive: bool,
+) -> PyResult<PyObject> {
+    let rows: Vec<Row> = if let Ok(directory) = dir_or_docs.extract::<PathBuf>() {
+        let mut files = batch::find_yaml_files(&directory, recursive)?;
+        files.sort();
+        let rows: Result<Vec<Vec<Row>>, YAMLError> = py.allow_threads(|| {
+            files
+                .par_iter()
+                .map(|path| {
+                    let documents = diff::read_documents(path)?;
+                    Ok(rows_of(&path.to_string_lossy(), &documents))
+                })
+                .collect()
+        });
+        rows?.into_iter().flatten().collect()
+    } else if let Ok(documents) = dir_or_docs.extract::<Vec<Bound<'_, PyAny>>>() {
+        let documents = documents
+            .iter()
+            .map(python_to_yaml)
+            .collect::<PyResult<Vec<Value>>>()?;
+        rows_of("", &documents)
+    } else {
+        return Err(PyTypeError::new_err(
+            "dir_or_docs must be a directory or a list of documents",
+        ));
+    };
+
+    let csv = py.allow_threads(|| write_csv(&rows));
+    match out_path {
+        Some(out_path) => {
+            fs::write(PathBuf::from(&out_path), csv).map_err(YAMLError::from)?;
+            Ok(rows.len().into_py(py))
+        }
+        None => Ok(csv.into_py(py)),
+    }
+}
+
+/// `database.port` as an environment variable name part: `DATABASE`
+fn env_name(key: &str) -> String {
+    key.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Quote a dotenv value if needed
+///
+/// Single quotes are literal (no `$` interpolation), so they are preferred;
+/// values with line breaks or single quotes use double quotes and the
+/// escapes dotenv loaders understand (`\\`, `\"`, `\n`, `\r`, `\t`), plus
+/// `\$` and `` \` `` so shells and loaders that expand double-quoted values
+/// keep them literal.
+fn env_value(text: &str) -> String {
+    let bare = |c: char| c.is_ascii_alphanumeric() || "_-./:@+,%".contains(c);
+    if text.chars().all(bare) {
+        return text.to_string();
+    }
+    if !text.contains(['\'', '\n', '\r']) {
+        return format!("'{}'", text);
+    }
+    let mut out = String::from('"');
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '$' => out.push_str("\\$"),
+            '`' => out.push_str("\\`"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Render `value` as dotenv lines, nested keys joined by `__`
+///
+/// Sequence items use their index (`APP_HOSTS__0`); nulls become empty
+/// values and empty collections are left out.
+pub fn dotenv(value: &Value, prefix: &str) -> String {
+    let mut out = String::new();
+    dotenv_into(value, env_name(prefix), &mut out);
+    out
+}
+
+fn dotenv_into(value: &Value, name: String, out: &mut String) {
+    let join = |part: String| {
+        if name.is_empty() || name.ends_with('_') {
+            format!("{}{}", name, part)
+        } else {
+            format!("{}__{}", name, part)
+        }
+    };
+    let text = match value {
+        Value::Null => String::new(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => env_value(s),
+        Value::Tagged(tagged) => return dotenv_into(&tagged.value, name, out),
+        Value::Mapping(map) => {
+            for (key, child) in map {
+                if let Some(key) = key_text(key) {
+                    dotenv_into(child, join(env_name(&key)), out);
+                }
+            }
+            return;
+        }
+        Value::Sequence(items) => {
+            for (index, child) in items.iter().enumerate() {
+                dotenv_into(child, join(index.to_string()), out);
+            }
+            return;
+        }
+    };
+    out.push_str(&name);
+    out.push('=');
+    out.push_str(&text);
+    out.push('\n');
+}
+
+/// Flatten a config into dotenv (`.env`) lines
+///
+/// # Arguments
+/// * `doc` - Loaded document (a dict)
+/// * `prefix` - Prepended to every variable name
+///
+/// # Returns
+/// One `NAME=value` line per scalar. Names are upper-cased, with `__`
+/// between nesting levels and `_` for any other character; values are
+/// quoted and escaped when needed.
+///
+/// # Example
+/// ```python
+/// rustyyaml.to_dotenv({"database": {"port": 5432, "host": "db"}})
+/// # 'APP_DATABASE__PORT=5432\nAPP_DATABASE__HOST=db\n'
+/// ```
+#[pyfunction]
+#[pyo3(signature = (doc, prefix="APP_"))]
+pub fn to_dotenv(doc: &Bound<'_, PyAny>, prefix: &str) -> PyResult<String> {
+    let value = python_to_yaml(doc)?;
+    if !value.is_mapping() {
+        return Err(PyTypeError::new_err("to_dotenv needs a mapping"));
+    }
+    Ok(dotenv(&value, prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flatten() {
+        let value: Value =
+            serde_yaml::from_str("a: {b: 1, c: [x, 2.5]}\nd: null\ne: {}\nf: []\ng: true").unwrap();
+        assert_eq!(
+            flatten(&value),
+            [
+                ("a.b".to_string(), "1".to_string(), "int"),
+                ("a.c[0]".to_string(), "x".to_string(), "str"),
+                ("a.c[1]".to_string(), "2.5".to_string(), "float"),
+                ("d".to_string(), String::new(), "null"),
+                ("e".to_string(), String::new(), "map"),
+                ("f".to_string(), String::new(), "seq"),
+                ("g".to_string(), "true".to_string(), "bool"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dotenv() {
+        let value: Value = serde_yaml::from_str(
+            "database: {port: 5432, host: db.local}\nfeature-flags: [a, b]\ndebug: false\nnone: null\n",
+        )
+        .unwrap();
+        assert_eq!(
+            dotenv(&value, "APP_"),
+            "APP_DATABASE__PORT=5432\nAPP_DATABASE__HOST=db.local\n\
+             APP_FEATURE_FLAGS__0=a\nAPP_FEATURE_FLAGS__1=b\nAPP_DEBUG=false\nAPP_NONE=\n"
+        );
+        assert_eq!(dotenv(&value, "")[..15], *"DATABASE__PORT=");
+    }
+
+    #[test]
+    fn test_env_value_quoting() {
+        assert_eq!(
+            env_value("postgres://u@h:5432/db"),
+            "postgres://u@h:5432/db"
+        );
+        assert_eq!(env_value("two words $HOME"), "'two words $HOME'");
+        assert_eq!(env_value("it's\n\"x\""), "\"it's\\n\\\"x\\\"\"");
+        assert_eq!(env_value("it's $HOME `id`"), "\"it's \\$HOME \\`id\\`\"");
+        assert_eq!(env_value(""), "");
+    }
+
+    #[test]
+    fn test_write_csv_quotes_fields() {
+        let value: Value = serde_yaml::from_str("msg: 'a, \"b\"'\ntext: \"x\\ny\"").unwrap();
+        let csv = write_csv(&rows_of("dir/f.yaml", &[value]));
+        assert_eq!(
+            csv,
+            "file,document,key_path,value,type\n\
+             dir/f.yaml,0,msg,\"a, \"\"b\"\"\",str\n\
+             dir/f.yaml,0,text,\"x\ny\",str\n"
+        );
+    }
+}
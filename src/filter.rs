@@ -0,0 +1,172 @@
+//! Document filters for batch loading (`filter=` argument)
+//!
+//! A filter is either a declarative matcher (a dict such as
+//! `{"kind": "Deployment"}`) or a Python callable. Matchers are checked in
+//! Rust against the parsed `Value` before anything is converted, so
+//! rejected documents never reach Python. Documents from the composer path
+//! and callables are checked after conversion.
+
+use pyo3::exceptions::PyTypeError;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+use serde_yaml::Value;
+
+use crate::parser::Parsed;
+use crate::types::{python_to_yaml, yaml_to_python};
+
+pub enum DocumentFilter {
+    /// Keep documents containing this mapping (see `matches`)
+    Matcher(Value),
+    /// Keep documents for which the callable returns a truthy value
+    Callable(PyObject),
+}
+
+impl DocumentFilter {
+    /// Build a filter from the `filter=` argument
+    ///
+    /// # Errors
+    /// * `TypeError` if it is neither a dict nor callable, or the dict
+    ///   contains values that are not plain data
+    pub fn from_py(obj: &Bound<'_, PyAny>) -> PyResult<Self> {
+        if obj.downcast::<PyDict>().is_ok() {
+            return Ok(DocumentFilter::Matcher(python_to_yaml(obj)?));
+        }
+        if obj.is_callable() {
+            return Ok(DocumentFilter::Callable(obj.clone().unbind()));
+        }
+        Err(PyTypeError::new_err(
+            "filter must be a dict matcher or a callable",
+        ))
+    }
+
+    /// Is `parsed` fully handled by `check_parsed`?
+    pub fn is_declarative(&self, parsed: &Parsed) -> bool {
+        matches!(
+            (self, parsed),
+            (DocumentFilter::Matcher(_), Parsed::Value(_))
+        )
+    }
+
+    /// Check a parsed document without the GIL
+    ///
+    /// Returns `None` if the decision needs the converted object.
+    pub fn check_parsed(&self, parsed: &Parsed) -> Option<bool> {
+        match (self, parsed) {
+            (DocumentFilter::Matcher(expected), Parsed::Value(actual)) => {
+                Some(matches(expected, actual))
+            }
+            _ => None,
+        }
+    }
+
+    /// Check a converted document
+    pub fn check_object(&self, py: Python, obj: &Bound<'_, PyAny>) -> PyResult<bool> {
+        match self {
+            DocumentFilter::Matcher(expected) => {
+                py_matches(yaml_to_python(py, expected)?.bind(py), obj)
+            }
+            DocumentFilter::Callable(callable) => callable.bind(py).call1((obj,))?.is_truthy(),
+        }
+    }
+}
+
+/// Does `actual` contain `expected`?
+///
+/// Mappings match if every expected key is present with a matching value
+/// (extra keys are ignored); sequences match item by item; scalars must be
+/// equal, with `1` matching `1.0` as in Python.
+pub fn matches(expected: &Value, actual: &Value) -> bool {
+    match (expected, actual) {
+        (Value::Mapping(expected), Value::Mapping(actual)) => expected
+            .iter()
+            .all(|(key, value)| actual.get(key).is_some_and(|actual| matches(value, actual))),
+        (Value::Sequence(expected), Value::Sequence(actual)) => {
+            expected.len() == actual.len()
+                && expected.iter().zip(actual).all(|(e, a)| matches(e, a))
+        }
+        (Value::Number(expected), Value::Number(actual)) => {
+            match (expected.as_i64(), actual.as_i64()) {
+                (Some(e), Some(a)) => e == a,
+                _ => expected.as_f64() == actual.as_f64(),
+            }
+        }
+        (Value::Tagged(_), _) => false,
+        (_, Value::Tagged(actual)) => matches(expected, &actual.value),
+        _ => expected == actual,
+    }
+}
+
+/// `matches` for converted Python objects
+fn py_matches(expected: &Bound<'_, PyAny>, actual: &Bound<'_, PyAny>) -> PyResult<bool> {
+    if let Ok(expected) = expected.downcast::<PyDict>() {
+        let Ok(actual) = actual.downcast::<PyDict>() else {
+            return Ok(false);
+        };
+        for (key, value) in expected.iter() {
+            match actual.get_item(&key)? {
+                Some(actual) if py_matches(&value, &actual)? => {}
+                _ => return Ok(false),
+            }
+        }
+        return Ok(true);
+    }
+    if let Ok(expected) = expected.downcast::<PyList>() {
+        let Ok(actual) = actual.downcast::<PyList>() else {
+            return Ok(false);
+        };
+        if expected.len() != actual.len() {
+            return Ok(false);
+        }
+        for (e, a) in expected.iter().zip(actual.iter()) {
+            if !py_matches(&e, &a)? {
+                return Ok(false);
+            }
+        }
+        return Ok(true);
+    }
+    expected.eq(actual)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn yaml(text: &str) -> Value {
+        serde_yaml::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn test_subset_matching() {
+        let expected = yaml("{kind: Deployment, metadata: {namespace: prod}}");
+        assert!(matches(
+            &expected,
+            &yaml("{kind: Deployment, metadata: {name: web, namespace: prod}}")
+        ));
+        assert!(!matches(&expected, &yaml("{kind: Deployment}")));
+        assert!(!matches(&expected, &yaml("{kind: Service}")));
+        assert!(!matches(&expected, &yaml("[kind, Deployment]")));
+    }
+
+    #[test]
+    fn test_scalar_matching() {
+        assert!(matches(&yaml("{replicas: 1}"), &yaml("{replicas: 1.0}")));
+        assert!(!matches(&yaml("{replicas: 1}"), &yaml("{replicas: '1'}")));
+        assert!(matches(&yaml("{ports: [80]}"), &yaml("{ports: [80]}")));
+        assert!(!matches(
+            &yaml("{ports: [80]}"),
+            &yaml("{ports: [80, 443]}")
+        ));
+    }
+
+    #[test]
+    fn test_python_matching() {
+        Python::with_gil(|py| {
+            let expected = yaml("{kind: Deployment}");
+            let filter = DocumentFilter::Matcher(expected);
+            let doc = yaml_to_python(py, &yaml("{kind: Deployment, spec: {}}")).unwrap();
+            assert!(filter.check_object(py, doc.bind(py)).unwrap());
+            let doc = yaml_to_python(py, &yaml("[1, 2]")).unwrap();
+            assert!(!filter.check_object(py, doc.bind(py)).unwrap());
+        });
+    }
+}
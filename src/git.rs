@@ -0,0 +1,272 @@
+//! Load YAML from a git repository at any revision, without a checkout
+//!
+//! `load_git` reads blobs straight from the object database, so configs
+//! can be loaded (and validated) as of any commit, tag or branch without
+//! touching a worktree. Remote repositories are cloned bare into a
+//! temporary directory first, which is removed afterwards.
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::path::PathBuf;
+
+use crate::batch;
+use crate::encoding;
+use crate::error::YAMLError;
+use crate::filter::DocumentFilter;
+use crate::interrupt::{self, Cancel};
+use crate::options::LoadOptions;
+
+fn git_error(message: String) -> YAMLError {
+    YAMLError::Git { message }
+}
+
+fn is_yaml(path: &str) -> bool {
+    path.ends_with(".yaml") || path.ends_with(".yml")
+}
+
+/// Does `repo` name a remote repository (as opposed to a local path)?
+fn is_remote(repo: &str) -> bool {
+    if let Some((scheme, _)) = repo.split_once("://") {
+        return scheme != "file";
+    }
+    // scp-like syntax: git@github.com:org/repo.git
+    match repo.split_once(':') {
+        Some((host, _)) => host.contains('@') || (host.contains('.') && !host.contains('/')),
+        None => false,
+    }
+}
+
+/// Open a local repository (worktree or bare), or clone a remote one into
+/// `scratch`
+fn open(
+    repo: &str,
+    scratch: &mut Option<PathBuf>,
+    cancel: &Cancel,
+) -> Result<gix::Repository, YAMLError> {
+    if !is_remote(repo) {
+        let path = repo.strip_prefix("file://").unwrap_or(repo);
+        return gix::open(path)
+            .map_err(|err| git_error(format!("Failed to open repository {}: {}", repo, err)));
+    }
+    let directory = std::env::temp_dir().join(format!(
+        "rustyyaml-git-{}-{}",
+        std::process::id(),
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos()
+    ));
+    *scratch = Some(directory.clone());
+    let clone_error = |err: gix::Error| git_error(format!("Failed to clone {}: {}", repo, err));
+    let mut clone = gix::prepare_clone_bare(repo, &directory).map_err(clone_error)?;
+    let (repository, _) = clone
+        .fetch_only(gix::progress::Discard, cancel.flag())
+        .map_err(clone_error)?;
+    Ok(repository)
+}
+
+/// `(path, bytes)` of the YAML blobs at `path` in `revision`, in path order
+///
+/// `path` may name a directory (its YAML files are listed, recursively if
+/// asked) or a single file.
+///
+/// # Errors
+/// * Unknown revisions and paths
+pub fn read_blobs(
+    repository: &gix::Repository,
+    revision: &str,
+    path: &str,
+    recursive: bool,
+    cancel: &Cancel,
+) -> Result<Vec<(String, Vec<u8>)>, YAMLError> {
+    let read_error = |err: gix::Error| git_error(format!("Failed to read {}: {}", path, err));
+    let tree = repository
+        .rev_parse_single(revision)
+        .and_then(|id| id.object())
+        .and_then(|object| object.peel_to_tree())
+        .map_err(|err| git_error(format!("Unknown revision '{}': {}", revision, err)))?;
+
+    let path = path.trim_matches('/');
+    let root = path.is_empty() || path == ".";
+    let mut files = Vec::new();
+    let tree = if root {
+        Some(tree)
+    } else {
+        let entry = tree
+            .lookup_entry_by_path(path)
+            .map_err(read_error)?
+            .ok_or_else(|| git_error(format!("'{}' does not exist at {}", path, revision)))?;
+        if entry.mode().is_blob() {
+            files.push((path.to_string(), entry.object_id()));
+            None
+        } else {
+            Some(
+                entry
+                    .object()
+                    .and_then(|object| object.peel_to_tree())
+                    .map_err(read_error)?,
+            )
+        }
+    };
+
+    if let Some(tree) = tree {
+        let prefix = if root {
+            String::new()
+        } else {
+            format!("{}/", path)
+        };
+        if recursive {
+            for entry in tree.traverse().breadthfirst.files().map_err(read_error)? {
+                if entry.mode.is_blob() {
+                    files.push((format!("{}{}", prefix, entry.filepath), entry.oid));
+                }
+            }
+        } else {
+            for entry in tree.iter() {
+                let entry = entry.map_err(read_error)?;
+                if entry.mode().is_blob() {
+                    files.push((
+                        format!("{}{}", prefix, entry.filename()),
+                        entry.oid().to_owned(),
+                    ));
+                }
+            }
+        }
+    }
+    files.retain(|(path, _)| is_yaml(path));
+    files.sort();
+
+    let mut blobs = Vec::with_capacity(files.len());
+    for (path, id) in files {
+        if cancel.is_set() {
+            break;
+        }
+        let mut blob = repository.find_blob(id).map_err(read_error)?;
+        blobs.push((path, blob.take_data()));
+    }
+    Ok(blobs)
+}
+
+/// Load every YAML file under a path of a git repository at a revision
+///
+/// # Arguments
+/// * `repo` - Local repository (worktree or bare) or a URL to clone
+/// * `ref` - Any revision git understands: branch, tag, commit, `HEAD~3`
+/// * `path` - Directory (or single file) within the repository
+/// * `recursive` - Include subdirectories of `path`
+/// * `transform`, `filter` - As for `load_directory`
+/// * `options` - Load options, applied to every file
+///
+/// # Returns
+/// List of `(path, data)` tuples with paths relative to the repository
+/// root; `(results, errors)` with `collect_errors=True`
+///
+/// # Example
+/// ```python
+/// manifests = rustyyaml.load_git(".", ref="v1.4.0", path="deploy/", recursive=True)
+/// ```
+#[pyfunction]
+#[pyo3(signature = (repo, r#ref="main", path="", recursive=false, transform=None, filter=None, **options))]
+#[allow(clippy::too_many_arguments)]
+pub fn load_git(
+    py: Python,
+    repo: &str,
+    r#ref: &str,
+    path: &str,
+    recursive: bool,
+    transform: Option<&Bound<'_, PyAny>>,
+    filter: Option<&Bound<'_, PyAny>>,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<PyObject> {
+    let options = LoadOptions::from_kwargs(options)?;
+    let filter = filter.map(DocumentFilter::from_py).transpose()?;
+    let filter = filter.as_ref();
+
+    let mut scratch = None;
+    let loaded = interrupt::run(py, options.cancel.as_deref(), |cancel| {
+        let repository = open(repo, &mut scratch, cancel)?;
+        let blobs = read_blobs(&repository, r#ref, path, recursive, cancel)?;
+        let contents = blobs
+            .into_iter()
+            .map(|(path, bytes)| {
+                (
+                    PathBuf::from(path),
+                    encoding::decode_with(&bytes, options.encoding),
+                )
+            })
+            .collect();
+        Ok::<_, YAMLError>(batch::parse_deduplicated(
+            contents, cancel, filter, &options, true,
+        ))
+    });
+    if let Some(scratch) = scratch {
+        let _ = std::fs::remove_dir_all(scratch);
+    }
+
+    batch::convert_results(py, loaded??, filter, transform, &options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    fn git(directory: &std::path::Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(directory)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    #[test]
+    fn test_is_remote() {
+        assert!(is_remote("https://github.com/org/repo.git"));
+        assert!(is_remote("git@github.com:org/repo.git"));
+        assert!(is_remote("ssh://git@host/repo"));
+        assert!(!is_remote("file:///srv/repo"));
+        assert!(!is_remote("../configs"));
+        assert!(!is_remote("/srv/repo.git"));
+    }
+
+    #[test]
+    fn test_read_blobs() {
+        let directory =
+            std::env::temp_dir().join(format!("rustyyaml-git-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(directory.join("deploy/nested")).unwrap();
+        std::fs::write(directory.join("deploy/a.yaml"), "v: 1").unwrap();
+        std::fs::write(directory.join("deploy/nested/b.yml"), "v: 2").unwrap();
+        std::fs::write(directory.join("deploy/README.md"), "docs").unwrap();
+        git(&directory, &["init", "-q", "-b", "main"]);
+        git(&directory, &["add", "."]);
+        git(&directory, &["commit", "-q", "-m", "first"]);
+        std::fs::write(directory.join("deploy/a.yaml"), "v: 3").unwrap();
+        git(&directory, &["commit", "-q", "-am", "second"]);
+
+        let repository = gix::open(&directory).unwrap();
+        let cancel = Cancel::default();
+        let read =
+            |revision, path, recursive| read_blobs(&repository, revision, path, recursive, &cancel);
+        assert_eq!(
+            read("main", "deploy/", true).unwrap(),
+            [
+                ("deploy/a.yaml".to_string(), b"v: 3".to_vec()),
+                ("deploy/nested/b.yml".to_string(), b"v: 2".to_vec()),
+            ]
+        );
+        assert_eq!(
+            read("HEAD~1", "deploy", false).unwrap(),
+            [("deploy/a.yaml".to_string(), b"v: 1".to_vec())]
+        );
+        assert_eq!(read("main", "deploy/nested/b.yml", false).unwrap().len(), 1);
+        let missing = read("main", "missing", false).unwrap_err();
+        assert_eq!(missing.to_string(), "'missing' does not exist at main");
+        let unknown = read("no-such-branch", "", false).unwrap_err();
+        assert!(matches!(unknown, YAMLError::Git { .. }));
+        assert_eq!(unknown.location(), None);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,342 @@
+//! Convert between YAML and INI (ConfigParser) files
+//!
+//! Top-level mappings are sections. INI has no nesting, so a mapping inside
+//! a section becomes a dotted section (`[server.tls]`), and a sequence of
+//! scalars becomes a value with one item per continuation line:
+//!
+//! ```ini
+//! [server]
+//! hosts =
+//!     a.example.com
+//!     b.example.com
+//! ```
+//!
+//! Reading INI goes the other way: dotted sections nest, values that start
+//! on a continuation line become lists, and values resolve like plain YAML
+//! scalars (`8080` is an int, `true` a bool; an empty value stays an empty
+//! string). Key case and order are kept.
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use serde_yaml::{Mapping, Value};
+
+use crate::error::YAMLError;
+use crate::options::LoadOptions;
+use crate::parser::{self, Parsed};
+use crate::resolve;
+use crate::schema::scalar_value;
+use crate::search::key_text;
+
+/// A scalar as INI text; `None` for collections
+fn scalar_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => Some(String::new()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::String(s) => Some(s.clone()),
+        Value::Tagged(tagged) => scalar_text(&tagged.value),
+        Value::Mapping(_) | Value::Sequence(_) => None,
+    }
+}
+
+/// Write one section, then its subsections
+fn write_section(name: &str, map: &Mapping, out: &mut String) -> Result<(), String> {
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    out.push_str(&format!("[{}]\n", name));
+
+    let mut subsections = Vec::new();
+    for (key, value) in map {
+        let key = key_text(key).ok_or_else(|| format!("non-scalar key in [{}]", name))?;
+        let value = match value {
+            Value::Tagged(tagged) => &tagged.value,
+            value => value,
+        };
+        match value {
+            Value::Mapping(child) => subsections.push((format!("{}.{}", name, key), child)),
+            Value::Sequence(items) => {
+                out.push_str(&format!("{} =\n", key));
+                for item in items {
+                    let text = scalar_text(item).ok_or_else(|| {
+                        format!(
+                            "{}.{}: only lists of scalars can be written to INI",
+                            name, key
+                        )
+                    })?;
+                    out.push_str(&format!("    {}\n", single_line(&text, name, &key)?));
+                }
+            }
+            value => {
+                let text = scalar_text(value).unwrap_or_default();
+                out.push_str(&format!("{} = {}\n", key, single_line(&text, name, &key)?));
+            }
+        }
+    }
+    for (name, child) in subsections {
+        write_section(&name, child, out)?;
+    }
+    Ok(())
+}
+
+fn single_line<'t>(text: &'t str, section: &str, key: &str) -> Result<&'t str, String> {
+    if text.contains(['\n', '\r']) {
+        return Err(format!(
+            "{}.{}: multi-line strings cannot be written to INI",
+            section, key
+        ));
+    }
+    Ok(text)
+}
+
+/// Render a document as INI
+///
+/// # Errors
+/// A message if the document does not fit INI: it is not a mapping of
+/// mappings, or holds multi-line strings or nested lists.
+pub fn to_ini(document: &Value) -> Result<String, String> {
+    let Value::Mapping(sections) = document else {
+        return Err("an INI document must be a mapping of sections".to_string());
+    };
+    let mut out = String::new();
+    for (name, section) in sections {
+        let name = key_text(name).ok_or("section names must be scalars")?;
+        match section {
+            Value::Mapping(map) => write_section(&name, map, &mut out)?,
+            _ => {
+                return Err(format!(
+                    "top-level key '{}' is not a mapping (INI values must be in a section)",
+                    name
+                ))
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Resolve text like a plain YAML scalar (an empty string stays a string)
+pub fn typed(text: &str) -> Value {
+    if text.is_empty() {
+        return Value::String(String::new());
+    }
+    scalar_value(resolve::resolve_core(text)).unwrap_or_else(|| Value::String(text.to_string()))
+}
+
+/// The mapping for a (dotted) section name, created if missing
+fn section_mut<'m>(
+    root: &'m mut Mapping,
+    name: &str,
+    line: usize,
+) -> Result<&'m mut Mapping, YAMLError> {
+    let mut map = root;
+    for part in name.split('.') {
+        let entry = map
+            .entry(Value::String(part.to_string()))
+            .or_insert_with(|| Value::Mapping(Mapping::new()));
+        map = match entry {
+            Value::Mapping(child) => child,
+            _ => {
+                return Err(YAMLError::parse(
+                    line,
+                    1,
+                    format!("section [{}] clashes with the key '{}'", name, part),
+                ))
+            }
+        };
+    }
+    Ok(map)
+}
+
+/// A key waiting for possible continuation lines
+struct Pending {
+    section: String,
+    key: String,
+    first: String,
+    rest: Vec<String>,
+    line: usize,
+}
+
+impl Pending {
+    fn store(self, root: &mut Mapping) -> Result<(), YAMLError> {
+        let value = if self.rest.is_empty() {
+            typed(&self.first)
+        } else if self.first.is_empty() {
+            Value::Sequence(self.rest.iter().map(|item| typed(item)).collect())
+        } else {
+            Value::String(format!("{}\n{}", self.first, self.rest.join("\n")))
+        };
+        let map = section_mut(root, &self.section, self.line)?;
+        let key = Value::String(self.key);
+        if map.contains_key(&key) {
+            return Err(YAMLError::parse(
+                self.line,
+                1,
+                format!(
+                    "duplicate key '{}' in [{}]",
+                    key.as_str().unwrap_or_default(),
+                    self.section
+                ),
+            ));
+        }
+        map.insert(key, value);
+        Ok(())
+    }
+}
+
+/// Parse INI text into a mapping of sections
+///
+/// # Errors
+/// * Parse errors (with the 1-based line) for keys outside a section,
+///   lines that are not `key = value`, and duplicate keys
+pub fn from_ini(text: &str) -> Result<Value, YAMLError> {
+    let mut root = Mapping::new();
+    let mut section: Option<String> = None;
+    let mut pending: Option<Pending> = None;
+
+    for (index, raw) in text.lines().enumerate() {
+        let line = index + 1;
+        let trimmed = raw.trim();
+        if raw.starts_with([' ', '\t']) && !trimmed.is_empty() {
+            if let Some(pending) = &mut pending {
+                pending.rest.push(trimmed.to_string());
+                continue;
+            }
+        }
+        if let Some(done) = pending.take() {
+            done.store(&mut root)?;
+        }
+        if trimmed.is_empty() || trimmed.starts_with(['#', ';']) {
+            continue;
+        }
+        if let Some(name) = trimmed
+            .strip_prefix('[')
+            .and_then(|rest| rest.strip_suffix(']'))
+        {
+            let name = name.trim().to_string();
+            section_mut(&mut root, &name, line)?;
+            section = Some(name);
+            continue;
+        }
+        let Some(split) = trimmed.find(['=', ':']) else {
+            return Err(YAMLError::parse(
+                line,
+                1,
+                format!("expected 'key = value', found '{}'", trimmed),
+            ));
+        };
+        let Some(section) = &section else {
+            return Err(YAMLError::parse(
+                line,
+                1,
+                "key outside of a section (add a [section] header)".to_string(),
+            ));
+        };
+        pending = Some(Pending {
+            section: section.clone(),
+            key: trimmed[..split].trim().to_string(),
+            first: trimmed[split + 1..].trim().to_string(),
+            rest: Vec::new(),
+            line,
+        });
+    }
+    if let Some(done) = pending {
+        done.store(&mut root)?;
+    }
+    Ok(Value::Mapping(root))
+}
+
+/// Convert a YAML document to INI
+///
+/// # Arguments
+/// * `yaml_str` - YAML content: a mapping of sections
+///
+/// # Returns
+/// INI text readable by `configparser`
+///
+/// # Example
+/// ```python
+/// rustyyaml.yaml_to_ini("server: {port: 8080, hosts: [a, b]}")
+/// # '[server]\nport = 8080\nhosts =\n    a\n    b\n'
+/// ```
+#[pyfunction]
+pub fn yaml_to_ini(yaml_str: &str) -> PyResult<String> {
+    let document = match parser::parse_document(yaml_str, &LoadOptions::default(), true)? {
+        Parsed::Value(value) => value,
+        Parsed::Composed(_) => unreachable!("default options use serde_yaml"),
+    };
+    to_ini(&document).map_err(PyValueError::new_err)
+}
+
+/// Convert INI text to YAML
+///
+/// # Arguments
+/// * `ini_str` - INI content
+///
+/// # Returns
+/// YAML text with one top-level mapping per section, in file order
+///
+/// # Example
+/// ```python
+/// rustyyaml.ini_to_yaml("[server]\nport = 8080\n")
+/// # 'server:\n  port: 8080\n'
+/// ```
+#[pyfunction]
+pub fn ini_to_yaml(ini_str: &str) -> PyResult<String> {
+    let document = from_ini(ini_str)?;
+    serde_yaml::to_string(&document).map_err(|err| PyValueError::new_err(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn yaml(text: &str) -> Value {
+        serde_yaml::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn test_to_ini() {
+        let document = yaml(
+            "server:\n  port: 8080\n  tls: {cert: a.pem}\n  hosts: [a, b]\n  debug: false\n\
+             empty: {}\n",
+        );
+        assert_eq!(
+            to_ini(&document).unwrap(),
+            "[server]\nport = 8080\nhosts =\n    a\n    b\ndebug = false\n\n\
+             [server.tls]\ncert = a.pem\n\n[empty]\n"
+        );
+        assert!(to_ini(&yaml("a: 1")).is_err());
+        assert!(to_ini(&yaml("a: {b: [[1]]}")).is_err());
+        assert!(to_ini(&yaml("a: {b: \"x\\ny\"}")).is_err());
+    }
+
+    #[test]
+    fn test_from_ini() {
+        let ini =
+            "; legacy\n[server]\nport = 8080\nHost: example.com\nempty =\nhosts =\n    a\n    \
+                   b\n\n[server.tls]\nenabled = true\nratio = 0.5\n";
+        assert_eq!(
+            from_ini(ini).unwrap(),
+            yaml(
+                "server:\n  port: 8080\n  Host: example.com\n  empty: ''\n  hosts: [a, b]\n  \
+                 tls: {enabled: true, ratio: 0.5}\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let document = yaml("db:\n  port: 5432\n  replicas: [r1, r2]\n  ssl: {mode: require}\n");
+        assert_eq!(from_ini(&to_ini(&document).unwrap()).unwrap(), document);
+    }
+
+    #[test]
+    fn test_from_ini_errors() {
+        let err = from_ini("a = 1\n").unwrap_err();
+        assert_eq!(err.location(), Some((1, 1)));
+        assert!(from_ini("[s]\nnot a pair\n").is_err());
+        let err = from_ini("[s]\na = 1\na = 2\n").unwrap_err();
+        assert!(err.to_string().contains("duplicate key 'a' in [s]"));
+        assert!(from_ini("[s]\nt = 1\n[s.t]\n").is_err());
+    }
+}
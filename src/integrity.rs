@@ -0,0 +1,240 @@
+//! Checksum-verified loading (`safe_load_file(path, sha256=...)`)
+//!
+//! Deploy-time configs can be pinned to the SHA-256 digests recorded when
+//! they were released. Each file is hashed from the bytes read, before it
+//! is decoded or parsed, so a modified file never reaches the parser; a
+//! mismatch raises `IntegrityError`, a `YAMLError` subclass with
+//! `expected` and `actual` digests.
+
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use rayon::prelude::*;
+use sha2::{Digest, Sha256};
+
+use crate::batch::{self, convert_results, parse_deduplicated};
+use crate::compression::{self, Compression};
+use crate::encoding::Decoded;
+use crate::error::YAMLError;
+use crate::filter::DocumentFilter;
+use crate::interrupt;
+use crate::options::LoadOptions;
+use crate::parser;
+
+/// Lowercase hex SHA-256 of `bytes`
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .fold(String::with_capacity(64), |mut hex, byte| {
+            let _ = write!(hex, "{:02x}", byte);
+            hex
+        })
+}
+
+/// Normalize an expected digest: hex, either case, optionally prefixed
+/// with `sha256:` (as in OCI and SRI-style manifests)
+///
+/// # Errors
+/// * `ValueError` if it isn't 64 hex digits
+pub fn parse_digest(digest: &str) -> PyResult<String> {
+    let hex = digest.trim();
+    let hex = hex.strip_prefix("sha256:").unwrap_or(hex);
+    if hex.len() != 64 || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return Err(PyValueError::new_err(format!(
+            "sha256 must be 64 hex digits, not {:?}",
+            digest
+        )));
+    }
+    Ok(hex.to_ascii_lowercase())
+}
+
+/// Read (or map, with `use_mmap`) a file, check its digest, then decode it
+///
+/// The digest is of the file as stored, so for `config.yaml.gz` it is
+/// checked before decompressing.
+///
+/// # Errors
+/// * `Integrity` if the file's SHA-256 isn't `expected` (normalized)
+/// * Read and decoding errors, as for `batch::read_decoded`
+pub fn read_verified(
+    path: &Path,
+    expected: &str,
+    options: &LoadOptions,
+) -> Result<Decoded, YAMLError> {
+    if options.use_mmap && Compression::of(path).is_none() {
+        let map = batch::map_file(path)?;
+        options
+            .limits
+            .check_size(map.as_ref().map_or(0, |map| map.len()))?;
+        check_digest(path, expected, map.as_deref().map_or(&[], |map| &map[..]))?;
+        return batch::decode_mapped_file(path, map, options.encoding);
+    }
+    // A compressed file is hashed as stored, so it's read whole; it's
+    // only its decompressed size that `max_size_bytes` bounds
+    let bytes = match Compression::of(path) {
+        Some(_) => batch::read_bytes(path)?,
+        None => compression::read(path, &options.limits)?,
+    };
+    check_digest(path, expected, &bytes)?;
+    let bytes = compression::decompress(path, bytes, &options.limits)?;
+    batch::decode_file(path, &bytes, options.encoding)
+}
+
+fn check_digest(path: &Path, expected: &str, bytes: &[u8]) -> Result<(), YAMLError> {
+    let actual = sha256_hex(bytes);
+    if actual != expected {
+        return Err(YAMLError::Integrity {
+            path: path.display().to_string(),
+            expected: expected.to_string(),
+            actual,
+        });
+    }
+    Ok(())
+}
+
+/// Load a single-document file after checking its SHA-256
+///
+/// # Arguments
+/// * `path` - Path to the YAML file
+/// * `sha256` - Expected digest (hex, optionally prefixed `sha256:`)
+/// * `safe` - Reject unsafe tags, as `safe_load` does
+/// * `options` - Load options
+///
+/// # Errors
+/// * `IntegrityError` if the digest doesn't match; nothing is parsed
+/// * Parse errors, with `path` set
+#[pyfunction]
+#[pyo3(signature = (path, sha256, safe=true, **options))]
+pub fn load_file_verified(
+    py: Python,
+    path: PathBuf,
+    sha256: &str,
+    safe: bool,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<PyObject> {
+    let options = LoadOptions::from_kwargs(options)?;
+    options.check_single_document()?;
+    let expected = parse_digest(sha256)?;
+    let loaded = py
+        .allow_threads(|| read_verified(&path, &expected, &options))
+        .map_err(PyErr::from)
+        .and_then(|decoded| {
+            if safe {
+                parser::parse_safe(py, &decoded.text, &options)
+            } else {
+                parser::parse_unsafe(py, &decoded.text, &options)
+            }
+        });
+    loaded.inspect_err(|err| {
+        // Best effort: the error is raised either way
+        let _ = err.value_bound(py).setattr("path", &path);
+    })
+}
+
+/// Load the files of a manifest in parallel, checking each one's SHA-256
+///
+/// # Arguments
+/// * `manifest` - Mapping of path to expected digest
+/// * `transform` - Called on each parsed document (see `load_directory`)
+/// * `filter` - Keep only matching files (see `load_directory`)
+/// * `options` - Load options, applied to every file
+///
+/// # Returns
+/// `(path, data)` tuples in manifest order, shaped like `load_directory`'s
+/// results (`file_info`, `collect_errors` and `on_error` work the same;
+/// a mismatched file fails with `IntegrityError`)
+///
+/// # Example
+/// ```python
+/// manifest = json.load(open("release.json"))["sha256"]
+/// configs = rustyyaml.load_files_verified(manifest)
+/// ```
+#[pyfunction]
+#[pyo3(signature = (manifest, transform=None, filter=None, **options))]
+pub fn load_files_verified(
+    py: Python,
+    manifest: &Bound<'_, PyAny>,
+    transform: Option<&Bound<'_, PyAny>>,
+    filter: Option<&Bound<'_, PyAny>>,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<PyObject> {
+    let options = LoadOptions::from_kwargs(options)?;
+    let filter = filter.map(DocumentFilter::from_py).transpose()?;
+    let mut files = Vec::new();
+    for item in manifest.call_method0("items")?.iter()? {
+        let (path, digest): (PathBuf, String) = item?.extract()?;
+        files.push((path, parse_digest(&digest)?));
+    }
+
+    let loaded = interrupt::run(py, options.cancel.as_deref(), |cancel| {
+        let contents = files
+            .par_iter()
+            .map(|(path, expected)| {
+                let content = if cancel.is_set() {
+                    Ok(Decoded::default())
+                } else {
+                    read_verified(path, expected, &options)
+                };
+                (path.clone(), content)
+            })
+            .collect();
+        parse_deduplicated(contents, cancel, filter.as_ref(), &options, true)
+    })?;
+
+    convert_results(py, loaded, filter.as_ref(), transform, &options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::Text;
+    use std::fs;
+
+    const EMPTY: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    #[test]
+    fn test_sha256_hex() {
+        assert_eq!(sha256_hex(b""), EMPTY);
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_parse_digest() {
+        assert_eq!(parse_digest(EMPTY).unwrap(), EMPTY);
+        let prefixed = format!("sha256:{}", EMPTY.to_uppercase());
+        assert_eq!(parse_digest(&prefixed).unwrap(), EMPTY);
+        assert!(parse_digest("abc").is_err());
+        assert!(parse_digest(&EMPTY.replace('e', "g")).is_err());
+    }
+
+    #[test]
+    fn test_read_verified() {
+        let dir = std::env::temp_dir().join(format!("rustyyaml-integrity-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.yaml");
+        fs::write(&path, "a: 1\n").unwrap();
+
+        let digest = sha256_hex(b"a: 1\n");
+        let options = LoadOptions::default();
+        let decoded = read_verified(&path, &digest, &options).unwrap();
+        assert_eq!(decoded.text, "a: 1\n");
+        let mapped = LoadOptions {
+            use_mmap: true,
+            ..Default::default()
+        };
+        let decoded = read_verified(&path, &digest, &mapped).unwrap();
+        assert!(matches!(decoded.text, Text::Mapped { .. }));
+        assert_eq!(decoded.text, "a: 1\n");
+
+        let err = read_verified(&path, EMPTY, &options).unwrap_err();
+        assert!(matches!(&err, YAMLError::Integrity { actual, .. } if *actual == digest));
+        assert!(err.to_string().contains("config.yaml"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,182 @@
+//! Keep long parallel jobs interruptible
+//!
+//! Python only runs signal handlers (and so raises `KeyboardInterrupt`)
+//! when the main thread executes bytecode. While a batch job runs on the
+//! rayon pool with the GIL released, the main thread is stuck waiting for
+//! it, so Ctrl-C would only be noticed once every file was parsed.
+//!
+//! `run` moves the job to a helper thread and has the calling thread poll
+//! for signals while it waits. When a signal handler raises, the job is
+//! told to stop through a shared flag, which workers check between items;
+//! `run` waits for the workers to wind down (so no thread outlives the
+//! call) and returns the handler's exception.
+//!
+//! A `CancellationToken` (the `cancel=` load option) stops a job the same
+//! way from any thread, for services that run batch loads in an executor
+//! and need to shut down without waiting for them; the job then raises
+//! `concurrent.futures.CancelledError`.
+
+use pyo3::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+/// How often to check for signals while a job runs
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Set when the job should stop early
+#[derive(Debug, Default)]
+pub struct Cancel(AtomicBool);
+
+impl Cancel {
+    pub fn is_set(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub fn set(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// The flag itself, for libraries that take their own interrupt flag
+    pub fn flag(&self) -> &AtomicBool {
+        &self.0
+    }
+}
+
+/// Lets another thread cancel the batch loads it is passed to
+///
+/// ```python
+/// token = rustyyaml.CancellationToken()
+/// future = executor.submit(rustyyaml.load_directory, "k8s/", cancel=token)
+/// ...
+/// token.cancel()  # the load raises concurrent.futures.CancelledError
+/// ```
+#[pyclass(module = "rustyyaml", frozen)]
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    pub cancel: Arc<Cancel>,
+}
+
+#[pymethods]
+impl CancellationToken {
+    #[new]
+    fn new() -> Self {
+        CancellationToken::default()
+    }
+
+    /// Stop every load using this token; loads started later fail at once
+    fn cancel(&self) {
+        self.cancel.set();
+    }
+
+    #[getter]
+    fn cancelled(&self) -> bool {
+        self.cancel.is_set()
+    }
+
+    fn __repr__(&self) -> String {
+        let cancelled = if self.cancelled() { "True" } else { "False" };
+        format!("CancellationToken(cancelled={})", cancelled)
+    }
+}
+
+fn cancelled_error(py: Python) -> PyErr {
+    match py
+        .import_bound("concurrent.futures")
+        .and_then(|module| module.getattr("CancelledError"))
+    {
+        Ok(error) => PyErr::from_value_bound(
+            error
+                .call1(("the operation was cancelled",))
+                .unwrap_or(error),
+        ),
+        Err(err) => err,
+    }
+}
+
+/// Run `job` without the GIL, raising `KeyboardInterrupt` (or whatever a
+/// signal handler raises) if a signal arrives before it finishes, and
+/// `CancelledError` if `token` is cancelled
+///
+/// `job` should check `Cancel::is_set` between items and skip the rest of
+/// its work once it is set; its result is discarded in that case.
+pub fn run<T, F>(py: Python, token: Option<&Cancel>, job: F) -> PyResult<T>
+where
+    T: Send,
+    F: FnOnce(&Cancel) -> T + Send,
+{
+    if token.is_some_and(Cancel::is_set) {
+        return Err(cancelled_error(py));
+    }
+    let cancel = Cancel::default();
+    py.allow_threads(|| {
+        thread::scope(|scope| {
+            let (sender, receiver) = mpsc::channel();
+            let cancel = &cancel;
+            scope.spawn(move || {
+                // The receiver is gone only if we were interrupted
+                let _ = sender.send(job(cancel));
+            });
+            loop {
+                match receiver.recv_timeout(POLL_INTERVAL) {
+                    Ok(result) => return Ok(result),
+                    Err(mpsc::RecvTimeoutError::Timeout) => {
+                        let stop = Python::with_gil(|py| match py.check_signals() {
+                            Err(err) => Some(err),
+                            Ok(()) if token.is_some_and(Cancel::is_set) => {
+                                Some(cancelled_error(py))
+                            }
+                            Ok(()) => None,
+                        });
+                        if let Some(err) = stop {
+                            // Leaving the scope joins the job once workers notice
+                            cancel.set();
+                            return Err(err);
+                        }
+                    }
+                    Err(mpsc::RecvTimeoutError::Disconnected) => {
+                        panic!("batch job panicked")
+                    }
+                }
+            }
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_returns_result() {
+        Python::with_gil(|py| {
+            let result = run(py, None, |cancel| {
+                thread::sleep(POLL_INTERVAL * 2);
+                !cancel.is_set()
+            });
+            assert!(result.unwrap());
+        });
+    }
+
+    #[test]
+    fn test_run_stops_when_cancelled() {
+        Python::with_gil(|py| {
+            let token = CancellationToken::default();
+            let stopped = AtomicBool::new(false);
+            let result = run(py, Some(&token.cancel), |cancel| {
+                token.cancel();
+                while !cancel.is_set() {
+                    thread::sleep(Duration::from_millis(5));
+                }
+                stopped.store(true, Ordering::Relaxed);
+            });
+            let err = result.unwrap_err();
+            assert!(err.to_string().contains("CancelledError"));
+            // Workers have wound down by the time `run` returns
+            assert!(stopped.load(Ordering::Relaxed));
+            // Later jobs don't start
+            assert!(run(py, Some(&token.cancel), |_| unreachable!()).is_err());
+        });
+    }
+}
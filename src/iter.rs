@@ -0,0 +1,254 @@
+//! Lazy multi-document loading (`load_all_iter`)
+//!
+//! `load_all` parses and converts every document before it returns. The
+//! iterator here composes and converts one document per `next()`, so a
+//! caller can stop early, and a stream of millions of small documents is
+//! never held as nodes or Python objects all at once.
+//!
+//! Documents go through the composer (the path options such as
+//! `timestamps=` use), with the same checks and `sops_key=`, `schema=` and
+//! `redact=` passes as in `load_all`. With `document_spans=` each document
+//! comes with its span, as `load_all` gives it.
+
+use std::borrow::Cow;
+use std::io::Cursor;
+use std::sync::Arc;
+use std::time::Instant;
+
+use pyo3::exceptions::{PyException, PyTypeError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::compose::{Document, Documents};
+use crate::construct::construct_document;
+use crate::error::exceptions;
+use crate::events::Parser;
+use crate::limits;
+use crate::markers;
+use crate::metrics;
+use crate::options::LoadOptions;
+use crate::parser;
+use crate::safe;
+use crate::tabs;
+
+/// The stream's text, shared by the parser reading it and error messages
+pub struct Text(pub Arc<str>);
+
+impl AsRef<[u8]> for Text {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_bytes()
+    }
+}
+
+/// Iterator over the documents of a stream, loaded as they are reached
+#[pyclass(module = "rustyyaml", unsendable)]
+pub struct DocumentIterator {
+    documents: Documents<'static>,
+    text: Arc<str>,
+    /// The stream before `document_markers=` cleaned it, if that changed
+    /// it (spans are offsets into the original)
+    original: Option<Arc<str>>,
+    options: LoadOptions,
+    safe: bool,
+    /// Index of the next document
+    index: usize,
+}
+
+#[pymethods]
+impl DocumentIterator {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self, py: Python) -> PyResult<Option<PyObject>> {
+        let start = Instant::now();
+        let Some(document) = self.documents.next() else {
+            return Ok(None);
+        };
+        let index = self.index;
+        self.index += 1;
+
+        let prepared = document
+            .map_err(|err| tabs::explain(err, &self.text))
+            .and_then(|mut document| {
+                parser::prepare_document(&mut document, &self.options, self.safe)?;
+                Ok(document)
+            });
+        let size = prepared
+            .as_ref()
+            .map_or(0, |document| document.end.index - document.start.index);
+        metrics::record_parse(size, start.elapsed(), prepared.as_ref().ok().map(|_| 1));
+
+        prepared
+            .map_err(PyErr::from)
+            .and_then(|document| {
+                let obj = construct_document(py, Some(&document), &self.options)
+                    .map_err(|err| as_yaml_error(py, err))?;
+                if !self.options.document_spans {
+                    return Ok(obj);
+                }
+                Ok((obj, self.span(&document)).into_py(py))
+            })
+            .map(Some)
+            .inspect_err(|err| {
+                // Best effort: the error is raised either way
+                let _ = err.value_bound(py).setattr("document", index);
+            })
+    }
+}
+
+/// An error from building a document that isn't a `YAMLError` (the
+/// `TypeError` of an unhashable key) as one, the way `load_all` raises it
+fn as_yaml_error(py: Python, err: PyErr) -> PyErr {
+    if err.is_instance_of::<exceptions::YAMLError>(py) || !err.is_instance_of::<PyException>(py) {
+        return err;
+    }
+    let wrapped = exceptions::YAMLError::new_err(err.value_bound(py).to_string());
+    let value = wrapped.value_bound(py);
+    for name in ["line", "column", "path"] {
+        let _ = value.setattr(name, py.None());
+    }
+    wrapped.set_cause(py, Some(err));
+    wrapped
+}
+
+impl DocumentIterator {
+    /// Where `document` is in the original stream
+    fn span(&self, document: &Document) -> parser::Span {
+        let offset = |index| match &self.original {
+            Some(original) => parser::original_offset(original, &self.text, index),
+            None => index,
+        };
+        (
+            offset(document.start.index),
+            offset(document.end.index),
+            document.start.line + 1,
+        )
+    }
+}
+
+/// Load the documents of a stream lazily
+///
+/// # Arguments
+/// * `yaml_str` - YAML content with any number of documents
+/// * `safe` - Reject unsafe tags, as `load_all` does
+/// * `options` - Load options (`collect_errors` and `profile` need every
+///   document at once, and are rejected)
+///
+/// # Returns
+/// An iterator yielding one Python object per document, or
+/// `(document, (start, end, line))` pairs with `document_spans`
+///
+/// # Errors
+/// * `TypeError` for options that need every document
+/// * Unsafe tags anywhere in the stream (when `safe`); errors in a document
+///   are raised when it is reached, with `document` set to its index
+#[pyfunction]
+#[pyo3(signature = (yaml_str, safe=true, **options))]
+pub fn load_all_iter(
+    yaml_str: &str,
+    safe: bool,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<DocumentIterator> {
+    let options = LoadOptions::from_kwargs(options)?;
+    if options.collect_errors || options.profile.is_some() {
+        return Err(PyTypeError::new_err(
+            "load_all_iter can't be combined with collect_errors or profile",
+        ));
+    }
+    limits::check_size(yaml_str, &options)?;
+    if safe {
+        safe::quick_safety_check(yaml_str)?;
+    }
+    let cleaned = markers::apply(yaml_str, options.document_markers)?;
+    let original = match &cleaned {
+        Cow::Owned(_) if options.document_spans => Some(yaml_str.into()),
+        _ => None,
+    };
+    let text: Arc<str> = cleaned.into();
+    Ok(DocumentIterator {
+        documents: Documents::new(Parser::from_reader(Cursor::new(Text(text.clone())))),
+        text,
+        original,
+        options,
+        safe,
+        index: 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect(py: Python, yaml: &str) -> PyResult<Vec<PyObject>> {
+        let mut documents = load_all_iter(yaml, true, None)?;
+        std::iter::from_fn(|| documents.__next__(py).transpose()).collect()
+    }
+
+    #[test]
+    fn test_matches_load_all() {
+        Python::with_gil(|py| {
+            let yaml = "a: 1\n---\n- x\n- 2.5\n---\n---\nnull\n";
+            let lazy = collect(py, yaml).unwrap();
+            let eager = parser::parse_all(py, yaml, &LoadOptions::default()).unwrap();
+            assert_eq!(lazy.len(), eager.len());
+            for (lazy, eager) in lazy.iter().zip(&eager) {
+                assert!(lazy.bind(py).eq(eager).unwrap());
+            }
+            assert!(collect(py, "").unwrap().is_empty());
+        });
+    }
+
+    #[test]
+    fn test_errors_when_reached() {
+        Python::with_gil(|py| {
+            let mut documents = load_all_iter("a: 1\n---\nb: [\n", true, None).unwrap();
+            assert!(documents.__next__(py).unwrap().is_some());
+            let err = documents.__next__(py).unwrap_err();
+            let index: usize = err
+                .value_bound(py)
+                .getattr("document")
+                .unwrap()
+                .extract()
+                .unwrap();
+            assert_eq!(index, 1);
+            assert!(documents.__next__(py).unwrap().is_none());
+
+            // An unhashable key is a YAMLError, as from load_all
+            let mut documents = load_all_iter("a: 1\n---\n? [1, 2]\n: x\n", true, None).unwrap();
+            assert!(documents.__next__(py).unwrap().is_some());
+            let err = documents.__next__(py).unwrap_err();
+            assert!(err.is_instance_of::<exceptions::YAMLError>(py));
+            assert!(err.value_bound(py).to_string().contains("unhashable"));
+            assert!(err.cause(py).unwrap().is_instance_of::<PyTypeError>(py));
+
+            assert!(load_all_iter("a: !!python/object:os.system x", true, None).is_err());
+            let options = PyDict::new_bound(py);
+            options.set_item("collect_errors", true).unwrap();
+            assert!(load_all_iter("a: 1", true, Some(&options)).is_err());
+        });
+    }
+
+    #[test]
+    fn test_document_spans() {
+        Python::with_gil(|py| {
+            let options = PyDict::new_bound(py);
+            options.set_item("document_spans", true).unwrap();
+            options.set_item("document_markers", "lenient").unwrap();
+            let yaml = "---\n---\na: 1\n...\n...\n---\nb: 2\n";
+            let mut documents = load_all_iter(yaml, true, Some(&options)).unwrap();
+            let lazy: Vec<(PyObject, parser::Span)> =
+                std::iter::from_fn(|| documents.__next__(py).transpose())
+                    .map(|item| item.unwrap().extract(py).unwrap())
+                    .collect();
+            let options = LoadOptions::from_kwargs(Some(&options)).unwrap();
+            let eager = parser::parse_all_with_spans(py, yaml, &options, true).unwrap();
+            assert_eq!(lazy.len(), 2);
+            assert_eq!(lazy[1].1, (21, 30, 6));
+            for ((lazy, lazy_span), (eager, eager_span)) in lazy.iter().zip(&eager) {
+                assert!(lazy.bind(py).eq(eager).unwrap());
+                assert_eq!(lazy_span, eager_span);
+            }
+        });
+    }
+}
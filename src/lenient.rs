@@ -0,0 +1,334 @@
+//! Error-recovering parse for editors and language servers
+//!
+//! libyaml stops at the first error. `parse_lenient` recovers from
+//! localized ones instead: each document (split at `---` lines, like
+//! `load_all(collect_errors=True)`) is parsed, and on an error the line it
+//! points at is blanked out and the document parsed again. Lines are
+//! replaced by spaces, so later errors keep their original positions.
+//!
+//! An unclosed bracket or quote is reported where libyaml gave up looking
+//! for its end, so when the message names what was being parsed ("while
+//! parsing a flow sequence at line 2 column 4") that line goes instead.
+//! When the line to blank already is, the closest non-blank line above it
+//! goes. That is only a new diagnostic if the error isn't on a line blanked
+//! for an earlier one: an error at the end of the input is, a cascade from
+//! a line already left out is the same problem.
+//!
+//! A tag `safe_load` would reject (`!!python/object`, or a custom tag not in
+//! `allowed_tags=`) is recoverable too: the lines of the node it's on are
+//! blanked and the error recorded at the tag.
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::compose::{self, Node, NodeKind};
+use crate::error::{exceptions, YAMLError};
+use crate::options::LoadOptions;
+use crate::parser::{self, Parsed};
+use crate::safe;
+
+/// Documents that could be built, and every error found on the way
+pub struct Recovered {
+    pub documents: Vec<Parsed>,
+    /// `(document index, error)` in the order they were found
+    pub errors: Vec<(usize, YAMLError)>,
+}
+
+/// Replace line `line` (0-based) of `text` with spaces
+fn blank_line(text: &mut String, line: usize) {
+    let start = text
+        .split_inclusive('\n')
+        .take(line)
+        .map(str::len)
+        .sum::<usize>();
+    let end = text[start..].find('\n').map_or(text.len(), |n| start + n);
+    let spaces = " ".repeat(text[start..end].chars().count());
+    text.replace_range(start..end, &spaces);
+}
+
+/// The line of the construct being parsed when `err` happened (1-based)
+fn context_line(err: &YAMLError) -> Option<usize> {
+    let YAMLError::ParseError { message, .. } = err else {
+        return None;
+    };
+    let context = &message[message.find(", while ")?..];
+    let line = &context[context.find(" at line ")? + " at line ".len()..];
+    let digits = line
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(line.len());
+    line[..digits].parse().ok()
+}
+
+/// The line to blank for an error reported on `line` (0-based): that line,
+/// or the closest non-blank one above it
+fn culprit(text: &str, line: usize) -> Option<usize> {
+    let lines: Vec<&str> = text.split('\n').collect();
+    let line = line.min(lines.len().saturating_sub(1));
+    (0..=line)
+        .rev()
+        .find(|&index| !lines[index].trim().is_empty())
+}
+
+/// The first node under `node` whose tag construction would reject
+fn disallowed_tag<'a>(node: &'a Node, options: &LoadOptions) -> Option<&'a Node> {
+    if let Some(tag) = &node.tag {
+        let allowed = options.allowed_tags.iter().any(|allowed| allowed == tag);
+        if safe::is_unsafe_tag(tag) || !(allowed || tag.starts_with("tag:yaml.org,2002:")) {
+            return Some(node);
+        }
+    }
+    match &node.kind {
+        NodeKind::Sequence { items, .. } => {
+            items.iter().find_map(|item| disallowed_tag(item, options))
+        }
+        NodeKind::Mapping { pairs, .. } => pairs.iter().find_map(|(key, value)| {
+            disallowed_tag(key, options).or_else(|| disallowed_tag(value, options))
+        }),
+        NodeKind::Scalar { .. } | NodeKind::Alias { .. } => None,
+    }
+}
+
+/// An error for the first disallowed tag in `text`, and the lines (0-based)
+/// of the node it's on
+///
+/// Text that doesn't compose is left to the parser to report.
+fn tag_error(
+    text: &str,
+    options: &LoadOptions,
+) -> Option<(YAMLError, std::ops::RangeInclusive<usize>)> {
+    let documents = compose::compose_all(text).ok()?;
+    let node = documents
+        .iter()
+        .find_map(|document| disallowed_tag(&document.root, options))?;
+    // A block collection ends at the start of the line after it
+    let last = if node.end.column == 0 && node.end.line > node.start.line {
+        node.end.line - 1
+    } else {
+        node.end.line
+    };
+    let err = YAMLError::parse(
+        node.start.line + 1,
+        node.start.column + 1,
+        format!("Unsafe YAML tag detected: {}", node.tag.as_deref()?),
+    );
+    Some((err, node.start.line..=last))
+}
+
+/// Parse `chunk`, blanking offending lines until it parses
+fn recover_chunk(
+    chunk: &str,
+    options: &LoadOptions,
+    document: usize,
+    errors: &mut Vec<(usize, YAMLError)>,
+) -> Vec<Parsed> {
+    let mut text = chunk.to_string();
+    let parse = |text: &str| {
+        safe::quick_safety_check(text)?;
+        parser::parse_documents(text, options, true)
+    };
+    let reported = errors.len();
+    // Lines left out so far, each for an error already recorded
+    let mut blanked = Vec::new();
+    // Every retry blanks a non-blank line, so this always terminates
+    loop {
+        if let Some((err, lines)) = tag_error(&text, options) {
+            errors.push((document, err));
+            for line in lines {
+                blank_line(&mut text, line);
+                blanked.push(line);
+            }
+            continue;
+        }
+        let err = match parse(&text) {
+            Ok(documents) => return documents,
+            Err(err) => err,
+        };
+        let line = context_line(&err)
+            .or_else(|| err.location().map(|(line, _)| line))
+            .map(|line| line.saturating_sub(1));
+        let repeated = line.is_some_and(|line| blanked.contains(&line));
+        match line.and_then(|line| culprit(&text, line)) {
+            Some(culprit) => {
+                if !repeated {
+                    errors.push((document, err));
+                }
+                blank_line(&mut text, culprit);
+                blanked.push(culprit);
+            }
+            None => {
+                // No position to go by, or nothing left to blank
+                if !repeated && (line.is_some() || errors.len() == reported) {
+                    errors.push((document, err));
+                }
+                return Vec::new();
+            }
+        }
+    }
+}
+
+/// Parse every document of `yaml_str`, recovering from syntax errors
+pub fn recover(yaml_str: &str, options: &LoadOptions) -> Recovered {
+    let mut documents = Vec::new();
+    let mut errors = Vec::new();
+    for chunk in parser::split_documents(yaml_str) {
+        let index = documents.len();
+        let parsed = recover_chunk(&chunk, options, index, &mut errors);
+        if parsed.is_empty() {
+            // Keep indices aligned with the source: a lost document is None
+            documents.push(Parsed::Composed(None));
+        }
+        documents.extend(parsed);
+    }
+    Recovered { documents, errors }
+}
+
+/// Parse YAML, recovering from localized syntax errors
+///
+/// # Arguments
+/// * `yaml_str` - YAML content (any number of documents)
+/// * `options` - Load options
+///
+/// # Returns
+/// `(documents, errors)`: every document, with offending lines left out
+/// (`None` for one that could not be recovered at all), and a `YAMLError`
+/// per problem with `document` set, and `line` and `column` where known
+///
+/// # Example
+/// ```python
+/// documents, errors = rustyyaml.parse_lenient("a: 1\nb: [\nc: 3\n")
+/// # documents == [{"a": 1, "c": 3}]; errors[0].line == 2
+/// ```
+#[pyfunction]
+#[pyo3(signature = (yaml_str, **options))]
+pub fn parse_lenient(
+    py: Python,
+    yaml_str: &str,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<(PyObject, PyObject)> {
+    let options = LoadOptions::from_kwargs(options)?;
+    options.check_single_document()?;
+    let recovered = py.allow_threads(|| recover(yaml_str, &options));
+
+    let mut errors = recovered
+        .errors
+        .into_iter()
+        .map(|(document, err)| parser::annotate_error(py, err.into(), Some(document), None))
+        .collect::<PyResult<Vec<_>>>()?;
+    // Construction can still reject what parsed (a tag on the wrong kind of
+    // node): that document is lost, not the whole result
+    let mut documents = Vec::new();
+    for (index, document) in recovered.documents.iter().enumerate() {
+        match parser::to_python(py, document, &options) {
+            Ok(obj) => documents.push(obj),
+            Err(err) if err.is_instance_of::<exceptions::YAMLError>(py) => {
+                errors.push(parser::annotate_error(py, err, Some(index), None)?);
+                documents.push(py.None());
+            }
+            Err(err) => return Err(err),
+        }
+    }
+    Ok((documents.into_py(py), errors.into_py(py)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_yaml::Value;
+
+    fn values(recovered: &Recovered) -> Vec<Option<Value>> {
+        recovered
+            .documents
+            .iter()
+            .map(|document| match document {
+                Parsed::Value(value) => Some(value.clone()),
+                Parsed::Composed(_) => None,
+            })
+            .collect()
+    }
+
+    fn yaml(text: &str) -> Option<Value> {
+        Some(serde_yaml::from_str(text).unwrap())
+    }
+
+    #[test]
+    fn test_blank_line() {
+        let mut text = "a: 1\nb: é\nc: 3".to_string();
+        blank_line(&mut text, 1);
+        assert_eq!(text, "a: 1\n    \nc: 3");
+        blank_line(&mut text, 2);
+        assert_eq!(text, "a: 1\n    \n    ");
+    }
+
+    #[test]
+    fn test_recovers_localized_errors() {
+        let recovered = recover(
+            "a: 1\nb: [1, 2\nc: 3\nd: 'unterminated\ne: 5\n",
+            &LoadOptions::default(),
+        );
+        assert_eq!(values(&recovered), [yaml("a: 1\nc: 3\ne: 5")]);
+        assert!(!recovered.errors.is_empty());
+        let lines: Vec<usize> = recovered
+            .errors
+            .iter()
+            .map(|(_, err)| context_line(err).unwrap())
+            .collect();
+        assert_eq!(lines, [2, 4]);
+    }
+
+    #[test]
+    fn test_documents_recover_independently() {
+        let recovered = recover("a: 1\n---\nb: : :\n---\nc: 3\n", &LoadOptions::default());
+        let documents = values(&recovered);
+        assert_eq!(documents.len(), 3);
+        assert_eq!(documents[0], yaml("a: 1"));
+        assert_eq!(documents[2], yaml("c: 3"));
+        assert!(recovered.errors.iter().all(|(document, _)| *document == 1));
+    }
+
+    #[test]
+    fn test_recovers_disallowed_tags() {
+        let text =
+            "a: 1\nb: !custom x\nc: !!python/object:os.system y\nd: !Ref\n  e: 5\nf: !!str 6\n";
+        let recovered = recover(text, &LoadOptions::default());
+        assert_eq!(values(&recovered), [yaml("a: 1\nf: '6'")]);
+        let found: Vec<_> = recovered
+            .errors
+            .iter()
+            .map(|(_, err)| (err.location().unwrap(), err.to_string()))
+            .collect();
+        assert_eq!(found.len(), 3);
+        assert_eq!(found[0].0, (2, 4));
+        assert!(found[0].1.contains("!custom"));
+        assert_eq!(found[1].0, (3, 4));
+        assert_eq!(found[2].0, (4, 4));
+
+        let options = LoadOptions {
+            allowed_tags: vec!["!custom".to_string()],
+            ..LoadOptions::default()
+        };
+        let recovered = recover(text, &options);
+        assert_eq!(recovered.errors.len(), 2);
+    }
+
+    #[test]
+    fn test_errors_at_end_of_input() {
+        // libyaml reports these on the empty line after the last newline
+        for (text, expected) in [
+            ("a: 1\nb: [\n", yaml("a: 1")),
+            ("a: 1\nb: [\n\n\n", yaml("a: 1")),
+            ("a: 1\nb: {c: 2\n", yaml("a: 1")),
+            ("[[[\n", yaml("null")),
+        ] {
+            let recovered = recover(text, &LoadOptions::default());
+            assert_eq!(values(&recovered), [expected], "{:?}", text);
+            assert_eq!(recovered.errors.len(), 1, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn test_valid_input_has_no_errors() {
+        let recovered = recover("a: 1\n", &LoadOptions::default());
+        assert!(recovered.errors.is_empty());
+        assert_eq!(values(&recovered), [yaml("a: 1")]);
+    }
+}
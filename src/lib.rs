@@ -8,31 +8,99 @@
 //! - Drop-in replacement for PyYAML
 //! - Parallel batch loading for multiple files
 
+mod anchors;
+mod archive;
 mod batch;
+mod bundle;
+mod codegen;
+mod comments;
+mod compose;
+mod compression;
+mod construct;
+mod detect;
+mod diff;
+mod dumper;
+mod encoding;
 mod error;
+mod event_stream;
+mod events;
+mod export;
+mod filter;
+mod git;
+mod ini;
+mod integrity;
+mod interrupt;
+mod iter;
+mod lenient;
+mod limits;
+mod loader;
+mod logging;
+mod markers;
+mod merge;
+mod metrics;
+mod namespace;
+mod nodes;
+mod options;
 mod parser;
+mod profile;
+mod quantity;
+mod redact;
+mod remote;
+mod resolve;
+mod roundtrip;
 mod safe;
+mod schema;
+mod search;
+mod share;
+mod shm;
+mod sops;
+mod sourcemap;
+mod stream;
+mod tabs;
+mod tags;
+mod transform;
 mod types;
+mod validate;
+mod variables;
+mod walk;
+mod xml;
+
+use std::path::PathBuf;
 
 use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::options::LoadOptions;
 
 /// Parse YAML string safely (no code execution)
 ///
 /// # Arguments
 /// * `yaml_str` - YAML content as string
+/// * `options` - Load options (see `options::LoadOptions`)
 ///
 /// # Returns
 /// Python object (dict, list, str, int, float, bool, None)
 ///
 /// # Example
 /// ```python
-/// import rustyaml
-/// data = rustyaml.safe_load("key: value")
+/// import rustyyaml
+/// data = rustyyaml.safe_load("key: value")
 /// print(data)  # {'key': 'value'}
+///
+/// # Same types PyYAML's SafeLoader would produce
+/// data = rustyyaml.safe_load("enabled: yes", pyyaml_compat=True)
+/// print(data)  # {'enabled': True}
 /// ```
 #[pyfunction]
-fn safe_load(py: Python, yaml_str: &str) -> PyResult<PyObject> {
-    parser::parse_safe(py, yaml_str)
+#[pyo3(signature = (yaml_str, **options))]
+fn safe_load(
+    py: Python,
+    yaml_str: &str,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<PyObject> {
+    let options = LoadOptions::from_kwargs(options)?;
+    options.check_single_document()?;
+    parser::parse_safe(py, yaml_str, &options)
 }
 
 /// Parse YAML string without safety checks (DANGEROUS!)
@@ -49,8 +117,88 @@ fn safe_load(py: Python, yaml_str: &str) -> PyResult<PyObject> {
 /// # Warning
 /// This can execute arbitrary code embedded in YAML
 #[pyfunction]
-fn unsafe_load(py: Python, yaml_str: &str) -> PyResult<PyObject> {
-    parser::parse_unsafe(py, yaml_str)
+#[pyo3(signature = (yaml_str, **options))]
+fn unsafe_load(
+    py: Python,
+    yaml_str: &str,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<PyObject> {
+    let options = LoadOptions::from_kwargs(options)?;
+    options.check_single_document()?;
+    parser::parse_unsafe(py, yaml_str, &options)
+}
+
+/// Read a file and parse its single document
+///
+/// The file is read and decoded in Rust with the GIL released, so other
+/// threads keep running during the IO.
+///
+/// # Arguments
+/// * `path` - Path to the YAML file (UTF-8, UTF-16 or UTF-32, or as
+///   `encoding=` says)
+/// * `safe` - Reject unsafe tags, as `safe_load` does; `false` is
+///   `unsafe_load`
+/// * `options` - Load options (see `options::LoadOptions`)
+///
+/// # Errors
+/// * `YAMLError` if the file can't be read, decoded or parsed, with `path`
+///   set to the file
+///
+/// # Example
+/// ```python
+/// config = rustyyaml.load_file("config.yaml", timestamps=True)
+/// ```
+#[pyfunction]
+#[pyo3(signature = (path, safe=true, **options))]
+fn load_file(
+    py: Python,
+    path: PathBuf,
+    safe: bool,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<PyObject> {
+    let options = LoadOptions::from_kwargs(options)?;
+    options.check_single_document()?;
+    let loaded = py
+        .allow_threads(|| batch::read_decoded(&path, &options))
+        .map_err(PyErr::from)
+        .and_then(|decoded| {
+            if safe {
+                parser::parse_safe(py, &decoded.text, &options)
+            } else {
+                parser::parse_unsafe(py, &decoded.text, &options)
+            }
+        });
+    loaded.inspect_err(|err| {
+        // Best effort: the error is raised either way
+        let _ = err.value_bound(py).setattr("path", &path);
+    })
+}
+
+/// Parse YAML string safely and return a source map alongside the data
+///
+/// # Arguments
+/// * `yaml_str` - YAML content as string
+/// * `options` - Load options (see `options::LoadOptions`)
+///
+/// # Returns
+/// `(data, sourcemap)` where `sourcemap` maps JSON pointers (`"/a/0"`) to
+/// `(line, column, (start, end))`: 1-based position and byte span
+///
+/// # Example
+/// ```python
+/// data, sourcemap = rustyyaml.safe_load_with_sourcemap("a:\n  - x")
+/// print(sourcemap["/a/0"])  # (2, 5, (7, 8))
+/// ```
+#[pyfunction]
+#[pyo3(signature = (yaml_str, **options))]
+fn safe_load_with_sourcemap(
+    py: Python,
+    yaml_str: &str,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<(PyObject, PyObject)> {
+    let options = LoadOptions::from_kwargs(options)?;
+    options.check_single_document()?;
+    parser::parse_safe_with_sourcemap(py, yaml_str, &options)
 }
 
 /// Parse multiple YAML documents from a single string
@@ -59,11 +207,11 @@ fn unsafe_load(py: Python, yaml_str: &str) -> PyResult<PyObject> {
 /// * `yaml_str` - YAML content with documents separated by '---'
 ///
 /// # Returns
-/// List of Python objects
+/// List of Python objects, or `(documents, errors)` with `collect_errors=True`
 ///
 /// # Example
 /// ```python
-/// import rustyaml
+/// import rustyyaml
 /// yaml_str = '''
 /// doc: 1
 /// ---
@@ -71,18 +219,135 @@ fn unsafe_load(py: Python, yaml_str: &str) -> PyResult<PyObject> {
 /// ---
 /// doc: 3
 /// '''
-/// docs = rustyaml.load_all(yaml_str)
+/// docs = rustyyaml.load_all(yaml_str)
 /// print(len(docs))  # 3
+///
+/// # Keep going past broken documents
+/// docs, errors = rustyyaml.load_all(yaml_str, collect_errors=True)
+///
+/// # Map documents back to the source
+/// for doc, (start, end, line) in rustyyaml.load_all(yaml_str, document_spans=True):
+///     region = yaml_str.encode()[start:end]
 /// ```
 #[pyfunction]
-fn load_all(py: Python, yaml_str: &str) -> PyResult<Vec<PyObject>> {
-    parser::parse_all(py, yaml_str)
+#[pyo3(signature = (yaml_str, **options))]
+fn load_all(py: Python, yaml_str: &str, options: Option<&Bound<'_, PyDict>>) -> PyResult<PyObject> {
+    let options = LoadOptions::from_kwargs(options)?;
+    parse_all_documents(py, yaml_str, &options, true)
 }
 
 /// Parse multiple YAML documents without safety checks
 #[pyfunction]
-fn load_all_unsafe(py: Python, yaml_str: &str) -> PyResult<Vec<PyObject>> {
-    parser::parse_all_unsafe(py, yaml_str)
+#[pyo3(signature = (yaml_str, **options))]
+fn load_all_unsafe(
+    py: Python,
+    yaml_str: &str,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<PyObject> {
+    let options = LoadOptions::from_kwargs(options)?;
+    parse_all_documents(py, yaml_str, &options, false)
+}
+
+/// Read a file and parse every document in it
+///
+/// Like `load_file`, the file is read and decoded in Rust with the GIL
+/// released, and never becomes a Python string.
+///
+/// # Arguments
+/// * `path` - Path to the YAML file
+/// * `safe` - Reject unsafe tags, as `load_all` does; `false` is
+///   `load_all_unsafe`
+/// * `options` - Load options; `collect_errors` and `document_spans` shape
+///   the result as for `load_all`
+///
+/// # Errors
+/// * `YAMLError` if the file can't be read, decoded or parsed, with `path`
+///   set to the file
+#[pyfunction]
+#[pyo3(signature = (path, safe=true, **options))]
+fn load_all_file(
+    py: Python,
+    path: PathBuf,
+    safe: bool,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<PyObject> {
+    let options = LoadOptions::from_kwargs(options)?;
+    let loaded = py
+        .allow_threads(|| batch::read_decoded(&path, &options))
+        .map_err(PyErr::from)
+        .and_then(|decoded| parse_all_documents(py, &decoded.text, &options, safe));
+    loaded.inspect_err(|err| {
+        // Best effort: the error is raised either way
+        let _ = err.value_bound(py).setattr("path", &path);
+    })
+}
+
+/// Read standard input and parse its single document
+///
+/// Stdin is read to its end and decoded in Rust with the GIL released,
+/// so pipelines (`kubectl get -o yaml | python script.py`) need no
+/// buffering in Python.
+///
+/// # Arguments
+/// * `safe` - Reject unsafe tags, as `safe_load` does
+/// * `options` - Load options (see `options::LoadOptions`)
+///
+/// # Errors
+/// * `YAMLError` if stdin can't be read, decoded or parsed
+#[pyfunction]
+#[pyo3(signature = (safe=true, **options))]
+fn load_stdin(py: Python, safe: bool, options: Option<&Bound<'_, PyDict>>) -> PyResult<PyObject> {
+    let options = LoadOptions::from_kwargs(options)?;
+    options.check_single_document()?;
+    let decoded = py.allow_threads(|| batch::read_stdin(options.encoding))?;
+    if safe {
+        parser::parse_safe(py, &decoded.text, &options)
+    } else {
+        parser::parse_unsafe(py, &decoded.text, &options)
+    }
+}
+
+/// Read standard input and parse every document in it
+///
+/// # Arguments
+/// * `safe` - Reject unsafe tags, as `load_all` does
+/// * `options` - Load options; `collect_errors` and `document_spans` shape
+///   the result as for `load_all`
+///
+/// # Errors
+/// * `YAMLError` if stdin can't be read, decoded or parsed
+#[pyfunction]
+#[pyo3(signature = (safe=true, **options))]
+fn load_all_stdin(
+    py: Python,
+    safe: bool,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<PyObject> {
+    let options = LoadOptions::from_kwargs(options)?;
+    let decoded = py.allow_threads(|| batch::read_stdin(options.encoding))?;
+    parse_all_documents(py, &decoded.text, &options, safe)
+}
+
+/// Every document of a stream, shaped as `collect_errors` and
+/// `document_spans` ask
+fn parse_all_documents(
+    py: Python,
+    yaml_str: &str,
+    options: &LoadOptions,
+    safe: bool,
+) -> PyResult<PyObject> {
+    if options.document_spans {
+        return Ok(parser::parse_all_with_spans(py, yaml_str, options, safe)?.into_py(py));
+    }
+    if options.collect_errors {
+        return Ok(parser::parse_all_collect(py, yaml_str, options, safe)?.into_py(py));
+    }
+    let documents = if safe {
+        parser::parse_all(py, yaml_str, options)?
+    } else {
+        parser::parse_all_unsafe(py, yaml_str, options)?
+    };
+    Ok(documents.into_py(py))
 }
 
 /// Get the version string
@@ -99,15 +364,109 @@ fn rustyyaml(m: &Bound<'_, PyModule>) -> PyResult<()> {
     // Core functions
     m.add_function(wrap_pyfunction!(safe_load, m)?)?;
     m.add_function(wrap_pyfunction!(unsafe_load, m)?)?;
+    m.add_function(wrap_pyfunction!(safe_load_with_sourcemap, m)?)?;
     m.add_function(wrap_pyfunction!(load_all, m)?)?;
     m.add_function(wrap_pyfunction!(load_all_unsafe, m)?)?;
+    m.add_function(wrap_pyfunction!(load_file, m)?)?;
+    m.add_function(wrap_pyfunction!(load_all_file, m)?)?;
+    m.add_function(wrap_pyfunction!(iter::load_all_iter, m)?)?;
+    m.add_function(wrap_pyfunction!(load_stdin, m)?)?;
+    m.add_function(wrap_pyfunction!(load_all_stdin, m)?)?;
+    m.add_function(wrap_pyfunction!(lenient::parse_lenient, m)?)?;
+    m.add_function(wrap_pyfunction!(event_stream::parse, m)?)?;
+    m.add_function(wrap_pyfunction!(nodes::py_compose, m)?)?;
+    m.add_function(wrap_pyfunction!(nodes::py_compose_all, m)?)?;
+    m.add_function(wrap_pyfunction!(dumper::safe_dump, m)?)?;
+    m.add_function(wrap_pyfunction!(dumper::safe_dump_all, m)?)?;
+    m.add_function(wrap_pyfunction!(roundtrip::load_rt, m)?)?;
+    m.add_function(wrap_pyfunction!(roundtrip::dump_rt, m)?)?;
+    m.add_function(wrap_pyfunction!(roundtrip::update_file, m)?)?;
+    m.add_function(wrap_pyfunction!(dumper::dump_file, m)?)?;
+    m.add_function(wrap_pyfunction!(dumper::dump_all_file, m)?)?;
+    m.add_function(wrap_pyfunction!(dumper::add_representer, m)?)?;
+    m.add_function(wrap_pyfunction!(integrity::load_file_verified, m)?)?;
+    m.add_function(wrap_pyfunction!(stream::load_file_streaming, m)?)?;
     m.add_function(wrap_pyfunction!(version, m)?)?;
+    m.add_class::<namespace::Namespace>()?;
+    m.add_class::<loader::Loader>()?;
+    m.add_class::<dumper::Dumper>()?;
+    m.add_class::<iter::DocumentIterator>()?;
+    m.add_class::<event_stream::EventIterator>()?;
 
     // Batch operations
     m.add_function(wrap_pyfunction!(batch::safe_load_many, m)?)?;
     m.add_function(wrap_pyfunction!(batch::unsafe_load_many, m)?)?;
     m.add_function(wrap_pyfunction!(batch::load_directory, m)?)?;
     m.add_function(wrap_pyfunction!(batch::load_directory_unsafe, m)?)?;
+    m.add_function(wrap_pyfunction!(remote::load_object_store, m)?)?;
+    m.add_function(wrap_pyfunction!(git::load_git, m)?)?;
+    m.add_function(wrap_pyfunction!(integrity::load_files_verified, m)?)?;
+    m.add_function(wrap_pyfunction!(archive::load_archive, m)?)?;
+    m.add_class::<interrupt::CancellationToken>()?;
+
+    // Sharing between processes
+    m.add_function(wrap_pyfunction!(shm::dump_shm, m)?)?;
+    m.add_function(wrap_pyfunction!(shm::load_shm, m)?)?;
+    m.add_class::<shm::SharedMapping>()?;
+    m.add_class::<shm::SharedSequence>()?;
+
+    // Search
+    m.add_function(wrap_pyfunction!(search::grep, m)?)?;
+    m.add_function(wrap_pyfunction!(search::index_keys, m)?)?;
+    m.add_function(wrap_pyfunction!(walk::walk, m)?)?;
+    m.add_function(wrap_pyfunction!(transform::map_scalars, m)?)?;
+
+    // File tools
+    m.add_function(wrap_pyfunction!(bundle::explode, m)?)?;
+    m.add_function(wrap_pyfunction!(bundle::bundle, m)?)?;
+    m.add_function(wrap_pyfunction!(diff::drift, m)?)?;
+    m.add_function(wrap_pyfunction!(export::to_csv, m)?)?;
+    m.add_function(wrap_pyfunction!(export::to_dotenv, m)?)?;
+    m.add_function(wrap_pyfunction!(ini::yaml_to_ini, m)?)?;
+    m.add_function(wrap_pyfunction!(ini::ini_to_yaml, m)?)?;
+    m.add_function(wrap_pyfunction!(xml::yaml_to_xml, m)?)?;
+    m.add_function(wrap_pyfunction!(xml::xml_to_yaml, m)?)?;
+
+    // Validation
+    m.add_function(wrap_pyfunction!(validate::validate, m)?)?;
+    m.add_function(wrap_pyfunction!(schema::register_schema, m)?)?;
+    m.add_class::<validate::Report>()?;
+    m.add_class::<validate::Finding>()?;
+
+    // Helpers
+    m.add_function(wrap_pyfunction!(quantity::py_parse_quantity, m)?)?;
+    m.add_function(wrap_pyfunction!(variables::py_list_variables, m)?)?;
+    m.add_function(wrap_pyfunction!(redact::redact, m)?)?;
+    m.add_function(wrap_pyfunction!(logging::load_logging_config, m)?)?;
+    m.add_function(wrap_pyfunction!(codegen::generate_dataclasses, m)?)?;
+    m.add_function(wrap_pyfunction!(comments::extract_comments, m)?)?;
+    m.add_function(wrap_pyfunction!(detect::detect_type, m)?)?;
+    m.add_function(wrap_pyfunction!(anchors::inspect_anchors, m)?)?;
+    m.add_function(wrap_pyfunction!(tags::audit_tags, m)?)?;
+    m.add_function(wrap_pyfunction!(metrics::metrics, m)?)?;
+    m.add_function(wrap_pyfunction!(encoding::decode, m)?)?;
+
+    // Exceptions
+    let yaml_error = m.py().get_type_bound::<error::exceptions::YAMLError>();
+    // Class-level defaults, so errors raised from Python code have them too
+    for name in ["line", "column", "document", "path"] {
+        yaml_error.setattr(name, m.py().None())?;
+    }
+    m.add("YAMLError", yaml_error)?;
+    m.add(
+        "TabIndentationError",
+        m.py()
+            .get_type_bound::<error::exceptions::TabIndentationError>(),
+    )?;
+    m.add(
+        "IntegrityError",
+        m.py().get_type_bound::<error::exceptions::IntegrityError>(),
+    )?;
+    m.add(
+        "ResourceLimitError",
+        m.py()
+            .get_type_bound::<error::exceptions::ResourceLimitError>(),
+    )?;
 
     // Add version constant
     m.add("__version__", env!("CARGO_PKG_VERSION"))?;
@@ -124,7 +483,7 @@ mod tests {
     fn test_safe_load_simple() {
         Python::with_gil(|py| {
             let yaml = "key: value";
-            let result = safe_load(py, yaml).unwrap();
+            let result = safe_load(py, yaml, None).unwrap();
             let dict = result.bind(py).downcast::<PyDict>().unwrap();
 
             let val: String = dict.get_item("key").unwrap().unwrap().extract().unwrap();
@@ -136,8 +495,8 @@ mod tests {
     fn test_load_all_multiple_docs() {
         Python::with_gil(|py| {
             let yaml = "doc: 1\n---\ndoc: 2\n---\ndoc: 3";
-            let results = load_all(py, yaml).unwrap();
-            assert_eq!(results.len(), 3);
+            let results = load_all(py, yaml, None).unwrap();
+            assert_eq!(results.bind(py).len().unwrap(), 3);
         });
     }
 
@@ -155,7 +514,7 @@ database:
   host: localhost
   port: 5432
 "#;
-            let result = safe_load(py, yaml).unwrap();
+            let result = safe_load(py, yaml, None).unwrap();
             let dict = result.bind(py).downcast::<PyDict>().unwrap();
 
             let db = dict.get_item("database").unwrap().unwrap();
@@ -183,7 +542,7 @@ database:
     fn test_safe_load_list() {
         Python::with_gil(|py| {
             let yaml = "- item1\n- item2\n- item3";
-            let result = safe_load(py, yaml).unwrap();
+            let result = safe_load(py, yaml, None).unwrap();
 
             let list: Vec<String> = result.extract(py).unwrap();
             assert_eq!(list, vec!["item1", "item2", "item3"]);
@@ -194,7 +553,7 @@ database:
     fn test_safe_load_empty() {
         Python::with_gil(|py| {
             let yaml = "";
-            let result = safe_load(py, yaml).unwrap();
+            let result = safe_load(py, yaml, None).unwrap();
             assert!(result.is_none(py));
         });
     }
@@ -203,20 +562,55 @@ database:
     fn test_safe_load_rejects_unsafe_tags() {
         Python::with_gil(|py| {
             let yaml = "!!python/object/apply:os.system ['echo bad']";
-            let result = safe_load(py, yaml);
+            let result = safe_load(py, yaml, None);
             assert!(result.is_err());
         });
     }
 
+    #[test]
+    fn test_safe_load_with_sourcemap() {
+        Python::with_gil(|py| {
+            let (data, sourcemap) = safe_load_with_sourcemap(py, "a:\n  - x", None).unwrap();
+            assert!(data.bind(py).downcast::<PyDict>().is_ok());
+
+            let sourcemap = sourcemap.bind(py).downcast::<PyDict>().unwrap().clone();
+            let location: (usize, usize, (usize, usize)) = sourcemap
+                .get_item("/a/0")
+                .unwrap()
+                .unwrap()
+                .extract()
+                .unwrap();
+            assert_eq!(location, (2, 5, (7, 8)));
+        });
+    }
+
     #[test]
     fn test_unsafe_load_allows_regular_yaml() {
         Python::with_gil(|py| {
             let yaml = "key: value";
-            let result = unsafe_load(py, yaml).unwrap();
+            let result = unsafe_load(py, yaml, None).unwrap();
             let dict = result.bind(py).downcast::<PyDict>().unwrap();
 
             let val: String = dict.get_item("key").unwrap().unwrap().extract().unwrap();
             assert_eq!(val, "value");
         });
     }
+
+    #[test]
+    fn test_safe_load_pyyaml_compat() {
+        Python::with_gil(|py| {
+            let options = PyDict::new_bound(py);
+            options.set_item("pyyaml_compat", true).unwrap();
+            let result = safe_load(py, "enabled: on", Some(&options)).unwrap();
+            let dict = result.bind(py).downcast::<PyDict>().unwrap();
+
+            let enabled: bool = dict
+                .get_item("enabled")
+                .unwrap()
+                .unwrap()
+                .extract()
+                .unwrap();
+            assert!(enabled);
+        });
+    }
 }
@@ -0,0 +1,362 @@
+//! Resource limits (`max_size_bytes=`, `max_nodes=`, `max_scalar_bytes=`,
+//! `max_mapping_entries=`)
+//!
+//! `max_size_bytes` caps the text before it's parsed. Files are refused
+//! from their size when they can be, and otherwise read (or decompressed,
+//! or parsed as they stream in) through a `SizeLimited` reader that stops
+//! one byte past the limit, so a huge input is never read whole. An upload of modest
+//! total size can still put all of it in one place: a single multi-megabyte
+//! scalar, a flat mapping with a million keys, or a few aliases that expand
+//! to millions of nodes. The other limits are checked on the parsed
+//! document, before anything is converted to Python objects. Keys count as
+//! scalars (and nodes) too, and an alias counts as the nodes it stands for.
+//!
+//! A `Value` has no positions, so when it breaks a limit the document is
+//! composed again to say where.
+
+use std::collections::HashMap;
+use std::io::{self, Read};
+
+use serde_yaml::Value;
+
+use crate::compose::{self, Node, NodeKind};
+use crate::error::YAMLError;
+use crate::events::Mark;
+use crate::options::LoadOptions;
+
+/// The limits set by the options (`None` for no limit)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Limits {
+    pub max_size_bytes: Option<usize>,
+    pub max_nodes: Option<u64>,
+    pub max_scalar_bytes: Option<usize>,
+    pub max_mapping_entries: Option<usize>,
+}
+
+/// Nodes of a document counted so far, for `max_nodes`
+#[derive(Debug, Default)]
+pub struct Count {
+    nodes: u64,
+    /// Expanded size of each anchored node, by anchor
+    anchors: HashMap<String, u64>,
+}
+
+impl Limits {
+    /// No limit on the parsed document (`max_size_bytes` is checked apart)
+    fn is_unlimited(&self) -> bool {
+        self.max_nodes.is_none()
+            && self.max_scalar_bytes.is_none()
+            && self.max_mapping_entries.is_none()
+    }
+
+    /// Check the length of the text to parse
+    pub fn check_size(&self, len: usize) -> Result<(), YAMLError> {
+        match self.max_size_bytes {
+            Some(max) if len > max => Err(YAMLError::Limit {
+                message: format!("input of {} bytes (max_size_bytes={})", len, max),
+                mark: None,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Read `reader` to its end through a `SizeLimited`
+    ///
+    /// # Errors
+    /// * `Limit` once it yields more than `max_size_bytes`
+    /// * `error(err)` for any other read error
+    pub fn read_to_end(
+        &self,
+        reader: impl Read,
+        error: impl FnOnce(io::Error) -> YAMLError,
+    ) -> Result<Vec<u8>, YAMLError> {
+        let mut bytes = Vec::new();
+        match SizeLimited::new(reader, self).read_to_end(&mut bytes) {
+            Ok(_) => Ok(bytes),
+            Err(err) => Err(size_error(&err).unwrap_or_else(|| error(err))),
+        }
+    }
+
+    /// Add `nodes` to `count`, failing at `mark` once there are too many
+    pub fn add_nodes(
+        &self,
+        count: &mut Count,
+        nodes: u64,
+        mark: Option<Mark>,
+    ) -> Result<(), YAMLError> {
+        count.nodes = count.nodes.saturating_add(nodes);
+        match self.max_nodes {
+            Some(max) if count.nodes > max => Err(YAMLError::Limit {
+                message: format!("document of more than {} nodes (max_nodes={})", max, max),
+                mark,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_scalar(&self, len: usize, mark: Option<Mark>) -> Result<(), YAMLError> {
+        match self.max_scalar_bytes {
+            Some(max) if len > max => Err(YAMLError::Limit {
+                message: format!("scalar of {} bytes (max_scalar_bytes={})", len, max),
+                mark,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn check_mapping(&self, len: usize, mark: Option<Mark>) -> Result<(), YAMLError> {
+        match self.max_mapping_entries {
+            Some(max) if len > max => Err(YAMLError::Limit {
+                message: format!("mapping with {} entries (max_mapping_entries={})", len, max),
+                mark,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check a composed node and everything below it
+    pub fn check_node(&self, node: &Node) -> Result<(), YAMLError> {
+        self.check_entry(node, &mut Count::default())
+    }
+
+    /// Check one part of a document checked a part at a time
+    /// (`streaming=True`), adding its nodes to `count`
+    pub fn check_entry(&self, node: &Node, count: &mut Count) -> Result<(), YAMLError> {
+        self.node(node, count).map(|_| ())
+    }
+
+    /// Check `node`, returning how many nodes it expands to
+    fn node(&self, node: &Node, count: &mut Count) -> Result<u64, YAMLError> {
+        let size = match &node.kind {
+            NodeKind::Alias { anchor } => {
+                let size = count.anchors.get(anchor).copied().unwrap_or(1);
+                self.add_nodes(count, size, Some(node.start))?;
+                return Ok(size);
+            }
+            NodeKind::Scalar { value, .. } => {
+                self.check_scalar(value.len(), Some(node.start))?;
+                self.add_nodes(count, 1, Some(node.start))?;
+                1
+            }
+            NodeKind::Sequence { items, .. } => {
+                self.add_nodes(count, 1, Some(node.start))?;
+                let mut size: u64 = 1;
+                for item in items {
+                    size = size.saturating_add(self.node(item, count)?);
+                }
+                size
+            }
+            NodeKind::Mapping { pairs, .. } => {
+                self.check_mapping(pairs.len(), Some(node.start))?;
+                self.add_nodes(count, 1, Some(node.start))?;
+                let mut size: u64 = 1;
+                for (key, value) in pairs {
+                    size = size.saturating_add(self.node(key, count)?);
+                    size = size.saturating_add(self.node(value, count)?);
+                }
+                size
+            }
+        };
+        if let Some(anchor) = &node.anchor {
+            count.anchors.insert(anchor.clone(), size);
+        }
+        Ok(size)
+    }
+
+    /// Check a value and everything below it
+    pub fn check_value(&self, value: &Value) -> Result<(), YAMLError> {
+        self.value(value, &mut Count::default())
+    }
+
+    fn value(&self, value: &Value, count: &mut Count) -> Result<(), YAMLError> {
+        // A tag doesn't make another node
+        if let Value::Tagged(tagged) = value {
+            return self.value(&tagged.value, count);
+        }
+        self.add_nodes(count, 1, None)?;
+        match value {
+            Value::String(text) => self.check_scalar(text.len(), None),
+            Value::Sequence(items) => items.iter().try_for_each(|item| self.value(item, count)),
+            Value::Mapping(mapping) => {
+                self.check_mapping(mapping.len(), None)?;
+                mapping.iter().try_for_each(|(key, value)| {
+                    self.value(key, count)?;
+                    self.value(value, count)
+                })
+            }
+            // Numbers are at most a few dozen bytes once parsed
+            Value::Null | Value::Bool(_) | Value::Number(_) | Value::Tagged(_) => Ok(()),
+        }
+    }
+}
+
+/// A reader that fails once it has yielded more than `max_size_bytes`,
+/// for input that is decompressed or parsed as it's read. It never reads
+/// more than one byte past the limit from the reader it wraps; its error is
+/// an `io::Error` carrying the `Limit` error (see `size_error`).
+pub struct SizeLimited<R> {
+    reader: io::Take<R>,
+    max: Option<usize>,
+    read: usize,
+}
+
+impl<R: Read> SizeLimited<R> {
+    pub fn new(reader: R, limits: &Limits) -> Self {
+        let cap = limits
+            .max_size_bytes
+            .map_or(u64::MAX, |max| (max as u64).saturating_add(1));
+        SizeLimited {
+            reader: reader.take(cap),
+            max: limits.max_size_bytes,
+            read: 0,
+        }
+    }
+}
+
+impl<R: Read> Read for SizeLimited<R> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let read = self.reader.read(buffer)?;
+        self.read += read;
+        match self.max {
+            Some(max) if self.read > max => Err(io::Error::other(YAMLError::Limit {
+                message: format!("input of more than {} bytes (max_size_bytes={})", max, max),
+                mark: None,
+            })),
+            _ => Ok(read),
+        }
+    }
+}
+
+/// The `Limit` error behind a read error from a `SizeLimited` reader
+pub fn size_error(err: &io::Error) -> Option<YAMLError> {
+    err.get_ref()
+        .and_then(|inner| inner.downcast_ref::<YAMLError>())
+        .cloned()
+}
+
+/// Apply `max_size_bytes` to the text about to be parsed
+pub fn check_size(yaml_str: &str, options: &LoadOptions) -> Result<(), YAMLError> {
+    options.limits.check_size(yaml_str.len())
+}
+
+/// Apply the limits to document `index` of `yaml_str`
+pub fn check_value(
+    value: &Value,
+    options: &LoadOptions,
+    yaml_str: &str,
+    index: usize,
+) -> Result<(), YAMLError> {
+    let limits = options.limits;
+    if limits.is_unlimited() {
+        return Ok(());
+    }
+    limits.check_value(value).map_err(|err| {
+        // Documents keep their key order, so the composed one breaks the
+        // same limit first, and knows where
+        compose::compose_all(yaml_str)
+            .ok()
+            .and_then(|documents| documents.into_iter().nth(index))
+            .and_then(|document| limits.check_node(&document.root).err())
+            .unwrap_or(err)
+    })
+}
+
+/// Apply the limits to a composed document
+pub fn check_document(node: &Node, options: &LoadOptions) -> Result<(), YAMLError> {
+    if options.limits.is_unlimited() {
+        return Ok(());
+    }
+    options.limits.check_node(node)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMITS: Limits = Limits {
+        max_size_bytes: None,
+        max_nodes: None,
+        max_scalar_bytes: Some(5),
+        max_mapping_entries: Some(2),
+    };
+
+    fn check(yaml: &str) -> Result<(), YAMLError> {
+        let options = LoadOptions {
+            limits: LIMITS,
+            ..Default::default()
+        };
+        check_value(&serde_yaml::from_str(yaml).unwrap(), &options, yaml, 0)
+    }
+
+    #[test]
+    fn test_within_limits() {
+        assert!(check("a: short\nb: [1, 2, 3, 4]\n").is_ok());
+        assert!(check("12345678901234567890").is_ok());
+    }
+
+    #[test]
+    fn test_long_scalar_located() {
+        let err = check("a: ok\nb:\n  - too long\n").unwrap_err();
+        assert!(err.to_string().contains("max_scalar_bytes=5"));
+        assert_eq!(err.location(), Some((3, 5)));
+
+        let err = check("long key: 1\n").unwrap_err();
+        assert_eq!(err.location(), Some((1, 1)));
+    }
+
+    #[test]
+    fn test_large_mapping_located() {
+        let err = check("outer:\n  a: 1\n  b: 2\n  c: 3\n").unwrap_err();
+        assert!(err.to_string().contains("mapping with 3 entries"));
+        assert_eq!(err.location(), Some((2, 3)));
+    }
+
+    #[test]
+    fn test_node_count_located() {
+        let limits = Limits {
+            max_nodes: Some(6),
+            ..Default::default()
+        };
+        let options = LoadOptions {
+            limits,
+            ..Default::default()
+        };
+        let check =
+            |yaml: &str| check_value(&serde_yaml::from_str(yaml).unwrap(), &options, yaml, 0);
+        // The root, two keys and three values
+        assert!(check("a: 1\nb: [x]\n").is_ok());
+        let err = check("a: 1\nb: [x, y]\n").unwrap_err();
+        assert!(err.to_string().contains("max_nodes=6"), "{}", err);
+        assert_eq!(err.location(), Some((2, 8)));
+
+        // An alias counts as the nodes it stands for, tags add none
+        let err = check("a: &a [1, 2]\nb: *a\n").unwrap_err();
+        assert_eq!(err.location(), Some((2, 4)));
+        assert!(check("a: !t [1]\nb: !t x\n").is_ok());
+    }
+
+    #[test]
+    fn test_size() {
+        let limits = Limits {
+            max_size_bytes: Some(4),
+            ..Default::default()
+        };
+        assert!(limits.check_size(4).is_ok());
+        let err = limits.check_size(5).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("input of 5 bytes (max_size_bytes=4)"));
+
+        let read = |bytes: &[u8]| limits.read_to_end(bytes, YAMLError::from);
+        assert_eq!(read(b"a: 1").unwrap(), b"a: 1");
+        let err = read(b"a: 12").unwrap_err();
+        assert!(matches!(err, YAMLError::Limit { .. }));
+        assert!(err.to_string().contains("input of more than 4 bytes"));
+
+        // Only one byte past the limit is read
+        let mut reader = SizeLimited::new(&b"a: 12345"[..], &limits);
+        let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+        assert!(size_error(&err).is_some());
+        assert_eq!(reader.reader.into_inner(), b"345");
+    }
+}
@@ -0,0 +1,116 @@
+//! Reusable loader with preconfigured options
+//!
+//! Every call to `safe_load(text, **options)` parses its keyword arguments
+//! again, and options such as `schema=` or `redact=` compile something on
+//! the way. A `Loader` does that once, in its constructor, and keeps the
+//! result for every document it loads.
+
+use std::path::PathBuf;
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::batch;
+use crate::options::LoadOptions;
+use crate::parser;
+
+/// Loads documents with options validated once
+///
+/// ```python
+/// loader = rustyyaml.Loader(schema="deployment", timestamps=True)
+/// for text in texts:
+///     data = loader.load(text)
+/// ```
+#[pyclass(module = "rustyyaml", frozen, subclass)]
+pub struct Loader {
+    options: LoadOptions,
+    safe: bool,
+}
+
+#[pymethods]
+impl Loader {
+    /// Validate `options` (as for `safe_load`); `safe=False` skips safety
+    /// checks like `unsafe_load`
+    #[new]
+    #[pyo3(signature = (safe=true, **options))]
+    fn new(safe: bool, options: Option<&Bound<'_, PyDict>>) -> PyResult<Self> {
+        Ok(Loader {
+            options: LoadOptions::from_kwargs(options)?,
+            safe,
+        })
+    }
+
+    /// Parse a single document
+    fn load(&self, py: Python, yaml_str: &str) -> PyResult<PyObject> {
+        self.options.check_single_document()?;
+        if self.safe {
+            parser::parse_safe(py, yaml_str, &self.options)
+        } else {
+            parser::parse_unsafe(py, yaml_str, &self.options)
+        }
+    }
+
+    /// Parse every document (`(documents, errors)` with `collect_errors`,
+    /// `(document, span)` pairs with `document_spans`)
+    fn load_all(&self, py: Python, yaml_str: &str) -> PyResult<PyObject> {
+        if self.options.document_spans {
+            let documents = parser::parse_all_with_spans(py, yaml_str, &self.options, self.safe)?;
+            return Ok(documents.into_py(py));
+        }
+        if self.options.collect_errors {
+            let collected = parser::parse_all_collect(py, yaml_str, &self.options, self.safe)?;
+            return Ok(collected.into_py(py));
+        }
+        let documents = if self.safe {
+            parser::parse_all(py, yaml_str, &self.options)?
+        } else {
+            parser::parse_all_unsafe(py, yaml_str, &self.options)?
+        };
+        Ok(documents.into_py(py))
+    }
+
+    /// Read a file (UTF-8, UTF-16 or UTF-32, or as `encoding=` says) and
+    /// parse its single document; errors have `path` set
+    fn load_file(&self, py: Python, path: PathBuf) -> PyResult<PyObject> {
+        let decoded = py.allow_threads(|| batch::read_decoded(&path, &self.options))?;
+        let text = decoded.text;
+        self.load(py, &text).inspect_err(|err| {
+            // Best effort: the error is raised either way
+            let _ = err.value_bound(py).setattr("path", &path);
+        })
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Loader(safe={})", if self.safe { "True" } else { "False" })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_loader_reuses_options() {
+        Python::with_gil(|py| {
+            let options = PyDict::new_bound(py);
+            options.set_item("pyyaml_compat", true).unwrap();
+            let loader = Loader::new(true, Some(&options)).unwrap();
+            for _ in 0..2 {
+                let value = loader.load(py, "enabled: yes").unwrap();
+                let enabled: bool = value
+                    .bind(py)
+                    .get_item("enabled")
+                    .unwrap()
+                    .extract()
+                    .unwrap();
+                assert!(enabled);
+            }
+            let documents = loader.load_all(py, "a: 1\n---\nb: 2\n").unwrap();
+            assert_eq!(documents.bind(py).len().unwrap(), 2);
+            assert!(loader.load(py, "a: !!python/object:os.system x").is_err());
+
+            options.set_item("no_such_option", true).unwrap();
+            assert!(Loader::new(true, Some(&options)).is_err());
+        });
+    }
+}
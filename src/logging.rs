@@ -0,0 +1,406 @@
+//! Load `logging.config.dictConfig` configs from YAML
+//!
+//! `dictConfig` only looks up a handler's formatter or a logger's handlers
+//! when it builds them, so a typo surfaces as a `ValueError` deep in
+//! application startup, or not at all until that logger is used. The
+//! checks here run on the parsed document first and report every problem
+//! with its line:
+//! - `version` is present and `1`
+//! - sections and their entries are mappings
+//! - handlers have a `class` (or a `()` factory)
+//! - levels are standard level names or numbers
+//! - formatter, filter and handler references point at defined entries
+//!
+//! With `incremental: true` handlers refer to ones configured earlier, so
+//! references are not checked.
+
+use pyo3::prelude::*;
+use serde_yaml::{Mapping, Value};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::batch;
+use crate::compose;
+use crate::error::{SchemaViolation, YAMLError};
+use crate::options::LoadOptions;
+use crate::parser::{self, Parsed};
+use crate::schema;
+use crate::search::key_text;
+use crate::sourcemap;
+use crate::types::yaml_to_python;
+
+const LEVELS: [&str; 8] = [
+    "CRITICAL", "FATAL", "ERROR", "WARN", "WARNING", "INFO", "DEBUG", "NOTSET",
+];
+
+/// `handler 'console'` for `["handlers", "console"]`
+fn describe(parts: &[&str]) -> String {
+    match parts {
+        [section, name] => format!("{} '{}'", &section[..section.len() - 1], name),
+        _ => "the root logger".to_string(),
+    }
+}
+
+fn pointer(parts: &[&str]) -> String {
+    parts
+        .iter()
+        .map(|part| format!("/{}", sourcemap::escape(part)))
+        .collect()
+}
+
+/// Collects violations while walking a config
+struct Checker<'c> {
+    config: &'c Mapping,
+    /// Check references between sections (off for incremental configs)
+    references: bool,
+    violations: Vec<SchemaViolation>,
+}
+
+impl<'c> Checker<'c> {
+    fn report(&mut self, parts: &[&str], message: String) {
+        self.violations.push(SchemaViolation {
+            pointer: pointer(parts),
+            mark: None,
+            message,
+        });
+    }
+
+    /// Names defined in a section
+    fn defined(&self, section: &str) -> HashSet<String> {
+        match self.config.get(section) {
+            Some(Value::Mapping(entries)) => entries.keys().filter_map(key_text).collect(),
+            _ => HashSet::new(),
+        }
+    }
+
+    /// The entries of a section, reporting a section that is not a mapping
+    fn entries(&mut self, section: &str) -> Vec<(String, &'c Mapping)> {
+        let config = self.config;
+        let entries = match config.get(section) {
+            None => return Vec::new(),
+            Some(Value::Mapping(entries)) => entries,
+            Some(_) => {
+                self.report(&[section], format!("'{}' must be a mapping", section));
+                return Vec::new();
+            }
+        };
+        let mut found = Vec::new();
+        for (name, entry) in entries {
+            let Some(name) = key_text(name) else {
+                continue;
+            };
+            match entry {
+                Value::Mapping(entry) => found.push((name, entry)),
+                _ => {
+                    let parts = [section, name.as_str()];
+                    self.report(&parts, format!("{} must be a mapping", describe(&parts)))
+                }
+            }
+        }
+        found
+    }
+
+    fn check_bool(&mut self, entry: &Mapping, parts: &[&str]) {
+        let key = parts[parts.len() - 1];
+        if let Some(value) = entry.get(key) {
+            if !value.is_bool() {
+                self.report(parts, format!("'{}' must be true or false", key));
+            }
+        }
+    }
+
+    fn check_level(&mut self, entry: &Mapping, parts: &[&str]) {
+        let valid = match entry.get("level") {
+            None | Some(Value::Number(_)) => true,
+            Some(Value::String(level)) => LEVELS.contains(&level.as_str()),
+            Some(_) => false,
+        };
+        if !valid {
+            let level = serde_yaml::to_string(&entry["level"]).unwrap_or_default();
+            let mut parts = parts.to_vec();
+            parts.push("level");
+            self.report(
+                &parts,
+                format!(
+                    "unknown level {} (expected one of {})",
+                    level.trim_end(),
+                    LEVELS.join(", ")
+                ),
+            );
+        }
+    }
+
+    /// Check that `entry[key]` (a name or list of names) is defined in `section`
+    fn check_refs(
+        &mut self,
+        entry: &Mapping,
+        parts: &[&str],
+        key: &str,
+        section: &str,
+        defined: &HashSet<String>,
+    ) {
+        if !self.references {
+            return;
+        }
+        let owner = describe(parts);
+        let kind = &section[..section.len() - 1];
+        let mut parts = parts.to_vec();
+        parts.push(key);
+        match entry.get(key) {
+            None | Some(Value::Null) => {}
+            Some(Value::String(name)) if key == "formatter" || key == "target" => {
+                if !defined.contains(name) {
+                    self.report(
+                        &parts,
+                        format!("{} uses undefined {} '{}'", owner, kind, name),
+                    );
+                }
+            }
+            Some(Value::Sequence(names)) if key != "formatter" && key != "target" => {
+                for (index, name) in names.iter().enumerate() {
+                    // Filters may also be given inline as mappings
+                    let Value::String(name) = name else {
+                        continue;
+                    };
+                    if !defined.contains(name) {
+                        let index = index.to_string();
+                        let mut parts = parts.clone();
+                        parts.push(&index);
+                        self.report(
+                            &parts,
+                            format!("{} uses undefined {} '{}'", owner, kind, name),
+                        );
+                    }
+                }
+            }
+            Some(_) if key == "formatter" || key == "target" => {
+                self.report(&parts, format!("'{}' must be a {} name", key, kind))
+            }
+            Some(_) => self.report(&parts, format!("'{}' must be a list of names", key)),
+        }
+    }
+
+    fn check_logger(&mut self, entry: &Mapping, parts: &[&str]) {
+        let filters = self.defined("filters");
+        let handlers = self.defined("handlers");
+        self.check_level(entry, parts);
+        self.check_refs(entry, parts, "handlers", "handlers", &handlers);
+        self.check_refs(entry, parts, "filters", "filters", &filters);
+    }
+
+    fn check(&mut self) {
+        match self.config.get("version") {
+            Some(Value::Number(n)) if n.as_i64() == Some(1) => {}
+            Some(version) => {
+                let version = serde_yaml::to_string(version).unwrap_or_default();
+                self.report(
+                    &["version"],
+                    format!(
+                        "unsupported version {} (dictConfig only supports 1)",
+                        version.trim_end()
+                    ),
+                );
+            }
+            None => self.report(&[], "missing 'version: 1'".to_string()),
+        }
+        let config = self.config;
+        self.check_bool(config, &["incremental"]);
+        self.check_bool(config, &["disable_existing_loggers"]);
+
+        for (name, formatter) in self.entries("formatters") {
+            if let Some(style) = formatter.get("style") {
+                if !matches!(style.as_str(), Some("%" | "{" | "$")) {
+                    self.report(
+                        &["formatters", &name, "style"],
+                        "'style' must be one of %, {, $".to_string(),
+                    );
+                }
+            }
+        }
+        self.entries("filters");
+
+        let formatters = self.defined("formatters");
+        let filters = self.defined("filters");
+        let handlers = self.defined("handlers");
+        for (name, handler) in self.entries("handlers") {
+            let parts = ["handlers", name.as_str()];
+            if !self.references {
+                self.check_level(handler, &parts);
+                continue;
+            }
+            if !handler.contains_key("class") && !handler.contains_key("()") {
+                self.report(&parts, format!("{} has no 'class'", describe(&parts)));
+            }
+            self.check_level(handler, &parts);
+            self.check_refs(handler, &parts, "formatter", "formatters", &formatters);
+            self.check_refs(handler, &parts, "filters", "filters", &filters);
+            // MemoryHandler flushes to another handler
+            self.check_refs(handler, &parts, "target", "handlers", &handlers);
+        }
+
+        for (name, logger) in self.entries("loggers") {
+            let parts = ["loggers", name.as_str()];
+            self.check_logger(logger, &parts);
+            self.check_bool(logger, &["loggers", &name, "propagate"]);
+        }
+        match config.get("root") {
+            None | Some(Value::Null) => {}
+            Some(Value::Mapping(root)) => self.check_logger(root, &["root"]),
+            Some(_) => self.report(&["root"], "'root' must be a mapping".to_string()),
+        }
+    }
+}
+
+/// Check a `dictConfig` config
+///
+/// Violations are in config order, without positions (see
+/// `schema::locate`).
+pub fn check(config: &Value) -> Vec<SchemaViolation> {
+    let Value::Mapping(config) = config else {
+        return vec![SchemaViolation {
+            pointer: String::new(),
+            mark: None,
+            message: "a logging config must be a mapping".to_string(),
+        }];
+    };
+    let mut checker = Checker {
+        config,
+        references: config.get("incremental") != Some(&Value::Bool(true)),
+        violations: Vec::new(),
+    };
+    checker.check();
+    checker.violations
+}
+
+/// Read and check a logging config file (no GIL needed)
+///
+/// # Errors
+/// * Read and parse errors
+/// * `LoggingConfig` listing every problem with its line
+pub fn read_logging_config(path: &Path) -> Result<Value, YAMLError> {
+    let content = batch::read_file(path)?;
+    let config = match parser::parse_document(&content, &LoadOptions::default(), true)
+        .map_err(|err| err.in_file(path))?
+    {
+        Parsed::Value(value) => value,
+        Parsed::Composed(_) => unreachable!("default options use serde_yaml"),
+    };
+    let mut violations = check(&config);
+    if violations.is_empty() {
+        return Ok(config);
+    }
+    if let Some(document) = compose::compose_one(&content)? {
+        schema::locate(&mut violations, &document.root);
+    }
+    Err(YAMLError::LoggingConfig {
+        path: path.display().to_string(),
+        violations,
+    })
+}
+
+/// Load a `logging.config.dictConfig` config from a YAML file
+///
+/// # Arguments
+/// * `path` - YAML file
+/// * `apply` - Also pass the config to `logging.config.dictConfig`
+///
+/// # Returns
+/// The config as a dict
+///
+/// # Example
+/// ```python
+/// rustyyaml.load_logging_config("logging.yaml", apply=True)
+/// ```
+#[pyfunction]
+#[pyo3(signature = (path, apply=false))]
+pub fn load_logging_config(py: Python, path: PathBuf, apply: bool) -> PyResult<PyObject> {
+    let config = py.allow_threads(|| read_logging_config(&path))?;
+    let config = yaml_to_python(py, &config)?;
+    if apply {
+        py.import_bound("logging.config")?
+            .call_method1("dictConfig", (&config,))?;
+    }
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(yaml: &str) -> Vec<(String, String)> {
+        check(&serde_yaml::from_str(yaml).unwrap())
+            .into_iter()
+            .map(|v| (v.pointer, v.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_config() {
+        let yaml = "version: 1\ndisable_existing_loggers: false\n\
+                    formatters: {plain: {format: '%(message)s'}}\n\
+                    filters: {quiet: {name: app}}\n\
+                    handlers:\n  console: {class: logging.StreamHandler, formatter: plain, level: INFO}\n  \
+                    memory: {class: logging.handlers.MemoryHandler, capacity: 10, target: console}\n\
+                    loggers: {app: {handlers: [console], filters: [quiet], propagate: false, level: 10}}\n\
+                    root: {handlers: [memory], level: WARNING}\n";
+        assert_eq!(messages(yaml), []);
+    }
+
+    #[test]
+    fn test_dangling_references() {
+        let yaml = "version: 1\n\
+                    handlers:\n  console: {class: logging.StreamHandler, formatter: detailed}\n  \
+                    broken: {level: LOUD}\n\
+                    loggers: {app: {handlers: [console, file]}}\n\
+                    root: {handlers: [missing]}\n";
+        assert_eq!(
+            messages(yaml),
+            [
+                (
+                    "/handlers/console/formatter".to_string(),
+                    "handler 'console' uses undefined formatter 'detailed'".to_string()
+                ),
+                (
+                    "/handlers/broken".to_string(),
+                    "handler 'broken' has no 'class'".to_string()
+                ),
+                (
+                    "/handlers/broken/level".to_string(),
+                    "unknown level LOUD (expected one of CRITICAL, FATAL, ERROR, WARN, WARNING, INFO, DEBUG, NOTSET)".to_string()
+                ),
+                (
+                    "/loggers/app/handlers/1".to_string(),
+                    "logger 'app' uses undefined handler 'file'".to_string()
+                ),
+                (
+                    "/root/handlers/0".to_string(),
+                    "the root logger uses undefined handler 'missing'".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_version_and_shape() {
+        assert_eq!(
+            messages("handlers: []"),
+            [
+                ("".to_string(), "missing 'version: 1'".to_string()),
+                (
+                    "/handlers".to_string(),
+                    "'handlers' must be a mapping".to_string()
+                ),
+            ]
+        );
+        assert_eq!(
+            messages("version: 2")[0].1,
+            "unsupported version 2 (dictConfig only supports 1)"
+        );
+        assert_eq!(messages("- a")[0].1, "a logging config must be a mapping");
+        // Incremental configs refer to handlers configured earlier
+        assert_eq!(
+            messages("version: 1\nincremental: true\nloggers: {app: {handlers: [h]}}"),
+            []
+        );
+    }
+}
@@ -0,0 +1,269 @@
+//! Stray document markers and junk between documents
+//!
+//! Naively concatenated files end up with empty documents (`---` followed
+//! by another `---`), repeated `...` end markers and whitespace junk (tabs,
+//! NULs) after the last document. libyaml either rejects these with an
+//! unhelpful scanner error or produces extra `None` documents.
+//!
+//! This is a line-based pre-scan: `---` and `...` are only markers at
+//! column 0, which is also where they end block scalars, so no real parsing
+//! is needed. Lenient mode blanks the offending lines (keeping line numbers
+//! intact for later errors); strict mode reports them.
+
+use std::borrow::Cow;
+
+use crate::error::YAMLError;
+
+/// How to treat stray markers (`document_markers=` option)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MarkerMode {
+    /// Drop stray markers and junk whitespace before parsing
+    Lenient,
+    /// Raise an error pointing at the first stray marker
+    Strict,
+}
+
+impl MarkerMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "lenient" => Some(MarkerMode::Lenient),
+            "strict" => Some(MarkerMode::Strict),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StrayKind {
+    /// A `---` whose document has no content
+    EmptyDocument,
+    /// A `...` with no open document
+    RepeatedEnd,
+    /// A blank-looking line with tabs or control characters outside a document
+    JunkWhitespace,
+}
+
+impl StrayKind {
+    fn describe(self) -> &'static str {
+        match self {
+            StrayKind::EmptyDocument => "stray '---' starts an empty document",
+            StrayKind::RepeatedEnd => "stray '...' with no open document",
+            StrayKind::JunkWhitespace => "tab or control characters outside any document",
+        }
+    }
+}
+
+/// A stray marker or junk line (1-based line number)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Stray {
+    pub line: usize,
+    pub kind: StrayKind,
+}
+
+/// Apply `mode` to the input before parsing
+///
+/// # Errors
+/// * In strict mode, a parse error at the first stray marker
+pub fn apply(yaml_str: &str, mode: Option<MarkerMode>) -> Result<Cow<'_, str>, YAMLError> {
+    let Some(mode) = mode else {
+        return Ok(Cow::Borrowed(yaml_str));
+    };
+    let (strays, blank) = scan(yaml_str);
+    if strays.is_empty() {
+        return Ok(Cow::Borrowed(yaml_str));
+    }
+
+    match mode {
+        MarkerMode::Strict => {
+            let first = &strays[0];
+            let mut message = first.kind.describe().to_string();
+            if strays.len() > 1 {
+                let others: Vec<String> = strays[1..]
+                    .iter()
+                    .map(|stray| stray.line.to_string())
+                    .collect();
+                message.push_str(&format!(" (more on lines {})", others.join(", ")));
+            }
+            Err(YAMLError::parse_with_context(
+                first.line, 1, message, yaml_str,
+            ))
+        }
+        MarkerMode::Lenient => {
+            let cleaned: Vec<&str> = yaml_str
+                .split('\n')
+                .enumerate()
+                .map(|(i, line)| if blank.contains(&(i + 1)) { "" } else { line })
+                .collect();
+            Ok(Cow::Owned(cleaned.join("\n")))
+        }
+    }
+}
+
+fn is_marker(line: &str, marker: &str) -> bool {
+    line.strip_prefix(marker)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', '\t']))
+}
+
+/// Nothing but whitespace or a comment
+fn is_empty_rest(rest: &str) -> bool {
+    let rest = rest.trim_start_matches([' ', '\t']);
+    rest.is_empty() || rest.starts_with('#')
+}
+
+fn is_junk_char(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\r' | '\0' | '\x0b' | '\x0c')
+}
+
+/// Blank-looking, but not something libyaml accepts everywhere
+fn is_junk_line(line: &str) -> bool {
+    !line.is_empty()
+        && line.chars().all(is_junk_char)
+        && !line.chars().all(|c| c == ' ' || c == '\r')
+}
+
+/// Find stray markers; also returns every line lenient mode should blank
+pub fn scan(yaml_str: &str) -> (Vec<Stray>, Vec<usize>) {
+    let mut strays = Vec::new();
+    let mut blank = Vec::new();
+    let mut in_document = false;
+    // Line of a bare `---` still waiting for content
+    let mut open_start: Option<usize> = None;
+    let mut last_content = 0;
+
+    let lines: Vec<&str> = yaml_str.split('\n').collect();
+    for (i, raw) in lines.iter().enumerate() {
+        let number = i + 1;
+        let line = raw.strip_suffix('\r').unwrap_or(raw);
+
+        if is_marker(line, "---") {
+            if let Some(start) = open_start {
+                strays.push(Stray {
+                    line: start,
+                    kind: StrayKind::EmptyDocument,
+                });
+                blank.push(start);
+            }
+            open_start = is_empty_rest(&line[3..]).then_some(number);
+            in_document = true;
+            last_content = number;
+        } else if is_marker(line, "...") && is_empty_rest(&line[3..]) {
+            if let Some(start) = open_start.take() {
+                strays.push(Stray {
+                    line: start,
+                    kind: StrayKind::EmptyDocument,
+                });
+                blank.extend([start, number]);
+            } else if !in_document {
+                strays.push(Stray {
+                    line: number,
+                    kind: StrayKind::RepeatedEnd,
+                });
+                blank.push(number);
+            }
+            in_document = false;
+            last_content = number;
+        } else if is_junk_line(line) {
+            if !in_document {
+                strays.push(Stray {
+                    line: number,
+                    kind: StrayKind::JunkWhitespace,
+                });
+                blank.push(number);
+            }
+        } else if is_empty_rest(line) || (!in_document && line.starts_with('%')) {
+            // Blank lines, comments and directives don't start a document
+        } else {
+            in_document = true;
+            open_start = None;
+            last_content = number;
+        }
+    }
+
+    if let Some(start) = open_start {
+        strays.push(Stray {
+            line: start,
+            kind: StrayKind::EmptyDocument,
+        });
+        blank.push(start);
+    }
+
+    // Junk after the last document, even if it was never closed with `...`
+    for (i, raw) in lines.iter().enumerate().skip(last_content) {
+        let number = i + 1;
+        if is_junk_line(raw) && !blank.contains(&number) {
+            strays.push(Stray {
+                line: number,
+                kind: StrayKind::JunkWhitespace,
+            });
+            blank.push(number);
+        }
+    }
+
+    strays.sort_by_key(|stray| stray.line);
+    (strays, blank)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(yaml: &str) -> Vec<(usize, StrayKind)> {
+        scan(yaml).0.into_iter().map(|s| (s.line, s.kind)).collect()
+    }
+
+    #[test]
+    fn test_clean_streams() {
+        assert!(kinds("a: 1\n").is_empty());
+        assert!(kinds("---\na: 1\n...\n").is_empty());
+        assert!(kinds("--- 1\n--- 2\n").is_empty());
+        assert!(kinds("%YAML 1.2\n---\na: 1\n").is_empty());
+        assert!(kinds("a: |\n  text\n\n").is_empty());
+    }
+
+    #[test]
+    fn test_empty_documents() {
+        assert_eq!(kinds("---\n---\na: 1\n"), [(1, StrayKind::EmptyDocument)]);
+        assert_eq!(kinds("a: 1\n---\n"), [(2, StrayKind::EmptyDocument)]);
+        assert_eq!(
+            kinds("a: 1\n...\n---\n...\n"),
+            [(3, StrayKind::EmptyDocument)]
+        );
+    }
+
+    #[test]
+    fn test_repeated_end_and_junk() {
+        assert_eq!(kinds("a: 1\n...\n...\n"), [(3, StrayKind::RepeatedEnd)]);
+        assert_eq!(
+            kinds("---\na: 1\n...\n   \n\t\n"),
+            [(5, StrayKind::JunkWhitespace)]
+        );
+        assert_eq!(
+            kinds("a: 1\n\t\n\0"),
+            [
+                (2, StrayKind::JunkWhitespace),
+                (3, StrayKind::JunkWhitespace)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lenient_keeps_line_numbers() {
+        let cleaned = apply("---\n---\na: 1\n...\n\t\n", Some(MarkerMode::Lenient)).unwrap();
+        assert_eq!(cleaned, "\n---\na: 1\n...\n\n");
+    }
+
+    #[test]
+    fn test_strict_pinpoints_first() {
+        let err = apply("a: 1\n...\n...\n---\n", Some(MarkerMode::Strict)).unwrap_err();
+        let msg = err.to_string();
+        assert!(msg.contains("line 3"));
+        assert!(msg.contains("'...'"));
+        assert!(msg.contains("more on lines 4"));
+    }
+
+    #[test]
+    fn test_default_mode_untouched() {
+        let input = "---\n---\n";
+        assert!(matches!(apply(input, None).unwrap(), Cow::Borrowed(_)));
+    }
+}
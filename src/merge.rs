@@ -0,0 +1,257 @@
+//! `<<` merge keys on serde_yaml values
+//!
+//! The composer path merges while constructing (`construct.rs`). A `Value`
+//! can't tell a plain `<<` from a quoted `"<<"` and has no positions, so a
+//! document with a `<<` key is composed again and its nodes walked
+//! alongside the value: they say which keys merge and where a bad merge is.
+//! Everything else skips the second parse.
+
+use std::collections::HashMap;
+
+use serde_yaml::{Mapping, Value};
+
+use crate::compose::{self, Node, NodeKind};
+use crate::construct::is_merge_key;
+use crate::error::YAMLError;
+use crate::options::LoadOptions;
+
+/// Apply the merge keys of document `index` of `yaml_str`
+///
+/// Merged entries come first and the mapping's own keys override them;
+/// of a list of mappings, earlier ones take precedence (PyYAML's order).
+///
+/// # Errors
+/// * `ParseError` if a merge value isn't a mapping or list of mappings
+pub fn merge_value(
+    value: &mut Value,
+    options: &LoadOptions,
+    yaml_str: &str,
+    index: usize,
+) -> Result<(), YAMLError> {
+    if !options.merge_keys() || !yaml_str.contains("<<") || !has_merge_key(value) {
+        return Ok(());
+    }
+    let Some(document) = compose::compose_all(yaml_str)?.into_iter().nth(index) else {
+        return Ok(());
+    };
+    let mut merger = Merger {
+        anchors: HashMap::new(),
+    };
+    merger.merge(&document.root, value, true)
+}
+
+/// Does `value` have a `<<` key anywhere?
+fn has_merge_key(value: &Value) -> bool {
+    match value {
+        Value::Mapping(mapping) => mapping
+            .iter()
+            .any(|(key, value)| is_merge_text(key) || has_merge_key(value)),
+        Value::Sequence(items) => items.iter().any(has_merge_key),
+        Value::Tagged(tagged) => has_merge_key(&tagged.value),
+        _ => false,
+    }
+}
+
+fn is_merge_text(key: &Value) -> bool {
+    match key {
+        Value::String(text) => text == "<<",
+        Value::Tagged(tagged) => is_merge_text(&tagged.value),
+        _ => false,
+    }
+}
+
+fn merge_error(node: &Node, message: &str) -> YAMLError {
+    YAMLError::parse(
+        node.start.line + 1,
+        node.start.column + 1,
+        message.to_string(),
+    )
+}
+
+struct Merger<'n> {
+    /// Most recent node defined for each anchor
+    anchors: HashMap<&'n str, &'n Node>,
+}
+
+impl<'n> Merger<'n> {
+    /// The node an alias stands for
+    fn deref(&self, node: &'n Node) -> &'n Node {
+        match &node.kind {
+            NodeKind::Alias { anchor } => {
+                self.anchors.get(anchor.as_str()).copied().unwrap_or(node)
+            }
+            _ => node,
+        }
+    }
+
+    /// Record the anchors defined in `node` (a key, which isn't merged)
+    fn register(&mut self, node: &'n Node) {
+        if let Some(anchor) = &node.anchor {
+            self.anchors.insert(anchor, node);
+        }
+        match &node.kind {
+            NodeKind::Sequence { items, .. } => items.iter().for_each(|item| self.register(item)),
+            NodeKind::Mapping { pairs, .. } => pairs.iter().for_each(|(key, value)| {
+                self.register(key);
+                self.register(value);
+            }),
+            NodeKind::Scalar { .. } | NodeKind::Alias { .. } => {}
+        }
+    }
+
+    /// Apply the merge keys below `node` to `value`, its (alias-expanded)
+    /// value; anchors are only recorded where they're defined, not again
+    /// in the copy an alias stands for
+    fn merge(
+        &mut self,
+        node: &'n Node,
+        value: &mut Value,
+        register: bool,
+    ) -> Result<(), YAMLError> {
+        if let (true, Some(anchor)) = (register, &node.anchor) {
+            self.anchors.insert(anchor, node);
+        }
+        let (node, register) = match &node.kind {
+            NodeKind::Alias { .. } => (self.deref(node), false),
+            _ => (node, register),
+        };
+        let value = match value {
+            Value::Tagged(tagged) => &mut tagged.value,
+            value => value,
+        };
+        match (&node.kind, value) {
+            (NodeKind::Sequence { items, .. }, Value::Sequence(values)) => {
+                for (item, value) in items.iter().zip(values) {
+                    self.merge(item, value, register)?;
+                }
+                Ok(())
+            }
+            (NodeKind::Mapping { pairs, .. }, Value::Mapping(mapping)) => {
+                self.mapping(pairs, mapping, register)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn mapping(
+        &mut self,
+        pairs: &'n [(Node, Node)],
+        mapping: &mut Mapping,
+        register: bool,
+    ) -> Result<(), YAMLError> {
+        // Values first, so what's merged in has had its own merges applied
+        for ((key, node), value) in pairs.iter().zip(mapping.values_mut()) {
+            if register {
+                self.register(key);
+            }
+            self.merge(node, value, register)?;
+        }
+        if !pairs.iter().any(|(key, _)| is_merge_key(key, true)) {
+            return Ok(());
+        }
+
+        let mut merged = Mapping::new();
+        let mut own = Vec::with_capacity(mapping.len());
+        for ((key, node), (key_value, value)) in pairs.iter().zip(std::mem::take(mapping)) {
+            if !is_merge_key(key, true) {
+                own.push((key_value, value));
+                continue;
+            }
+            let node = self.deref(node);
+            let value = match value {
+                Value::Tagged(tagged) => tagged.value,
+                value => value,
+            };
+            match (&node.kind, value) {
+                (_, Value::Mapping(entries)) => merged.extend(entries),
+                (NodeKind::Sequence { items, .. }, Value::Sequence(values)) => {
+                    // Earlier mappings in the list take precedence
+                    for (item, value) in items.iter().zip(values).rev() {
+                        let Value::Mapping(entries) = value else {
+                            return Err(merge_error(
+                                self.deref(item),
+                                "expected a mapping for merging",
+                            ));
+                        };
+                        merged.extend(entries);
+                    }
+                }
+                _ => {
+                    return Err(merge_error(
+                        node,
+                        "expected a mapping or list of mappings for merging",
+                    ))
+                }
+            }
+        }
+        merged.extend(own);
+        *mapping = merged;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn merged(yaml: &str, options: &LoadOptions) -> Result<Value, YAMLError> {
+        let mut value = serde_yaml::from_str(yaml).unwrap();
+        merge_value(&mut value, options, yaml, 0)?;
+        Ok(value)
+    }
+
+    fn yaml(text: &str) -> Value {
+        serde_yaml::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn test_merge() {
+        let options = LoadOptions::default();
+        let value = merged("b: &b {x: 1, y: 2}\nc:\n  <<: *b\n  y: 3\n", &options).unwrap();
+        assert_eq!(value, yaml("b: {x: 1, y: 2}\nc: {x: 1, y: 3}"));
+        // Key order: merged entries first
+        let keys: Vec<_> = value["c"].as_mapping().unwrap().keys().cloned().collect();
+        assert_eq!(keys, [yaml("x"), yaml("y")]);
+
+        let value = merged(
+            "a: &a {x: 1}\nb: &b {x: 2, y: 2}\nc:\n  <<: [*a, *b]\n  z: 3\n",
+            &options,
+        )
+        .unwrap();
+        assert_eq!(value["c"], yaml("{x: 1, y: 2, z: 3}"));
+    }
+
+    #[test]
+    fn test_nested_merges() {
+        let value = merged(
+            "a: &a {x: 1}\nb: &b {<<: *a, y: 2}\nc: {<<: *b, z: 3}\nd: [{<<: *a}]\n",
+            &LoadOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(value["b"], yaml("{x: 1, y: 2}"));
+        assert_eq!(value["c"], yaml("{x: 1, y: 2, z: 3}"));
+        assert_eq!(value["d"], yaml("[{x: 1}]"));
+    }
+
+    #[test]
+    fn test_quoted_and_disabled() {
+        let source = "a: &a {x: 1}\nb: {'<<': *a}\n";
+        let value = merged(source, &LoadOptions::default()).unwrap();
+        assert_eq!(value["b"], yaml("{'<<': {x: 1}}"));
+
+        let options = LoadOptions {
+            merge_keys: Some(false),
+            ..Default::default()
+        };
+        let source = "a: &a {x: 1}\nb: {<<: *a}\n";
+        assert_eq!(merged(source, &options).unwrap(), yaml(source));
+    }
+
+    #[test]
+    fn test_bad_merge() {
+        let err = merged("a: 1\nb:\n  <<: 5\n", &LoadOptions::default()).unwrap_err();
+        assert!(err.to_string().contains("line 3"), "{}", err);
+        let err = merged("b:\n  <<: [{x: 1}, 2]\n", &LoadOptions::default()).unwrap_err();
+        assert!(err.to_string().contains("expected a mapping"), "{}", err);
+    }
+}
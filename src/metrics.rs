@@ -0,0 +1,86 @@
+//! Process-wide parser counters
+//!
+//! Every parse goes through `parser::parse_document` or
+//! `parser::parse_documents`, which add to these counters; batch loads
+//! also count the files whose content was already parsed. The counters are
+//! atomics, so rayon workers update them without locking, and they only
+//! ever grow (what Prometheus expects of a counter).
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+static DOCUMENTS: AtomicU64 = AtomicU64::new(0);
+static BYTES: AtomicU64 = AtomicU64::new(0);
+static PARSE_NANOS: AtomicU64 = AtomicU64::new(0);
+static CACHE_HITS: AtomicU64 = AtomicU64::new(0);
+static ERRORS: AtomicU64 = AtomicU64::new(0);
+
+/// Count one parse of `bytes` bytes: `Some(documents)` if it succeeded
+pub fn record_parse(bytes: usize, elapsed: Duration, documents: Option<usize>) {
+    BYTES.fetch_add(bytes as u64, Ordering::Relaxed);
+    let nanos = u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX);
+    PARSE_NANOS.fetch_add(nanos, Ordering::Relaxed);
+    match documents {
+        Some(documents) => DOCUMENTS.fetch_add(documents as u64, Ordering::Relaxed),
+        None => ERRORS.fetch_add(1, Ordering::Relaxed),
+    };
+}
+
+/// Count files that reused the parse of an identical file
+pub fn record_cache_hits(hits: usize) {
+    CACHE_HITS.fetch_add(hits as u64, Ordering::Relaxed);
+}
+
+/// Cumulative parser counters for this process
+///
+/// # Returns
+/// Dict with `documents` (documents parsed), `bytes` (bytes of YAML
+/// parsed), `parse_time` (seconds spent parsing, summed over threads),
+/// `cache_hits` (batch-loaded files that reused the parse of an identical
+/// file) and `errors` (parses that failed)
+///
+/// # Example
+/// ```python
+/// stats = rustyyaml.metrics()
+/// parsed_docs.set(stats["documents"])
+/// ```
+#[pyfunction]
+pub fn metrics(py: Python) -> PyResult<PyObject> {
+    let stats = PyDict::new_bound(py);
+    stats.set_item("documents", DOCUMENTS.load(Ordering::Relaxed))?;
+    stats.set_item("bytes", BYTES.load(Ordering::Relaxed))?;
+    let nanos = PARSE_NANOS.load(Ordering::Relaxed);
+    stats.set_item("parse_time", Duration::from_nanos(nanos).as_secs_f64())?;
+    stats.set_item("cache_hits", CACHE_HITS.load(Ordering::Relaxed))?;
+    stats.set_item("errors", ERRORS.load(Ordering::Relaxed))?;
+    Ok(stats.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counters_grow() {
+        Python::with_gil(|py| {
+            let count = |name: &str| -> u64 {
+                metrics(py)
+                    .unwrap()
+                    .bind(py)
+                    .get_item(name)
+                    .unwrap()
+                    .extract()
+                    .unwrap()
+            };
+            let (documents, bytes, errors) = (count("documents"), count("bytes"), count("errors"));
+            record_parse(10, Duration::from_millis(1), Some(2));
+            record_parse(5, Duration::ZERO, None);
+            // Other tests parse concurrently, so only lower bounds hold
+            assert!(count("documents") >= documents + 2);
+            assert!(count("bytes") >= bytes + 15);
+            assert!(count("errors") > errors);
+        });
+    }
+}
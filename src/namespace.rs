@@ -0,0 +1,233 @@
+//! Attribute-access mappings for config objects (`as_namespace=True`)
+//!
+//! `Namespace` wraps a dict so that `cfg.database.port` and
+//! `cfg["database"]["port"]` both work. The loader creates one for every
+//! mapping through the `dict_class` hook, so nested mappings (including
+//! those inside lists) are namespaces too.
+//!
+//! Keys that clash with a method name (`items`, `keys`, ...) or are not
+//! identifiers are only reachable by subscript.
+
+use pyo3::exceptions::{PyAttributeError, PyKeyError};
+use pyo3::prelude::*;
+use pyo3::pyclass::CompareOp;
+use pyo3::types::{PyDict, PyList};
+
+/// A dict that also exposes its keys as attributes
+///
+/// ```python
+/// cfg = rustyyaml.safe_load(text, as_namespace=True)
+/// cfg.database.port == cfg["database"]["port"]
+/// cfg.to_dict()  # plain dicts and lists again, e.g. for json.dumps
+/// ```
+#[pyclass(module = "rustyyaml", frozen, mapping)]
+pub struct Namespace {
+    data: Py<PyDict>,
+}
+
+/// Wrap dicts in `obj` (recursively, through lists) as namespaces
+fn wrap(py: Python, obj: &Bound<'_, PyAny>) -> PyResult<PyObject> {
+    if let Ok(dict) = obj.downcast::<PyDict>() {
+        let data = PyDict::new_bound(py);
+        for (key, value) in dict.iter() {
+            data.set_item(key, wrap(py, &value)?)?;
+        }
+        return Ok(Namespace {
+            data: data.unbind(),
+        }
+        .into_py(py));
+    }
+    if let Ok(list) = obj.downcast::<PyList>() {
+        let items = list
+            .iter()
+            .map(|item| wrap(py, &item))
+            .collect::<PyResult<Vec<_>>>()?;
+        return Ok(PyList::new_bound(py, items).into());
+    }
+    Ok(obj.clone().unbind())
+}
+
+/// Turn namespaces in `obj` (recursively, through lists) back into dicts
+fn unwrap(py: Python, obj: &Bound<'_, PyAny>) -> PyResult<PyObject> {
+    if let Ok(namespace) = obj.downcast::<Namespace>() {
+        return namespace.get().to_dict(py);
+    }
+    if let Ok(list) = obj.downcast::<PyList>() {
+        let items = list
+            .iter()
+            .map(|item| unwrap(py, &item))
+            .collect::<PyResult<Vec<_>>>()?;
+        return Ok(PyList::new_bound(py, items).into());
+    }
+    Ok(obj.clone().unbind())
+}
+
+impl Namespace {
+    /// The wrapped dict
+    pub fn dict<'py>(&self, py: Python<'py>) -> &Bound<'py, PyDict> {
+        self.data.bind(py)
+    }
+}
+
+#[pymethods]
+impl Namespace {
+    /// Wrap `data`, converting nested dicts to namespaces
+    #[new]
+    #[pyo3(signature = (data=None))]
+    fn new(py: Python, data: Option<&Bound<'_, PyDict>>) -> PyResult<Self> {
+        let wrapped = PyDict::new_bound(py);
+        if let Some(data) = data {
+            for (key, value) in data.iter() {
+                wrapped.set_item(key, wrap(py, &value)?)?;
+            }
+        }
+        Ok(Namespace {
+            data: wrapped.unbind(),
+        })
+    }
+
+    fn __getattr__(&self, py: Python, name: &str) -> PyResult<PyObject> {
+        match self.dict(py).get_item(name)? {
+            Some(value) => Ok(value.unbind()),
+            None => Err(PyAttributeError::new_err(name.to_string())),
+        }
+    }
+
+    fn __setattr__(&self, py: Python, name: &str, value: PyObject) -> PyResult<()> {
+        self.dict(py).set_item(name, value)
+    }
+
+    fn __delattr__(&self, py: Python, name: &str) -> PyResult<()> {
+        if !self.dict(py).contains(name)? {
+            return Err(PyAttributeError::new_err(name.to_string()));
+        }
+        self.dict(py).del_item(name)
+    }
+
+    fn __getitem__(&self, py: Python, key: &Bound<'_, PyAny>) -> PyResult<PyObject> {
+        match self.dict(py).get_item(key)? {
+            Some(value) => Ok(value.unbind()),
+            None => Err(PyKeyError::new_err(key.clone().unbind())),
+        }
+    }
+
+    fn __setitem__(&self, py: Python, key: PyObject, value: PyObject) -> PyResult<()> {
+        self.dict(py).set_item(key, value)
+    }
+
+    fn __delitem__(&self, py: Python, key: &Bound<'_, PyAny>) -> PyResult<()> {
+        self.dict(py).del_item(key)
+    }
+
+    fn __contains__(&self, py: Python, key: &Bound<'_, PyAny>) -> PyResult<bool> {
+        self.dict(py).contains(key)
+    }
+
+    fn __len__(&self, py: Python) -> usize {
+        self.dict(py).len()
+    }
+
+    fn __iter__(&self, py: Python) -> PyResult<PyObject> {
+        Ok(self.dict(py).as_any().iter()?.into_any().unbind())
+    }
+
+    fn __richcmp__(
+        &self,
+        py: Python,
+        other: &Bound<'_, PyAny>,
+        op: CompareOp,
+    ) -> PyResult<PyObject> {
+        let other = match other.downcast::<Namespace>() {
+            Ok(namespace) => namespace.get().dict(py).clone().into_any(),
+            Err(_) if other.is_instance_of::<PyDict>() => other.clone(),
+            Err(_) => return Ok(py.NotImplemented()),
+        };
+        match op {
+            CompareOp::Eq => Ok(self.dict(py).eq(other)?.into_py(py)),
+            CompareOp::Ne => Ok(self.dict(py).ne(other)?.into_py(py)),
+            _ => Ok(py.NotImplemented()),
+        }
+    }
+
+    fn __repr__(&self, py: Python) -> PyResult<String> {
+        Ok(format!("Namespace({})", self.dict(py).repr()?))
+    }
+
+    fn keys(&self, py: Python) -> PyResult<PyObject> {
+        Ok(self.dict(py).call_method0("keys")?.unbind())
+    }
+
+    fn values(&self, py: Python) -> PyResult<PyObject> {
+        Ok(self.dict(py).call_method0("values")?.unbind())
+    }
+
+    fn items(&self, py: Python) -> PyResult<PyObject> {
+        Ok(self.dict(py).call_method0("items")?.unbind())
+    }
+
+    #[pyo3(signature = (key, default=None))]
+    fn get(
+        &self,
+        py: Python,
+        key: &Bound<'_, PyAny>,
+        default: Option<PyObject>,
+    ) -> PyResult<PyObject> {
+        match self.dict(py).get_item(key)? {
+            Some(value) => Ok(value.unbind()),
+            None => Ok(default.unwrap_or_else(|| py.None())),
+        }
+    }
+
+    /// Plain dicts and lists all the way down
+    fn to_dict(&self, py: Python) -> PyResult<PyObject> {
+        let dict = PyDict::new_bound(py);
+        for (key, value) in self.dict(py).iter() {
+            dict.set_item(key, unwrap(py, &value)?)?;
+        }
+        Ok(dict.into())
+    }
+
+    /// Pickle (and copy) as the constructor called on `to_dict()`
+    fn __reduce__(slf: &Bound<'_, Self>) -> PyResult<(PyObject, (PyObject,))> {
+        let py = slf.py();
+        Ok((
+            slf.get_type().into_any().unbind(),
+            (slf.get().to_dict(py)?,),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap_and_unwrap() {
+        Python::with_gil(|py| {
+            let data = py
+                .eval_bound(
+                    "{'db': {'port': 5432}, 'hosts': [{'name': 'a'}]}",
+                    None,
+                    None,
+                )
+                .unwrap();
+            let namespace = wrap(py, &data).unwrap();
+            let namespace = namespace.bind(py);
+            let port: i64 = namespace
+                .getattr("db")
+                .and_then(|db| db.getattr("port"))
+                .unwrap()
+                .extract()
+                .unwrap();
+            assert_eq!(port, 5432);
+            let host = namespace.getattr("hosts").unwrap().get_item(0).unwrap();
+            assert!(host.downcast::<Namespace>().is_ok());
+            assert!(namespace.getattr("missing").is_err());
+
+            let plain = namespace.call_method0("to_dict").unwrap();
+            assert!(plain.downcast::<PyDict>().is_ok());
+            assert!(plain.eq(&data).unwrap());
+            assert!(namespace.eq(&data).unwrap());
+        });
+    }
+}
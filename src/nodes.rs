@@ -0,0 +1,224 @@
+//! PyYAML-compatible node graph (`compose`)
+//!
+//! `compose` hands out the composed document as instances of the classes
+//! in `rustyyaml.nodes`, which mirror PyYAML's `yaml.nodes`: each node has
+//! its resolved tag, marks and style, plus the anchor it was defined with.
+//! As in PyYAML, an alias is the very node object its anchor names, so a
+//! recursive document becomes a cyclic graph.
+//!
+//! Implicit tags are resolved as construction would resolve them: YAML 1.2
+//! core rules by default, PyYAML's YAML 1.1 rules with `pyyaml_compat`.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use pyo3::prelude::*;
+use pyo3::sync::GILOnceCell;
+use pyo3::types::{IntoPyDict, PyDict, PyList, PyModule, PyTuple};
+
+use crate::compose::{self, Document, Node, NodeKind};
+use crate::error::YAMLError;
+use crate::event_stream::{scalar_style, CharIndex};
+use crate::limits;
+use crate::options::LoadOptions;
+use crate::resolve::{self, Scalar, Schema};
+use crate::tabs;
+
+fn nodes_module(py: Python<'_>) -> PyResult<&Bound<'_, PyModule>> {
+    static NODES: GILOnceCell<Py<PyModule>> = GILOnceCell::new();
+    NODES
+        .get_or_try_init(py, || py.import_bound("rustyyaml.nodes").map(Bound::unbind))
+        .map(|module| module.bind(py))
+}
+
+/// The tag `node` resolves to: the one written, or the implicit one
+fn resolved_tag(node: &Node, schema: Schema) -> &str {
+    match (&node.kind, node.tag.as_deref()) {
+        (_, Some(tag)) if tag != "!" => tag,
+        (NodeKind::Sequence { .. }, _) => resolve::TAG_SEQ,
+        (NodeKind::Mapping { .. }, _) => resolve::TAG_MAP,
+        (NodeKind::Scalar { value, .. }, _) if node.is_plain_untagged() => {
+            match resolve::resolve_plain(value, schema) {
+                Scalar::Null => resolve::TAG_NULL,
+                Scalar::Bool(_) => resolve::TAG_BOOL,
+                Scalar::Int(_) => resolve::TAG_INT,
+                Scalar::Float(_) => resolve::TAG_FLOAT,
+                Scalar::Timestamp(_) => resolve::TAG_TIMESTAMP,
+                Scalar::Merge => resolve::TAG_MERGE,
+                _ => resolve::TAG_STR,
+            }
+        }
+        _ => resolve::TAG_STR,
+    }
+}
+
+/// Builds the Python nodes of one stream
+struct Builder<'py> {
+    py: Python<'py>,
+    module: &'py Bound<'py, PyModule>,
+    index: CharIndex,
+    schema: Schema,
+    /// Nodes by anchor, for the aliases that follow
+    anchors: HashMap<String, Bound<'py, PyAny>>,
+}
+
+impl<'py> Builder<'py> {
+    fn build(&mut self, node: &Node) -> PyResult<Bound<'py, PyAny>> {
+        if let NodeKind::Alias { anchor } = &node.kind {
+            return self.anchors.get(anchor).cloned().ok_or_else(|| {
+                YAMLError::parse(
+                    node.start.line + 1,
+                    node.start.column + 1,
+                    format!("found undefined alias '{}'", anchor),
+                )
+                .into()
+            });
+        }
+
+        let start = self.index.mark(self.py, node.start)?;
+        let end = self.index.mark(self.py, node.end)?;
+        let tag = resolved_tag(node, self.schema);
+        let anchor = [("anchor", node.anchor.as_deref())].into_py_dict_bound(self.py);
+        let (class, value, style) = match &node.kind {
+            NodeKind::Scalar { value, style } => {
+                let args = (tag, value, start, end, scalar_style(*style));
+                let scalar = self
+                    .module
+                    .getattr("ScalarNode")?
+                    .call(args, Some(&anchor))?;
+                self.define(node, &scalar);
+                return Ok(scalar);
+            }
+            NodeKind::Sequence { flow, .. } => ("SequenceNode", PyList::empty_bound(self.py), flow),
+            NodeKind::Mapping { flow, .. } => ("MappingNode", PyList::empty_bound(self.py), flow),
+            NodeKind::Alias { .. } => unreachable!("aliases are returned above"),
+        };
+        let args = (tag, &value, start, end, *style);
+        let collection = self.module.getattr(class)?.call(args, Some(&anchor))?;
+        // Defined before the children are built, so they can alias it
+        self.define(node, &collection);
+        match &node.kind {
+            NodeKind::Sequence { items, .. } => {
+                for item in items {
+                    value.append(self.build(item)?)?;
+                }
+            }
+            NodeKind::Mapping { pairs, .. } => {
+                for (key, item) in pairs {
+                    let pair = PyTuple::new_bound(self.py, [self.build(key)?, self.build(item)?]);
+                    value.append(pair)?;
+                }
+            }
+            NodeKind::Scalar { .. } | NodeKind::Alias { .. } => {}
+        }
+        Ok(collection)
+    }
+
+    fn define(&mut self, node: &Node, built: &Bound<'py, PyAny>) {
+        if let Some(anchor) = &node.anchor {
+            self.anchors.insert(anchor.clone(), built.clone());
+        }
+    }
+}
+
+/// Build the Python nodes of `documents`, composed from `yaml_str`
+fn build_documents<'py>(
+    py: Python<'py>,
+    documents: &[Document],
+    yaml_str: &str,
+    options: &LoadOptions,
+) -> PyResult<Vec<Bound<'py, PyAny>>> {
+    let mut builder = Builder {
+        py,
+        module: nodes_module(py)?,
+        index: CharIndex::new(Arc::from(yaml_str)),
+        schema: options.schema(),
+        anchors: HashMap::new(),
+    };
+    let mut roots = Vec::with_capacity(documents.len());
+    for document in documents {
+        limits::check_document(&document.root, options)?;
+        // Anchors don't carry over from one document to the next
+        builder.anchors.clear();
+        roots.push(builder.build(&document.root)?);
+    }
+    Ok(roots)
+}
+
+/// Compose a single document into a node graph, like PyYAML's
+/// `yaml.compose()`
+///
+/// # Arguments
+/// * `yaml_str` - YAML content with at most one document
+/// * `options` - Load options: `pyyaml_compat` selects how implicit tags are
+///   resolved, and limits (`max_depth`, ...) apply; nothing is constructed,
+///   so any tag is allowed
+///
+/// # Returns
+/// The root `rustyyaml.nodes` node, or `None` for an empty stream
+///
+/// # Errors
+/// * `YAMLError` for syntax errors, undefined aliases, exceeded limits or a
+///   second document
+#[pyfunction(name = "compose")]
+#[pyo3(signature = (yaml_str, **options))]
+pub fn py_compose(
+    py: Python,
+    yaml_str: &str,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<PyObject> {
+    let options = LoadOptions::from_kwargs(options)?;
+    limits::check_size(yaml_str, &options)?;
+    let document = compose::compose_one(yaml_str).map_err(|err| tabs::explain(err, yaml_str))?;
+    let mut roots = build_documents(py, document.as_slice(), yaml_str, &options)?;
+    Ok(roots.pop().map_or_else(|| py.None(), Bound::unbind))
+}
+
+/// Compose every document into a node graph, like PyYAML's
+/// `yaml.compose_all()`
+///
+/// # Arguments
+/// * `yaml_str` - YAML content with any number of documents
+/// * `options` - As for `compose`
+///
+/// # Returns
+/// A list with the root node of each document
+#[pyfunction(name = "compose_all")]
+#[pyo3(signature = (yaml_str, **options))]
+pub fn py_compose_all(
+    py: Python,
+    yaml_str: &str,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<PyObject> {
+    let options = LoadOptions::from_kwargs(options)?;
+    limits::check_size(yaml_str, &options)?;
+    let documents = compose::compose_all(yaml_str).map_err(|err| tabs::explain(err, yaml_str))?;
+    let roots = build_documents(py, &documents, yaml_str, &options)?;
+    Ok(PyList::new_bound(py, roots).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(yaml: &str, schema: Schema) -> String {
+        let document = compose::compose_one(yaml).unwrap().unwrap();
+        resolved_tag(&document.root, schema).to_string()
+    }
+
+    #[test]
+    fn test_resolved_tag() {
+        assert_eq!(tag("42", Schema::Core), resolve::TAG_INT);
+        assert_eq!(tag("'42'", Schema::Core), resolve::TAG_STR);
+        assert_eq!(tag("~", Schema::Core), resolve::TAG_NULL);
+        assert_eq!(tag("yes", Schema::Core), resolve::TAG_STR);
+        assert_eq!(tag("yes", Schema::PyYaml), resolve::TAG_BOOL);
+        assert_eq!(tag("2024-01-01", Schema::PyYaml), resolve::TAG_TIMESTAMP);
+        assert_eq!(tag("<<", Schema::PyYaml), resolve::TAG_MERGE);
+        assert_eq!(tag("! 42", Schema::Core), resolve::TAG_STR);
+        assert_eq!(tag("!Ref x", Schema::Core), "!Ref");
+        assert_eq!(tag("[a]", Schema::Core), resolve::TAG_SEQ);
+        assert_eq!(tag("!!set {a}", Schema::Core), resolve::TAG_SET);
+        assert_eq!(tag("{a: 1}", Schema::Core), resolve::TAG_MAP);
+    }
+}
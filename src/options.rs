@@ -0,0 +1,415 @@
+//! Keyword options shared by the load functions
+//!
+//! Every loader accepts the same `**options`, parsed once into a
+//! `LoadOptions`. The struct is plain data so batch operations can share it
+//! across rayon workers.
+
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::sync::Arc;
+
+use crate::encoding::Decoding;
+use crate::interrupt::{Cancel, CancellationToken};
+use crate::limits::Limits;
+use crate::markers::MarkerMode;
+use crate::namespace::Namespace;
+use crate::redact;
+use crate::resolve::Schema;
+use crate::safe::{self, TagStrategy};
+use crate::schema;
+use crate::search::Segment;
+
+#[derive(Clone, Debug, Default)]
+pub struct LoadOptions {
+    /// Produce exactly the Python types PyYAML's SafeLoader would
+    /// (YAML 1.1 booleans, timestamps, `!!binary`, merge keys, shared aliases)
+    pub pyyaml_compat: bool,
+    /// Resolve plain timestamps: `2024-01-01` becomes `datetime.date`,
+    /// `2024-01-01T10:00:00Z` becomes `datetime.datetime`
+    pub timestamps: bool,
+    /// Construct every timestamp as `datetime.datetime` (dates at midnight)
+    pub dates_as_datetime: bool,
+    /// Resolve ISO-8601 (`PT30S`) and Go-style (`5m30s`) durations to
+    /// `datetime.timedelta`
+    pub durations: bool,
+    /// Resolve UUID-shaped scalars to `uuid.UUID`
+    pub uuids: bool,
+    /// Resolve IP addresses and CIDR networks to `ipaddress` objects
+    pub ip_addresses: bool,
+    /// Convert quantities under `resources.*` (`128Mi`, `500m`) to integers:
+    /// millicores for `cpu`, base units (bytes) for everything else
+    pub kubernetes_quantities: bool,
+    /// Aliases construct to the same Python object as their anchor
+    /// (`preserve_aliases=` option; implied by `pyyaml_compat`)
+    pub preserve_aliases: bool,
+    /// Merge the mappings under plain `<<` keys into their parent
+    /// (`merge_keys=` option; on unless set to false)
+    pub merge_keys: Option<bool>,
+    /// Handling of stray `---`/`...` markers and junk whitespace
+    /// (`"lenient"` or `"strict"`; `None` leaves it to the parser)
+    pub document_markers: Option<MarkerMode>,
+    /// Return `rustyyaml.marked` containers whose `.lc` attribute holds
+    /// the source position (ruamel.yaml conventions)
+    pub marked: bool,
+    /// Multi-document and batch loaders keep going after failures and
+    /// return `(results, errors)` instead of raising the first error
+    pub collect_errors: bool,
+    /// Directory loaders add an `info` dict (encoding, BOM, size, parse
+    /// time) to each result
+    pub file_info: bool,
+    /// How file and directory loaders decode files (`encoding=` option)
+    pub encoding: Decoding,
+    /// File and directory loaders map files instead of reading them, and
+    /// parse UTF-8 in place (`use_mmap=` option)
+    pub use_mmap: bool,
+    /// Coerce string scalars to the types this schema declares
+    /// (`schema=` option)
+    pub json_schema: Option<Arc<schema::Schema>>,
+    /// Key paths whose values are replaced by `"***"` (`redact=` option)
+    pub redact: Vec<Vec<Segment>>,
+    /// Decrypt SOPS documents, unwrapping their data key with this
+    /// callable (`sops_key=` option)
+    pub sops_key: Option<Arc<PyObject>>,
+    /// Decrypt SOPS documents without checking their MAC
+    /// (`sops_verify_mac=False`)
+    pub sops_ignore_mac: bool,
+    /// Largest input, document, scalar and mapping allowed (`max_size_bytes=`,
+    /// `max_nodes=`, `max_scalar_bytes=`, `max_mapping_entries=` options)
+    pub limits: Limits,
+    /// Called with `(path, error)` for each file a file or directory batch
+    /// load fails on, as soon as it fails (`on_error=` option)
+    pub on_error: Option<Arc<PyObject>>,
+    /// Stop batch loads when this token is cancelled (`cancel=` option)
+    pub cancel: Option<Arc<Cancel>>,
+    /// Mapping type to construct instead of `dict` (`dict_class=` option,
+    /// or `Namespace` for `as_namespace=True`)
+    pub dict_class: Option<Arc<PyObject>>,
+    /// Insert every mapping's keys in sorted order (`sort_keys=` option)
+    pub sort_keys: bool,
+    /// Merge this profile's sections over the base document
+    /// (`profile=` option)
+    pub profile: Option<String>,
+    /// Batch loaders return one frozen object for all structurally
+    /// identical subtrees (`share_subtrees=` option)
+    pub share_subtrees: bool,
+    /// `load_all` pairs each document with its byte range and first line
+    /// (`document_spans=` option)
+    pub document_spans: bool,
+    /// Custom tags constructed instead of rejected (`allowed_tags=` option)
+    pub allowed_tags: Vec<String>,
+    /// What those tags construct to (`tag_strategy=` option)
+    pub tag_strategy: TagStrategy,
+    /// Load integers too long for 64 bits exactly, through the composer
+    /// (set for every Python loader; Rust callers that need a `Value` for
+    /// a document leave it off)
+    pub exact_ints: bool,
+}
+
+impl LoadOptions {
+    /// Build options from the `**options` passed to a pyfunction
+    ///
+    /// # Errors
+    /// * `TypeError` for unknown option names or badly typed values
+    /// * `ValueError` for values outside an option's choices
+    pub fn from_kwargs(kwargs: Option<&Bound<'_, PyDict>>) -> PyResult<Self> {
+        let mut options = LoadOptions {
+            exact_ints: true,
+            ..LoadOptions::default()
+        };
+        let Some(kwargs) = kwargs else {
+            return Ok(options);
+        };
+
+        let mut as_namespace = false;
+        for (key, value) in kwargs.iter() {
+            let key: String = key.extract()?;
+            match key.as_str() {
+                "pyyaml_compat" => options.pyyaml_compat = value.extract()?,
+                "timestamps" => options.timestamps = value.extract()?,
+                "dates_as_datetime" => options.dates_as_datetime = value.extract()?,
+                "durations" => options.durations = value.extract()?,
+                "uuids" => options.uuids = value.extract()?,
+                "ip_addresses" => options.ip_addresses = value.extract()?,
+                "kubernetes_quantities" => options.kubernetes_quantities = value.extract()?,
+                "preserve_aliases" => options.preserve_aliases = value.extract()?,
+                "merge_keys" => options.merge_keys = value.extract()?,
+                "marked" => options.marked = value.extract()?,
+                "collect_errors" => options.collect_errors = value.extract()?,
+                "file_info" => options.file_info = value.extract()?,
+                "sort_keys" => options.sort_keys = value.extract()?,
+                "profile" => options.profile = value.extract()?,
+                "share_subtrees" => options.share_subtrees = value.extract()?,
+                "document_spans" => options.document_spans = value.extract()?,
+                "use_mmap" => options.use_mmap = value.extract()?,
+                "schema" => {
+                    options.json_schema = if value.is_none() {
+                        None
+                    } else {
+                        Some(schema::from_python(&value)?)
+                    };
+                }
+                "max_size_bytes" => options.limits.max_size_bytes = value.extract()?,
+                "max_nodes" => options.limits.max_nodes = value.extract()?,
+                "max_scalar_bytes" => options.limits.max_scalar_bytes = value.extract()?,
+                "max_mapping_entries" => options.limits.max_mapping_entries = value.extract()?,
+                "redact" => options.redact = redact::extract_paths(&value)?,
+                "allowed_tags" => options.allowed_tags = safe::extract_allowed_tags(&value)?,
+                "tag_strategy" => options.tag_strategy = TagStrategy::from_python(&value)?,
+                "sops_key" => {
+                    options.sops_key = if value.is_none() {
+                        None
+                    } else if value.is_callable() {
+                        Some(Arc::new(value.unbind()))
+                    } else {
+                        return Err(PyTypeError::new_err("sops_key must be callable"));
+                    };
+                }
+                "sops_verify_mac" => options.sops_ignore_mac = !value.extract::<bool>()?,
+                "on_error" => {
+                    options.on_error = if value.is_none() {
+                        None
+                    } else if value.is_callable() {
+                        Some(Arc::new(value.unbind()))
+                    } else {
+                        return Err(PyTypeError::new_err("on_error must be callable"));
+                    };
+                }
+                "as_namespace" => as_namespace = value.extract()?,
+                "dict_class" => {
+                    options.dict_class = if value.is_none() {
+                        None
+                    } else if value.is_callable() {
+                        Some(Arc::new(value.unbind()))
+                    } else {
+                        return Err(PyTypeError::new_err("dict_class must be callable"));
+                    };
+                }
+                "cancel" => {
+                    options.cancel = if value.is_none() {
+                        None
+                    } else {
+                        let token = value.downcast::<CancellationToken>().map_err(|_| {
+                            PyTypeError::new_err("cancel must be a CancellationToken")
+                        })?;
+                        Some(token.get().cancel.clone())
+                    };
+                }
+                "encoding" => {
+                    let name: Option<String> = value.extract()?;
+                    options.encoding = match name {
+                        None => Decoding::Detect,
+                        Some(name) => Decoding::from_name(&name).ok_or_else(|| {
+                            PyValueError::new_err(format!(
+                                "encoding must be 'auto', a UTF-8/16/32 codec, 'latin-1' \
+                                 or 'cp1252', not '{}'",
+                                name
+                            ))
+                        })?,
+                    };
+                }
+                "document_markers" => {
+                    let mode: Option<String> = value.extract()?;
+                    options.document_markers = match mode {
+                        None => None,
+                        Some(mode) => Some(MarkerMode::from_name(&mode).ok_or_else(|| {
+                            PyValueError::new_err(format!(
+                                "document_markers must be 'lenient' or 'strict', not '{}'",
+                                mode
+                            ))
+                        })?),
+                    };
+                }
+                other => {
+                    return Err(PyTypeError::new_err(format!(
+                        "unexpected keyword argument '{}'",
+                        other
+                    )))
+                }
+            }
+        }
+
+        if as_namespace {
+            if options.dict_class.is_some() {
+                return Err(PyValueError::new_err(
+                    "as_namespace can't be combined with dict_class",
+                ));
+            }
+            let namespace = kwargs.py().get_type_bound::<Namespace>();
+            options.dict_class = Some(Arc::new(namespace.into_any().unbind()));
+        }
+        if options.marked && options.dict_class.is_some() {
+            return Err(PyValueError::new_err(
+                "dict_class and as_namespace can't be combined with marked=True",
+            ));
+        }
+        Ok(options)
+    }
+
+    /// Do these options need the event-level composer?
+    ///
+    /// The default configuration keeps using serde_yaml's `Value`, which is
+    /// the fastest path. Anything that needs tags, styles or positions
+    /// switches to `compose`/`construct`.
+    pub fn needs_composer(&self) -> bool {
+        self.pyyaml_compat
+            || self.resolve_timestamps()
+            || self.durations
+            || self.uuids
+            || self.ip_addresses
+            || self.kubernetes_quantities
+            || self.preserve_aliases
+            || self.marked
+            || self.dict_class.is_some()
+            || !self.allowed_tags.is_empty()
+    }
+
+    /// Should plain `<<` keys merge? (YAML 1.1's merge type, which strict
+    /// YAML 1.2 doesn't have)
+    pub fn merge_keys(&self) -> bool {
+        self.merge_keys.unwrap_or(true)
+    }
+
+    /// Should an alias construct to its anchor's object rather than a copy?
+    pub fn share_aliases(&self) -> bool {
+        self.pyyaml_compat || self.preserve_aliases
+    }
+
+    /// Should untagged plain scalars be checked for timestamps?
+    ///
+    /// Always true in PyYAML mode; `dates_as_datetime` implies `timestamps`.
+    pub fn resolve_timestamps(&self) -> bool {
+        self.pyyaml_compat || self.timestamps || self.dates_as_datetime
+    }
+
+    /// Reject options that only make sense for multi-document loaders
+    ///
+    /// # Errors
+    /// * `TypeError` if `collect_errors`, `file_info`, `on_error`,
+    ///   `share_subtrees` or `document_spans` was passed to a
+    ///   single-document loader
+    pub fn check_single_document(&self) -> PyResult<()> {
+        if self.collect_errors {
+            return Err(PyTypeError::new_err(
+                "collect_errors is only supported by load_all and the batch loaders",
+            ));
+        }
+        self.check_no_files()?;
+        if self.share_subtrees {
+            return Err(PyTypeError::new_err(
+                "share_subtrees is only supported by the batch loaders",
+            ));
+        }
+        if self.document_spans {
+            return Err(PyTypeError::new_err(
+                "document_spans is only supported by load_all",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Reject options that report on files, for loaders given strings
+    ///
+    /// # Errors
+    /// * `TypeError` if `file_info` or `on_error` was passed
+    pub fn check_no_files(&self) -> PyResult<()> {
+        if self.file_info {
+            return Err(PyTypeError::new_err(
+                "file_info is only supported by the directory loaders",
+            ));
+        }
+        if self.on_error.is_some() {
+            return Err(PyTypeError::new_err(
+                "on_error is only supported by the directory loaders",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Implicit resolution rules for plain scalars
+    pub fn schema(&self) -> Schema {
+        if self.pyyaml_compat {
+            Schema::PyYaml
+        } else {
+            Schema::Core
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_options() {
+        let options = LoadOptions::from_kwargs(None).unwrap();
+        assert!(!options.pyyaml_compat);
+        assert!(!options.needs_composer());
+        assert_eq!(options.schema(), Schema::Core);
+    }
+
+    #[test]
+    fn test_pyyaml_compat_option() {
+        Python::with_gil(|py| {
+            let kwargs = PyDict::new_bound(py);
+            kwargs.set_item("pyyaml_compat", true).unwrap();
+            let options = LoadOptions::from_kwargs(Some(&kwargs)).unwrap();
+            assert!(options.needs_composer());
+            assert_eq!(options.schema(), Schema::PyYaml);
+        });
+    }
+
+    #[test]
+    fn test_dates_as_datetime_implies_timestamps() {
+        let options = LoadOptions {
+            dates_as_datetime: true,
+            ..Default::default()
+        };
+        assert!(options.resolve_timestamps());
+        assert!(options.needs_composer());
+        assert_eq!(options.schema(), Schema::Core);
+    }
+
+    #[test]
+    fn test_document_markers_option() {
+        Python::with_gil(|py| {
+            let kwargs = PyDict::new_bound(py);
+            kwargs.set_item("document_markers", "strict").unwrap();
+            let options = LoadOptions::from_kwargs(Some(&kwargs)).unwrap();
+            assert_eq!(options.document_markers, Some(MarkerMode::Strict));
+
+            kwargs.set_item("document_markers", "loose").unwrap();
+            let err = LoadOptions::from_kwargs(Some(&kwargs)).unwrap_err();
+            assert!(err.is_instance_of::<PyValueError>(py));
+        });
+    }
+
+    #[test]
+    fn test_dict_class_option() {
+        Python::with_gil(|py| {
+            let kwargs = PyDict::new_bound(py);
+            let ordered_dict = py
+                .import_bound("collections")
+                .unwrap()
+                .getattr("OrderedDict")
+                .unwrap();
+            kwargs.set_item("dict_class", ordered_dict).unwrap();
+            let options = LoadOptions::from_kwargs(Some(&kwargs)).unwrap();
+            assert!(options.needs_composer());
+
+            kwargs.set_item("marked", true).unwrap();
+            let err = LoadOptions::from_kwargs(Some(&kwargs)).unwrap_err();
+            assert!(err.is_instance_of::<PyValueError>(py));
+        });
+    }
+
+    #[test]
+    fn test_unknown_option_rejected() {
+        Python::with_gil(|py| {
+            let kwargs = PyDict::new_bound(py);
+            kwargs.set_item("not_an_option", true).unwrap();
+            let err = LoadOptions::from_kwargs(Some(&kwargs)).unwrap_err();
+            assert!(err.to_string().contains("not_an_option"));
+        });
+    }
+}
@@ -4,13 +4,481 @@
 //! - Safety checks (reject dangerous tags)
 //! - Better error messages
 //! - Python type conversion
+//!
+//! Loading happens in two stages so batch operations can do the first one
+//! without the GIL: `parse_document`/`parse_documents` produce `Parsed`
+//! values (pure Rust), and `to_python` converts them.
+//!
+//! Documents that are plain JSON (API-generated files often are) skip
+//! libyaml: `parse_json` hands them to serde_json, which is several times
+//! faster, and anything it does not accept goes through serde_yaml as usual.
 
+use pyo3::exceptions::PyTypeError;
 use pyo3::prelude::*;
 use serde_yaml::Value;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::Instant;
 
+use crate::compose::{self, Document, Node, NodeKind};
+use crate::construct::construct_document;
 use crate::error::YAMLError;
+use crate::limits;
+use crate::markers;
+use crate::merge;
+use crate::metrics;
+use crate::options::LoadOptions;
+use crate::profile;
+use crate::redact;
+use crate::resolve::{self, Scalar};
 use crate::safe;
-use crate::types::yaml_to_python;
+use crate::schema;
+use crate::sops;
+use crate::sourcemap;
+use crate::tabs;
+use crate::types::{yaml_to_python, yaml_to_python_sorted};
+
+/// A document parsed without touching Python, ready for conversion
+pub enum Parsed {
+    /// Default path: serde_yaml's `Value`
+    Value(Value),
+    /// Composer path, used when options need tags/styles/positions
+    /// (`None` for an empty stream)
+    Composed(Option<Document>),
+}
+
+/// Parse a single YAML document (no GIL needed)
+///
+/// # Arguments
+/// * `yaml_str` - YAML content as UTF-8 string
+/// * `options` - Load options
+/// * `safe` - Reject unsafe tags
+pub fn parse_document(
+    yaml_str: &str,
+    options: &LoadOptions,
+    safe: bool,
+) -> Result<Parsed, YAMLError> {
+    let start = Instant::now();
+    let parsed = parse_one(yaml_str, options, safe);
+    let documents = parsed.as_ref().ok().map(|parsed| match parsed {
+        Parsed::Composed(None) => 0,
+        _ => 1,
+    });
+    metrics::record_parse(yaml_str.len(), start.elapsed(), documents);
+    parsed
+}
+
+fn parse_one(yaml_str: &str, options: &LoadOptions, safe: bool) -> Result<Parsed, YAMLError> {
+    limits::check_size(yaml_str, options)?;
+    // A profile's sections may be in later documents of the stream
+    if options.profile.is_some() {
+        return Ok(match parse_many(yaml_str, options, safe)?.pop() {
+            Some(parsed) => parsed,
+            None if options.needs_composer() => Parsed::Composed(None),
+            None => Parsed::Value(Value::Null),
+        });
+    }
+    let yaml_str = &*markers::apply(yaml_str, options.document_markers)?;
+    if use_composer(yaml_str, options) {
+        let mut document =
+            compose::compose_one(yaml_str).map_err(|err| tabs::explain(err, yaml_str))?;
+        if let Some(document) = &mut document {
+            if !options.needs_composer() {
+                check_unique_keys(&document.root)?;
+            }
+            prepare_document(document, options, safe)?;
+        }
+        return Ok(Parsed::Composed(document));
+    }
+
+    let mut value = match parse_json(yaml_str) {
+        Some(value) => value,
+        None => serde_yaml::from_str(yaml_str)
+            .map_err(|err| tabs::explain(YAMLError::from(err), yaml_str))?,
+    };
+    limits::check_value(&value, options, yaml_str, 0)?;
+    if safe {
+        safe::check_safety(&value)?;
+    }
+    merge::merge_value(&mut value, options, yaml_str, 0)?;
+    sops::decrypt_value(&mut value, options)?;
+    coerce_value(&mut value, options, yaml_str, 0)?;
+    redact_value(&mut value, options);
+    Ok(Parsed::Value(value))
+}
+
+/// Parse `text` as JSON if it is a single JSON object or array
+///
+/// Returns `None` (use the YAML parser) for anything else, including JSON
+/// that serde_json would read differently (see `reads_as_yaml`), and
+/// floats out of range, which serde_json rejects and serde_yaml reads as
+/// strings.
+pub fn parse_json(text: &str) -> Option<Value> {
+    let trimmed = text.trim_matches([' ', '\t', '\r', '\n']);
+    let bytes = trimmed.as_bytes();
+    match (bytes.first(), bytes.last()) {
+        (Some(b'{'), Some(b'}')) | (Some(b'['), Some(b']')) => {}
+        _ => return None,
+    }
+
+    // Number tokens outside strings (the text ends with `}` or `]`, so
+    // every token ends before it does)
+    let (mut in_string, mut escaped) = (false, false);
+    let mut number = None;
+    for (i, &byte) in bytes.iter().enumerate() {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        let numeric = byte.is_ascii_digit() || matches!(byte, b'-' | b'+' | b'.' | b'e' | b'E');
+        match number {
+            None if byte == b'-' || byte.is_ascii_digit() => number = Some(i),
+            Some(start) if !numeric => {
+                if !reads_as_yaml(&bytes[start..i]) {
+                    return None;
+                }
+                number = None;
+            }
+            _ => {}
+        }
+        in_string = byte == b'"';
+    }
+    serde_json::from_str(trimmed).ok()
+}
+
+/// Parse `yaml_str` with the composer rather than serde_yaml?
+///
+/// serde_yaml can't hold an integer beyond 64 bits: it rejects one that
+/// fits in 128 and turns a longer one into a float. The composer builds
+/// any size, so with `exact_ints` text with a run of 19 or more decimal
+/// digits (too many for an `i64`) goes to it.
+fn use_composer(yaml_str: &str, options: &LoadOptions) -> bool {
+    options.needs_composer()
+        || options.exact_ints
+            && yaml_str
+                .as_bytes()
+                .split(|byte| !byte.is_ascii_digit())
+                .any(|run| run.len() >= 19)
+}
+
+/// Reject a repeated mapping key as serde_yaml would, for a document only
+/// composed for its big integers
+///
+/// Keys compare by their resolved value (`1` and `0x1` are the same key);
+/// collections, aliases and custom-tagged keys aren't compared.
+fn check_unique_keys(node: &Node) -> Result<(), YAMLError> {
+    match &node.kind {
+        NodeKind::Sequence { items, .. } => items.iter().try_for_each(check_unique_keys),
+        NodeKind::Mapping { pairs, .. } => {
+            let mut seen = HashSet::new();
+            for (key, value) in pairs {
+                if let Some((identity, shown)) = key_identity(key) {
+                    if !seen.insert(identity) {
+                        return Err(YAMLError::parse(
+                            key.start.line + 1,
+                            key.start.column + 1,
+                            format!("duplicate entry with key {}", shown),
+                        ));
+                    }
+                }
+                check_unique_keys(key)?;
+                check_unique_keys(value)?;
+            }
+            Ok(())
+        }
+        NodeKind::Scalar { .. } | NodeKind::Alias { .. } => Ok(()),
+    }
+}
+
+/// What a scalar key is compared by, and how an error shows it
+fn key_identity(key: &Node) -> Option<(String, String)> {
+    let NodeKind::Scalar { value, .. } = &key.kind else {
+        return None;
+    };
+    let quoted = || (format!("str:{}", value), format!("{:?}", value));
+    match key.tag.as_deref() {
+        None if key.is_plain_untagged() => match resolve::resolve_core(value) {
+            Scalar::Str => Some(quoted()),
+            scalar => Some((format!("{:?}", scalar), value.clone())),
+        },
+        None | Some("!") | Some(resolve::TAG_STR) => Some(quoted()),
+        Some(_) => None,
+    }
+}
+
+/// Does serde_json read this JSON number as serde_yaml does?
+///
+/// Floats are, as both round correctly (serde_json's `float_roundtrip`
+/// feature). Integers too long for 64 bits aren't: serde_json turns them
+/// into floats and serde_yaml rejects them. Nor is `-0`, which serde_json
+/// reads as the float -0.0 and YAML as the integer 0.
+fn reads_as_yaml(number: &[u8]) -> bool {
+    if number.iter().any(|byte| matches!(byte, b'.' | b'e' | b'E')) {
+        return true;
+    }
+    let digits = number.strip_prefix(b"-").unwrap_or(number);
+    digits.len() <= 18 && number != b"-0"
+}
+
+/// Parse every document in a multi-document stream (no GIL needed)
+pub fn parse_documents(
+    yaml_str: &str,
+    options: &LoadOptions,
+    safe: bool,
+) -> Result<Vec<Parsed>, YAMLError> {
+    let start = Instant::now();
+    let parsed = parse_many(yaml_str, options, safe);
+    let documents = parsed.as_ref().ok().map(Vec::len);
+    metrics::record_parse(yaml_str.len(), start.elapsed(), documents);
+    parsed
+}
+
+fn parse_many(yaml_str: &str, options: &LoadOptions, safe: bool) -> Result<Vec<Parsed>, YAMLError> {
+    limits::check_size(yaml_str, options)?;
+    let Some(profile) = &options.profile else {
+        return parse_stream(yaml_str, options, safe);
+    };
+    // The stream collapses to the base document with the profile merged
+    // over it; the schema and redaction apply to that, not to each section
+    let sections = LoadOptions {
+        json_schema: None,
+        redact: Vec::new(),
+        ..options.clone()
+    };
+    let documents = parse_stream(yaml_str, &sections, safe)?;
+    let Some(mut merged) = profile::select(documents, profile)? else {
+        return Ok(Vec::new());
+    };
+    match &mut merged {
+        Parsed::Value(value) => {
+            coerce_value(value, options, yaml_str, 0)?;
+            redact_value(value, options);
+        }
+        Parsed::Composed(Some(document)) => {
+            coerce_document(document, options)?;
+            redact_document(document, options);
+        }
+        Parsed::Composed(None) => {}
+    }
+    Ok(vec![merged])
+}
+
+fn parse_stream(
+    yaml_str: &str,
+    options: &LoadOptions,
+    safe: bool,
+) -> Result<Vec<Parsed>, YAMLError> {
+    let yaml_str = &*markers::apply(yaml_str, options.document_markers)?;
+    if use_composer(yaml_str, options) {
+        let mut documents =
+            compose::compose_all(yaml_str).map_err(|err| tabs::explain(err, yaml_str))?;
+        for document in &mut documents {
+            if !options.needs_composer() {
+                check_unique_keys(&document.root)?;
+            }
+            prepare_document(document, options, safe)?;
+        }
+        return Ok(documents
+            .into_iter()
+            .map(|document| Parsed::Composed(Some(document)))
+            .collect());
+    }
+
+    if let Some(mut value) = parse_json(yaml_str) {
+        limits::check_value(&value, options, yaml_str, 0)?;
+        if safe {
+            safe::check_safety(&value)?;
+        }
+        sops::decrypt_value(&mut value, options)?;
+        coerce_value(&mut value, options, yaml_str, 0)?;
+        redact_value(&mut value, options);
+        return Ok(vec![Parsed::Value(value)]);
+    }
+
+    let mut documents = Vec::new();
+
+    // serde_yaml provides a Deserializer that can handle multiple documents
+    for (index, document) in serde_yaml::Deserializer::from_str(yaml_str).enumerate() {
+        let mut value: Value = serde::Deserialize::deserialize(document)
+            .map_err(|err| tabs::explain(YAMLError::from(err), yaml_str))?;
+        limits::check_value(&value, options, yaml_str, index)?;
+
+        // Check safety for each document
+        if safe {
+            safe::check_safety(&value)?;
+        }
+        merge::merge_value(&mut value, options, yaml_str, index)?;
+        sops::decrypt_value(&mut value, options)?;
+        coerce_value(&mut value, options, yaml_str, index)?;
+        redact_value(&mut value, options);
+
+        documents.push(Parsed::Value(value));
+    }
+
+    Ok(documents)
+}
+
+/// Check a composed document and apply the options that rewrite it
+/// (`sops_key=`, `schema=`, `redact=`)
+pub fn prepare_document(
+    document: &mut Document,
+    options: &LoadOptions,
+    safe: bool,
+) -> Result<(), YAMLError> {
+    limits::check_document(&document.root, options)?;
+    if safe {
+        safe::check_node_safety(&document.root)?;
+    }
+    sops::decrypt_document(document, options)?;
+    coerce_document(document, options)?;
+    redact_document(document, options);
+    Ok(())
+}
+
+/// Apply the `schema=` option to document `index` of `yaml_str`
+///
+/// A `Value` has no positions, so on failure the document is composed
+/// again to find where each violation is.
+fn coerce_value(
+    value: &mut Value,
+    options: &LoadOptions,
+    yaml_str: &str,
+    index: usize,
+) -> Result<(), YAMLError> {
+    let Some(schema) = &options.json_schema else {
+        return Ok(());
+    };
+    schema::coerce_value(value, schema).map_err(|err| match err {
+        YAMLError::Schema { mut violations } => {
+            let document = compose::compose_all(yaml_str)
+                .ok()
+                .and_then(|documents| documents.into_iter().nth(index));
+            if let Some(document) = document {
+                schema::locate(&mut violations, &document.root);
+            }
+            YAMLError::Schema { violations }
+        }
+        err => err,
+    })
+}
+
+/// Apply the `schema=` option to a composed document
+fn coerce_document(document: &mut Document, options: &LoadOptions) -> Result<(), YAMLError> {
+    match &options.json_schema {
+        Some(schema) => schema::coerce_node(&mut document.root, schema, options.schema()),
+        None => Ok(()),
+    }
+}
+
+/// Apply the `redact=` option (after coercion, so masks are never coerced)
+fn redact_value(value: &mut Value, options: &LoadOptions) {
+    for segments in &options.redact {
+        redact::redact_value(value, segments);
+    }
+}
+
+/// Apply the `redact=` option to a composed document
+fn redact_document(document: &mut Document, options: &LoadOptions) {
+    for segments in &options.redact {
+        redact::redact_node(&mut document.root, segments);
+    }
+}
+
+/// Parse every document, recording failures instead of stopping at the first
+///
+/// libyaml cannot resume after a syntax error, so when the stream as a whole
+/// fails it is split at `---` lines and each document is parsed on its own.
+/// The result has one entry per document, in order.
+pub fn parse_documents_collect(
+    yaml_str: &str,
+    options: &LoadOptions,
+    safe: bool,
+) -> Vec<Result<Parsed, YAMLError>> {
+    let parse = |text: &str| {
+        if safe {
+            safe::quick_safety_check(text)?;
+        }
+        parse_documents(text, options, safe)
+    };
+    // The limit is on the whole stream, not the documents it's split into
+    if let Err(err) = limits::check_size(yaml_str, options) {
+        return vec![Err(err)];
+    }
+    match parse(yaml_str) {
+        Ok(documents) => return documents.into_iter().map(Ok).collect(),
+        // With a profile the stream is merged into one document, which
+        // fails as a whole
+        Err(err) if options.profile.is_some() => return vec![Err(err)],
+        Err(_) => {}
+    }
+
+    let mut results = Vec::new();
+    for chunk in split_documents(yaml_str) {
+        match parse(&chunk) {
+            Ok(documents) => results.extend(documents.into_iter().map(Ok)),
+            Err(err) => results.push(Err(err)),
+        }
+    }
+    results
+}
+
+/// Split a stream at `---` lines for per-document error recovery
+///
+/// Each chunk is padded with the newlines before it so that error line
+/// numbers still refer to the original stream. Directives and comments
+/// before a `---` stay with the document they introduce.
+pub fn split_documents(yaml_str: &str) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut has_content = false;
+
+    for (line_count, line) in yaml_str.split_inclusive('\n').enumerate() {
+        let trimmed = line.trim_end();
+        let is_start = trimmed == "---" || line.starts_with("--- ") || line.starts_with("---\t");
+        if is_start && has_content {
+            chunks.push(std::mem::take(&mut current));
+            current.push_str(&"\n".repeat(line_count));
+            has_content = false;
+        }
+        let trivial = trimmed.trim_start().is_empty()
+            || trimmed.trim_start().starts_with('#')
+            || trimmed.starts_with('%');
+        has_content |= is_start || !trivial;
+        current.push_str(line);
+    }
+    if has_content {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// Attach the failing document index or file path to a collected error
+pub fn annotate_error(
+    py: Python,
+    err: PyErr,
+    document: Option<usize>,
+    path: Option<&Path>,
+) -> PyResult<PyObject> {
+    let value = err.value_bound(py);
+    value.setattr("document", document)?;
+    value.setattr("path", path)?;
+    Ok(value.clone().unbind().into_any())
+}
+
+/// Convert a parsed document to a Python object (requires GIL)
+pub fn to_python(py: Python, parsed: &Parsed, options: &LoadOptions) -> PyResult<PyObject> {
+    match parsed {
+        Parsed::Value(value) if options.sort_keys => yaml_to_python_sorted(py, value),
+        Parsed::Value(value) => yaml_to_python(py, value),
+        Parsed::Composed(document) => construct_document(py, document.as_ref(), options),
+    }
+}
 
 /// Parse a YAML string safely (no code execution)
 ///
@@ -18,6 +486,7 @@ use crate::types::yaml_to_python;
 ///
 /// # Arguments
 /// * `yaml_str` - YAML content as UTF-8 string
+/// * `options` - Load options
 ///
 /// # Returns
 /// * `PyObject` - Python object (dict, list, str, int, float, bool, None)
@@ -25,21 +494,40 @@ use crate::types::yaml_to_python;
 /// # Errors
 /// * Parse errors (syntax issues)
 /// * Unsafe tags (!!python/object, etc.)
-/// * UTF-8 decoding errors
-pub fn parse_safe(py: Python, yaml_str: &str) -> PyResult<PyObject> {
+/// * Decoding errors
+pub fn parse_safe(py: Python, yaml_str: &str, options: &LoadOptions) -> PyResult<PyObject> {
     // Step 1: Quick scan for unsafe patterns in raw string
     // This catches tags that serde_yaml might silently ignore
     safe::quick_safety_check(yaml_str)?;
 
-    // Step 2: Parse YAML string to serde_yaml::Value
+    // Step 2: Parse and check for unsafe tags in parsed structure
     // This is pure Rust - no Python interaction yet
-    let value: Value = serde_yaml::from_str(yaml_str).map_err(YAMLError::from)?;
+    let parsed = parse_document(yaml_str, options, true)?;
 
-    // Step 3: Check for unsafe tags in parsed structure
-    safe::check_safety(&value)?;
+    // Step 3: Convert to Python object
+    to_python(py, &parsed, options)
+}
 
-    // Step 4: Convert to Python object
-    yaml_to_python(py, &value)
+/// Parse a YAML string safely and record where each value came from
+///
+/// The data is loaded exactly like `parse_safe`; the source map comes from
+/// a second, composer-only pass over the same (marker-cleaned) input.
+///
+/// # Returns
+/// `(data, sourcemap)`, see `sourcemap::to_python` for the map's shape
+pub fn parse_safe_with_sourcemap(
+    py: Python,
+    yaml_str: &str,
+    options: &LoadOptions,
+) -> PyResult<(PyObject, PyObject)> {
+    let data = parse_safe(py, yaml_str, options)?;
+
+    let yaml_str = &*markers::apply(yaml_str, options.document_markers)?;
+    let spans = match compose::compose_one(yaml_str)? {
+        Some(document) => sourcemap::build(&document.root),
+        None => Vec::new(),
+    };
+    Ok((data, sourcemap::to_python(py, &spans)?))
 }
 
 /// Parse a YAML string without safety checks (DANGEROUS!)
@@ -49,13 +537,13 @@ pub fn parse_safe(py: Python, yaml_str: &str) -> PyResult<PyObject> {
 ///
 /// # Safety
 /// This can execute arbitrary Python code embedded in YAML
-pub fn parse_unsafe(py: Python, yaml_str: &str) -> PyResult<PyObject> {
+pub fn parse_unsafe(py: Python, yaml_str: &str, options: &LoadOptions) -> PyResult<PyObject> {
     // For now, same as safe_load but we'll add custom tag handlers later
     // TODO: Implement custom Python object deserialization
-    let value: Value = serde_yaml::from_str(yaml_str).map_err(YAMLError::from)?;
+    let parsed = parse_document(yaml_str, options, false)?;
 
     // Skip safety check for unsafe_load
-    yaml_to_python(py, &value)
+    to_python(py, &parsed, options)
 }
 
 /// Parse multiple YAML documents from a single string
@@ -69,49 +557,175 @@ pub fn parse_unsafe(py: Python, yaml_str: &str) -> PyResult<PyObject> {
 /// ---
 /// doc: 3
 /// ```
-pub fn parse_all(py: Python, yaml_str: &str) -> PyResult<Vec<PyObject>> {
+pub fn parse_all(py: Python, yaml_str: &str, options: &LoadOptions) -> PyResult<Vec<PyObject>> {
     // Quick scan for unsafe patterns in raw string first
     safe::quick_safety_check(yaml_str)?;
 
-    let mut documents = Vec::new();
+    parse_documents(yaml_str, options, true)?
+        .iter()
+        .map(|parsed| to_python(py, parsed, options))
+        .collect()
+}
 
-    // serde_yaml provides a Deserializer that can handle multiple documents
-    for document in serde_yaml::Deserializer::from_str(yaml_str) {
-        let value: Value = serde::Deserialize::deserialize(document).map_err(YAMLError::from)?;
+/// Parse multiple YAML documents, collecting failures (collect-errors mode)
+///
+/// # Returns
+/// * `(documents, errors)` - every document that loaded, and an exception
+///   object (with `line`, `column` and `document` set) for each that did not
+pub fn parse_all_collect(
+    py: Python,
+    yaml_str: &str,
+    options: &LoadOptions,
+    safe: bool,
+) -> PyResult<(Vec<PyObject>, Vec<PyObject>)> {
+    let mut documents = Vec::new();
+    let mut errors = Vec::new();
+    for (index, result) in parse_documents_collect(yaml_str, options, safe)
+        .into_iter()
+        .enumerate()
+    {
+        match result
+            .map_err(PyErr::from)
+            .and_then(|parsed| to_python(py, &parsed, options))
+        {
+            Ok(obj) => documents.push(obj),
+            Err(err) => errors.push(annotate_error(py, err, Some(index), None)?),
+        }
+    }
+    Ok((documents, errors))
+}
 
-        // Check safety for each document
-        safe::check_safety(&value)?;
+/// Where a document is in its stream: byte offsets of its start and end,
+/// and the 1-based line it starts on
+pub type Span = (usize, usize, usize);
 
-        let py_obj = yaml_to_python(py, &value)?;
-        documents.push(py_obj);
+/// Parse every document, each with its `Span` in `yaml_str`
+///
+/// Spans come from a composer pass over the same (marker-cleaned) input,
+/// mapped back to `yaml_str` if cleaning blanked any lines.
+///
+/// # Errors
+/// * `TypeError` with `collect_errors` or `profile`, which don't keep one
+///   result per document of the stream
+pub fn parse_all_with_spans(
+    py: Python,
+    yaml_str: &str,
+    options: &LoadOptions,
+    safe: bool,
+) -> PyResult<Vec<(PyObject, Span)>> {
+    if options.collect_errors || options.profile.is_some() {
+        return Err(PyTypeError::new_err(
+            "document_spans can't be combined with collect_errors or profile",
+        ));
     }
+    if safe {
+        safe::quick_safety_check(yaml_str)?;
+    }
+    let documents = parse_documents(yaml_str, options, safe)?;
+
+    let cleaned = markers::apply(yaml_str, options.document_markers)?;
+    let offset = |index: usize| match &cleaned {
+        Cow::Borrowed(_) => index,
+        Cow::Owned(cleaned) => original_offset(yaml_str, cleaned, index),
+    };
+    let spans = compose::compose_all(&cleaned)?.into_iter().map(|document| {
+        let start = offset(document.start.index);
+        (start, offset(document.end.index), document.start.line + 1)
+    });
+    documents
+        .iter()
+        .zip(spans)
+        .map(|(parsed, span)| Ok((to_python(py, parsed, options)?, span)))
+        .collect()
+}
 
-    Ok(documents)
+/// Byte offset in `original` of offset `index` in `cleaned`, a copy of it
+/// with some lines blanked (so line numbers still match)
+pub fn original_offset(original: &str, cleaned: &str, index: usize) -> usize {
+    let before = &cleaned[..index];
+    let line = before.matches('\n').count();
+    let column = index - before.rfind('\n').map_or(0, |newline| newline + 1);
+    let line_start: usize = original
+        .split_inclusive('\n')
+        .take(line)
+        .map(str::len)
+        .sum();
+    line_start + column
 }
 
 /// Parse multiple YAML documents without safety checks
-pub fn parse_all_unsafe(py: Python, yaml_str: &str) -> PyResult<Vec<PyObject>> {
-    let mut documents = Vec::new();
-
-    for document in serde_yaml::Deserializer::from_str(yaml_str) {
-        let value: Value = serde::Deserialize::deserialize(document).map_err(YAMLError::from)?;
-        let py_obj = yaml_to_python(py, &value)?;
-        documents.push(py_obj);
-    }
-
-    Ok(documents)
+pub fn parse_all_unsafe(
+    py: Python,
+    yaml_str: &str,
+    options: &LoadOptions,
+) -> PyResult<Vec<PyObject>> {
+    parse_documents(yaml_str, options, false)?
+        .iter()
+        .map(|parsed| to_python(py, parsed, options))
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    #[test]
+    fn test_parse_json_matches_yaml() {
+        for text in [
+            r#"{"name": "web", "ports": [80, 443], "ratio": 0.5, "tls": true, "x": null}"#,
+            "  [1, -2, 1e3, \"a\\\"b\\u00e9\", {}]\n",
+            r#"{"id": 123456789012345678}"#,
+            "[-0.0, 0, -10, 912068.5437784989, 9007199254740993.0, 1.5E-7]",
+        ] {
+            let json = parse_json(text).expect(text);
+            assert_eq!(json, serde_yaml::from_str::<Value>(text).unwrap());
+        }
+        // Left to the YAML parser
+        for text in [
+            "{a: 1}",
+            "{\"a\": 1} # comment",
+            r#"{"n": 18446744073709551616}"#,
+            r#"{"n": -1234567890123456789}"#,
+            "[1, -0]",
+            r#"{"a": [-0 ]}"#,
+            r#"{"f": 1e400}"#,
+            "{\"a\": 1}\n---\n{\"b\": 2}",
+            "key: {\"a\": 1}",
+        ] {
+            assert!(parse_json(text).is_none(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_parse_json_floats_match_yaml() {
+        // Shortest round-trip reprs (what Python's repr() gives) of
+        // pseudo-random doubles, written plain and with an exponent
+        let mut state: u64 = 0x9e3779b97f4a7c15;
+        let mut numbers = Vec::new();
+        while numbers.len() < 20000 {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let float = f64::from_bits(state >> 2);
+            if !float.is_finite() || float.abs() > 1e300 || float.abs() < 1e-300 {
+                continue;
+            }
+            numbers.push(format!("{:e}", float));
+            numbers.push(format!("{:?}", (float % 1e9) * 0.37));
+        }
+        for chunk in numbers.chunks(100) {
+            let text = format!("[{}]", chunk.join(", "));
+            let yaml: Value = serde_yaml::from_str(&text).unwrap();
+            assert_eq!(parse_json(&text), Some(yaml), "{}", text);
+        }
+    }
     use pyo3::types::{PyDict, PyList};
 
     #[test]
     fn test_parse_simple_dict() {
         Python::with_gil(|py| {
             let yaml = "key: value";
-            let result = parse_safe(py, yaml).unwrap();
+            let result = parse_safe(py, yaml, &LoadOptions::default()).unwrap();
 
             // Should be a dict
             assert!(result.bind(py).downcast::<PyDict>().is_ok());
@@ -122,7 +736,7 @@ mod tests {
     fn test_parse_list() {
         Python::with_gil(|py| {
             let yaml = "- item1\n- item2\n- item3";
-            let result = parse_safe(py, yaml).unwrap();
+            let result = parse_safe(py, yaml, &LoadOptions::default()).unwrap();
 
             // Should be a list
             assert!(result.bind(py).downcast::<PyList>().is_ok());
@@ -138,7 +752,7 @@ database:
   port: 5432
   enabled: true
 "#;
-            let result = parse_safe(py, yaml).unwrap();
+            let result = parse_safe(py, yaml, &LoadOptions::default()).unwrap();
             let dict = result.bind(py).downcast::<PyDict>().unwrap();
 
             // Check nested structure
@@ -152,7 +766,7 @@ database:
     fn test_parse_multiple_documents() {
         Python::with_gil(|py| {
             let yaml = "doc: 1\n---\ndoc: 2\n---\ndoc: 3";
-            let results = parse_all(py, yaml).unwrap();
+            let results = parse_all(py, yaml, &LoadOptions::default()).unwrap();
 
             assert_eq!(results.len(), 3);
         });
@@ -162,7 +776,7 @@ database:
     fn test_parse_empty_string() {
         Python::with_gil(|py| {
             let yaml = "";
-            let result = parse_safe(py, yaml).unwrap();
+            let result = parse_safe(py, yaml, &LoadOptions::default()).unwrap();
 
             // Empty YAML should return None
             assert!(result.is_none(py));
@@ -173,7 +787,7 @@ database:
     fn test_parse_null() {
         Python::with_gil(|py| {
             let yaml = "null";
-            let result = parse_safe(py, yaml).unwrap();
+            let result = parse_safe(py, yaml, &LoadOptions::default()).unwrap();
 
             assert!(result.is_none(py));
         });
@@ -188,7 +802,7 @@ negative: -17
 float: 3.14
 scientific: 1.23e-4
 "#;
-            let result = parse_safe(py, yaml).unwrap();
+            let result = parse_safe(py, yaml, &LoadOptions::default()).unwrap();
             let dict = result.bind(py).downcast::<PyDict>().unwrap();
 
             let int_val: i64 = dict
@@ -209,6 +823,31 @@ scientific: 1.23e-4
         });
     }
 
+    #[test]
+    fn test_parse_big_integers() {
+        Python::with_gil(|py| {
+            let yaml = "big: 18446744073709551616\nhuge: -1606938044258990275541962092341162602522202993782792835301376\n";
+            let options = LoadOptions {
+                exact_ints: true,
+                ..Default::default()
+            };
+            let result = parse_safe(py, yaml, &options).unwrap();
+            let expected = py
+                .eval_bound("{'big': 2**64, 'huge': -2**200}", None, None)
+                .unwrap();
+            assert!(result.bind(py).eq(expected).unwrap());
+            // Rust callers still get a `Value`, or serde_yaml's error
+            assert!(parse_document(yaml, &LoadOptions::default(), true).is_err());
+
+            let Err(err) = parse_document("a: 99999999999999999999\nb: 1\na: 2\n", &options, true)
+            else {
+                panic!("duplicate key accepted");
+            };
+            assert_eq!(err.location(), Some((3, 1)));
+            assert!(err.to_string().contains("duplicate entry with key \"a\""));
+        });
+    }
+
     #[test]
     fn test_parse_booleans() {
         Python::with_gil(|py| {
@@ -218,7 +857,12 @@ no_val: no
 true_val: true
 false_val: false
 "#;
-            let result = parse_safe(py, yaml).unwrap();
+            // yes/no are only booleans under YAML 1.1 (PyYAML) rules
+            let options = LoadOptions {
+                pyyaml_compat: true,
+                ..Default::default()
+            };
+            let result = parse_safe(py, yaml, &options).unwrap();
             let dict = result.bind(py).downcast::<PyDict>().unwrap();
 
             let yes: bool = dict
@@ -238,7 +882,7 @@ false_val: false
     fn test_parse_invalid_yaml() {
         Python::with_gil(|py| {
             let yaml = "key: : invalid";
-            let result = parse_safe(py, yaml);
+            let result = parse_safe(py, yaml, &LoadOptions::default());
 
             assert!(result.is_err());
         });
@@ -252,11 +896,117 @@ text: |
   This is a
   multiline string
 "#;
-            let result = parse_safe(py, yaml).unwrap();
+            let result = parse_safe(py, yaml, &LoadOptions::default()).unwrap();
             let dict = result.bind(py).downcast::<PyDict>().unwrap();
 
             let text: String = dict.get_item("text").unwrap().unwrap().extract().unwrap();
             assert!(text.contains("multiline"));
         });
     }
+
+    #[test]
+    fn test_parse_pyyaml_compat_rejects_core_python_tags() {
+        Python::with_gil(|py| {
+            let options = LoadOptions {
+                pyyaml_compat: true,
+                ..Default::default()
+            };
+            let yaml = "!!python/object/apply:os.system ['echo bad']";
+            assert!(parse_unsafe(py, yaml, &options).is_err());
+        });
+    }
+
+    #[test]
+    fn test_parse_all_pyyaml_compat() {
+        Python::with_gil(|py| {
+            let options = LoadOptions {
+                pyyaml_compat: true,
+                ..Default::default()
+            };
+            let results = parse_all(py, "a: on\n---\n---\nb: 1", &options).unwrap();
+            assert_eq!(results.len(), 3);
+            assert!(results[1].is_none(py));
+        });
+    }
+
+    #[test]
+    fn test_split_documents_keeps_line_numbers() {
+        let chunks = split_documents("%YAML 1.2\n---\na: 1\n---\nb: [\n--- 3\n");
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0], "%YAML 1.2\n---\na: 1\n");
+        assert_eq!(chunks[1], "\n\n\n---\nb: [\n");
+        assert_eq!(chunks[2], "\n\n\n\n\n--- 3\n");
+    }
+
+    #[test]
+    fn test_parse_all_collect() {
+        Python::with_gil(|py| {
+            let yaml = "a: 1\n---\nb: [unclosed\n---\nc: !!python/object:os.system x\n---\nd: 4\n";
+            let options = LoadOptions::default();
+            let (documents, errors) = parse_all_collect(py, yaml, &options, true).unwrap();
+            assert_eq!(documents.len(), 2);
+            assert_eq!(errors.len(), 2);
+
+            let first = errors[0].bind(py);
+            let document: usize = first.getattr("document").unwrap().extract().unwrap();
+            let line: usize = first.getattr("line").unwrap().extract().unwrap();
+            assert_eq!(document, 1);
+            assert!(line >= 3);
+            let document: usize = errors[1]
+                .bind(py)
+                .getattr("document")
+                .unwrap()
+                .extract()
+                .unwrap();
+            assert_eq!(document, 2);
+        });
+    }
+
+    #[test]
+    fn test_parse_lenient_document_markers() {
+        Python::with_gil(|py| {
+            let options = LoadOptions {
+                document_markers: Some(markers::MarkerMode::Lenient),
+                ..Default::default()
+            };
+            let yaml = "---\n---\na: 1\n...\n...\n\t\n";
+            assert!(parse_safe(py, yaml, &LoadOptions::default()).is_err());
+            let result = parse_safe(py, yaml, &options).unwrap();
+            assert!(result.bind(py).downcast::<PyDict>().is_ok());
+            assert_eq!(parse_all(py, yaml, &options).unwrap().len(), 1);
+        });
+    }
+
+    #[test]
+    fn test_parse_all_with_spans() {
+        Python::with_gil(|py| {
+            let yaml = "# head\na: 1\n---\nb: 2\n...\n--- c\n";
+            let options = LoadOptions::default();
+            let spans: Vec<Span> = parse_all_with_spans(py, yaml, &options, true)
+                .unwrap()
+                .into_iter()
+                .map(|(_, span)| span)
+                .collect();
+            assert_eq!(spans.len(), 3);
+            assert_eq!(&yaml[spans[0].0..spans[0].1], "a: 1\n");
+            assert_eq!(&yaml[spans[1].0..spans[1].1], "---\nb: 2\n...");
+            assert_eq!((spans[1].2, spans[2].2), (3, 6));
+
+            // Offsets still point into the original when lines were blanked
+            let yaml = "---\n---\na: 1\n";
+            let options = LoadOptions {
+                document_markers: Some(markers::MarkerMode::Lenient),
+                ..Default::default()
+            };
+            let documents = parse_all_with_spans(py, yaml, &options, true).unwrap();
+            let (start, _, line) = documents[0].1;
+            assert_eq!((&yaml[start..], line), ("---\na: 1\n", 2));
+
+            let options = LoadOptions {
+                collect_errors: true,
+                ..Default::default()
+            };
+            assert!(parse_all_with_spans(py, yaml, &options, true).is_err());
+        });
+    }
 }
@@ -0,0 +1,295 @@
+//! Profile selection (`profile=` option)
+//!
+//! Spring-style profiles keep the settings of every environment in one
+//! file. The base document can list them under a top-level `profiles:` key:
+//!
+//! ```yaml
+//! port: 8080
+//! profiles:
+//!   prod: {port: 80}
+//! ```
+//!
+//! or the stream can follow the base document with `---`-separated ones,
+//! each naming its profile (or a list of them) in a top-level `profile:`
+//! key. Later documents without a `profile:` key apply to every profile.
+//!
+//! Loading with `profile="prod"` returns the base with the selected
+//! sections deep-merged over it in document order: mappings are merged key
+//! by key, anything else (sequences included) is replaced. A profile the
+//! file has no section for just loads the base.
+
+use serde_yaml::Value;
+
+use crate::compose::{Document, Node, NodeKind};
+use crate::error::YAMLError;
+use crate::events::Mark;
+use crate::parser::Parsed;
+
+/// Top-level key naming the profiles of a `---`-separated document
+const PROFILE_KEY: &str = "profile";
+/// Top-level key holding the profile sections of the base document
+const PROFILES_KEY: &str = "profiles";
+
+fn invalid(message: &str, mark: Option<Mark>) -> YAMLError {
+    YAMLError::Profile {
+        message: message.to_string(),
+        mark,
+    }
+}
+
+/// A parsed document that profiles can be selected from
+trait Tree: Sized {
+    /// Remove a top-level key, returning its value
+    fn take(&mut self, key: &str) -> Option<Self>;
+    /// The profile names in a `profile:` value
+    fn names(&self) -> Result<Vec<String>, YAMLError>;
+    /// Check that a `profiles:` value is a mapping
+    fn check_sections(&self) -> Result<(), YAMLError>;
+    /// Deep-merge `overlay` over this tree
+    fn merge(&mut self, overlay: Self);
+}
+
+impl Tree for Value {
+    fn take(&mut self, key: &str) -> Option<Self> {
+        self.as_mapping_mut()?.remove(key)
+    }
+
+    fn names(&self) -> Result<Vec<String>, YAMLError> {
+        let name = |value: &Value| match value {
+            Value::String(name) => Ok(name.clone()),
+            _ => Err(invalid("profile must be a name or a list of names", None)),
+        };
+        match self {
+            Value::Sequence(items) => items.iter().map(name).collect(),
+            _ => Ok(vec![name(self)?]),
+        }
+    }
+
+    fn check_sections(&self) -> Result<(), YAMLError> {
+        match self {
+            Value::Mapping(_) => Ok(()),
+            _ => Err(invalid("profiles must be a mapping of profile names", None)),
+        }
+    }
+
+    fn merge(&mut self, overlay: Self) {
+        match (self, overlay) {
+            (Value::Mapping(base), Value::Mapping(overlay)) => {
+                for (key, value) in overlay {
+                    match base.get_mut(&key) {
+                        Some(existing) => existing.merge(value),
+                        None => {
+                            base.insert(key, value);
+                        }
+                    }
+                }
+            }
+            (base, overlay) => *base = overlay,
+        }
+    }
+}
+
+/// The text of an untagged scalar
+fn scalar(node: &Node) -> Option<&str> {
+    match &node.kind {
+        NodeKind::Scalar { value, .. } if node.tag.is_none() => Some(value),
+        _ => None,
+    }
+}
+
+impl Tree for Node {
+    fn take(&mut self, key: &str) -> Option<Self> {
+        let NodeKind::Mapping { pairs, .. } = &mut self.kind else {
+            return None;
+        };
+        let index = pairs.iter().position(|(k, _)| scalar(k) == Some(key))?;
+        Some(pairs.remove(index).1)
+    }
+
+    fn names(&self) -> Result<Vec<String>, YAMLError> {
+        let name = |node: &Node| {
+            scalar(node).map(str::to_string).ok_or_else(|| {
+                invalid(
+                    "profile must be a name or a list of names",
+                    Some(node.start),
+                )
+            })
+        };
+        match &self.kind {
+            NodeKind::Sequence { items, .. } => items.iter().map(name).collect(),
+            _ => Ok(vec![name(self)?]),
+        }
+    }
+
+    fn check_sections(&self) -> Result<(), YAMLError> {
+        match &self.kind {
+            NodeKind::Mapping { .. } => Ok(()),
+            _ => Err(invalid(
+                "profiles must be a mapping of profile names",
+                Some(self.start),
+            )),
+        }
+    }
+
+    fn merge(&mut self, overlay: Self) {
+        let (NodeKind::Mapping { pairs: base, .. }, NodeKind::Mapping { .. }) =
+            (&mut self.kind, &overlay.kind)
+        else {
+            *self = overlay;
+            return;
+        };
+        let NodeKind::Mapping { pairs, .. } = overlay.kind else {
+            unreachable!("checked above");
+        };
+        for (key, value) in pairs {
+            let existing = scalar(&key)
+                .and_then(|name| base.iter_mut().find(|(k, _)| scalar(k) == Some(name)));
+            match existing {
+                Some((_, existing)) => existing.merge(value),
+                None => base.push((key, value)),
+            }
+        }
+    }
+}
+
+/// Merge the sections for `profile` over the base document
+fn select_trees<T: Tree>(documents: Vec<T>, profile: &str) -> Result<Option<T>, YAMLError> {
+    let mut documents = documents.into_iter();
+    let Some(mut base) = documents.next() else {
+        return Ok(None);
+    };
+    if let Some(mut sections) = base.take(PROFILES_KEY) {
+        sections.check_sections()?;
+        if let Some(section) = sections.take(profile) {
+            base.merge(section);
+        }
+    }
+    for mut document in documents {
+        let selected = match document.take(PROFILE_KEY) {
+            Some(names) => names.names()?.iter().any(|name| name == profile),
+            None => true,
+        };
+        if selected {
+            base.merge(document);
+        }
+    }
+    Ok(Some(base))
+}
+
+/// Collapse a parsed stream into its base document merged with `profile`
+///
+/// Returns `None` for an empty stream.
+pub fn select(documents: Vec<Parsed>, profile: &str) -> Result<Option<Parsed>, YAMLError> {
+    let mut values = Vec::new();
+    let mut composed = Vec::new();
+    for parsed in documents {
+        match parsed {
+            Parsed::Value(value) => values.push(value),
+            Parsed::Composed(document) => composed.extend(document),
+        }
+    }
+    if !values.is_empty() {
+        return Ok(select_trees(values, profile)?.map(Parsed::Value));
+    }
+    let Some(first) = composed.first() else {
+        return Ok(None);
+    };
+    let (start, end) = (first.start, first.end);
+    let (explicit_start, explicit_end) = (first.explicit_start, first.explicit_end);
+    let roots = composed.into_iter().map(|document| document.root).collect();
+    Ok(select_trees(roots, profile)?.map(|root| {
+        Parsed::Composed(Some(Document {
+            root,
+            start,
+            end,
+            explicit_start,
+            explicit_end,
+        }))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compose;
+
+    const INLINE: &str = "db: {host: localhost, port: 5432}\nhosts: [a]\nprofiles:\n  prod:\n    db: {host: db.internal}\n    hosts: [b, c]\n";
+    const STREAM: &str = "db: {host: localhost, port: 5432}\n---\nprofile: [prod, staging]\ndb: {host: db.internal}\n---\nprofile: dev\ndebug: true\n---\nlogging: info\n";
+
+    fn values(yaml: &str, profile: &str) -> Value {
+        let documents = serde_yaml::Deserializer::from_str(yaml)
+            .map(|document| Parsed::Value(serde::Deserialize::deserialize(document).unwrap()))
+            .collect();
+        match select(documents, profile).unwrap() {
+            Some(Parsed::Value(value)) => value,
+            _ => panic!("expected a value"),
+        }
+    }
+
+    fn yaml(text: &str) -> Value {
+        serde_yaml::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn test_inline_profiles() {
+        assert_eq!(
+            values(INLINE, "prod"),
+            yaml("db: {host: db.internal, port: 5432}\nhosts: [b, c]")
+        );
+        assert_eq!(
+            values(INLINE, "dev"),
+            yaml("db: {host: localhost, port: 5432}\nhosts: [a]")
+        );
+    }
+
+    #[test]
+    fn test_profile_documents() {
+        assert_eq!(
+            values(STREAM, "staging"),
+            yaml("db: {host: db.internal, port: 5432}\nlogging: info")
+        );
+        assert_eq!(
+            values(STREAM, "dev"),
+            yaml("db: {host: localhost, port: 5432}\ndebug: true\nlogging: info")
+        );
+    }
+
+    #[test]
+    fn test_composed_matches_values() {
+        for source in [INLINE, STREAM] {
+            let documents = compose::compose_all(source)
+                .unwrap()
+                .into_iter()
+                .map(|document| Parsed::Composed(Some(document)))
+                .collect();
+            let Some(Parsed::Composed(Some(document))) = select(documents, "prod").unwrap() else {
+                panic!("expected a document");
+            };
+            let NodeKind::Mapping { pairs, .. } = &document.root.kind else {
+                panic!("expected a mapping");
+            };
+            let keys: Vec<_> = pairs.iter().filter_map(|(key, _)| scalar(key)).collect();
+            let expected: Vec<String> = match values(source, "prod") {
+                Value::Mapping(mapping) => mapping
+                    .keys()
+                    .map(|key| key.as_str().unwrap().to_string())
+                    .collect(),
+                _ => panic!("expected a mapping"),
+            };
+            assert_eq!(keys, expected);
+        }
+    }
+
+    #[test]
+    fn test_invalid_profiles() {
+        let documents = vec![Parsed::Value(yaml("profiles: [prod]"))];
+        assert!(select(documents, "prod").is_err());
+        let documents = compose::compose_all("a: 1\n---\nprofile: {x: 1}\n")
+            .unwrap()
+            .into_iter()
+            .map(|document| Parsed::Composed(Some(document)))
+            .collect();
+        let err = select(documents, "prod").err().unwrap();
+        assert_eq!(err.location(), Some((3, 10)));
+    }
+}
@@ -0,0 +1,151 @@
+//! Kubernetes resource quantities (`128Mi`, `500m`, `1.5G`, `2e3`)
+//!
+//! Follows `k8s.io/apimachinery/pkg/api/resource.Quantity`: a signed decimal
+//! number with an optional binary (`Ki`..`Ei`), decimal (`n`..`E`) or
+//! exponent (`e3`) suffix. Conversions round away from zero like
+//! `Quantity.Value()` / `Quantity.MilliValue()`.
+
+use pyo3::prelude::*;
+use regex::Regex;
+use std::sync::OnceLock;
+
+use crate::error::YAMLError;
+
+fn quantity_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(
+            r"^(?P<sign>[-+])?(?P<number>[0-9]+(?:\.[0-9]*)?|\.[0-9]+)(?P<suffix>[eE][-+]?[0-9]+|Ki|Mi|Gi|Ti|Pi|Ei|n|u|m|k|M|G|T|P|E)?$",
+        )
+        .expect("valid quantity regex")
+    })
+}
+
+/// Convert a quantity to an integer number of base units
+///
+/// With `milli`, the result is in thousandths (millicores for CPU).
+/// Returns `None` if the text is not a quantity or the result overflows.
+pub fn parse_quantity(value: &str, milli: bool) -> Option<i128> {
+    let caps = quantity_regex().captures(value)?;
+    let number = &caps["number"];
+    let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+    let mantissa: i128 = format!("{}{}", whole, fraction).parse().ok()?;
+
+    // value = mantissa * 2^binary * 10^decimal
+    let mut decimal = -(fraction.len() as i32);
+    let mut binary = 0u32;
+    match caps.name("suffix").map(|m| m.as_str()) {
+        None => {}
+        Some(suffix @ ("Ki" | "Mi" | "Gi" | "Ti" | "Pi" | "Ei")) => {
+            binary = 10 * (1 + "KMGTPE".find(&suffix[..1])? as u32);
+        }
+        Some("n") => decimal -= 9,
+        Some("u") => decimal -= 6,
+        Some("m") => decimal -= 3,
+        Some("k") => decimal += 3,
+        Some("M") => decimal += 6,
+        Some("G") => decimal += 9,
+        Some("T") => decimal += 12,
+        Some("P") => decimal += 15,
+        Some("E") => decimal += 18,
+        Some(exponent) => decimal += exponent[1..].parse::<i32>().ok()?,
+    }
+    if milli {
+        decimal += 3;
+    }
+
+    let mut magnitude = mantissa.checked_mul(1i128.checked_shl(binary)?)?;
+    if decimal >= 0 {
+        magnitude = magnitude.checked_mul(10i128.checked_pow(decimal as u32)?)?;
+    } else {
+        let divisor = 10i128.checked_pow(decimal.unsigned_abs());
+        magnitude = match divisor {
+            Some(divisor) => (magnitude + divisor - 1) / divisor,
+            // Smaller than any representable divisor: rounds up to one unit
+            None => i128::from(magnitude != 0),
+        };
+    }
+
+    Some(if caps.name("sign").map(|m| m.as_str()) == Some("-") {
+        -magnitude
+    } else {
+        magnitude
+    })
+}
+
+/// Parse a Kubernetes resource quantity
+///
+/// # Arguments
+/// * `value` - Quantity such as `"128Mi"`, `"500m"` or `"1.5"`
+/// * `milli` - Return thousandths of a unit (millicores) instead of units
+///
+/// # Returns
+/// Integer number of units (bytes for memory), rounded away from zero
+///
+/// # Example
+/// ```python
+/// import rustyyaml
+/// rustyyaml.parse_quantity("128Mi")            # 134217728
+/// rustyyaml.parse_quantity("500m", milli=True)  # 500
+/// ```
+#[pyfunction(name = "parse_quantity")]
+#[pyo3(signature = (value, milli=false))]
+pub fn py_parse_quantity(value: &str, milli: bool) -> PyResult<i128> {
+    parse_quantity(value, milli).ok_or_else(|| YAMLError::invalid_number(value.to_string()).into())
+}
+
+/// Is `key` the CPU resource (`cpu`, `requests.cpu`, `limits.cpu`)?
+///
+/// CPU is counted in millicores; every other resource in base units.
+pub fn is_cpu_resource(key: &str) -> bool {
+    key == "cpu" || key.ends_with(".cpu")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binary_suffixes() {
+        assert_eq!(parse_quantity("128Mi", false), Some(128 * 1024 * 1024));
+        assert_eq!(parse_quantity("1Ki", false), Some(1024));
+        assert_eq!(parse_quantity("1.5Gi", false), Some(1_610_612_736));
+        assert_eq!(parse_quantity("2Ei", false), Some(2 << 60));
+    }
+
+    #[test]
+    fn test_decimal_suffixes() {
+        assert_eq!(parse_quantity("1k", false), Some(1000));
+        assert_eq!(parse_quantity("1.5G", false), Some(1_500_000_000));
+        assert_eq!(parse_quantity("500m", true), Some(500));
+        assert_eq!(parse_quantity("2", true), Some(2000));
+        assert_eq!(parse_quantity("0.25", true), Some(250));
+        assert_eq!(parse_quantity("12e6", false), Some(12_000_000));
+        assert_eq!(parse_quantity("1E", false), Some(1_000_000_000_000_000_000));
+    }
+
+    #[test]
+    fn test_rounds_away_from_zero() {
+        assert_eq!(parse_quantity("100m", false), Some(1));
+        assert_eq!(parse_quantity("1n", true), Some(1));
+        assert_eq!(parse_quantity("-100m", false), Some(-1));
+        assert_eq!(parse_quantity("0m", false), Some(0));
+        assert_eq!(parse_quantity("1e-50", false), Some(1));
+    }
+
+    #[test]
+    fn test_invalid_quantities() {
+        assert_eq!(parse_quantity("", false), None);
+        assert_eq!(parse_quantity("Mi", false), None);
+        assert_eq!(parse_quantity("12 Mi", false), None);
+        assert_eq!(parse_quantity("12MB", false), None);
+        assert_eq!(parse_quantity("1e999", false), None);
+    }
+
+    #[test]
+    fn test_cpu_resource() {
+        assert!(is_cpu_resource("cpu"));
+        assert!(is_cpu_resource("requests.cpu"));
+        assert!(!is_cpu_resource("memory"));
+    }
+}
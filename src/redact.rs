@@ -0,0 +1,250 @@
+//! Mask values at key paths for safe logging and sharing
+//!
+//! Paths use the `grep` syntax (`data.*`, `env[*].value`). A matched value
+//! is replaced as a whole, so masking a mapping hides every key below it.
+//!
+//! YAML text is redacted by splicing the source: only the matched spans
+//! change, so comments, key order and formatting survive. Parsed documents
+//! (`Value` or `Node`) are redacted in place, which is how the `redact=`
+//! load option masks values inside the batch loaders before they ever
+//! become Python objects.
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyString;
+use serde_yaml::Value;
+
+use crate::compose::{self, Node, NodeKind};
+use crate::error::YAMLError;
+use crate::events::ScalarStyle;
+use crate::search::{self, Segment};
+use crate::types::{python_to_yaml, yaml_to_python};
+
+/// What every redacted value becomes
+pub const MASK: &str = "***";
+
+/// Parse a key path, or a list of key paths, passed from Python
+///
+/// # Errors
+/// * `TypeError` for anything but a string or a list of strings
+/// * `ValueError` naming the first malformed path
+pub fn extract_paths(paths: &Bound<'_, PyAny>) -> PyResult<Vec<Vec<Segment>>> {
+    let paths: Vec<String> = match paths.extract::<String>() {
+        Ok(path) => vec![path],
+        Err(_) => paths.extract()?,
+    };
+    parse_paths(&paths)
+}
+
+/// Parse key path patterns
+///
+/// # Errors
+/// * `ValueError` naming the first malformed pattern
+pub fn parse_paths(paths: &[String]) -> PyResult<Vec<Vec<Segment>>> {
+    paths
+        .iter()
+        .map(|path| {
+            search::parse_key_path(path)
+                .ok_or_else(|| PyValueError::new_err(format!("invalid key path '{}'", path)))
+        })
+        .collect()
+}
+
+/// Mask the values at `segments` in a parsed value
+pub fn redact_value(value: &mut Value, segments: &[Segment]) {
+    let value = match value {
+        Value::Tagged(tagged) => &mut tagged.value,
+        value => value,
+    };
+    let Some((segment, rest)) = segments.split_first() else {
+        *value = Value::String(MASK.to_string());
+        return;
+    };
+    match (segment, value) {
+        (Segment::Key(_) | Segment::AnyKey, Value::Mapping(map)) => {
+            for (key, child) in map.iter_mut() {
+                let Some(key) = search::key_text(key) else {
+                    continue;
+                };
+                if matches!(segment, Segment::Key(name) if *name != key) {
+                    continue;
+                }
+                redact_value(child, rest);
+            }
+        }
+        (Segment::Index(index), Value::Sequence(items)) => {
+            if let Some(child) = items.get_mut(*index) {
+                redact_value(child, rest);
+            }
+        }
+        (Segment::AnyIndex, Value::Sequence(items)) => {
+            for child in items {
+                redact_value(child, rest);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Mask the values at `segments` in a composed node tree
+///
+/// Masked nodes keep their anchor, so aliases of a secret are masked too.
+pub fn redact_node(node: &mut Node, segments: &[Segment]) {
+    let Some((segment, rest)) = segments.split_first() else {
+        node.tag = None;
+        node.kind = NodeKind::Scalar {
+            value: MASK.to_string(),
+            style: ScalarStyle::DoubleQuoted,
+        };
+        return;
+    };
+    match (segment, &mut node.kind) {
+        (Segment::Key(_) | Segment::AnyKey, NodeKind::Mapping { pairs, .. }) => {
+            for (key, value) in pairs {
+                let NodeKind::Scalar { value: name, .. } = &key.kind else {
+                    continue;
+                };
+                if matches!(segment, Segment::Key(wanted) if wanted != name) {
+                    continue;
+                }
+                redact_node(value, rest);
+            }
+        }
+        (Segment::Index(_) | Segment::AnyIndex, NodeKind::Sequence { items, .. }) => {
+            for (index, item) in items.iter_mut().enumerate() {
+                if matches!(segment, Segment::Index(wanted) if *wanted != index) {
+                    continue;
+                }
+                redact_node(item, rest);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Mask the values at `paths` in YAML text, leaving everything else as written
+///
+/// # Errors
+/// * Parse errors
+pub fn redact_text(yaml_str: &str, paths: &[Vec<Segment>]) -> Result<String, YAMLError> {
+    let mut spans = Vec::new();
+    for document in compose::compose_all(yaml_str)? {
+        for segments in paths {
+            for (_, node) in search::find_nodes(&document.root, segments) {
+                // Block scalars and collections end after their last line break
+                let end =
+                    node.start.index + yaml_str[node.start.index..node.end.index].trim_end().len();
+                spans.push((node.start.index, end, node.anchor.clone()));
+            }
+        }
+    }
+    spans.sort_by_key(|&(start, end, _)| (start, std::cmp::Reverse(end)));
+
+    let mut out = String::with_capacity(yaml_str.len());
+    let mut copied = 0;
+    for (start, end, anchor) in spans {
+        // Already inside a masked span (a parent was matched too)
+        if start < copied {
+            continue;
+        }
+        out.push_str(&yaml_str[copied..start]);
+        if let Some(anchor) = &anchor {
+            out.push('&');
+            out.push_str(anchor);
+            out.push(' ');
+        }
+        out.push('"');
+        out.push_str(MASK);
+        out.push('"');
+        copied = end;
+    }
+    out.push_str(&yaml_str[copied..]);
+    Ok(out)
+}
+
+/// Mask values at key paths
+///
+/// # Arguments
+/// * `doc_or_yaml` - Parsed data, or YAML text
+/// * `paths` - Key path or list of key paths in `grep` syntax
+///   (`spec.containers[*].env[*].value`, `data.*`)
+///
+/// # Returns
+/// For YAML text, the same text with each matched value replaced by
+/// `"***"` (comments and formatting are kept). For parsed data, a copy with
+/// the matched values replaced by `"***"`.
+///
+/// # Example
+/// ```python
+/// print(rustyyaml.redact(manifest_text, ["data.*", "stringData.*"]))
+/// ```
+#[pyfunction]
+pub fn redact(
+    py: Python,
+    doc_or_yaml: &Bound<'_, PyAny>,
+    paths: &Bound<'_, PyAny>,
+) -> PyResult<PyObject> {
+    let paths = extract_paths(paths)?;
+    if doc_or_yaml.is_instance_of::<PyString>() {
+        let yaml_str: String = doc_or_yaml.extract()?;
+        let redacted = py.allow_threads(|| redact_text(&yaml_str, &paths))?;
+        return Ok(redacted.into_py(py));
+    }
+    let mut value = python_to_yaml(doc_or_yaml)?;
+    for segments in &paths {
+        redact_value(&mut value, segments);
+    }
+    yaml_to_python(py, &value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(yaml: &str, paths: &[&str]) -> String {
+        let paths: Vec<String> = paths.iter().map(|p| p.to_string()).collect();
+        redact_text(yaml, &parse_paths(&paths).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_redact_text_keeps_formatting() {
+        let yaml =
+            "# creds\nuser: admin  # owner\npassword: hunter2\nenv:\n  - {name: A, value: x}\n";
+        assert_eq!(
+            text(yaml, &["password", "env[*].value"]),
+            "# creds\nuser: admin  # owner\npassword: \"***\"\nenv:\n  - {name: A, value: \"***\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_redact_text_blocks() {
+        let yaml = "data:\n  a: 1\n  b: 2\nkey: |\n  line one\n  line two\nafter: &s !!str x\n";
+        assert_eq!(
+            text(yaml, &["data", "data.a", "key", "after"]),
+            "data:\n  \"***\"\nkey: \"***\"\nafter: &s \"***\"\n"
+        );
+        let redacted = text(yaml, &["data", "key"]);
+        let value: Value = serde_yaml::from_str(&redacted).unwrap();
+        assert_eq!(value["data"], Value::String(MASK.to_string()));
+        assert_eq!(value["after"], Value::String("x".to_string()));
+    }
+
+    #[test]
+    fn test_redact_value_and_node() {
+        let mut value: Value = serde_yaml::from_str("a: {b: 1, c: [1, 2]}\nd: 2").unwrap();
+        redact_value(&mut value, &search::parse_key_path("a.c[1]").unwrap());
+        redact_value(&mut value, &search::parse_key_path("*.b").unwrap());
+        let expected: Value = serde_yaml::from_str("a: {b: '***', c: [1, '***']}\nd: 2").unwrap();
+        assert_eq!(value, expected);
+
+        let mut document = compose::compose_one("a: &x secret\nb: *x\n")
+            .unwrap()
+            .unwrap();
+        redact_node(&mut document.root, &search::parse_key_path("a").unwrap());
+        let NodeKind::Mapping { pairs, .. } = &document.root.kind else {
+            panic!("expected a mapping");
+        };
+        assert_eq!(pairs[0].1.anchor.as_deref(), Some("x"));
+        assert!(matches!(&pairs[0].1.kind, NodeKind::Scalar { value, .. } if value == MASK));
+    }
+}
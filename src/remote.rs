@@ -0,0 +1,288 @@
+//! Load YAML straight from object stores (S3, GCS, Azure Blob Storage)
+//!
+//! `load_object_store` is `load_directory` for a bucket prefix: it lists
+//! the `.yaml`/`.yml` objects under the prefix, downloads them with a
+//! bounded number of requests in flight, and parses them in parallel.
+//!
+//! Stores and credentials are configured by the `object_store` crate from
+//! the URL (`s3://bucket/prefix/`, `gs://...`, `az://container/...`,
+//! `file:///...`), the usual environment variables (`AWS_ACCESS_KEY_ID`,
+//! `AWS_REGION`, `GOOGLE_SERVICE_ACCOUNT`, `AZURE_STORAGE_ACCOUNT_NAME`,
+//! ...) and `storage_options`, which take precedence.
+
+use futures::{StreamExt, TryStreamExt};
+use object_store::path::Path as ObjectPath;
+use object_store::ObjectStore;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use url::Url;
+
+use crate::batch;
+use crate::encoding::{self, Decoded};
+use crate::error::YAMLError;
+use crate::filter::DocumentFilter;
+use crate::interrupt::{self, Cancel};
+use crate::options::LoadOptions;
+
+/// The error for failing to list or fetch `url`: `FileNotFound` if the
+/// store has no such object, `Io` otherwise
+fn store_error(url: &str, err: object_store::Error) -> YAMLError {
+    if let object_store::Error::NotFound { .. } = err {
+        return YAMLError::FileNotFound {
+            path: url.to_string(),
+        };
+    }
+    YAMLError::Io {
+        action: "read",
+        path: url.to_string(),
+        message: err.to_string(),
+    }
+}
+
+fn is_yaml(location: &ObjectPath) -> bool {
+    matches!(location.extension(), Some("yaml" | "yml"))
+}
+
+/// List the YAML objects under `prefix`, in path order
+///
+/// Without `recursive`, only objects directly under the prefix are listed.
+pub async fn list_yaml(
+    store: &dyn ObjectStore,
+    prefix: &ObjectPath,
+    recursive: bool,
+) -> object_store::Result<Vec<ObjectPath>> {
+    let mut locations: Vec<ObjectPath> = if recursive {
+        store
+            .list(Some(prefix))
+            .map_ok(|meta| meta.location)
+            .try_collect()
+            .await?
+    } else {
+        let listing = store.list_with_delimiter(Some(prefix)).await?;
+        listing
+            .objects
+            .into_iter()
+            .map(|meta| meta.location)
+            .collect()
+    };
+    locations.retain(is_yaml);
+    locations.sort();
+    Ok(locations)
+}
+
+/// Download `locations` with at most `max_concurrency` requests in flight
+///
+/// Results are in the order of `locations`. Once `cancel` is set the
+/// remaining objects are skipped (`None`).
+pub async fn fetch_all(
+    store: &dyn ObjectStore,
+    locations: &[ObjectPath],
+    max_concurrency: usize,
+    cancel: &Cancel,
+) -> Vec<Option<object_store::Result<Vec<u8>>>> {
+    futures::stream::iter(locations)
+        .map(|location| async move {
+            if cancel.is_set() {
+                return None;
+            }
+            let bytes = match store.get(location).await {
+                Ok(result) => result.bytes().await,
+                Err(err) => Err(err),
+            };
+            Some(bytes.map(Vec::from))
+        })
+        .buffered(max_concurrency.max(1))
+        .collect()
+        .await
+}
+
+/// `s3://bucket/` part of an object store URL, for naming results
+fn base_url(url: &Url) -> String {
+    match url.scheme() {
+        "file" => "file:///".to_string(),
+        scheme => format!("{}://{}/", scheme, url.host_str().unwrap_or_default()),
+    }
+}
+
+/// Load every YAML object under an object store prefix
+///
+/// # Arguments
+/// * `url` - Prefix URL: `s3://bucket/prefix/`, `gs://bucket/prefix/`,
+///   `az://container/prefix/`, or `file:///path/`
+/// * `recursive` - Include objects below nested prefixes
+/// * `transform`, `filter` - As for `load_directory`
+/// * `storage_options` - Store settings such as `aws_region`,
+///   `aws_endpoint` or `azure_storage_account_key`, overriding the
+///   environment
+/// * `max_concurrency` - Downloads in flight at once
+/// * `options` - Load options, applied to every object
+///
+/// # Returns
+/// List of `(object_url, data)` tuples in path order; `(results, errors)`
+/// with `collect_errors=True`
+///
+/// # Example
+/// ```python
+/// for url, manifest in rustyyaml.load_object_store("s3://deploys/prod/", recursive=True):
+///     print(url, manifest["kind"])
+/// ```
+#[pyfunction]
+#[pyo3(signature = (
+    url,
+    recursive=false,
+    transform=None,
+    filter=None,
+    storage_options=None,
+    max_concurrency=16,
+    **options
+))]
+#[allow(clippy::too_many_arguments)]
+pub fn load_object_store(
+    py: Python,
+    url: &str,
+    recursive: bool,
+    transform: Option<&Bound<'_, PyAny>>,
+    filter: Option<&Bound<'_, PyAny>>,
+    storage_options: Option<HashMap<String, String>>,
+    max_concurrency: usize,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<PyObject> {
+    let options = LoadOptions::from_kwargs(options)?;
+    let filter = filter.map(DocumentFilter::from_py).transpose()?;
+    let filter = filter.as_ref();
+    let parsed_url = Url::parse(url)
+        .map_err(|err| PyValueError::new_err(format!("invalid URL '{}': {}", url, err)))?;
+
+    // Configuration keys are the lower-cased variable names (aws_region)
+    let mut settings: Vec<(String, String)> = std::env::vars()
+        .map(|(key, value)| (key.to_ascii_lowercase(), value))
+        .collect();
+    settings.extend(storage_options.unwrap_or_default());
+    let (store, prefix) = object_store::parse_url_opts(&parsed_url, settings)
+        .map_err(|err| PyValueError::new_err(format!("unsupported store '{}': {}", url, err)))?;
+    let base = base_url(&parsed_url);
+
+    let loaded = interrupt::run(py, options.cancel.as_deref(), |cancel| {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|err| YAMLError::Io {
+                action: "read",
+                path: url.to_string(),
+                message: format!("can't start the I/O runtime: {}", err),
+            })?;
+        let (locations, contents) = runtime.block_on(async {
+            let locations = list_yaml(store.as_ref(), &prefix, recursive)
+                .await
+                .map_err(|err| store_error(url, err))?;
+            let contents = fetch_all(store.as_ref(), &locations, max_concurrency, cancel).await;
+            Ok::<_, YAMLError>((locations, contents))
+        })?;
+
+        // Objects skipped after cancelling are left out like rejected ones
+        let contents = locations
+            .iter()
+            .zip(contents)
+            .map(|(location, content)| {
+                let object_url = format!("{}{}", base, location);
+                let content = match content {
+                    None => Ok(Decoded::default()),
+                    Some(content) => content
+                        .map_err(|err| store_error(&object_url, err))
+                        .and_then(|bytes| encoding::decode_with(&bytes, options.encoding)),
+                };
+                (PathBuf::from(object_url), content)
+            })
+            .collect();
+        Ok::<_, YAMLError>(batch::parse_deduplicated(
+            contents, cancel, filter, &options, true,
+        ))
+    })??;
+
+    batch::convert_results(py, loaded, filter, transform, &options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use object_store::memory::InMemory;
+    use object_store::PutPayload;
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    fn store() -> InMemory {
+        let store = InMemory::new();
+        block_on(async {
+            for (path, content) in [
+                ("deploy/b.yaml", "name: b"),
+                ("deploy/a.yml", "name: a"),
+                ("deploy/notes.txt", "not yaml"),
+                ("deploy/nested/c.yaml", "name: c"),
+                ("other/d.yaml", "name: d"),
+            ] {
+                store
+                    .put(&ObjectPath::from(path), PutPayload::from(content))
+                    .await
+                    .unwrap();
+            }
+        });
+        store
+    }
+
+    #[test]
+    fn test_list_yaml() {
+        let store = store();
+        let prefix = ObjectPath::from("deploy");
+        let names = |recursive| -> Vec<String> {
+            block_on(list_yaml(&store, &prefix, recursive))
+                .unwrap()
+                .iter()
+                .map(|location| location.to_string())
+                .collect()
+        };
+        assert_eq!(names(false), ["deploy/a.yml", "deploy/b.yaml"]);
+        assert_eq!(
+            names(true),
+            ["deploy/a.yml", "deploy/b.yaml", "deploy/nested/c.yaml"]
+        );
+    }
+
+    #[test]
+    fn test_fetch_all() {
+        let store = store();
+        let locations = [
+            ObjectPath::from("deploy/b.yaml"),
+            ObjectPath::from("deploy/missing.yaml"),
+            ObjectPath::from("deploy/a.yml"),
+        ];
+        let cancel = Cancel::default();
+        let mut contents = block_on(fetch_all(&store, &locations, 2, &cancel));
+        assert_eq!(contents[0].as_ref().unwrap().as_ref().unwrap(), b"name: b");
+        assert_eq!(contents[2].as_ref().unwrap().as_ref().unwrap(), b"name: a");
+        let missing = contents[1].take().unwrap().unwrap_err();
+        assert!(matches!(
+            store_error("memory:///deploy/missing.yaml", missing),
+            YAMLError::FileNotFound { .. }
+        ));
+
+        cancel.set();
+        let contents = block_on(fetch_all(&store, &locations, 2, &cancel));
+        assert!(contents.iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn test_base_url() {
+        let url = |text| base_url(&Url::parse(text).unwrap());
+        assert_eq!(url("s3://deploys/prod/"), "s3://deploys/");
+        assert_eq!(url("file:///srv/configs/"), "file:///");
+    }
+}
@@ -0,0 +1,750 @@
+//! Implicit tag resolution for plain scalars
+//!
+//! Two schemas are supported:
+//! - `Core`: mirrors serde_yaml's YAML 1.2 rules, so composer-based loads
+//!   agree with the default `safe_load` path
+//! - `PyYaml`: the YAML 1.1 rules used by PyYAML's SafeLoader
+//!   (`yes`/`on` booleans, `0755` octals, sexagesimals, timestamps, `<<`)
+//!
+//! On top of either schema, opt-in resolvers (timestamps, durations, UUIDs,
+//! IP addresses) can claim plain scalars the schema left as strings.
+
+use regex::Regex;
+use std::net::IpAddr;
+use std::sync::OnceLock;
+
+/// Which set of implicit resolvers to apply to plain scalars
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Schema {
+    #[default]
+    Core,
+    PyYaml,
+}
+
+/// Core YAML tags
+pub const TAG_NULL: &str = "tag:yaml.org,2002:null";
+pub const TAG_BOOL: &str = "tag:yaml.org,2002:bool";
+pub const TAG_INT: &str = "tag:yaml.org,2002:int";
+pub const TAG_FLOAT: &str = "tag:yaml.org,2002:float";
+pub const TAG_STR: &str = "tag:yaml.org,2002:str";
+pub const TAG_BINARY: &str = "tag:yaml.org,2002:binary";
+pub const TAG_TIMESTAMP: &str = "tag:yaml.org,2002:timestamp";
+pub const TAG_MERGE: &str = "tag:yaml.org,2002:merge";
+pub const TAG_SEQ: &str = "tag:yaml.org,2002:seq";
+pub const TAG_MAP: &str = "tag:yaml.org,2002:map";
+pub const TAG_SET: &str = "tag:yaml.org,2002:set";
+pub const TAG_OMAP: &str = "tag:yaml.org,2002:omap";
+pub const TAG_PAIRS: &str = "tag:yaml.org,2002:pairs";
+
+/// An integer too large for i64 is kept as text and handed to Python's `int()`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Int {
+    Small(i64),
+    Big { text: String, radix: u32 },
+}
+
+/// A parsed YAML timestamp
+#[derive(Clone, Debug, PartialEq)]
+pub struct Timestamp {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+    /// `None` for a bare date (`2024-01-01`)
+    pub time: Option<Time>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Time {
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    pub microsecond: u32,
+    /// UTC offset in minutes; `None` for a naive datetime
+    pub offset_minutes: Option<i32>,
+}
+
+/// Which `ipaddress` constructor an IP scalar needs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IpKind {
+    /// `10.0.0.1`, `::1`
+    Address,
+    /// `10.0.0.0/8` (no host bits set)
+    Network,
+    /// `10.0.0.1/8` (an address together with its network)
+    Interface,
+}
+
+/// The result of resolving a scalar
+#[derive(Clone, Debug, PartialEq)]
+pub enum Scalar {
+    Null,
+    Bool(bool),
+    Int(Int),
+    Float(f64),
+    Str,
+    Timestamp(Timestamp),
+    /// A duration, as a whole number of microseconds
+    Duration(i64),
+    /// A canonical `8-4-4-4-12` hex UUID; the text is passed to `uuid.UUID`
+    Uuid,
+    /// An IP address or network; the text is passed to the `ipaddress` module
+    Ip(IpKind),
+    Merge,
+}
+
+/// Resolve an untagged plain scalar
+pub fn resolve_plain(value: &str, schema: Schema) -> Scalar {
+    match schema {
+        Schema::Core => resolve_core(value),
+        Schema::PyYaml => resolve_pyyaml(value),
+    }
+}
+
+/// Resolve a plain scalar that the schema left as a string as a timestamp
+///
+/// Used to add timestamp resolution on top of the `Core` schema.
+pub fn resolve_timestamp(value: &str) -> Option<Timestamp> {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    let regex =
+        REGEX.get_or_init(|| Regex::new(IMPLICIT_TIMESTAMP).expect("valid timestamp regex"));
+    if regex.is_match(value) {
+        parse_timestamp(value)
+    } else {
+        None
+    }
+}
+
+/// Resolve a scalar against an explicit tag (`!!int "42"`)
+///
+/// Returns `None` if the value does not fit the tag
+pub fn resolve_tagged(value: &str, tag: &str) -> Option<Scalar> {
+    match tag {
+        TAG_STR => Some(Scalar::Str),
+        TAG_NULL => Some(Scalar::Null),
+        TAG_BOOL => match resolve_pyyaml(value) {
+            Scalar::Bool(b) => Some(Scalar::Bool(b)),
+            _ => None,
+        },
+        TAG_INT => match resolve_core(value) {
+            Scalar::Int(i) => Some(Scalar::Int(i)),
+            _ => parse_int_pyyaml(value).map(Scalar::Int),
+        },
+        TAG_FLOAT => match resolve_pyyaml(value) {
+            Scalar::Float(f) => Some(Scalar::Float(f)),
+            Scalar::Int(Int::Small(i)) => Some(Scalar::Float(i as f64)),
+            _ => parse_float_pyyaml(value).map(Scalar::Float),
+        },
+        TAG_TIMESTAMP => parse_timestamp(value).map(Scalar::Timestamp),
+        _ => None,
+    }
+}
+
+/// Resolve a scalar with the YAML 1.2 core rules serde_yaml uses
+pub fn resolve_core(value: &str) -> Scalar {
+    match value {
+        "" | "~" | "null" | "Null" | "NULL" => return Scalar::Null,
+        "true" | "True" | "TRUE" => return Scalar::Bool(true),
+        "false" | "False" | "FALSE" => return Scalar::Bool(false),
+        _ => {}
+    }
+    if let Some(int) = parse_int_core(value) {
+        return Scalar::Int(int);
+    }
+    if !digits_but_not_number(value) {
+        if let Some(float) = parse_float_core(value) {
+            return Scalar::Float(float);
+        }
+    }
+    Scalar::Str
+}
+
+/// Same rules as serde_yaml's untagged scalar visitor, except that an integer
+/// beyond 128 bits, which it reads as a float, stays an integer
+fn parse_int_core(value: &str) -> Option<Int> {
+    let (negative, unsigned) = match value.as_bytes().first()? {
+        b'-' => (true, &value[1..]),
+        b'+' => (false, &value[1..]),
+        _ => (false, value),
+    };
+    if unsigned.starts_with(['+', '-']) {
+        return None;
+    }
+    let (radix, digits) = if let Some(rest) = unsigned.strip_prefix("0x") {
+        (16, rest)
+    } else if let Some(rest) = unsigned.strip_prefix("0o") {
+        (8, rest)
+    } else if let Some(rest) = unsigned.strip_prefix("0b") {
+        (2, rest)
+    } else {
+        if digits_but_not_number(value) {
+            return None;
+        }
+        (10, unsigned)
+    };
+    if digits.is_empty() || digits.starts_with(['+', '-']) {
+        return None;
+    }
+    let signed = match u128::from_str_radix(digits, radix) {
+        Ok(magnitude) if negative => 0i128.checked_sub_unsigned(magnitude),
+        Ok(magnitude) => i128::try_from(magnitude).ok(),
+        Err(_) => None,
+    };
+    match signed {
+        Some(signed) => Some(match i64::try_from(signed) {
+            Ok(small) => Int::Small(small),
+            Err(_) => Int::Big {
+                text: signed.to_string(),
+                radix: 10,
+            },
+        }),
+        None if digits.chars().all(|c| c.is_digit(radix)) => Some(Int::Big {
+            text: format!("{}{}", if negative { "-" } else { "" }, digits),
+            radix,
+        }),
+        None => None,
+    }
+}
+
+fn parse_float_core(value: &str) -> Option<f64> {
+    let unsigned = match value.strip_prefix('+') {
+        Some(rest) if rest.starts_with(['+', '-']) => return None,
+        Some(rest) => rest,
+        None => value,
+    };
+    match unsigned {
+        ".inf" | ".Inf" | ".INF" => return Some(f64::INFINITY),
+        _ => {}
+    }
+    match value {
+        "-.inf" | "-.Inf" | "-.INF" => return Some(f64::NEG_INFINITY),
+        ".nan" | ".NaN" | ".NAN" => return Some(f64::NAN),
+        _ => {}
+    }
+    unsigned.parse::<f64>().ok().filter(|f| f.is_finite())
+}
+
+/// Leading zero(s) followed by digits is a string in YAML 1.2
+fn digits_but_not_number(value: &str) -> bool {
+    let value = value.strip_prefix(['-', '+']).unwrap_or(value);
+    value.len() > 1 && value.starts_with('0') && value[1..].bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Plain scalars that implicitly resolve to `!!timestamp`
+///
+/// A bare date needs two-digit month and day; with a time they may be one digit.
+const IMPLICIT_TIMESTAMP: &str = r"^(?:[0-9][0-9][0-9][0-9]-[0-9][0-9]-[0-9][0-9]|[0-9][0-9][0-9][0-9]-[0-9][0-9]?-[0-9][0-9]?(?:[Tt]|[ \t]+)[0-9][0-9]?:[0-9][0-9]:[0-9][0-9](?:\.[0-9]*)?(?:[ \t]*(?:Z|[-+][0-9][0-9]?(?::[0-9][0-9])?))?)$";
+
+/// Builds the resolved scalar once a resolver regex matched
+type Builder = fn(&str) -> Option<Scalar>;
+
+/// PyYAML's implicit resolvers, in the order it tries them
+fn pyyaml_regexes() -> &'static [(Regex, Builder)] {
+    static REGEXES: OnceLock<Vec<(Regex, Builder)>> = OnceLock::new();
+    REGEXES.get_or_init(|| {
+        let table: [(&str, Builder); 6] = [
+            (
+                r"^(?:yes|Yes|YES|no|No|NO|true|True|TRUE|false|False|FALSE|on|On|ON|off|Off|OFF)$",
+                |v| {
+                    Some(Scalar::Bool(matches!(
+                        v.to_ascii_lowercase().as_str(),
+                        "yes" | "true" | "on"
+                    )))
+                },
+            ),
+            (
+                r"^(?:[-+]?(?:[0-9][0-9_]*)\.[0-9_]*(?:[eE][-+][0-9]+)?|\.[0-9][0-9_]*(?:[eE][-+][0-9]+)?|[-+]?[0-9][0-9_]*(?::[0-5]?[0-9])+\.[0-9_]*|[-+]?\.(?:inf|Inf|INF)|\.(?:nan|NaN|NAN))$",
+                |v| parse_float_pyyaml(v).map(Scalar::Float),
+            ),
+            (
+                r"^(?:[-+]?0b[0-1_]+|[-+]?0[0-7_]+|[-+]?(?:0|[1-9][0-9_]*)|[-+]?0x[0-9a-fA-F_]+|[-+]?[1-9][0-9_]*(?::[0-5]?[0-9])+)$",
+                |v| parse_int_pyyaml(v).map(Scalar::Int),
+            ),
+            (r"^(?:<<)$", |_| Some(Scalar::Merge)),
+            (r"^(?:~|null|Null|NULL|)$", |_| Some(Scalar::Null)),
+            (IMPLICIT_TIMESTAMP, |v| {
+                parse_timestamp(v).map(Scalar::Timestamp)
+            }),
+        ];
+        table
+            .into_iter()
+            .map(|(pattern, build)| (Regex::new(pattern).expect("valid resolver regex"), build))
+            .collect()
+    })
+}
+
+fn resolve_pyyaml(value: &str) -> Scalar {
+    for (regex, build) in pyyaml_regexes() {
+        if regex.is_match(value) {
+            if let Some(scalar) = build(value) {
+                return scalar;
+            }
+        }
+    }
+    Scalar::Str
+}
+
+/// Split off a sign the way PyYAML's constructors do
+fn split_sign(value: &str) -> (bool, &str) {
+    match value.as_bytes().first() {
+        Some(b'-') => (true, &value[1..]),
+        Some(b'+') => (false, &value[1..]),
+        _ => (false, value),
+    }
+}
+
+/// PyYAML's `construct_yaml_int`
+fn parse_int_pyyaml(value: &str) -> Option<Int> {
+    let cleaned = value.replace('_', "");
+    let (negative, unsigned) = split_sign(&cleaned);
+    if unsigned.is_empty() {
+        return None;
+    }
+    let (radix, digits) = if unsigned == "0" {
+        (10, "0")
+    } else if let Some(rest) = unsigned.strip_prefix("0b") {
+        (2, rest)
+    } else if let Some(rest) = unsigned.strip_prefix("0x") {
+        (16, rest)
+    } else if let Some(rest) = unsigned.strip_prefix('0') {
+        (8, rest)
+    } else if unsigned.contains(':') {
+        let mut total: i128 = 0;
+        for part in unsigned.split(':') {
+            total = total
+                .checked_mul(60)?
+                .checked_add(part.parse::<i128>().ok()?)?;
+        }
+        let total = if negative { -total } else { total };
+        return Some(match i64::try_from(total) {
+            Ok(small) => Int::Small(small),
+            Err(_) => Int::Big {
+                text: total.to_string(),
+                radix: 10,
+            },
+        });
+    } else {
+        (10, unsigned)
+    };
+    if digits.is_empty() {
+        return None;
+    }
+    match i64::from_str_radix(digits, radix) {
+        Ok(small) => Some(Int::Small(if negative { -small } else { small })),
+        Err(_) if digits.chars().all(|c| c.is_digit(radix)) => Some(Int::Big {
+            text: format!("{}{}", if negative { "-" } else { "" }, digits),
+            radix,
+        }),
+        Err(_) => None,
+    }
+}
+
+/// PyYAML's `construct_yaml_float`
+fn parse_float_pyyaml(value: &str) -> Option<f64> {
+    let cleaned = value.replace('_', "").to_ascii_lowercase();
+    let (negative, unsigned) = split_sign(&cleaned);
+    let sign = if negative { -1.0 } else { 1.0 };
+    if unsigned == ".inf" {
+        return Some(sign * f64::INFINITY);
+    }
+    if unsigned == ".nan" {
+        return Some(f64::NAN);
+    }
+    if unsigned.contains(':') {
+        let mut total = 0.0;
+        for part in unsigned.split(':') {
+            total = total * 60.0 + part.parse::<f64>().ok()?;
+        }
+        return Some(sign * total);
+    }
+    unsigned.parse::<f64>().ok().map(|f| sign * f)
+}
+
+fn timestamp_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(
+            r"^(?P<year>[0-9][0-9][0-9][0-9])-(?P<month>[0-9][0-9]?)-(?P<day>[0-9][0-9]?)(?:(?:[Tt]|[ \t]+)(?P<hour>[0-9][0-9]?):(?P<minute>[0-9][0-9]):(?P<second>[0-9][0-9])(?:\.(?P<fraction>[0-9]*))?(?:[ \t]*(?P<tz>Z|(?P<tz_sign>[-+])(?P<tz_hour>[0-9][0-9]?)(?::(?P<tz_minute>[0-9][0-9]))?))?)?$",
+        )
+        .expect("valid timestamp regex")
+    })
+}
+
+/// PyYAML's `construct_yaml_timestamp`
+pub fn parse_timestamp(value: &str) -> Option<Timestamp> {
+    let caps = timestamp_regex().captures(value)?;
+    let num = |name: &str| caps.name(name).and_then(|m| m.as_str().parse::<u32>().ok());
+
+    let time = match num("hour") {
+        None => None,
+        Some(hour) => {
+            let microsecond = caps
+                .name("fraction")
+                .map(|m| {
+                    let mut digits: String = m.as_str().chars().take(6).collect();
+                    while digits.len() < 6 {
+                        digits.push('0');
+                    }
+                    digits.parse::<u32>().unwrap_or(0)
+                })
+                .unwrap_or(0);
+            let offset_minutes = if let Some(sign) = caps.name("tz_sign") {
+                let minutes = num("tz_hour")? as i32 * 60 + num("tz_minute").unwrap_or(0) as i32;
+                Some(if sign.as_str() == "-" {
+                    -minutes
+                } else {
+                    minutes
+                })
+            } else if caps.name("tz").is_some() {
+                Some(0)
+            } else {
+                None
+            };
+            Some(Time {
+                hour,
+                minute: num("minute")?,
+                second: num("second")?,
+                microsecond,
+                offset_minutes,
+            })
+        }
+    };
+
+    Some(Timestamp {
+        year: num("year")? as i32,
+        month: num("month")?,
+        day: num("day")?,
+        time,
+    })
+}
+
+fn iso_duration_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(
+            r"^(?P<sign>[-+])?P(?:(?P<weeks>[0-9]+(?:[.,][0-9]+)?)W)?(?:(?P<days>[0-9]+(?:[.,][0-9]+)?)D)?(?:T(?:(?P<hours>[0-9]+(?:[.,][0-9]+)?)H)?(?:(?P<minutes>[0-9]+(?:[.,][0-9]+)?)M)?(?:(?P<seconds>[0-9]+(?:[.,][0-9]+)?)S)?)?$",
+        )
+        .expect("valid duration regex")
+    })
+}
+
+fn go_duration_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"^[-+]?(?:(?:[0-9]+(?:\.[0-9]*)?|\.[0-9]+)(?:ns|us|µs|ms|s|m|h))+$")
+            .expect("valid duration regex")
+    })
+}
+
+fn go_duration_part_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"(?P<number>[0-9]+(?:\.[0-9]*)?|\.[0-9]+)(?P<unit>ns|us|µs|ms|s|m|h)")
+            .expect("valid duration regex")
+    })
+}
+
+const NANOS_PER_SECOND: i128 = 1_000_000_000;
+
+/// `number * unit_nanos`, exact for up to nine fractional digits
+fn scaled_nanos(number: &str, unit_nanos: i128) -> Option<i128> {
+    let number = number.replace(',', ".");
+    let (whole, fraction) = number.split_once('.').unwrap_or((&number, ""));
+    let whole: i128 = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().ok()?
+    };
+    let mut total = whole.checked_mul(unit_nanos)?;
+    let fraction = &fraction[..fraction.len().min(9)];
+    if !fraction.is_empty() {
+        let digits: i128 = fraction.parse().ok()?;
+        total = total.checked_add(digits * unit_nanos / 10i128.pow(fraction.len() as u32))?;
+    }
+    Some(total)
+}
+
+/// Parse an ISO-8601 (`P1DT2H`, `PT30S`) or Go-style (`5m30s`, `1.5h`) duration
+///
+/// Returns the duration in microseconds. ISO years and months have no fixed
+/// length, so they are not accepted.
+pub fn parse_duration(value: &str) -> Option<i64> {
+    let (negative, nanos) = if let Some(caps) = iso_duration_regex().captures(value) {
+        let units = [
+            ("weeks", 7 * 24 * 3600),
+            ("days", 24 * 3600),
+            ("hours", 3600),
+            ("minutes", 60),
+            ("seconds", 1),
+        ];
+        let mut total: i128 = 0;
+        let mut any = false;
+        for (name, seconds) in units {
+            if let Some(m) = caps.name(name) {
+                total = total.checked_add(scaled_nanos(m.as_str(), seconds * NANOS_PER_SECOND)?)?;
+                any = true;
+            }
+        }
+        // "P" and "PT" on their own are not durations
+        if !any || value.ends_with('T') {
+            return None;
+        }
+        (caps.name("sign").map(|m| m.as_str()) == Some("-"), total)
+    } else if go_duration_regex().is_match(value) {
+        let mut total: i128 = 0;
+        for caps in go_duration_part_regex().captures_iter(value) {
+            let unit = match &caps["unit"] {
+                "h" => 3600 * NANOS_PER_SECOND,
+                "m" => 60 * NANOS_PER_SECOND,
+                "s" => NANOS_PER_SECOND,
+                "ms" => 1_000_000,
+                "us" | "µs" => 1_000,
+                _ => 1,
+            };
+            total = total.checked_add(scaled_nanos(&caps["number"], unit)?)?;
+        }
+        (value.starts_with('-'), total)
+    } else {
+        return None;
+    };
+
+    // timedelta has microsecond resolution; round half away from zero
+    let micros = i64::try_from((nanos + 500) / 1000).ok()?;
+    Some(if negative { -micros } else { micros })
+}
+
+/// Write a duration of `micros` microseconds Go-style, as `parse_duration`
+/// reads it back (`1h30m0s`, `-0.25s`, `0s`)
+pub fn format_duration(micros: i128) -> String {
+    let sign = if micros < 0 { "-" } else { "" };
+    let micros = micros.unsigned_abs();
+    let (hours, minutes) = (micros / 3_600_000_000, micros / 60_000_000 % 60);
+    let (seconds, fraction) = (micros / 1_000_000 % 60, micros % 1_000_000);
+    let mut text = sign.to_string();
+    if hours > 0 {
+        text.push_str(&format!("{}h", hours));
+    }
+    if hours > 0 || minutes > 0 {
+        text.push_str(&format!("{}m", minutes));
+    }
+    text.push_str(&seconds.to_string());
+    if fraction > 0 {
+        let fraction = format!("{:06}", fraction);
+        text.push('.');
+        text.push_str(fraction.trim_end_matches('0'));
+    }
+    text.push('s');
+    text
+}
+
+/// Does the scalar look like a UUID (`123e4567-e89b-12d3-a456-426614174000`)?
+pub fn is_uuid(value: &str) -> bool {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX
+        .get_or_init(|| {
+            Regex::new(
+                r"^[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}$",
+            )
+            .expect("valid uuid regex")
+        })
+        .is_match(value)
+}
+
+/// Classify an IPv4/IPv6 address or CIDR network
+pub fn resolve_ip(value: &str) -> Option<IpKind> {
+    let Some((address, prefix)) = value.split_once('/') else {
+        return value.parse::<IpAddr>().ok().map(|_| IpKind::Address);
+    };
+    if prefix.is_empty() || !prefix.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let prefix: u32 = prefix.parse().ok()?;
+    let host_bits = match address.parse::<IpAddr>().ok()? {
+        IpAddr::V4(v4) if prefix <= 32 => u32::from(v4)
+            .checked_shl(prefix)
+            .is_some_and(|bits| bits != 0),
+        IpAddr::V6(v6) if prefix <= 128 => u128::from(v6)
+            .checked_shl(prefix)
+            .is_some_and(|bits| bits != 0),
+        _ => return None,
+    };
+    Some(if host_bits {
+        IpKind::Interface
+    } else {
+        IpKind::Network
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_core_matches_serde_yaml() {
+        assert_eq!(resolve_plain("yes", Schema::Core), Scalar::Str);
+        assert_eq!(resolve_plain("True", Schema::Core), Scalar::Bool(true));
+        assert_eq!(resolve_plain("0755", Schema::Core), Scalar::Str);
+        assert_eq!(
+            resolve_plain("0x1F", Schema::Core),
+            Scalar::Int(Int::Small(31))
+        );
+        assert_eq!(resolve_plain("1e3", Schema::Core), Scalar::Float(1000.0));
+        assert_eq!(resolve_plain("2024-01-01", Schema::Core), Scalar::Str);
+        // Beyond 128 bits serde_yaml reads a float, but an int is kept
+        assert_eq!(
+            resolve_plain("-0x100000000000000000000000000000000", Schema::Core),
+            Scalar::Int(Int::Big {
+                text: "-100000000000000000000000000000000".to_string(),
+                radix: 16
+            })
+        );
+    }
+
+    #[test]
+    fn test_pyyaml_booleans() {
+        for v in ["yes", "Yes", "on", "ON", "true"] {
+            assert_eq!(
+                resolve_plain(v, Schema::PyYaml),
+                Scalar::Bool(true),
+                "{}",
+                v
+            );
+        }
+        for v in ["no", "Off", "FALSE"] {
+            assert_eq!(
+                resolve_plain(v, Schema::PyYaml),
+                Scalar::Bool(false),
+                "{}",
+                v
+            );
+        }
+        assert_eq!(resolve_plain("y", Schema::PyYaml), Scalar::Str);
+    }
+
+    #[test]
+    fn test_pyyaml_integers() {
+        let int = |v| match resolve_plain(v, Schema::PyYaml) {
+            Scalar::Int(Int::Small(i)) => i,
+            other => panic!("{} resolved to {:?}", v, other),
+        };
+        assert_eq!(int("0755"), 493);
+        assert_eq!(int("0b101"), 5);
+        assert_eq!(int("-0x1F"), -31);
+        assert_eq!(int("1_000"), 1000);
+        assert_eq!(int("190:20:30"), 685230);
+    }
+
+    #[test]
+    fn test_pyyaml_floats() {
+        assert_eq!(resolve_plain("1.5", Schema::PyYaml), Scalar::Float(1.5));
+        assert_eq!(
+            resolve_plain("1.0e+3", Schema::PyYaml),
+            Scalar::Float(1000.0)
+        );
+        // PyYAML requires a dot and a signed exponent
+        assert_eq!(resolve_plain("1e3", Schema::PyYaml), Scalar::Str);
+        assert_eq!(
+            resolve_plain("-.inf", Schema::PyYaml),
+            Scalar::Float(f64::NEG_INFINITY)
+        );
+    }
+
+    #[test]
+    fn test_big_integers() {
+        assert!(matches!(
+            resolve_plain("123456789012345678901234567890", Schema::PyYaml),
+            Scalar::Int(Int::Big { radix: 10, .. })
+        ));
+    }
+
+    #[test]
+    fn test_timestamps() {
+        let ts = parse_timestamp("2024-01-01").unwrap();
+        assert_eq!((ts.year, ts.month, ts.day), (2024, 1, 1));
+        assert!(ts.time.is_none());
+
+        let ts = parse_timestamp("2001-12-14t21:59:43.10-05:00").unwrap();
+        let time = ts.time.unwrap();
+        assert_eq!(time.microsecond, 100000);
+        assert_eq!(time.offset_minutes, Some(-300));
+
+        let ts = parse_timestamp("2024-01-01T10:00:00Z").unwrap();
+        assert_eq!(ts.time.unwrap().offset_minutes, Some(0));
+        // Single-digit months only resolve when a time is present
+        assert_eq!(resolve_plain("2024-1-1", Schema::PyYaml), Scalar::Str);
+        assert!(resolve_timestamp("2024-1-1").is_none());
+        assert!(resolve_timestamp("2024-1-1 1:02:03").is_some());
+    }
+
+    #[test]
+    fn test_iso_durations() {
+        assert_eq!(parse_duration("PT30S"), Some(30_000_000));
+        assert_eq!(parse_duration("P1DT2H"), Some(26 * 3_600_000_000));
+        assert_eq!(parse_duration("P1W"), Some(7 * 86_400_000_000));
+        assert_eq!(parse_duration("PT0.5S"), Some(500_000));
+        assert_eq!(parse_duration("-PT1M"), Some(-60_000_000));
+        assert_eq!(parse_duration("P"), None);
+        assert_eq!(parse_duration("PT"), None);
+        assert_eq!(parse_duration("P1Y"), None);
+    }
+
+    #[test]
+    fn test_go_durations() {
+        assert_eq!(parse_duration("5m30s"), Some(330_000_000));
+        assert_eq!(parse_duration("1.5h"), Some(5_400_000_000));
+        assert_eq!(parse_duration("300ms"), Some(300_000));
+        assert_eq!(parse_duration("-2us"), Some(-2));
+        assert_eq!(parse_duration("1500ns"), Some(2));
+        assert_eq!(parse_duration("5"), None);
+        assert_eq!(parse_duration("5x"), None);
+        assert_eq!(parse_duration("ms"), None);
+    }
+
+    #[test]
+    fn test_format_duration() {
+        for (micros, text) in [
+            (0, "0s"),
+            (250_000, "0.25s"),
+            (-1, "-0.000001s"),
+            (90 * 60_000_000, "1h30m0s"),
+            (61_500_000, "1m1.5s"),
+            (-26 * 3_600_000_000, "-26h0m0s"),
+        ] {
+            assert_eq!(format_duration(micros), text);
+            assert_eq!(parse_duration(text), Some(micros as i64));
+        }
+    }
+
+    #[test]
+    fn test_uuids() {
+        assert!(is_uuid("123e4567-e89b-12d3-a456-426614174000"));
+        assert!(is_uuid("123E4567-E89B-12D3-A456-426614174000"));
+        assert!(!is_uuid("123e4567e89b12d3a456426614174000"));
+        assert!(!is_uuid("123e4567-e89b-12d3-a456-42661417400g"));
+    }
+
+    #[test]
+    fn test_ip_addresses() {
+        assert_eq!(resolve_ip("10.0.0.1"), Some(IpKind::Address));
+        assert_eq!(resolve_ip("::1"), Some(IpKind::Address));
+        assert_eq!(resolve_ip("10.0.0.0/8"), Some(IpKind::Network));
+        assert_eq!(resolve_ip("10.0.0.1/8"), Some(IpKind::Interface));
+        assert_eq!(resolve_ip("0.0.0.0/0"), Some(IpKind::Network));
+        assert_eq!(resolve_ip("10.0.0.1/32"), Some(IpKind::Network));
+        assert_eq!(resolve_ip("2001:db8::/32"), Some(IpKind::Network));
+        assert_eq!(resolve_ip("10.0.0.0/33"), None);
+        assert_eq!(resolve_ip("10.0.0.0/"), None);
+        assert_eq!(resolve_ip("10.0.0"), None);
+        assert_eq!(resolve_ip("hello"), None);
+    }
+
+    #[test]
+    fn test_explicit_tags() {
+        assert_eq!(
+            resolve_tagged("42", TAG_INT),
+            Some(Scalar::Int(Int::Small(42)))
+        );
+        assert_eq!(resolve_tagged("1", TAG_FLOAT), Some(Scalar::Float(1.0)));
+        assert_eq!(resolve_tagged("abc", TAG_INT), None);
+        assert_eq!(resolve_tagged("123", TAG_STR), Some(Scalar::Str));
+    }
+}
@@ -0,0 +1,1090 @@
+//! Round-trip loading and dumping (`load_rt`, `dump_rt`)
+//!
+//! `load_rt` returns its document as a `rustyyaml.roundtrip.CommentedMap`
+//! or `CommentedSeq`: a dict or list that also remembers the text it was
+//! loaded from and the options it was loaded with. `dump_rt` composes that
+//! text again, compares each node with the data now in its place and
+//! splices only what changed into the text (the approach `redact` takes),
+//! so everything else - comments, blank lines, quoting, key order,
+//! indentation, anchors - is written exactly as it was read:
+//!
+//! * a changed scalar is rewritten in place, keeping its quote style
+//! * a mapping entry or sequence item that's gone loses its lines
+//! * new entries and items are appended after the last one, at its
+//!   indentation
+//! * a flow collection whose keys or length changed is rewritten in flow
+//!   style
+//!
+//! Values are compared strictly (`1`, `1.0` and `True` differ), since each
+//! is written differently. Existing keys keep their place if the dict was
+//! reordered.
+//!
+//! `update_file` does a whole load-edit-dump for one value at a key path,
+//! writing the file back in place.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::sync::GILOnceCell;
+use pyo3::types::{PyBool, PyDict, PyFloat, PyInt, PyList, PyModule, PyString, PyTuple};
+use serde_yaml::{Mapping, Value};
+
+use crate::batch;
+use crate::compose::{self, Node, NodeKind};
+use crate::compression::Compression;
+use crate::construct;
+use crate::dumper::{self, DumpOptions};
+use crate::encoding::{self, Decoding, Encoding};
+use crate::events::ScalarStyle;
+use crate::limits;
+use crate::options::LoadOptions;
+use crate::search::{self, Segment};
+use crate::sourcemap;
+use crate::tabs;
+use crate::types;
+
+/// Nesting beyond which data is compared no further (it is cyclic, and
+/// can't be dumped anyway)
+const MAX_COMPARE_DEPTH: usize = 1000;
+
+fn roundtrip_module(py: Python<'_>) -> PyResult<&Bound<'_, PyModule>> {
+    static ROUNDTRIP: GILOnceCell<Py<PyModule>> = GILOnceCell::new();
+    ROUNDTRIP
+        .get_or_try_init(py, || {
+            py.import_bound("rustyyaml.roundtrip").map(Bound::unbind)
+        })
+        .map(|module| module.bind(py))
+}
+
+/// Reject options the round trip can't honour: those that rewrite the
+/// source or the data after construction, or change the container types
+///
+/// # Errors
+/// * `TypeError` naming the option
+pub fn check_options(options: &LoadOptions) -> PyResult<()> {
+    let unsupported = [
+        ("document_markers", options.document_markers.is_some()),
+        ("marked", options.marked),
+        ("dict_class", options.dict_class.is_some()),
+        ("schema", options.json_schema.is_some()),
+        ("redact", !options.redact.is_empty()),
+        ("sops_key", options.sops_key.is_some()),
+        ("profile", options.profile.is_some()),
+    ];
+    match unsupported.iter().find(|(_, set)| *set) {
+        Some((name, _)) => Err(PyTypeError::new_err(format!(
+            "{} is not supported by the round-trip functions",
+            name
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Whether `a` and `b` are equal and of the same types throughout
+fn same(a: &Bound<'_, PyAny>, b: &Bound<'_, PyAny>, depth: usize) -> PyResult<bool> {
+    if a.is(b) {
+        return Ok(true);
+    }
+    if depth > MAX_COMPARE_DEPTH {
+        return Ok(false);
+    }
+    if let (Ok(a), Ok(b)) = (a.downcast::<PyDict>(), b.downcast::<PyDict>()) {
+        if a.len() != b.len() {
+            return Ok(false);
+        }
+        for (key, value) in a.iter() {
+            match b.get_item(&key)? {
+                Some(other) if same(&value, &other, depth + 1)? => {}
+                _ => return Ok(false),
+            }
+        }
+        return Ok(true);
+    }
+    let items = |obj: &Bound<'_, PyAny>| -> Option<Vec<PyObject>> {
+        if let Ok(list) = obj.downcast::<PyList>() {
+            Some(list.iter().map(Bound::unbind).collect())
+        } else {
+            obj.downcast::<PyTuple>()
+                .ok()
+                .map(|tuple| tuple.iter().map(Bound::unbind).collect())
+        }
+    };
+    if !a.get_type().is(&b.get_type()) {
+        return Ok(false);
+    }
+    if let (Some(a_items), Some(b_items)) = (items(a), items(b)) {
+        if a_items.len() != b_items.len() {
+            return Ok(false);
+        }
+        let py = a.py();
+        for (x, y) in a_items.iter().zip(&b_items) {
+            if !same(x.bind(py), y.bind(py), depth + 1)? {
+                return Ok(false);
+            }
+        }
+        return Ok(true);
+    }
+    if let (Ok(x), Ok(y)) = (a.downcast::<PyFloat>(), b.downcast::<PyFloat>()) {
+        if x.value().is_nan() && y.value().is_nan() {
+            return Ok(true);
+        }
+    }
+    a.eq(b)
+}
+
+/// Start of the line `pos` is on
+fn line_start(source: &str, pos: usize) -> usize {
+    source[..pos].rfind('\n').map_or(0, |newline| newline + 1)
+}
+
+/// Start of the line after the one `pos` is on (or the end of the text)
+fn line_end(source: &str, pos: usize) -> usize {
+    source[pos..]
+        .find('\n')
+        .map_or(source.len(), |newline| pos + newline + 1)
+}
+
+/// End of the last thing written for `node`, before any trailing comment,
+/// blank line or the token after it (which block nodes' end marks include)
+fn content_end(node: &Node, source: &str) -> usize {
+    match &node.kind {
+        NodeKind::Mapping { pairs, flow: false } => pairs
+            .last()
+            .map_or(node.end.index, |(_, value)| content_end(value, source)),
+        NodeKind::Sequence { items, flow: false } => items
+            .last()
+            .map_or(node.end.index, |item| content_end(item, source)),
+        NodeKind::Scalar {
+            style: ScalarStyle::Literal | ScalarStyle::Folded,
+            ..
+        } => node.start.index + source[node.start.index..node.end.index].trim_end().len(),
+        _ => node.end.index,
+    }
+}
+
+/// Position of the `-` of a block sequence item
+fn dash(item: &Node, source: &str) -> usize {
+    let before = source[..item.start.index].trim_end();
+    match before.strip_suffix('-') {
+        Some(rest) => rest.len(),
+        None => before.rfind('-').unwrap_or(item.start.index),
+    }
+}
+
+/// Whether only indentation (or the `?` of an explicit key) precedes
+/// `pos` on its line
+fn starts_line(source: &str, pos: usize) -> bool {
+    matches!(source[line_start(source, pos)..pos].trim(), "" | "?")
+}
+
+/// Is `node` written on one line (so another one-line value can take its
+/// place)? Empty scalars aren't: they have no text to replace.
+fn is_inline(node: &Node) -> bool {
+    match &node.kind {
+        NodeKind::Scalar { value, style } => match style {
+            ScalarStyle::Literal | ScalarStyle::Folded => false,
+            ScalarStyle::Plain => !value.is_empty() || node.tag.is_some(),
+            ScalarStyle::SingleQuoted | ScalarStyle::DoubleQuoted => true,
+        },
+        NodeKind::Sequence { flow, .. } | NodeKind::Mapping { flow, .. } => *flow,
+        NodeKind::Alias { .. } => true,
+    }
+}
+
+/// Is `value` best written over several lines (a block collection, or a
+/// literal block for a multi-line string)?
+fn is_multiline(value: &Value) -> bool {
+    match value {
+        Value::Mapping(mapping) => !mapping.is_empty(),
+        Value::Sequence(items) => !items.is_empty(),
+        Value::String(text) => text.contains('\n'),
+        Value::Tagged(tagged) => is_multiline(&tagged.value),
+        _ => false,
+    }
+}
+
+/// Indent every line of `text` after the first, except empty ones
+fn indent_lines(text: &str, indent: usize) -> String {
+    let mut out = String::with_capacity(text.len());
+    for (index, line) in text.split_inclusive('\n').enumerate() {
+        if index > 0 && line != "\n" {
+            out.extend(std::iter::repeat_n(' ', indent));
+        }
+        out.push_str(line);
+    }
+    out
+}
+
+/// Options new text is written with: insertion order, default layout
+fn dump_options() -> DumpOptions {
+    DumpOptions {
+        sort_keys: false,
+        ..DumpOptions::default()
+    }
+}
+
+/// Where a node sits, which decides how a replacement is written
+#[derive(Clone, Copy, Debug)]
+enum Context {
+    /// The root, or inside a flow collection: only one-line text fits
+    Flow,
+    /// A block mapping value; `colon` is just past its `:` and `indent`
+    /// is the key's column
+    Value { colon: usize, indent: usize },
+    /// A block sequence item; `dash` is just past its `-` and `indent` is
+    /// the dash's column
+    Item { dash: usize, indent: usize },
+}
+
+/// A change to the source: `start..end` becomes `text`
+struct Edit {
+    start: usize,
+    end: usize,
+    text: String,
+}
+
+/// Compares a composed document with the data now in its place
+struct Splicer<'a, 'py> {
+    py: Python<'py>,
+    source: &'a str,
+    root: &'a Node,
+    options: &'a LoadOptions,
+    edits: Vec<Edit>,
+    /// Anchored nodes that changed: `Some(data)` if the anchor is still
+    /// written (on a collection edited in place), `None` if it's gone
+    changed_anchors: HashMap<&'a str, Option<Bound<'py, PyAny>>>,
+}
+
+impl<'a, 'py> Splicer<'a, 'py> {
+    fn new(py: Python<'py>, source: &'a str, root: &'a Node, options: &'a LoadOptions) -> Self {
+        Splicer {
+            py,
+            source,
+            root,
+            options,
+            edits: Vec::new(),
+            changed_anchors: HashMap::new(),
+        }
+    }
+
+    /// The data `node` was loaded as
+    fn original(&self, node: &Node) -> PyResult<Bound<'py, PyAny>> {
+        construct::construct_node(self.py, self.root, node, self.options)
+            .map(|obj| obj.into_bound(self.py))
+    }
+
+    /// Does `node` contain an alias of an anchor that changed (so it must
+    /// be looked at even if its data is the same)?
+    fn has_stale_alias(&self, node: &Node) -> bool {
+        match &node.kind {
+            NodeKind::Alias { anchor } => self.changed_anchors.contains_key(anchor.as_str()),
+            NodeKind::Sequence { items, .. } => items.iter().any(|item| self.has_stale_alias(item)),
+            NodeKind::Mapping { pairs, .. } => pairs
+                .iter()
+                .any(|(key, value)| self.has_stale_alias(key) || self.has_stale_alias(value)),
+            NodeKind::Scalar { .. } => false,
+        }
+    }
+
+    /// Record the edits that turn `node` into `value`
+    fn diff(
+        &mut self,
+        node: &'a Node,
+        value: &Bound<'py, PyAny>,
+        context: Context,
+    ) -> PyResult<()> {
+        if let NodeKind::Alias { anchor } = &node.kind {
+            let keep = match self.changed_anchors.get(anchor.as_str()) {
+                None => same(&self.original(node)?, value, 0)?,
+                Some(Some(current)) => same(current, value, 0)?,
+                Some(None) => false,
+            };
+            if !keep {
+                self.replace(node, value, context)?;
+            }
+            return Ok(());
+        }
+        if same(&self.original(node)?, value, 0)? && !self.has_stale_alias(node) {
+            if let Some(anchor) = &node.anchor {
+                // A new definition of the anchor, as it was
+                self.changed_anchors.remove(anchor.as_str());
+            }
+            return Ok(());
+        }
+
+        if let Some(anchor) = &node.anchor {
+            self.changed_anchors
+                .insert(anchor.as_str(), Some(value.clone()));
+        }
+        let edited = match &node.kind {
+            NodeKind::Mapping { pairs, flow } => match value.downcast::<PyDict>() {
+                Ok(dict) => self.mapping(node, pairs, *flow, dict)?,
+                Err(_) => false,
+            },
+            NodeKind::Sequence { items, flow } => match value.downcast::<PyList>() {
+                Ok(list) => self.sequence(items, *flow, list)?,
+                Err(_) => false,
+            },
+            NodeKind::Scalar { .. } | NodeKind::Alias { .. } => false,
+        };
+        if !edited {
+            self.forget_anchors(node);
+            self.replace(node, value, context)?;
+        }
+        Ok(())
+    }
+
+    /// The anchors defined in `node`, which is deleted or rewritten, are
+    /// gone: aliases of them have to be written out
+    fn forget_anchors(&mut self, node: &'a Node) {
+        if let Some(anchor) = &node.anchor {
+            self.changed_anchors.insert(anchor.as_str(), None);
+        }
+        match &node.kind {
+            NodeKind::Sequence { items, .. } => {
+                for item in items {
+                    self.forget_anchors(item);
+                }
+            }
+            NodeKind::Mapping { pairs, .. } => {
+                for (key, value) in pairs {
+                    self.forget_anchors(key);
+                    self.forget_anchors(value);
+                }
+            }
+            NodeKind::Scalar { .. } | NodeKind::Alias { .. } => {}
+        }
+    }
+
+    /// Edit a mapping's entries in place; `false` if it has to be written
+    /// anew (it's empty now, or a flow mapping whose keys changed)
+    fn mapping(
+        &mut self,
+        node: &'a Node,
+        pairs: &'a [(Node, Node)],
+        flow: bool,
+        dict: &Bound<'py, PyDict>,
+    ) -> PyResult<bool> {
+        if dict.is_empty() {
+            return Ok(false);
+        }
+        let merge_keys = self.options.merge_keys();
+        // The key of each entry written in this mapping (not merged in)
+        let mut keys = Vec::with_capacity(pairs.len());
+        let own = PyDict::new_bound(self.py);
+        for (key, value) in pairs {
+            if construct::is_merge_key(key, merge_keys) {
+                if self.has_stale_alias(value) {
+                    // What's merged in changed: write the entries out
+                    return Ok(false);
+                }
+                keys.push(None);
+                continue;
+            }
+            let key = self.original(key)?;
+            own.set_item(&key, self.py.None())?;
+            keys.push(Some(key));
+        }
+        // Merged entries stay as they are unless their value changed
+        let merged = if keys.iter().any(Option::is_none) {
+            Some(self.original(node)?)
+        } else {
+            None
+        };
+        let added = PyDict::new_bound(self.py);
+        for (key, value) in dict.iter() {
+            if own.contains(&key)? {
+                continue;
+            }
+            if let Some(merged) = &merged {
+                let inherited = merged.downcast::<PyDict>()?.get_item(&key)?;
+                if let Some(inherited) = inherited {
+                    if same(&inherited, &value, 0)? {
+                        continue;
+                    }
+                }
+            }
+            added.set_item(key, value)?;
+        }
+        let kept = keys
+            .iter()
+            .map(|key| match key {
+                Some(key) => dict.contains(key),
+                None => Ok(true),
+            })
+            .collect::<PyResult<Vec<bool>>>()?;
+        let removed = kept.iter().any(|kept| !kept);
+        if flow && (removed || !added.is_empty()) {
+            return Ok(false);
+        }
+        if !kept
+            .iter()
+            .zip(&keys)
+            .any(|(kept, key)| *kept && key.is_some())
+        {
+            // No entry left to write new ones next to
+            return Ok(false);
+        }
+
+        let starts: Vec<usize> = pairs.iter().map(|(key, _)| key.start.index).collect();
+        for (index, (key, value)) in pairs.iter().enumerate() {
+            let Some(key_obj) = &keys[index] else {
+                continue;
+            };
+            if !kept[index] {
+                self.forget_anchors(key);
+                self.forget_anchors(value);
+                self.delete(&starts, &kept, index, value);
+                continue;
+            }
+            let context = if flow {
+                Context::Flow
+            } else {
+                let colon = key.end.index
+                    + self.source[key.end.index..]
+                        .find(':')
+                        .map_or(0, |colon| colon + 1);
+                Context::Value {
+                    colon,
+                    indent: key.start.column,
+                }
+            };
+            let current = dict
+                .get_item(key_obj)?
+                .expect("only kept entries are compared");
+            self.diff(value, &current, context)?;
+        }
+        if !added.is_empty() {
+            let (first, _) = &pairs[0];
+            let (_, last) = &pairs[pairs.len() - 1];
+            let added = types::python_to_yaml_for_dump(&added)?;
+            self.append(last, &added, first.start.column);
+        }
+        Ok(true)
+    }
+
+    /// Edit a sequence's items in place; `false` if it has to be written
+    /// anew (it's empty now, or a flow sequence whose length changed)
+    fn sequence(
+        &mut self,
+        items: &'a [Node],
+        flow: bool,
+        list: &Bound<'py, PyList>,
+    ) -> PyResult<bool> {
+        if list.is_empty() || (flow && list.len() != items.len()) {
+            return Ok(false);
+        }
+        for (item, current) in items.iter().zip(list.iter()) {
+            let context = if flow {
+                Context::Flow
+            } else {
+                let dash = dash(item, self.source);
+                Context::Item {
+                    dash: dash + 1,
+                    indent: dash - line_start(self.source, dash),
+                }
+            };
+            self.diff(item, &current, context)?;
+        }
+        if items.len() > list.len() {
+            // Only trailing items go, and never the first, so each starts
+            // its own line
+            for item in &items[list.len()..] {
+                self.forget_anchors(item);
+            }
+            let first = dash(&items[list.len()], self.source);
+            let last = &items[items.len() - 1];
+            self.edits.push(Edit {
+                start: line_start(self.source, first),
+                end: line_end(self.source, content_end(last, self.source)),
+                text: String::new(),
+            });
+        }
+        if list.len() > items.len() {
+            let dash = dash(&items[0], self.source);
+            let added = PyList::new_bound(self.py, list.iter().skip(items.len()));
+            let added = types::python_to_yaml_for_dump(&added)?;
+            self.append(
+                &items[items.len() - 1],
+                &added,
+                dash - line_start(self.source, dash),
+            );
+        }
+        Ok(true)
+    }
+
+    /// Remove entry `index` of a block mapping whose entries start at
+    /// `starts`; `kept` says which entries stay
+    fn delete(&mut self, starts: &[usize], kept: &[bool], index: usize, value: &Node) {
+        let start = starts[index];
+        let (start, end) = if starts_line(self.source, start) {
+            let end = line_end(self.source, content_end(value, self.source));
+            (line_start(self.source, start), end)
+        } else {
+            // The first entry, after a `- `: the next entry kept moves up
+            // into its place (and the entries between go with it)
+            let next = (index + 1..starts.len())
+                .find(|&next| kept[next])
+                .map_or(self.source.len(), |next| starts[next]);
+            (start, next)
+        };
+        if self.edits.last().is_some_and(|edit| edit.end > start) {
+            return;
+        }
+        self.edits.push(Edit {
+            start,
+            end,
+            text: String::new(),
+        });
+    }
+
+    /// Write the entries or items of `value` after `last`, the current
+    /// last one, at column `indent`
+    fn append(&mut self, last: &Node, value: &Value, indent: usize) {
+        let pos = line_end(self.source, content_end(last, self.source));
+        let mut text = String::new();
+        if !self.source[..pos].ends_with('\n') {
+            text.push('\n');
+        }
+        let rendered = dumper::emit(value, dump_options());
+        text.push_str(&" ".repeat(indent));
+        text.push_str(&indent_lines(&rendered, indent));
+        self.edits.push(Edit {
+            start: pos,
+            end: pos,
+            text,
+        });
+    }
+
+    /// Write `value` in place of `node`
+    fn replace(
+        &mut self,
+        node: &Node,
+        value: &Bound<'py, PyAny>,
+        context: Context,
+    ) -> PyResult<()> {
+        let value = types::python_to_yaml_for_dump(value)?;
+        let mut end = content_end(node, self.source);
+        let fits_inline = match &node.kind {
+            NodeKind::Sequence { flow: true, .. } | NodeKind::Mapping { flow: true, .. } => true,
+            _ => is_inline(node) && !is_multiline(&value),
+        };
+        let (start, text) = match context {
+            Context::Value { colon, indent } if !fits_inline => {
+                // Written as the value of a one-entry mapping, then moved
+                // to this key's column
+                let mut wrapper = Mapping::new();
+                wrapper.insert(Value::String("k".to_string()), value);
+                let rendered = dumper::emit(&Value::Mapping(wrapper), dump_options());
+                let text = rendered["k:".len()..].trim_end_matches('\n');
+                (colon, indent_lines(text, indent))
+            }
+            Context::Item { dash, indent } if !fits_inline => {
+                let rendered = dumper::emit(&Value::Sequence(vec![value]), dump_options());
+                let text = rendered["-".len()..].trim_end_matches('\n');
+                (dash, indent_lines(text, indent))
+            }
+            _ => (node.start.index, self.inline(node, &value)),
+        };
+        let mut text = text;
+        if text.contains('\n') {
+            // Text after the old value (a comment) can't follow a block,
+            // which may end in a literal scalar that would take it in
+            let next_line = line_end(self.source, end);
+            if self.source[..next_line].ends_with('\n') {
+                end = next_line - 1;
+            } else {
+                end = next_line;
+                text.push('\n');
+            }
+        }
+        self.edits.push(Edit { start, end, text });
+        Ok(())
+    }
+
+    /// `value` on one line, quoted as `node` was if it's still a string
+    fn inline(&self, node: &Node, value: &Value) -> String {
+        match (&node.kind, value) {
+            (NodeKind::Scalar { style, .. }, Value::String(text))
+                if matches!(style, ScalarStyle::SingleQuoted | ScalarStyle::DoubleQuoted) =>
+            {
+                dumper::quoted(text, *style == ScalarStyle::SingleQuoted)
+            }
+            _ => dumper::emit_flow(value),
+        }
+    }
+
+    /// The source with every edit made
+    fn finish(mut self) -> String {
+        self.edits.sort_by_key(|edit| (edit.start, edit.end));
+        let mut out = String::with_capacity(self.source.len());
+        let mut copied = 0;
+        for edit in &self.edits {
+            debug_assert!(edit.start >= copied, "edits overlap");
+            out.push_str(&self.source[copied..edit.start]);
+            out.push_str(&edit.text);
+            copied = edit.end;
+        }
+        out.push_str(&self.source[copied..]);
+        out
+    }
+}
+
+/// Rewrite `source`, loaded with `options`, so it reads as `data`, keeping
+/// the text of everything that didn't change
+///
+/// # Errors
+/// * Parse errors in `source`
+/// * `TypeError` for data that can't be written as YAML
+pub fn splice(
+    py: Python,
+    source: &str,
+    data: &Bound<'_, PyAny>,
+    options: &LoadOptions,
+) -> PyResult<String> {
+    let Some(document) = compose::compose_one(source)? else {
+        // Nothing to edit: keep the comments and write the data after them
+        let mut out = source.to_string();
+        if !out.is_empty() && !out.ends_with('\n') {
+            out.push('\n');
+        }
+        let value = types::python_to_yaml_for_dump(data)?;
+        out.push_str(&dumper::emit(&value, dump_options()));
+        return Ok(out);
+    };
+    let mut splicer = Splicer::new(py, source, &document.root, options);
+    splicer.diff(&document.root, data, Context::Flow)?;
+    Ok(splicer.finish())
+}
+
+/// Check `options` and construct the single document of `source`
+fn construct<'py>(
+    py: Python<'py>,
+    source: &str,
+    options: &LoadOptions,
+) -> PyResult<(Bound<'py, PyAny>, Option<compose::Document>)> {
+    options.check_single_document()?;
+    check_options(options)?;
+    limits::check_size(source, options)?;
+    let document = compose::compose_one(source).map_err(|err| tabs::explain(err, source))?;
+    if let Some(document) = &document {
+        limits::check_document(&document.root, options)?;
+    }
+    let data = construct::construct_document(py, document.as_ref(), options)?.into_bound(py);
+    Ok((data, document))
+}
+
+/// Record the style of every scalar value below `node` by JSON pointer
+/// (as `sourcemap` builds them; aliases and non-scalar keys are skipped)
+fn collect_styles(node: &Node, pointer: &mut String, styles: &Bound<'_, PyDict>) -> PyResult<()> {
+    let len = pointer.len();
+    match &node.kind {
+        NodeKind::Scalar { style, .. } => styles.set_item(pointer.as_str(), style.name())?,
+        NodeKind::Sequence { items, .. } => {
+            for (i, item) in items.iter().enumerate() {
+                pointer.push('/');
+                pointer.push_str(&i.to_string());
+                collect_styles(item, pointer, styles)?;
+                pointer.truncate(len);
+            }
+        }
+        NodeKind::Mapping { pairs, .. } => {
+            for (key, value) in pairs {
+                let NodeKind::Scalar { value: key, .. } = &key.kind else {
+                    continue;
+                };
+                pointer.push('/');
+                pointer.push_str(&sourcemap::escape(key));
+                collect_styles(value, pointer, styles)?;
+                pointer.truncate(len);
+            }
+        }
+        NodeKind::Alias { .. } => {}
+    }
+    Ok(())
+}
+
+/// Parse a single document for round-trip editing
+///
+/// # Arguments
+/// * `yaml_str` - YAML content with at most one document
+/// * `options` - Load options that change construction (`pyyaml_compat`,
+///   `timestamps`, limits, ...); those that rewrite the source or the data
+///   (`document_markers`, `marked`, `dict_class`, `schema`, `redact`,
+///   `sops_key`, `profile`) aren't supported
+///
+/// # Returns
+/// The data, as a `CommentedMap` or `CommentedSeq` remembering `yaml_str`
+/// and `options` if it's a mapping or sequence. Its `styles` maps the JSON
+/// pointer of each scalar value to how it was written (`"plain"`,
+/// `"single-quoted"`, `"double-quoted"`, `"literal"` or `"folded"`)
+///
+/// # Errors
+/// * `YAMLError` for syntax errors, unsafe tags or exceeded limits
+/// * `TypeError` for unsupported options
+#[pyfunction]
+#[pyo3(signature = (yaml_str, **options))]
+pub fn load_rt(
+    py: Python,
+    yaml_str: &str,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<PyObject> {
+    let kwargs = match options {
+        Some(options) => options.copy()?,
+        None => PyDict::new_bound(py),
+    };
+    let options = LoadOptions::from_kwargs(Some(&kwargs))?;
+    let (data, document) = construct(py, yaml_str, &options)?;
+
+    let class = if data.is_instance_of::<PyDict>() {
+        "CommentedMap"
+    } else if data.is_instance_of::<PyList>() {
+        "CommentedSeq"
+    } else {
+        return Ok(data.unbind());
+    };
+    let data = roundtrip_module(py)?.getattr(class)?.call1((data,))?;
+    data.setattr("source", yaml_str)?;
+    data.setattr("options", kwargs)?;
+    let styles = PyDict::new_bound(py);
+    if let Some(document) = &document {
+        collect_styles(&document.root, &mut String::new(), &styles)?;
+    }
+    data.setattr("styles", styles)?;
+    Ok(data.unbind())
+}
+
+/// Serialize data loaded with `load_rt`, keeping the original text of
+/// everything that didn't change
+///
+/// # Arguments
+/// * `data` - A `CommentedMap` or `CommentedSeq` from `load_rt`; other
+///   data is dumped as `safe_dump(data, sort_keys=False)` would
+///
+/// # Returns
+/// The YAML text
+///
+/// # Errors
+/// * `TypeError` for objects that can't be written as YAML
+#[pyfunction]
+pub fn dump_rt(py: Python, data: &Bound<'_, PyAny>) -> PyResult<String> {
+    let roundtrip = roundtrip_module(py)?;
+    let loaded = data.is_instance(&roundtrip.getattr("CommentedMap")?)?
+        || data.is_instance(&roundtrip.getattr("CommentedSeq")?)?;
+    if !loaded {
+        let value = types::python_to_yaml_for_dump(data)?;
+        return Ok(dumper::emit(&value, dump_options()));
+    }
+    let source: String = data.getattr("source")?.extract()?;
+    let kwargs = data.getattr("options")?;
+    let options = LoadOptions::from_kwargs(Some(kwargs.downcast::<PyDict>()?))?;
+    splice(py, &source, data, &options)
+}
+
+/// The key of `dict` that `name` stands for in a key path
+fn find_key<'py>(dict: &Bound<'py, PyDict>, name: &str) -> PyResult<Option<Bound<'py, PyAny>>> {
+    for key in dict.keys() {
+        let text = if key.is_instance_of::<PyString>() {
+            key.extract::<String>()?
+        } else if key.is_instance_of::<PyBool>() {
+            key.extract::<bool>()?.to_string()
+        } else if key.is_instance_of::<PyInt>() || key.is_instance_of::<PyFloat>() {
+            key.str()?.to_string()
+        } else {
+            continue;
+        };
+        if text == name {
+            return Ok(Some(key));
+        }
+    }
+    Ok(None)
+}
+
+/// Set the value at `segments` below `data`
+///
+/// Every step but the last must exist; the last may add a key to a
+/// mapping.
+fn set_path(
+    data: &Bound<'_, PyAny>,
+    segments: &[Segment],
+    key_path: &str,
+    new_value: &Bound<'_, PyAny>,
+) -> PyResult<()> {
+    let missing = || PyValueError::new_err(format!("no value at '{}'", key_path));
+    let mut target = data.clone();
+    for (position, segment) in segments.iter().enumerate() {
+        let last = position + 1 == segments.len();
+        let key = match segment {
+            Segment::Key(name) => {
+                let dict = target.downcast::<PyDict>().map_err(|_| missing())?;
+                match find_key(dict, name)? {
+                    Some(key) => key,
+                    None if last => name.into_py(data.py()).into_bound(data.py()),
+                    None => return Err(missing()),
+                }
+            }
+            Segment::Index(index) => {
+                let list = target.downcast::<PyList>().map_err(|_| missing())?;
+                if *index >= list.len() {
+                    return Err(missing());
+                }
+                index.into_py(data.py()).into_bound(data.py())
+            }
+            Segment::AnyKey | Segment::AnyIndex => {
+                return Err(PyValueError::new_err(format!(
+                    "key path '{}' must name a single value (no '*')",
+                    key_path
+                )))
+            }
+        };
+        if last {
+            target.set_item(key, new_value)?;
+        } else {
+            target = target.get_item(key)?;
+        }
+    }
+    Ok(())
+}
+
+/// Set one value in a YAML file, keeping the text of everything else
+///
+/// The file is loaded as by `load_rt`, the value at `key_path` replaced
+/// and the file rewritten as `dump_rt` would write it, so comments, key
+/// order, quoting and indentation outside the value are kept. The file is
+/// only written if its text changes.
+///
+/// # Arguments
+/// * `path` - UTF-8 YAML file with a single document
+/// * `key_path` - Value to set, in `grep` syntax (`spec.containers[0].image`)
+///   without `*`; the last key is added if the mapping doesn't have it
+/// * `new_value` - The value to write there
+/// * `options` - As for `load_rt`
+///
+/// # Errors
+/// * `YAMLError` if the file can't be read, parsed or written
+/// * `ValueError` if `key_path` is invalid or names no value, or the file
+///   is compressed or not UTF-8
+/// * `TypeError` for unsupported options or values
+///
+/// # Example
+/// ```python
+/// rustyyaml.update_file("Chart.yaml", "version", "1.4.0")
+/// ```
+#[pyfunction]
+#[pyo3(signature = (path, key_path, new_value, **options))]
+pub fn update_file(
+    py: Python,
+    path: PathBuf,
+    key_path: &str,
+    new_value: &Bound<'_, PyAny>,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<()> {
+    let segments = search::parse_key_path(key_path)
+        .ok_or_else(|| PyValueError::new_err(format!("invalid key path '{}'", key_path)))?;
+    if Compression::of(&path).is_some() {
+        return Err(PyValueError::new_err(format!(
+            "{} is compressed and can't be updated in place",
+            path.display()
+        )));
+    }
+    let decoded = encoding::decode_with(&batch::read_bytes(&path)?, Decoding::Detect)?;
+    if decoded.encoding != Encoding::Utf8 {
+        return Err(PyValueError::new_err(format!(
+            "{} is {}; only UTF-8 files can be updated in place",
+            path.display(),
+            decoded.encoding.name()
+        )));
+    }
+    let source = decoded.text.into_string();
+
+    let options = LoadOptions::from_kwargs(options)?;
+    let (data, _) = construct(py, &source, &options)?;
+    set_path(&data, &segments, key_path, new_value)?;
+    let text = splice(py, &source, &data, &options)?;
+    if text == source {
+        return Ok(());
+    }
+
+    let bom = if decoded.bom { "\u{feff}" } else { "" };
+    fs::write(&path, format!("{}{}", bom, text)).map_err(|e| batch::write_error(&path, e).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use pyo3::types::IntoPyDict;
+
+    use super::*;
+
+    /// Load `yaml` (YAML 1.2 rules), let `edit` change the data, and dump
+    /// it again
+    fn round_trip(yaml: &str, edit: &str) -> String {
+        round_trip_with(yaml, edit, false)
+    }
+
+    fn round_trip_with(yaml: &str, edit: &str, pyyaml_compat: bool) -> String {
+        Python::with_gil(|py| {
+            let kwargs = [("pyyaml_compat", pyyaml_compat)].into_py_dict_bound(py);
+            let options = LoadOptions::from_kwargs(Some(&kwargs)).unwrap();
+            let document = compose::compose_one(yaml).unwrap();
+            let data = construct::construct_document(py, document.as_ref(), &options).unwrap();
+            let locals = [("data", data)].into_py_dict_bound(py);
+            py.run_bound(edit, None, Some(&locals)).unwrap();
+            let data = locals.get_item("data").unwrap().unwrap();
+            splice(py, yaml, &data, &options).unwrap()
+        })
+    }
+
+    #[test]
+    fn test_unchanged_text_is_kept() {
+        let yaml = "# config\nname:   'web'  # quoted\nports: [80, 443]\n\nlevel: |\n  debug\n";
+        assert_eq!(round_trip(yaml, "pass"), yaml);
+    }
+
+    #[test]
+    fn test_scalars_keep_style() {
+        let yaml = "a: 'x'  # one\nb: \"y\"\nc: 1\nd: [1, 2]\n";
+        assert_eq!(
+            round_trip(
+                yaml,
+                "data['a'] = \"it's\"; data['b'] = 'z'; data['c'] = 2; data['d'][1] = 3"
+            ),
+            "a: 'it''s'  # one\nb: \"z\"\nc: 2\nd: [1, 3]\n"
+        );
+    }
+
+    #[test]
+    fn test_entries_added_and_removed() {
+        let yaml = "a: 1\nb:\n  c: 2  # keep\n  d: 3\ne: [1]\n# end\n";
+        assert_eq!(
+            round_trip(yaml, "del data['b']['d']; data['b']['f'] = [1, 2]; data['e'].append(2); data['g'] = {'h': 'i'}"),
+            "a: 1\nb:\n  c: 2  # keep\n  f:\n  - 1\n  - 2\ne: [1, 2]\ng:\n  h: i\n# end\n"
+        );
+    }
+
+    #[test]
+    fn test_sequences() {
+        let yaml = "- a: 1\n  b: 2\n- x\n- y\n";
+        assert_eq!(
+            round_trip(yaml, "del data[0]['a']; data.pop(); data.append({'c': 3})"),
+            "- b: 2\n- x\n- c: 3\n"
+        );
+        assert_eq!(
+            round_trip("- x\n- y", "data.append('z')"),
+            "- x\n- y\n- z\n"
+        );
+    }
+
+    #[test]
+    fn test_replaced_by_other_types() {
+        let yaml = "a: 1\nb: {x: 1}\nc:\n  - 1\nd:\n";
+        assert_eq!(
+            round_trip(
+                yaml,
+                "data['a'] = {'n': 1}; data['b'] = {}; data['c'] = 'line\\nnext\\n'; data['d'] = 5"
+            ),
+            "a:\n  n: 1\nb: {}\nc: |\n  line\n  next\nd: 5\n"
+        );
+    }
+
+    #[test]
+    fn test_anchors_and_merges() {
+        let yaml = "base: &b {x: 1}\nuse: *b\nchild:\n  <<: *b\n  y: 2\n";
+        assert_eq!(
+            round_trip(yaml, "data['child']['y'] = 3"),
+            "base: &b {x: 1}\nuse: *b\nchild:\n  <<: *b\n  y: 3\n"
+        );
+        // Without shared aliases, an alias keeps the value it had; merged
+        // entries were copied, so they're written out
+        assert_eq!(
+            round_trip(yaml, "data['base']['x'] = 2"),
+            "base: &b {x: 2}\nuse:\n  x: 1\nchild:\n  x: 1\n  y: 2\n"
+        );
+        // With them, it follows the anchor
+        assert_eq!(
+            round_trip_with(yaml, "data['base']['x'] = 2", true),
+            "base: &b {x: 2}\nuse: *b\nchild:\n  x: 1\n  y: 2\n"
+        );
+        assert_eq!(
+            round_trip_with(yaml, "data['child']['z'] = 0", true),
+            "base: &b {x: 1}\nuse: *b\nchild:\n  <<: *b\n  y: 2\n  z: 0\n"
+        );
+    }
+
+    #[test]
+    fn test_strict_comparison() {
+        assert_eq!(round_trip("a: 1\n", "data['a'] = True"), "a: true\n");
+        assert_eq!(round_trip("a: 1\n", "data['a'] = 1.0"), "a: 1.0\n");
+        assert_eq!(round_trip("a: .nan\n", "pass"), "a: .nan\n");
+    }
+
+    #[test]
+    fn test_collect_styles() {
+        Python::with_gil(|py| {
+            let source = "a: 'x'\nb: [&z \"y\", *z, 2]\n~/k: >\n  f\n";
+            let document = compose::compose_one(source).unwrap().unwrap();
+            let styles = PyDict::new_bound(py);
+            collect_styles(&document.root, &mut String::new(), &styles).unwrap();
+            let styles: HashMap<String, String> = styles.extract().unwrap();
+            let expected = [
+                ("/a", "single-quoted"),
+                ("/b/0", "double-quoted"),
+                ("/b/2", "plain"),
+                ("/~0~1k", "folded"),
+            ];
+            let expected = expected
+                .iter()
+                .map(|(pointer, style)| (pointer.to_string(), style.to_string()))
+                .collect();
+            assert_eq!(styles, expected);
+        })
+    }
+
+    #[test]
+    fn test_set_path() {
+        Python::with_gil(|py| {
+            let set = |yaml: &str, key_path: &str| -> PyResult<String> {
+                let options = LoadOptions::default();
+                let (data, _) = construct(py, yaml, &options)?;
+                let segments = search::parse_key_path(key_path).unwrap();
+                set_path(&data, &segments, key_path, &PyString::new_bound(py, "v"))?;
+                splice(py, yaml, &data, &options)
+            };
+            assert_eq!(
+                set("a: {b: [1, 2]}\n", "a.b[1]").unwrap(),
+                "a: {b: [1, v]}\n"
+            );
+            assert_eq!(
+                set("1: x\nfalse: y\n", "false").unwrap(),
+                "1: x\nfalse: v\n"
+            );
+            assert_eq!(set("a:\n  b: 1\n", "a.c").unwrap(), "a:\n  b: 1\n  c: v\n");
+            assert!(set("a: 1\n", "b.c").is_err());
+            assert!(set("a: [1]\n", "a[1]").is_err());
+            assert!(set("a: [1]\n", "a[*]").is_err());
+        });
+    }
+
+    #[test]
+    fn test_update_file() {
+        let dir = std::env::temp_dir().join(format!("rustyyaml-update-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("Chart.yaml");
+        fs::write(
+            &path,
+            "\u{feff}# chart\nversion: 1.2.3  # bump me\nname: web\n",
+        )
+        .unwrap();
+        Python::with_gil(|py| {
+            let version = PyString::new_bound(py, "1.2.4").into_any();
+            update_file(py, path.clone(), "version", &version, None).unwrap();
+        });
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "\u{feff}# chart\nversion: 1.2.4  # bump me\nname: web\n"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -5,9 +5,17 @@
 //! - !!python/object/apply:os.system
 //! - !!python/object/new:subprocess.Popen
 //!
-//! We block ALL custom tags in safe mode.
+//! We block ALL custom tags in safe mode, except the harmless ones a
+//! caller lists in `allowed_tags=` (CloudFormation's `!Ref`, `!Sub`, ...),
+//! which are constructed as `tag_strategy=` says. Unsafe tags can't be
+//! listed.
 
+use std::sync::Arc;
+
+use crate::compose::{Node, NodeKind};
 use crate::error::YAMLError;
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
 use serde_yaml::Value;
 
 /// List of tags that are ALWAYS unsafe
@@ -38,10 +46,8 @@ pub fn check_safety(value: &Value) -> Result<(), YAMLError> {
             // Check if this tag is in the unsafe list
             let tag_str = tagged.tag.to_string();
 
-            for unsafe_tag in UNSAFE_TAGS {
-                if tag_str.contains(unsafe_tag) {
-                    return Err(YAMLError::UnsafeTag { tag: tag_str });
-                }
+            if is_unsafe_tag(&tag_str) {
+                return Err(YAMLError::UnsafeTag { tag: tag_str });
             }
 
             // Also check the value inside the tag
@@ -70,6 +76,82 @@ pub fn check_safety(value: &Value) -> Result<(), YAMLError> {
     }
 }
 
+/// Check a composed node tree for unsafe tags
+///
+/// Unlike `check_safety`, this sees tags on every node, including the
+/// `!!python/...` tags serde_yaml silently drops
+pub fn check_node_safety(node: &Node) -> Result<(), YAMLError> {
+    if let Some(tag) = &node.tag {
+        if is_unsafe_tag(tag) {
+            return Err(YAMLError::UnsafeTag { tag: tag.clone() });
+        }
+    }
+
+    match &node.kind {
+        NodeKind::Sequence { items, .. } => items.iter().try_for_each(check_node_safety),
+        NodeKind::Mapping { pairs, .. } => pairs.iter().try_for_each(|(k, v)| {
+            check_node_safety(k)?;
+            check_node_safety(v)
+        }),
+        NodeKind::Scalar { .. } | NodeKind::Alias { .. } => Ok(()),
+    }
+}
+
+/// What a tag listed in `allowed_tags=` constructs to (`tag_strategy=`)
+#[derive(Clone, Debug, Default)]
+pub enum TagStrategy {
+    /// The value as if it weren't tagged
+    #[default]
+    Value,
+    /// `{name: value}`, the tag without its leading `!` as the key
+    Mapping,
+    /// Whatever the callable returns, called with `(tag, value)`
+    Call(Arc<PyObject>),
+}
+
+impl TagStrategy {
+    pub fn from_python(value: &Bound<'_, PyAny>) -> PyResult<Self> {
+        if value.is_callable() {
+            return Ok(TagStrategy::Call(Arc::new(value.clone().unbind())));
+        }
+        let name: String = value.extract().map_err(|_| {
+            PyTypeError::new_err("tag_strategy must be 'value', 'mapping' or a callable")
+        })?;
+        match name.as_str() {
+            "value" => Ok(TagStrategy::Value),
+            "mapping" => Ok(TagStrategy::Mapping),
+            _ => Err(PyValueError::new_err(format!(
+                "tag_strategy must be 'value', 'mapping' or a callable, not '{}'",
+                name
+            ))),
+        }
+    }
+}
+
+/// Read the `allowed_tags=` option
+///
+/// # Errors
+/// * `ValueError` if it lists an unsafe tag
+pub fn extract_allowed_tags(value: &Bound<'_, PyAny>) -> PyResult<Vec<String>> {
+    if value.is_none() {
+        return Ok(Vec::new());
+    }
+    let tags: Vec<String> = value.extract()?;
+    match tags.iter().find(|tag| is_unsafe_tag(tag)) {
+        Some(tag) => Err(PyValueError::new_err(format!(
+            "allowed_tags can't include the unsafe tag {}",
+            tag
+        ))),
+        None => Ok(tags),
+    }
+}
+
+pub fn is_unsafe_tag(tag: &str) -> bool {
+    UNSAFE_TAGS
+        .iter()
+        .any(|unsafe_tag| tag.contains(unsafe_tag))
+}
+
 /// Check if a raw YAML string contains unsafe patterns
 ///
 /// This is a quick pre-scan before full parsing to catch obvious issues
@@ -162,4 +244,30 @@ nested:
         // Note: serde_yaml might parse this differently, so we test the quick check
         assert!(quick_safety_check(yaml).is_err());
     }
+
+    #[test]
+    fn test_allowed_tags_exclude_unsafe() {
+        Python::with_gil(|py| {
+            let tags = pyo3::types::PyList::new_bound(py, ["!Ref", "!Sub"]);
+            assert_eq!(extract_allowed_tags(&tags).unwrap(), ["!Ref", "!Sub"]);
+            assert!(extract_allowed_tags(&py.None().into_bound(py))
+                .unwrap()
+                .is_empty());
+            let tags = pyo3::types::PyList::new_bound(py, ["!Ref", "!!python/object/apply"]);
+            assert!(extract_allowed_tags(&tags).is_err());
+        });
+    }
+
+    #[test]
+    fn test_node_safety_sees_core_prefixed_tags() {
+        // serde_yaml drops this tag entirely; the composer keeps it
+        let yaml = "!!python/object/apply:os.system ['echo dangerous']";
+        let doc = crate::compose::compose_one(yaml).unwrap().unwrap();
+        assert!(check_node_safety(&doc.root).is_err());
+
+        let doc = crate::compose::compose_one("a: [1, {b: c}]")
+            .unwrap()
+            .unwrap();
+        assert!(check_node_safety(&doc.root).is_ok());
+    }
 }
@@ -0,0 +1,574 @@
+//! Schemas for loaded documents
+//!
+//! A schema is a JSON Schema subset (`type`, `properties`, `required`,
+//! `additionalProperties`, `items`), given as a dict or loaded from YAML.
+//! With the `schema=` load option, string scalars are coerced to the type
+//! the schema declares for their position (`"8080"` becomes `8080`), so
+//! templated configs that arrive stringly-typed load with the right types.
+//! `validate` checks documents against the same schemas.
+//!
+//! Schemas used over and over can be compiled once with
+//! `register_schema(name, schema)`; both `validate` and `schema=` then
+//! accept the name in place of the schema.
+//!
+//! Coercion uses the YAML 1.2 core rules: a string becomes an integer only
+//! if it would have loaded as one unquoted. Strings are left alone where
+//! the schema allows them; anything that is not a string is never changed.
+
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use pyo3::exceptions::{PyKeyError, PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyString;
+use serde_yaml::{Mapping, Number, Value};
+
+use crate::compose::{Node, NodeKind};
+use crate::error::{SchemaViolation, YAMLError};
+use crate::events::Mark;
+use crate::resolve::{self, Int, Scalar};
+use crate::sourcemap;
+use crate::types::python_to_yaml;
+
+/// JSON Schema primitive types
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Type {
+    String,
+    Integer,
+    Number,
+    Boolean,
+    Null,
+    Object,
+    Array,
+}
+
+impl Type {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "string" => Some(Type::String),
+            "integer" => Some(Type::Integer),
+            "number" => Some(Type::Number),
+            "boolean" => Some(Type::Boolean),
+            "null" => Some(Type::Null),
+            "object" => Some(Type::Object),
+            "array" => Some(Type::Array),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Type::String => "string",
+            Type::Integer => "integer",
+            Type::Number => "number",
+            Type::Boolean => "boolean",
+            Type::Null => "null",
+            Type::Object => "object",
+            Type::Array => "array",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Schema {
+    /// Allowed types (empty: any)
+    pub types: Vec<Type>,
+    /// Schemas of known mapping keys, in declaration order
+    pub properties: Vec<(String, Schema)>,
+    /// Keys that must be present
+    pub required: Vec<String>,
+    /// Schema of keys not listed in `properties`
+    pub additional: Option<Box<Schema>>,
+    /// `additionalProperties: false`: keys not in `properties` are errors
+    pub closed: bool,
+    /// Schema of every sequence item
+    pub items: Option<Box<Schema>>,
+}
+
+impl Schema {
+    /// Build a schema from its JSON Schema form
+    ///
+    /// Unknown keywords are ignored, so full JSON Schemas can be passed in.
+    ///
+    /// # Errors
+    /// * A description of the first malformed keyword
+    pub fn from_value(value: &Value) -> Result<Self, String> {
+        let map = value
+            .as_mapping()
+            .ok_or_else(|| "a schema must be a mapping".to_string())?;
+        let mut schema = Schema::default();
+
+        match map.get("type") {
+            None => {}
+            Some(Value::String(name)) => schema.types.push(parse_type(name)?),
+            Some(Value::Sequence(names)) => {
+                for name in names {
+                    let name = name
+                        .as_str()
+                        .ok_or_else(|| "'type' entries must be strings".to_string())?;
+                    schema.types.push(parse_type(name)?);
+                }
+            }
+            Some(_) => return Err("'type' must be a string or a list".to_string()),
+        }
+
+        if let Some(properties) = map.get("properties") {
+            let properties = properties
+                .as_mapping()
+                .ok_or_else(|| "'properties' must be a mapping".to_string())?;
+            for (key, property) in properties {
+                let key = key
+                    .as_str()
+                    .ok_or_else(|| "property names must be strings".to_string())?;
+                let property =
+                    Schema::from_value(property).map_err(|err| format!("{}: {}", key, err))?;
+                schema.properties.push((key.to_string(), property));
+            }
+        }
+
+        if let Some(required) = map.get("required") {
+            let required = required
+                .as_sequence()
+                .ok_or_else(|| "'required' must be a list".to_string())?;
+            for key in required {
+                let key = key
+                    .as_str()
+                    .ok_or_else(|| "'required' entries must be strings".to_string())?;
+                schema.required.push(key.to_string());
+            }
+        }
+
+        match map.get("additionalProperties") {
+            None | Some(Value::Bool(true)) => {}
+            Some(Value::Bool(false)) => schema.closed = true,
+            Some(additional @ Value::Mapping(_)) => {
+                schema.additional = Some(Box::new(Schema::from_value(additional)?));
+            }
+            Some(_) => return Err("'additionalProperties' must be a bool or a schema".to_string()),
+        }
+
+        if let Some(items) = map.get("items") {
+            schema.items = Some(Box::new(
+                Schema::from_value(items).map_err(|err| format!("items: {}", err))?,
+            ));
+        }
+
+        Ok(schema)
+    }
+
+    /// Schema of the value under mapping key `key`
+    pub fn property(&self, key: &str) -> Option<&Schema> {
+        self.properties
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, schema)| schema)
+            .or(self.additional.as_deref())
+    }
+
+    /// Is `key` one of the declared `properties`?
+    pub fn declares(&self, key: &str) -> bool {
+        self.properties.iter().any(|(name, _)| name == key)
+    }
+
+    pub fn allows(&self, ty: Type) -> bool {
+        self.types.is_empty() || self.types.contains(&ty)
+    }
+}
+
+/// Compiled schemas by name
+fn registry() -> &'static RwLock<HashMap<String, Arc<Schema>>> {
+    static REGISTRY: OnceLock<RwLock<HashMap<String, Arc<Schema>>>> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+/// Compile a schema argument: a registered name, or the schema itself
+///
+/// # Errors
+/// * `KeyError` for a name that was never registered
+/// * `ValueError` for a malformed schema
+pub fn from_python(schema: &Bound<'_, PyAny>) -> PyResult<Arc<Schema>> {
+    if let Ok(name) = schema.downcast::<PyString>() {
+        let name = name.to_cow()?;
+        return registry()
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .get(name.as_ref())
+            .cloned()
+            .ok_or_else(|| PyKeyError::new_err(format!("no schema registered as '{}'", name)));
+    }
+    Schema::from_value(&python_to_yaml(schema)?)
+        .map(Arc::new)
+        .map_err(|err| PyValueError::new_err(format!("invalid schema: {}", err)))
+}
+
+/// Compile a schema once and make it available by name
+///
+/// # Arguments
+/// * `name` - Name to pass as `schema` to `validate` or the loaders
+/// * `schema` - The schema, as for `validate`; registering a name again
+///   replaces its schema
+///
+/// # Example
+/// ```python
+/// rustyyaml.register_schema("deployment", schema)
+/// for text in manifests:
+///     report = rustyyaml.validate(text, "deployment")
+/// ```
+#[pyfunction]
+pub fn register_schema(name: &str, schema: &Bound<'_, PyAny>) -> PyResult<()> {
+    if schema.is_instance_of::<PyString>() {
+        return Err(PyTypeError::new_err("schema must be a mapping, not a name"));
+    }
+    let schema = from_python(schema)?;
+    registry()
+        .write()
+        .unwrap_or_else(|err| err.into_inner())
+        .insert(name.to_string(), schema);
+    Ok(())
+}
+
+fn parse_type(name: &str) -> Result<Type, String> {
+    Type::from_name(name).ok_or_else(|| format!("unknown type '{}'", name))
+}
+
+/// The first declared type `text` can be read as
+///
+/// # Errors
+/// * A message naming the expected types
+fn coerce_text(text: &str, schema: &Schema) -> Result<Scalar, String> {
+    let resolved = resolve::resolve_core(text.trim());
+    for ty in &schema.types {
+        match (ty, &resolved) {
+            (Type::Integer, Scalar::Int(_))
+            | (Type::Number, Scalar::Int(_) | Scalar::Float(_))
+            | (Type::Boolean, Scalar::Bool(_))
+            | (Type::Null, Scalar::Null) => return Ok(resolved),
+            _ => {}
+        }
+    }
+    let expected: Vec<&str> = schema.types.iter().map(|ty| ty.name()).collect();
+    Err(format!(
+        "expected {}, got '{}'",
+        expected.join(" or "),
+        text
+    ))
+}
+
+/// Does `schema` ask for `text` to be coerced?
+fn wants_coercion(schema: &Schema) -> bool {
+    !schema.allows(Type::String)
+        && schema.types.iter().any(|ty| {
+            matches!(
+                ty,
+                Type::Integer | Type::Number | Type::Boolean | Type::Null
+            )
+        })
+}
+
+/// Coerce string scalars in a parsed value
+///
+/// # Errors
+/// * `SchemaViolation` listing every string that could not be coerced
+pub fn coerce_value(value: &mut Value, schema: &Schema) -> Result<(), YAMLError> {
+    let mut violations = Vec::new();
+    let mut pointer = String::new();
+    walk_value(value, schema, &mut pointer, &mut violations);
+    violations_result(violations)
+}
+
+fn walk_value(
+    value: &mut Value,
+    schema: &Schema,
+    pointer: &mut String,
+    violations: &mut Vec<SchemaViolation>,
+) {
+    let len = pointer.len();
+    match value {
+        Value::String(text) if wants_coercion(schema) => match coerce_text(text, schema) {
+            Ok(scalar) => match scalar_value(scalar) {
+                Some(coerced) => *value = coerced,
+                None => violations.push(violation(
+                    pointer,
+                    None,
+                    format!("'{}' is out of range", text),
+                )),
+            },
+            Err(message) => violations.push(violation(pointer, None, message)),
+        },
+        Value::Mapping(map) => walk_mapping(map, schema, pointer, violations),
+        Value::Sequence(items) => {
+            if let Some(item_schema) = &schema.items {
+                for (index, item) in items.iter_mut().enumerate() {
+                    pointer.push('/');
+                    pointer.push_str(&index.to_string());
+                    walk_value(item, item_schema, pointer, violations);
+                    pointer.truncate(len);
+                }
+            }
+        }
+        Value::Tagged(tagged) => walk_value(&mut tagged.value, schema, pointer, violations),
+        _ => {}
+    }
+}
+
+fn walk_mapping(
+    map: &mut Mapping,
+    schema: &Schema,
+    pointer: &mut String,
+    violations: &mut Vec<SchemaViolation>,
+) {
+    let len = pointer.len();
+    for (key, value) in map.iter_mut() {
+        let Some(key) = key.as_str() else {
+            continue;
+        };
+        if let Some(property) = schema.property(key) {
+            pointer.push('/');
+            pointer.push_str(&sourcemap::escape(key));
+            walk_value(value, property, pointer, violations);
+            pointer.truncate(len);
+        }
+    }
+}
+
+/// A resolved null, bool, int or float as a `Value` (`None` for other kinds)
+pub fn scalar_value(scalar: Scalar) -> Option<Value> {
+    match scalar {
+        Scalar::Null => Some(Value::Null),
+        Scalar::Bool(b) => Some(Value::Bool(b)),
+        Scalar::Int(Int::Small(i)) => Some(Value::Number(i.into())),
+        Scalar::Float(f) => Some(Value::Number(Number::from(f))),
+        _ => None,
+    }
+}
+
+/// Coerce string scalars in a composed node tree
+///
+/// Coerced scalars get the explicit tag of their new type, so construction
+/// treats `"8080"` like `!!int "8080"`. Plain scalars `rules` already
+/// resolves to something other than a string are left alone.
+///
+/// # Errors
+/// * `SchemaViolation` listing every string that could not be coerced
+pub fn coerce_node(
+    node: &mut Node,
+    schema: &Schema,
+    rules: resolve::Schema,
+) -> Result<(), YAMLError> {
+    let mut violations = Vec::new();
+    let mut pointer = String::new();
+    walk_node(node, schema, rules, &mut pointer, &mut violations);
+    violations_result(violations)
+}
+
+fn walk_node(
+    node: &mut Node,
+    schema: &Schema,
+    rules: resolve::Schema,
+    pointer: &mut String,
+    violations: &mut Vec<SchemaViolation>,
+) {
+    let len = pointer.len();
+    let start = node.start;
+    let plain = node.is_plain_untagged();
+    let is_string = node.tag.is_none() || node.tag.as_deref() == Some(resolve::TAG_STR);
+    match &mut node.kind {
+        NodeKind::Scalar { value, .. } if wants_coercion(schema) && is_string => {
+            // Plain scalars that already resolve to a type are not strings
+            if plain && !matches!(resolve::resolve_plain(value, rules), Scalar::Str) {
+                return;
+            }
+            match coerce_text(value, schema) {
+                Ok(scalar) => {
+                    *value = value.trim().to_string();
+                    node.tag = Some(scalar_tag(&scalar).to_string());
+                }
+                Err(message) => violations.push(violation(pointer, Some(start), message)),
+            }
+        }
+        NodeKind::Mapping { pairs, .. } => {
+            for (key, value) in pairs.iter_mut() {
+                let NodeKind::Scalar { value: key, .. } = &key.kind else {
+                    continue;
+                };
+                if let Some(property) = schema.property(key) {
+                    pointer.push('/');
+                    pointer.push_str(&sourcemap::escape(key));
+                    walk_node(value, property, rules, pointer, violations);
+                    pointer.truncate(len);
+                }
+            }
+        }
+        NodeKind::Sequence { items, .. } => {
+            if let Some(item_schema) = &schema.items {
+                for (index, item) in items.iter_mut().enumerate() {
+                    pointer.push('/');
+                    pointer.push_str(&index.to_string());
+                    walk_node(item, item_schema, rules, pointer, violations);
+                    pointer.truncate(len);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn scalar_tag(scalar: &Scalar) -> &'static str {
+    match scalar {
+        Scalar::Null => resolve::TAG_NULL,
+        Scalar::Bool(_) => resolve::TAG_BOOL,
+        Scalar::Int(_) => resolve::TAG_INT,
+        _ => resolve::TAG_FLOAT,
+    }
+}
+
+fn violation(pointer: &str, mark: Option<Mark>, message: String) -> SchemaViolation {
+    SchemaViolation {
+        pointer: pointer.to_string(),
+        mark,
+        message,
+    }
+}
+
+/// Fill in the positions of violations found on a `Value`
+///
+/// `root` is the same document composed from the source; the source map
+/// translates each JSON pointer back to a line and column.
+pub fn locate(violations: &mut [SchemaViolation], root: &Node) {
+    let spans = sourcemap::build(root);
+    for violation in violations.iter_mut().filter(|v| v.mark.is_none()) {
+        violation.mark = spans
+            .iter()
+            .find(|span| span.pointer == violation.pointer)
+            .map(|span| span.start);
+    }
+}
+
+fn violations_result(violations: Vec<SchemaViolation>) -> Result<(), YAMLError> {
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(YAMLError::Schema { violations })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compose;
+
+    fn yaml(text: &str) -> Value {
+        serde_yaml::from_str(text).unwrap()
+    }
+
+    fn schema(text: &str) -> Schema {
+        Schema::from_value(&yaml(text)).unwrap()
+    }
+
+    const PORTS: &str = "
+type: object
+properties:
+  port: {type: integer}
+  debug: {type: boolean}
+  ratio: {type: number}
+  name: {type: string}
+  ports: {type: array, items: {type: integer}}
+additionalProperties: {type: [integer, 'null']}
+";
+
+    #[test]
+    fn test_from_value() {
+        let parsed = schema(PORTS);
+        assert_eq!(parsed.types, [Type::Object]);
+        assert_eq!(parsed.property("port").unwrap().types, [Type::Integer]);
+        assert_eq!(
+            parsed.property("other").unwrap().types,
+            [Type::Integer, Type::Null]
+        );
+        assert!(!parsed.closed);
+        let closed = schema("{required: [name], additionalProperties: false}");
+        assert!(closed.closed);
+        assert_eq!(closed.required, ["name"]);
+        assert!(Schema::from_value(&yaml("{type: decimal}")).is_err());
+        assert!(Schema::from_value(&yaml("[integer]")).is_err());
+    }
+
+    #[test]
+    fn test_coerce_value() {
+        let mut value = yaml(
+            "{port: '8080', debug: 'true', ratio: '0.5', name: '42', ports: ['80', 443], \
+             other: '', kept: 1}",
+        );
+        coerce_value(&mut value, &schema(PORTS)).unwrap();
+        assert_eq!(
+            value,
+            yaml(
+                "{port: 8080, debug: true, ratio: 0.5, name: '42', ports: [80, 443], \
+                 other: null, kept: 1}"
+            )
+        );
+    }
+
+    #[test]
+    fn test_coerce_value_failures() {
+        let mut value = yaml("{port: http, ports: ['80', eighty], debug: 'yes'}");
+        let err = coerce_value(&mut value, &schema(PORTS)).unwrap_err();
+        let YAMLError::Schema { violations } = err else {
+            panic!("expected a schema error");
+        };
+        let pointers: Vec<&str> = violations.iter().map(|v| v.pointer.as_str()).collect();
+        assert_eq!(pointers, ["/port", "/ports/1", "/debug"]);
+        assert_eq!(violations[0].message, "expected integer, got 'http'");
+        assert_eq!(violations[0].mark, None);
+    }
+
+    #[test]
+    fn test_locate_violations() {
+        let text = "name: web\nports:\n  - '80'\n  - eighty\n";
+        let mut value = yaml(text);
+        let err = coerce_value(&mut value, &schema(PORTS)).unwrap_err();
+        let YAMLError::Schema { mut violations } = err else {
+            panic!("expected a schema error");
+        };
+        let document = compose::compose_one(text).unwrap().unwrap();
+        locate(&mut violations, &document.root);
+        let mark = violations[0].mark.unwrap();
+        assert_eq!((mark.line, mark.column), (3, 4));
+
+        // The composer path knows positions already
+        let mut document = compose::compose_one(text).unwrap().unwrap();
+        let err = coerce_node(&mut document.root, &schema(PORTS), resolve::Schema::Core);
+        let Err(YAMLError::Schema { violations: found }) = err else {
+            panic!("expected a schema error");
+        };
+        assert_eq!(found[0].mark, Some(mark));
+    }
+
+    #[test]
+    fn test_coerce_node() {
+        let mut document = compose::compose_one("port: '8080'\nname: '42'\n")
+            .unwrap()
+            .unwrap();
+        coerce_node(&mut document.root, &schema(PORTS), resolve::Schema::Core).unwrap();
+        let NodeKind::Mapping { pairs, .. } = &document.root.kind else {
+            panic!("expected a mapping");
+        };
+        assert_eq!(pairs[0].1.tag.as_deref(), Some(resolve::TAG_INT));
+        assert_eq!(pairs[1].1.tag, None);
+    }
+
+    #[test]
+    fn test_registry() {
+        Python::with_gil(|py| {
+            let dict = py.eval_bound("{'type': 'integer'}", None, None).unwrap();
+            register_schema("test-registry", &dict).unwrap();
+            let name = PyString::new_bound(py, "test-registry");
+            let first = from_python(&name).unwrap();
+            assert!(Arc::ptr_eq(&first, &from_python(&name).unwrap()));
+            assert_eq!(first.types, [Type::Integer]);
+
+            assert!(from_python(&PyString::new_bound(py, "unregistered")).is_err());
+            assert!(register_schema("test-registry", &name).is_err());
+        });
+    }
+}
@@ -0,0 +1,430 @@
+//! Search YAML files for values at a key path
+//!
+//! Key paths use dots for mapping keys and brackets for sequence items:
+//! `spec.template.spec.containers[*].image`. `*` matches any key (or, in
+//! brackets, any item). Hits report the concrete path that matched, e.g.
+//! `spec.template.spec.containers[1].image`.
+//!
+//! `index_keys` goes the other way and lists every key path defined in a
+//! directory, using the composer so each definition keeps its position.
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+use rayon::prelude::*;
+use serde_yaml::Value;
+use std::path::{Path, PathBuf};
+
+use crate::batch;
+use crate::compose::{self, Node, NodeKind};
+use crate::error::YAMLError;
+use crate::events::Mark;
+use crate::options::LoadOptions;
+use crate::parser::{self, Parsed};
+use crate::sourcemap;
+use crate::types::yaml_to_python;
+
+/// `(file, path, value)` found by `grep`
+type Hit = (PathBuf, String, Value);
+
+/// One step of a key path
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Segment {
+    Key(String),
+    AnyKey,
+    Index(usize),
+    AnyIndex,
+}
+
+/// Parse a key path such as `spec.containers[*].image`
+///
+/// Returns `None` if the path is empty or malformed.
+pub fn parse_key_path(path: &str) -> Option<Vec<Segment>> {
+    let mut segments = Vec::new();
+    let mut rest = path;
+    let mut expect_key = true;
+
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('[') {
+            let (index, after) = after.split_once(']')?;
+            segments.push(match index {
+                "*" => Segment::AnyIndex,
+                _ => Segment::Index(index.parse().ok()?),
+            });
+            rest = after;
+            expect_key = false;
+            continue;
+        }
+        if !expect_key {
+            rest = rest.strip_prefix('.')?;
+        }
+        let end = rest.find(['.', '[']).unwrap_or(rest.len());
+        let key = &rest[..end];
+        segments.push(match key {
+            "" => return None,
+            "*" => Segment::AnyKey,
+            _ => Segment::Key(key.to_string()),
+        });
+        rest = &rest[end..];
+        expect_key = false;
+    }
+
+    (!segments.is_empty()).then_some(segments)
+}
+
+/// Every value at `segments` below `value`, with its concrete path
+pub fn find<'v>(value: &'v Value, segments: &[Segment]) -> Vec<(String, &'v Value)> {
+    let mut hits = Vec::new();
+    walk(value, segments, String::new(), &mut hits);
+    hits
+}
+
+fn walk<'v>(
+    value: &'v Value,
+    segments: &[Segment],
+    path: String,
+    hits: &mut Vec<(String, &'v Value)>,
+) {
+    let value = match value {
+        Value::Tagged(tagged) => &tagged.value,
+        value => value,
+    };
+    let Some((segment, rest)) = segments.split_first() else {
+        hits.push((path, value));
+        return;
+    };
+
+    match (segment, value) {
+        (Segment::Key(_) | Segment::AnyKey, Value::Mapping(map)) => {
+            for (key, child) in map {
+                let Some(key) = key_text(key) else {
+                    continue;
+                };
+                if matches!(segment, Segment::Key(name) if *name != key) {
+                    continue;
+                }
+                let child_path = if path.is_empty() {
+                    key
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                walk(child, rest, child_path, hits);
+            }
+        }
+        (Segment::Index(index), Value::Sequence(items)) => {
+            if let Some(child) = items.get(*index) {
+                walk(child, rest, format!("{}[{}]", path, index), hits);
+            }
+        }
+        (Segment::AnyIndex, Value::Sequence(items)) => {
+            for (index, child) in items.iter().enumerate() {
+                walk(child, rest, format!("{}[{}]", path, index), hits);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Nodes at `segments` below `node`, with their JSON pointers
+///
+/// Matches like `find`, but on the node tree so the matches keep
+/// their positions. Aliases are not followed.
+pub fn find_nodes<'n>(node: &'n Node, segments: &[Segment]) -> Vec<(String, &'n Node)> {
+    let mut found = Vec::new();
+    find_into(node, segments, &mut String::new(), &mut found);
+    found
+}
+
+fn find_into<'n>(
+    node: &'n Node,
+    segments: &[Segment],
+    pointer: &mut String,
+    found: &mut Vec<(String, &'n Node)>,
+) {
+    let Some((segment, rest)) = segments.split_first() else {
+        found.push((pointer.clone(), node));
+        return;
+    };
+    let len = pointer.len();
+    match (segment, &node.kind) {
+        (Segment::Key(_) | Segment::AnyKey, NodeKind::Mapping { pairs, .. }) => {
+            for (key, value) in pairs {
+                let NodeKind::Scalar { value: name, .. } = &key.kind else {
+                    continue;
+                };
+                if matches!(segment, Segment::Key(wanted) if wanted != name) {
+                    continue;
+                }
+                pointer.push('/');
+                pointer.push_str(&sourcemap::escape(name));
+                find_into(value, rest, pointer, found);
+                pointer.truncate(len);
+            }
+        }
+        (Segment::Index(_) | Segment::AnyIndex, NodeKind::Sequence { items, .. }) => {
+            for (index, item) in items.iter().enumerate() {
+                if matches!(segment, Segment::Index(wanted) if *wanted != index) {
+                    continue;
+                }
+                pointer.push('/');
+                pointer.push_str(&index.to_string());
+                find_into(item, rest, pointer, found);
+                pointer.truncate(len);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Scalar keys as they would be written in a key path
+pub fn key_text(key: &Value) -> Option<String> {
+    match key {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Find values at a key path in every YAML file of a directory
+///
+/// Files are read and searched in parallel; only the hits are converted
+/// to Python. Every document of a multi-document file is searched.
+///
+/// # Arguments
+/// * `directory` - Directory containing .yaml/.yml files
+/// * `key_path` - Path to look for, e.g. `spec.containers[*].image`
+/// * `recursive` - If true, search subdirectories
+///
+/// # Returns
+/// List of `(file, path, value)` tuples, ordered by file name
+///
+/// # Example
+/// ```python
+/// for file, path, image in rustyyaml.grep("./k8s", "spec.containers[*].image"):
+///     print(f"{file}: {path} = {image}")
+/// ```
+#[pyfunction]
+#[pyo3(signature = (directory, key_path, recursive=false))]
+pub fn grep(
+    py: Python,
+    directory: PathBuf,
+    key_path: &str,
+    recursive: bool,
+) -> PyResult<Vec<(PathBuf, String, PyObject)>> {
+    let segments = parse_key_path(key_path)
+        .ok_or_else(|| PyValueError::new_err(format!("invalid key path '{}'", key_path)))?;
+    let mut yaml_files = batch::find_yaml_files(&directory, recursive)?;
+    yaml_files.sort();
+
+    let found: Result<Vec<Vec<Hit>>, YAMLError> = py.allow_threads(|| {
+        yaml_files
+            .par_iter()
+            .map(|path| search_file(path, &segments))
+            .collect()
+    });
+
+    let mut results = Vec::new();
+    for (file, path, value) in found?.into_iter().flatten() {
+        results.push((file, path, yaml_to_python(py, &value)?));
+    }
+    Ok(results)
+}
+
+/// Search every document of one file (no GIL needed)
+fn search_file(path: &Path, segments: &[Segment]) -> Result<Vec<Hit>, YAMLError> {
+    let file = path.to_path_buf();
+    let content = batch::read_file(path)?;
+    let documents = parser::parse_documents(&content, &LoadOptions::default(), true)?;
+
+    let mut hits = Vec::new();
+    for document in &documents {
+        if let Parsed::Value(value) = document {
+            for (path, value) in find(value, segments) {
+                hits.push((file.clone(), path, value.clone()));
+            }
+        }
+    }
+    Ok(hits)
+}
+
+/// Index every key path defined in a directory
+///
+/// Paths use the same syntax as `grep` results (`spec.containers[0].image`).
+/// Every document of a multi-document file is indexed; aliases are not
+/// followed.
+///
+/// # Arguments
+/// * `directory` - Directory containing .yaml/.yml files
+/// * `recursive` - If true, index subdirectories
+///
+/// # Returns
+/// Dict of key path to a list of `(file, line, column)` definitions
+/// (1-based), in file name order
+///
+/// # Example
+/// ```python
+/// index = rustyyaml.index_keys("./overlays", recursive=True)
+/// for file, line, column in index.get("spec.replicas", []):
+///     print(f"{file}:{line}")
+/// ```
+#[pyfunction]
+#[pyo3(signature = (directory, recursive=false))]
+pub fn index_keys(py: Python, directory: PathBuf, recursive: bool) -> PyResult<PyObject> {
+    let mut yaml_files = batch::find_yaml_files(&directory, recursive)?;
+    yaml_files.sort();
+
+    let indexed: Result<Vec<Vec<(String, Mark)>>, YAMLError> =
+        py.allow_threads(|| yaml_files.par_iter().map(|path| index_file(path)).collect());
+
+    let index = PyDict::new_bound(py);
+    for (path, keys) in yaml_files.iter().zip(indexed?) {
+        for (key_path, mark) in keys {
+            let definition = (path, mark.line + 1, mark.column + 1);
+            match index.get_item(&key_path)? {
+                Some(definitions) => definitions.downcast::<PyList>()?.append(definition)?,
+                None => index.set_item(key_path, vec![definition])?,
+            }
+        }
+    }
+    Ok(index.into())
+}
+
+/// Key paths and key positions of every document in one file (no GIL needed)
+fn index_file(path: &Path) -> Result<Vec<(String, Mark)>, YAMLError> {
+    let content = batch::read_file(path)?;
+    let mut keys = Vec::new();
+    for document in compose::compose_all(&content)? {
+        index_node(&document.root, "", &mut keys);
+    }
+    Ok(keys)
+}
+
+fn index_node(node: &Node, path: &str, keys: &mut Vec<(String, Mark)>) {
+    match &node.kind {
+        NodeKind::Mapping { pairs, .. } => {
+            for (key, value) in pairs {
+                let NodeKind::Scalar { value: name, .. } = &key.kind else {
+                    continue;
+                };
+                let key_path = if path.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}.{}", path, name)
+                };
+                keys.push((key_path.clone(), key.start));
+                index_node(value, &key_path, keys);
+            }
+        }
+        NodeKind::Sequence { items, .. } => {
+            for (index, item) in items.iter().enumerate() {
+                index_node(item, &format!("{}[{}]", path, index), keys);
+            }
+        }
+        NodeKind::Scalar { .. } | NodeKind::Alias { .. } => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hits(yaml: &str, path: &str) -> Vec<(String, Value)> {
+        let value: Value = serde_yaml::from_str(yaml).unwrap();
+        let segments = parse_key_path(path).unwrap();
+        find(&value, &segments)
+            .into_iter()
+            .map(|(path, value)| (path, value.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_key_path() {
+        assert_eq!(
+            parse_key_path("spec.containers[*].image").unwrap(),
+            [
+                Segment::Key("spec".to_string()),
+                Segment::Key("containers".to_string()),
+                Segment::AnyIndex,
+                Segment::Key("image".to_string()),
+            ]
+        );
+        assert_eq!(
+            parse_key_path("[0].*").unwrap(),
+            [Segment::Index(0), Segment::AnyKey]
+        );
+        assert!(parse_key_path("").is_none());
+        assert!(parse_key_path("a..b").is_none());
+        assert!(parse_key_path("a[x]").is_none());
+        assert!(parse_key_path("a[0]b").is_none());
+    }
+
+    #[test]
+    fn test_find_wildcards() {
+        let yaml = "spec:\n  containers:\n    - {name: a, image: nginx}\n    - {name: b}\n    \
+                    - {name: c, image: redis}\n";
+        let found = hits(yaml, "spec.containers[*].image");
+        let paths: Vec<&str> = found.iter().map(|(p, _)| p.as_str()).collect();
+        assert_eq!(
+            paths,
+            ["spec.containers[0].image", "spec.containers[2].image"]
+        );
+        assert_eq!(found[1].1, Value::String("redis".to_string()));
+
+        let found = hits("a: {x: 1}\nb: {x: 2}\nc: 3\n", "*.x");
+        assert_eq!(found.len(), 2);
+    }
+
+    #[test]
+    fn test_find_nodes() {
+        let document = compose::compose_one(
+            "spec:\n  containers:\n    - image: a\n    - name: x\n    - image: b\n",
+        )
+        .unwrap()
+        .unwrap();
+        let segments = parse_key_path("spec.containers[*].image").unwrap();
+        let found: Vec<(String, usize)> = find_nodes(&document.root, &segments)
+            .into_iter()
+            .map(|(pointer, node)| (pointer, node.start.line))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("/spec/containers/0/image".to_string(), 2),
+                ("/spec/containers/2/image".to_string(), 4),
+            ]
+        );
+        let segments = parse_key_path("spec.containers[1]").unwrap();
+        assert_eq!(find_nodes(&document.root, &segments).len(), 1);
+    }
+
+    #[test]
+    fn test_index_node() {
+        let document = compose::compose_one("spec:\n  replicas: 3\n  ports:\n    - port: 80\n")
+            .unwrap()
+            .unwrap();
+        let mut keys = Vec::new();
+        index_node(&document.root, "", &mut keys);
+        let found: Vec<(&str, usize, usize)> = keys
+            .iter()
+            .map(|(path, mark)| (path.as_str(), mark.line, mark.column))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("spec", 0, 0),
+                ("spec.replicas", 1, 2),
+                ("spec.ports", 2, 2),
+                ("spec.ports[0].port", 3, 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_index_and_missing() {
+        assert_eq!(hits("[a, b]", "[1]")[0].0, "[1]");
+        assert!(hits("[a, b]", "[5]").is_empty());
+        assert!(hits("a: 1", "a.b").is_empty());
+        assert_eq!(hits("8080: web", "8080").len(), 1);
+    }
+}
@@ -0,0 +1,168 @@
+//! Structural sharing across batch results (`share_subtrees=True`)
+//!
+//! Thousands of overlays loaded for read-only analysis tend to repeat the
+//! same blocks (labels, resource limits) over and over. With sharing, every
+//! occurrence of a subtree that is structurally identical to one seen
+//! earlier in the batch is the same Python object, so each distinct block
+//! is in memory once.
+//!
+//! Shared objects must not change under the other documents, so mappings
+//! become read-only `types.MappingProxyType` views and sequences become
+//! tuples. Subtrees are matched bottom-up: children are shared first, so
+//! two containers are identical exactly when they hold the same child
+//! objects in the same order, which a hash of their addresses finds.
+//! Equal scalars are shared too (by type and value).
+
+use std::collections::HashMap;
+
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList, PyTuple, PyType};
+
+use crate::options::LoadOptions;
+
+/// A container, by the (already shared) objects it holds
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+enum Shape {
+    Sequence(Vec<usize>),
+    /// Keys and values, alternating
+    Mapping(Vec<usize>),
+}
+
+/// Objects seen so far in one batch load
+pub struct Sharing<'py> {
+    py: Python<'py>,
+    /// `(type, value)` to the first equal scalar
+    scalars: Bound<'py, PyDict>,
+    /// Each distinct container; holding them keeps the addresses in
+    /// `Shape`s valid
+    containers: HashMap<Shape, PyObject>,
+    /// Objects that aren't shared, kept alive for the same reason
+    kept: Vec<PyObject>,
+    mapping_proxy: Bound<'py, PyType>,
+}
+
+impl<'py> Sharing<'py> {
+    pub fn new(py: Python<'py>) -> PyResult<Self> {
+        let mapping_proxy = py
+            .import_bound("types")?
+            .getattr("MappingProxyType")?
+            .downcast_into::<PyType>()?;
+        Ok(Sharing {
+            py,
+            scalars: PyDict::new_bound(py),
+            containers: HashMap::new(),
+            kept: Vec::new(),
+            mapping_proxy,
+        })
+    }
+
+    /// A `Sharing` if the options ask for one
+    pub fn for_options(py: Python<'py>, options: &LoadOptions) -> PyResult<Option<Self>> {
+        options.share_subtrees.then(|| Sharing::new(py)).transpose()
+    }
+
+    /// The shared, frozen equivalent of `obj`
+    ///
+    /// Plain dicts, lists and tuples are rebuilt from shared children;
+    /// other mappings (`dict_class`, `Namespace`, marked containers) and
+    /// objects are kept as they are.
+    pub fn share(&mut self, obj: &Bound<'py, PyAny>) -> PyResult<PyObject> {
+        if obj.is_exact_instance_of::<PyDict>() {
+            let mut items = Vec::new();
+            for (key, value) in obj.downcast::<PyDict>()?.iter() {
+                items.push(self.share(&key)?);
+                items.push(self.share(&value)?);
+            }
+            let shape = Shape::Mapping(items.iter().map(|item| item.as_ptr() as usize).collect());
+            if let Some(shared) = self.containers.get(&shape) {
+                return Ok(shared.clone_ref(self.py));
+            }
+            let dict = PyDict::new_bound(self.py);
+            for pair in items.chunks(2) {
+                dict.set_item(&pair[0], &pair[1])?;
+            }
+            let frozen = self.mapping_proxy.call1((dict,))?.unbind();
+            self.containers.insert(shape, frozen.clone_ref(self.py));
+            return Ok(frozen);
+        }
+        if obj.is_exact_instance_of::<PyList>() || obj.is_exact_instance_of::<PyTuple>() {
+            let items = obj
+                .iter()?
+                .map(|item| self.share(&item?))
+                .collect::<PyResult<Vec<_>>>()?;
+            let shape = Shape::Sequence(items.iter().map(|item| item.as_ptr() as usize).collect());
+            if let Some(shared) = self.containers.get(&shape) {
+                return Ok(shared.clone_ref(self.py));
+            }
+            let frozen: PyObject = PyTuple::new_bound(self.py, items).into();
+            self.containers.insert(shape, frozen.clone_ref(self.py));
+            return Ok(frozen);
+        }
+        if obj.is_instance_of::<PyDict>() {
+            // A mapping type of the caller's choosing
+            self.kept.push(obj.clone().unbind());
+            return Ok(obj.clone().unbind());
+        }
+        // The type is part of the key, so 1, 1.0 and True stay apart
+        let key = (obj.get_type(), obj);
+        match self.scalars.get_item(&key) {
+            Ok(Some(shared)) => Ok(shared.unbind()),
+            Ok(None) => {
+                self.scalars.set_item(&key, obj)?;
+                Ok(obj.clone().unbind())
+            }
+            // Unhashable objects aren't shared
+            Err(_) => {
+                self.kept.push(obj.clone().unbind());
+                Ok(obj.clone().unbind())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identical_subtrees_shared() {
+        Python::with_gil(|py| {
+            let mut sharing = Sharing::new(py).unwrap();
+            let first = py
+                .eval_bound("{'labels': {'app': 'web'}, 'ports': [80, 443]}", None, None)
+                .unwrap();
+            let second = py
+                .eval_bound(
+                    "{'labels': {'app': 'web'}, 'ports': [80, 8080]}",
+                    None,
+                    None,
+                )
+                .unwrap();
+            let first = sharing.share(&first).unwrap().into_bound(py);
+            let second = sharing.share(&second).unwrap().into_bound(py);
+
+            let labels = first.get_item("labels").unwrap();
+            assert!(labels.is(&second.get_item("labels").unwrap()));
+            assert_eq!(labels.get_type().name().unwrap(), "mappingproxy");
+            let ports = first.get_item("ports").unwrap();
+            assert!(!ports.is(&second.get_item("ports").unwrap()));
+            assert!(ports.is_instance_of::<PyTuple>());
+            assert!(first.set_item("labels", 1).is_err());
+        });
+    }
+
+    #[test]
+    fn test_scalars_keep_their_type() {
+        Python::with_gil(|py| {
+            let mut sharing = Sharing::new(py).unwrap();
+            let doc = py
+                .eval_bound("[1, 1.0, True, [1], [True]]", None, None)
+                .unwrap();
+            let shared = sharing.share(&doc).unwrap();
+            assert_eq!(
+                shared.bind(py).repr().unwrap().to_string(),
+                "(1, 1.0, True, (1,), (True,))"
+            );
+        });
+    }
+}
@@ -0,0 +1,688 @@
+//! Sharing parsed documents between processes (`dump_shm` / `load_shm`)
+//!
+//! A process pool that needs the same large config in every worker would
+//! otherwise re-parse it or unpickle a copy per worker. `dump_shm` encodes
+//! a document once into a compact binary file in shared memory
+//! (`/dev/shm` where there is one); `load_shm` maps that file read-only, so
+//! every worker reads the same pages, and returns views that decode values
+//! only when they are accessed.
+//!
+//! Layout (little-endian): an 8-byte magic, the offset of the root node and
+//! four bytes of padding, then the nodes. Each node is a tag byte and a
+//! payload; containers hold the offsets of their children, which are always
+//! written before them:
+//!
+//! | tag | node     | payload                                              |
+//! |-----|----------|------------------------------------------------------|
+//! | 0   | null     |                                                      |
+//! | 1/2 | bool     |                                                      |
+//! | 3   | int      | `i64`                                                |
+//! | 4   | int      | `u64` (above `i64::MAX`)                             |
+//! | 5   | float    | `f64`                                                |
+//! | 6   | string   | `u32` length, UTF-8 bytes                            |
+//! | 7   | sequence | `u32` count, `u32` offset per item                   |
+//! | 8   | mapping  | `u32` count, `u32` key and value offset per entry,   |
+//! |     |          | `u32` count and entry numbers of the string keys in  |
+//! |     |          | byte order (for binary search)                       |
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use memmap2::Mmap;
+use pyo3::exceptions::{PyIndexError, PyKeyError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::pyclass::CompareOp;
+use pyo3::types::{PyDict, PyList, PySlice, PyString, PyTuple};
+use serde_yaml::Value;
+
+use crate::error::exceptions;
+use crate::types;
+
+const MAGIC: &[u8; 8] = b"RYSHM\0\x01\0";
+const HEADER: usize = 16;
+
+const NULL: u8 = 0;
+const FALSE: u8 = 1;
+const TRUE: u8 = 2;
+const INT: u8 = 3;
+const UINT: u8 = 4;
+const FLOAT: u8 = 5;
+const STRING: u8 = 6;
+const SEQUENCE: u8 = 7;
+const MAPPING: u8 = 8;
+
+/// Regions written by this process, for unique names
+static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+fn too_large() -> PyErr {
+    PyValueError::new_err("document is too large to share (over 4 GiB encoded)")
+}
+
+/// Serializes a document into the binary layout
+struct Encoder {
+    buf: Vec<u8>,
+}
+
+impl Encoder {
+    fn offset(&self) -> PyResult<u32> {
+        u32::try_from(self.buf.len()).map_err(|_| too_large())
+    }
+
+    fn u32(&mut self, value: usize) -> PyResult<()> {
+        let value = u32::try_from(value).map_err(|_| too_large())?;
+        self.buf.extend_from_slice(&value.to_le_bytes());
+        Ok(())
+    }
+
+    /// Write `value` (children first), returning its offset
+    fn node(&mut self, value: &Value) -> PyResult<u32> {
+        match value {
+            Value::Sequence(items) => {
+                let children = items
+                    .iter()
+                    .map(|item| self.node(item))
+                    .collect::<PyResult<Vec<_>>>()?;
+                let offset = self.offset()?;
+                self.buf.push(SEQUENCE);
+                self.u32(children.len())?;
+                for child in children {
+                    self.buf.extend_from_slice(&child.to_le_bytes());
+                }
+                Ok(offset)
+            }
+            Value::Mapping(mapping) => {
+                let mut entries = Vec::with_capacity(mapping.len());
+                for (key, value) in mapping {
+                    entries.push((self.node(key)?, self.node(value)?));
+                }
+                let mut index: Vec<(usize, &str)> = mapping
+                    .keys()
+                    .enumerate()
+                    .filter_map(|(entry, key)| key.as_str().map(|key| (entry, key)))
+                    .collect();
+                index.sort_by(|a, b| a.1.as_bytes().cmp(b.1.as_bytes()));
+
+                let offset = self.offset()?;
+                self.buf.push(MAPPING);
+                self.u32(entries.len())?;
+                for (key, value) in entries {
+                    self.buf.extend_from_slice(&key.to_le_bytes());
+                    self.buf.extend_from_slice(&value.to_le_bytes());
+                }
+                self.u32(index.len())?;
+                for (entry, _) in index {
+                    self.u32(entry)?;
+                }
+                Ok(offset)
+            }
+            Value::Tagged(tagged) => self.node(&tagged.value),
+            scalar => {
+                let offset = self.offset()?;
+                match scalar {
+                    Value::Null => self.buf.push(NULL),
+                    Value::Bool(false) => self.buf.push(FALSE),
+                    Value::Bool(true) => self.buf.push(TRUE),
+                    Value::Number(n) => {
+                        if let Some(i) = n.as_i64() {
+                            self.buf.push(INT);
+                            self.buf.extend_from_slice(&i.to_le_bytes());
+                        } else if let Some(u) = n.as_u64() {
+                            self.buf.push(UINT);
+                            self.buf.extend_from_slice(&u.to_le_bytes());
+                        } else {
+                            self.buf.push(FLOAT);
+                            let f = n.as_f64().unwrap_or(f64::NAN);
+                            self.buf.extend_from_slice(&f.to_le_bytes());
+                        }
+                    }
+                    Value::String(s) => {
+                        self.buf.push(STRING);
+                        self.u32(s.len())?;
+                        self.buf.extend_from_slice(s.as_bytes());
+                    }
+                    _ => unreachable!("containers are handled above"),
+                }
+                Ok(offset)
+            }
+        }
+    }
+}
+
+/// Encode a document: header, nodes, root offset
+pub fn encode(value: &Value) -> PyResult<Vec<u8>> {
+    let mut encoder = Encoder {
+        buf: Vec::with_capacity(1024),
+    };
+    encoder.buf.extend_from_slice(MAGIC);
+    encoder.buf.extend_from_slice(&[0; HEADER - MAGIC.len()]);
+    let root = encoder.node(value)?;
+    encoder.buf[8..12].copy_from_slice(&root.to_le_bytes());
+    Ok(encoder.buf)
+}
+
+/// An encoded document, mapped from a file or (in tests) in memory
+pub struct Region {
+    data: Box<dyn AsRef<[u8]> + Send + Sync>,
+}
+
+fn corrupt() -> PyErr {
+    exceptions::YAMLError::new_err("shared document is corrupt or truncated")
+}
+
+/// A child offset read from the container at `parent`: children are written
+/// before their container, so anything else would let a corrupt region loop
+fn child(parent: usize, offset: usize) -> PyResult<usize> {
+    if offset < parent {
+        Ok(offset)
+    } else {
+        Err(corrupt())
+    }
+}
+
+impl Region {
+    /// Check the header, returning the region and its root offset
+    fn new(data: Box<dyn AsRef<[u8]> + Send + Sync>) -> PyResult<(Arc<Region>, usize)> {
+        let region = Region { data };
+        if !region.bytes().starts_with(MAGIC) {
+            return Err(PyValueError::new_err("not a rustyyaml shared document"));
+        }
+        let root = region.u32(8)?;
+        Ok((Arc::new(region), root))
+    }
+
+    fn bytes(&self) -> &[u8] {
+        (*self.data).as_ref()
+    }
+
+    fn slice(&self, offset: usize, len: usize) -> PyResult<&[u8]> {
+        let end = offset.checked_add(len).ok_or_else(corrupt)?;
+        self.bytes().get(offset..end).ok_or_else(corrupt)
+    }
+
+    fn tag(&self, offset: usize) -> PyResult<u8> {
+        Ok(self.slice(offset, 1)?[0])
+    }
+
+    fn u32(&self, offset: usize) -> PyResult<usize> {
+        let bytes = self.slice(offset, 4)?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()) as usize)
+    }
+
+    fn u64(&self, offset: usize) -> PyResult<[u8; 8]> {
+        Ok(self.slice(offset, 8)?.try_into().unwrap())
+    }
+
+    /// The bytes of a string node, if `offset` is one
+    fn str_bytes(&self, offset: usize) -> PyResult<Option<&[u8]>> {
+        if self.tag(offset)? != STRING {
+            return Ok(None);
+        }
+        let len = self.u32(offset + 1)?;
+        self.slice(offset + 5, len).map(Some)
+    }
+
+    /// Number of items or entries of the container at `offset`
+    fn len(&self, offset: usize) -> PyResult<usize> {
+        self.u32(offset + 1)
+    }
+
+    /// Offset of item `index` of a sequence
+    fn item(&self, offset: usize, index: usize) -> PyResult<usize> {
+        child(offset, self.u32(offset + 5 + 4 * index)?)
+    }
+
+    /// Key and value offsets of entry `index` of a mapping
+    fn entry(&self, offset: usize, index: usize) -> PyResult<(usize, usize)> {
+        let at = offset + 5 + 8 * index;
+        Ok((
+            child(offset, self.u32(at)?)?,
+            child(offset, self.u32(at + 4)?)?,
+        ))
+    }
+
+    /// Find the entry with string key `key` by binary search
+    fn find_str(&self, offset: usize, key: &[u8]) -> PyResult<Option<usize>> {
+        let index = offset + 5 + 8 * self.len(offset)?;
+        let (mut low, mut high) = (0, self.u32(index)?);
+        while low < high {
+            let mid = (low + high) / 2;
+            let entry = self.u32(index + 4 + 4 * mid)?;
+            let (key_offset, value) = self.entry(offset, entry)?;
+            let candidate = self.str_bytes(key_offset)?.ok_or_else(corrupt)?;
+            match candidate.cmp(key) {
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+                std::cmp::Ordering::Equal => return Ok(Some(value)),
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// The node at `offset`: scalars decoded, containers as views
+fn view(py: Python, region: &Arc<Region>, offset: usize) -> PyResult<PyObject> {
+    match region.tag(offset)? {
+        SEQUENCE => Ok(SharedSequence {
+            region: region.clone(),
+            offset,
+        }
+        .into_py(py)),
+        MAPPING => Ok(SharedMapping {
+            region: region.clone(),
+            offset,
+        }
+        .into_py(py)),
+        _ => materialize(py, region, offset, false),
+    }
+}
+
+/// The node at `offset` as plain Python objects; sequences are tuples if
+/// `frozen` (for mapping keys, which must be hashable)
+fn materialize(py: Python, region: &Region, offset: usize, frozen: bool) -> PyResult<PyObject> {
+    match region.tag(offset)? {
+        NULL => Ok(py.None()),
+        FALSE => Ok(false.into_py(py)),
+        TRUE => Ok(true.into_py(py)),
+        INT => Ok(i64::from_le_bytes(region.u64(offset + 1)?).into_py(py)),
+        UINT => Ok(u64::from_le_bytes(region.u64(offset + 1)?).into_py(py)),
+        FLOAT => Ok(f64::from_le_bytes(region.u64(offset + 1)?).into_py(py)),
+        STRING => {
+            let bytes = region.str_bytes(offset)?.ok_or_else(corrupt)?;
+            let text = std::str::from_utf8(bytes).map_err(|_| corrupt())?;
+            Ok(PyString::new_bound(py, text).into())
+        }
+        SEQUENCE => {
+            let items = (0..region.len(offset)?)
+                .map(|index| materialize(py, region, region.item(offset, index)?, frozen))
+                .collect::<PyResult<Vec<_>>>()?;
+            if frozen {
+                Ok(PyTuple::new_bound(py, items).into())
+            } else {
+                Ok(PyList::new_bound(py, items).into())
+            }
+        }
+        MAPPING => {
+            let dict = PyDict::new_bound(py);
+            for index in 0..region.len(offset)? {
+                let (key, value) = region.entry(offset, index)?;
+                dict.set_item(
+                    materialize(py, region, key, true)?,
+                    materialize(py, region, value, frozen)?,
+                )?;
+            }
+            Ok(dict.into())
+        }
+        _ => Err(corrupt()),
+    }
+}
+
+/// A read-only mapping in a shared document
+///
+/// Values are decoded when they are accessed; nested mappings and
+/// sequences are views too. `to_dict()` copies the whole subtree.
+#[pyclass(module = "rustyyaml", frozen, mapping)]
+pub struct SharedMapping {
+    region: Arc<Region>,
+    offset: usize,
+}
+
+impl SharedMapping {
+    /// Offset of the value for `key`, if there is one
+    fn find(&self, py: Python, key: &Bound<'_, PyAny>) -> PyResult<Option<usize>> {
+        if let Ok(key) = key.downcast::<PyString>() {
+            return self.region.find_str(self.offset, key.to_cow()?.as_bytes());
+        }
+        // Other keys are rare; compare them as Python objects
+        for index in 0..self.region.len(self.offset)? {
+            let (key_offset, value) = self.region.entry(self.offset, index)?;
+            if self.region.tag(key_offset)? == STRING {
+                continue;
+            }
+            if materialize(py, &self.region, key_offset, true)?
+                .bind(py)
+                .eq(key)?
+            {
+                return Ok(Some(value));
+            }
+        }
+        Ok(None)
+    }
+
+    fn collect(
+        &self,
+        py: Python,
+        mut each: impl FnMut(PyObject, usize) -> PyResult<PyObject>,
+    ) -> PyResult<PyObject> {
+        let items = (0..self.region.len(self.offset)?)
+            .map(|index| {
+                let (key, value) = self.region.entry(self.offset, index)?;
+                each(materialize(py, &self.region, key, true)?, value)
+            })
+            .collect::<PyResult<Vec<_>>>()?;
+        Ok(PyList::new_bound(py, items).into())
+    }
+}
+
+#[pymethods]
+impl SharedMapping {
+    fn __getitem__(&self, py: Python, key: &Bound<'_, PyAny>) -> PyResult<PyObject> {
+        match self.find(py, key)? {
+            Some(value) => view(py, &self.region, value),
+            None => Err(PyKeyError::new_err(key.clone().unbind())),
+        }
+    }
+
+    fn __contains__(&self, py: Python, key: &Bound<'_, PyAny>) -> PyResult<bool> {
+        Ok(self.find(py, key)?.is_some())
+    }
+
+    fn __len__(&self) -> PyResult<usize> {
+        self.region.len(self.offset)
+    }
+
+    fn __iter__(&self, py: Python) -> PyResult<PyObject> {
+        Ok(self.keys(py)?.bind(py).iter()?.into_any().unbind())
+    }
+
+    fn __richcmp__(
+        &self,
+        py: Python,
+        other: &Bound<'_, PyAny>,
+        op: CompareOp,
+    ) -> PyResult<PyObject> {
+        let other = match other.downcast::<SharedMapping>() {
+            Ok(shared) => shared.get().to_dict(py)?.into_bound(py),
+            Err(_) if other.is_instance_of::<PyDict>() => other.clone(),
+            Err(_) => return Ok(py.NotImplemented()),
+        };
+        let this = self.to_dict(py)?.into_bound(py);
+        match op {
+            CompareOp::Eq => Ok(this.eq(other)?.into_py(py)),
+            CompareOp::Ne => Ok(this.ne(other)?.into_py(py)),
+            _ => Ok(py.NotImplemented()),
+        }
+    }
+
+    fn __repr__(&self) -> PyResult<String> {
+        Ok(format!("SharedMapping(<{} keys>)", self.__len__()?))
+    }
+
+    fn keys(&self, py: Python) -> PyResult<PyObject> {
+        self.collect(py, |key, _| Ok(key))
+    }
+
+    fn values(&self, py: Python) -> PyResult<PyObject> {
+        self.collect(py, |_, value| view(py, &self.region, value))
+    }
+
+    fn items(&self, py: Python) -> PyResult<PyObject> {
+        self.collect(py, |key, value| {
+            Ok((key, view(py, &self.region, value)?).into_py(py))
+        })
+    }
+
+    #[pyo3(signature = (key, default=None))]
+    fn get(
+        &self,
+        py: Python,
+        key: &Bound<'_, PyAny>,
+        default: Option<PyObject>,
+    ) -> PyResult<PyObject> {
+        match self.find(py, key)? {
+            Some(value) => view(py, &self.region, value),
+            None => Ok(default.unwrap_or_else(|| py.None())),
+        }
+    }
+
+    /// Plain dicts and lists all the way down
+    fn to_dict(&self, py: Python) -> PyResult<PyObject> {
+        materialize(py, &self.region, self.offset, false)
+    }
+}
+
+/// A read-only sequence in a shared document
+///
+/// Items are decoded when they are accessed; `to_list()` copies the whole
+/// subtree.
+#[pyclass(module = "rustyyaml", frozen, sequence)]
+pub struct SharedSequence {
+    region: Arc<Region>,
+    offset: usize,
+}
+
+#[pymethods]
+impl SharedSequence {
+    fn __getitem__(&self, py: Python, index: &Bound<'_, PyAny>) -> PyResult<PyObject> {
+        let len = self.__len__()?;
+        if let Ok(slice) = index.downcast::<PySlice>() {
+            let indices = slice.indices(len as isize)?;
+            let items = (0..indices.slicelength)
+                .map(|n| {
+                    let index = (indices.start + n as isize * indices.step) as usize;
+                    view(py, &self.region, self.region.item(self.offset, index)?)
+                })
+                .collect::<PyResult<Vec<_>>>()?;
+            return Ok(PyList::new_bound(py, items).into());
+        }
+        let index: isize = index.extract()?;
+        let resolved = if index < 0 {
+            index + len as isize
+        } else {
+            index
+        };
+        if resolved < 0 || resolved as usize >= len {
+            return Err(PyIndexError::new_err("index out of range"));
+        }
+        view(
+            py,
+            &self.region,
+            self.region.item(self.offset, resolved as usize)?,
+        )
+    }
+
+    fn __len__(&self) -> PyResult<usize> {
+        self.region.len(self.offset)
+    }
+
+    fn __iter__(&self, py: Python) -> PyResult<PyObject> {
+        let items = (0..self.__len__()?)
+            .map(|index| view(py, &self.region, self.region.item(self.offset, index)?))
+            .collect::<PyResult<Vec<_>>>()?;
+        Ok(PyList::new_bound(py, items)
+            .as_any()
+            .iter()?
+            .into_any()
+            .unbind())
+    }
+
+    fn __richcmp__(
+        &self,
+        py: Python,
+        other: &Bound<'_, PyAny>,
+        op: CompareOp,
+    ) -> PyResult<PyObject> {
+        let other = match other.downcast::<SharedSequence>() {
+            Ok(shared) => shared.get().to_list(py)?.into_bound(py),
+            Err(_) if other.is_instance_of::<PyList>() => other.clone(),
+            Err(_) => return Ok(py.NotImplemented()),
+        };
+        let this = self.to_list(py)?.into_bound(py);
+        match op {
+            CompareOp::Eq => Ok(this.eq(other)?.into_py(py)),
+            CompareOp::Ne => Ok(this.ne(other)?.into_py(py)),
+            _ => Ok(py.NotImplemented()),
+        }
+    }
+
+    fn __repr__(&self) -> PyResult<String> {
+        Ok(format!("SharedSequence(<{} items>)", self.__len__()?))
+    }
+
+    /// Plain lists and dicts all the way down
+    fn to_list(&self, py: Python) -> PyResult<PyObject> {
+        materialize(py, &self.region, self.offset, false)
+    }
+}
+
+/// Where new regions are written: shared memory if the system has it
+fn shm_dir() -> PathBuf {
+    let dev_shm = Path::new("/dev/shm");
+    if dev_shm.is_dir() {
+        dev_shm.to_path_buf()
+    } else {
+        std::env::temp_dir()
+    }
+}
+
+/// Encode a document into shared memory for other processes to load
+///
+/// # Arguments
+/// * `doc` - Loaded data (dicts, lists, tuples and YAML scalars)
+///
+/// # Returns
+/// The handle (a file path) to pass to `load_shm`; the caller removes it
+/// once the workers have loaded it
+///
+/// # Example
+/// ```python
+/// handle = rustyyaml.dump_shm(rustyyaml.safe_load_file("corpus.yaml"))
+/// with ProcessPoolExecutor(initializer=init, initargs=(handle,)) as pool:
+///     ...
+/// ```
+#[pyfunction]
+pub fn dump_shm(py: Python, doc: &Bound<'_, PyAny>) -> PyResult<String> {
+    let value = types::python_to_yaml(doc)?;
+    let path = shm_dir().join(format!(
+        "rustyyaml-{}-{}.shm",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    py.allow_threads(|| {
+        let bytes = encode(&value)?;
+        File::create(&path)
+            .and_then(|mut file| file.write_all(&bytes))
+            .inspect_err(|_| {
+                let _ = fs::remove_file(&path);
+            })?;
+        Ok(path.display().to_string())
+    })
+}
+
+/// Map a document written by `dump_shm`
+///
+/// # Returns
+/// A `SharedMapping` or `SharedSequence` view (or the scalar, for a scalar
+/// document). The mapping stays valid after the file is removed.
+///
+/// # Errors
+/// * `ValueError` if the file isn't a shared document
+#[pyfunction]
+pub fn load_shm(py: Python, handle: PathBuf) -> PyResult<PyObject> {
+    let file = File::open(&handle)?;
+    // SAFETY: regions are written once by `dump_shm` and never modified;
+    // the views only read through bounds-checked slices
+    let map = unsafe { Mmap::map(&file)? };
+    let (region, root) = Region::new(Box::new(map))?;
+    view(py, &region, root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(py: Python, yaml: &str) -> PyObject {
+        let value: Value = serde_yaml::from_str(yaml).unwrap();
+        let (region, root) = Region::new(Box::new(encode(&value).unwrap())).unwrap();
+        view(py, &region, root).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        Python::with_gil(|py| {
+            let yaml = "name: app\nports: [80, 443]\nratio: 0.5\nbig: 18446744073709551615\nnested: {a: [true, null]}\n";
+            let doc = load(py, yaml);
+            let doc = doc.bind(py);
+            assert_eq!(doc.get_type().name().unwrap(), "SharedMapping");
+            assert_eq!(doc.len().unwrap(), 5);
+            let ports = doc.get_item("ports").unwrap();
+            assert_eq!(ports.get_item(-1).unwrap().extract::<i64>().unwrap(), 443);
+            assert_eq!(
+                doc.get_item("big").unwrap().extract::<u64>().unwrap(),
+                u64::MAX
+            );
+
+            let expected = py
+                .eval_bound(
+                    "{'name': 'app', 'ports': [80, 443], 'ratio': 0.5, 'big': 2**64 - 1, 'nested': {'a': [True, None]}}",
+                    None,
+                    None,
+                )
+                .unwrap();
+            assert!(doc.eq(&expected).unwrap());
+            assert!(doc.get_item("missing").is_err());
+        });
+    }
+
+    #[test]
+    fn test_key_lookup() {
+        Python::with_gil(|py| {
+            let keys: Vec<String> = (0..50).map(|n| format!("key{}", 49 - n)).collect();
+            let yaml = keys
+                .iter()
+                .enumerate()
+                .map(|(n, key)| format!("{}: {}\n", key, n))
+                .collect::<String>()
+                + "1: int\n";
+            let doc = load(py, &yaml);
+            let doc = doc.bind(py);
+            for (n, key) in keys.iter().enumerate() {
+                assert_eq!(doc.get_item(key).unwrap().extract::<usize>().unwrap(), n);
+            }
+            assert_eq!(doc.get_item(1).unwrap().extract::<String>().unwrap(), "int");
+            // Document order, not lookup order
+            let first: String = doc
+                .iter()
+                .unwrap()
+                .next()
+                .unwrap()
+                .unwrap()
+                .extract()
+                .unwrap();
+            assert_eq!(first, "key49");
+        });
+    }
+
+    #[test]
+    fn test_rejects_corrupt_regions() {
+        assert!(Region::new(Box::new(b"not a region".to_vec())).is_err());
+        let mut bytes = encode(&serde_yaml::from_str("[a, b]").unwrap()).unwrap();
+        bytes.truncate(bytes.len() - 2);
+        let (region, root) = Region::new(Box::new(bytes)).unwrap();
+        Python::with_gil(|py| {
+            assert!(materialize(py, &region, root, false).is_err());
+        });
+    }
+
+    #[test]
+    fn test_rejects_cycles() {
+        Python::with_gil(|py| {
+            // Offset of the first value within the root node, and its key
+            for (yaml, at, key) in [("[a, [b]]", 5, "0"), ("{a: {b: c}}", 9, "'a'")] {
+                let mut bytes = encode(&serde_yaml::from_str(yaml).unwrap()).unwrap();
+                // Point the root's first child back at the root
+                let root = u32::from_le_bytes(bytes[8..12].try_into().unwrap());
+                let at = root as usize + at;
+                bytes[at..at + 4].copy_from_slice(&root.to_le_bytes());
+                let (region, root) = Region::new(Box::new(bytes)).unwrap();
+                let err = materialize(py, &region, root, false).unwrap_err();
+                assert!(err.is_instance_of::<exceptions::YAMLError>(py));
+                let doc = view(py, &region, root).unwrap();
+                let key = py.eval_bound(key, None, None).unwrap();
+                assert!(doc.bind(py).get_item(key).is_err());
+            }
+        });
+    }
+}
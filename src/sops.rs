@@ -0,0 +1,614 @@
+//! Decrypt SOPS-encrypted documents while loading
+//!
+//! A SOPS document keeps its structure in the clear and replaces each value
+//! with `ENC[AES256_GCM,data:...,iv:...,tag:...,type:str]`. The values are
+//! encrypted with one random data key, which the top-level `sops` section
+//! stores once per recipient (age, KMS, PGP, ...), encrypted for that
+//! recipient.
+//!
+//! Unwrapping the data key is left to a Python callback (`sops_key=`
+//! option), so any age library or cloud SDK can be used. It is called as
+//! `callback(kind, entry)` for each recipient (`kind` is `"age"`, `"kms"`,
+//! ...; `entry` is that recipient's dict, with the wrapped key in `enc`)
+//! until it returns the 32-byte key. Everything else happens here: values
+//! are decrypted and authenticated with their GCM tag (bound to their key
+//! path), typed as `type:` says, and the `sops` section is dropped.
+//!
+//! Each value's tag only vouches for that value, so the document as a whole
+//! is checked against the MAC in the `sops` section, as `sops decrypt` does:
+//! a SHA-512 of every value's plaintext in document order (only the
+//! encrypted ones with `mac_only_encrypted: true`), itself encrypted with
+//! the data key and the `lastmodified` time. A value removed, added,
+//! reordered or edited in the clear fails the load unless
+//! `sops_verify_mac=False`. The MAC of a multi-document file covers all its
+//! documents, so such files can only be loaded that way.
+
+use aes_gcm::aead::generic_array::typenum::U32;
+use aes_gcm::aead::generic_array::GenericArray;
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::aes::Aes256;
+use aes_gcm::AesGcm;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use regex::Regex;
+use serde_yaml::Value;
+use sha2::{Digest, Sha512};
+use std::fmt::Write;
+use std::sync::OnceLock;
+
+use crate::compose::{Document, Node, NodeKind};
+use crate::construct::construct_node;
+use crate::error::YAMLError;
+use crate::events::ScalarStyle;
+use crate::options::LoadOptions;
+use crate::resolve::{self, Int, Scalar};
+use crate::types::{python_to_yaml, yaml_to_python};
+
+/// AES-256-GCM with the 32-byte nonces SOPS uses
+type Cipher = AesGcm<Aes256, U32>;
+
+/// Recipient lists of the `sops` section, in the order they are tried
+const KEY_KINDS: [&str; 6] = ["age", "kms", "gcp_kms", "azure_kv", "hc_vault", "pgp"];
+
+/// A decrypted value, typed as its `type:` field says
+#[derive(Clone, Debug, PartialEq)]
+pub enum Plain {
+    Str(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Bytes(Vec<u8>),
+}
+
+fn enc_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"^ENC\[AES256_GCM,data:(.*),iv:(.*),tag:(.*),type:(.*)\]$")
+            .expect("valid SOPS value regex")
+    })
+}
+
+fn error(message: String) -> YAMLError {
+    YAMLError::Sops { message }
+}
+
+/// Decrypt one scalar; `Ok(None)` if it is not an encrypted value
+///
+/// `path` is the key path of the value (sequence items share their
+/// parent's path); it is authenticated along with the value.
+pub fn decrypt_scalar(
+    text: &str,
+    key: &[u8; 32],
+    path: &[&str],
+) -> Result<Option<Plain>, YAMLError> {
+    let aad: String = path.iter().map(|key| format!("{}:", key)).collect();
+    decrypt(text, key, aad.as_bytes(), &path.join("."))
+}
+
+/// Decrypt one scalar authenticated with `aad`; errors name `location`
+fn decrypt(
+    text: &str,
+    key: &[u8; 32],
+    aad: &[u8],
+    location: &str,
+) -> Result<Option<Plain>, YAMLError> {
+    let Some(caps) = enc_regex().captures(text) else {
+        return Ok(None);
+    };
+    let field = |index: usize, name: &str| {
+        STANDARD
+            .decode(&caps[index])
+            .map_err(|err| error(format!("bad {} in value at {}: {}", name, location, err)))
+    };
+    let (data, iv, tag) = (field(1, "data")?, field(2, "iv")?, field(3, "tag")?);
+    if iv.len() != 32 || tag.len() != 16 {
+        return Err(error(format!("malformed value at {}", location)));
+    }
+
+    let mut ciphertext = data;
+    ciphertext.extend_from_slice(&tag);
+    let cipher = Cipher::new(GenericArray::from_slice(key));
+    let plaintext = cipher
+        .decrypt(
+            GenericArray::from_slice(&iv),
+            Payload {
+                msg: &ciphertext,
+                aad,
+            },
+        )
+        .map_err(|_| {
+            error(format!(
+                "could not decrypt value at {} (wrong key or tampered value)",
+                location
+            ))
+        })?;
+
+    let kind = &caps[4];
+    if kind == "bytes" {
+        return Ok(Some(Plain::Bytes(plaintext)));
+    }
+    let text = String::from_utf8(plaintext)
+        .map_err(|_| error(format!("value at {} is not UTF-8", location)))?;
+    let bad = || error(format!("value at {} is not a valid {}", location, kind));
+    Ok(Some(match kind {
+        "str" => Plain::Str(text),
+        "int" => Plain::Int(text.parse().map_err(|_| bad())?),
+        "float" => Plain::Float(text.parse().map_err(|_| bad())?),
+        "bool" => Plain::Bool(match text.to_ascii_lowercase().as_str() {
+            "true" => true,
+            "false" => false,
+            _ => return Err(bad()),
+        }),
+        _ => return Err(error(format!("unsupported value type '{}'", kind))),
+    }))
+}
+
+/// Unwrap the data key with the user's callback
+///
+/// Takes the GIL only for the callback, so rayon workers can call this.
+fn data_key(callback: &PyObject, sops: &Value) -> Result<[u8; 32], YAMLError> {
+    let lists = recipient_lists(sops)?;
+    if lists.iter().all(|(_, entries)| entries.is_empty()) {
+        return Err(error("the sops section lists no recipients".to_string()));
+    }
+    Python::with_gil(|py| {
+        for (kind, entries) in lists {
+            for entry in entries {
+                let entry = yaml_to_python(py, entry).map_err(|err| error(err.to_string()))?;
+                let key = callback
+                    .call1(py, (kind, entry))
+                    .map_err(|err| error(format!("sops_key callback failed: {}", err)))?;
+                if key.is_none(py) {
+                    continue;
+                }
+                let key = key.downcast_bound::<PyBytes>(py).map_err(|_| {
+                    error("sops_key callback must return bytes or None".to_string())
+                })?;
+                return key.as_bytes().try_into().map_err(|_| {
+                    error(format!(
+                        "sops_key callback returned {} bytes, expected a 32-byte data key",
+                        key.as_bytes().len()
+                    ))
+                });
+            }
+        }
+        Err(error(
+            "sops_key callback returned None for every recipient".to_string(),
+        ))
+    })
+}
+
+/// The recipient lists of a `sops` section (Shamir key groups are not supported)
+fn recipient_lists(sops: &Value) -> Result<Vec<(&'static str, &Vec<Value>)>, YAMLError> {
+    let group = match sops.get("key_groups").and_then(Value::as_sequence) {
+        Some(groups) if groups.len() > 1 => {
+            return Err(error("Shamir key groups are not supported".to_string()))
+        }
+        Some(groups) => groups.first().unwrap_or(sops),
+        None => sops,
+    };
+    Ok(KEY_KINDS
+        .iter()
+        .filter_map(|&kind| Some((kind, group.get(kind)?.as_sequence()?)))
+        .collect())
+}
+
+/// The document MAC, computed as values are decrypted
+struct Mac {
+    hasher: Sha512,
+    /// `mac_only_encrypted: true`: values left in the clear aren't covered
+    only_encrypted: bool,
+    /// The `mac` and `lastmodified` fields of the `sops` section (`None`
+    /// with `sops_verify_mac=False`)
+    recorded: Option<(String, String)>,
+}
+
+impl Mac {
+    fn new(sops: &Value, options: &LoadOptions) -> Result<Self, YAMLError> {
+        let field = |name: &str| {
+            sops.get(name)
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .ok_or_else(|| {
+                    error(format!(
+                        "the sops section has no {} to verify the document with \
+                         (sops_verify_mac=False skips the check)",
+                        name
+                    ))
+                })
+        };
+        let recorded = if options.sops_ignore_mac {
+            None
+        } else {
+            Some((field("mac")?, field("lastmodified")?))
+        };
+        Ok(Mac {
+            hasher: Sha512::new(),
+            only_encrypted: sops.get("mac_only_encrypted").and_then(Value::as_bool) == Some(true),
+            recorded,
+        })
+    }
+
+    /// Add a value, as the text sops hashes for it (Go's formatting)
+    fn add(&mut self, plain: &Plain, encrypted: bool) {
+        if self.only_encrypted && !encrypted {
+            return;
+        }
+        match plain {
+            Plain::Str(s) => self.hasher.update(s.as_bytes()),
+            Plain::Int(i) => self.hasher.update(i.to_string().as_bytes()),
+            // Go's 'f' format: shortest round trip, never an exponent
+            Plain::Float(f) => self.hasher.update(f.to_string().as_bytes()),
+            Plain::Bool(b) => self.hasher.update(if *b { "True" } else { "False" }),
+            Plain::Bytes(bytes) => self.hasher.update(bytes),
+        }
+    }
+
+    /// Compare with the MAC the `sops` section records
+    ///
+    /// # Errors
+    /// * `Sops` if they differ, or the recorded one can't be decrypted
+    fn verify(self, key: &[u8; 32]) -> Result<(), YAMLError> {
+        let Some((mac, lastmodified)) = self.recorded else {
+            return Ok(());
+        };
+        let recorded = match decrypt(&mac, key, lastmodified.as_bytes(), "sops.mac")? {
+            Some(Plain::Str(recorded)) => recorded,
+            _ => return Err(error("malformed sops.mac".to_string())),
+        };
+        let computed =
+            self.hasher
+                .finalize()
+                .iter()
+                .fold(String::with_capacity(128), |mut hex, byte| {
+                    let _ = write!(hex, "{:02X}", byte);
+                    hex
+                });
+        if recorded != computed {
+            return Err(error(
+                "MAC mismatch: the document was changed after it was encrypted \
+                 (sops_verify_mac=False skips the check)"
+                    .to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A value left in the clear, as sops reads it; `None` for null
+fn clear_value(value: &Value) -> Option<Plain> {
+    Some(match value {
+        Value::String(s) => Plain::Str(s.clone()),
+        Value::Bool(b) => Plain::Bool(*b),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => Plain::Int(i),
+            None => Plain::Float(n.as_f64()?),
+        },
+        Value::Tagged(tagged) => return clear_value(&tagged.value),
+        Value::Null | Value::Sequence(_) | Value::Mapping(_) => return None,
+    })
+}
+
+/// A scalar node left in the clear, as sops reads it; `None` for null
+fn clear_scalar(tag: Option<&str>, value: &str, style: ScalarStyle) -> Option<Plain> {
+    let scalar = match tag {
+        Some(tag) => resolve::resolve_tagged(value, tag).unwrap_or(Scalar::Str),
+        None if style == ScalarStyle::Plain => resolve::resolve_core(value),
+        None => Scalar::Str,
+    };
+    Some(match scalar {
+        Scalar::Null => return None,
+        Scalar::Bool(b) => Plain::Bool(b),
+        Scalar::Int(Int::Small(i)) => Plain::Int(i),
+        Scalar::Float(f) => Plain::Float(f),
+        _ => Plain::Str(value.to_string()),
+    })
+}
+
+fn plain_to_value(plain: Plain) -> Value {
+    match plain {
+        Plain::Str(s) => Value::String(s),
+        Plain::Int(i) => Value::Number(i.into()),
+        Plain::Float(f) => Value::Number(f.into()),
+        Plain::Bool(b) => Value::Bool(b),
+        // `Value` has no bytes; binary values are only used by sops for
+        // whole binary files
+        Plain::Bytes(bytes) => Value::String(String::from_utf8_lossy(&bytes).into_owned()),
+    }
+}
+
+/// Decrypt every value below `value` in place
+fn decrypt_tree(
+    value: &mut Value,
+    key: &[u8; 32],
+    path: &mut Vec<String>,
+    mac: &mut Mac,
+) -> Result<(), YAMLError> {
+    if let Value::String(text) = value {
+        let keys: Vec<&str> = path.iter().map(String::as_str).collect();
+        if let Some(plain) = decrypt_scalar(text, key, &keys)? {
+            mac.add(&plain, true);
+            *value = plain_to_value(plain);
+            return Ok(());
+        }
+    }
+    match value {
+        Value::Sequence(items) => {
+            for item in items {
+                decrypt_tree(item, key, path, mac)?;
+            }
+        }
+        Value::Mapping(map) => {
+            for (name, child) in map.iter_mut() {
+                let Some(name) = name.as_str() else {
+                    continue;
+                };
+                path.push(name.to_string());
+                decrypt_tree(child, key, path, mac)?;
+                path.pop();
+            }
+        }
+        Value::Tagged(tagged) => decrypt_tree(&mut tagged.value, key, path, mac)?,
+        Value::String(_) | Value::Null | Value::Bool(_) | Value::Number(_) => {
+            if let Some(plain) = clear_value(value) {
+                mac.add(&plain, false);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Apply the `sops_key=` option to a parsed document
+///
+/// Documents without a top-level `sops` section are left alone. Called
+/// from rayon workers too, so the GIL is only taken for the callback.
+pub fn decrypt_value(value: &mut Value, options: &LoadOptions) -> Result<(), YAMLError> {
+    let Some(callback) = &options.sops_key else {
+        return Ok(());
+    };
+    let Some(sops) = value.get("sops").filter(|sops| sops.is_mapping()) else {
+        return Ok(());
+    };
+    let key = data_key(callback, sops)?;
+    let mut mac = Mac::new(sops, options)?;
+
+    if let Value::Mapping(map) = value {
+        map.remove("sops");
+    }
+    decrypt_tree(value, &key, &mut Vec::new(), &mut mac)?;
+    mac.verify(&key)
+}
+
+fn plain_to_node(node: &mut Node, plain: Plain) {
+    let (tag, value) = match plain {
+        Plain::Str(s) => (resolve::TAG_STR, s),
+        Plain::Int(i) => (resolve::TAG_INT, i.to_string()),
+        Plain::Float(f) => (resolve::TAG_FLOAT, f.to_string()),
+        Plain::Bool(b) => (resolve::TAG_BOOL, b.to_string()),
+        Plain::Bytes(bytes) => (resolve::TAG_BINARY, STANDARD.encode(bytes)),
+    };
+    node.tag = Some(tag.to_string());
+    node.kind = NodeKind::Scalar {
+        value,
+        style: ScalarStyle::Plain,
+    };
+}
+
+fn decrypt_node(
+    node: &mut Node,
+    key: &[u8; 32],
+    path: &mut Vec<String>,
+    mac: &mut Mac,
+) -> Result<(), YAMLError> {
+    match &mut node.kind {
+        NodeKind::Scalar { value, style } => {
+            let keys: Vec<&str> = path.iter().map(String::as_str).collect();
+            if let Some(plain) = decrypt_scalar(value, key, &keys)? {
+                mac.add(&plain, true);
+                plain_to_node(node, plain);
+            } else if let Some(plain) = clear_scalar(node.tag.as_deref(), value, *style) {
+                mac.add(&plain, false);
+            }
+        }
+        NodeKind::Sequence { items, .. } => {
+            for item in items {
+                decrypt_node(item, key, path, mac)?;
+            }
+        }
+        NodeKind::Mapping { pairs, .. } => {
+            for (name, child) in pairs {
+                let NodeKind::Scalar { value: name, .. } = &name.kind else {
+                    continue;
+                };
+                path.push(name.clone());
+                decrypt_node(child, key, path, mac)?;
+                path.pop();
+            }
+        }
+        NodeKind::Alias { .. } => {}
+    }
+    Ok(())
+}
+
+/// Apply the `sops_key=` option to a composed document
+pub fn decrypt_document(document: &mut Document, options: &LoadOptions) -> Result<(), YAMLError> {
+    let Some(callback) = &options.sops_key else {
+        return Ok(());
+    };
+    let NodeKind::Mapping { pairs, .. } = &document.root.kind else {
+        return Ok(());
+    };
+    let Some(position) = pairs.iter().position(|(key, value)| {
+        matches!(&key.kind, NodeKind::Scalar { value, .. } if value == "sops")
+            && matches!(value.kind, NodeKind::Mapping { .. })
+    }) else {
+        return Ok(());
+    };
+
+    // The metadata is small; a plain copy is easier to read than the nodes
+    let metadata = Python::with_gil(|py| {
+        let sops = construct_node(
+            py,
+            &document.root,
+            &pairs[position].1,
+            &LoadOptions::default(),
+        )?;
+        python_to_yaml(sops.bind(py))
+    })
+    .map_err(|err| error(err.to_string()))?;
+    let key = data_key(callback, &metadata)?;
+    let mut mac = Mac::new(&metadata, options)?;
+
+    if let NodeKind::Mapping { pairs, .. } = &mut document.root.kind {
+        pairs.remove(position);
+    }
+    decrypt_node(&mut document.root, &key, &mut Vec::new(), &mut mac)?;
+    mac.verify(&key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; 32] = [
+        0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24,
+        25, 26, 27, 28, 29, 30, 31,
+    ];
+    const USER: &str = "ENC[AES256_GCM,data:uEV+9Ks=,iv:CxIZICcuNTxDSlFYX2ZtdHuCiZCXnqWss7rByM/W3eQ=,tag:qruLASjxYrCUGfHasiVH1A==,type:str]";
+    const PORT: &str = "ENC[AES256_GCM,data:Ztw2xA==,iv:ChEYHyYtNDtCSVBXXmVsc3qBiI+WnaSrsrnAx87V3OM=,tag:r36T8RUsfCj9MlQsXud/xQ==,type:int]";
+
+    #[test]
+    fn test_decrypt_scalar() {
+        assert_eq!(
+            decrypt_scalar(USER, &KEY, &["db", "user"]).unwrap(),
+            Some(Plain::Str("admin".to_string()))
+        );
+        assert_eq!(
+            decrypt_scalar(PORT, &KEY, &["db", "port"]).unwrap(),
+            Some(Plain::Int(5432))
+        );
+        assert_eq!(decrypt_scalar("admin", &KEY, &["db"]).unwrap(), None);
+    }
+
+    #[test]
+    fn test_decrypt_scalar_is_bound_to_key_and_path() {
+        // Moving a value to another key must not decrypt
+        assert!(decrypt_scalar(USER, &KEY, &["db", "password"]).is_err());
+        assert!(decrypt_scalar(USER, &[7; 32], &["db", "user"]).is_err());
+        assert!(
+            decrypt_scalar("ENC[AES256_GCM,data:,iv:AA==,tag:AA==,type:str]", &KEY, &[]).is_err()
+        );
+    }
+
+    /// A MAC that isn't verified (`sops_verify_mac=False`)
+    fn unchecked() -> Mac {
+        let options = LoadOptions {
+            sops_ignore_mac: true,
+            ..Default::default()
+        };
+        Mac::new(&Value::Null, &options).ok().unwrap()
+    }
+
+    #[test]
+    fn test_decrypt_tree() {
+        // Sequence items share the path of their parent
+        let yaml = format!("db:\n  user: {}\nlist:\n  - {}\n", USER, USER);
+        let mut value: Value = serde_yaml::from_str(&yaml).unwrap();
+        assert!(decrypt_tree(&mut value, &KEY, &mut Vec::new(), &mut unchecked()).is_err());
+
+        let yaml = format!("db:\n  user: {}\n  host: h\n", USER);
+        let mut value: Value = serde_yaml::from_str(&yaml).unwrap();
+        decrypt_tree(&mut value, &KEY, &mut Vec::new(), &mut unchecked()).unwrap();
+        let expected: Value = serde_yaml::from_str("db: {user: admin, host: h}").unwrap();
+        assert_eq!(value, expected);
+    }
+
+    /// Encrypt `plain` as sops does, with a fixed IV
+    fn encrypt(plain: &str, aad: &[u8]) -> String {
+        let iv = [9; 32];
+        let mut data = Cipher::new(GenericArray::from_slice(&KEY))
+            .encrypt(
+                GenericArray::from_slice(&iv),
+                Payload {
+                    msg: plain.as_bytes(),
+                    aad,
+                },
+            )
+            .unwrap();
+        let tag = data.split_off(data.len() - 16);
+        format!(
+            "ENC[AES256_GCM,data:{},iv:{},tag:{},type:str]",
+            STANDARD.encode(data),
+            STANDARD.encode(iv),
+            STANDARD.encode(tag)
+        )
+    }
+
+    #[test]
+    fn test_mac() {
+        // The values in document order, as Go formats them; nulls are left out
+        let digest = Sha512::digest("admin5432h3True2.5");
+        let digest: String = digest.iter().map(|byte| format!("{:02X}", byte)).collect();
+        let lastmodified = "2024-01-01T00:00:00Z";
+        let sops: Value = serde_yaml::from_str(&format!(
+            "lastmodified: '{}'\nmac: '{}'",
+            lastmodified,
+            encrypt(&digest, lastmodified.as_bytes())
+        ))
+        .unwrap();
+        let yaml = format!(
+            "db:\n  user: {}\n  port: {}\n  host: h\n  replicas: 3\n  tls: true\n  ratio: 2.5\n  none: ~\n",
+            USER, PORT
+        );
+        let check = |yaml: &str, options: &LoadOptions| -> Result<(), YAMLError> {
+            let mut value: Value = serde_yaml::from_str(yaml).unwrap();
+            let mut mac = Mac::new(&sops, options)?;
+            decrypt_tree(&mut value, &KEY, &mut Vec::new(), &mut mac)?;
+            mac.verify(&KEY)?;
+
+            // The composer path hashes the same text
+            let mut document = crate::compose::compose_all(yaml).unwrap().remove(0);
+            let mut mac = Mac::new(&sops, options)?;
+            decrypt_node(&mut document.root, &KEY, &mut Vec::new(), &mut mac)?;
+            mac.verify(&KEY)
+        };
+        let options = LoadOptions::default();
+        check(&yaml, &options).unwrap();
+
+        for tampered in [
+            yaml.replace("host: h", "host: evil"),
+            yaml.replace("replicas: 3", "replicas: 4"),
+            yaml.replace("  tls: true\n", ""),
+        ] {
+            let err = check(&tampered, &options).unwrap_err();
+            assert!(err.to_string().contains("MAC mismatch"), "{}", err);
+            let ignore = LoadOptions {
+                sops_ignore_mac: true,
+                ..Default::default()
+            };
+            check(&tampered, &ignore).unwrap();
+        }
+        let err = Mac::new(&Value::Null, &options).err().unwrap();
+        assert!(err.to_string().contains("no mac"));
+    }
+
+    #[test]
+    fn test_recipient_lists() {
+        let sops: Value =
+            serde_yaml::from_str("kms: [{arn: a}]\nage: [{recipient: r, enc: e}]\npgp: []")
+                .unwrap();
+        let kinds: Vec<&str> = recipient_lists(&sops)
+            .unwrap()
+            .iter()
+            .map(|(kind, _)| *kind)
+            .collect();
+        assert_eq!(kinds, ["age", "kms", "pgp"]);
+
+        let grouped: Value = serde_yaml::from_str("key_groups: [{age: [{enc: e}]}]").unwrap();
+        assert_eq!(recipient_lists(&grouped).unwrap().len(), 1);
+        let shamir: Value = serde_yaml::from_str("key_groups: [{age: []}, {kms: []}]").unwrap();
+        assert!(recipient_lists(&shamir).is_err());
+    }
+}
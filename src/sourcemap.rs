@@ -0,0 +1,128 @@
+//! Source maps: where each value of a document came from
+//!
+//! Paths are JSON pointers (RFC 6901): `""` is the root, `/spec/ports/0`
+//! the first item of `spec.ports`. Keys that are not scalars have no
+//! pointer, so they and everything below them are left out. Aliases map to
+//! the alias itself (`*name`), not to the anchored node.
+
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyTuple};
+
+use crate::compose::{Node, NodeKind};
+use crate::events::Mark;
+
+/// Source span of the node at `pointer`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub pointer: String,
+    pub start: Mark,
+    pub end: Mark,
+}
+
+/// Collect a span for every node reachable through scalar keys
+pub fn build(root: &Node) -> Vec<Span> {
+    let mut spans = Vec::new();
+    let mut pointer = String::new();
+    walk(root, &mut pointer, &mut spans);
+    spans
+}
+
+fn walk(node: &Node, pointer: &mut String, spans: &mut Vec<Span>) {
+    spans.push(Span {
+        pointer: pointer.clone(),
+        start: node.start,
+        end: node.end,
+    });
+
+    let len = pointer.len();
+    match &node.kind {
+        NodeKind::Sequence { items, .. } => {
+            for (i, item) in items.iter().enumerate() {
+                pointer.push('/');
+                pointer.push_str(&i.to_string());
+                walk(item, pointer, spans);
+                pointer.truncate(len);
+            }
+        }
+        NodeKind::Mapping { pairs, .. } => {
+            for (key, value) in pairs {
+                let NodeKind::Scalar { value: key, .. } = &key.kind else {
+                    continue;
+                };
+                pointer.push('/');
+                pointer.push_str(&escape(key));
+                walk(value, pointer, spans);
+                pointer.truncate(len);
+            }
+        }
+        NodeKind::Scalar { .. } | NodeKind::Alias { .. } => {}
+    }
+}
+
+/// Escape a key as a JSON pointer reference token
+pub fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+/// Convert spans to `{pointer: (line, column, (start, end))}`
+///
+/// Lines and columns are 1-based like error locations; the span is a
+/// half-open range of byte offsets into the input.
+pub fn to_python(py: Python, spans: &[Span]) -> PyResult<PyObject> {
+    let dict = PyDict::new_bound(py);
+    for span in spans {
+        let range = PyTuple::new_bound(py, [span.start.index, span.end.index]);
+        let location = (span.start.line + 1, span.start.column + 1, range);
+        dict.set_item(&span.pointer, location)?;
+    }
+    Ok(dict.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compose::compose_one;
+
+    fn spans(yaml: &str) -> Vec<(String, usize, usize, usize, usize)> {
+        let document = compose_one(yaml).unwrap().unwrap();
+        build(&document.root)
+            .into_iter()
+            .map(|s| {
+                (
+                    s.pointer,
+                    s.start.line,
+                    s.start.column,
+                    s.start.index,
+                    s.end.index,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_nested_paths() {
+        let yaml = "a: 1\nb:\n  - x\n  - {c: 2}\n";
+        let spans = spans(yaml);
+        let pointers: Vec<&str> = spans.iter().map(|s| s.0.as_str()).collect();
+        assert_eq!(pointers, ["", "/a", "/b", "/b/0", "/b/1", "/b/1/c"]);
+
+        let (_, line, column, start, end) = spans[5].clone();
+        assert_eq!((line, column), (3, 8));
+        assert_eq!(&yaml[start..end], "2");
+    }
+
+    #[test]
+    fn test_escaped_keys() {
+        let pointers: Vec<String> = spans("a/b: 1\nc~d: 2\n").into_iter().map(|s| s.0).collect();
+        assert_eq!(pointers, ["", "/a~1b", "/c~0d"]);
+    }
+
+    #[test]
+    fn test_aliases_and_complex_keys() {
+        let pointers: Vec<String> = spans("a: &x {k: v}\nb: *x\n? [1, 2]\n: c\n")
+            .into_iter()
+            .map(|s| s.0)
+            .collect();
+        assert_eq!(pointers, ["", "/a", "/a/k", "/b"]);
+    }
+}
@@ -0,0 +1,344 @@
+//! Streaming load of huge single documents (`streaming=True`)
+//!
+//! A normal file load holds the whole text, then the whole `Value` or node
+//! tree, before the first Python object exists, so peak memory is several
+//! times the file size. Streaming feeds libyaml from the file a chunk at a
+//! time and converts the document one root entry at a time: each item of
+//! a root sequence, or each pair of a root mapping, is composed, checked
+//! and constructed, then its nodes are dropped. Peak memory is the Python
+//! result plus the largest single entry.
+//!
+//! Entries are built by `construct`, so values come out as on the composer
+//! path. Entries that define anchors are kept, so later entries can still
+//! alias them. Merge keys in the root mapping work, but merged keys keep
+//! their place in the file instead of moving first.
+
+use std::path::PathBuf;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList, PySet};
+
+use crate::compose::{Entries, Node, NodeKind, Root};
+use crate::compression;
+use crate::construct::{construct_node, is_merge_key};
+use crate::encoding::Decoding;
+use crate::error::YAMLError;
+use crate::events::{Mark, Parser};
+use crate::limits::{Count, SizeLimited};
+use crate::options::LoadOptions;
+use crate::safe;
+use crate::types::sorted_items;
+
+/// Load a single-document file without reading it into memory whole
+///
+/// # Arguments
+/// * `path` - Path to the YAML file (UTF-8 or UTF-16, detected from its
+///   BOM; `.gz`, `.bz2` and `.xz` files are decompressed as they are read)
+/// * `safe` - Reject unsafe tags, as `safe_load` does
+/// * `options` - Load options; the ones that need the whole document
+///   (`marked`, `schema`, `redact`, `sops_key`, `profile`,
+///   `document_markers`, `encoding`) are rejected; `max_size_bytes` is
+///   checked as the file is read
+///
+/// # Errors
+/// * `ValueError` for an option streaming can't apply
+/// * Read and parse errors, with `path` set
+#[pyfunction]
+#[pyo3(signature = (path, safe=true, **options))]
+pub fn load_file_streaming(
+    py: Python,
+    path: PathBuf,
+    safe: bool,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<PyObject> {
+    let options = LoadOptions::from_kwargs(options)?;
+    options.check_single_document()?;
+    check_options(&options)?;
+    let loaded = compression::open(&path)
+        .map_err(PyErr::from)
+        .and_then(|file| {
+            let file = SizeLimited::new(file, &options.limits);
+            load(py, Parser::from_reader(file), &options, safe)
+        });
+    loaded.inspect_err(|err| {
+        // Best effort: the error is raised either way
+        let _ = err.value_bound(py).setattr("path", &path);
+    })
+}
+
+/// Reject options that need the whole document (or file) at once
+fn check_options(options: &LoadOptions) -> PyResult<()> {
+    let whole_document = [
+        ("marked", options.marked),
+        ("schema", options.json_schema.is_some()),
+        ("redact", !options.redact.is_empty()),
+        ("sops_key", options.sops_key.is_some()),
+        ("profile", options.profile.is_some()),
+        ("document_markers", options.document_markers.is_some()),
+        ("encoding", options.encoding != Decoding::Detect),
+    ];
+    match whole_document.iter().find(|(_, set)| *set) {
+        Some((name, _)) => Err(PyValueError::new_err(format!(
+            "streaming=True can't be combined with {}",
+            name
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Load the single document `parser` reads, one root entry at a time
+pub fn load(
+    py: Python,
+    parser: Parser<'_>,
+    options: &LoadOptions,
+    safe: bool,
+) -> PyResult<PyObject> {
+    let mut entries = Entries::new(parser);
+    let mut anchored = Anchored::default();
+    // Nodes of the whole document, for `max_nodes`
+    let mut count = Count::default();
+    let loaded = match entries.root()? {
+        None => return Ok(py.None()),
+        Some(Root::Node(node)) => {
+            check(&node, options, safe, &mut count)?;
+            construct_node(py, &node, &node, options)?
+        }
+        Some(Root::Sequence) => {
+            options.limits.add_nodes(&mut count, 1, None)?;
+            let list = PyList::empty_bound(py);
+            while let Some(item) = entries.next_item()? {
+                check(&item, options, safe, &mut count)?;
+                list.append(anchored.construct(py, item, options)?)?;
+            }
+            list.into()
+        }
+        Some(Root::Mapping(start)) => {
+            options.limits.add_nodes(&mut count, 1, Some(start))?;
+            mapping(
+                py,
+                &mut entries,
+                start,
+                &mut anchored,
+                &mut count,
+                options,
+                safe,
+            )?
+        }
+    };
+    entries.finish()?;
+    Ok(loaded)
+}
+
+/// Build a root mapping pair by pair
+///
+/// Each pair is constructed as a one-pair mapping, so `resources:` and
+/// merge keys are handled as they are anywhere else; its items are then
+/// moved into the result.
+fn mapping(
+    py: Python,
+    entries: &mut Entries,
+    start: Mark,
+    anchored: &mut Anchored,
+    nodes: &mut Count,
+    options: &LoadOptions,
+    safe: bool,
+) -> PyResult<PyObject> {
+    let items = PyDict::new_bound(py);
+    // Keys written in the root mapping itself, which merged keys don't override
+    let own = PySet::empty_bound(py)?;
+    let mut count = 0;
+    while let Some((key, value)) = entries.next_pair()? {
+        count += 1;
+        options.limits.check_mapping(count, Some(start))?;
+        check(&key, options, safe, nodes)?;
+        check(&value, options, safe, nodes)?;
+        let merge = is_merge_key(&key, options.merge_keys());
+        let pair = Node {
+            start: key.start,
+            end: value.end,
+            kind: NodeKind::Mapping {
+                pairs: vec![(key, value)],
+                flow: false,
+            },
+            tag: None,
+            anchor: None,
+        };
+        let constructed = anchored.construct(py, pair, options)?;
+        for item in constructed.bind(py).call_method0("items")?.iter()? {
+            let (key, value): (Bound<PyAny>, Bound<PyAny>) = item?.extract()?;
+            if !merge {
+                own.add(&key)?;
+            } else if own.contains(&key)? {
+                continue;
+            }
+            items.set_item(key, value)?;
+        }
+    }
+
+    if !options.sort_keys && options.dict_class.is_none() {
+        return Ok(items.into());
+    }
+    let pairs = if options.sort_keys {
+        sorted_items(&items)
+    } else {
+        items.iter().collect()
+    };
+    let mapping = match &options.dict_class {
+        Some(class) => class.bind(py).call0()?,
+        None => PyDict::new_bound(py).into_any(),
+    };
+    for (key, value) in pairs {
+        mapping.set_item(key, value)?;
+    }
+    Ok(mapping.unbind())
+}
+
+/// Limits and (for safe loads) tag checks for one entry, adding its nodes
+/// to `count`
+fn check(
+    node: &Node,
+    options: &LoadOptions,
+    safe: bool,
+    count: &mut Count,
+) -> Result<(), YAMLError> {
+    options.limits.check_entry(node, count)?;
+    if safe {
+        safe::check_node_safety(node)?;
+    }
+    Ok(())
+}
+
+/// Root entries constructed so far that define anchors
+///
+/// Later entries are constructed under this sequence, so their aliases
+/// resolve as they would in a full load.
+struct Anchored {
+    root: Node,
+}
+
+impl Default for Anchored {
+    fn default() -> Self {
+        Anchored {
+            root: Node {
+                kind: NodeKind::Sequence {
+                    items: Vec::new(),
+                    flow: false,
+                },
+                tag: None,
+                anchor: None,
+                start: Mark::default(),
+                end: Mark::default(),
+            },
+        }
+    }
+}
+
+impl Anchored {
+    /// Construct `entry`, keeping it if later entries may alias into it
+    fn construct(&mut self, py: Python, entry: Node, options: &LoadOptions) -> PyResult<PyObject> {
+        let constructed = construct_node(py, &self.root, &entry, options)?;
+        if defines_anchor(&entry) {
+            if let NodeKind::Sequence { items, .. } = &mut self.root.kind {
+                items.push(entry);
+            }
+        }
+        Ok(constructed)
+    }
+}
+
+fn defines_anchor(node: &Node) -> bool {
+    node.anchor.is_some()
+        || match &node.kind {
+            NodeKind::Sequence { items, .. } => items.iter().any(defines_anchor),
+            NodeKind::Mapping { pairs, .. } => pairs
+                .iter()
+                .any(|(key, value)| defines_anchor(key) || defines_anchor(value)),
+            NodeKind::Scalar { .. } | NodeKind::Alias { .. } => false,
+        }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+    use std::io::Cursor;
+
+    fn stream(py: Python, yaml: &'static str, options: &LoadOptions) -> PyResult<PyObject> {
+        load(py, Parser::from_reader(Cursor::new(yaml)), options, true)
+    }
+
+    #[test]
+    fn test_matches_full_load() {
+        Python::with_gil(|py| {
+            let options = LoadOptions::default();
+            for yaml in [
+                "a: 1\nb: [x, {c: 2.5}]\nc: null\n",
+                "- 1\n- two\n- {three: 3}\n",
+                "plain scalar\n",
+                "",
+                "defaults: &d {x: 1, y: 2}\nitems:\n  - <<: *d\n    y: 3\n",
+            ] {
+                let streamed = stream(py, yaml, &options).unwrap();
+                let full = parser::parse_safe(py, yaml, &options).unwrap();
+                assert!(streamed.bind(py).eq(full).unwrap(), "{:?}", yaml);
+            }
+        });
+    }
+
+    #[test]
+    fn test_aliases_across_entries() {
+        Python::with_gil(|py| {
+            let options = LoadOptions {
+                pyyaml_compat: true,
+                ..Default::default()
+            };
+            let value = stream(py, "- &a [1]\n- 2\n- *a\n", &options).unwrap();
+            assert_eq!(value.bind(py).repr().unwrap().to_string(), "[[1], 2, [1]]");
+            assert!(stream(py, "- *missing\n", &options).is_err());
+        });
+    }
+
+    #[test]
+    fn test_root_merge_keys() {
+        Python::with_gil(|py| {
+            let options = LoadOptions {
+                pyyaml_compat: true,
+                sort_keys: true,
+                ..Default::default()
+            };
+            let yaml = "base: &b {x: 1, y: 2}\ny: 3\n<<: *b\nz: 4\n";
+            let value = stream(py, yaml, &options).unwrap();
+            assert_eq!(
+                value.bind(py).repr().unwrap().to_string(),
+                "{'base': {'x': 1, 'y': 2}, 'x': 1, 'y': 3, 'z': 4}"
+            );
+        });
+    }
+
+    #[test]
+    fn test_checks() {
+        Python::with_gil(|py| {
+            let options = LoadOptions::default();
+            assert!(stream(py, "a: !!python/object:os.system x\n", &options).is_err());
+            assert!(stream(py, "a: 1\n---\nb: 2\n", &options).is_err());
+
+            let mut limited = LoadOptions::default();
+            limited.limits.max_mapping_entries = Some(2);
+            assert!(stream(py, "a: 1\nb: 2\n", &limited).is_ok());
+            assert!(stream(py, "a: 1\nb: 2\nc: 3\n", &limited).is_err());
+            // Aliases into earlier entries count as what they stand for
+            limited.limits.max_nodes = Some(9);
+            assert!(stream(py, "a: &a [1, 2]\nb: *a\n", &limited).is_ok());
+            limited.limits.max_nodes = Some(8);
+            assert!(stream(py, "a: &a [1, 2]\nb: *a\n", &limited).is_err());
+
+            let marked = LoadOptions {
+                marked: true,
+                ..Default::default()
+            };
+            assert!(check_options(&marked).is_err());
+            assert!(check_options(&options).is_ok());
+        });
+    }
+}
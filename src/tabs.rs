@@ -0,0 +1,79 @@
+//! Tab indentation diagnostics
+//!
+//! YAML forbids tabs for indentation, but libyaml reports them as generic
+//! scanner errors ("found character that cannot start any token", "found a
+//! tab character that violates indentation") that often point somewhere
+//! else. When a parse fails, scan the source for a tab in a line's leading
+//! whitespace and report that instead.
+//!
+//! The scan only runs on the error path: tabs are legal inside block
+//! scalars and flow collections, so finding one does not mean a document
+//! is invalid.
+
+use crate::error::YAMLError;
+
+/// Find the first tab used as indentation (1-based line and column)
+pub fn find_tab_indentation(yaml_str: &str) -> Option<(usize, usize)> {
+    for (i, line) in yaml_str.lines().enumerate() {
+        let indent = line.len() - line.trim_start_matches([' ', '\t']).len();
+        let rest = line[indent..].trim_end();
+        if rest.is_empty() || rest.starts_with('#') {
+            continue;
+        }
+        if let Some(col) = line[..indent].find('\t') {
+            return Some((i + 1, col + 1));
+        }
+    }
+    None
+}
+
+/// Replace a parse error with a tab error if a tab at or before it explains it
+pub fn explain(err: YAMLError, yaml_str: &str) -> YAMLError {
+    let YAMLError::ParseError { line, .. } = &err else {
+        return err;
+    };
+    match find_tab_indentation(yaml_str) {
+        // Line 0 means the parser did not know where the error was
+        Some((tab_line, col)) if *line == 0 || tab_line <= *line => {
+            YAMLError::tab_indentation(tab_line, col, yaml_str)
+        }
+        _ => err,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_tab_indentation() {
+        assert_eq!(find_tab_indentation("a:\n\tb: 1"), Some((2, 1)));
+        assert_eq!(find_tab_indentation("a:\n  \tb: 1"), Some((2, 3)));
+        assert_eq!(find_tab_indentation("a:\tb"), None);
+        assert_eq!(find_tab_indentation("a: 1\n\t\n\t# note"), None);
+    }
+
+    #[test]
+    fn test_explain_replaces_parse_error() {
+        let yaml = "a:\n\tb: 1";
+        let err = explain(YAMLError::parse(2, 1, "scanner".to_string()), yaml);
+        assert!(matches!(
+            err,
+            YAMLError::TabIndentation {
+                line: 2,
+                col: 1,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_explain_keeps_unrelated_errors() {
+        let yaml = "a: [1\nb:\n\tc: 1";
+        let err = explain(YAMLError::parse(1, 4, "unclosed".to_string()), yaml);
+        assert!(matches!(err, YAMLError::ParseError { .. }));
+
+        let err = explain(YAMLError::unsafe_tag("!!python/object".to_string()), yaml);
+        assert!(matches!(err, YAMLError::UnsafeTag { .. }));
+    }
+}
@@ -0,0 +1,159 @@
+//! Inventory the tags of a YAML stream (`audit_tags`)
+//!
+//! Before settling on an `allowed_tags=` policy, a security team needs to
+//! know which tags its YAML actually uses. The stream is only composed,
+//! so nothing is constructed and any tag, unsafe ones included, can be
+//! reported rather than rejected.
+
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+
+use crate::compose::{self, Node, NodeKind};
+use crate::error::YAMLError;
+use crate::safe;
+
+/// One tagged node
+#[derive(Debug, PartialEq, Eq)]
+pub struct TagUse {
+    /// The tag, resolved (`!!str` is `tag:yaml.org,2002:str`)
+    pub tag: String,
+    /// Index of the document it's in
+    pub document: usize,
+    /// 0-based position of the tagged node
+    pub line: usize,
+    pub column: usize,
+    /// `"scalar"`, `"sequence"` or `"mapping"`
+    pub kind: &'static str,
+}
+
+impl TagUse {
+    /// Would `safe_load` refuse it even when listed in `allowed_tags`?
+    pub fn is_unsafe(&self) -> bool {
+        safe::is_unsafe_tag(&self.tag)
+    }
+}
+
+fn collect(node: &Node, document: usize, uses: &mut Vec<TagUse>) {
+    let kind = match &node.kind {
+        NodeKind::Scalar { .. } => "scalar",
+        NodeKind::Sequence { .. } => "sequence",
+        NodeKind::Mapping { .. } => "mapping",
+        // An alias has no tag of its own, and its anchor was reported
+        NodeKind::Alias { .. } => return,
+    };
+    if let Some(tag) = &node.tag {
+        uses.push(TagUse {
+            tag: tag.clone(),
+            document,
+            line: node.start.line,
+            column: node.start.column,
+            kind,
+        });
+    }
+    match &node.kind {
+        NodeKind::Sequence { items, .. } => {
+            for item in items {
+                collect(item, document, uses);
+            }
+        }
+        NodeKind::Mapping { pairs, .. } => {
+            for (key, value) in pairs {
+                collect(key, document, uses);
+                collect(value, document, uses);
+            }
+        }
+        NodeKind::Scalar { .. } | NodeKind::Alias { .. } => {}
+    }
+}
+
+/// Every explicitly tagged node of every document in `yaml_str`, in
+/// document order
+///
+/// # Errors
+/// * Parse errors
+pub fn audit(yaml_str: &str) -> Result<Vec<TagUse>, YAMLError> {
+    let mut uses = Vec::new();
+    for (index, document) in compose::compose_all(yaml_str)?.iter().enumerate() {
+        collect(&document.root, index, &mut uses);
+    }
+    Ok(uses)
+}
+
+/// List the tags of a YAML stream without constructing anything
+///
+/// # Arguments
+/// * `yaml_str` - YAML content; every document is audited
+///
+/// # Returns
+/// A list with a dict per tagged node, in document order: `tag` (resolved,
+/// so `!!binary` is `tag:yaml.org,2002:binary`), `document` (index),
+/// `line` and `column` (1-based), `kind` (`"scalar"`, `"sequence"` or
+/// `"mapping"`) and `unsafe` (whether it's a tag like `!!python/object`
+/// that `safe_load` always rejects)
+///
+/// # Errors
+/// * `YAMLError` for syntax errors
+///
+/// # Example
+/// ```python
+/// for use in rustyyaml.audit_tags(template):
+///     print(f"{use['line']}:{use['column']}: {use['tag']}")
+/// ```
+#[pyfunction]
+pub fn audit_tags(py: Python, yaml_str: &str) -> PyResult<PyObject> {
+    let uses = py.allow_threads(|| audit(yaml_str))?;
+    let result = PyList::empty_bound(py);
+    for tag_use in &uses {
+        let entry = PyDict::new_bound(py);
+        entry.set_item("tag", &tag_use.tag)?;
+        entry.set_item("document", tag_use.document)?;
+        entry.set_item("line", tag_use.line + 1)?;
+        entry.set_item("column", tag_use.column + 1)?;
+        entry.set_item("kind", tag_use.kind)?;
+        entry.set_item("unsafe", tag_use.is_unsafe())?;
+        result.append(entry)?;
+    }
+    Ok(result.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audit() {
+        let uses = audit("a: !Ref x\nb: !Sub [y]\n!Key k: &m !!map {c: !!str 1}\nd: *m\n").unwrap();
+        let found: Vec<_> = uses
+            .iter()
+            .map(|tag_use| {
+                (
+                    tag_use.tag.as_str(),
+                    tag_use.line,
+                    tag_use.column,
+                    tag_use.kind,
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("!Ref", 0, 3, "scalar"),
+                ("!Sub", 1, 3, "sequence"),
+                ("!Key", 2, 0, "scalar"),
+                ("tag:yaml.org,2002:map", 2, 8, "mapping"),
+                ("tag:yaml.org,2002:str", 2, 21, "scalar"),
+            ]
+        );
+        assert!(uses.iter().all(|tag_use| !tag_use.is_unsafe()));
+    }
+
+    #[test]
+    fn test_unsafe_and_documents() {
+        let uses = audit("a: 1\n---\n!!python/object/apply:os.system [ls]\n").unwrap();
+        assert_eq!(uses.len(), 1);
+        assert_eq!(uses[0].document, 1);
+        assert!(uses[0].is_unsafe());
+        assert!(audit("").unwrap().is_empty());
+        assert!(audit("a: [\n").is_err());
+    }
+}
@@ -0,0 +1,307 @@
+//! Apply a Python callable to scalar values
+//!
+//! `map_scalars` is the primitive behind templating, unit conversion and
+//! secret rewriting: every scalar value (not keys) is passed to a callable
+//! and replaced by what it returns. With `paths`, only scalars at or below
+//! a matching key path (`grep` syntax) are passed.
+//!
+//! YAML text is edited in place like `redact` does: only the scalars whose
+//! value changed are rewritten, so comments and formatting are kept.
+//! Loaded data comes back as a transformed copy.
+
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList, PyString, PyTuple};
+use serde_yaml::Value;
+
+use crate::compose::{self, Node, NodeKind};
+use crate::construct::construct_node;
+use crate::namespace::Namespace;
+use crate::options::LoadOptions;
+use crate::redact;
+use crate::search::Segment;
+use crate::types::python_to_yaml;
+
+/// A step from a collection to one of its children
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Step {
+    Key(String),
+    Index(usize),
+}
+
+/// Is `path`, or one of its ancestors, matched by a pattern?
+///
+/// No patterns means everything is selected.
+fn selected(patterns: &[Vec<Segment>], path: &[Step]) -> bool {
+    patterns.is_empty()
+        || patterns.iter().any(|pattern| {
+            pattern.len() <= path.len()
+                && pattern
+                    .iter()
+                    .zip(path)
+                    .all(|(segment, step)| match (segment, step) {
+                        (Segment::Key(name), Step::Key(key)) => name == key,
+                        (Segment::AnyKey, Step::Key(_)) | (Segment::AnyIndex, Step::Index(_)) => {
+                            true
+                        }
+                        (Segment::Index(wanted), Step::Index(index)) => wanted == index,
+                        _ => false,
+                    })
+        })
+}
+
+/// Calls the callable and tracks where the traversal is
+struct Mapper<'a, 'py> {
+    py: Python<'py>,
+    func: &'a Bound<'py, PyAny>,
+    patterns: &'a [Vec<Segment>],
+    path: Vec<Step>,
+}
+
+impl<'py> Mapper<'_, 'py> {
+    /// A transformed copy of loaded data
+    fn map_object(&mut self, value: &Bound<'py, PyAny>) -> PyResult<PyObject> {
+        let py = self.py;
+        if let Ok(namespace) = value.downcast::<Namespace>() {
+            let dict = self.map_object(namespace.get().dict(py).as_any())?;
+            return Ok(value.get_type().call1((dict,))?.unbind());
+        }
+        if let Ok(dict) = value.downcast::<PyDict>() {
+            let mapped = PyDict::new_bound(py);
+            for (key, child) in dict.iter() {
+                let step = match key.downcast::<PyString>() {
+                    Ok(key) => key.to_cow()?.into_owned(),
+                    Err(_) => key.str()?.to_cow()?.into_owned(),
+                };
+                self.path.push(Step::Key(step));
+                mapped.set_item(key, self.map_object(&child)?)?;
+                self.path.pop();
+            }
+            return Ok(mapped.into());
+        }
+        if value.is_instance_of::<PyList>() || value.is_instance_of::<PyTuple>() {
+            let mut items = Vec::new();
+            for (index, item) in value.iter()?.enumerate() {
+                self.path.push(Step::Index(index));
+                items.push(self.map_object(&item?)?);
+                self.path.pop();
+            }
+            if value.is_instance_of::<PyTuple>() {
+                return Ok(PyTuple::new_bound(py, items).into());
+            }
+            return Ok(PyList::new_bound(py, items).into());
+        }
+        if selected(self.patterns, &self.path) {
+            return Ok(self.func.call1((value,))?.unbind());
+        }
+        Ok(value.clone().unbind())
+    }
+
+    /// Collect replacements for the scalars of a composed document
+    fn map_node(
+        &mut self,
+        root: &Node,
+        node: &Node,
+        source: &str,
+        edits: &mut Vec<(usize, usize, String)>,
+    ) -> PyResult<()> {
+        match &node.kind {
+            NodeKind::Mapping { pairs, .. } => {
+                for (key, value) in pairs {
+                    let NodeKind::Scalar { value: name, .. } = &key.kind else {
+                        continue;
+                    };
+                    self.path.push(Step::Key(name.clone()));
+                    self.map_node(root, value, source, edits)?;
+                    self.path.pop();
+                }
+            }
+            NodeKind::Sequence { items, .. } => {
+                for (index, item) in items.iter().enumerate() {
+                    self.path.push(Step::Index(index));
+                    self.map_node(root, item, source, edits)?;
+                    self.path.pop();
+                }
+            }
+            NodeKind::Scalar { .. } if selected(self.patterns, &self.path) => {
+                let options = LoadOptions::default();
+                let original = construct_node(self.py, root, node, &options)?;
+                let original = original.bind(self.py);
+                let mapped = self.func.call1((original,))?;
+                // Unchanged values keep their exact spelling
+                if mapped.eq(original)? && mapped.get_type().is(&original.get_type()) {
+                    return Ok(());
+                }
+                let mut text = emit(&python_to_yaml(&mapped)?)?;
+                if let Some(anchor) = &node.anchor {
+                    text = format!("&{} {}", anchor, text);
+                }
+                // Block scalars end after their last line break
+                let end =
+                    node.start.index + source[node.start.index..node.end.index].trim_end().len();
+                edits.push((node.start.index, end, text));
+            }
+            NodeKind::Scalar { .. } | NodeKind::Alias { .. } => {}
+        }
+        Ok(())
+    }
+}
+
+/// A value written so it can stand in for any scalar, block or flow
+fn emit(value: &Value) -> PyResult<String> {
+    let invalid = |err: &dyn std::fmt::Display| PyValueError::new_err(err.to_string());
+    match value {
+        Value::String(text) => {
+            let yaml = serde_yaml::to_string(value).map_err(|err| invalid(&err))?;
+            let yaml = yaml.trim_end();
+            // Plain or single-quoted on one line, or else a JSON string,
+            // which YAML reads as a double-quoted scalar
+            if yaml.contains('\n') || yaml.contains(['[', ']', '{', '}', ',']) {
+                serde_json::to_string(text).map_err(|err| invalid(&err))
+            } else {
+                Ok(yaml.to_string())
+            }
+        }
+        Value::Sequence(_) | Value::Mapping(_) => {
+            // Flow style fits where a scalar was
+            serde_json::to_string(value).map_err(|err| invalid(&err))
+        }
+        _ => serde_yaml::to_string(value)
+            .map(|yaml| yaml.trim_end().to_string())
+            .map_err(|err| invalid(&err)),
+    }
+}
+
+/// Apply `func` to the scalars of YAML text, keeping everything else
+///
+/// # Errors
+/// * Parse errors, or whatever `func` raises
+fn map_text(
+    py: Python,
+    source: &str,
+    func: &Bound<'_, PyAny>,
+    patterns: &[Vec<Segment>],
+) -> PyResult<String> {
+    let documents = py.allow_threads(|| compose::compose_all(source))?;
+    let mut mapper = Mapper {
+        py,
+        func,
+        patterns,
+        path: Vec::new(),
+    };
+    let mut edits = Vec::new();
+    for document in &documents {
+        mapper.map_node(&document.root, &document.root, source, &mut edits)?;
+    }
+    let mut out = String::with_capacity(source.len());
+    let mut copied = 0;
+    for (start, end, text) in edits {
+        out.push_str(&source[copied..start]);
+        out.push_str(&text);
+        copied = end;
+    }
+    out.push_str(&source[copied..]);
+    Ok(out)
+}
+
+/// Apply a callable to every scalar value
+///
+/// # Arguments
+/// * `yaml_or_doc` - YAML text, or loaded data
+/// * `func` - Called with each scalar value (as loaded); its return value
+///   replaces it
+/// * `paths` - Key path or list of key paths in `grep` syntax; only
+///   scalars at or below a match are passed (default: all)
+///
+/// # Returns
+/// For YAML text, the same text with the changed scalars rewritten
+/// (comments and formatting are kept). For loaded data, a transformed copy.
+///
+/// # Example
+/// ```python
+/// text = rustyyaml.map_scalars(text, str.upper, paths="metadata.labels.*")
+/// ```
+#[pyfunction]
+#[pyo3(signature = (yaml_or_doc, func, paths=None))]
+pub fn map_scalars(
+    py: Python,
+    yaml_or_doc: &Bound<'_, PyAny>,
+    func: &Bound<'_, PyAny>,
+    paths: Option<&Bound<'_, PyAny>>,
+) -> PyResult<PyObject> {
+    if !func.is_callable() {
+        return Err(PyTypeError::new_err("map_scalars() needs a callable"));
+    }
+    let patterns = match paths {
+        Some(paths) if !paths.is_none() => redact::extract_paths(paths)?,
+        _ => Vec::new(),
+    };
+    if let Ok(source) = yaml_or_doc.downcast::<PyString>() {
+        return Ok(map_text(py, &source.to_cow()?, func, &patterns)?.into_py(py));
+    }
+    Mapper {
+        py,
+        func,
+        patterns: &patterns,
+        path: Vec::new(),
+    }
+    .map_object(yaml_or_doc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search;
+
+    fn text(source: &str, func: &str, paths: &[&str]) -> String {
+        Python::with_gil(|py| {
+            let func = py.eval_bound(func, None, None).unwrap();
+            let patterns: Vec<Vec<Segment>> = paths
+                .iter()
+                .map(|path| search::parse_key_path(path).unwrap())
+                .collect();
+            map_text(py, source, &func, &patterns).unwrap()
+        })
+    }
+
+    #[test]
+    fn test_selected() {
+        let patterns = vec![search::parse_key_path("spec.ports[*]").unwrap()];
+        let key = |name: &str| Step::Key(name.to_string());
+        assert!(selected(
+            &patterns,
+            &[key("spec"), key("ports"), Step::Index(0)]
+        ));
+        assert!(selected(
+            &patterns,
+            &[key("spec"), key("ports"), Step::Index(1), key("port")]
+        ));
+        assert!(!selected(&patterns, &[key("spec"), key("ports")]));
+        assert!(!selected(&patterns, &[key("spec"), key("name")]));
+        assert!(selected(&[], &[]));
+    }
+
+    #[test]
+    fn test_emit() {
+        let emitted = |yaml: &str| emit(&serde_yaml::from_str(yaml).unwrap()).unwrap();
+        assert_eq!(emitted("plain text"), "plain text");
+        assert_eq!(emitted("'123'"), "'123'");
+        assert_eq!(emitted("'a, b'"), "\"a, b\"");
+        assert_eq!(emitted("|\n  two\n  lines\n"), "\"two\\nlines\\n\"");
+        assert_eq!(emitted("[1, {a: b}]"), "[1,{\"a\":\"b\"}]");
+        assert_eq!(emitted("~"), "null");
+    }
+
+    #[test]
+    fn test_map_text_keeps_formatting() {
+        let source = "# config\nname: web  # public\nport: &p 80\nalias: *p\nflow: [1, 2]\n";
+        assert_eq!(
+            text(source, "lambda v: v * 2 if isinstance(v, int) else v", &[]),
+            "# config\nname: web  # public\nport: &p 160\nalias: *p\nflow: [2, 4]\n"
+        );
+        assert_eq!(
+            text(source, "lambda v: str(v)", &["flow"]),
+            "# config\nname: web  # public\nport: &p 80\nalias: *p\nflow: ['1', '2']\n"
+        );
+    }
+}
@@ -5,92 +5,457 @@
 //! - Collections: sequence (list), mapping (dict)
 //! - Tags: Custom type annotations (we reject these in safe mode)
 
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::hash::{BuildHasherDefault, Hasher};
+
+use pyo3::exceptions::{PyTypeError, PyValueError};
 use pyo3::prelude::*;
-use pyo3::types::{PyDict, PyFloat, PyList, PyString};
+use pyo3::types::{PyBool, PyDict, PyFloat, PyInt, PyList, PyString, PyTuple};
+use serde_yaml::value::{Tag, TaggedValue};
 use serde_yaml::Value;
 
+use crate::dumper::{self, Builtin};
 use crate::error::YAMLError;
+use crate::namespace::Namespace;
 
 /// Convert a serde_yaml::Value to a Python object
 ///
 /// This is the critical path - every YAML element passes through here.
 /// Performance notes:
-/// - PyString::new() copies the string (unavoidable FFI cost)
+/// - Creating a Python str (`PyUnicode_FromStringAndSize`, the only
+///   constructor in the stable ABI) copies and decodes the bytes, so
+///   repeated short strings (mapping keys in a list of records, enum-like
+///   values) are created once per conversion and shared, see `StrCache`
+/// - Lists are allocated at their final size rather than appended to
 /// - PyDict::new() allocates on Python heap
-/// - We use intern_bound() for small strings (Python caches them)
 pub fn yaml_to_python(py: Python, value: &Value) -> PyResult<PyObject> {
-    match value {
-        // Null becomes None
-        Value::Null => Ok(py.None()),
-
-        // Booleans
-        Value::Bool(b) => Ok(b.to_object(py)),
-
-        // Numbers (YAML allows arbitrary precision, Python has int/float)
-        Value::Number(n) => {
-            if let Some(i) = n.as_i64() {
-                // Try as i64 first (most common case)
-                Ok(i.to_object(py))
-            } else if let Some(u) = n.as_u64() {
-                // Large unsigned integers
-                Ok(u.to_object(py))
-            } else if let Some(f) = n.as_f64() {
-                // Floating point
-                Ok(PyFloat::new_bound(py, f).into())
-            } else {
-                // Shouldn't happen with serde_yaml, but be defensive
-                Err(YAMLError::invalid_number(n.to_string()).into())
-            }
+    Converter {
+        py,
+        strings: StrCache::default(),
+        sort_keys: false,
+    }
+    .convert(value)
+}
+
+/// Convert a serde_yaml::Value with every mapping's keys sorted
+/// (`sort_keys=True`, see `key_order`)
+pub fn yaml_to_python_sorted(py: Python, value: &Value) -> PyResult<PyObject> {
+    Converter {
+        py,
+        strings: StrCache::default(),
+        sort_keys: true,
+    }
+    .convert(value)
+}
+
+/// Rank of a key's type under `sort_keys=True`
+fn key_rank(key: &Bound<'_, PyAny>) -> u8 {
+    if key.is_none() {
+        0
+    } else if key.is_instance_of::<PyBool>() {
+        1
+    } else if key.is_instance_of::<PyInt>() || key.is_instance_of::<PyFloat>() {
+        2
+    } else if key.is_instance_of::<PyString>() {
+        3
+    } else {
+        4
+    }
+}
+
+/// The order of mapping keys under `sort_keys=True`
+///
+/// `None`, then booleans, numbers and strings, each in their natural
+/// order; other keys (dates, tuples) come last, grouped by type name.
+/// Keys Python cannot compare are equal, so a stable sort keeps their
+/// document order.
+pub fn key_order(a: &Bound<'_, PyAny>, b: &Bound<'_, PyAny>) -> Ordering {
+    let rank = key_rank(a).cmp(&key_rank(b));
+    if rank != Ordering::Equal {
+        return rank;
+    }
+    if key_rank(a) == 4 {
+        let name = |key: &Bound<'_, PyAny>| {
+            key.get_type()
+                .qualname()
+                .map(|name| name.to_string())
+                .unwrap_or_default()
+        };
+        let by_type = name(a).cmp(&name(b));
+        if by_type != Ordering::Equal {
+            return by_type;
         }
+    }
+    if a.lt(b).unwrap_or(false) {
+        Ordering::Less
+    } else if b.lt(a).unwrap_or(false) {
+        Ordering::Greater
+    } else {
+        Ordering::Equal
+    }
+}
 
-        // Strings (most common case - optimize this)
-        Value::String(s) => {
-            // For short strings (<10 chars), use interning (Python caches these)
-            if s.len() < 10 {
-                Ok(PyString::intern_bound(py, s).into())
-            } else {
-                Ok(PyString::new_bound(py, s).into())
-            }
+/// The entries of `dict` in `key_order`
+///
+/// Sort entries only once they are in a dict: colliding keys (`1` and
+/// `true`) then resolve in document order, the first key with the last
+/// value, whether or not the keys are sorted.
+pub fn sorted_items<'py>(dict: &Bound<'py, PyDict>) -> Vec<(Bound<'py, PyAny>, Bound<'py, PyAny>)> {
+    let mut items: Vec<_> = dict.iter().collect();
+    items.sort_by(|(a, _), (b, _)| key_order(a, b));
+    items
+}
+
+/// Python strings already created during one conversion
+///
+/// Only short strings are cached, and only up to `MAX_ENTRIES` of them, so
+/// documents of unique values do not pay for a table they never hit.
+#[derive(Default)]
+pub struct StrCache<'v> {
+    strings: HashMap<&'v str, Py<PyString>, BuildHasherDefault<Fnv>>,
+}
+
+/// FNV-1a: much cheaper than SipHash for short keys; the input is the
+/// document being loaded, so there is nobody to mount a collision attack
+#[derive(Default)]
+struct Fnv(u64);
+
+impl Hasher for Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        let mut hash = if self.0 == 0 {
+            0xcbf29ce484222325
+        } else {
+            self.0
+        };
+        for byte in bytes {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(0x100000001b3);
         }
+        self.0 = hash;
+    }
 
-        // Sequences (YAML lists → Python lists)
-        Value::Sequence(seq) => {
-            let list = PyList::empty_bound(py);
-            for item in seq {
-                // Recursive conversion
-                let py_item = yaml_to_python(py, item)?;
-                list.append(py_item)?;
-            }
-            Ok(list.into())
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+impl<'v> StrCache<'v> {
+    /// Longest string worth looking up (in bytes)
+    const MAX_LEN: usize = 32;
+    const MAX_ENTRIES: usize = 4096;
+
+    pub fn get(&mut self, py: Python, s: &'v str) -> PyObject {
+        if s.len() > Self::MAX_LEN {
+            return PyString::new_bound(py, s).into();
+        }
+        if let Some(cached) = self.strings.get(s) {
+            return cached.clone_ref(py).into_any();
         }
+        let string = PyString::new_bound(py, s).unbind();
+        if self.strings.len() < Self::MAX_ENTRIES {
+            self.strings.insert(s, string.clone_ref(py));
+        }
+        string.into_any()
+    }
+}
+
+struct Converter<'py, 'v> {
+    py: Python<'py>,
+    strings: StrCache<'v>,
+    sort_keys: bool,
+}
+
+impl<'v> Converter<'_, 'v> {
+    fn convert(&mut self, value: &'v Value) -> PyResult<PyObject> {
+        let py = self.py;
+        match value {
+            // Null becomes None
+            Value::Null => Ok(py.None()),
+
+            // Booleans
+            Value::Bool(b) => Ok(b.to_object(py)),
+
+            // Numbers (YAML allows arbitrary precision, Python has int/float)
+            Value::Number(n) => {
+                if let Some(i) = n.as_i64() {
+                    // Try as i64 first (most common case)
+                    Ok(i.to_object(py))
+                } else if let Some(u) = n.as_u64() {
+                    // Large unsigned integers
+                    Ok(u.to_object(py))
+                } else if let Some(f) = n.as_f64() {
+                    // Floating point
+                    Ok(PyFloat::new_bound(py, f).into())
+                } else {
+                    // Shouldn't happen with serde_yaml, but be defensive
+                    Err(YAMLError::invalid_number(n.to_string()).into())
+                }
+            }
+
+            // Strings (most common case - optimize this)
+            Value::String(s) => Ok(self.strings.get(py, s)),
+
+            // Sequences (YAML lists → Python lists)
+            Value::Sequence(seq) => {
+                let items = seq
+                    .iter()
+                    .map(|item| self.convert(item))
+                    .collect::<PyResult<Vec<_>>>()?;
+                Ok(PyList::new_bound(py, items).into())
+            }
 
-        // Mappings (YAML maps → Python dicts)
-        // CRITICAL: Must preserve insertion order (YAML 1.2 spec requirement)
-        Value::Mapping(map) => {
-            let dict = PyDict::new_bound(py);
-            for (k, v) in map {
-                let py_key = yaml_to_python(py, k)?;
-                let py_val = yaml_to_python(py, v)?;
-                dict.set_item(py_key, py_val)?;
+            // Mappings (YAML maps → Python dicts)
+            // CRITICAL: Must preserve insertion order (YAML 1.2 spec requirement)
+            Value::Mapping(map) => {
+                let dict = PyDict::new_bound(py);
+                if self.sort_keys {
+                    let resolved = PyDict::new_bound(py);
+                    for (k, v) in map {
+                        resolved.set_item(self.convert(k)?, self.convert(v)?)?;
+                    }
+                    for (py_key, py_val) in sorted_items(&resolved) {
+                        dict.set_item(py_key, py_val)?;
+                    }
+                    return Ok(dict.into());
+                }
+                for (k, v) in map {
+                    let py_key = self.convert(k)?;
+                    let py_val = self.convert(v)?;
+                    dict.set_item(py_key, py_val)?;
+                }
+                Ok(dict.into())
             }
-            Ok(dict.into())
+
+            // Tagged values (!!python/object, etc.)
+            // These are DANGEROUS - reject in safe mode
+            Value::Tagged(tagged) => Err(YAMLError::unsafe_tag(tagged.tag.to_string()).into()),
         }
+    }
+}
 
-        // Tagged values (!!python/object, etc.)
-        // These are DANGEROUS - reject in safe mode
-        Value::Tagged(tagged) => Err(YAMLError::unsafe_tag(tagged.tag.to_string()).into()),
+/// Convert a Rust string to a Python str
+///
+/// For short strings (<10 chars), use interning (Python caches these)
+pub fn str_to_python(py: Python, s: &str) -> PyObject {
+    if s.len() < 10 {
+        PyString::intern_bound(py, s).into()
+    } else {
+        PyString::new_bound(py, s).into()
     }
 }
 
-/// Convert Python object to YAML Value (for dump/emit - Phase 2 feature)
+/// Convert a plain Python object to a YAML Value
 ///
-/// Not implementing this in MVP, but here's the signature for future:
-#[allow(dead_code)]
-pub fn python_to_yaml(_py: Python, _obj: &Bound<'_, PyAny>) -> PyResult<Value> {
-    // TODO: Implement in Phase 2 (YAML emission)
-    // Priority: Lists, Dicts, Scalars
-    // Challenge: Handle Python custom objects safely
-    unimplemented!("YAML emission not yet implemented")
+/// Handles the types `yaml_to_python` produces: None, bool, int, float, str,
+/// list/tuple, dict and `Namespace`; the stdlib types `dumper::builtin`
+/// knows (dates, `Decimal`, `UUID`, paths, enums, dataclasses); and objects
+/// of types registered with `add_representer` (as what their representer
+/// returns). Anything else is a `TypeError` (we never serialize arbitrary
+/// objects), and a container that contains itself is a `ValueError` rather
+/// than endless recursion.
+pub fn python_to_yaml(obj: &Bound<'_, PyAny>) -> PyResult<Value> {
+    ToYaml::default().convert(obj)
+}
+
+/// Tag prefix (followed by the object's address) marking a container that
+/// `python_to_yaml_for_dump` found more than once
+pub const SHARED_TAG: &str = "rustyyaml:shared:";
+
+/// Tag marking a string that the emitter writes as it is: a timestamp or
+/// `Decimal` from `python_to_yaml_for_dump`, which must stay unquoted to
+/// load back as one
+pub const PLAIN_TAG: &str = "rustyyaml:plain";
+
+/// `python_to_yaml` for the emitter: every dict, list and non-empty tuple
+/// reached more than once is wrapped in a `SHARED_TAG` tag, so it can be
+/// written once with an anchor and aliased afterwards (as PyYAML does), and
+/// timestamps and decimals are wrapped in `PLAIN_TAG`
+pub fn python_to_yaml_for_dump(obj: &Bound<'_, PyAny>) -> PyResult<Value> {
+    python_to_yaml_for_dump_with(obj, None)
+}
+
+/// `python_to_yaml_for_dump` with a `Dumper`'s own representers, which
+/// take precedence over those registered with `add_representer`
+pub fn python_to_yaml_for_dump_with(
+    obj: &Bound<'_, PyAny>,
+    representers: Option<&Py<PyDict>>,
+) -> PyResult<Value> {
+    let mut counts = HashMap::new();
+    count_containers(obj, &mut counts)?;
+    let shared = counts
+        .into_iter()
+        .filter(|&(_, count)| count > 1)
+        .map(|(address, _)| address)
+        .collect();
+    ToYaml {
+        active: HashSet::new(),
+        shared,
+        for_dump: true,
+        representers: representers.map(|own| own.clone_ref(obj.py())),
+    }
+    .convert(obj)
+}
+
+/// Count how often each container is reached, by address (a repeat isn't
+/// descended into again, so recursive data ends)
+fn count_containers(obj: &Bound<'_, PyAny>, counts: &mut HashMap<usize, usize>) -> PyResult<()> {
+    if let Ok(namespace) = obj.downcast::<Namespace>() {
+        return count_containers(namespace.get().dict(obj.py()).as_any(), counts);
+    }
+    let is_container = obj.is_instance_of::<PyDict>()
+        || obj.is_instance_of::<PyList>()
+        // The empty tuple is a singleton, never worth an alias
+        || obj.downcast::<PyTuple>().is_ok_and(|tuple| !tuple.is_empty());
+    if !is_container {
+        return Ok(());
+    }
+    let count = counts.entry(obj.as_ptr() as usize).or_default();
+    *count += 1;
+    if *count > 1 {
+        return Ok(());
+    }
+    if let Ok(dict) = obj.downcast::<PyDict>() {
+        for (k, v) in dict.iter() {
+            count_containers(&k, counts)?;
+            count_containers(&v, counts)?;
+        }
+    } else {
+        for item in obj.iter()? {
+            count_containers(&item?, counts)?;
+        }
+    }
+    Ok(())
+}
+
+/// One `python_to_yaml` conversion
+#[derive(Default)]
+struct ToYaml {
+    /// Containers being converted, by address
+    active: HashSet<usize>,
+    /// Containers to mark with `SHARED_TAG`, by address
+    shared: HashSet<usize>,
+    /// Mark timestamps and decimals with `PLAIN_TAG`
+    for_dump: bool,
+    /// A `Dumper`'s own representers, by type
+    representers: Option<Py<PyDict>>,
+}
+
+impl ToYaml {
+    fn convert(&mut self, obj: &Bound<'_, PyAny>) -> PyResult<Value> {
+        if let Some(representer) = dumper::representer_for(obj, self.representers.as_ref())? {
+            return self.convert_in_place_of(obj, &representer.call1((obj,))?);
+        }
+        if obj.is_none() {
+            return Ok(Value::Null);
+        }
+        // bool before int: Python's bool is an int subclass
+        if let Ok(b) = obj.downcast::<PyBool>() {
+            return Ok(Value::Bool(b.is_true()));
+        }
+        if obj.is_instance_of::<PyInt>() {
+            if let Ok(i) = obj.extract::<i64>() {
+                return Ok(Value::Number(i.into()));
+            }
+            if let Ok(u) = obj.extract::<u64>() {
+                return Ok(Value::Number(u.into()));
+            }
+            let digits = obj
+                .py()
+                .get_type_bound::<PyInt>()
+                .call_method1("__repr__", (obj,))?;
+            if self.for_dump {
+                // Too big for a `Number`: written as its digits, which
+                // read back as the same int
+                return Ok(self.plain(digits.extract()?));
+            }
+            return Err(YAMLError::invalid_number(digits.to_string()).into());
+        }
+        if let Ok(f) = obj.downcast::<PyFloat>() {
+            return Ok(Value::Number(f.value().into()));
+        }
+        if obj.is_instance_of::<PyString>() {
+            return Ok(Value::String(obj.extract()?));
+        }
+        if let Ok(namespace) = obj.downcast::<Namespace>() {
+            return self.convert(namespace.get().dict(obj.py()).as_any());
+        }
+        let is_mapping = obj.is_instance_of::<PyDict>();
+        if !is_mapping && !obj.is_instance_of::<PyList>() && !obj.is_instance_of::<PyTuple>() {
+            return match dumper::builtin(obj)? {
+                Some(Builtin::Timestamp(text)) => Ok(self.plain(text)),
+                Some(Builtin::Decimal(Some(text), _)) if self.for_dump => Ok(self.plain(text)),
+                Some(Builtin::Decimal(_, f)) => Ok(Value::Number(f.into())),
+                Some(Builtin::Data(data)) => self.convert_in_place_of(obj, &data),
+                None => Err(PyTypeError::new_err(format!(
+                    "cannot convert {} to YAML",
+                    obj.get_type().name()?
+                ))),
+            };
+        }
+        let address = obj.as_ptr() as usize;
+        if !self.active.insert(address) {
+            return Err(PyValueError::new_err(format!(
+                "cannot convert a recursive {} to YAML: it contains itself",
+                obj.get_type().name()?
+            )));
+        }
+        let value = if let Ok(dict) = obj.downcast::<PyDict>() {
+            let mut map = serde_yaml::Mapping::with_capacity(dict.len());
+            for (k, v) in dict.iter() {
+                map.insert(self.convert(&k)?, self.convert(&v)?);
+            }
+            Value::Mapping(map)
+        } else {
+            let items = obj
+                .iter()?
+                .map(|item| self.convert(&item?))
+                .collect::<PyResult<_>>()?;
+            Value::Sequence(items)
+        };
+        // Shared (not recursive) containers are fine: only the path is tracked
+        self.active.remove(&address);
+        if self.shared.contains(&address) {
+            return Ok(Value::Tagged(Box::new(TaggedValue {
+                tag: Tag::new(format!("{}{}", SHARED_TAG, address)),
+                value,
+            })));
+        }
+        Ok(value)
+    }
+
+    /// Convert `data`, a representer's (or `dumper::builtin`'s) stand-in
+    /// for `obj`
+    fn convert_in_place_of(
+        &mut self,
+        obj: &Bound<'_, PyAny>,
+        data: &Bound<'_, PyAny>,
+    ) -> PyResult<Value> {
+        // `obj` is active while its stand-in is converted, so a representer
+        // that hands back `obj` (or something holding it) is caught like a
+        // recursive container
+        let address = obj.as_ptr() as usize;
+        if !self.active.insert(address) {
+            return Err(PyValueError::new_err(format!(
+                "cannot convert {} to YAML: its representation contains the object itself",
+                obj.get_type().name()?
+            )));
+        }
+        let value = self.convert(data);
+        self.active.remove(&address);
+        value
+    }
+
+    /// A string written unquoted when dumping
+    fn plain(&self, text: String) -> Value {
+        if !self.for_dump {
+            return Value::String(text);
+        }
+        Value::Tagged(Box::new(TaggedValue {
+            tag: Tag::new(PLAIN_TAG),
+            value: Value::String(text),
+        }))
+    }
 }
 
 #[cfg(test)]
@@ -118,6 +483,33 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_repeated_strings_shared() {
+        Python::with_gil(|py| {
+            let long = "x".repeat(StrCache::MAX_LEN + 1);
+            let yaml: Value =
+                serde_yaml::from_str(&format!("[{{name: a, v: {long}}}, {{name: b, v: {long}}}]"))
+                    .unwrap();
+            let list = yaml_to_python(py, &yaml).unwrap();
+            let list = list.bind(py);
+            let first = list.get_item(0).unwrap();
+            let second = list.get_item(1).unwrap();
+            let keys = |dict: &Bound<'_, PyAny>| -> Vec<PyObject> {
+                dict.downcast::<PyDict>()
+                    .unwrap()
+                    .keys()
+                    .iter()
+                    .map(|k| k.unbind())
+                    .collect()
+            };
+            // Short keys are created once, long values every time
+            assert!(keys(&first)[0].is(&keys(&second)[0]));
+            let (a, b) = (first.get_item("v").unwrap(), second.get_item("v").unwrap());
+            assert!(!a.is(&b));
+            assert!(a.eq(&b).unwrap());
+        });
+    }
+
     #[test]
     fn test_boolean_conversion() {
         Python::with_gil(|py| {
@@ -168,6 +560,91 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_python_to_yaml_roundtrip() {
+        Python::with_gil(|py| {
+            let value: Value =
+                serde_yaml::from_str("{a: [1, 2.5, true, null], b: {c: text}}").unwrap();
+            let obj = yaml_to_python(py, &value).unwrap();
+            assert_eq!(python_to_yaml(obj.bind(py)).unwrap(), value);
+        });
+    }
+
+    #[test]
+    fn test_python_to_yaml_rejects_objects() {
+        Python::with_gil(|py| {
+            let obj = py.eval_bound("object()", None, None).unwrap();
+            assert!(python_to_yaml(&obj).is_err());
+        });
+    }
+
+    #[test]
+    fn test_python_to_yaml_cycles() {
+        Python::with_gil(|py| {
+            let shared = py
+                .eval_bound("(lambda x: [x, {'a': x}, (x,)])([1])", None, None)
+                .unwrap();
+            let value = python_to_yaml(&shared).unwrap();
+            assert_eq!(
+                value,
+                serde_yaml::from_str::<Value>("[[1], {a: [1]}, [[1]]]").unwrap()
+            );
+
+            let globals = PyDict::new_bound(py);
+            py.run_bound("d = {}\nd['self'] = [d]", Some(&globals), None)
+                .unwrap();
+            let recursive = globals.get_item("d").unwrap().unwrap();
+            let err = python_to_yaml(&recursive).unwrap_err();
+            assert!(err.is_instance_of::<PyValueError>(py));
+            assert!(err.to_string().contains("recursive dict"));
+            assert!(python_to_yaml_for_dump(&recursive).is_err());
+        });
+    }
+
+    #[test]
+    fn test_python_to_yaml_for_dump() {
+        Python::with_gil(|py| {
+            let data = py
+                .eval_bound(
+                    "(lambda x, t: [x, {'a': x}, t, t, (), ()])([1], (2,))",
+                    None,
+                    None,
+                )
+                .unwrap();
+            let Value::Sequence(items) = python_to_yaml_for_dump(&data).unwrap() else {
+                panic!("expected a sequence");
+            };
+            let tag = |value: &Value| match value {
+                Value::Tagged(tagged) => Some(tagged.tag.to_string()),
+                _ => None,
+            };
+            let list_tag = tag(&items[0]).unwrap();
+            assert!(list_tag.contains(SHARED_TAG));
+            assert_eq!(tag(&items[1]), None);
+            assert_eq!(tag(&items[1]["a"]), Some(list_tag.clone()));
+            assert_ne!(tag(&items[2]), Some(list_tag));
+            assert_eq!(tag(&items[2]), tag(&items[3]));
+            assert_eq!(tag(&items[4]), None);
+
+            let big = py.eval_bound("[2**80, -2**80]", None, None).unwrap();
+            let value = python_to_yaml_for_dump(&big).unwrap();
+            let plain = |text: &str| {
+                Value::Tagged(Box::new(TaggedValue {
+                    tag: Tag::new(PLAIN_TAG),
+                    value: Value::String(text.to_string()),
+                }))
+            };
+            assert_eq!(
+                value,
+                Value::Sequence(vec![
+                    plain("1208925819614629174706176"),
+                    plain("-1208925819614629174706176")
+                ])
+            );
+            assert!(python_to_yaml(&big).is_err());
+        });
+    }
+
     #[test]
     fn test_dict_conversion() {
         Python::with_gil(|py| {
@@ -183,4 +660,29 @@ mod tests {
             assert_eq!(dict.len(), 1);
         });
     }
+
+    #[test]
+    fn test_sorted_conversion() {
+        Python::with_gil(|py| {
+            let value: Value =
+                serde_yaml::from_str("b: {z: 1, y: 2}\n10: x\n9: y\na: 1\n~: n\ntrue: t\n")
+                    .unwrap();
+            let dict = yaml_to_python_sorted(py, &value).unwrap();
+            let keys = dict.bind(py).call_method0("keys").unwrap();
+            assert_eq!(
+                keys.iter()
+                    .unwrap()
+                    .map(|key| key.unwrap().to_string())
+                    .collect::<Vec<_>>(),
+                ["None", "True", "9", "10", "a", "b"]
+            );
+            let inner = dict.bind(py).get_item("b").unwrap();
+            assert_eq!(inner.to_string(), "{'y': 2, 'z': 1}");
+
+            // `1` and `true` collide as they do unsorted
+            let value: Value = serde_yaml::from_str("z: 0\n1: one\ntrue: yes\n").unwrap();
+            let dict = yaml_to_python_sorted(py, &value).unwrap();
+            assert_eq!(dict.bind(py).to_string(), "{1: 'yes', 'z': 0}");
+        });
+    }
 }
@@ -0,0 +1,625 @@
+//! Validate documents against a schema
+//!
+//! Documents are checked on the composed node tree, so every finding points
+//! at the line and column it is about: unexpected keys at the key, type
+//! mismatches at the value, missing keys at the mapping. Scalars are typed
+//! with the YAML 1.2 core rules, the same ones `safe_load` uses.
+//!
+//! Unknown keys are errors where the schema says `additionalProperties:
+//! false`, or everywhere with `additional_properties=False`; they come with
+//! a did-you-mean suggestion when a declared key is close enough. Where
+//! unknown keys are allowed, one that looks like a typo of a declared key
+//! is still reported, as a warning.
+//!
+//! Results come back as a `Report` of `Finding`s, each with a severity and
+//! the id of the rule that produced it.
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+
+use crate::compose::{self, Document, Node, NodeKind};
+use crate::construct::construct_node;
+use crate::error::YAMLError;
+use crate::events::Mark;
+use crate::options::LoadOptions;
+use crate::resolve::{self, Scalar};
+use crate::schema::{self, Schema, Type};
+use crate::search::{self, Segment};
+use crate::sourcemap;
+
+/// Rule ids, as reported in `Finding.rule`
+pub const RULE_TYPE: &str = "type";
+pub const RULE_REQUIRED: &str = "required";
+pub const RULE_ADDITIONAL_PROPERTIES: &str = "additional-properties";
+pub const RULE_POSSIBLE_TYPO: &str = "possible-typo";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// A single problem found in a document
+#[pyclass(module = "rustyyaml", frozen)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Finding {
+    /// JSON pointer of the offending node
+    pub pointer: String,
+    /// Where the problem is in the source
+    pub mark: Mark,
+    pub severity: Severity,
+    /// Rule id: one of the `RULE_*` constants, or the key path pattern of
+    /// a custom validator
+    pub rule: String,
+    pub message: String,
+}
+
+impl Finding {
+    fn error(pointer: &str, mark: Mark, rule: &str, message: String) -> Self {
+        Finding {
+            pointer: pointer.to_string(),
+            mark,
+            severity: Severity::Error,
+            rule: rule.to_string(),
+            message,
+        }
+    }
+
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("path", &self.pointer)?;
+        dict.set_item("line", self.mark.line + 1)?;
+        dict.set_item("column", self.mark.column + 1)?;
+        dict.set_item("severity", self.severity.name())?;
+        dict.set_item("rule", &self.rule)?;
+        dict.set_item("message", &self.message)?;
+        Ok(dict)
+    }
+}
+
+#[pymethods]
+impl Finding {
+    /// JSON pointer of the offending node (`/spec/ports/0/port`)
+    #[getter]
+    fn path(&self) -> &str {
+        &self.pointer
+    }
+
+    /// 1-based line
+    #[getter]
+    fn line(&self) -> usize {
+        self.mark.line + 1
+    }
+
+    /// 1-based column
+    #[getter]
+    fn column(&self) -> usize {
+        self.mark.column + 1
+    }
+
+    /// `"error"` or `"warning"`
+    #[getter]
+    fn severity(&self) -> &'static str {
+        self.severity.name()
+    }
+
+    /// Id of the rule that produced the finding (`"type"`, `"required"`,
+    /// `"additional-properties"`, `"possible-typo"`, or the key path of a
+    /// custom validator)
+    #[getter]
+    fn rule(&self) -> &str {
+        &self.rule
+    }
+
+    #[getter]
+    fn message(&self) -> &str {
+        &self.message
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Finding({} {} at {} (line {}, column {}): {})",
+            self.severity.name(),
+            self.rule,
+            self.pointer,
+            self.mark.line + 1,
+            self.mark.column + 1,
+            self.message
+        )
+    }
+}
+
+/// Result of `validate`
+#[pyclass(module = "rustyyaml", frozen)]
+pub struct Report {
+    findings: Vec<Finding>,
+}
+
+impl Report {
+    fn with_severity(&self, severity: Severity) -> Vec<Finding> {
+        self.findings
+            .iter()
+            .filter(|finding| finding.severity == severity)
+            .cloned()
+            .collect()
+    }
+}
+
+#[pymethods]
+impl Report {
+    /// Every finding, in document order
+    #[getter]
+    fn findings(&self) -> Vec<Finding> {
+        self.findings.clone()
+    }
+
+    #[getter]
+    fn errors(&self) -> Vec<Finding> {
+        self.with_severity(Severity::Error)
+    }
+
+    #[getter]
+    fn warnings(&self) -> Vec<Finding> {
+        self.with_severity(Severity::Warning)
+    }
+
+    /// True if there are no errors (warnings are allowed)
+    #[getter]
+    fn passed(&self) -> bool {
+        self.findings
+            .iter()
+            .all(|finding| finding.severity != Severity::Error)
+    }
+
+    /// The report as JSON: `{"passed": ..., "findings": [{"path", "line",
+    /// "column", "severity", "rule", "message"}, ...]}`
+    fn to_json(&self, py: Python) -> PyResult<String> {
+        let findings = PyList::empty_bound(py);
+        for finding in &self.findings {
+            findings.append(finding.to_dict(py)?)?;
+        }
+        let report = PyDict::new_bound(py);
+        report.set_item("passed", self.passed())?;
+        report.set_item("findings", findings)?;
+        py.import_bound("json")?
+            .call_method1("dumps", (report,))?
+            .extract()
+    }
+
+    fn __len__(&self) -> usize {
+        self.findings.len()
+    }
+
+    fn __repr__(&self) -> String {
+        let errors = self.with_severity(Severity::Error).len();
+        format!(
+            "Report(passed={}, errors={}, warnings={})",
+            if errors == 0 { "True" } else { "False" },
+            errors,
+            self.findings.len() - errors
+        )
+    }
+}
+
+/// How strictly to treat keys the schema does not declare
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Strictness {
+    /// Reject undeclared keys in every object schema with `properties`
+    pub closed: bool,
+}
+
+/// Check every composed document against `schema` (no GIL needed)
+pub fn check_documents(
+    documents: &[Document],
+    schema: &Schema,
+    strictness: Strictness,
+) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for document in documents {
+        let mut pointer = String::new();
+        check(
+            &document.root,
+            schema,
+            strictness,
+            &mut pointer,
+            &mut findings,
+        );
+    }
+    findings
+}
+
+fn check(
+    node: &Node,
+    schema: &Schema,
+    strictness: Strictness,
+    pointer: &mut String,
+    findings: &mut Vec<Finding>,
+) {
+    // Aliases are checked where their anchor is defined
+    let Some(actual) = node_type(node) else {
+        return;
+    };
+    let allowed = schema.allows(actual) || (actual == Type::Integer && schema.allows(Type::Number));
+    if !allowed {
+        let expected: Vec<&str> = schema.types.iter().map(|ty| ty.name()).collect();
+        findings.push(Finding::error(
+            pointer,
+            node.start,
+            RULE_TYPE,
+            format!("expected {}, got {}", expected.join(" or "), actual.name()),
+        ));
+        return;
+    }
+
+    let len = pointer.len();
+    match &node.kind {
+        NodeKind::Mapping { pairs, .. } => {
+            let keys: Vec<Option<&str>> = pairs.iter().map(|(key, _)| scalar_text(key)).collect();
+            for required in &schema.required {
+                if !keys.contains(&Some(required.as_str())) {
+                    findings.push(Finding::error(
+                        pointer,
+                        node.start,
+                        RULE_REQUIRED,
+                        format!("missing required key '{}'", required),
+                    ));
+                }
+            }
+
+            let closed = schema.closed || (strictness.closed && !schema.properties.is_empty());
+            for ((key, value), name) in pairs.iter().zip(keys) {
+                let Some(name) = name else {
+                    continue;
+                };
+                pointer.push('/');
+                pointer.push_str(&sourcemap::escape(name));
+                let declared = schema.declares(name);
+                if !declared {
+                    let finding = unexpected_key(name, schema, closed, pointer, key.start);
+                    findings.extend(finding);
+                }
+                if declared || !closed {
+                    if let Some(property) = schema.property(name) {
+                        check(value, property, strictness, pointer, findings);
+                    }
+                }
+                pointer.truncate(len);
+            }
+        }
+        NodeKind::Sequence { items, .. } => {
+            if let Some(item_schema) = &schema.items {
+                for (index, item) in items.iter().enumerate() {
+                    pointer.push('/');
+                    pointer.push_str(&index.to_string());
+                    check(item, item_schema, strictness, pointer, findings);
+                    pointer.truncate(len);
+                }
+            }
+        }
+        NodeKind::Scalar { .. } | NodeKind::Alias { .. } => {}
+    }
+}
+
+/// JSON type of a node, or `None` if it cannot be known (aliases, custom tags)
+fn node_type(node: &Node) -> Option<Type> {
+    let value = match &node.kind {
+        NodeKind::Mapping { .. } => return Some(Type::Object),
+        NodeKind::Sequence { .. } => return Some(Type::Array),
+        NodeKind::Alias { .. } => return None,
+        NodeKind::Scalar { value, .. } => value,
+    };
+    let scalar = match node.tag.as_deref() {
+        None if node.is_plain_untagged() => resolve::resolve_core(value),
+        None => Scalar::Str,
+        Some(tag) => resolve::resolve_tagged(value, tag)?,
+    };
+    match scalar {
+        Scalar::Null => Some(Type::Null),
+        Scalar::Bool(_) => Some(Type::Boolean),
+        Scalar::Int(_) => Some(Type::Integer),
+        Scalar::Float(_) => Some(Type::Number),
+        Scalar::Str => Some(Type::String),
+        _ => None,
+    }
+}
+
+fn scalar_text(node: &Node) -> Option<&str> {
+    match &node.kind {
+        NodeKind::Scalar { value, .. } => Some(value),
+        _ => None,
+    }
+}
+
+/// Finding for undeclared key `name`, if it deserves one
+///
+/// In a closed object every undeclared key is an error; in an open one only
+/// keys that look like a typo of a declared key are reported, as warnings.
+fn unexpected_key(
+    name: &str,
+    schema: &Schema,
+    closed: bool,
+    pointer: &str,
+    mark: Mark,
+) -> Option<Finding> {
+    let known = schema.properties.iter().map(|(known, _)| known.as_str());
+    let suggestion = suggest(name, known);
+    let message = match suggestion {
+        Some(known) => format!("unexpected key '{}' (did you mean '{}'?)", name, known),
+        None => format!("unexpected key '{}'", name),
+    };
+    if closed {
+        Some(Finding::error(
+            pointer,
+            mark,
+            RULE_ADDITIONAL_PROPERTIES,
+            message,
+        ))
+    } else {
+        suggestion.map(|_| Finding {
+            severity: Severity::Warning,
+            ..Finding::error(pointer, mark, RULE_POSSIBLE_TYPO, message)
+        })
+    }
+}
+
+/// The known key closest to `name`, if it is a plausible typo
+pub fn suggest<'k>(name: &str, known: impl Iterator<Item = &'k str>) -> Option<&'k str> {
+    let limit = (name.chars().count() / 3).clamp(1, 3);
+    known
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Levenshtein distance, ignoring ASCII case
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().map(|c| c.to_ascii_lowercase()).collect();
+    let b: Vec<char> = b.chars().map(|c| c.to_ascii_lowercase()).collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// A custom validator: a key path pattern and the callable it runs
+struct Validator {
+    pattern: String,
+    segments: Vec<Segment>,
+    callable: PyObject,
+}
+
+/// Run each validator on the values its pattern matches
+///
+/// A validator accepts a value by returning `None` or `True`, and vetoes it
+/// by returning `False` or a message. Exceptions propagate.
+fn run_validators(
+    py: Python,
+    documents: &[Document],
+    validators: &[Validator],
+    findings: &mut Vec<Finding>,
+) -> PyResult<()> {
+    let options = LoadOptions::default();
+    for document in documents {
+        for validator in validators {
+            for (pointer, node) in search::find_nodes(&document.root, &validator.segments) {
+                let value = construct_node(py, &document.root, node, &options)?;
+                let verdict = validator.callable.bind(py).call1((value,))?;
+                let message = if verdict.is_none() {
+                    continue;
+                } else if let Ok(message) = verdict.extract::<String>() {
+                    message
+                } else if verdict.is_truthy()? {
+                    continue;
+                } else {
+                    "rejected by validator".to_string()
+                };
+                findings.push(Finding::error(
+                    &pointer,
+                    node.start,
+                    &validator.pattern,
+                    message,
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn parse_validators(validators: Option<&Bound<'_, PyDict>>) -> PyResult<Vec<Validator>> {
+    let Some(validators) = validators else {
+        return Ok(Vec::new());
+    };
+    let mut parsed = Vec::new();
+    for (pattern, callable) in validators.iter() {
+        let pattern: String = pattern.extract()?;
+        let segments = search::parse_key_path(&pattern)
+            .ok_or_else(|| PyValueError::new_err(format!("invalid key path '{}'", pattern)))?;
+        if !callable.is_callable() {
+            return Err(PyValueError::new_err(format!(
+                "validator for '{}' is not callable",
+                pattern
+            )));
+        }
+        parsed.push(Validator {
+            pattern,
+            segments,
+            callable: callable.unbind(),
+        });
+    }
+    Ok(parsed)
+}
+
+/// Validate YAML against a JSON Schema subset
+///
+/// Supports `type`, `properties`, `required`, `additionalProperties` and
+/// `items`. Every document of the stream is checked. Schema checks run
+/// without the GIL; custom validators then run only on the values their
+/// patterns match.
+///
+/// # Arguments
+/// * `yaml_str` - YAML content as string
+/// * `schema` - The schema, as a dict
+/// * `additional_properties` - `False` rejects undeclared keys in every
+///   object that declares `properties`, as if it said
+///   `additionalProperties: false`
+/// * `validators` - Dict of key path pattern (`spec.containers[*].image`,
+///   as in `grep`) to a callable run on every matching value: it returns
+///   `None`/`True` to accept, `False` or a message to veto
+///
+/// # Returns
+/// A `Report`: `.errors`, `.warnings` and `.findings` hold `Finding`s with
+/// `path` (JSON pointer of the offending node, `/spec/ports/0/port`),
+/// 1-based `line`/`column`, `severity`, `rule` and `message`; `.passed` is
+/// true when there are no errors. Validator findings use the pattern as
+/// their rule.
+///
+/// # Example
+/// ```python
+/// def registry(image):
+///     if not image.startswith("registry.example.com/"):
+///         return f"image {image} is not from our registry"
+///
+/// report = rustyyaml.validate(
+///     manifest, schema, validators={"spec.containers[*].image": registry}
+/// )
+/// ```
+#[pyfunction]
+#[pyo3(signature = (yaml_str, schema, additional_properties=true, validators=None))]
+pub fn validate(
+    py: Python,
+    yaml_str: &str,
+    schema: &Bound<'_, PyAny>,
+    additional_properties: bool,
+    validators: Option<&Bound<'_, PyDict>>,
+) -> PyResult<Report> {
+    let schema = schema::from_python(schema)?;
+    let validators = parse_validators(validators)?;
+    let strictness = Strictness {
+        closed: !additional_properties,
+    };
+
+    let (documents, mut findings) = py.allow_threads(|| {
+        let documents = compose::compose_all(yaml_str)?;
+        let findings = check_documents(&documents, &schema, strictness);
+        Ok::<_, YAMLError>((documents, findings))
+    })?;
+    run_validators(py, &documents, &validators, &mut findings)?;
+    // Keep schema and validator findings in source order
+    findings.sort_by_key(|finding| finding.mark.index);
+    Ok(Report { findings })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: &str = "
+type: object
+required: [kind]
+properties:
+  kind: {type: string}
+  spec:
+    type: object
+    properties:
+      replicas: {type: integer}
+      ratio: {type: number}
+      ports: {type: array, items: {type: integer}}
+";
+
+    fn findings(yaml: &str, closed: bool) -> Vec<(String, usize, usize, String)> {
+        report(yaml, closed)
+            .into_iter()
+            .map(|f| (f.pointer, f.mark.line, f.mark.column, f.message))
+            .collect()
+    }
+
+    fn report(yaml: &str, closed: bool) -> Vec<Finding> {
+        let schema = Schema::from_value(&serde_yaml::from_str(SCHEMA).unwrap()).unwrap();
+        let documents = compose::compose_all(yaml).unwrap();
+        check_documents(&documents, &schema, Strictness { closed })
+    }
+
+    #[test]
+    fn test_valid_document() {
+        let yaml = "kind: Deployment\nspec: {replicas: 3, ratio: 1, ports: [80]}\nextra: x\n";
+        assert!(findings(yaml, false).is_empty());
+    }
+
+    #[test]
+    fn test_type_and_required() {
+        let found = findings("spec:\n  replicas: '3'\n  ports: [80, http]\n", false);
+        let messages: Vec<(&str, &str)> = found
+            .iter()
+            .map(|(pointer, _, _, message)| (pointer.as_str(), message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                ("", "missing required key 'kind'"),
+                ("/spec/replicas", "expected integer, got string"),
+                ("/spec/ports/1", "expected integer, got string"),
+            ]
+        );
+        assert_eq!((found[1].1, found[1].2), (1, 12));
+    }
+
+    #[test]
+    fn test_unexpected_keys() {
+        let yaml = "kind: Deployment\nspec:\n  replica: 3\n  zzz: 1\n  ratio: x\n";
+        let found = report(yaml, true);
+        let summary: Vec<(Severity, &str, &str)> = found
+            .iter()
+            .map(|f| (f.severity, f.rule.as_str(), f.pointer.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (Severity::Error, RULE_ADDITIONAL_PROPERTIES, "/spec/replica"),
+                (Severity::Error, RULE_ADDITIONAL_PROPERTIES, "/spec/zzz"),
+                (Severity::Error, RULE_TYPE, "/spec/ratio"),
+            ]
+        );
+        assert_eq!((found[0].mark.line, found[0].mark.column), (2, 2));
+        assert_eq!(
+            found[0].message,
+            "unexpected key 'replica' (did you mean 'replicas'?)"
+        );
+        assert_eq!(found[1].message, "unexpected key 'zzz'");
+    }
+
+    #[test]
+    fn test_possible_typo_warning() {
+        let yaml = "kind: Deployment\nspec:\n  replica: 3\n  zzz: 1\n";
+        let found = report(yaml, false);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].severity, Severity::Warning);
+        assert_eq!(found[0].rule, RULE_POSSIBLE_TYPO);
+        assert_eq!(found[0].pointer, "/spec/replica");
+    }
+
+    #[test]
+    fn test_suggest() {
+        let known = ["replicas", "selector", "template"];
+        assert_eq!(suggest("replica", known.into_iter()), Some("replicas"));
+        assert_eq!(suggest("Selectr", known.into_iter()), Some("selector"));
+        assert_eq!(suggest("image", known.into_iter()), None);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+}
@@ -0,0 +1,278 @@
+//! Find template placeholders in scalar values
+//!
+//! Two syntaxes are supported:
+//! - `dollar`: shell/envsubst style `${VAR}`, `${VAR:-default}` (`$${VAR}`
+//!   is an escaped literal)
+//! - `gotpl`: Go templates as used by Helm; every field reference such as
+//!   `.Values.image.tag` inside `{{ ... }}` is reported
+//!
+//! Placeholders are found in the source text of each scalar, so their
+//! positions point at the placeholder itself. Go templates with control
+//! blocks (`{{- if ... }}`) are often not valid YAML; for those the whole
+//! text is scanned instead.
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use regex::Regex;
+use std::sync::OnceLock;
+
+use crate::compose::{self, Node, NodeKind};
+use crate::error::YAMLError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Syntax {
+    Dollar,
+    GoTemplate,
+}
+
+impl Syntax {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "dollar" => Some(Syntax::Dollar),
+            "gotpl" => Some(Syntax::GoTemplate),
+            _ => None,
+        }
+    }
+}
+
+/// A placeholder and where it is (1-based)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Variable {
+    pub name: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+fn dollar_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"\$+\{([A-Za-z_][A-Za-z0-9_]*)(?:[:]?[-=?+][^}]*)?\}")
+            .expect("valid placeholder regex")
+    })
+}
+
+fn action_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"(?s)\{\{(.*?)\}\}").expect("valid action regex"))
+}
+
+fn field_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"(?:^|[^\w.$)\]])\$?(\.[A-Za-z_]\w*(?:\.[A-Za-z_]\w*)*)")
+            .expect("valid field regex")
+    })
+}
+
+/// Placeholders in `text` as (name, byte offset)
+pub fn find_in_text(text: &str, syntax: Syntax) -> Vec<(String, usize)> {
+    match syntax {
+        Syntax::Dollar => dollar_regex()
+            .captures_iter(text)
+            .filter_map(|caps| {
+                let whole = caps.get(0)?;
+                let dollars = whole.as_str().bytes().take_while(|&b| b == b'$').count();
+                // `$${VAR}` is an escaped literal `${VAR}`
+                (dollars % 2 == 1).then(|| {
+                    let start = whole.start() + dollars - 1;
+                    (caps[1].to_string(), start)
+                })
+            })
+            .collect(),
+        Syntax::GoTemplate => {
+            let mut found = Vec::new();
+            for action in action_regex().captures_iter(text) {
+                let body = action.get(1).expect("group 1 always participates");
+                let code = blank_strings(body.as_str());
+                for field in field_regex().captures_iter(&code) {
+                    let name = field.get(1).expect("group 1 always participates");
+                    found.push((name.as_str().to_string(), body.start() + name.start()));
+                }
+            }
+            found
+        }
+    }
+}
+
+/// Replace string literals with spaces so `"a.b"` is not a field reference
+fn blank_strings(code: &str) -> String {
+    let mut out = String::with_capacity(code.len());
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for c in code.chars() {
+        match quote {
+            Some(q) => {
+                if c == q && !escaped {
+                    quote = None;
+                }
+                escaped = c == '\\' && !escaped && q == '"';
+                out.extend(std::iter::repeat_n(' ', c.len_utf8()));
+            }
+            None => {
+                if matches!(c, '"' | '`' | '\'') {
+                    quote = Some(c);
+                }
+                out.push(c);
+            }
+        }
+    }
+    out
+}
+
+/// Byte offsets of the start of every line
+fn line_starts(text: &str) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+        .collect()
+}
+
+fn locate(text: &str, starts: &[usize], offset: usize) -> (usize, usize) {
+    let line = starts.partition_point(|&start| start <= offset) - 1;
+    let column = text[starts[line]..offset].chars().count();
+    (line + 1, column + 1)
+}
+
+/// Scalar values (not keys) in document order
+fn collect_values<'n>(node: &'n Node, values: &mut Vec<&'n Node>) {
+    match &node.kind {
+        NodeKind::Scalar { .. } => values.push(node),
+        NodeKind::Sequence { items, .. } => {
+            for item in items {
+                collect_values(item, values);
+            }
+        }
+        NodeKind::Mapping { pairs, .. } => {
+            for (_, value) in pairs {
+                collect_values(value, values);
+            }
+        }
+        NodeKind::Alias { .. } => {}
+    }
+}
+
+/// Find every placeholder in the scalar values of `yaml_str`
+///
+/// # Errors
+/// * Parse errors, except for Go templates (which fall back to scanning
+///   the whole text)
+pub fn list_variables(yaml_str: &str, syntax: Syntax) -> Result<Vec<Variable>, YAMLError> {
+    let mut found = match compose::compose_all(yaml_str) {
+        Ok(documents) => {
+            let mut values = Vec::new();
+            for document in &documents {
+                collect_values(&document.root, &mut values);
+            }
+            values
+                .iter()
+                .flat_map(|node| {
+                    let source = &yaml_str[node.start.index..node.end.index];
+                    find_in_text(source, syntax)
+                        .into_iter()
+                        .map(|(name, offset)| (name, node.start.index + offset))
+                })
+                .collect()
+        }
+        Err(_) if syntax == Syntax::GoTemplate => find_in_text(yaml_str, syntax),
+        Err(err) => return Err(err),
+    };
+    found.sort_by_key(|(_, offset)| *offset);
+
+    let starts = line_starts(yaml_str);
+    Ok(found
+        .into_iter()
+        .map(|(name, offset)| {
+            let (line, column) = locate(yaml_str, &starts, offset);
+            Variable { name, line, column }
+        })
+        .collect())
+}
+
+/// List template placeholders used in a document
+///
+/// # Arguments
+/// * `yaml_str` - YAML content as string
+/// * `syntax` - `"dollar"` for `${VAR}` or `"gotpl"` for `{{ .Values.x }}`
+///
+/// # Returns
+/// List of `(name, line, column)` tuples (1-based) in source order. Names
+/// are `VAR` for `${VAR}` and the field path (`.Values.x`) for Go templates.
+///
+/// # Example
+/// ```python
+/// rustyyaml.list_variables("image: ${REGISTRY}/app:${TAG:-latest}")
+/// # [('REGISTRY', 1, 8), ('TAG', 1, 24)]
+/// ```
+#[pyfunction(name = "list_variables")]
+#[pyo3(signature = (yaml_str, syntax="dollar"))]
+pub fn py_list_variables(yaml_str: &str, syntax: &str) -> PyResult<Vec<(String, usize, usize)>> {
+    let syntax = Syntax::from_name(syntax).ok_or_else(|| {
+        PyValueError::new_err(format!(
+            "syntax must be 'dollar' or 'gotpl', not '{}'",
+            syntax
+        ))
+    })?;
+    Ok(list_variables(yaml_str, syntax)?
+        .into_iter()
+        .map(|v| (v.name, v.line, v.column))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(text: &str, syntax: Syntax) -> Vec<String> {
+        find_in_text(text, syntax)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    #[test]
+    fn test_dollar_placeholders() {
+        assert_eq!(
+            names("${A}/${B:-x}/${C-y}/${D:?err}", Syntax::Dollar),
+            ["A", "B", "C", "D"]
+        );
+        assert!(names("$${ESCAPED} $A ${1BAD}", Syntax::Dollar).is_empty());
+        assert_eq!(names("$$${REAL}", Syntax::Dollar), ["REAL"]);
+        assert_eq!(find_in_text("ab${X}", Syntax::Dollar)[0].1, 2);
+        assert_eq!(find_in_text("$$${X}", Syntax::Dollar)[0].1, 2);
+    }
+
+    #[test]
+    fn test_gotpl_fields() {
+        assert_eq!(
+            names(
+                r#"{{ .Values.image.tag | default "a.b" }}:{{ $.Release.Name }}"#,
+                Syntax::GoTemplate
+            ),
+            [".Values.image.tag", ".Release.Name"]
+        );
+        assert!(names("{{ include \"x\" . }} .NotInAction", Syntax::GoTemplate).is_empty());
+    }
+
+    #[test]
+    fn test_list_variables_positions() {
+        let yaml = "image: ${REGISTRY}/app:${TAG:-latest}\n${KEY}: 1\nlist:\n  - '${QUOTED}'\n";
+        let found = list_variables(yaml, Syntax::Dollar).unwrap();
+        let found: Vec<(&str, usize, usize)> = found
+            .iter()
+            .map(|v| (v.name.as_str(), v.line, v.column))
+            .collect();
+        assert_eq!(
+            found,
+            [("REGISTRY", 1, 8), ("TAG", 1, 24), ("QUOTED", 4, 6)]
+        );
+    }
+
+    #[test]
+    fn test_gotpl_fallback_for_invalid_yaml() {
+        let yaml = "{{- if .Values.enabled }}\nreplicas: {{ .Values.replicas }}\n{{- end }}\n";
+        let found = list_variables(yaml, Syntax::GoTemplate).unwrap();
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[1].name, ".Values.replicas");
+        assert_eq!((found[1].line, found[1].column), (2, 14));
+        assert!(list_variables("a: [", Syntax::Dollar).is_err());
+    }
+}
@@ -0,0 +1,213 @@
+//! Walk a document, listing every value with its key path
+//!
+//! The traversal runs in Rust over the Python objects themselves, so loaded
+//! data is neither copied nor converted: the values returned are the ones
+//! in the document, and types YAML has no name for (datetimes, custom
+//! classes) are simply leaves. Paths use the `grep` syntax
+//! (`spec.containers[0].image`).
+
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList, PyString, PyTuple};
+
+use crate::namespace::Namespace;
+use crate::options::LoadOptions;
+use crate::parser;
+
+/// How far to go, and what to report
+#[derive(Clone, Copy, Debug)]
+struct Limits {
+    max_depth: Option<usize>,
+    leaves_only: bool,
+}
+
+struct Walker<'py> {
+    limits: Limits,
+    found: Vec<(String, Bound<'py, PyAny>)>,
+}
+
+/// Children of a collection, each with the step that leads to it
+type Children<'py> = Vec<(Child, Bound<'py, PyAny>)>;
+
+/// Children of a mapping or sequence (`None` for anything else)
+fn children<'py>(value: &Bound<'py, PyAny>) -> PyResult<Option<Children<'py>>> {
+    let py = value.py();
+    if let Ok(namespace) = value.downcast::<Namespace>() {
+        return children(namespace.get().dict(py).as_any());
+    }
+    if let Ok(dict) = value.downcast::<PyDict>() {
+        let pairs = dict
+            .iter()
+            .map(|(key, child)| {
+                let key = match key.downcast::<PyString>() {
+                    Ok(key) => key.to_cow()?.into_owned(),
+                    Err(_) => key.str()?.to_cow()?.into_owned(),
+                };
+                Ok((Child::Key(key), child))
+            })
+            .collect::<PyResult<_>>()?;
+        return Ok(Some(pairs));
+    }
+    let items = if let Ok(list) = value.downcast::<PyList>() {
+        list.iter().collect::<Vec<_>>()
+    } else if let Ok(tuple) = value.downcast::<PyTuple>() {
+        tuple.iter().collect()
+    } else {
+        return Ok(None);
+    };
+    Ok(Some(
+        items
+            .into_iter()
+            .enumerate()
+            .map(|(index, item)| (Child::Index(index), item))
+            .collect(),
+    ))
+}
+
+/// A step from a collection to one of its children
+enum Child {
+    Key(String),
+    Index(usize),
+}
+
+impl<'py> Walker<'py> {
+    fn walk(&mut self, value: Bound<'py, PyAny>, path: &mut String, depth: usize) -> PyResult<()> {
+        let children = if self.limits.max_depth.is_some_and(|max| depth >= max) {
+            None
+        } else {
+            children(&value)?
+        };
+        let Some(children) = children else {
+            // A leaf, or as deep as we may go
+            if depth > 0 {
+                self.found.push((path.clone(), value));
+            }
+            return Ok(());
+        };
+        if depth > 0 && !self.limits.leaves_only {
+            self.found.push((path.clone(), value));
+        }
+        let len = path.len();
+        for (step, child) in children {
+            match step {
+                Child::Key(key) => {
+                    if !path.is_empty() {
+                        path.push('.');
+                    }
+                    path.push_str(&key);
+                }
+                Child::Index(index) => {
+                    path.push('[');
+                    path.push_str(&index.to_string());
+                    path.push(']');
+                }
+            }
+            self.walk(child, path, depth + 1)?;
+            path.truncate(len);
+        }
+        Ok(())
+    }
+}
+
+/// Every value in a document with its key path, in document order
+///
+/// # Arguments
+/// * `doc_or_yaml` - YAML text (loaded like `safe_load`) or loaded data
+/// * `max_depth` - Don't descend below this many levels (`1`: top-level
+///   keys only); collections at the limit are reported whole
+/// * `leaves_only` - Skip mappings and sequences, reporting only what is
+///   inside them
+///
+/// # Returns
+/// List of `(path, value)` tuples; paths use the `grep` syntax
+/// (`spec.containers[0].image`)
+///
+/// # Example
+/// ```python
+/// for path, value in rustyyaml.walk(config, leaves_only=True):
+///     if isinstance(value, str) and "TODO" in value:
+///         print(path)
+/// ```
+#[pyfunction]
+#[pyo3(signature = (doc_or_yaml, max_depth=None, leaves_only=false))]
+pub fn walk(
+    py: Python,
+    doc_or_yaml: &Bound<'_, PyAny>,
+    max_depth: Option<usize>,
+    leaves_only: bool,
+) -> PyResult<PyObject> {
+    let doc = if let Ok(yaml_str) = doc_or_yaml.downcast::<PyString>() {
+        parser::parse_safe(py, &yaml_str.to_cow()?, &LoadOptions::default())?.into_bound(py)
+    } else {
+        doc_or_yaml.clone()
+    };
+    let mut walker = Walker {
+        limits: Limits {
+            max_depth,
+            leaves_only,
+        },
+        found: Vec::new(),
+    };
+    walker.walk(doc, &mut String::new(), 0)?;
+    Ok(walker.found.into_py(py))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(source: &str, max_depth: Option<usize>, leaves_only: bool) -> Vec<String> {
+        Python::with_gil(|py| {
+            let source = PyString::new_bound(py, source);
+            walk(py, &source, max_depth, leaves_only)
+                .unwrap()
+                .extract::<Vec<(String, PyObject)>>(py)
+                .unwrap()
+                .into_iter()
+                .map(|(path, _)| path)
+                .collect()
+        })
+    }
+
+    const SOURCE: &str = "name: web\nspec:\n  ports: [80, 443]\n  env: {}\n";
+
+    #[test]
+    fn test_walk() {
+        assert_eq!(
+            paths(SOURCE, None, false),
+            [
+                "name",
+                "spec",
+                "spec.ports",
+                "spec.ports[0]",
+                "spec.ports[1]",
+                "spec.env"
+            ]
+        );
+        assert_eq!(
+            paths(SOURCE, None, true),
+            ["name", "spec.ports[0]", "spec.ports[1]"]
+        );
+        assert_eq!(paths(SOURCE, Some(1), false), ["name", "spec"]);
+        assert_eq!(
+            paths(SOURCE, Some(2), true),
+            ["name", "spec.ports", "spec.env"]
+        );
+        assert_eq!(paths("[[1], 2]", None, true), ["[0][0]", "[1]"]);
+        assert!(paths("plain", None, false).is_empty());
+    }
+
+    #[test]
+    fn test_walk_keeps_objects() {
+        Python::with_gil(|py| {
+            let doc = py
+                .eval_bound("{'a': [object()], 1: (2,)}", None, None)
+                .unwrap();
+            let found: Vec<(String, PyObject)> =
+                walk(py, &doc, None, true).unwrap().extract(py).unwrap();
+            let item = doc.get_item("a").unwrap().get_item(0).unwrap();
+            assert_eq!(found[0].0, "a[0]");
+            assert!(found[0].1.bind(py).is(&item));
+            assert_eq!(found[1].0, "1[0]");
+        });
+    }
+}
@@ -0,0 +1,509 @@
+//! Convert between YAML and XML
+//!
+//! The mapping follows the common `xmltodict` conventions:
+//! - attributes are keys with a prefix (`@id`)
+//! - text next to attributes or child elements is under a text key (`#text`)
+//! - an element with only text is that text; an empty one is null
+//! - repeated child elements become a list
+//!
+//! Going to XML, a list is written as the element repeated once per item,
+//! or with `list_item`, as one element wrapping an `<item>` per entry.
+//! Going to YAML, `force_list` names elements that are always lists (so a
+//! single `<host>` doesn't come back as a scalar), and text resolves like
+//! plain YAML scalars unless `typed` is off.
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde_yaml::{Mapping, Value};
+use std::collections::HashSet;
+
+use crate::error::YAMLError;
+use crate::ini::typed;
+use crate::options::LoadOptions;
+use crate::parser::{self, Parsed};
+use crate::search::key_text;
+
+/// How keys map to attributes and text
+#[derive(Clone, Debug)]
+pub struct XmlStyle {
+    pub attr_prefix: String,
+    pub text_key: String,
+}
+
+impl Default for XmlStyle {
+    fn default() -> Self {
+        XmlStyle {
+            attr_prefix: "@".to_string(),
+            text_key: "#text".to_string(),
+        }
+    }
+}
+
+fn escape(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+}
+
+fn check_name(name: &str) -> Result<(), String> {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_' || c == ':')
+        && chars.all(|c| c.is_alphanumeric() || matches!(c, '_' | ':' | '-' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("'{}' is not a valid XML element name", name))
+    }
+}
+
+fn scalar_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => Some(String::new()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::String(s) => Some(s.clone()),
+        Value::Tagged(tagged) => scalar_text(&tagged.value),
+        Value::Mapping(_) | Value::Sequence(_) => None,
+    }
+}
+
+/// Writes documents as indented XML
+struct Writer<'s> {
+    style: &'s XmlStyle,
+    list_item: Option<&'s str>,
+    out: String,
+}
+
+impl Writer<'_> {
+    fn indent(&mut self, depth: usize) {
+        self.out.extend(std::iter::repeat_n("  ", depth));
+    }
+
+    fn element(&mut self, name: &str, value: &Value, depth: usize) -> Result<(), String> {
+        check_name(name)?;
+        let value = match value {
+            Value::Tagged(tagged) => &tagged.value,
+            value => value,
+        };
+        match value {
+            Value::Sequence(items) => match self.list_item {
+                None => {
+                    for item in items {
+                        if item.is_sequence() {
+                            return Err(format!(
+                                "{}: nested lists can only be written with list_item",
+                                name
+                            ));
+                        }
+                        self.element(name, item, depth)?;
+                    }
+                    Ok(())
+                }
+                Some(item_name) => {
+                    self.indent(depth);
+                    self.out.push_str(&format!("<{}>\n", name));
+                    for item in items {
+                        self.element(item_name, item, depth + 1)?;
+                    }
+                    self.indent(depth);
+                    self.out.push_str(&format!("</{}>\n", name));
+                    Ok(())
+                }
+            },
+            Value::Mapping(map) => self.mapping(name, map, depth),
+            scalar => {
+                self.indent(depth);
+                let text = scalar_text(scalar).unwrap_or_default();
+                if text.is_empty() {
+                    self.out.push_str(&format!("<{}/>\n", name));
+                } else {
+                    self.out.push_str(&format!("<{}>", name));
+                    escape(&text, &mut self.out);
+                    self.out.push_str(&format!("</{}>\n", name));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn mapping(&mut self, name: &str, map: &Mapping, depth: usize) -> Result<(), String> {
+        let mut attributes = String::new();
+        let mut text = None;
+        let mut children = Vec::new();
+        for (key, value) in map {
+            let key = key_text(key).ok_or_else(|| format!("{}: keys must be scalars", name))?;
+            if key == self.style.text_key {
+                text = Some(
+                    scalar_text(value).ok_or_else(|| format!("{}: text must be a scalar", name))?,
+                );
+            } else if let Some(attribute) = key.strip_prefix(&self.style.attr_prefix) {
+                check_name(attribute)?;
+                let value = scalar_text(value).ok_or_else(|| {
+                    format!("{}: attribute '{}' must be a scalar", name, attribute)
+                })?;
+                attributes.push_str(&format!(" {}=\"", attribute));
+                escape(&value, &mut attributes);
+                attributes.push('"');
+            } else {
+                children.push((key, value));
+            }
+        }
+
+        self.indent(depth);
+        match (text, children.is_empty()) {
+            (None, true) => self.out.push_str(&format!("<{}{}/>\n", name, attributes)),
+            (Some(text), true) => {
+                self.out.push_str(&format!("<{}{}>", name, attributes));
+                escape(&text, &mut self.out);
+                self.out.push_str(&format!("</{}>\n", name));
+            }
+            (text, false) => {
+                self.out.push_str(&format!("<{}{}>\n", name, attributes));
+                if let Some(text) = text {
+                    self.indent(depth + 1);
+                    escape(&text, &mut self.out);
+                    self.out.push('\n');
+                }
+                for (key, value) in children {
+                    self.element(&key, value, depth + 1)?;
+                }
+                self.indent(depth);
+                self.out.push_str(&format!("</{}>\n", name));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Render a document as XML
+///
+/// A mapping with a single element key is the root element; anything else
+/// is wrapped in an element named `root`.
+///
+/// # Errors
+/// A message for keys that are not valid element names, non-scalar
+/// attributes, and nested lists without `list_item`.
+pub fn to_xml(
+    document: &Value,
+    root: &str,
+    style: &XmlStyle,
+    list_item: Option<&str>,
+) -> Result<String, String> {
+    let mut writer = Writer {
+        style,
+        list_item,
+        out: String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"),
+    };
+    let single = document
+        .as_mapping()
+        .filter(|map| map.len() == 1)
+        .and_then(|map| {
+            let (key, value) = map.iter().next()?;
+            let key = key_text(key)?;
+            let special = key.starts_with(&style.attr_prefix) || key == style.text_key;
+            (!special && !value.is_sequence()).then_some((key, value))
+        });
+    match single {
+        Some((key, value)) => writer.element(&key, value, 0)?,
+        None => writer.element(root, document, 0)?,
+    }
+    Ok(writer.out)
+}
+
+/// An element being read
+struct Element {
+    name: String,
+    map: Mapping,
+    /// Children already stored as lists
+    lists: HashSet<String>,
+    text: String,
+}
+
+impl Element {
+    fn open(
+        start: &BytesStart,
+        style: &XmlStyle,
+        convert: &dyn Fn(&str) -> Value,
+    ) -> Result<Self, String> {
+        let name = String::from_utf8_lossy(start.name().as_ref()).into_owned();
+        let mut map = Mapping::new();
+        for attribute in start.attributes() {
+            let attribute = attribute.map_err(|err| err.to_string())?;
+            let key = String::from_utf8_lossy(attribute.key.as_ref());
+            let value = attribute.unescape_value().map_err(|err| err.to_string())?;
+            map.insert(
+                Value::String(format!("{}{}", style.attr_prefix, key)),
+                convert(&value),
+            );
+        }
+        Ok(Element {
+            name,
+            map,
+            lists: HashSet::new(),
+            text: String::new(),
+        })
+    }
+
+    fn add_child(&mut self, name: String, value: Value, force_list: bool) {
+        let key = Value::String(name.clone());
+        if self.lists.contains(&name) {
+            if let Some(Value::Sequence(items)) = self.map.get_mut(&key) {
+                items.push(value);
+            }
+        } else if let Some(existing) = self.map.remove(&key) {
+            self.map.insert(key, Value::Sequence(vec![existing, value]));
+            self.lists.insert(name);
+        } else if force_list {
+            self.map.insert(key, Value::Sequence(vec![value]));
+            self.lists.insert(name);
+        } else {
+            self.map.insert(key, value);
+        }
+    }
+
+    fn close(self, style: &XmlStyle, convert: &dyn Fn(&str) -> Value) -> Value {
+        let text = self.text.trim();
+        if self.map.is_empty() {
+            return if text.is_empty() {
+                Value::Null
+            } else {
+                convert(text)
+            };
+        }
+        let mut map = self.map;
+        if !text.is_empty() {
+            map.insert(Value::String(style.text_key.clone()), convert(text));
+        }
+        Value::Mapping(map)
+    }
+}
+
+/// Parse XML into a document: `{root_name: ...}`
+///
+/// # Errors
+/// * Parse errors with the 1-based line
+pub fn from_xml(
+    text: &str,
+    style: &XmlStyle,
+    force_list: &[String],
+    resolve: bool,
+) -> Result<Value, YAMLError> {
+    let convert = |text: &str| {
+        if resolve {
+            typed(text)
+        } else {
+            Value::String(text.to_string())
+        }
+    };
+    let mut reader = Reader::from_str(text);
+    let mut stack: Vec<Element> = Vec::new();
+    let mut root: Option<(String, Value)> = None;
+    let error = |reader: &Reader<&[u8]>, message: String| {
+        let position = (reader.buffer_position() as usize).min(text.len());
+        let line = text[..position].matches('\n').count() + 1;
+        YAMLError::parse(line, 1, message)
+    };
+
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|err| error(&reader, err.to_string()))?;
+        let (start, empty) = match &event {
+            Event::Start(start) => (Some(start), false),
+            Event::Empty(start) => (Some(start), true),
+            _ => (None, false),
+        };
+        if let Some(start) = start {
+            if root.is_some() {
+                return Err(error(&reader, "content after the root element".to_string()));
+            }
+            let element =
+                Element::open(start, style, &convert).map_err(|message| error(&reader, message))?;
+            stack.push(element);
+            if !empty {
+                continue;
+            }
+        }
+        match event {
+            Event::Start(_) => {}
+            Event::Text(content) => {
+                let content = content
+                    .unescape()
+                    .map_err(|err| error(&reader, err.to_string()))?;
+                match stack.last_mut() {
+                    Some(element) => element.text.push_str(&content),
+                    None if content.trim().is_empty() => {}
+                    None => {
+                        return Err(error(&reader, "text outside the root element".to_string()))
+                    }
+                }
+            }
+            Event::CData(content) => {
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&String::from_utf8_lossy(&content));
+                }
+            }
+            Event::End(_) | Event::Empty(_) => {
+                let element = stack.pop().expect("the reader checks that tags match");
+                let name = element.name.clone();
+                let value = element.close(style, &convert);
+                match stack.last_mut() {
+                    Some(parent) => {
+                        parent.add_child(name.clone(), value, force_list.contains(&name))
+                    }
+                    None => root = Some((name, value)),
+                }
+            }
+            Event::Eof => break,
+            Event::Comment(_) | Event::Decl(_) | Event::PI(_) | Event::DocType(_) => {}
+        }
+    }
+
+    let (name, value) =
+        root.ok_or_else(|| YAMLError::parse(0, 0, "no root element".to_string()))?;
+    let mut document = Mapping::new();
+    document.insert(Value::String(name), value);
+    Ok(Value::Mapping(document))
+}
+
+/// Convert a YAML document to XML
+///
+/// # Arguments
+/// * `yaml_str` - YAML content
+/// * `root` - Root element name, used unless the document is a mapping
+///   with a single element key
+/// * `attr_prefix` - Keys with this prefix are attributes
+/// * `text_key` - Key holding an element's text next to attributes/children
+/// * `list_item` - Write lists as one element wrapping an element of this
+///   name per item, instead of repeating the element
+///
+/// # Example
+/// ```python
+/// rustyyaml.yaml_to_xml("server: {'@id': web, port: 8080, host: [a, b]}")
+/// # <server id="web"><port>8080</port><host>a</host><host>b</host></server>
+/// ```
+#[pyfunction]
+#[pyo3(signature = (yaml_str, root="root", attr_prefix="@", text_key="#text", list_item=None))]
+pub fn yaml_to_xml(
+    yaml_str: &str,
+    root: &str,
+    attr_prefix: &str,
+    text_key: &str,
+    list_item: Option<&str>,
+) -> PyResult<String> {
+    let document = match parser::parse_document(yaml_str, &LoadOptions::default(), true)? {
+        Parsed::Value(value) => value,
+        Parsed::Composed(_) => unreachable!("default options use serde_yaml"),
+    };
+    let style = XmlStyle {
+        attr_prefix: attr_prefix.to_string(),
+        text_key: text_key.to_string(),
+    };
+    to_xml(&document, root, &style, list_item).map_err(PyValueError::new_err)
+}
+
+/// Convert XML to YAML
+///
+/// # Arguments
+/// * `xml_str` - XML content
+/// * `attr_prefix` - Prefix for attribute keys
+/// * `text_key` - Key for text next to attributes or child elements
+/// * `force_list` - Element names that are always lists
+/// * `typed` - Resolve text like plain YAML scalars (`8080` is an int);
+///   with `False` every value is a string
+///
+/// # Example
+/// ```python
+/// rustyyaml.xml_to_yaml('<server id="web"><port>8080</port></server>')
+/// # "server:\n  '@id': web\n  port: 8080\n"
+/// ```
+#[pyfunction]
+#[pyo3(signature = (xml_str, attr_prefix="@", text_key="#text", force_list=None, typed=true))]
+pub fn xml_to_yaml(
+    xml_str: &str,
+    attr_prefix: &str,
+    text_key: &str,
+    force_list: Option<Vec<String>>,
+    typed: bool,
+) -> PyResult<String> {
+    let style = XmlStyle {
+        attr_prefix: attr_prefix.to_string(),
+        text_key: text_key.to_string(),
+    };
+    let document = from_xml(xml_str, &style, &force_list.unwrap_or_default(), typed)?;
+    serde_yaml::to_string(&document).map_err(|err| PyValueError::new_err(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn yaml(text: &str) -> Value {
+        serde_yaml::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn test_to_xml() {
+        let document = yaml(
+            "server:\n  '@id': web\n  port: 8080\n  host: [a, b]\n  note: 'x < y'\n  empty: null\n",
+        );
+        let xml = to_xml(&document, "root", &XmlStyle::default(), None).unwrap();
+        assert_eq!(
+            xml,
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <server id=\"web\">\n  <port>8080</port>\n  <host>a</host>\n  <host>b</host>\n  \
+             <note>x &lt; y</note>\n  <empty/>\n</server>\n"
+        );
+        let xml = to_xml(&yaml("[1, 2]"), "values", &XmlStyle::default(), Some("v")).unwrap();
+        assert!(xml.ends_with("<values>\n  <v>1</v>\n  <v>2</v>\n</values>\n"));
+        assert!(to_xml(&yaml("a: {'1bad': x}"), "root", &XmlStyle::default(), None).is_err());
+    }
+
+    #[test]
+    fn test_from_xml() {
+        let xml = "<?xml version=\"1.0\"?>\n<!-- config -->\n<server id=\"web\">\n  \
+                   <port>8080</port>\n  <host>a</host>\n  <host>b</host>\n  <tls enabled=\"true\">on</tls>\n  \
+                   <empty/>\n  <![CDATA[raw <text>]]>\n</server>\n";
+        let document = from_xml(xml, &XmlStyle::default(), &[], true).unwrap();
+        assert_eq!(
+            document,
+            yaml(
+                "server:\n  '@id': web\n  port: 8080\n  host: [a, b]\n  \
+                 tls: {'@enabled': true, '#text': 'on'}\n  empty: null\n  '#text': raw <text>\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_from_xml_options() {
+        let xml = "<a x=\"1\"><b>007</b></a>";
+        let style = XmlStyle {
+            attr_prefix: "_".to_string(),
+            text_key: "value".to_string(),
+        };
+        let document = from_xml(xml, &style, &["b".to_string()], false).unwrap();
+        assert_eq!(document, yaml("a: {_x: '1', b: ['007']}"));
+        let err = from_xml("<a>\n<b></a>", &XmlStyle::default(), &[], true).unwrap_err();
+        assert_eq!(err.location(), Some((2, 1)));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let document = yaml("config:\n  '@version': 2\n  name: app\n  port: [80, 443]\n");
+        let xml = to_xml(&document, "root", &XmlStyle::default(), None).unwrap();
+        assert_eq!(
+            from_xml(&xml, &XmlStyle::default(), &[], true).unwrap(),
+            document
+        );
+    }
+}
@@ -1,6 +1,18 @@
 """Basic functionality tests for RustyAML"""
 
+import bz2
+import datetime
+import gzip
+import hashlib
+import io
+import ipaddress
+import lzma
+import os
+import pickle
+import subprocess
+import sys
 import tempfile
+import uuid
 from pathlib import Path
 
 import pytest
@@ -253,6 +265,35 @@ class TestBasicParsing:
         assert eng["employees"][0]["name"] == "Alice"
         assert "Python" in eng["employees"][0]["skills"]
 
+    def test_json_documents(self):
+        """JSON documents (parsed on a fast path) load like any YAML"""
+        import json
+
+        doc = {"items": [{"id": 1, "name": "a\u00e9", "price": 0.5, "on": True}]}
+        text = json.dumps(doc, indent=2)
+        assert yaml.safe_load(text) == doc
+        assert yaml.load_all(text) == [doc]
+        assert yaml.safe_load(text, redact="items[*].name") == {
+            "items": [{"id": 1, "name": "***", "price": 0.5, "on": True}]
+        }
+        # Duplicate keys are still rejected; oversized integers load exactly
+        with pytest.raises(yaml.YAMLError):
+            yaml.safe_load('{"a": 1, "a": 2}')
+        assert yaml.safe_load('{"n": 18446744073709551616}') == {"n": 2**64}
+
+    def test_json_numbers_match_yaml_path(self):
+        """Numbers load with the same type and value on both paths"""
+        import random
+
+        rng = random.Random(978)
+        floats = [repr(rng.uniform(-1e6, 1e6)) for _ in range(2000)]
+        text = "[" + ", ".join(floats + ["9007199254740993.0", "-0", "-0.0", "0"]) + "]"
+        fast = yaml.safe_load(text)
+        # A trailing comment sends the same text through libyaml
+        slow = yaml.safe_load(text + " # yaml")
+        assert [(type(n), repr(n)) for n in fast] == [(type(n), repr(n)) for n in slow]
+        assert fast[-3:] == [0, -0.0, 0] and type(fast[-3]) is int
+
 
 class TestMultipleDocuments:
     """Test load_all functionality"""
@@ -309,6 +350,51 @@ just a string
         assert docs[2] == "just a string"
 
 
+class TestDocumentMarkers:
+    """Test stray document marker handling"""
+
+    CONCATENATED = "---\n---\nname: app\n...\n...\n\t\n"
+
+    def test_default_errors(self):
+        """Without the option, junk after the document is an error"""
+        with pytest.raises(yaml.YAMLError):
+            yaml.safe_load(self.CONCATENATED)
+
+    def test_lenient_safe_load(self):
+        """Stray markers and junk whitespace are ignored"""
+        result = yaml.safe_load(self.CONCATENATED, document_markers="lenient")
+        assert result == {"name": "app"}
+
+    def test_lenient_load_all_drops_empty_documents(self):
+        """Empty documents created by stray markers are dropped"""
+        stream = "a: 1\n---\n---\nb: 2\n---\n"
+        assert yaml.load_all(stream) == [{"a": 1}, None, {"b": 2}, None]
+        assert yaml.load_all(stream, document_markers="lenient") == [
+            {"a": 1},
+            {"b": 2},
+        ]
+
+    def test_strict_pinpoints_marker(self):
+        """Strict mode reports the line of the first stray marker"""
+        with pytest.raises(yaml.YAMLError) as exc_info:
+            yaml.load_all("a: 1\n...\n...\n", document_markers="strict")
+        assert "line 3" in str(exc_info.value)
+        assert "'...'" in str(exc_info.value)
+
+    def test_strict_accepts_clean_stream(self):
+        """Well-formed streams load normally in strict mode"""
+        stream = "---\na: 1\n...\n---\nb: 2\n"
+        assert yaml.load_all(stream, document_markers="strict") == [
+            {"a": 1},
+            {"b": 2},
+        ]
+
+    def test_invalid_mode(self):
+        """Unknown modes are rejected"""
+        with pytest.raises(yaml.YAMLError):
+            yaml.safe_load("a: 1", document_markers="loose")
+
+
 class TestFileOperations:
     """Test file loading"""
 
@@ -340,12 +426,50 @@ class TestFileOperations:
 
         assert result == {"key": "value"}
 
+    def test_load_from_chunked_stream(self):
+        """Load from a file-like object that returns short reads"""
+
+        class Trickle:
+            def __init__(self, data):
+                self.data = data
+
+            def read(self, size):
+                chunk, self.data = self.data[:3], self.data[3:]
+                return chunk
+
+        text = "name: café\nitems: [1, 2, 3]\n"
+        expected = {"name": "café", "items": [1, 2, 3]}
+        assert yaml.safe_load(Trickle(text)) == expected
+        assert yaml.safe_load(Trickle(text.encode("utf-16"))) == expected
+        assert yaml.safe_load(Trickle("")) is None
+
+        class Broken:
+            def read(self, size):
+                raise OSError("connection reset")
+
+        with pytest.raises(yaml.YAMLError, match="connection reset"):
+            yaml.safe_load(Broken())
+
     def test_load_from_bytes(self):
         """Load from bytes"""
         yaml_bytes = b"key: value"
         result = yaml.safe_load(yaml_bytes)
         assert result == {"key": "value"}
 
+    def test_load_from_bytes_encodings(self, tmp_path):
+        """Bytes in any YAML encoding, with or without a BOM"""
+        text = "name: café\n"
+        for encoding in ["utf-8-sig", "utf-16", "utf-16-be", "utf-32", "utf-32-le"]:
+            data = text.encode(encoding)
+            assert yaml.safe_load(data) == {"name": "café"}, encoding
+            assert yaml.safe_load(bytearray(data)) == {"name": "café"}
+            assert yaml.load_all(memoryview(data)) == [{"name": "café"}]
+        path = tmp_path / "utf16.yaml"
+        path.write_bytes(text.encode("utf-16"))
+        assert yaml.safe_load(path) == {"name": "café"}
+        with open(path, "rb") as f:
+            assert yaml.safe_load(f) == {"name": "café"}
+
     def test_safe_load_file(self, tmp_path):
         """Test safe_load_file convenience function"""
         yaml_file = tmp_path / "test.yaml"
@@ -353,6 +477,185 @@ class TestFileOperations:
 
         result = yaml.safe_load_file(yaml_file)
         assert result == {"key": "value"}
+        assert yaml.safe_load_file(str(yaml_file)) == {"key": "value"}
+
+        yaml_file.write_text("a: [1\n")
+        with pytest.raises(yaml.YAMLError) as exc:
+            yaml.safe_load_file(yaml_file)
+        assert Path(exc.value.path) == yaml_file
+        missing = tmp_path / "missing.yaml"
+        with pytest.raises(yaml.YAMLError, match="^File not found: ") as exc:
+            yaml.safe_load_file(missing)
+        assert str(exc.value) == f"File not found: {missing}"
+        assert exc.value.line is None
+        # Other read errors are reported as such, not as a parse error
+        with pytest.raises(yaml.YAMLError, match="^Failed to read ") as exc:
+            yaml.safe_load_file(tmp_path)
+        assert exc.value.line is None
+        assert "parse error" not in str(exc.value)
+
+    def test_unsafe_load_file(self, tmp_path):
+        yaml_file = tmp_path / "trusted.yaml"
+        yaml_file.write_text("# !!python/object is fine here\na: 1\n")
+        with pytest.raises(yaml.YAMLError, match="Unsafe"):
+            yaml.safe_load_file(yaml_file)
+        assert yaml.unsafe_load_file(yaml_file) == {"a": 1}
+
+    def test_load_file_use_mmap(self, tmp_path):
+        """use_mmap=True loads the same data from a mapped file"""
+        yaml_file = tmp_path / "big.yaml"
+        yaml_file.write_text("".join(f"key{i}: [{i}, café]\n" for i in range(500)))
+        expected = yaml.safe_load_file(yaml_file)
+        assert yaml.safe_load_file(yaml_file, use_mmap=True) == expected
+        assert yaml.load_all_file(yaml_file, use_mmap=True) == [expected]
+        assert yaml.Loader(use_mmap=True).load_file(yaml_file) == expected
+        results = yaml.load_directory(tmp_path, use_mmap=True)
+        assert [data for _, data in results] == [expected]
+
+        yaml_file.write_text("a: 1\n", encoding="utf-16")
+        assert yaml.safe_load_file(yaml_file, use_mmap=True) == {"a": 1}
+        yaml_file.write_text("")
+        assert yaml.safe_load_file(yaml_file, use_mmap=True) is None
+        with pytest.raises(yaml.YAMLError, match="File not found"):
+            yaml.safe_load_file(tmp_path / "missing.yaml", use_mmap=True)
+
+    def test_load_file_streaming(self, tmp_path):
+        """streaming=True loads the same data one entry at a time"""
+        yaml_file = tmp_path / "export.yaml"
+        yaml_file.write_text(
+            "".join(f"- id: {i}\n  tags: [a, b]\n" for i in range(1000))
+        )
+        expected = yaml.safe_load_file(yaml_file)
+        assert yaml.safe_load_file(yaml_file, streaming=True) == expected
+        assert yaml.unsafe_load_file(yaml_file, streaming=True) == expected
+
+        yaml_file.write_text(
+            "base: &base {replicas: 2}\n"
+            "created: 2024-01-01\n"
+            "web: {<<: *base, port: 80}\n"
+        )
+        assert yaml.safe_load_file(
+            yaml_file, streaming=True, pyyaml_compat=True
+        ) == yaml.safe_load_file(yaml_file, pyyaml_compat=True)
+
+        yaml_file.write_text("a: 1\nb: !!python/object:os.system x\n")
+        with pytest.raises(yaml.YAMLError, match="Unsafe") as exc:
+            yaml.safe_load_file(yaml_file, streaming=True)
+        assert Path(exc.value.path) == yaml_file
+        with pytest.raises(yaml.YAMLError, match="marked"):
+            yaml.safe_load_file(yaml_file, streaming=True, marked=True)
+        with pytest.raises(yaml.YAMLError, match="sha256"):
+            yaml.safe_load_file(yaml_file, sha256="0" * 64, streaming=True)
+        with pytest.raises(yaml.YAMLError, match="File not found"):
+            yaml.safe_load_file(tmp_path / "missing.yaml", streaming=True)
+
+    def test_load_gzipped_file(self, tmp_path):
+        """.gz files are decompressed in Rust by the file loaders"""
+        import gzip
+
+        text = "".join(f"key{i}: [{i}, café]\n" for i in range(200))
+        expected = yaml.safe_load(text)
+        gz_file = tmp_path / "config.yaml.gz"
+        gz_file.write_bytes(gzip.compress(text.encode()))
+        assert yaml.safe_load_file(gz_file) == expected
+        assert yaml.safe_load_file(gz_file, use_mmap=True) == expected
+        assert yaml.safe_load_file(gz_file, streaming=True) == expected
+        assert yaml.load_all_file(gz_file) == [expected]
+        digest = hashlib.sha256(gz_file.read_bytes()).hexdigest()
+        assert yaml.safe_load_file(gz_file, sha256=digest) == expected
+
+        (tmp_path / "plain.yml").write_text("a: 1\n")
+        (tmp_path / "notes.txt.gz").write_bytes(gzip.compress(b"a: 2\n"))
+        results = dict(yaml.load_directory(tmp_path))
+        assert {Path(path).name: data for path, data in results.items()} == {
+            "config.yaml.gz": expected,
+            "plain.yml": {"a": 1},
+        }
+
+        broken = tmp_path / "broken.yaml.gz"
+        broken.write_bytes(b"a: 1\n")
+        with pytest.raises(yaml.YAMLError, match="Failed to decompress"):
+            yaml.safe_load_file(broken)
+
+    def test_bzip2_and_xz_files(self, tmp_path):
+        expected = {"a": 1, "b": [2, 3]}
+        text = b"a: 1\nb: [2, 3]\n"
+        (tmp_path / "config.yaml.bz2").write_bytes(bz2.compress(text))
+        (tmp_path / "config.yml.xz").write_bytes(lzma.compress(text))
+        for name in ["config.yaml.bz2", "config.yml.xz"]:
+            path = tmp_path / name
+            assert yaml.safe_load_file(path) == expected
+            assert yaml.safe_load_file(path, streaming=True) == expected
+        results = dict(yaml.load_directory(tmp_path))
+        assert {Path(path).name: data for path, data in results.items()} == {
+            "config.yaml.bz2": expected,
+            "config.yml.xz": expected,
+        }
+        broken = tmp_path / "broken.yaml.xz"
+        broken.write_bytes(text)
+        with pytest.raises(yaml.YAMLError, match="Failed to decompress"):
+            yaml.safe_load_file(broken)
+
+    def test_load_all_iter(self):
+        """load_all_iter parses documents as they are reached"""
+        stream = "a: 1\n---\n- x\n---\nb: [\n"
+        documents = yaml.load_all_iter(stream)
+        assert next(documents) == {"a": 1}
+        assert next(documents) == ["x"]
+        with pytest.raises(yaml.YAMLError) as exc:
+            next(documents)
+        assert exc.value.document == 2
+        assert list(documents) == []
+
+        text = "".join(f"---\nid: {i}\n" for i in range(1000))
+        assert list(yaml.load_all_iter(text)) == yaml.load_all(text)
+        assert next(yaml.load_all_iter(text.encode("utf-16"))) == {"id": 0}
+        dates = yaml.load_all_iter("d: 2024-01-01\n", timestamps=True)
+        assert list(dates) == yaml.load_all("d: 2024-01-01\n", timestamps=True)
+
+        with pytest.raises(yaml.YAMLError, match="Unsafe"):
+            yaml.load_all_iter("a: 1\n---\nb: !!python/object:os.system x\n")
+        with pytest.raises(yaml.YAMLError, match="collect_errors"):
+            yaml.load_all_iter("a: 1", collect_errors=True)
+
+    def test_load_all_iter_unhashable_key(self):
+        """Construction errors are YAMLErrors, as from load_all"""
+        stream = "a: 1\n---\n? [1, 2]\n: x\n"
+        with pytest.raises(yaml.YAMLError, match="unhashable") as eager:
+            yaml.load_all(stream)
+        documents = yaml.load_all_iter(stream)
+        assert next(documents) == {"a": 1}
+        with pytest.raises(yaml.YAMLError) as lazy:
+            next(documents)
+        assert str(lazy.value) == str(eager.value)
+        assert lazy.value.document == 1
+        assert isinstance(lazy.value.__cause__, TypeError)
+
+    def test_load_from_stdin(self):
+        """safe_load_stdin and load_all_stdin read stdin in Rust"""
+        code = (
+            "import rustyyaml, sys; "
+            "print(getattr(rustyyaml, sys.argv[1])())"
+        )
+        package = os.path.dirname(os.path.dirname(yaml.__file__))
+        env = dict(os.environ, PYTHONPATH=package)
+
+        def run(function, stdin):
+            return subprocess.run(
+                [sys.executable, "-c", code, function],
+                input=stdin,
+                capture_output=True,
+                env=env,
+            )
+
+        result = run("safe_load_stdin", b"kind: Pod\nspec: {replicas: 2}\n")
+        expected = "{'kind': 'Pod', 'spec': {'replicas': 2}}"
+        assert result.stdout.decode().strip() == expected
+        result = run("load_all_stdin", "a: café\n---\nb: 2\n".encode("utf-16"))
+        assert result.stdout.decode().strip() == "[{'a': 'café'}, {'b': 2}]"
+        result = run("safe_load_stdin", b"x: !!python/object:os.system y\n")
+        assert result.returncode != 0
+        assert b"Unsafe" in result.stderr
 
     def test_load_all_file(self, tmp_path):
         """Test load_all_file convenience function"""
@@ -362,10 +665,103 @@ class TestFileOperations:
         result = yaml.load_all_file(yaml_file)
         assert len(result) == 2
 
+        yaml_file.write_text("doc: 1\n---\n!!python/object:os.system x\n")
+        with pytest.raises(yaml.YAMLError, match="Unsafe") as exc:
+            yaml.load_all_file(yaml_file)
+        assert Path(exc.value.path) == yaml_file
+        yaml_file.write_text("doc: 1\n---\n[broken\n")
+        documents, errors = yaml.load_all_file(yaml_file, collect_errors=True)
+        assert documents == [{"doc": 1}] and len(errors) == 1
+
+    def test_path_like(self, tmp_path):
+        """Any os.PathLike is accepted where a path is"""
+
+        class Location:
+            def __init__(self, path):
+                self.path = path
+
+            def __fspath__(self):
+                return str(self.path)
+
+        (tmp_path / "app.yaml").write_text("name: web\n")
+        assert yaml.safe_load(Location(tmp_path / "app.yaml")) == {"name": "web"}
+        assert yaml.Loader().load_file(Location(tmp_path / "app.yaml")) == {
+            "name": "web"
+        }
+        results = yaml.load_directory(Location(tmp_path))
+        assert results == [(str(tmp_path / "app.yaml"), {"name": "web"})]
+        assert yaml.grep(Location(tmp_path), "name")[0][2] == "web"
+        assert yaml.bundle([Location(tmp_path / "app.yaml")], tmp_path / "b.yml") == 1
+
+    def test_bytes_path(self, tmp_path):
+        """Bytes paths, and path-likes that return bytes, are accepted"""
+
+        class Location:
+            def __init__(self, path):
+                self.path = path
+
+            def __fspath__(self):
+                return os.fsencode(self.path)
+
+        app = tmp_path / "app.yaml"
+        app.write_text("name: web\n")
+        assert yaml.safe_load(Location(app)) == {"name": "web"}
+        for path in (os.fsencode(app), Location(app)):
+            assert yaml.safe_load_file(path) == {"name": "web"}
+            assert yaml.load_all_file(path) == [{"name": "web"}]
+            assert yaml.Loader().load_file(path) == {"name": "web"}
+        directory = os.fsencode(tmp_path)
+        results = yaml.load_directory(directory)
+        assert results == [(str(app), {"name": "web"})]
+        assert yaml.grep(Location(tmp_path), "name")[0][2] == "web"
+        yaml.dump_file({"a": 1}, os.fsencode(tmp_path / "out.yml"))
+        assert yaml.safe_load_file(tmp_path / "out.yml") == {"a": 1}
+        assert yaml.bundle([Location(app)], os.fsencode(tmp_path / "b.yml")) == 1
+
+    @pytest.mark.skipif(os.name != "posix", reason="bytes file names")
+    def test_non_utf8_path(self, tmp_path):
+        """File names that aren't UTF-8 come back as os.fsdecode gives them"""
+        name = os.fsdecode(b"caf\xe9.yaml")
+        try:
+            (tmp_path / name).write_text("a: 1\n")
+        except (OSError, UnicodeEncodeError):
+            pytest.skip("file system rejects the name")
+        [(path, data)] = yaml.load_directory(tmp_path)
+        assert path == os.path.join(str(tmp_path), name)
+        assert data == {"a": 1}
+        assert yaml.Loader().load_file(path) == {"a": 1}
+
 
 class TestErrorHandling:
     """Test error cases"""
 
+    def test_errors_pickle(self, tmp_path):
+        """Errors keep their position and path across process boundaries"""
+        with pytest.raises(yaml.YAMLError) as info:
+            yaml.safe_load("a: 1\nb: [\n")
+        copy = pickle.loads(pickle.dumps(info.value))
+        assert type(copy) is yaml.YAMLError
+        assert (copy.line, copy.column) == (info.value.line, info.value.column)
+        assert str(copy) == str(info.value)
+
+        with pytest.raises(yaml.TabIndentationError) as info:
+            yaml.safe_load("a:\n\t- b")
+        copy = pickle.loads(pickle.dumps(info.value))
+        assert type(copy) is yaml.TabIndentationError
+        assert copy.line == 2
+
+        (tmp_path / "bad.yaml").write_text("a: [")
+        _, [error] = yaml.load_directory(tmp_path, collect_errors=True)
+        copy = pickle.loads(pickle.dumps(error))
+        assert copy.path == error.path
+        assert copy.line == error.line is not None
+
+    def test_error_attributes_default_to_none(self):
+        """Every YAMLError has the structured attributes"""
+        error = yaml.YAMLError("raised from Python")
+        assert (error.line, error.column, error.document, error.path) == (None,) * 4
+        assert pickle.loads(pickle.dumps(error)).line is None
+
     def test_invalid_yaml_syntax(self):
         """Invalid YAML syntax"""
         with pytest.raises(yaml.YAMLError):
@@ -383,8 +779,8 @@ class TestErrorHandling:
 
     def test_invalid_utf8(self):
         """Invalid UTF-8 bytes"""
-        with pytest.raises(yaml.YAMLError):
-            yaml.safe_load(b"\xff\xfe")
+        with pytest.raises(yaml.YAMLError, match="invalid utf-8"):
+            yaml.safe_load(b"key: \xff\xfe")
 
     def test_file_not_found(self):
         """Non-existent file"""
@@ -415,6 +811,663 @@ key:
             pass
 
 
+class TestTabIndentation:
+    """Test dedicated tab indentation errors"""
+
+    def test_tab_indentation_error(self):
+        """A tab in indentation raises TabIndentationError with a position"""
+        with pytest.raises(yaml.TabIndentationError) as exc_info:
+            yaml.safe_load("server:\n  host: localhost\n\tport: 8080\n")
+        err = exc_info.value
+        assert (err.line, err.column) == (3, 1)
+        assert "line 3" in str(err)
+        assert "^" in str(err)
+
+    def test_is_a_yaml_error(self):
+        """Existing `except YAMLError` handlers still catch it"""
+        assert issubclass(yaml.TabIndentationError, yaml.YAMLError)
+        with pytest.raises(yaml.YAMLError):
+            yaml.load_all("a:\n\tb: 1")
+
+    def test_batch_loading(self):
+        """Batch loaders raise the same exception"""
+        with pytest.raises(yaml.TabIndentationError):
+            yaml.safe_load_many(["a: 1", "a:\n\tb: 1"])
+
+    def test_tabs_inside_values_allowed(self):
+        """Tabs that are not indentation still parse"""
+        assert yaml.safe_load("a:\tb\nc: |\n  x\ty\n") == {"a": "b", "c": "x\ty\n"}
+
+
+class TestSourceMap:
+    """Test safe_load_with_sourcemap"""
+
+    def test_data_matches_safe_load(self):
+        """The data is the same plain objects safe_load returns"""
+        text = "a: 1\nb: [x, y]\n"
+        data, _ = yaml.safe_load_with_sourcemap(text)
+        assert data == yaml.safe_load(text)
+        assert type(data) is dict
+
+    def test_pointers_and_locations(self):
+        """Every value gets a JSON pointer with line, column and span"""
+        text = "spec:\n  ports:\n    - 80\n    - 443\n"
+        _, sourcemap = yaml.safe_load_with_sourcemap(text)
+        assert set(sourcemap) == {
+            "",
+            "/spec",
+            "/spec/ports",
+            "/spec/ports/0",
+            "/spec/ports/1",
+        }
+        line, column, (start, end) = sourcemap["/spec/ports/1"]
+        assert (line, column) == (4, 7)
+        assert text[start:end] == "443"
+
+    def test_escaped_keys(self):
+        """'/' and '~' in keys are escaped as in RFC 6901"""
+        _, sourcemap = yaml.safe_load_with_sourcemap("a/b: 1\nc~d: 2")
+        assert "/a~1b" in sourcemap
+        assert "/c~0d" in sourcemap
+
+    def test_empty_document(self):
+        """An empty stream has no entries"""
+        assert yaml.safe_load_with_sourcemap("") == (None, {})
+
+    def test_unsafe_tags_rejected(self):
+        """Same safety guarantees as safe_load"""
+        with pytest.raises(yaml.YAMLError):
+            yaml.safe_load_with_sourcemap("!!python/object/apply:os.system ['ls']")
+
+
+class TestMarked:
+    """Test marked=True position-carrying containers"""
+
+    def test_containers_are_subclasses(self):
+        """Marked values still behave like dict/list/str"""
+        data = yaml.safe_load("a:\n  - x\n  - 1\n", marked=True)
+        assert data == {"a": ["x", 1]}
+        assert isinstance(data, dict)
+        assert isinstance(data["a"], list)
+        assert isinstance(data["a"][0], str)
+        assert type(data["a"][1]) is int
+
+    def test_line_col(self):
+        """lc.line/lc.col are 0-based like ruamel.yaml"""
+        data = yaml.safe_load("top: 1\nitems:\n  - name: x\n", marked=True)
+        assert (data.lc.line, data.lc.col) == (0, 0)
+        assert (data["items"].lc.line, data["items"].lc.col) == (2, 2)
+        item = data["items"][0]
+        assert (item.lc.line, item.lc.col) == (2, 4)
+        assert (item["name"].lc.line, item["name"].lc.col) == (2, 10)
+
+    def test_key_value_item_positions(self):
+        """Containers record where their keys and items are"""
+        data = yaml.safe_load("a: 1\nb: [x, y]\n", marked=True)
+        assert data.lc.key("b") == (1, 0)
+        assert data.lc.value("b") == (1, 3)
+        assert data["b"].lc.item(1) == (1, 7)
+
+    def test_scalar_styles(self):
+        """Scalars record whether they were quoted, plain or block"""
+        source = "'a': \"x\"\nc: &c |\n  text\nb: [plain, 1, *c]\nd: >\n  more\n"
+        data = yaml.safe_load(source, marked=True)
+        assert data["a"].lc.style == "double-quoted"
+        assert data.lc.key_style("a") == "single-quoted"
+        assert data.lc.value_style("a") == "double-quoted"
+        assert data.lc.value_style("b") is None
+        styles = [data["b"].lc.item_style(i) for i in range(3)]
+        assert styles == ["plain", "plain", None]
+        assert data.lc.value_style("c") == "literal"
+        assert data.lc.value_style("d") == "folded"
+
+    def test_keys_stay_plain(self):
+        """Mapping keys are ordinary str objects"""
+        data = yaml.safe_load("a: 1", marked=True)
+        assert [type(k) for k in data] == [str]
+
+    def test_default_is_unmarked(self):
+        """Without the option, plain builtins are returned"""
+        assert type(yaml.safe_load("a: [x]")) is dict
+
+
+class TestDictClass:
+    """Test dict_class= mapping construction"""
+
+    def test_ordered_dict(self):
+        """Every mapping, nested or in a list, uses the class"""
+        from collections import OrderedDict
+
+        data = yaml.safe_load("b: 1\na: {c: [{d: 2}]}\n", dict_class=OrderedDict)
+        assert type(data) is OrderedDict
+        assert list(data) == ["b", "a"]
+        assert type(data["a"]) is OrderedDict
+        assert type(data["a"]["c"][0]) is OrderedDict
+
+    def test_case_insensitive(self):
+        """Items go through the class's __setitem__"""
+
+        class CaseInsensitiveDict(dict):
+            def __setitem__(self, key, value):
+                super().__setitem__(key.lower(), value)
+
+            def __getitem__(self, key):
+                return super().__getitem__(key.lower())
+
+        docs = yaml.load_all(
+            "Content-Type: json\n---\nHOST: x\n", dict_class=CaseInsensitiveDict
+        )
+        assert docs[0]["content-type"] == "json"
+        assert docs[1]["Host"] == "x"
+
+    def test_with_other_options(self):
+        """Works alongside resolvers and merge keys"""
+        from collections import OrderedDict
+
+        doc = "base: &b {when: 2024-01-01}\nprod:\n  <<: *b\n  x: 1\n"
+        data = yaml.safe_load(doc, dict_class=OrderedDict, timestamps=True)
+        assert type(data["prod"]) is OrderedDict
+        assert data["prod"]["x"] == 1
+
+    def test_invalid(self):
+        """Non-callables and marked=True are rejected"""
+        with pytest.raises(yaml.YAMLError, match="callable"):
+            yaml.safe_load("a: 1", dict_class=5)
+        with pytest.raises(yaml.YAMLError, match="marked"):
+            yaml.safe_load("a: 1", dict_class=dict, marked=True)
+
+
+class TestNamespace:
+    """Test as_namespace=True config objects"""
+
+    def test_attribute_and_item_access(self):
+        """Nested mappings, also inside lists, allow both spellings"""
+        cfg = yaml.safe_load(
+            "database:\n  port: 5432\nreplicas:\n  - host: a\n", as_namespace=True
+        )
+        assert cfg.database.port == cfg["database"]["port"] == 5432
+        assert cfg.replicas[0].host == "a"
+        assert isinstance(cfg, yaml.Namespace)
+        with pytest.raises(AttributeError):
+            cfg.missing
+        with pytest.raises(KeyError):
+            cfg["missing"]
+
+    def test_mapping_protocol(self):
+        """Namespaces behave like dicts"""
+        from collections.abc import Mapping
+
+        cfg = yaml.safe_load("a: 1\nitems: [x]\n", as_namespace=True)
+        assert isinstance(cfg, Mapping)
+        assert list(cfg) == ["a", "items"]
+        assert len(cfg) == 2 and "a" in cfg
+        assert cfg["items"] == ["x"]
+        assert cfg.get("b", 2) == 2
+        assert dict(cfg.items()) == {"a": 1, "items": ["x"]}
+        assert cfg == {"a": 1, "items": ["x"]}
+
+    def test_mutation(self):
+        """Attributes and items write through to the same data"""
+        cfg = yaml.safe_load("a: 1", as_namespace=True)
+        cfg.b = 2
+        cfg["c"] = 3
+        del cfg.a
+        assert cfg == {"b": 2, "c": 3}
+
+    def test_to_dict(self):
+        """to_dict() returns plain containers, e.g. for json.dumps"""
+        import json
+
+        cfg = yaml.safe_load("a: {b: [{c: 1}]}", as_namespace=True)
+        plain = cfg.to_dict()
+        assert type(plain["a"]["b"][0]) is dict
+        assert json.loads(json.dumps(plain)) == {"a": {"b": [{"c": 1}]}}
+
+    def test_constructor(self):
+        """Namespace(dict) wraps nested dicts"""
+        cfg = yaml.Namespace({"db": {"port": 1}})
+        assert cfg.db.port == 1
+        assert repr(yaml.Namespace({"a": 1})) == "Namespace({'a': 1})"
+
+    def test_pickle_and_copy(self):
+        """Namespaces pickle and deep-copy as namespaces"""
+        import copy
+
+        text = "db: {port: 5432}\nhosts: [{name: a}]\n"
+        cfg = yaml.safe_load(text, as_namespace=True)
+        for clone in (pickle.loads(pickle.dumps(cfg)), copy.deepcopy(cfg)):
+            assert type(clone) is yaml.Namespace
+            assert clone == cfg and clone is not cfg
+            assert clone.db.port == 5432 and clone.hosts[0].name == "a"
+            assert clone.db is not cfg.db
+        assert copy.copy(cfg) == cfg
+
+    def test_with_dict_class(self):
+        """as_namespace and dict_class are exclusive"""
+        with pytest.raises(yaml.YAMLError, match="dict_class"):
+            yaml.safe_load("a: 1", as_namespace=True, dict_class=dict)
+
+
+class TestCollectErrors:
+    """Test collect-errors mode for multi-document and batch loading"""
+
+    def test_load_all_collects_every_error(self):
+        """Broken documents are reported, the rest still load"""
+        stream = "a: 1\n---\nb: [unclosed\n---\nc: 3\n---\nd: {\n"
+        docs, errors = yaml.load_all(stream, collect_errors=True)
+        assert docs == [{"a": 1}, {"c": 3}]
+        assert [e.document for e in errors] == [1, 3]
+        assert all(isinstance(e, yaml.YAMLError) for e in errors)
+        assert errors[0].line >= 3
+
+    def test_load_all_without_errors(self):
+        """A clean stream returns an empty error list"""
+        assert yaml.load_all("a: 1\n---\nb: 2", collect_errors=True) == (
+            [{"a": 1}, {"b": 2}],
+            [],
+        )
+
+    def test_unsafe_documents_collected(self):
+        """Safety violations are collected like parse errors"""
+        stream = "a: 1\n---\nb: !!python/object:os.system x\n"
+        docs, errors = yaml.load_all(stream, collect_errors=True)
+        assert docs == [{"a": 1}]
+        assert errors[0].document == 1
+
+    def test_safe_load_many(self):
+        """Errors carry the index of the failing string"""
+        results, errors = yaml.safe_load_many(
+            ["a: 1", "b: [", "c: 3"], collect_errors=True
+        )
+        assert results == [{"a": 1}, {"c": 3}]
+        assert errors[0].document == 1
+
+    def test_load_directory(self):
+        """Errors carry the path of the failing file"""
+        with tempfile.TemporaryDirectory() as tmpdir:
+            Path(tmpdir, "good.yaml").write_text("a: 1")
+            Path(tmpdir, "bad.yaml").write_text("a: [")
+            results, errors = yaml.load_directory(tmpdir, collect_errors=True)
+        assert [Path(p).name for p, _ in results] == ["good.yaml"]
+        assert Path(errors[0].path).name == "bad.yaml"
+
+    def test_single_document_loaders_reject_option(self):
+        """collect_errors makes no sense for safe_load"""
+        with pytest.raises(yaml.YAMLError):
+            yaml.safe_load("a: 1", collect_errors=True)
+
+    def test_raised_errors_have_location(self):
+        """Errors raised normally expose line/column too"""
+        with pytest.raises(yaml.YAMLError) as exc_info:
+            yaml.load_all("a: 1\nb: [\n")
+        assert exc_info.value.line is not None
+        assert exc_info.value.document is None
+
+
+class TestLoader:
+    """Test the reusable Loader"""
+
+    def test_load_methods(self, tmp_path):
+        """Options given once apply to every call"""
+        loader = yaml.Loader(pyyaml_compat=True)
+        assert loader.load("on: yes") == {True: True}
+        assert loader.load(b"a: no") == {"a": False}
+        assert loader.load_all("a: on\n---\nb: off\n") == [{"a": True}, {"b": False}]
+        path = tmp_path / "config.yaml"
+        path.write_text("debug: yes\n")
+        assert loader.load_file(path) == {"debug": True}
+        assert isinstance(loader, yaml.Loader)
+
+    def test_safety_and_errors(self, tmp_path):
+        """Loaders are safe by default; errors are YAMLErrors"""
+        with pytest.raises(yaml.YAMLError):
+            yaml.Loader().load("a: !!python/object:os.system x")
+        with pytest.raises(yaml.YAMLError):
+            yaml.Loader(no_such_option=True)
+        loader = yaml.Loader(collect_errors=True)
+        docs, errors = loader.load_all("a: 1\n---\nb: [\n")
+        assert docs == [{"a": 1}] and errors[0].document == 1
+        path = tmp_path / "bad.yaml"
+        path.write_text("a: [\n")
+        with pytest.raises(yaml.YAMLError) as exc_info:
+            yaml.Loader().load_file(path)
+        assert exc_info.value.path == str(path)
+
+
+class TestDumper:
+    """Test the reusable Dumper"""
+
+    def test_dump_methods(self, tmp_path):
+        """Options given once apply to every call"""
+        dumper = yaml.Dumper(sort_keys=False, bool_style="True", null_style="~")
+        data = {"b": True, "a": None}
+        assert dumper.dump(data) == "b: True\na: ~\n"
+        assert dumper.dump(data, comments={"a": "unset"}) == "b: True\n# unset\na: ~\n"
+        assert dumper.dump_all([data, [False]], headers=["first", None]) == (
+            "---\n# first\nb: True\na: ~\n---\n- False\n"
+        )
+        stream = io.StringIO()
+        assert dumper.dump([1], stream) is None
+        assert stream.getvalue() == "- 1\n"
+        path = tmp_path / "out" / "data.yaml"
+        dumper.dump_file(data, path, make_dirs=True)
+        assert path.read_text() == "b: True\na: ~\n"
+        assert isinstance(dumper, yaml.Dumper)
+
+    def test_representers_and_errors(self):
+        """A Dumper's representers are its own; errors are YAMLErrors"""
+
+        class Money:
+            def __init__(self, cents):
+                self.cents = cents
+
+        dumper = yaml.Dumper()
+        dumper.add_representer(Money, lambda money: f"${money.cents / 100:.2f}")
+        assert dumper.dump({"price": Money(150)}) == "price: $1.50\n"
+        with pytest.raises(yaml.YAMLError):
+            yaml.safe_dump({"price": Money(150)})
+        with pytest.raises(yaml.YAMLError):
+            yaml.Dumper().dump({"price": Money(150)})
+        with pytest.raises(yaml.YAMLError):
+            dumper.add_representer(Money, "not callable")
+        with pytest.raises(yaml.YAMLError):
+            yaml.Dumper(no_such_option=True)
+
+
+class TestParseLenient:
+    """Test parse_lenient()"""
+
+    def test_recovers_around_errors(self):
+        """Offending lines are left out and every problem is reported"""
+        stream = "a: 1\nb: [1, 2\nc: 3\nd: 'open\ne: 5\n"
+        docs, errors = yaml.parse_lenient(stream)
+        assert docs == [{"a": 1, "c": 3, "e": 5}]
+        assert len(errors) == 2
+        assert all(isinstance(e, yaml.YAMLError) for e in errors)
+        assert [e.document for e in errors] == [0, 0]
+        assert errors[0].line == 3
+
+    def test_documents_recover_independently(self):
+        """A broken document does not affect the others"""
+        docs, errors = yaml.parse_lenient("a: 1\n---\nb: : :\nc: 2\n---\nd: 4\n")
+        assert docs == [{"a": 1}, {"c": 2}, {"d": 4}]
+        assert [e.document for e in errors] == [1]
+        assert errors[0].line == 3
+
+    def test_errors_at_end_of_input(self):
+        """An error reported after the last newline is still recorded"""
+        docs, errors = yaml.parse_lenient("a: 1\nb: [\n")
+        assert docs == [{"a": 1}]
+        assert len(errors) == 1
+        assert errors[0].document == 0
+        docs, errors = yaml.parse_lenient("[[[\n")
+        assert docs == [None]
+        assert len(errors) == 1
+
+    def test_clean_stream(self):
+        """Valid YAML parses as load_all would"""
+        assert yaml.parse_lenient("a: 1\n---\nb: 2\n") == ([{"a": 1}, {"b": 2}], [])
+
+    def test_unsafe_tags_reported(self):
+        """Documents with unsafe tags are reported, not constructed"""
+        docs, errors = yaml.parse_lenient("a: !!python/object:os.system x\n")
+        assert docs == [None]
+        assert errors[0].document == 0
+
+    def test_disallowed_tags_recorded(self):
+        """A rejected tag is recorded and the rest of the document kept"""
+        stream = "a: 1\nb: !custom x\nc: !!python/name:os.system\nd: 4\n"
+        docs, errors = yaml.parse_lenient(stream)
+        assert docs == [{"a": 1, "d": 4}]
+        assert [(e.line, e.document) for e in errors] == [(2, 0), (3, 0)]
+        assert "!custom" in str(errors[0])
+        docs, errors = yaml.parse_lenient(stream, allowed_tags=["!custom"])
+        assert docs == [{"a": 1, "b": "x", "d": 4}]
+        assert len(errors) == 1
+
+
+class TestParse:
+    """Test parse() event streams"""
+
+    def test_events(self):
+        from rustyyaml import events
+
+        stream = yaml.parse("a: &x [1, 'two']\nb: *x\n")
+        assert isinstance(next(stream), events.StreamStartEvent)
+        assert isinstance(next(stream), events.DocumentStartEvent)
+        rest = list(stream)
+        assert [type(e).__name__ for e in rest] == [
+            "MappingStartEvent",
+            "ScalarEvent",
+            "SequenceStartEvent",
+            "ScalarEvent",
+            "ScalarEvent",
+            "SequenceEndEvent",
+            "ScalarEvent",
+            "AliasEvent",
+            "MappingEndEvent",
+            "DocumentEndEvent",
+            "StreamEndEvent",
+        ]
+        sequence, two = rest[2], rest[4]
+        assert sequence.anchor == "x" and sequence.flow_style is True
+        assert two.value == "two" and two.style == "'"
+        assert two.implicit == (False, True)
+        assert (two.start_mark.line, two.start_mark.column) == (0, 10)
+        assert rest[7].anchor == "x"
+
+    def test_unsafe_tags_are_events(self):
+        """Events aren't constructed, so any tag is reported as written"""
+        scalar = list(yaml.parse("!!python/object:os.system x"))[2]
+        assert scalar.tag == "tag:yaml.org,2002:python/object:os.system"
+
+    def test_errors_when_reached(self):
+        stream = yaml.parse("a: 1\nb: [\n")
+        assert next(stream) is not None
+        with pytest.raises(yaml.YAMLError, match="line 3"):
+            list(stream)
+
+    def test_tab_indentation(self):
+        stream = yaml.parse("server:\n\thost: web\n")
+        with pytest.raises(yaml.TabIndentationError) as exc:
+            list(stream)
+        assert (exc.value.line, exc.value.column) == (2, 1)
+
+
+class TestSafeLoadMarked:
+    """Test safe_load_marked()"""
+
+    def test_positions(self):
+        data = yaml.safe_load_marked("server:\n  host: web\n  port: 80\n")
+        assert (data["server"].lc.line, data["server"].lc.col) == (1, 2)
+        assert (data["server"]["host"].lc.line, data["server"]["host"].lc.col) == (
+            1,
+            8,
+        )
+        # Numbers are plain; their position is on the mapping
+        assert data["server"]["port"] == 80
+        assert data["server"].lc.value("port") == (2, 8)
+
+    def test_same_data_as_safe_load(self):
+        stream = "a: [1, {b: c}]\nd: yes\n"
+        assert yaml.safe_load_marked(stream) == yaml.safe_load(stream)
+
+    def test_unsafe_tags_rejected(self):
+        with pytest.raises(yaml.YAMLError):
+            yaml.safe_load_marked("!!python/object/apply:os.system ['x']")
+
+
+class TestRoundTrip:
+    """Test load_rt() and dump_rt()"""
+
+    SOURCE = (
+        "# deployment\n"
+        "name: web  # service name\n"
+        "version: '1.2'\n"
+        "\n"
+        "spec:\n"
+        "    replicas: 2\n"
+        "    ports: [80, 443]\n"
+        "    env:\n"
+        "        - name: A\n"
+        "          value: x\n"
+    )
+
+    def test_unchanged(self):
+        data = yaml.load_rt(self.SOURCE)
+        assert data == yaml.safe_load(self.SOURCE)
+        assert yaml.dump_rt(data) == self.SOURCE
+
+    def test_edits_keep_formatting(self):
+        from rustyyaml.roundtrip import CommentedMap
+
+        data = yaml.load_rt(self.SOURCE)
+        assert isinstance(data, CommentedMap)
+        data["version"] = "1.3"
+        data["spec"]["replicas"] = 3
+        data["spec"]["ports"].append(8080)
+        data["spec"]["env"].append({"name": "B", "value": "y"})
+        del data["name"]
+        data["owner"] = "ops"
+        assert yaml.dump_rt(data) == (
+            "# deployment\n"
+            "version: '1.3'\n"
+            "\n"
+            "spec:\n"
+            "    replicas: 3\n"
+            "    ports: [80, 443, 8080]\n"
+            "    env:\n"
+            "        - name: A\n"
+            "          value: x\n"
+            "        - name: B\n"
+            "          value: y\n"
+            "owner: ops\n"
+        )
+
+    def test_sequence_document(self, tmp_path):
+        data = yaml.load_rt("- a  # first\n- b\n")
+        data[1] = "c"
+        path = tmp_path / "out.yaml"
+        with open(path, "w") as f:
+            assert yaml.dump_rt(data, f) is None
+        assert path.read_text() == "- a  # first\n- c\n"
+
+    def test_styles(self):
+        """styles maps the JSON pointer of each scalar to how it was written"""
+        data = yaml.load_rt(self.SOURCE)
+        assert data.styles["/name"] == "plain"
+        assert data.styles["/version"] == "single-quoted"
+        assert data.styles["/spec/ports/1"] == "plain"
+        assert "/spec" not in data.styles
+        assert yaml.load_rt("- \"a/b\": |\n    x\n").styles == {"/0/a~1b": "literal"}
+
+    def test_plain_data(self):
+        """Data not from load_rt is dumped in insertion order"""
+        assert yaml.dump_rt({"b": 1, "a": 2}) == "b: 1\na: 2\n"
+        assert yaml.load_rt("42") == 42
+
+    def test_options(self):
+        data = yaml.load_rt("on: yes\n", pyyaml_compat=True)
+        data[True] = False
+        assert yaml.dump_rt(data) == "on: false\n"
+        with pytest.raises(yaml.YAMLError, match="marked"):
+            yaml.load_rt("a: 1", marked=True)
+
+    def test_unsafe_tags_rejected(self):
+        with pytest.raises(yaml.YAMLError):
+            yaml.load_rt("!!python/object/apply:os.system ['x']")
+
+
+class TestUpdateFile:
+    """Test update_file()"""
+
+    def test_bump_version(self, tmp_path):
+        chart = tmp_path / "Chart.yaml"
+        chart.write_text(
+            "# web chart\n"
+            "name: web\n"
+            "version: 1.2.3  # bump on release\n"
+            "dependencies:\n"
+            "    - name: redis\n"
+            "      version: '17.0'\n"
+        )
+        yaml.update_file(chart, "version", "1.2.4")
+        yaml.update_file(str(chart), "dependencies[0].version", "17.1")
+        assert chart.read_text() == (
+            "# web chart\n"
+            "name: web\n"
+            "version: 1.2.4  # bump on release\n"
+            "dependencies:\n"
+            "    - name: redis\n"
+            "      version: '17.1'\n"
+        )
+
+    def test_new_key(self, tmp_path):
+        config = tmp_path / "config.yaml"
+        config.write_text("spec:\n  replicas: 2  # min\n")
+        yaml.update_file(config, "spec.paused", True)
+        assert config.read_text() == "spec:\n  replicas: 2  # min\n  paused: true\n"
+
+    def test_errors(self, tmp_path):
+        config = tmp_path / "config.yaml"
+        config.write_text("items: [1, 2]\n")
+        for key_path in ("missing.key", "items[2]", "items[*]", "items..x"):
+            with pytest.raises(yaml.YAMLError):
+                yaml.update_file(config, key_path, 0)
+        with pytest.raises(yaml.YAMLError):
+            yaml.update_file(tmp_path / "missing.yaml", "a", 0)
+        assert config.read_text() == "items: [1, 2]\n"
+
+
+class TestCompose:
+    """Test compose() node graphs"""
+
+    def test_nodes(self):
+        from rustyyaml import nodes
+
+        root = yaml.compose("a: &x [1, 'two']\nb: *x\n")
+        assert isinstance(root, nodes.MappingNode)
+        assert root.tag == "tag:yaml.org,2002:map" and root.flow_style is False
+        (a, sequence), (b, alias) = root.value
+        assert a.value == "a" and a.style is None
+        assert sequence.anchor == "x" and sequence.flow_style is True
+        assert alias is sequence
+        one, two = sequence.value
+        assert one.tag == "tag:yaml.org,2002:int"
+        assert two.tag == "tag:yaml.org,2002:str" and two.style == "'"
+        assert (two.start_mark.line, two.start_mark.column) == (0, 10)
+
+    def test_tags_resolved_by_schema(self):
+        assert yaml.compose("yes").tag == "tag:yaml.org,2002:str"
+        assert yaml.compose("yes", pyyaml_compat=True).tag == "tag:yaml.org,2002:bool"
+        assert yaml.compose("!Ref x").tag == "!Ref"
+
+    def test_unsafe_tags_are_nodes(self):
+        """Nodes aren't constructed, so any tag is kept as written"""
+        node = yaml.compose("!!python/object:os.system x")
+        assert node.tag == "tag:yaml.org,2002:python/object:os.system"
+
+    def test_recursive_alias(self):
+        root = yaml.compose("&a [*a]")
+        assert root.value[0] is root
+
+    def test_documents(self):
+        assert yaml.compose("") is None
+        roots = yaml.compose_all("a: 1\n---\n- b\n")
+        assert [type(node).__name__ for node in roots] == [
+            "MappingNode",
+            "SequenceNode",
+        ]
+        with pytest.raises(yaml.YAMLError):
+            yaml.compose("a: 1\n---\nb: 2\n")
+
+    def test_errors(self):
+        with pytest.raises(yaml.YAMLError):
+            yaml.compose("a: [1\n")
+        with pytest.raises(yaml.YAMLError):
+            yaml.compose("[[[1]]]", max_depth=2)
+
+
 class TestSafety:
     """Test security features"""
 
@@ -496,6 +1549,14 @@ class TestBatchOperations:
         with pytest.raises(yaml.YAMLError):
             yaml.safe_load_many(yamls)
 
+    def test_load_many_rejects_file_options(self):
+        """Options that report on files are refused for strings"""
+        for load in (yaml.safe_load_many, yaml.unsafe_load_many):
+            with pytest.raises(yaml.YAMLError, match="on_error is only"):
+                load(["a: 1"], on_error=print)
+            with pytest.raises(yaml.YAMLError, match="file_info is only"):
+                load(["a: 1"], file_info=True)
+
     def test_load_directory(self, tmp_path):
         """Load all YAML files from directory"""
         # Create test files
@@ -530,6 +1591,2348 @@ class TestBatchOperations:
         with pytest.raises(yaml.YAMLError):
             yaml.load_directory("/nonexistent/path")
 
+    def test_filter_matcher(self):
+        """A dict matcher keeps documents containing its keys and values"""
+        docs = [
+            "kind: Deployment\nmetadata: {name: web, namespace: prod}",
+            "kind: Service\nmetadata: {name: web}",
+            "kind: Deployment\nmetadata: {name: db, namespace: dev}",
+            "- not a mapping",
+        ]
+        results = yaml.safe_load_many(docs, filter={"kind": "Deployment"})
+        assert [r["metadata"]["name"] for r in results] == ["web", "db"]
+
+        matcher = {"kind": "Deployment", "metadata": {"namespace": "prod"}}
+        results = yaml.safe_load_many(docs, filter=matcher)
+        assert [r["metadata"]["name"] for r in results] == ["web"]
+
+    def test_filter_matcher_composer_path(self):
+        """Matchers also work with options that need the composer"""
+        docs = ["enabled: yes", "enabled: no"]
+        results = yaml.safe_load_many(
+            docs, filter={"enabled": True}, pyyaml_compat=True
+        )
+        assert results == [{"enabled": True}]
+
+    def test_filter_callable(self):
+        """A callable keeps documents it returns a truthy value for"""
+        results = yaml.safe_load_many(
+            ["n: 1", "n: 2", "n: 3"], filter=lambda doc: doc["n"] % 2
+        )
+        assert results == [{"n": 1}, {"n": 3}]
+
+    def test_filter_invalid(self):
+        """Filters must be a dict or callable"""
+        with pytest.raises(yaml.YAMLError):
+            yaml.safe_load_many(["a: 1"], filter="kind")
+
+    def test_load_directory_filter(self, tmp_path):
+        """Filtered-out files are left out of the results"""
+        (tmp_path / "a.yaml").write_text("kind: Deployment\nname: a")
+        (tmp_path / "b.yaml").write_text("kind: Service\nname: b")
+
+        results = yaml.load_directory(
+            tmp_path,
+            filter={"kind": "Deployment"},
+            transform=lambda doc: doc["name"],
+        )
+        assert [name for _, name in results] == ["a"]
+
+    def test_load_directory_transform(self, tmp_path):
+        """Transform replaces each document with its return value"""
+        (tmp_path / "a.yaml").write_text("kind: Deployment\nspec: {big: data}")
+        (tmp_path / "b.yaml").write_text("kind: Service\nspec: {big: data}")
+
+        results = yaml.load_directory(tmp_path, transform=lambda doc: doc["kind"])
+        assert sorted(kind for _, kind in results) == ["Deployment", "Service"]
+
+    def test_load_directory_transform_errors(self, tmp_path):
+        """Exceptions from transform are reported per file"""
+        (tmp_path / "a.yaml").write_text("kind: Deployment")
+        (tmp_path / "b.yaml").write_text("other: 1")
+
+        with pytest.raises(yaml.YAMLError):
+            yaml.load_directory(tmp_path, transform=lambda doc: doc["kind"])
+
+        results, errors = yaml.load_directory(
+            tmp_path, transform=lambda doc: doc["kind"], collect_errors=True
+        )
+        assert [kind for _, kind in results] == ["Deployment"]
+        assert Path(errors[0].path).name == "b.yaml"
+
+    def test_load_directory_identical_files(self, tmp_path):
+        """Copies of a file each get their own result (and error)"""
+        for name in ["a", "b", "c"]:
+            (tmp_path / f"{name}.yaml").write_text("defaults: {retries: 3}")
+        (tmp_path / "x.yaml").write_text("bad: [")
+        (tmp_path / "y.yaml").write_text("bad: [")
+
+        results, errors = yaml.load_directory(tmp_path, collect_errors=True)
+        assert [data for _, data in results] == [{"defaults": {"retries": 3}}] * 3
+        results[0][1]["defaults"]["retries"] = 0
+        assert results[1][1]["defaults"]["retries"] == 3
+        assert sorted(Path(error.path).name for error in errors) == ["x.yaml", "y.yaml"]
+
+    def test_load_directory_file_info(self, tmp_path):
+        """UTF-16 files load, and file_info reports how each file is stored"""
+        (tmp_path / "a.yaml").write_text("name: café", encoding="utf-8")
+        (tmp_path / "b.yaml").write_text("name: café", encoding="utf-16")
+        (tmp_path / "c.yaml").write_bytes("name: café".encode("utf-16-be"))
+
+        results = sorted(yaml.load_directory(tmp_path, file_info=True))
+        assert [data for _, data, _ in results] == [{"name": "café"}] * 3
+        infos = [info for _, _, info in results]
+        assert [(info["encoding"], info["bom"]) for info in infos] == [
+            ("utf-8", False),
+            ("utf-16-le", True),
+            ("utf-16-be", False),
+        ]
+        assert [info["size"] for info in infos] == [11, 22, 20]
+        assert all(info["parse_time"] >= 0 for info in infos)
+
+        assert len(yaml.load_directory(tmp_path)[0]) == 2
+        with pytest.raises(yaml.YAMLError, match="file_info"):
+            yaml.safe_load("a: 1", file_info=True)
+
+    def test_load_directory_on_error(self, tmp_path):
+        """on_error hears about each failed file; the rest still load"""
+        (tmp_path / "a.yaml").write_text("a: 1")
+        (tmp_path / "b.yaml").write_text("b: [")
+        (tmp_path / "c.yaml").write_text("b: [")
+        failed = []
+
+        def on_error(path, error):
+            line = getattr(error, "line", None)
+            failed.append((Path(path).name, line, Path(error.path).name))
+
+        results = yaml.load_directory(tmp_path, on_error=on_error)
+        assert [data for _, data in results] == [{"a": 1}]
+        assert sorted(failed) == [("b.yaml", 2, "b.yaml"), ("c.yaml", 2, "c.yaml")]
+
+        # Errors raised by transform are reported too
+        failed.clear()
+        results, errors = yaml.load_directory(
+            tmp_path,
+            transform=lambda doc: doc["missing"],
+            on_error=on_error,
+            collect_errors=True,
+        )
+        assert results == []
+        assert len(errors) == len(failed) == 3
+
+    def test_load_directory_on_error_raises(self, tmp_path):
+        """An exception raised by on_error stops the load"""
+        (tmp_path / "bad.yaml").write_text("b: [")
+
+        def on_error(path, error):
+            raise RuntimeError("stop")
+
+        with pytest.raises(yaml.YAMLError, match="stop"):
+            yaml.load_directory(tmp_path, on_error=on_error)
+        with pytest.raises(yaml.YAMLError, match="on_error"):
+            yaml.safe_load("a: 1", on_error=print)
+
+    def test_load_directory_encoding(self, tmp_path):
+        """encoding= reads legacy 8-bit files"""
+        (tmp_path / "a.yaml").write_text("name: café", encoding="utf-8")
+        (tmp_path / "b.yaml").write_bytes("name: café €".encode("cp1252"))
+
+        with pytest.raises(yaml.YAMLError, match="invalid utf-8"):
+            yaml.load_directory(tmp_path)
+        results = sorted(yaml.load_directory(tmp_path, encoding="auto", file_info=True))
+        assert [data["name"] for _, data, _ in results] == ["café", "café €"]
+        assert [info["encoding"] for _, _, info in results] == ["utf-8", "cp1252"]
+
+        (tmp_path / "c.txt").write_bytes(b"name: caf\xe9")
+        loader = yaml.Loader(encoding="latin-1")
+        assert loader.load_file(tmp_path / "c.txt") == {"name": "café"}
+        with pytest.raises(yaml.YAMLError, match="encoding must be"):
+            yaml.load_directory(tmp_path, encoding="ebcdic")
+
+    def test_interrupt(self):
+        """Ctrl-C stops a batch load without waiting for every document"""
+        import _thread
+        import threading
+        import time
+
+        doc = "".join(f"key{i}: [a, {{x: {i}}}]\n" for i in range(5000))
+        finished = False
+        threading.Timer(0.05, _thread.interrupt_main).start()
+        start = time.monotonic()
+        with pytest.raises(KeyboardInterrupt):
+            yaml.safe_load_many([doc] * 2000)
+            finished = True
+        assert not finished
+        assert time.monotonic() - start < 5
+
+    def test_cancellation_token(self, tmp_path):
+        """A token cancels loads from another thread"""
+        import threading
+        from concurrent.futures import CancelledError
+
+        token = yaml.CancellationToken()
+        assert not token.cancelled
+        doc = "".join(f"key{i}: [a, {{x: {i}}}]\n" for i in range(5000))
+        threading.Timer(0.05, token.cancel).start()
+        with pytest.raises(CancelledError):
+            yaml.safe_load_many([doc] * 2000, cancel=token)
+        assert token.cancelled
+        assert repr(token) == "CancellationToken(cancelled=True)"
+
+        (tmp_path / "a.yaml").write_text("a: 1")
+        with pytest.raises(CancelledError):
+            yaml.load_directory(tmp_path, cancel=token)
+        assert yaml.load_directory(tmp_path, cancel=yaml.CancellationToken())
+        with pytest.raises(yaml.YAMLError):
+            yaml.safe_load_many(["a: 1"], cancel="stop")
+
+    def test_load_object_store(self, tmp_path):
+        """Objects under a prefix load like a directory"""
+        (tmp_path / "b.yaml").write_text("kind: Service")
+        (tmp_path / "a.yml").write_text("kind: Deployment")
+        (tmp_path / "notes.txt").write_text("not yaml")
+        (tmp_path / "nested").mkdir()
+        (tmp_path / "nested" / "c.yaml").write_text("kind: ConfigMap")
+        url = tmp_path.as_uri() + "/"
+
+        results = yaml.load_object_store(url)
+        assert [Path(name).name for name, _ in results] == ["a.yml", "b.yaml"]
+        assert results[0] == (url + "a.yml", {"kind": "Deployment"})
+        kinds = yaml.load_object_store(
+            url, recursive=True, transform=lambda doc: doc["kind"], max_concurrency=1
+        )
+        assert [kind for _, kind in kinds] == ["Deployment", "Service", "ConfigMap"]
+        assert yaml.load_object_store(url, filter={"kind": "Service"})[0][1] == {
+            "kind": "Service"
+        }
+
+    def test_load_object_store_errors(self, tmp_path):
+        """Bad URLs and malformed objects raise YAMLError"""
+        (tmp_path / "bad.yaml").write_text("key: [unclosed")
+        with pytest.raises(yaml.YAMLError):
+            yaml.load_object_store(tmp_path.as_uri())
+        results, errors = yaml.load_object_store(tmp_path.as_uri(), collect_errors=True)
+        assert results == []
+        assert errors[0].path.endswith("bad.yaml")
+        with pytest.raises(yaml.YAMLError):
+            yaml.load_object_store("ftp://example.com/configs/")
+
+    def test_load_git(self, tmp_path):
+        """Files load as of any revision without a checkout"""
+        import subprocess
+
+        def git(*args):
+            subprocess.run(
+                ["git", "-c", "user.name=t", "-c", "user.email=t@example.com", *args],
+                cwd=tmp_path,
+                check=True,
+                capture_output=True,
+            )
+
+        (tmp_path / "deploy" / "nested").mkdir(parents=True)
+        (tmp_path / "deploy" / "web.yaml").write_text("replicas: 2")
+        (tmp_path / "deploy" / "nested" / "db.yml").write_text("replicas: 1")
+        git("init", "-q", "-b", "main")
+        git("add", ".")
+        git("commit", "-q", "-m", "first")
+        git("tag", "v1")
+        (tmp_path / "deploy" / "web.yaml").write_text("replicas: 5")
+        git("commit", "-q", "-am", "scale up")
+        (tmp_path / "deploy" / "web.yaml").write_text("replicas: [uncommitted")
+
+        assert yaml.load_git(tmp_path, path="deploy/") == [
+            ("deploy/web.yaml", {"replicas": 5})
+        ]
+        assert yaml.load_git(tmp_path, ref="v1", path="deploy", recursive=True) == [
+            ("deploy/nested/db.yml", {"replicas": 1}),
+            ("deploy/web.yaml", {"replicas": 2}),
+        ]
+        replicas = yaml.load_git(
+            tmp_path.as_uri(), ref="HEAD~1", recursive=True, transform=len
+        )
+        assert [path for path, _ in replicas] == [
+            "deploy/nested/db.yml",
+            "deploy/web.yaml",
+        ]
+        with pytest.raises(yaml.YAMLError, match="^Unknown revision") as exc:
+            yaml.load_git(tmp_path, ref="no-such-ref")
+        assert exc.value.line is None
+        with pytest.raises(yaml.YAMLError, match="does not exist"):
+            yaml.load_git(tmp_path, path="missing/")
+        with pytest.raises(yaml.YAMLError, match="^Failed to open repository"):
+            yaml.load_git(tmp_path / "not-a-repo")
+
+
+class TestGrep:
+    """Test searching a directory for a key path"""
+
+    def test_wildcard_hits(self, tmp_path):
+        """Every match is reported with its file and concrete path"""
+        (tmp_path / "a.yaml").write_text(
+            "spec:\n  containers:\n    - image: nginx\n    - image: redis\n"
+        )
+        (tmp_path / "b.yaml").write_text("spec:\n  containers: []\n")
+
+        hits = yaml.grep(tmp_path, "spec.containers[*].image")
+        assert [(Path(f).name, p, v) for f, p, v in hits] == [
+            ("a.yaml", "spec.containers[0].image", "nginx"),
+            ("a.yaml", "spec.containers[1].image", "redis"),
+        ]
+
+    def test_multi_document_files(self, tmp_path):
+        """Every document of a file is searched"""
+        (tmp_path / "bundle.yaml").write_text("kind: A\n---\nkind: B\n")
+        assert [v for _, _, v in yaml.grep(tmp_path, "kind")] == ["A", "B"]
+
+    def test_values_are_converted(self, tmp_path):
+        """Matched values can be whole subtrees"""
+        (tmp_path / "a.yaml").write_text("metadata: {labels: {app: web}}")
+        hits = yaml.grep(tmp_path, "metadata.labels")
+        assert hits[0][2] == {"app": "web"}
+
+    def test_recursive(self, tmp_path):
+        """Subdirectories are only searched with recursive=True"""
+        (tmp_path / "sub").mkdir()
+        (tmp_path / "sub" / "a.yaml").write_text("key: 1")
+        assert yaml.grep(tmp_path, "key") == []
+        assert len(yaml.grep(tmp_path, "key", recursive=True)) == 1
+
+    def test_invalid_key_path(self, tmp_path):
+        """Malformed paths are rejected"""
+        with pytest.raises(yaml.YAMLError):
+            yaml.grep(tmp_path, "a[x]")
+
+
+class TestIndexKeys:
+    """Test the key path index"""
+
+    def test_definitions_with_positions(self, tmp_path):
+        """Each key path lists every file and position defining it"""
+        (tmp_path / "base.yaml").write_text("spec:\n  replicas: 1\n")
+        (tmp_path / "prod.yaml").write_text("kind: X\nspec:\n  replicas: 5\n")
+
+        index = yaml.index_keys(tmp_path)
+        replicas = index["spec.replicas"]
+        assert [(Path(f).name, line, col) for f, line, col in replicas] == [
+            ("base.yaml", 2, 3),
+            ("prod.yaml", 3, 3),
+        ]
+        assert len(index["kind"]) == 1
+
+    def test_list_items(self, tmp_path):
+        """Keys inside lists get [N] in their path"""
+        (tmp_path / "a.yaml").write_text("ports:\n  - port: 80\n  - port: 443\n")
+        index = yaml.index_keys(tmp_path)
+        assert set(index) == {"ports", "ports[0].port", "ports[1].port"}
+
+    def test_malformed_file(self, tmp_path):
+        """Malformed files raise"""
+        (tmp_path / "bad.yaml").write_text("a: [")
+        with pytest.raises(yaml.YAMLError):
+            yaml.index_keys(tmp_path)
+
+
+class TestWalk:
+    """Test walk()"""
+
+    def test_text_and_options(self):
+        """YAML text is loaded; depth and leaves limit what is listed"""
+        source = "name: web\nspec:\n  ports: [80, 443]\n"
+        assert yaml.walk(source) == [
+            ("name", "web"),
+            ("spec", {"ports": [80, 443]}),
+            ("spec.ports", [80, 443]),
+            ("spec.ports[0]", 80),
+            ("spec.ports[1]", 443),
+        ]
+        assert [p for p, _ in yaml.walk(source, leaves_only=True)] == [
+            "name",
+            "spec.ports[0]",
+            "spec.ports[1]",
+        ]
+        assert yaml.walk(source, max_depth=1, leaves_only=True) == [
+            ("name", "web"),
+            ("spec", {"ports": [80, 443]}),
+        ]
+
+    def test_loaded_data(self):
+        """Loaded data is walked in place, whatever its leaf types"""
+        stamp = datetime.datetime(2024, 1, 1)
+        doc = {"items": [{"at": stamp}], 5: "five"}
+        found = dict(yaml.walk(doc, leaves_only=True))
+        assert found == {"items[0].at": stamp, "5": "five"}
+        assert found["items[0].at"] is stamp
+        namespace = yaml.safe_load("a: {b: 1}", as_namespace=True)
+        assert yaml.walk(namespace, leaves_only=True) == [("a.b", 1)]
+
+    def test_malformed(self):
+        """Malformed YAML raises"""
+        with pytest.raises(yaml.YAMLError):
+            yaml.walk("a: [")
+
+
+class TestMapScalars:
+    """Test map_scalars()"""
+
+    def test_text_keeps_formatting(self):
+        """Only changed scalars are rewritten"""
+        source = "# app\nname: web  # public\nports: [80, 443]\nenabled: true\n"
+        assert yaml.map_scalars(
+            source, lambda v: v + 8000 if type(v) is int else v
+        ) == ("# app\nname: web  # public\nports: [8080, 8443]\nenabled: true\n")
+        mapped = yaml.map_scalars(source, lambda v: f"{v}, v2", paths="name")
+        assert mapped.startswith('# app\nname: "web, v2"  # public\n')
+        assert yaml.safe_load(mapped)["name"] == "web, v2"
+
+    def test_loaded_data(self):
+        """Loaded data comes back as a copy"""
+        doc = {"env": [{"name": "TOKEN", "value": "abc"}], "replicas": 2}
+        mapped = yaml.map_scalars(doc, str.upper, paths=["env[*].value"])
+        assert mapped == {"env": [{"name": "TOKEN", "value": "ABC"}], "replicas": 2}
+        assert doc["env"][0]["value"] == "abc"
+
+    def test_errors(self):
+        """Errors from fn, bad paths and bad YAML surface as YAMLError"""
+        with pytest.raises(yaml.YAMLError, match="division by zero"):
+            yaml.map_scalars("a: 1", lambda v: v / 0)
+        with pytest.raises(yaml.YAMLError):
+            yaml.map_scalars("a: 1", str, paths="a[")
+        with pytest.raises(yaml.YAMLError):
+            yaml.map_scalars("a: [", str)
+
+
+class TestExplode:
+    """Test splitting a bundle into one file per document"""
+
+    BUNDLE = (
+        "# web\n"
+        "kind: Deployment\n"
+        "metadata: {name: web}\n"
+        "---\n"
+        "kind: Service\n"
+        "metadata:\n"
+        "  name: 'web'  # quoted\n"
+        "---\n"
+    )
+
+    def test_writes_verbatim_documents(self, tmp_path):
+        """Each document keeps its original text"""
+        bundle = tmp_path / "bundle.yaml"
+        bundle.write_text(self.BUNDLE)
+
+        written = yaml.explode(bundle, tmp_path / "out")
+        assert [Path(p).name for p in written] == [
+            "Deployment-web.yaml",
+            "Service-web.yaml",
+        ]
+        first, second = (Path(p).read_text() for p in written)
+        assert first == "# web\nkind: Deployment\nmetadata: {name: web}\n"
+        assert "name: 'web'  # quoted" in second
+        assert yaml.safe_load(second)["kind"] == "Service"
+
+    def test_custom_template(self, tmp_path):
+        """Templates can use key paths and the document index"""
+        bundle = tmp_path / "bundle.yaml"
+        bundle.write_text(self.BUNDLE)
+
+        written = yaml.explode(bundle, tmp_path, name_template="{index}-{kind}.yml")
+        assert [Path(p).name for p in written] == ["0-Deployment.yml", "1-Service.yml"]
+
+    def test_name_collision(self, tmp_path):
+        """Nothing is written when two documents share a name"""
+        bundle = tmp_path / "bundle.yaml"
+        bundle.write_text(self.BUNDLE)
+
+        with pytest.raises(yaml.YAMLError):
+            yaml.explode(bundle, tmp_path / "out", name_template="{name}.yaml")
+        assert not (tmp_path / "out").exists()
+
+    def test_missing_field(self, tmp_path):
+        """A document without a template field is an error"""
+        bundle = tmp_path / "bundle.yaml"
+        bundle.write_text("a: 1\n")
+        with pytest.raises(yaml.YAMLError):
+            yaml.explode(bundle, tmp_path / "out")
+
+
+class TestBundle:
+    """Test concatenating files into a multi-document bundle"""
+
+    def test_directory_bundle(self, tmp_path):
+        """Files are joined in name order with --- between documents"""
+        src = tmp_path / "src"
+        src.mkdir()
+        (src / "b.yaml").write_text("# service\nkind: Service")
+        (src / "a.yaml").write_text("kind: Deployment\n")
+        out = tmp_path / "bundle.yaml"
+
+        assert yaml.bundle(src, out) == 2
+        assert out.read_text() == (
+            "---\nkind: Deployment\n---\n# service\nkind: Service\n"
+        )
+        assert yaml.load_all(out.read_text()) == [
+            {"kind": "Deployment"},
+            {"kind": "Service"},
+        ]
+
+    def test_file_list_and_multi_document_inputs(self, tmp_path):
+        """Explicit paths keep their order; bundles can be re-bundled"""
+        (tmp_path / "one.yaml").write_text("a: 1\n---\nb: 2\n---\n")
+        (tmp_path / "two.yaml").write_text("c: 3\n")
+        out = tmp_path / "out.yml"
+
+        count = yaml.bundle([tmp_path / "two.yaml", tmp_path / "one.yaml"], out)
+        assert count == 3
+        assert yaml.load_all(out.read_text()) == [{"c": 3}, {"a": 1}, {"b": 2}]
+
+    def test_malformed_input(self, tmp_path):
+        """A malformed file fails before anything is written"""
+        (tmp_path / "good.yaml").write_text("a: 1")
+        (tmp_path / "bad.yaml").write_text("a: [")
+        out = tmp_path / "out" / "bundle.yaml"
+
+        with pytest.raises(yaml.YAMLError) as exc_info:
+            yaml.bundle(tmp_path, out)
+        assert "bad.yaml" in str(exc_info.value)
+        assert not out.exists()
+
+    def test_write_failure(self, tmp_path):
+        """An output that can't be written is an I/O error, not a parse error"""
+        (tmp_path / "a.yaml").write_text("a: 1\n")
+        (tmp_path / "file").write_text("")
+        out = tmp_path / "file" / "bundle.yaml"
+
+        with pytest.raises(yaml.YAMLError, match="^Failed to write ") as exc_info:
+            yaml.bundle([tmp_path / "a.yaml"], out)
+        assert exc_info.value.line is None
+
+    def test_roundtrip_with_explode(self, tmp_path):
+        """explode then bundle gives back the same documents"""
+        original = "kind: A\nmetadata: {name: x}\n---\nkind: B\nmetadata: {name: y}\n"
+        (tmp_path / "in.yaml").write_text(original)
+        yaml.explode(tmp_path / "in.yaml", tmp_path / "parts")
+        yaml.bundle(tmp_path / "parts", tmp_path / "out.yaml")
+        assert yaml.load_all((tmp_path / "out.yaml").read_text()) == yaml.load_all(
+            original
+        )
+
+
+class TestTimestamps:
+    """Test date/datetime resolution options"""
+
+    def test_strings_by_default(self):
+        """Timestamps stay strings unless requested"""
+        assert yaml.safe_load("d: 2024-01-01") == {"d": "2024-01-01"}
+
+    def test_date_and_datetime(self):
+        """Bare dates become date, full timestamps become datetime"""
+        result = yaml.safe_load(
+            "d: 2024-01-01\nt: 2024-01-01T10:00:00Z", timestamps=True
+        )
+        assert type(result["d"]) is datetime.date
+        assert result["d"] == datetime.date(2024, 1, 1)
+        assert type(result["t"]) is datetime.datetime
+        assert result["t"] == datetime.datetime(
+            2024, 1, 1, 10, tzinfo=datetime.timezone.utc
+        )
+
+    def test_dates_as_datetime(self):
+        """dates_as_datetime gives every timestamp the same type"""
+        result = yaml.safe_load(
+            "d: 2024-01-01\nt: 2024-01-01 10:00:00", dates_as_datetime=True
+        )
+        assert result["d"] == datetime.datetime(2024, 1, 1)
+        assert type(result["d"]) is datetime.datetime
+        assert result["t"] == datetime.datetime(2024, 1, 1, 10)
+
+    def test_quoted_timestamp_stays_string(self):
+        """Quoting opts out of resolution"""
+        assert yaml.safe_load("d: '2024-01-01'", timestamps=True) == {
+            "d": "2024-01-01"
+        }
+
+    def test_yaml12_rules_otherwise(self):
+        """timestamps does not switch on YAML 1.1 booleans"""
+        assert yaml.safe_load("a: yes", timestamps=True) == {"a": "yes"}
+
+
+class TestDurations:
+    """Test the opt-in duration resolver"""
+
+    def test_strings_by_default(self):
+        """Durations stay strings unless requested"""
+        assert yaml.safe_load("t: 5m30s") == {"t": "5m30s"}
+
+    def test_iso_and_go_durations(self):
+        """Both notations become timedelta"""
+        result = yaml.safe_load(
+            "iso: PT30S\nmixed: P1DT2H\ngo: 5m30s\nfrac: 1.5h\nms: 300ms",
+            durations=True,
+        )
+        assert result == {
+            "iso": datetime.timedelta(seconds=30),
+            "mixed": datetime.timedelta(days=1, hours=2),
+            "go": datetime.timedelta(minutes=5, seconds=30),
+            "frac": datetime.timedelta(hours=1, minutes=30),
+            "ms": datetime.timedelta(milliseconds=300),
+        }
+
+    def test_negative_duration(self):
+        """A leading minus sign is supported"""
+        assert yaml.safe_load("-10s", durations=True) == datetime.timedelta(seconds=-10)
+
+    def test_non_durations_untouched(self):
+        """Plain numbers, quoted values and calendar units are left alone"""
+        result = yaml.safe_load("[5, '5m', P1Y, 10x]", durations=True)
+        assert result == [5, "5m", "P1Y", "10x"]
+
+
+class TestUUIDs:
+    """Test the opt-in UUID resolver"""
+
+    ID = "123e4567-e89b-12d3-a456-426614174000"
+
+    def test_strings_by_default(self):
+        """UUIDs stay strings unless requested"""
+        assert yaml.safe_load(f"id: {self.ID}") == {"id": self.ID}
+
+    def test_uuid_resolution(self):
+        """UUID-shaped plain scalars become uuid.UUID"""
+        result = yaml.safe_load(f"id: {self.ID}\nupper: {self.ID.upper()}", uuids=True)
+        assert result == {"id": uuid.UUID(self.ID), "upper": uuid.UUID(self.ID)}
+
+    def test_quoted_and_malformed_untouched(self):
+        """Quoted values and near-misses stay strings"""
+        result = yaml.safe_load(f"['{self.ID}', {self.ID[:-1]}]", uuids=True)
+        assert result == [self.ID, self.ID[:-1]]
+
+
+class TestIPAddresses:
+    """Test the opt-in IP address resolver"""
+
+    def test_strings_by_default(self):
+        """Addresses stay strings unless requested"""
+        assert yaml.safe_load("host: 10.0.0.1") == {"host": "10.0.0.1"}
+
+    def test_addresses_and_networks(self):
+        """Addresses, networks and interfaces map to ipaddress types"""
+        result = yaml.safe_load(
+            "host: 10.0.0.1\nv6: ::1\nnet: 10.0.0.0/8\n"
+            "iface: 192.168.1.5/24\nv6net: 2001:db8::/32",
+            ip_addresses=True,
+        )
+        assert result == {
+            "host": ipaddress.ip_address("10.0.0.1"),
+            "v6": ipaddress.ip_address("::1"),
+            "net": ipaddress.ip_network("10.0.0.0/8"),
+            "iface": ipaddress.ip_interface("192.168.1.5/24"),
+            "v6net": ipaddress.ip_network("2001:db8::/32"),
+        }
+
+    def test_non_addresses_untouched(self):
+        """Numbers, versions and quoted addresses are left alone"""
+        result = yaml.safe_load(
+            "[1.5, 1.2.3, '10.0.0.1', 10.0.0.0/40]", ip_addresses=True
+        )
+        assert result == [1.5, "1.2.3", "10.0.0.1", "10.0.0.0/40"]
+
+
+class TestDrift:
+    """Test comparing a directory against a baseline"""
+
+    def test_against_directory(self, tmp_path):
+        """Files pair by name; added and removed files are reported"""
+        live, base = tmp_path / "live", tmp_path / "base"
+        live.mkdir()
+        base.mkdir()
+        (base / "app.yaml").write_text("spec: {replicas: 2, paused: true}\n")
+        (live / "app.yaml").write_text("spec: {replicas: 3}\n")
+        (base / "same.yaml").write_text("a: 1\n")
+        (live / "same.yaml").write_text("a: 1.0\n")
+        (base / "old.yaml").write_text("b: 2\n")
+        (live / "new.yaml").write_text("c: 3\n")
+
+        reports = {r["file"]: r for r in yaml.drift(live, base)}
+        assert sorted(reports) == ["app.yaml", "new.yaml", "old.yaml", "same.yaml"]
+        assert reports["app.yaml"]["status"] == "changed"
+        assert reports["app.yaml"]["changes"] == [
+            {
+                "document": 0,
+                "op": "changed",
+                "path": "spec.replicas",
+                "old": 2,
+                "new": 3,
+            },
+            {"document": 0, "op": "removed", "path": "spec.paused", "old": True},
+        ]
+        assert reports["same.yaml"] == {
+            "file": "same.yaml",
+            "status": "unchanged",
+            "changes": [],
+        }
+        assert reports["new.yaml"]["status"] == "added"
+        assert reports["new.yaml"]["changes"][0]["new"] == {"c": 3}
+        assert reports["old.yaml"]["status"] == "removed"
+
+    def test_against_bundle(self, tmp_path):
+        """Documents pair with bundle documents by kind and name"""
+        live = tmp_path / "live"
+        live.mkdir()
+        (live / "web.yaml").write_text(
+            "kind: Service\nmetadata: {name: web}\nspec: {port: 8080}\n"
+            "---\nkind: Deployment\nmetadata: {name: web}\n"
+        )
+        baseline = tmp_path / "release.yaml"
+        baseline.write_text(
+            "kind: Deployment\nmetadata: {name: web}\n"
+            "---\nkind: Service\nmetadata: {name: web}\nspec: {port: 80}\n"
+            "---\nkind: ConfigMap\nmetadata: {name: cfg}\n"
+        )
+
+        web, gone = yaml.drift(live, baseline)
+        assert web["file"] == "web.yaml"
+        (change,) = web["changes"]
+        assert (change["op"], change["path"]) == ("changed", "spec.port")
+        assert (change["old"], change["new"]) == (80, 8080)
+        assert gone["file"] == str(baseline)
+        assert gone["status"] == "removed"
+        assert [c["document"] for c in gone["changes"]] == [2]
+
+    def test_missing_baseline(self, tmp_path):
+        """A baseline that does not exist raises YAMLError"""
+        with pytest.raises(yaml.YAMLError):
+            yaml.drift(tmp_path, tmp_path / "missing")
+
+
+class TestToCsv:
+    """Test flattening configs into CSV"""
+
+    def test_directory(self, tmp_path):
+        """Rows follow file name and document order"""
+        import csv
+        import io
+
+        (tmp_path / "b.yaml").write_text("kind: Service\nports: [80, 443]\n")
+        (tmp_path / "a.yaml").write_text("x: 1\n---\nmsg: 'a, b'\nnone: null\n")
+        rows = list(csv.reader(io.StringIO(yaml.to_csv(tmp_path))))
+        assert rows[0] == ["file", "document", "key_path", "value", "type"]
+        a, b = str(tmp_path / "a.yaml"), str(tmp_path / "b.yaml")
+        assert rows[1:] == [
+            [a, "0", "x", "1", "int"],
+            [a, "1", "msg", "a, b", "str"],
+            [a, "1", "none", "", "null"],
+            [b, "0", "kind", "Service", "str"],
+            [b, "0", "ports[0]", "80", "int"],
+            [b, "0", "ports[1]", "443", "int"],
+        ]
+
+    def test_documents_and_out_path(self, tmp_path):
+        """Loaded documents can be exported and written to a file"""
+        out = tmp_path / "audit.csv"
+        docs = [{"a": {"b": True, "c": []}}, {"d": 1.5}]
+        assert yaml.to_csv(docs, out) == 3
+        assert out.read_text() == (
+            "file,document,key_path,value,type\n"
+            ",0,a.b,true,bool\n"
+            ",0,a.c,,seq\n"
+            ",1,d,1.5,float\n"
+        )
+
+    def test_errors(self, tmp_path):
+        """Malformed files and missing directories raise YAMLError"""
+        (tmp_path / "bad.yaml").write_text("a: [")
+        with pytest.raises(yaml.YAMLError):
+            yaml.to_csv(tmp_path)
+        with pytest.raises(yaml.YAMLError):
+            yaml.to_csv(tmp_path / "missing")
+
+
+class TestToDotenv:
+    """Test exporting configs as env files"""
+
+    def test_flatten(self):
+        """Nested keys join with __; values are quoted when needed"""
+        doc = yaml.safe_load(
+            "database:\n  port: 5432\n  url: postgres://db:5432/app\n"
+            "log-level: debug info\nhosts: [a, b]\ntls: true\nmotd: \"hi\\nit's me\"\n"
+        )
+        assert yaml.to_dotenv(doc) == (
+            "APP_DATABASE__PORT=5432\n"
+            "APP_DATABASE__URL=postgres://db:5432/app\n"
+            "APP_LOG_LEVEL='debug info'\n"
+            "APP_HOSTS__0=a\n"
+            "APP_HOSTS__1=b\n"
+            "APP_TLS=true\n"
+            'APP_MOTD="hi\\nit\'s me"\n'
+        )
+
+    def test_double_quoted_expansions(self):
+        """$ and backticks stay literal inside double quotes"""
+        doc = {"greeting": "it's $USER", "cmd": "don't `rm -rf`\n"}
+        text = yaml.to_dotenv(doc, prefix="")
+        assert text == (
+            'GREETING="it\'s \\$USER"\n'
+            'CMD="don\'t \\`rm -rf\\`\\n"\n'
+        )
+
+    def test_prefix(self):
+        """The prefix is configurable and may be empty"""
+        assert yaml.to_dotenv({"a": {"b": None}}, prefix="") == "A__B=\n"
+        assert yaml.to_dotenv({"a": 1}, prefix="svc") == "SVC__A=1\n"
+        with pytest.raises(yaml.YAMLError):
+            yaml.to_dotenv([1, 2])
+
+
+class TestIni:
+    """Test converting between YAML and INI"""
+
+    def test_yaml_to_ini(self):
+        """configparser reads the output"""
+        import configparser
+
+        ini = yaml.yaml_to_ini(
+            "server:\n  port: 8080\n  hosts: [a, b]\n  tls: {cert: c.pem}\n"
+        )
+        parser = configparser.ConfigParser()
+        parser.read_string(ini)
+        assert parser.sections() == ["server", "server.tls"]
+        assert parser.getint("server", "port") == 8080
+        assert parser.get("server", "hosts").split() == ["a", "b"]
+        assert parser.get("server.tls", "cert") == "c.pem"
+
+    def test_ini_to_yaml(self):
+        """Sections nest, values are typed, order and case are kept"""
+        ini = (
+            "# legacy service\n"
+            "[database]\nHost = db.local\nport = 5432\nssl = true\npassword =\n"
+            "[database.replicas]\nhosts =\n    r1\n    r2\n"
+        )
+        assert yaml.safe_load(yaml.ini_to_yaml(ini)) == {
+            "database": {
+                "Host": "db.local",
+                "port": 5432,
+                "ssl": True,
+                "password": "",
+                "replicas": {"hosts": ["r1", "r2"]},
+            }
+        }
+        data = "db: {port: '5432', items: [1, x]}\n"
+        assert yaml.safe_load(yaml.ini_to_yaml(yaml.yaml_to_ini(data))) == {
+            "db": {"port": 5432, "items": [1, "x"]}
+        }
+
+    def test_errors(self):
+        """Unrepresentable documents and malformed INI raise YAMLError"""
+        with pytest.raises(yaml.YAMLError):
+            yaml.yaml_to_ini("name: top-level scalar")
+        with pytest.raises(yaml.YAMLError) as info:
+            yaml.ini_to_yaml("[s]\nkey = 1\nkey = 2\n")
+        assert info.value.line == 3
+
+
+class TestXml:
+    """Test converting between YAML and XML"""
+
+    def test_yaml_to_xml(self):
+        """Attributes, repeated elements and escaping"""
+        import xml.etree.ElementTree as ET
+
+        text = yaml.yaml_to_xml(
+            "server:\n  '@id': web\n  host: [a, b]\n  note: 'x < y & z'\n"
+        )
+        root = ET.fromstring(text.split("\n", 1)[1])
+        assert root.tag == "server"
+        assert root.get("id") == "web"
+        assert [host.text for host in root.findall("host")] == ["a", "b"]
+        assert root.find("note").text == "x < y & z"
+        wrapped = yaml.yaml_to_xml("[1, 2]", root="ports", list_item="port")
+        assert "<ports>\n  <port>1</port>\n  <port>2</port>\n</ports>" in wrapped
+
+    def test_xml_to_yaml(self):
+        """Repeated and forced lists, typed text, custom keys"""
+        xml = (
+            '<?xml version="1.0"?>\n<!-- service -->\n<server id="web">'
+            '<port>8080</port><host>a</host><host>b</host>'
+            '<tls enabled="true">on</tls><empty/></server>'
+        )
+        assert yaml.safe_load(yaml.xml_to_yaml(xml)) == {
+            "server": {
+                "@id": "web",
+                "port": 8080,
+                "host": ["a", "b"],
+                "tls": {"@enabled": True, "#text": "on"},
+                "empty": None,
+            }
+        }
+        data = yaml.safe_load(
+            yaml.xml_to_yaml(
+                '<a x="1"><b>007</b></a>',
+                attr_prefix="_",
+                force_list=["b"],
+                typed=False,
+            )
+        )
+        assert data == {"a": {"_x": "1", "b": ["007"]}}
+
+    def test_errors(self):
+        """Malformed XML and invalid element names raise YAMLError"""
+        with pytest.raises(yaml.YAMLError) as info:
+            yaml.xml_to_yaml("<a>\n<b></a>")
+        assert info.value.line == 2
+        with pytest.raises(yaml.YAMLError):
+            yaml.yaml_to_xml("a: {'1bad': x}")
+
+
+class TestListVariables:
+    """Test finding template placeholders"""
+
+    def test_dollar_placeholders(self):
+        """${VAR} and ${VAR:-default} in values, with positions"""
+        source = "image: ${REGISTRY}/app:${TAG:-latest}\nenv:\n  - $${LITERAL}\n"
+        assert yaml.list_variables(source) == [("REGISTRY", 1, 8), ("TAG", 1, 24)]
+
+    def test_gotpl_references(self):
+        """Field references inside {{ }} actions"""
+        source = 'image: "{{ .Values.image.repo }}:{{ .Values.image.tag }}"\n'
+        assert yaml.list_variables(source, syntax="gotpl") == [
+            (".Values.image.repo", 1, 12),
+            (".Values.image.tag", 1, 37),
+        ]
+
+    def test_gotpl_template_not_valid_yaml(self):
+        """Helm control blocks are scanned as text"""
+        source = "{{- if .Values.enabled }}\nreplicas: 1\n{{- end }}\n"
+        found = yaml.list_variables(source, syntax="gotpl")
+        assert found == [(".Values.enabled", 1, 8)]
+
+    def test_errors(self):
+        """Malformed YAML and unknown syntaxes raise YAMLError"""
+        with pytest.raises(yaml.YAMLError):
+            yaml.list_variables("a: [")
+        with pytest.raises(yaml.YAMLError):
+            yaml.list_variables("a: 1", syntax="jinja")
+
+
+class TestSchemaCoercion:
+    """Test coercing string scalars to schema types"""
+
+    SCHEMA = {
+        "type": "object",
+        "properties": {
+            "port": {"type": "integer"},
+            "debug": {"type": "boolean"},
+            "version": {"type": "string"},
+            "replicas": {"type": ["integer", "null"]},
+            "ports": {"type": "array", "items": {"type": "integer"}},
+        },
+    }
+
+    def test_strings_coerced(self):
+        """Quoted scalars become the declared type; strings stay strings"""
+        source = (
+            "port: '8080'\ndebug: \"true\"\nversion: '1.10'\n"
+            "replicas: ''\nports: ['80', 443]\nother: '1'\n"
+        )
+        expected = {
+            "port": 8080,
+            "debug": True,
+            "version": "1.10",
+            "replicas": None,
+            "ports": [80, 443],
+            "other": "1",
+        }
+        assert yaml.safe_load(source, schema=self.SCHEMA) == expected
+        # The composer path coerces the same way
+        marked = yaml.safe_load(source, schema=self.SCHEMA, marked=True)
+        assert marked == expected
+
+    def test_failures_reported_with_paths(self):
+        """Every failure is listed by JSON pointer and source position"""
+        with pytest.raises(yaml.YAMLError) as exc:
+            yaml.safe_load("port: http\nports: ['80', eighty]", schema=self.SCHEMA)
+        message = str(exc.value)
+        assert "/port (line 1, column 7): expected integer, got 'http'" in message
+        assert "/ports/1 (line 2, column 15): expected integer" in message
+        assert (exc.value.line, exc.value.column) == (1, 7)
+
+    def test_batch_loaders(self):
+        """The option applies to every document"""
+        docs = yaml.load_all("port: '1'\n---\nport: '2'\n", schema=self.SCHEMA)
+        assert docs == [{"port": 1}, {"port": 2}]
+
+    def test_invalid_schema(self):
+        """Malformed schemas are rejected"""
+        with pytest.raises(ValueError):
+            yaml.safe_load("a: 1", schema={"type": "decimal"})
+
+
+class TestValidate:
+    """Test schema validation"""
+
+    SCHEMA = {
+        "type": "object",
+        "required": ["kind"],
+        "properties": {
+            "kind": {"type": "string"},
+            "spec": {
+                "type": "object",
+                "properties": {
+                    "replicas": {"type": "integer"},
+                    "ports": {"type": "array", "items": {"type": "integer"}},
+                },
+            },
+        },
+    }
+
+    def test_valid(self):
+        """A matching document passes with no findings"""
+        source = "kind: Deployment\nspec: {replicas: 3, ports: [80]}\nextra: 1\n"
+        report = yaml.validate(source, self.SCHEMA)
+        assert isinstance(report, yaml.Report)
+        assert report.passed
+        assert len(report) == 0
+
+    def test_registered_schema(self):
+        """A registered schema is referenced by name"""
+        yaml.register_schema("test-deployment", self.SCHEMA)
+        report = yaml.validate("spec:\n  replicas: three\n", "test-deployment")
+        direct = yaml.validate("spec:\n  replicas: three\n", self.SCHEMA)
+        assert [f.rule for f in report.errors] == [f.rule for f in direct.errors]
+        loaded = yaml.safe_load("port: '80'", schema="test-deployment")
+        assert loaded == {"port": "80"}
+
+    def test_unknown_schema_name(self):
+        """Names must be registered first; schemas must be valid"""
+        with pytest.raises(yaml.YAMLError, match="no schema registered"):
+            yaml.validate("a: 1", "never-registered")
+        with pytest.raises(yaml.YAMLError, match="invalid schema"):
+            yaml.register_schema("broken", {"type": "tuple"})
+
+    def test_type_and_required(self):
+        """Findings carry JSON pointer, position, severity and rule"""
+        report = yaml.validate("spec:\n  replicas: three\n", self.SCHEMA)
+        assert not report.passed
+        found = [(f.path, f.line, f.column, f.rule) for f in report.errors]
+        assert found == [("", 1, 1, "required"), ("/spec/replicas", 2, 13, "type")]
+        assert report.errors[1].message == "expected integer, got string"
+        assert report.errors[1].severity == "error"
+
+    def test_unknown_keys(self):
+        """Likely typos warn; additional_properties=False makes them errors"""
+        source = "kind: Deployment\nspec:\n  replica: 3\n  other: 1\n"
+        report = yaml.validate(source, self.SCHEMA)
+        assert report.passed
+        assert report.errors == []
+        (warning,) = report.warnings
+        assert (warning.path, warning.rule) == ("/spec/replica", "possible-typo")
+
+        report = yaml.validate(source, self.SCHEMA, additional_properties=False)
+        assert [(f.path, f.line, f.column) for f in report.errors] == [
+            ("/spec/replica", 3, 3),
+            ("/spec/other", 4, 3),
+        ]
+        assert report.errors[0].message == (
+            "unexpected key 'replica' (did you mean 'replicas'?)"
+        )
+        assert report.errors[0].rule == "additional-properties"
+
+    def test_schema_additional_properties_false(self):
+        """additionalProperties: false in the schema is always enforced"""
+        schema = {"properties": {"name": {}}, "additionalProperties": False}
+        (error,) = yaml.validate("name: a\nimage: b\n", schema).errors
+        assert (error.path, error.line, error.message) == (
+            "/image",
+            2,
+            "unexpected key 'image'",
+        )
+
+    def test_to_json(self):
+        """The report serializes for annotations and dashboards"""
+        import json
+
+        report = yaml.validate("spec: {replicas: x}", self.SCHEMA)
+        data = json.loads(report.to_json())
+        assert data["passed"] is False
+        assert data["findings"][1] == {
+            "path": "/spec/replicas",
+            "line": 1,
+            "column": 18,
+            "severity": "error",
+            "rule": "type",
+            "message": "expected integer, got string",
+        }
+
+    def test_validators(self):
+        """Callbacks keyed by key path veto matching values"""
+        source = (
+            "kind: Pod\n"
+            "spec:\n"
+            "  containers:\n"
+            "    - image: registry.example.com/app\n"
+            "    - image: docker.io/nginx\n"
+            "    - image: quay.io/x\n"
+            "  replicas: x\n"
+        )
+        seen = []
+
+        def registry(image):
+            seen.append(image)
+            if image.startswith("quay.io/"):
+                return False
+            if not image.startswith("registry.example.com/"):
+                return f"{image} is not from the internal registry"
+            return None
+
+        pattern = "spec.containers[*].image"
+        report = yaml.validate(source, {}, validators={pattern: registry})
+        assert len(seen) == 3
+        found = [(f.path, f.line, f.rule, f.message) for f in report.errors]
+        assert found == [
+            (
+                "/spec/containers/1/image",
+                5,
+                pattern,
+                "docker.io/nginx is not from the internal registry",
+            ),
+            ("/spec/containers/2/image", 6, pattern, "rejected by validator"),
+        ]
+
+        # Schema and validator findings are merged in source order
+        report = yaml.validate(
+            source, self.SCHEMA, validators={"spec.replicas": lambda v: True}
+        )
+        assert [f.rule for f in report.errors] == ["type"]
+
+    def test_errors(self):
+        """Malformed YAML and schemas raise YAMLError"""
+        with pytest.raises(yaml.YAMLError):
+            yaml.validate("a: [", self.SCHEMA)
+        with pytest.raises(yaml.YAMLError):
+            yaml.validate("a: 1", {}, validators={"a..b": lambda v: True})
+        with pytest.raises(yaml.YAMLError):
+            yaml.validate("a: 1", {"type": "decimal"})
+
+
+class TestKubernetesQuantities:
+    """Test Kubernetes resource quantity parsing"""
+
+    def test_parse_quantity(self):
+        """Binary, decimal and exponent suffixes"""
+        assert yaml.parse_quantity("128Mi") == 128 * 1024 * 1024
+        assert yaml.parse_quantity("1.5G") == 1_500_000_000
+        assert yaml.parse_quantity("2e3") == 2000
+        assert yaml.parse_quantity("100m") == 1
+        assert yaml.parse_quantity("500m", milli=True) == 500
+        assert yaml.parse_quantity("2", milli=True) == 2000
+
+    def test_parse_quantity_invalid(self):
+        """Invalid quantities raise ValueError"""
+        with pytest.raises(ValueError):
+            yaml.parse_quantity("12MB")
+
+    def test_resources_converted(self):
+        """Quantities under resources.* become integers"""
+        manifest = """
+spec:
+  containers:
+    - name: app
+      image: app:1.0
+      resources:
+        limits:
+          cpu: "1.5"
+          memory: 256Mi
+        requests:
+          cpu: 250m
+          memory: 1G
+"""
+        result = yaml.safe_load(manifest, kubernetes_quantities=True)
+        resources = result["spec"]["containers"][0]["resources"]
+        assert resources == {
+            "limits": {"cpu": 1500, "memory": 256 * 1024 * 1024},
+            "requests": {"cpu": 250, "memory": 1_000_000_000},
+        }
+        assert result["spec"]["containers"][0]["image"] == "app:1.0"
+
+    def test_untouched_by_default(self):
+        """Without the option quantities stay strings"""
+        result = yaml.safe_load("resources: {limits: {memory: 256Mi}}")
+        assert result == {"resources": {"limits": {"memory": "256Mi"}}}
+
+
+class TestRedact:
+    """Test masking values at key paths"""
+
+    SECRET = (
+        "apiVersion: v1\n"
+        "kind: Secret\n"
+        "data:  # base64\n"
+        "  user: YWRtaW4=\n"
+        "  password: aHVudGVyMg==\n"
+        "env:\n"
+        "  - {name: A, value: x}\n"
+    )
+
+    def test_redact_text(self):
+        """Only the matched values change in YAML text"""
+        redacted = yaml.redact(self.SECRET, ["data.*", "env[*].value"])
+        assert redacted == self.SECRET.replace("YWRtaW4=", '"***"').replace(
+            "aHVudGVyMg==", '"***"'
+        ).replace("value: x", 'value: "***"')
+        assert yaml.redact(self.SECRET, "data") == (
+            'apiVersion: v1\nkind: Secret\ndata:  # base64\n  "***"\n'
+            "env:\n  - {name: A, value: x}\n"
+        )
+
+    def test_redact_data(self):
+        """Loaded data is copied, not modified"""
+        data = {"db": {"password": "p", "host": "h"}, "items": [1, 2]}
+        redacted = yaml.redact(data, ["db.password", "items[1]"])
+        assert redacted == {"db": {"password": "***", "host": "h"}, "items": [1, "***"]}
+        assert data["db"]["password"] == "p"
+
+    def test_redact_option(self):
+        """redact= masks values while loading, including in batch loaders"""
+        result = yaml.safe_load(self.SECRET, redact="data.password")
+        assert result["data"] == {"user": "YWRtaW4=", "password": "***"}
+        results = yaml.safe_load_many(
+            [self.SECRET, self.SECRET], redact=["data.*"], timestamps=True
+        )
+        assert [r["data"]["user"] for r in results] == ["***", "***"]
+
+    def test_invalid_path(self):
+        """Malformed key paths raise"""
+        with pytest.raises(yaml.YAMLError):
+            yaml.redact(self.SECRET, "data..x")
+        with pytest.raises(ValueError):
+            yaml.safe_load(self.SECRET, redact=["[x]"])
+
+
+class TestLoggingConfig:
+    """Test loading dictConfig configs"""
+
+    CONFIG = (
+        "version: 1\n"
+        "disable_existing_loggers: false\n"
+        "formatters:\n"
+        "  plain: {format: '%(levelname)s %(message)s'}\n"
+        "handlers:\n"
+        "  console: {class: logging.StreamHandler, formatter: plain}\n"
+        "loggers:\n"
+        "  rustyyaml.test: {handlers: [console], level: DEBUG, propagate: false}\n"
+    )
+
+    def test_load_and_apply(self, tmp_path):
+        """A valid config is returned and can be applied"""
+        import logging
+
+        path = tmp_path / "logging.yaml"
+        path.write_text(self.CONFIG)
+        config = yaml.load_logging_config(path)
+        assert config["handlers"]["console"]["formatter"] == "plain"
+        logger = logging.getLogger("rustyyaml.test")
+        yaml.load_logging_config(str(path), apply=True)
+        assert logger.level == logging.DEBUG
+        assert [type(h) for h in logger.handlers] == [logging.StreamHandler]
+
+    def test_dangling_references(self, tmp_path):
+        """Undefined references are reported with their lines"""
+        path = tmp_path / "logging.yaml"
+        path.write_text(
+            self.CONFIG.replace("formatter: plain", "formatter: detailed")
+            + "root: {handlers: [file], level: LOUD}\n"
+        )
+        with pytest.raises(yaml.YAMLError) as info:
+            yaml.load_logging_config(path)
+        message = str(info.value)
+        assert "handler 'console' uses undefined formatter 'detailed'" in message
+        assert "the root logger uses undefined handler 'file'" in message
+        assert "unknown level LOUD" in message
+        assert info.value.line == 6
+
+
+class TestGenerateDataclasses:
+    """Test generate_dataclasses()"""
+
+    def test_generated_source_runs(self):
+        """The source defines working classes matching the samples"""
+        samples = (
+            "name: web\nport: 80\ndatabase: {host: db}\n---\n"
+            "name: api\nport: 8080\ndatabase: {host: db2}\nlog-level: debug\n"
+        )
+        source = yaml.generate_dataclasses(samples, root_name="AppConfig")
+        namespace = {}
+        exec(source, namespace)
+        Database, AppConfig = namespace["Database"], namespace["AppConfig"]
+        config = AppConfig(name="web", port=80, database=Database(host="db"))
+        assert config.log_level is None
+        assert '# YAML key: "log-level"' in source
+        assert "log_level: Optional[str] = None" in source
+
+    def test_directory(self, tmp_path):
+        """Every document of every file is a sample"""
+        (tmp_path / "a.yaml").write_text("replicas: 1\n")
+        (tmp_path / "b.yaml").write_text("replicas: 2.5\nlabels: [x]\n")
+        source = yaml.generate_dataclasses(tmp_path)
+        assert "from 2 documents" in source
+        assert "    replicas: float\n" in source
+        assert "    labels: Optional[List[str]] = None\n" in source
+
+    def test_pydantic(self):
+        """style="pydantic" emits BaseModel subclasses with aliases"""
+        source = yaml.generate_dataclasses("app-id: 1", style="pydantic")
+        assert "from pydantic import BaseModel, Field" in source
+        assert "class Config(BaseModel):" in source
+        assert 'app_id: int = Field(alias="app-id")' in source
+
+    def test_invalid(self):
+        """Non-mapping samples and unknown styles are rejected"""
+        with pytest.raises(yaml.YAMLError, match="mapping"):
+            yaml.generate_dataclasses("[1, 2]")
+        with pytest.raises(yaml.YAMLError, match="style"):
+            yaml.generate_dataclasses("a: 1", style="attrs")
+
+
+class TestExtractComments:
+    """Test extract_comments()"""
+
+    SOURCE = """\
+# Service config
+name: web  # public name
+spec:
+  # deprecated: use ports
+  port: 80
+  command: "run # not a comment"
+"""
+
+    def test_comments_and_paths(self):
+        """Trailing comments describe their line, others the next node"""
+        comments = yaml.extract_comments(self.SOURCE)
+        assert [(c["text"], c["path"], c["inline"]) for c in comments] == [
+            ("Service config", "/name", False),
+            ("public name", "/name", True),
+            ("deprecated: use ports", "/spec/port", False),
+        ]
+        assert (comments[2]["line"], comments[2]["column"]) == (4, 3)
+
+    def test_deprecated_audit(self, tmp_path):
+        """Paths and files work as input"""
+        path = tmp_path / "config.yaml"
+        path.write_text(self.SOURCE)
+        deprecated = [
+            c["path"]
+            for c in yaml.extract_comments(path)
+            if c["text"].startswith("deprecated:")
+        ]
+        assert deprecated == ["/spec/port"]
+
+    def test_documents(self):
+        """Comments report their document; trailing ones may have no node"""
+        comments = yaml.extract_comments("a: 1\n---\nb: 2\n# end\n")
+        assert comments == [
+            {
+                "text": "end",
+                "line": 4,
+                "column": 1,
+                "inline": False,
+                "document": 1,
+                "path": None,
+            }
+        ]
+
+    def test_malformed(self):
+        """Malformed YAML raises"""
+        with pytest.raises(yaml.YAMLError):
+            yaml.extract_comments("a: [")
+
+
+class TestDetectType:
+    """Test detect_type()"""
+
+    def test_kubernetes(self, tmp_path):
+        """Kubernetes manifests report apiVersion, kind and metadata"""
+        path = tmp_path / "deploy.yaml"
+        path.write_text(
+            "apiVersion: apps/v1\nkind: Deployment\n"
+            "metadata:\n  name: web\n  namespace: prod\n---\nkind: Service\n"
+        )
+        assert yaml.detect_type(path) == (
+            "kubernetes",
+            {
+                "apiVersion": "apps/v1",
+                "kind": "Deployment",
+                "name": "web",
+                "namespace": "prod",
+            },
+        )
+
+    def test_other_types(self):
+        """Workflows, Compose files and Ansible are recognized"""
+        workflow = "name: CI\non:\n  push: {}\njobs:\n  test: {runs-on: ubuntu}\n"
+        assert yaml.detect_type(workflow) == (
+            "github-workflow",
+            {"name": "CI", "on": ["push"], "jobs": ["test"]},
+        )
+        compose = "services:\n  web: {image: nginx}\n"
+        assert yaml.detect_type(compose) == ("docker-compose", {"services": ["web"]})
+        playbook = "- hosts: all\n  tasks: []\n"
+        assert yaml.detect_type(playbook)[0] == "ansible-playbook"
+        tasks = "- name: Restart\n  service: {name: x}\n  notify: reload\n"
+        assert yaml.detect_type(tasks) == ("ansible-tasks", {"names": ["Restart"]})
+
+    def test_unknown(self):
+        """Anything else has no label"""
+        assert yaml.detect_type("a: 1\n") == (None, {})
+        assert yaml.detect_type("") == (None, {})
+        with pytest.raises(yaml.YAMLError):
+            yaml.detect_type("a: [")
+
+
+class TestInspectAnchors:
+    """Test inspect_anchors()"""
+
+    def test_report(self, tmp_path):
+        path = tmp_path / "compose.yaml"
+        path.write_text("x: &base {restart: always}\nweb: *base\napi: *base\n")
+        report = yaml.inspect_anchors(path)
+        assert report["anchors"] == [
+            {
+                "name": "base",
+                "document": 0,
+                "line": 1,
+                "column": 4,
+                "aliases": 2,
+                "size": 3,
+            }
+        ]
+        assert report["aliases"] == 2
+        assert (report["nodes"], report["expanded_nodes"]) == (9, 13)
+        assert report["expansion_factor"] == 13 / 9
+
+    def test_no_anchors(self):
+        report = yaml.inspect_anchors("")
+        assert report["anchors"] == [] and report["expansion_factor"] == 1.0
+        assert yaml.inspect_anchors("a: [1, 2]\n---\nb: 3\n")["nodes"] == 8
+
+    def test_billion_laughs(self):
+        lines = ["a0: &a0 [lol, lol, lol, lol, lol, lol, lol, lol, lol, lol]"]
+        for level in range(1, 10):
+            aliases = ", ".join([f"*a{level - 1}"] * 10)
+            lines.append(f"a{level}: &a{level} [{aliases}]")
+        report = yaml.inspect_anchors("\n".join(lines))
+        assert report["aliases"] == 90
+        assert report["anchors"][-1]["size"] > 10**9
+        assert report["expansion_factor"] > 10**7
+
+    def test_errors(self):
+        with pytest.raises(yaml.YAMLError, match="undefined alias"):
+            yaml.inspect_anchors("a: *missing\n")
+        with pytest.raises(yaml.YAMLError):
+            yaml.inspect_anchors("a: [\n")
+
+
+class TestAllowedTags:
+    """Custom tags let through by allowed_tags="""
+
+    TEMPLATE = (
+        "Bucket: !Ref Name\n"
+        "Url: !Sub 'https://${Host}'\n"
+        "Ports: !Split [80, 443]\n"
+    )
+
+    def test_listed_tags_load(self):
+        data = yaml.safe_load(self.TEMPLATE, allowed_tags=["!Ref", "!Sub", "!Split"])
+        assert data == {"Bucket": "Name", "Url": "https://${Host}", "Ports": [80, 443]}
+
+    def test_other_tags_rejected(self):
+        with pytest.raises(yaml.YAMLError, match="!Split"):
+            yaml.safe_load(self.TEMPLATE, allowed_tags=["!Ref", "!Sub"])
+        with pytest.raises(ValueError, match="unsafe tag"):
+            yaml.safe_load("a: 1", allowed_tags=["!!python/object/apply:os.system"])
+
+    def test_mapping_strategy(self):
+        data = yaml.safe_load(
+            "a: !Ref x\n", allowed_tags=["!Ref"], tag_strategy="mapping"
+        )
+        assert data == {"a": {"Ref": "x"}}
+        with pytest.raises(ValueError, match="tag_strategy"):
+            yaml.safe_load("a: 1", allowed_tags=["!Ref"], tag_strategy="drop")
+
+    def test_callable_strategy(self):
+        def intrinsic(tag, value):
+            return {"Fn::" + tag[1:]: value}
+
+        data = yaml.load_all(
+            "a: !Sub x\n---\n- !Sub [y, {z: 1}]\n",
+            allowed_tags=["!Sub"],
+            tag_strategy=intrinsic,
+        )
+        assert data == [{"a": {"Fn::Sub": "x"}}, [{"Fn::Sub": ["y", {"z": 1}]}]]
+
+
+class TestAuditTags:
+    """audit_tags() inventory"""
+
+    def test_tags_with_locations(self):
+        text = "Bucket: !Ref Name\nPorts: !Split [80]\n---\ndata: !!binary aGk=\n"
+        assert yaml.audit_tags(text) == [
+            {
+                "tag": "!Ref",
+                "document": 0,
+                "line": 1,
+                "column": 9,
+                "kind": "scalar",
+                "unsafe": False,
+            },
+            {
+                "tag": "!Split",
+                "document": 0,
+                "line": 2,
+                "column": 8,
+                "kind": "sequence",
+                "unsafe": False,
+            },
+            {
+                "tag": "tag:yaml.org,2002:binary",
+                "document": 1,
+                "line": 4,
+                "column": 7,
+                "kind": "scalar",
+                "unsafe": False,
+            },
+        ]
+
+    def test_unsafe_tags_reported(self):
+        (found,) = yaml.audit_tags("!!python/object/apply:os.system [ls]\n")
+        assert found["unsafe"] and found["kind"] == "sequence"
+        assert yaml.audit_tags("plain: data\n") == []
+        with pytest.raises(yaml.YAMLError):
+            yaml.audit_tags("a: [\n")
+
+
+class TestNodeLimits:
+    """Input size, node count, per-scalar and per-mapping limits"""
+
+    def test_within_limits(self):
+        result = yaml.safe_load(
+            "a: short\nb: [1, 2]\n", max_scalar_bytes=5, max_mapping_entries=2
+        )
+        assert result == {"a": "short", "b": [1, 2]}
+
+    def test_long_scalar(self):
+        with pytest.raises(yaml.YAMLError, match="max_scalar_bytes=4") as info:
+            yaml.safe_load("a: ok\nb: too long\n", max_scalar_bytes=4)
+        assert (info.value.line, info.value.column) == (2, 4)
+
+    def test_large_mapping(self):
+        with pytest.raises(yaml.YAMLError, match="mapping with 3 entries"):
+            yaml.safe_load('{"a": 1, "b": 2, "c": 3}', max_mapping_entries=2)
+        with pytest.raises(yaml.YAMLError, match="max_mapping_entries"):
+            yaml.safe_load("a: 1\nb: 2\n", max_mapping_entries=1, timestamps=True)
+
+    def test_load_all(self):
+        with pytest.raises(yaml.YAMLError) as info:
+            yaml.load_all("a: ok\n---\nb: longer\n", max_scalar_bytes=4)
+        assert info.value.line == 3
+
+    def test_resource_limit_error(self):
+        assert issubclass(yaml.ResourceLimitError, yaml.YAMLError)
+        with pytest.raises(yaml.ResourceLimitError):
+            yaml.safe_load("a: long\n", max_scalar_bytes=2)
+
+    def test_max_size_bytes(self, tmp_path):
+        assert yaml.safe_load("a: 1\n", max_size_bytes=5) == {"a": 1}
+        with pytest.raises(yaml.ResourceLimitError, match="max_size_bytes=4") as info:
+            yaml.safe_load("a: 1\n", max_size_bytes=4)
+        assert info.value.line is None
+        with pytest.raises(yaml.ResourceLimitError, match="input of 11 bytes"):
+            yaml.load_all("a: 1\n---\nb\n", max_size_bytes=9)
+        path = tmp_path / "big.yaml"
+        path.write_text("a: 1\n")
+        with pytest.raises(yaml.ResourceLimitError):
+            yaml.safe_load_file(path, max_size_bytes=4)
+        with pytest.raises(yaml.ResourceLimitError, match="max_size_bytes=4"):
+            yaml.safe_load_file(path, streaming=True, max_size_bytes=4)
+        assert yaml.safe_load_file(path, streaming=True, max_size_bytes=5) == {"a": 1}
+
+    def test_max_size_bytes_stops_reading(self, tmp_path):
+        class Endless:
+            reads = 0
+
+            def read(self, size):
+                self.reads += 1
+                return "a" * size
+
+        stream = Endless()
+        with pytest.raises(yaml.ResourceLimitError, match="more than 1000 bytes"):
+            yaml.safe_load(stream, max_size_bytes=1000)
+        assert stream.reads == 1
+
+        # Ten megabytes of zeros, about ten kilobytes compressed
+        path = tmp_path / "bomb.yaml.gz"
+        path.write_bytes(gzip.compress(b"a: " + b"0" * 10_000_000))
+        for streaming in (False, True):
+            with pytest.raises(yaml.ResourceLimitError, match="more than 1000 bytes"):
+                yaml.safe_load_file(path, streaming=streaming, max_size_bytes=1000)
+
+    def test_max_nodes(self):
+        # 13 nodes once the aliases are expanded, 9 as written
+        text = "a: &a [x, x]\nb: [*a, *a]\n"
+        assert yaml.safe_load(text, max_nodes=13)["b"] == [["x", "x"]] * 2
+        for options in ({}, {"timestamps": True}):
+            with pytest.raises(yaml.ResourceLimitError, match="max_nodes=12") as info:
+                yaml.safe_load(text, max_nodes=12, **options)
+            assert (info.value.line, info.value.column) == (2, 9)
+
+
+class TestSortKeys:
+    """Key-sorted mappings"""
+
+    def test_sort_keys(self):
+        text = "b: {z: 1, y: [{d: 1, c: 2}]}\na: 1\n2: two\n10: ten\n"
+        result = yaml.safe_load(text, sort_keys=True)
+        assert list(result) == [2, 10, "a", "b"]
+        assert list(result["b"]) == ["y", "z"]
+        assert list(result["b"]["y"][0]) == ["c", "d"]
+        assert list(yaml.safe_load(text)) == ["b", "a", 2, 10]
+
+    def test_sort_keys_composer(self):
+        text = "z: &x {b: 1, a: 2}\ny: *x\nx: 2024-01-01\n"
+        result = yaml.safe_load(text, sort_keys=True, timestamps=True)
+        assert list(result) == ["x", "y", "z"]
+        assert list(result["y"]) == ["a", "b"]
+        result = yaml.safe_load(text, sort_keys=True, as_namespace=True)
+        assert list(result.to_dict()) == ["x", "y", "z"]
+
+    def test_sort_keys_colliding_keys(self):
+        """Keys Python treats as equal resolve as they would unsorted"""
+        text = "z: 0\n1: one\ntrue: yes\n"
+        expected = yaml.safe_load(text)
+        assert expected == {"z": 0, 1: "yes"}
+        for options in ({}, {"timestamps": True}, {"as_namespace": True}):
+            result = yaml.safe_load(text, sort_keys=True, **options)
+            assert result == expected
+            assert [type(key) for key in result] == [int, str]
+
+
+class TestProfiles:
+    """Spring-style profile selection"""
+
+    INLINE = """\
+db: {host: localhost, port: 5432}
+profiles:
+  prod:
+    db: {host: db.internal}
+"""
+
+    STREAM = """\
+db: {host: localhost, port: 5432}
+replicas: 1
+---
+profile: [prod, staging]
+replicas: 3
+---
+profile: dev
+debug: true
+"""
+
+    def test_inline_profiles(self):
+        result = yaml.safe_load(self.INLINE, profile="prod")
+        assert result == {"db": {"host": "db.internal", "port": 5432}}
+        result = yaml.safe_load(self.INLINE, profile="dev")
+        assert result == {"db": {"host": "localhost", "port": 5432}}
+
+    def test_profile_documents(self):
+        result = yaml.safe_load(self.STREAM, profile="staging")
+        assert result == {"db": {"host": "localhost", "port": 5432}, "replicas": 3}
+        result = yaml.safe_load(self.STREAM, profile="dev", timestamps=True)
+        assert result["debug"] is True and result["replicas"] == 1
+        assert yaml.load_all(self.STREAM, profile="prod")[0]["replicas"] == 3
+
+    def test_profile_schema(self):
+        schema = {
+            "type": "object",
+            "additionalProperties": False,
+            "properties": {"replicas": {"type": "integer"}},
+        }
+        text = 'replicas: "1"\nprofiles: {prod: {replicas: "2"}}\n'
+        result = yaml.safe_load(text, profile="prod", schema=schema)
+        assert result == {"replicas": 2}
+
+    def test_invalid_profiles(self):
+        with pytest.raises(yaml.YAMLError, match="profiles must be a mapping"):
+            yaml.safe_load("profiles: [prod]\n", profile="prod")
+
+
+class TestIntegrity:
+    """Checksum-verified loading"""
+
+    def test_safe_load_file_sha256(self, tmp_path):
+        path = tmp_path / "deploy.yaml"
+        path.write_bytes(b"replicas: 3\n")
+        digest = hashlib.sha256(b"replicas: 3\n").hexdigest()
+        assert yaml.safe_load_file(path, sha256=digest) == {"replicas": 3}
+        assert yaml.safe_load_file(path, sha256="sha256:" + digest.upper())
+
+        with pytest.raises(yaml.IntegrityError) as info:
+            yaml.safe_load_file(path, sha256="0" * 64)
+        assert isinstance(info.value, yaml.YAMLError)
+        assert info.value.actual == digest
+        assert info.value.expected == "0" * 64
+        assert info.value.path == str(path)
+
+        with pytest.raises(yaml.YAMLError, match="64 hex digits"):
+            yaml.safe_load_file(path, sha256="abc")
+
+    def test_load_files_verified(self, tmp_path):
+        good = tmp_path / "good.yaml"
+        good.write_bytes(b"a: 1\n")
+        bad = tmp_path / "bad.yaml"
+        bad.write_bytes(b"b: 2\n")
+        manifest = {
+            good: hashlib.sha256(b"a: 1\n").hexdigest(),
+            str(bad): hashlib.sha256(b"b: 1\n").hexdigest(),
+        }
+        with pytest.raises(yaml.IntegrityError):
+            yaml.load_files_verified(manifest)
+
+        results, errors = yaml.load_files_verified(manifest, collect_errors=True)
+        assert results == [(str(good), {"a": 1})]
+        assert isinstance(errors[0], yaml.IntegrityError)
+        assert errors[0].path == str(bad)
+
+    def test_load_archive(self, tmp_path):
+        """load_archive parses the YAML members of zip and tar archives"""
+        import io
+        import tarfile
+        import zipfile
+
+        members = {
+            "chart/Chart.yaml": b"name: web\nversion: 1.0.0\n",
+            "chart/templates/svc.yml": b"kind: Service\n",
+            "chart/README.md": b"# web\n",
+            "chart/bad.yaml": b"a: [\n",
+        }
+        expected = [
+            ("chart/Chart.yaml", {"name": "web", "version": "1.0.0"}),
+            ("chart/templates/svc.yml", {"kind": "Service"}),
+        ]
+
+        chart = tmp_path / "web-1.0.0.tgz"
+        with tarfile.open(chart, "w:gz") as archive:
+            for name, data in members.items():
+                info = tarfile.TarInfo(name)
+                info.size = len(data)
+                archive.addfile(info, io.BytesIO(data))
+        bundle = tmp_path / "artifacts.zip"
+        with zipfile.ZipFile(bundle, "w", zipfile.ZIP_DEFLATED) as archive:
+            for name, data in members.items():
+                archive.writestr(name, data)
+
+        for path in (chart, bundle):
+            results, errors = yaml.load_archive(path, collect_errors=True)
+            assert results == expected
+            assert errors[0].path == "chart/bad.yaml"
+            kinds, _ = yaml.load_archive(
+                path,
+                filter={"kind": "Service"},
+                transform=lambda doc: doc["kind"],
+                collect_errors=True,
+            )
+            assert kinds == [("chart/templates/svc.yml", "Service")]
+            with pytest.raises(yaml.YAMLError):
+                yaml.load_archive(path)
+
+        not_archive = tmp_path / "notes.txt"
+        not_archive.write_text("plain text\n" * 100)
+        with pytest.raises(yaml.YAMLError, match="Invalid archive") as exc:
+            yaml.load_archive(not_archive)
+        assert Path(exc.value.path) == not_archive
+        assert exc.value.line is None
+        # Small and empty files aren't mistaken for empty archives
+        for content in ["a: 1\n", ""]:
+            plain = tmp_path / "plain.yaml"
+            plain.write_text(content)
+            with pytest.raises(yaml.YAMLError, match="not a zip or tar archive"):
+                yaml.load_archive(plain)
+
+
+class TestShareSubtrees:
+    """Structural sharing across batch results"""
+
+    def test_safe_load_many(self):
+        texts = [
+            "labels: {app: web, tier: front}\nports: [80]\n",
+            "labels: {app: web, tier: front}\nports: [443]\n",
+        ]
+        first, second = yaml.safe_load_many(texts, share_subtrees=True)
+        assert first["labels"] is second["labels"]
+        assert first["ports"] == (80,) and second["ports"] == (443,)
+        with pytest.raises(TypeError):
+            first["labels"]["app"] = "api"
+        assert dict(first["labels"]) == {"app": "web", "tier": "front"}
+
+    def test_load_directory(self, tmp_path):
+        (tmp_path / "a.yaml").write_text("limits: {cpu: 1}\nname: a\n")
+        (tmp_path / "b.yaml").write_text("limits: {cpu: 1}\nname: b\n")
+        results = yaml.load_directory(tmp_path, share_subtrees=True)
+        (_, a), (_, b) = sorted(results)
+        assert a["limits"] is b["limits"]
+
+    def test_single_document_loaders_reject(self):
+        with pytest.raises(yaml.YAMLError, match="batch loaders"):
+            yaml.safe_load("a: 1", share_subtrees=True)
+
+
+class TestMergeKeys:
+    """<< merge keys, on by default"""
+
+    COMPOSE = (
+        "x-defaults: &defaults\n"
+        "  restart: always\n"
+        "  environment: {TZ: UTC}\n"
+        "services:\n"
+        "  web:\n"
+        "    <<: *defaults\n"
+        "    restart: 'no'\n"
+        "    image: web\n"
+    )
+
+    def test_merged(self):
+        web = yaml.safe_load(self.COMPOSE)["services"]["web"]
+        assert web == {"restart": "no", "environment": {"TZ": "UTC"}, "image": "web"}
+        assert list(web) == ["restart", "environment", "image"]
+        assert yaml.load_all(self.COMPOSE + "---\n" + self.COMPOSE)[1] == {
+            "x-defaults": {"restart": "always", "environment": {"TZ": "UTC"}},
+            "services": {"web": web},
+        }
+
+    def test_every_load_path(self):
+        expected = yaml.safe_load(self.COMPOSE)
+        assert yaml.safe_load(self.COMPOSE, timestamps=True) == expected
+        assert yaml.safe_load_many([self.COMPOSE])[0] == expected
+        assert yaml.load_rt(self.COMPOSE) == expected
+
+    def test_disabled(self):
+        web = yaml.safe_load(self.COMPOSE, merge_keys=False)["services"]["web"]
+        assert web["<<"] == {"restart": "always", "environment": {"TZ": "UTC"}}
+        assert yaml.safe_load("a: {'<<': {x: 1}}")["a"] == {"<<": {"x": 1}}
+        data = yaml.safe_load("a: {<<: {x: 1}}", merge_keys=False, timestamps=True)
+        assert data == {"a": {"<<": {"x": 1}}}
+
+    def test_invalid_merge(self):
+        with pytest.raises(yaml.YAMLError, match="for merging") as exc:
+            yaml.safe_load("a: 1\nb:\n  <<: [{x: 1}, 2]\n")
+        assert exc.value.line == 3
+
+
+class TestPreserveAliases:
+    """Aliases constructed as their anchor's object"""
+
+    SOURCE = "base: &b {cpu: 1, tags: [a]}\nweb: *b\napi: *b\n"
+
+    def test_copies_by_default(self):
+        data = yaml.safe_load(self.SOURCE)
+        assert data["web"] == data["base"]
+        assert data["web"] is not data["base"]
+
+    def test_shared(self):
+        data = yaml.safe_load(self.SOURCE, preserve_aliases=True)
+        assert data["web"] is data["base"] and data["api"] is data["base"]
+        data["base"]["cpu"] = 2
+        assert data["api"] == {"cpu": 2, "tags": ["a"]}
+
+    def test_with_other_options(self):
+        data = yaml.safe_load("a: &x [yes]\nb: *x\n", preserve_aliases=True)
+        assert data["a"] == ["yes"] and data["b"] is data["a"]
+        stream = "- &x {n: 1}\n- *x\n---\n[&y 2, *y]\n"
+        docs = yaml.load_all(stream, preserve_aliases=True)
+        assert docs[0][0] is docs[0][1] and docs[1] == [2, 2]
+
+
+class TestDocumentSpans:
+    """Source regions of the documents in a stream"""
+
+    def test_load_all_spans(self):
+        text = "# café\na: 1\n---\nb: 2\n"
+        documents = yaml.load_all(text, document_spans=True)
+        assert [doc for doc, _ in documents] == [{"a": 1}, {"b": 2}]
+        source = text.encode()
+        (_, first), (_, second) = documents
+        assert source[first[0] : first[1]] == b"a: 1\n"
+        assert source[second[0] : second[1]] == b"---\nb: 2\n"
+        assert (first[2], second[2]) == (2, 3)
+
+    def test_spans_with_loader_and_options(self):
+        loader = yaml.Loader(document_spans=True, timestamps=True)
+        ((doc, span),) = loader.load_all("when: 2024-01-01\n")
+        assert span == (0, 17, 1)
+        assert isinstance(doc["when"], datetime.date)
+        with pytest.raises(yaml.YAMLError, match="collect_errors"):
+            yaml.load_all("a: 1", document_spans=True, collect_errors=True)
+
+    def test_lazy_spans(self):
+        text = "# café\na: 1\n---\nb: 2\n...\n---\n[c]\n"
+        lazy = yaml.load_all_iter(text, document_spans=True)
+        assert next(lazy) == ({"a": 1}, (8, 13, 2))
+        assert list(lazy) == yaml.load_all(text, document_spans=True)[1:]
+        with pytest.raises(yaml.YAMLError, match="only supported by load_all"):
+            yaml.safe_load("a: 1", document_spans=True)
+
+
+class TestSharedMemory:
+    """Documents shared between processes with dump_shm/load_shm"""
+
+    def test_round_trip(self):
+        doc = {"name": "app", "ports": [80, 443], "limits": {"cpu": 0.5}, 1: None}
+        handle = yaml.dump_shm(doc)
+        try:
+            shared = yaml.load_shm(handle)
+        finally:
+            os.remove(handle)
+        # The mapping outlives the file
+        assert isinstance(shared, yaml.SharedMapping)
+        assert shared == doc and shared.to_dict() == doc
+        assert shared["limits"]["cpu"] == 0.5 and shared[1] is None
+        ports = shared["ports"]
+        assert isinstance(ports, yaml.SharedSequence)
+        assert (ports[-1], ports[:1], len(ports)) == (443, [80], 2)
+        assert list(shared) == ["name", "ports", "limits", 1]
+        assert shared.get("missing", 3) == 3 and "name" in shared
+        with pytest.raises(KeyError):
+            shared["missing"]
+
+    def test_views_are_read_only(self):
+        handle = yaml.dump_shm([{"a": 1}])
+        try:
+            shared = yaml.load_shm(handle)
+            with pytest.raises(TypeError):
+                shared[0]["a"] = 2
+            assert shared.to_list() == [{"a": 1}]
+        finally:
+            os.remove(handle)
+
+    def test_load_in_another_process(self):
+        handle = yaml.dump_shm(yaml.safe_load("services: {web: {replicas: 3}}"))
+        code = "import rustyyaml, sys; print(rustyyaml.load_shm(sys.argv[1])"
+        code += "['services']['web']['replicas'])"
+        package = os.path.dirname(os.path.dirname(yaml.__file__))
+        env = dict(os.environ, PYTHONPATH=package)
+        try:
+            result = subprocess.run(
+                [sys.executable, "-c", code, handle],
+                capture_output=True,
+                text=True,
+                env=env,
+                check=True,
+            )
+        finally:
+            os.remove(handle)
+        assert result.stdout.strip() == "3"
+
+    def test_invalid_input(self, tmp_path):
+        with pytest.raises(yaml.YAMLError):
+            yaml.dump_shm({"when": object()})
+        path = tmp_path / "other.bin"
+        path.write_bytes(b"something else")
+        with pytest.raises(yaml.YAMLError, match="not a rustyyaml shared"):
+            yaml.load_shm(path)
+
+
+class TestMetrics:
+    """Process-wide parser counters"""
+
+    def test_counters(self, tmp_path):
+        before = yaml.metrics()
+        assert sorted(before) == [
+            "bytes",
+            "cache_hits",
+            "documents",
+            "errors",
+            "parse_time",
+        ]
+        yaml.safe_load("a: 1\n")
+        yaml.load_all("a: 1\n---\nb: 2\n")
+        with pytest.raises(yaml.YAMLError):
+            yaml.safe_load("a: [")
+        (tmp_path / "a.yaml").write_text("same: 1")
+        (tmp_path / "b.yaml").write_text("same: 1")
+        yaml.load_directory(tmp_path)
+
+        after = yaml.metrics()
+        assert after["documents"] - before["documents"] >= 4
+        assert after["bytes"] - before["bytes"] >= 30
+        assert after["errors"] - before["errors"] >= 1
+        assert after["cache_hits"] - before["cache_hits"] >= 1
+        assert after["parse_time"] > before["parse_time"]
+
+
+class TestSops:
+    """Test decrypting SOPS documents while loading"""
+
+    KEY = bytes(range(32))
+    DOCUMENT = """\
+db:
+  user: ENC[AES256_GCM,data:uEV+9Ks=,iv:CxIZICcuNTxDSlFYX2ZtdHuCiZCXnqWss7rByM/W3eQ=,tag:qruLASjxYrCUGfHasiVH1A==,type:str]
+  port: ENC[AES256_GCM,data:Ztw2xA==,iv:ChEYHyYtNDtCSVBXXmVsc3qBiI+WnaSrsrnAx87V3OM=,tag:r36T8RUsfCj9MlQsXud/xQ==,type:int]
+  tls: ENC[AES256_GCM,data:B5pwkw==,iv:ChEYHyYtNDtCSVBXXmVsc3qBiI+WnaSrsrnAx87V3OM=,tag:VlTJuZAAbwsHn6KGiN+ecw==,type:bool]
+hosts:
+  - ENC[AES256_GCM,data:D8nbKlb3//mEa/F5uw==,iv:EBceJSwzOkFIT1ZdZGtyeYCHjpWco6qxuL/GzdTb4uk=,tag:EI8H7cbpNb2aGLy7x1eZTg==,type:str]
+  - ENC[AES256_GCM,data:DMnbKlb3//mEa/F5uw==,iv:EBceJSwzOkFIT1ZdZGtyeYCHjpWco6qxuL/GzdTb4uk=,tag:ERMZAxHcTFbql8rGWwQgPA==,type:str]
+region_unencrypted: eu-west-1
+sops:
+  kms: []
+  age:
+    - recipient: age1examplerecipient
+      enc: |
+        -----BEGIN AGE ENCRYPTED FILE-----
+        -----END AGE ENCRYPTED FILE-----
+  lastmodified: "2024-01-01T00:00:00Z"
+  mac: ENC[AES256_GCM,data:+wTn8HooyjFyP3E/3+BmqGeAKN0I9IwVx7m1Jn2G6zh/PqxqNKc9w/FzM/5jxOHMpqR9krOppZsx0/MEl5jBDtu99Y00yjx/Zc6KBmwQ385SmkdtgXdDWqGL3A+zNapzHW00nG8ktMSBR3jYBxy2hsSfb4K5o7cvHHzYpbZw4AA=,iv:ZGVmZ2hpamtsbW5vcHFyc3R1dnd4eXp7fH1+f4CBgoM=,tag:PqmUJIEsTNOyJzyWZIBFuA==,type:str]
+  version: 3.8.1
+"""  # noqa: E501
+    EXPECTED = {
+        "db": {"user": "admin", "port": 5432, "tls": True},
+        "hosts": ["a.example.com", "b.example.com"],
+        "region_unencrypted": "eu-west-1",
+    }
+
+    def sops_key(self, kind, entry):
+        assert kind == "age"
+        assert entry["recipient"] == "age1examplerecipient"
+        assert entry["enc"].startswith("-----BEGIN AGE")
+        return self.KEY
+
+    def test_decrypt(self):
+        """Values are decrypted and typed; the sops section is removed"""
+        assert yaml.safe_load(self.DOCUMENT, sops_key=self.sops_key) == self.EXPECTED
+        # Same result through the composer and the batch loaders
+        result = yaml.safe_load(self.DOCUMENT, sops_key=self.sops_key, timestamps=True)
+        assert result == self.EXPECTED
+        results = yaml.safe_load_many(
+            [self.DOCUMENT, "plain: 1"], sops_key=self.sops_key
+        )
+        assert results == [self.EXPECTED, {"plain": 1}]
+
+    def test_without_option(self):
+        """Without sops_key the document loads as written"""
+        result = yaml.safe_load(self.DOCUMENT)
+        assert result["db"]["user"].startswith("ENC[AES256_GCM,")
+        assert "sops" in result
+
+    def test_key_failures(self):
+        """Wrong keys, missing keys and tampering raise YAMLError"""
+        with pytest.raises(yaml.YAMLError, match="wrong key or tampered"):
+            yaml.safe_load(self.DOCUMENT, sops_key=lambda kind, entry: bytes(32))
+        with pytest.raises(yaml.YAMLError, match="returned None"):
+            yaml.safe_load(self.DOCUMENT, sops_key=lambda kind, entry: None)
+        with pytest.raises(yaml.YAMLError, match="32-byte"):
+            yaml.safe_load(self.DOCUMENT, sops_key=lambda kind, entry: b"short")
+        # A value moved to another key no longer authenticates
+        moved = self.DOCUMENT.replace("  user:", "  password:")
+        with pytest.raises(yaml.YAMLError, match="db.password"):
+            yaml.safe_load(moved, sops_key=self.sops_key)
+        with pytest.raises(yaml.YAMLError):
+            yaml.safe_load(self.DOCUMENT, sops_key="not callable")
+
+    def test_mac(self):
+        """Changes the value tags can't see fail the document MAC"""
+        edited = self.DOCUMENT.replace("eu-west-1", "us-east-1")
+        dropped = self.DOCUMENT.replace("  - ENC[AES256_GCM,data:DMnb", "  # ")
+        unsigned = self.DOCUMENT.replace("  mac: ENC", "  not_mac: ENC")
+        for document in (edited, dropped, unsigned):
+            for options in ({}, {"timestamps": True}):
+                with pytest.raises(yaml.YAMLError, match="sops_verify_mac=False"):
+                    yaml.safe_load(document, sops_key=self.sops_key, **options)
+        result = yaml.safe_load(edited, sops_key=self.sops_key, sops_verify_mac=False)
+        assert result == {**self.EXPECTED, "region_unencrypted": "us-east-1"}
+        # A recorded MAC that was itself tampered with doesn't decrypt
+        forged = self.DOCUMENT.replace("tag:PqmUJIEs", "tag:AAAAAAAA")
+        with pytest.raises(yaml.YAMLError, match="sops.mac"):
+            yaml.safe_load(forged, sops_key=self.sops_key)
+
+
+class TestSafeDump:
+    """Serializing Python data to YAML"""
+
+    def test_layout(self):
+        data = {"name": "web", "ports": [80, 443], "env": {"DEBUG": "1"}}
+        assert yaml.safe_dump(data) == (
+            "env:\n  DEBUG: '1'\nname: web\nports:\n- 80\n- 443\n"
+        )
+        assert yaml.safe_dump(data, sort_keys=False).startswith("name: web\n")
+        assert yaml.safe_dump([{"a": 1, "b": None}, []]) == "- a: 1\n  b: null\n- []\n"
+        assert yaml.dump("text") == "text\n...\n"
+
+    def test_round_trip(self):
+        data = {
+            "strings": ["yes", "0755", "2024-01-01", "", " x ", "a: b", "#c", "1e3"],
+            "multi": "line one\nline two\n",
+            "numbers": [0, -7, 2**63, 1.5, 1e20, float("inf")],
+            "flags": [True, False, None],
+            "nested": {"list": [[1, 2], {"k": "v"}], 1: "int key"},
+            "tuple": (1, 2),
+        }
+        loaded = yaml.safe_load(yaml.safe_dump(data))
+        assert loaded == dict(data, tuple=[1, 2])
+        assert yaml.safe_load(yaml.safe_dump(data), pyyaml_compat=True) == loaded
+        assert "multi: |\n  line one\n  line two\n" in yaml.safe_dump(data)
+
+    def test_stream(self):
+        import io
+
+        stream = io.StringIO()
+        assert yaml.safe_dump({"a": 1}, stream) is None
+        assert stream.getvalue() == "a: 1\n"
+
+    def test_big_ints(self):
+        data = {"big": 2**80, "small": -(2**70), "max": 2**64 - 1}
+        text = yaml.safe_dump(data, sort_keys=False)
+        assert text == (
+            "big: 1208925819614629174706176\nsmall: -1180591620717411303424\n"
+            "max: 18446744073709551615\n"
+        )
+        assert yaml.safe_load(text) == data
+        for n in [2**64, -(2**63) - 1, 2**127, -(2**128), 2**200]:
+            loaded = yaml.safe_load(yaml.safe_dump(n))
+            assert loaded == n
+            assert type(loaded) is int
+        with pytest.raises(yaml.YAMLError, match="duplicate entry"):
+            yaml.safe_load("n: 18446744073709551616\nn: 1\n")
+
+    def test_big_ints_to_stream_and_file(self, tmp_path):
+        data = [2**100, {"id": 2**64}]
+        expected = "- 1267650600228229401496703205376\n- id: 18446744073709551616\n"
+        stream = io.StringIO()
+        assert yaml.dump(data, stream) is None
+        assert stream.getvalue() == expected
+        path = tmp_path / "big.yaml"
+        yaml.dump_file(data, path)
+        assert path.read_text() == expected
+        yaml.dump_all_file([data], path)
+        assert yaml.load_all_file(path) == [data]
+
+    def test_unsupported_type(self):
+        with pytest.raises(yaml.YAMLError, match="cannot convert"):
+            yaml.safe_dump({"when": object()})
+
+    def test_recursive_data(self):
+        shared = {"cpu": 1}
+        text = yaml.safe_dump({"a": shared, "b": [shared]})
+        assert text == "a: &id001\n  cpu: 1\nb:\n- *id001\n"
+        assert yaml.safe_load(text) == {"a": shared, "b": [shared]}
+        assert yaml.safe_dump({"a": [1], "b": [1]}) == "a:\n- 1\nb:\n- 1\n"
+        recursive = {"name": "loop"}
+        recursive["self"] = [recursive]
+        with pytest.raises(yaml.YAMLError, match="contains itself"):
+            yaml.safe_dump(recursive)
+
+    def test_stdlib_types(self):
+        import dataclasses
+        import datetime
+        import decimal
+        import enum
+        import pathlib
+        import uuid
+
+        class Tier(enum.Enum):
+            GOLD = 1
+
+        @dataclasses.dataclass
+        class Account:
+            owner: str
+            tier: Tier
+
+        data = {
+            "created": datetime.datetime(2024, 5, 1, 12, 30),
+            "expires": datetime.date(2025, 5, 1),
+            "balance": decimal.Decimal("10.50"),
+            "id": uuid.UUID(int=7),
+            "home": pathlib.PurePosixPath("/home/ada"),
+            "account": Account("ada", Tier.GOLD),
+        }
+        text = yaml.safe_dump(data)
+        assert "balance: 10.50\n" in text
+        assert "created: 2024-05-01 12:30:00\n" in text
+        loaded = yaml.safe_load(text, timestamps=True)
+        assert loaded["created"] == data["created"]
+        assert loaded["expires"] == data["expires"]
+        assert loaded["balance"] == 10.5
+        assert loaded["id"] == str(data["id"])
+        assert loaded["home"] == "/home/ada"
+        assert loaded["account"] == {"owner": "ada", "tier": 1}
+
+    def test_decimal_beyond_float_range(self):
+        """Finite Decimals too large for a float are written exactly"""
+        import decimal
+
+        for value in ("1E+1000", "-1E+1000", "1.5E+400", "1E+3"):
+            text = yaml.safe_dump(decimal.Decimal(value))
+            assert ".inf" not in text
+            assert decimal.Decimal(text.split("\n")[0]) == decimal.Decimal(value)
+        assert yaml.safe_dump(decimal.Decimal("-Infinity")).startswith("-.inf\n")
+
+    def test_timedelta(self):
+        delays = [
+            datetime.timedelta(0),
+            datetime.timedelta(minutes=90),
+            datetime.timedelta(days=2, microseconds=250),
+            datetime.timedelta(seconds=-1.5),
+        ]
+        text = yaml.safe_dump({"delays": delays})
+        assert text == "delays:\n- 0s\n- 1h30m0s\n- 48h0m0.00025s\n- -1.5s\n"
+        assert yaml.safe_load(text, durations=True) == {"delays": delays}
+
+    def test_round_trips_loaded_types(self):
+        text = (
+            "when: 2024-01-02 03:04:05+00:00\nday: 2024-01-02\nwait: 1h30m0s\n"
+            "id: 12345678-1234-5678-1234-567812345678\nhost: 10.0.0.1\n"
+            "net: 10.0.0.0/8\n"
+        )
+        options = dict(timestamps=True, durations=True, uuids=True, ip_addresses=True)
+        data = yaml.safe_load(text, **options)
+        assert yaml.safe_dump(data, sort_keys=False) == text
+        assert yaml.safe_load(yaml.safe_dump(data), **options) == data
+
+    def test_ip_addresses(self):
+        hosts = [
+            ipaddress.ip_address("10.0.0.1"),
+            ipaddress.ip_address("::1"),
+            ipaddress.ip_network("10.0.0.0/8"),
+            ipaddress.ip_network("2001:db8::/32"),
+            ipaddress.ip_interface("192.168.1.5/24"),
+        ]
+        text = yaml.safe_dump(hosts)
+        assert text == (
+            "- 10.0.0.1\n- ::1\n- 10.0.0.0/8\n- 2001:db8::/32\n- 192.168.1.5/24\n"
+        )
+        loaded = yaml.safe_load(text, ip_addresses=True)
+        assert loaded == hosts
+        assert [type(host) for host in loaded] == [type(host) for host in hosts]
+
+    def test_add_representer(self):
+        import ipaddress
+
+        class Endpoint:
+            def __init__(self, host, port):
+                self.host = ipaddress.ip_address(host)
+                self.port = port
+
+        with pytest.raises(yaml.YAMLError, match="cannot convert"):
+            yaml.safe_dump(Endpoint("10.0.0.1", 80))
+        yaml.add_representer(ipaddress.IPv4Address, str)
+        yaml.add_representer(Endpoint, lambda e: {"host": e.host, "port": e.port})
+        assert yaml.safe_dump([Endpoint("10.0.0.1", 80)]) == (
+            "- host: 10.0.0.1\n  port: 80\n"
+        )
+        with pytest.raises(yaml.YAMLError, match="callable"):
+            yaml.add_representer(Endpoint, "not callable")
+
+    def test_dump_file(self, tmp_path):
+        path = tmp_path / "out" / "config.yaml"
+        with pytest.raises(yaml.YAMLError, match="^Failed to write ") as exc:
+            yaml.dump_file({"a": 1}, path)
+        assert exc.value.line is None
+        yaml.dump_file({"b": 2, "a": 1}, path, sort_keys=False, make_dirs=True)
+        assert path.read_text() == "b: 2\na: 1\n"
+        yaml.dump_all_file([{"a": 1}, {"b": 2}], str(path))
+        assert yaml.load_all_file(path) == [{"a": 1}, {"b": 2}]
+
+    def test_formatting_options(self):
+        data = {"a": {"b": [1, {"c": 2}]}}
+        assert yaml.safe_dump(data, indent=4) == (
+            "a:\n    b:\n    - 1\n    -   c: 2\n"
+        )
+        flow = yaml.safe_dump(data, default_flow_style=True)
+        assert flow == "{a: {b: [1, {c: 2}]}}\n"
+        assert yaml.safe_dump({"a": [1, 2]}, default_flow_style=None) == "a: [1, 2]\n"
+
+        text = " ".join(["word"] * 40)
+        folded = yaml.safe_dump({"text": text}, width=30)
+        assert len(folded.splitlines()) > 5
+        assert yaml.safe_load(folded) == {"text": text}
+        assert len(yaml.safe_dump({"text": text}, width=None).splitlines()) == 1
+
+        with pytest.raises(yaml.YAMLError, match="indent"):
+            yaml.safe_dump(data, indent=12)
+        with pytest.raises(yaml.YAMLError, match="unexpected keyword"):
+            yaml.safe_dump(data, explicit_start=True)
+
+    def test_bool_style(self):
+        data = {"enabled": True, "debug": False, "name": "yes"}
+        assert yaml.safe_dump(data, sort_keys=False, bool_style="True") == (
+            "enabled: True\ndebug: False\nname: 'yes'\n"
+        )
+        text = yaml.safe_dump(data, sort_keys=False, bool_style="yes")
+        assert text == "enabled: yes\ndebug: no\nname: 'yes'\n"
+        assert yaml.safe_load(text, pyyaml_compat=True) == data
+        canonical = yaml.safe_dump(data, bool_style="True", canonical=True)
+        assert canonical.startswith("debug: False\n")
+        with pytest.raises(yaml.YAMLError, match="bool_style"):
+            yaml.safe_dump(data, bool_style="on")
+
+    def test_null_style(self):
+        data = {"name": None, "tags": [None, "x"], "empty": {}}
+        text = yaml.safe_dump(data, sort_keys=False, null_style="")
+        assert text == "name:\ntags:\n-\n- x\nempty: {}\n"
+        assert yaml.safe_load(text) == data
+        text = yaml.safe_dump(data, sort_keys=False, null_style="~")
+        assert text == "name: ~\ntags:\n- ~\n- x\nempty: {}\n"
+        assert yaml.safe_dump(None, null_style="") == "null\n...\n"
+        with pytest.raises(yaml.YAMLError, match="null_style"):
+            yaml.safe_dump(data, null_style="none")
+
+    def test_comments(self, tmp_path):
+        config = {"database": {"host": "db", "port": 5432}, "replicas": [1, 2]}
+        comments = {"database.port": "set by infra team", "replicas[0]": "primary"}
+        text = yaml.safe_dump(config, comments=comments)
+        assert text == (
+            "database:\n  host: db\n  # set by infra team\n  port: 5432\n"
+            "replicas:\n# primary\n- 1\n- 2\n"
+        )
+        assert yaml.safe_load(text) == config
+        path = tmp_path / "config.yaml"
+        yaml.dump_file(config, path, comments=comments)
+        assert path.read_text() == text
+        with pytest.raises(yaml.YAMLError, match="'database.user' matches no key"):
+            yaml.safe_dump(config, comments={"database.user": "x"})
+        with pytest.raises(yaml.YAMLError, match="block style"):
+            yaml.safe_dump(config, comments=comments, default_flow_style=None)
+
+    def test_canonical(self):
+        first = {"b": 1, ("x", 2): "tuple", "a": {"long": " ".join(["w"] * 60)}}
+        second = dict(reversed(list(first.items())))
+        text = yaml.safe_dump(first, canonical=True)
+        assert text == yaml.safe_dump(second, canonical=True)
+        assert text.endswith("b: 1\n[x, 2]: tuple\n")
+        assert len(text.splitlines()) == 4
+        with pytest.raises(yaml.YAMLError, match="canonical"):
+            yaml.safe_dump(first, canonical=True, indent=4)
+
+    def test_dump_all(self):
+        manifests = [{"kind": "Service"}, {"kind": "Deployment", "replicas": 2}]
+        text = yaml.dump_all(manifests)
+        assert text == "kind: Service\n---\nkind: Deployment\nreplicas: 2\n"
+        assert yaml.load_all(text) == manifests
+        generated = yaml.safe_dump_all(doc for doc in ["a", [1], None])
+        assert yaml.load_all(generated) == ["a", [1], None]
+        assert yaml.safe_dump_all([]) == ""
+
+    def test_dump_all_headers(self, tmp_path):
+        manifests = [{"kind": "Service"}, {"kind": "Deployment"}, {"kind": "Job"}]
+        headers = ["Source: app/templates/service.yaml", None, "Generated\nDo not edit"]
+        text = yaml.dump_all(manifests, headers=headers)
+        assert text == (
+            "---\n# Source: app/templates/service.yaml\nkind: Service\n"
+            "---\nkind: Deployment\n"
+            "---\n# Generated\n# Do not edit\nkind: Job\n"
+        )
+        assert yaml.load_all(text) == manifests
+        path = tmp_path / "bundle.yaml"
+        yaml.dump_all_file(manifests, path, headers=headers)
+        assert path.read_text() == text
+        with pytest.raises(yaml.YAMLError, match="headers has 2 entries for 3"):
+            yaml.safe_dump_all(manifests, headers=headers[:2])
+
 
 class TestPyYAMLCompatibility:
     """Test compatibility with PyYAML API"""
@@ -0,0 +1,216 @@
+"""Conformance tests: pyyaml_compat=True must match PyYAML's SafeLoader exactly"""
+
+import math
+
+import pytest
+
+import rustyyaml
+
+pyyaml = pytest.importorskip("yaml")
+
+
+def assert_same(ours, theirs, path="$"):
+    """Recursively compare values AND types"""
+    assert type(ours) is type(theirs), (
+        f"{path}: {type(ours).__name__} != {type(theirs).__name__} "
+        f"({ours!r} vs {theirs!r})"
+    )
+    if isinstance(theirs, dict):
+        assert list(ours.keys()) == list(theirs.keys()), path
+        for key in theirs:
+            assert_same(ours[key], theirs[key], f"{path}.{key}")
+    elif isinstance(theirs, (list, tuple)):
+        assert len(ours) == len(theirs), path
+        for i, (a, b) in enumerate(zip(ours, theirs)):
+            assert_same(a, b, f"{path}[{i}]")
+    elif isinstance(theirs, float) and math.isnan(theirs):
+        assert math.isnan(ours), path
+    else:
+        assert ours == theirs, f"{path}: {ours!r} != {theirs!r}"
+
+
+CORPUS = [
+    # Empty documents
+    "",
+    "# just a comment",
+    "---",
+    # YAML 1.1 booleans
+    "[yes, Yes, YES, no, No, NO, on, On, ON, off, Off, OFF]",
+    "[true, True, TRUE, false, False, FALSE, y, n, Y, N]",
+    # Nulls
+    "[~, null, Null, NULL, '', 'null']",
+    "key:",
+    # Integers
+    "[0, 42, -17, +5, 1_000, 0b1010, 0x1F, -0x1f, 0755, 0o17, 190:20:30]",
+    "123456789012345678901234567890",
+    "[012345, 09, 0_7]",
+    # Floats
+    "[1.5, -2.0, 1.0e+3, 1e3, 6.8523015e+5, .5, 1., 190:20:30.15]",
+    "[.inf, -.inf, +.inf, .Inf, .NaN]",
+    # Timestamps
+    "2024-01-01",
+    "2024-01-01T10:00:00Z",
+    "2001-12-14t21:59:43.10-05:00",
+    "2001-12-14 21:59:43.10",
+    "2001-12-15 2:59:43.10",
+    "[2024-1-1, '2024-01-01']",
+    # Strings that look like other types when quoted
+    "['yes', \"on\", '123', '1.5', \"~\"]",
+    # Explicit tags
+    "[!!str 123, !!int '42', !!float '1', !!bool 'yes', !!null '']",
+    "!!binary aGVsbG8gd29ybGQ=",
+    "data: !!binary |\n  R0lGODlhDAAMAIQAAP//9/X1\n  7unp5WZmZgAAAOfn515eXvPz",
+    "!!timestamp 2024-01-01",
+    # Collections
+    "!!set {a, b, c}",
+    "!!omap [a: 1, b: 2]",
+    "!!pairs [a: 1, a: 2]",
+    "a: {b: [1, 2, {c: d}]}",
+    "[1, [2, [3, [4]]]]",
+    # Merge keys
+    "base: &b {x: 1, y: 2}\nchild:\n  <<: *b\n  y: 3",
+    "a: &a {x: 1}\nb: &b {y: 2}\nc:\n  <<: [*a, *b]\n  z: 3",
+    "a: &a {x: 1}\nb: &b {x: 2}\nc:\n  <<: [*a, *b]",
+    # Anchors and aliases
+    "a: &x [1, 2]\nb: *x",
+    # Keys of different types
+    "{1: a, 2.5: b, true: c, ~: d, 2024-01-01: e}",
+    # Block scalars
+    "text: |\n  line1\n  line2\nfolded: >\n  a\n  b\n",
+    # Multiple docs are handled by load_all
+]
+
+
+@pytest.mark.parametrize("document", CORPUS)
+def test_safe_load_parity(document):
+    """Each document loads to identical values and types"""
+    expected = pyyaml.safe_load(document)
+    actual = rustyyaml.safe_load(document, pyyaml_compat=True)
+    assert_same(actual, expected)
+
+
+@pytest.mark.parametrize("document", [d for d in CORPUS if "<<" in d])
+def test_merge_key_parity(document):
+    """Merge keys apply without pyyaml_compat, in PyYAML's key order"""
+    expected = pyyaml.safe_load(document)
+    actual = rustyyaml.safe_load(document)
+    assert_same(actual, expected)
+    assert [list(v) for v in actual.values()] == [list(v) for v in expected.values()]
+
+
+EVENT_ATTRIBUTES = [
+    "anchor",
+    "tag",
+    "implicit",
+    "value",
+    "style",
+    "flow_style",
+    "explicit",
+]
+
+
+@pytest.mark.parametrize("document", CORPUS + ["é: ü\n---\n? [a]\n: 'b'\n...\n"])
+def test_parse_parity(document):
+    """parse() yields the events yaml.parse() does, with the same marks"""
+    ours = list(rustyyaml.parse(document))
+    theirs = list(pyyaml.parse(document))
+    assert [type(e).__name__ for e in ours] == [type(e).__name__ for e in theirs]
+    for a, b in zip(ours, theirs):
+        for attribute in EVENT_ATTRIBUTES:
+            assert getattr(a, attribute, None) == getattr(b, attribute, None), b
+
+    if not pyyaml.__with_libyaml__:
+        return
+    # Marks at the end of input follow libyaml, as PyYAML's CLoader does
+    for a, b in zip(ours, pyyaml.parse(document, Loader=pyyaml.CLoader)):
+        for mark in ("start_mark", "end_mark"):
+            a_mark, b_mark = getattr(a, mark), getattr(b, mark)
+            assert (a_mark.index, a_mark.line, a_mark.column) == (
+                b_mark.index,
+                b_mark.line,
+                b_mark.column,
+            ), b
+
+
+def _assert_same_nodes(ours, theirs, marks, seen):
+    assert type(ours).__name__ == type(theirs).__name__
+    if id(theirs) in seen:
+        return
+    seen.add(id(theirs))
+    for attribute in ("tag", "style", "flow_style"):
+        assert getattr(ours, attribute, None) == getattr(theirs, attribute, None)
+    for mark in ("start_mark", "end_mark"):
+        a_mark, b_mark = getattr(ours, mark), getattr(marks, mark)
+        assert (a_mark.index, a_mark.line, a_mark.column) == (
+            b_mark.index,
+            b_mark.line,
+            b_mark.column,
+        )
+    if isinstance(theirs.value, str):
+        assert ours.value == theirs.value
+        return
+    assert len(ours.value) == len(theirs.value)
+    children = zip(ours.value, theirs.value, marks.value)
+    if isinstance(theirs, pyyaml.MappingNode):
+        children = (
+            (a[i], b[i], m[i]) for a, b, m in children for i in (0, 1)
+        )
+    for a, b, m in children:
+        _assert_same_nodes(a, b, m, seen)
+
+
+@pytest.mark.skipif(not pyyaml.__with_libyaml__, reason="marks follow CLoader")
+@pytest.mark.parametrize("document", CORPUS + ["a: &x [1, *x]\né: 'ü'\n"])
+def test_compose_parity(document):
+    """compose() builds the nodes yaml.compose() does, with the same marks"""
+    ours = rustyyaml.compose(document, pyyaml_compat=True)
+    theirs = pyyaml.compose(document)
+    if theirs is None:
+        assert ours is None
+        return
+    # Marks at the end of input follow libyaml, as PyYAML's CLoader does
+    marks = pyyaml.compose(document, Loader=pyyaml.CLoader)
+    _assert_same_nodes(ours, theirs, marks, set())
+
+
+def test_load_all_parity():
+    """Multi-document streams match, including empty documents"""
+    stream = "a: yes\n---\n---\n- 2024-01-01\n...\n--- 0755\n"
+    expected = list(pyyaml.safe_load_all(stream))
+    actual = rustyyaml.load_all(stream, pyyaml_compat=True)
+    assert_same(actual, expected)
+
+
+def test_alias_identity_parity():
+    """PyYAML returns the same object for an anchor and its aliases"""
+    data = rustyyaml.safe_load("a: &x {k: v}\nb: *x", pyyaml_compat=True)
+    assert data["a"] is data["b"]
+
+
+def test_unsafe_tags_still_rejected():
+    """Compat mode keeps rustyyaml's safety guarantees"""
+    with pytest.raises(rustyyaml.YAMLError):
+        rustyyaml.safe_load(
+            "!!python/object/apply:os.system ['echo bad']", pyyaml_compat=True
+        )
+
+
+def test_unknown_tag_rejected():
+    """Like SafeLoader, unknown tags are an error"""
+    with pytest.raises(rustyyaml.YAMLError):
+        rustyyaml.safe_load("!Ref foo", pyyaml_compat=True)
+    with pytest.raises(pyyaml.YAMLError):
+        pyyaml.safe_load("!Ref foo")
+
+
+def test_batch_parity():
+    """Batch loaders accept the same option"""
+    docs = ["a: yes", "b: 2024-01-01"]
+    expected = [pyyaml.safe_load(d) for d in docs]
+    assert_same(rustyyaml.safe_load_many(docs, pyyaml_compat=True), expected)
+
+
+def test_default_mode_unchanged():
+    """Without the flag, YAML 1.2 rules still apply"""
+    assert rustyyaml.safe_load("a: yes") == {"a": "yes"}
+    assert rustyyaml.safe_load("a: 2024-01-01") == {"a": "2024-01-01"}